@@ -0,0 +1,428 @@
+//! Content-addressed artifact transfer between workers
+//!
+//! Blobs are addressed by the blake3 hash of their content and stored in any [`ArtifactStore`]
+//! as fixed-size chunks, each deflate-compressed under `cas/chunks/` and keyed by the hash of its
+//! uncompressed bytes, so identical chunks are uploaded once across every blob that contains
+//! them. A manifest under `cas/blobs/` lists a blob's chunks. Uploads skip chunks the store
+//! already has, and downloads verify every chunk and the whole blob against their hashes.
+//!
+//! Directories (a task's sources, or what a build produced) travel as a [`Tree`]: a blob
+//! listing each file's path and digest, which a worker materializes into its own directory.
+//!
+//! Chunks are ordinary artifacts, so pin `cas/` when running GC against a store shared by
+//! workers; a download whose chunks were evicted fails rather than returning partial content.
+
+use crate::{content_hash, ArtifactStore};
+use anyhow::{bail, Context, Result};
+use flate2::read::{DeflateDecoder, DeflateEncoder};
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+pub const DEFAULT_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+const CHUNKS_PREFIX: &str = "cas/chunks";
+const BLOBS_PREFIX: &str = "cas/blobs";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Chunk {
+    pub blake3: String,
+    pub size: u64,
+}
+
+/// The chunks a blob is made of, stored under `cas/blobs/<digest>.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlobManifest {
+    pub blake3: String,
+    pub size: u64,
+    pub chunks: Vec<Chunk>,
+}
+
+/// What an upload sent, and what it found already stored
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransferReport {
+    pub digest: String,
+    pub size: u64,
+    pub chunks: usize,
+    pub chunks_uploaded: usize,
+    /// Compressed bytes sent to the store
+    pub bytes_uploaded: u64,
+    /// Uncompressed bytes of chunks the store already had
+    pub bytes_deduplicated: u64,
+}
+
+impl TransferReport {
+    fn add(&mut self, other: &TransferReport) {
+        self.chunks += other.chunks;
+        self.chunks_uploaded += other.chunks_uploaded;
+        self.bytes_uploaded += other.bytes_uploaded;
+        self.bytes_deduplicated += other.bytes_deduplicated;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TreeEntry {
+    /// Slash-separated path relative to the tree's root
+    pub path: String,
+    pub blake3: String,
+    pub size: u64,
+    #[serde(default)]
+    pub executable: bool,
+}
+
+/// A directory snapshot, stored as a blob of its own
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tree {
+    pub entries: Vec<TreeEntry>,
+}
+
+pub struct ContentStore<'a> {
+    store: &'a dyn ArtifactStore,
+    chunk_size: usize,
+    compression: Compression,
+}
+
+impl<'a> ContentStore<'a> {
+    pub fn new(store: &'a dyn ArtifactStore) -> Self {
+        Self { store, chunk_size: DEFAULT_CHUNK_SIZE, compression: Compression::fast() }
+    }
+
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Deflate level from 0 (store uncompressed) to 9.
+    pub fn with_compression_level(mut self, level: u32) -> Self {
+        self.compression = Compression::new(level.min(9));
+        self
+    }
+
+    pub async fn contains(&self, digest: &str) -> Result<bool> {
+        Ok(self.store.head(&blob_key(digest)?).await?.is_some())
+    }
+
+    pub async fn put(&self, data: &[u8]) -> Result<TransferReport> {
+        let mut chunks = Vec::new();
+        let mut report = TransferReport::default();
+        for chunk in data.chunks(self.chunk_size) {
+            chunks.push(self.put_chunk(chunk, &mut report).await?);
+        }
+        self.finish_blob(content_hash(data), data.len() as u64, chunks, report).await
+    }
+
+    /// Upload a file chunk by chunk, without reading it into memory whole.
+    pub async fn put_file(&self, path: &Path) -> Result<TransferReport> {
+        let mut file = tokio::fs::File::open(path)
+            .await
+            .with_context(|| format!("opening {}", path.display()))?;
+        let mut hasher = blake3::Hasher::new();
+        let mut chunks = Vec::new();
+        let mut report = TransferReport::default();
+        let mut size = 0;
+        let mut buffer = vec![0; self.chunk_size];
+        loop {
+            let filled = read_full(&mut file, &mut buffer).await?;
+            if filled == 0 {
+                break;
+            }
+            hasher.update(&buffer[..filled]);
+            size += filled as u64;
+            chunks.push(self.put_chunk(&buffer[..filled], &mut report).await?);
+        }
+        let digest = hasher.finalize().to_hex().to_string();
+        self.finish_blob(digest, size, chunks, report).await
+    }
+
+    async fn put_chunk(&self, chunk: &[u8], report: &mut TransferReport) -> Result<Chunk> {
+        let hash = content_hash(chunk);
+        let key = chunk_key(&hash)?;
+        report.chunks += 1;
+        if self.store.head(&key).await?.is_some() {
+            report.bytes_deduplicated += chunk.len() as u64;
+        } else {
+            let mut compressed = Vec::new();
+            DeflateEncoder::new(chunk, self.compression).read_to_end(&mut compressed)?;
+            self.store.put(&key, &compressed).await?;
+            report.chunks_uploaded += 1;
+            report.bytes_uploaded += compressed.len() as u64;
+        }
+        Ok(Chunk { blake3: hash, size: chunk.len() as u64 })
+    }
+
+    async fn finish_blob(
+        &self,
+        digest: String,
+        size: u64,
+        chunks: Vec<Chunk>,
+        mut report: TransferReport,
+    ) -> Result<TransferReport> {
+        let key = blob_key(&digest)?;
+        if self.store.head(&key).await?.is_none() {
+            let manifest = BlobManifest { blake3: digest.clone(), size, chunks };
+            self.store.put(&key, &serde_json::to_vec(&manifest)?).await?;
+        }
+        tracing::debug!(
+            digest = %digest,
+            chunks = report.chunks,
+            uploaded = report.chunks_uploaded,
+            "📤 Stored blob"
+        );
+        report.digest = digest;
+        report.size = size;
+        Ok(report)
+    }
+
+    pub async fn manifest(&self, digest: &str) -> Result<BlobManifest> {
+        let data = self
+            .store
+            .get(&blob_key(digest)?)
+            .await
+            .with_context(|| format!("blob {} is not in the store", digest))?;
+        serde_json::from_slice(&data).with_context(|| format!("parsing manifest of {}", digest))
+    }
+
+    pub async fn get(&self, digest: &str) -> Result<Vec<u8>> {
+        let manifest = self.manifest(digest).await?;
+        let mut data = Vec::with_capacity(manifest.size as usize);
+        for chunk in &manifest.chunks {
+            data.extend(self.get_chunk(chunk).await?);
+        }
+        if content_hash(&data) != manifest.blake3 {
+            bail!("blob {} does not match its digest after reassembly", digest);
+        }
+        Ok(data)
+    }
+
+    /// Download a blob into `path`, chunk by chunk. The file only appears once it is complete.
+    pub async fn get_file(&self, digest: &str, path: &Path) -> Result<()> {
+        let manifest = self.manifest(digest).await?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let partial = path.with_extension("partial");
+        let mut file = tokio::fs::File::create(&partial).await?;
+        let mut hasher = blake3::Hasher::new();
+        for chunk in &manifest.chunks {
+            let data = self.get_chunk(chunk).await?;
+            hasher.update(&data);
+            file.write_all(&data).await?;
+        }
+        file.flush().await?;
+        if hasher.finalize().to_hex().as_str() != manifest.blake3 {
+            let _ = tokio::fs::remove_file(&partial).await;
+            bail!("blob {} does not match its digest after reassembly", digest);
+        }
+        tokio::fs::rename(&partial, path).await?;
+        Ok(())
+    }
+
+    async fn get_chunk(&self, chunk: &Chunk) -> Result<Vec<u8>> {
+        let compressed = self
+            .store
+            .get(&chunk_key(&chunk.blake3)?)
+            .await
+            .with_context(|| format!("chunk {} is missing", chunk.blake3))?;
+        let mut data = Vec::with_capacity(chunk.size as usize);
+        DeflateDecoder::new(compressed.as_slice()).read_to_end(&mut data)?;
+        if content_hash(&data) != chunk.blake3 {
+            bail!("chunk {} is corrupt", chunk.blake3);
+        }
+        Ok(data)
+    }
+
+    /// Upload every file under `dir` and the tree listing them. The report's digest is the
+    /// tree's; its counts cover the files and the tree.
+    pub async fn put_tree(&self, dir: &Path) -> Result<TransferReport> {
+        let mut tree = Tree::default();
+        let mut report = TransferReport::default();
+        for path in files_under(dir)? {
+            let file = self.put_file(&path).await?;
+            report.add(&file);
+            tree.entries.push(TreeEntry {
+                path: relative_path(dir, &path)?,
+                blake3: file.digest,
+                size: file.size,
+                executable: is_executable(&path),
+            });
+        }
+        let listing = self.put(&serde_json::to_vec(&tree)?).await?;
+        report.add(&listing);
+        report.digest = listing.digest;
+        report.size = tree.entries.iter().map(|entry| entry.size).sum();
+        Ok(report)
+    }
+
+    pub async fn tree(&self, digest: &str) -> Result<Tree> {
+        serde_json::from_slice(&self.get(digest).await?)
+            .with_context(|| format!("blob {} is not a tree", digest))
+    }
+
+    /// Write the tree `digest` into `dir`, skipping files already there with the right content.
+    /// Returns how many files were written.
+    pub async fn materialize_tree(&self, digest: &str, dir: &Path) -> Result<usize> {
+        let mut written = 0;
+        for entry in self.tree(digest).await?.entries {
+            let path = tree_path(dir, &entry.path)?;
+            if tokio::fs::read(&path).await.is_ok_and(|data| content_hash(&data) == entry.blake3) {
+                continue;
+            }
+            self.get_file(&entry.blake3, &path).await?;
+            if entry.executable {
+                set_executable(&path)?;
+            }
+            written += 1;
+        }
+        Ok(written)
+    }
+}
+
+/// Digests come from manifests in the store, so they are checked before becoming keys
+fn check_digest(digest: &str) -> Result<()> {
+    if digest.len() != 64 || !digest.bytes().all(|b| b.is_ascii_hexdigit()) {
+        bail!("not a blake3 digest: {:?}", digest);
+    }
+    Ok(())
+}
+
+fn chunk_key(hash: &str) -> Result<String> {
+    check_digest(hash)?;
+    Ok(format!("{}/{}/{}", CHUNKS_PREFIX, &hash[..2], hash))
+}
+
+fn blob_key(digest: &str) -> Result<String> {
+    check_digest(digest)?;
+    Ok(format!("{}/{}.json", BLOBS_PREFIX, digest))
+}
+
+/// Fill `buffer` unless the reader ends first; returns how much was read.
+async fn read_full(file: &mut tokio::fs::File, buffer: &mut [u8]) -> Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match file.read(&mut buffer[filled..]).await? {
+            0 => break,
+            read => filled += read,
+        }
+    }
+    Ok(filled)
+}
+
+fn files_under(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in
+            std::fs::read_dir(&current).with_context(|| format!("reading {}", current.display()))?
+        {
+            let entry = entry?;
+            let kind = entry.file_type()?;
+            if kind.is_dir() {
+                pending.push(entry.path());
+            } else if kind.is_file() {
+                files.push(entry.path());
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn relative_path(dir: &Path, path: &Path) -> Result<String> {
+    Ok(path
+        .strip_prefix(dir)?
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect::<Vec<_>>()
+        .join("/"))
+}
+
+/// Where a tree entry goes under `dir`, refusing paths that would leave it
+fn tree_path(dir: &Path, path: &str) -> Result<PathBuf> {
+    crate::validate_key(path).with_context(|| format!("unsafe path in tree: {:?}", path))?;
+    Ok(path.split('/').fold(dir.to_path_buf(), |full, segment| full.join(segment)))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path).is_ok_and(|m| m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn set_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mut permissions = std::fs::metadata(path)?.permissions();
+    permissions.set_mode(permissions.mode() | 0o755);
+    std::fs::set_permissions(path, permissions)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LocalStore;
+
+    #[tokio::test]
+    async fn test_chunks_dedups_and_ships_trees() {
+        let root =
+            std::env::temp_dir().join(format!("parflow-artifacts-cas-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let store = LocalStore::new(root.join("store")).unwrap();
+        let cas = ContentStore::new(&store).with_chunk_size(1024);
+
+        let data: Vec<u8> = (0..4096u32).flat_map(|i| (i % 251).to_le_bytes()).collect();
+        let first = cas.put(&data).await.unwrap();
+        assert_eq!(first.digest, content_hash(&data));
+        assert_eq!(first.chunks, 16);
+        assert!(first.bytes_uploaded < data.len() as u64);
+        assert_eq!(cas.get(&first.digest).await.unwrap(), data);
+
+        // The same content again uploads nothing; a one-byte change uploads one chunk
+        assert_eq!(cas.put(&data).await.unwrap().chunks_uploaded, 0);
+        let mut changed = data.clone();
+        changed[5000] ^= 1;
+        let second = cas.put(&changed).await.unwrap();
+        assert_eq!((second.chunks_uploaded, second.bytes_deduplicated), (1, 15 * 1024));
+
+        let sources = root.join("sources");
+        std::fs::create_dir_all(sources.join("src")).unwrap();
+        std::fs::write(sources.join("Cargo.toml"), "[package]\n").unwrap();
+        std::fs::write(sources.join("src/main.rs"), "fn main() {}\n").unwrap();
+        let tree = cas.put_tree(&sources).await.unwrap();
+        let worker = root.join("worker");
+        assert_eq!(cas.materialize_tree(&tree.digest, &worker).await.unwrap(), 2);
+        assert_eq!(std::fs::read_to_string(worker.join("src/main.rs")).unwrap(), "fn main() {}\n");
+        assert_eq!(cas.materialize_tree(&tree.digest, &worker).await.unwrap(), 0);
+
+        let chunk = &cas.manifest(&first.digest).await.unwrap().chunks[0];
+        store.put(&chunk_key(&chunk.blake3).unwrap(), b"garbage").await.unwrap();
+        assert!(cas.get(&first.digest).await.is_err());
+        assert!(cas.get("not-a-digest").await.is_err());
+
+        // Chunk hashes in a stored manifest are not trusted either
+        for hash in ["é", "../../etc/passwd", ""] {
+            let manifest = BlobManifest {
+                blake3: first.digest.clone(),
+                size: 1,
+                chunks: vec![Chunk { blake3: hash.to_string(), size: 1 }],
+            };
+            let key = blob_key(&first.digest).unwrap();
+            store.put(&key, &serde_json::to_vec(&manifest).unwrap()).await.unwrap();
+            let error = cas.get(&first.digest).await.unwrap_err();
+            assert!(error.to_string().contains("not a blake3 digest"), "{}", error);
+        }
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,297 @@
+//! Garbage collection for artifact stores.
+//!
+//! Eviction happens in two passes: artifacts older than `max_age_secs` are removed first, then
+//! the least recently used artifacts are removed until the store fits in `max_total_bytes`.
+//! Pinned artifacts (referenced by a release or a baseline) are never evicted, but they still
+//! count towards the size budget.
+
+use crate::{ArtifactMeta, ArtifactStore};
+use anyhow::{Context, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Keys under this prefix hold GC bookkeeping and are excluded from stats and eviction.
+pub const RESERVED_PREFIX: &str = "_gc/";
+const PINS_KEY: &str = "_gc/pins.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GcPolicy {
+    pub max_total_bytes: Option<u64>,
+    pub max_age_secs: Option<u64>,
+}
+
+impl Default for GcPolicy {
+    fn default() -> Self {
+        Self { max_total_bytes: Some(10 * 1024 * 1024 * 1024), max_age_secs: Some(30 * 86_400) }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pin {
+    /// Exact key, or a prefix ending in `/` that pins everything below it
+    pub pattern: String,
+    /// Why the artifact is kept, e.g. `release v1.4.0` or `baseline main`
+    pub reason: String,
+    pub pinned_at: i64,
+}
+
+/// Pins persisted inside the store itself so they travel with it across migrations.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct PinSet {
+    pins: Vec<Pin>,
+}
+
+impl PinSet {
+    pub async fn load(store: &dyn ArtifactStore) -> Result<Self> {
+        if store.head(PINS_KEY).await?.is_none() {
+            return Ok(Self::default());
+        }
+        let data = store.get(PINS_KEY).await?;
+        serde_json::from_slice(&data).context("parsing pin set")
+    }
+
+    pub async fn save(&self, store: &dyn ArtifactStore) -> Result<()> {
+        store.put(PINS_KEY, &serde_json::to_vec_pretty(self)?).await?;
+        Ok(())
+    }
+
+    pub fn pin(&mut self, pattern: &str, reason: &str) {
+        self.pins.retain(|p| p.pattern != pattern);
+        self.pins.push(Pin {
+            pattern: pattern.to_string(),
+            reason: reason.to_string(),
+            pinned_at: Utc::now().timestamp(),
+        });
+    }
+
+    /// Returns whether a pin with this exact pattern existed.
+    pub fn unpin(&mut self, pattern: &str) -> bool {
+        let before = self.pins.len();
+        self.pins.retain(|p| p.pattern != pattern);
+        self.pins.len() != before
+    }
+
+    pub fn pins(&self) -> &[Pin] {
+        &self.pins
+    }
+
+    pub fn pinned_by(&self, key: &str) -> Option<&Pin> {
+        self.pins
+            .iter()
+            .find(|p| p.pattern == key || (p.pattern.ends_with('/') && key.starts_with(&p.pattern)))
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub artifacts: usize,
+    pub total_bytes: u64,
+    pub pinned: usize,
+    pub pinned_bytes: u64,
+    pub oldest: Option<i64>,
+    pub newest: Option<i64>,
+    /// Artifact count and bytes grouped by the first key segment
+    pub by_namespace: BTreeMap<String, (usize, u64)>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct GcReport {
+    pub expired: Vec<String>,
+    pub evicted: Vec<String>,
+    pub freed_bytes: u64,
+    pub remaining_bytes: u64,
+    pub kept_pinned: usize,
+    pub dry_run: bool,
+}
+
+async fn tracked_artifacts(store: &dyn ArtifactStore) -> Result<Vec<ArtifactMeta>> {
+    let mut artifacts = store.list().await?;
+    artifacts.retain(|a| !a.key.starts_with(RESERVED_PREFIX));
+    Ok(artifacts)
+}
+
+fn last_used(meta: &ArtifactMeta) -> i64 {
+    meta.accessed.max(meta.modified).unwrap_or(0)
+}
+
+pub async fn stats(store: &dyn ArtifactStore) -> Result<CacheStats> {
+    let pins = PinSet::load(store).await?;
+    let mut stats = CacheStats::default();
+
+    for meta in tracked_artifacts(store).await? {
+        stats.artifacts += 1;
+        stats.total_bytes += meta.size;
+        if pins.pinned_by(&meta.key).is_some() {
+            stats.pinned += 1;
+            stats.pinned_bytes += meta.size;
+        }
+        if let Some(modified) = meta.modified {
+            stats.oldest = Some(stats.oldest.map_or(modified, |o| o.min(modified)));
+            stats.newest = Some(stats.newest.map_or(modified, |n| n.max(modified)));
+        }
+        let namespace = meta.key.split('/').next().unwrap_or_default().to_string();
+        let entry = stats.by_namespace.entry(namespace).or_default();
+        entry.0 += 1;
+        entry.1 += meta.size;
+    }
+
+    Ok(stats)
+}
+
+pub async fn collect_garbage(
+    store: &dyn ArtifactStore,
+    policy: &GcPolicy,
+    dry_run: bool,
+) -> Result<GcReport> {
+    let pins = PinSet::load(store).await?;
+    let now = Utc::now().timestamp();
+    let mut report = GcReport { dry_run, ..Default::default() };
+
+    let (pinned, mut candidates): (Vec<_>, Vec<_>) = tracked_artifacts(store)
+        .await?
+        .into_iter()
+        .partition(|meta| pins.pinned_by(&meta.key).is_some());
+    report.kept_pinned = pinned.len();
+
+    let mut total: u64 = pinned.iter().chain(candidates.iter()).map(|a| a.size).sum();
+    let mut doomed = Vec::new();
+
+    // An age too large to subtract from now reaches back further than any artifact: nothing
+    // expires
+    let cutoff = policy.max_age_secs.and_then(|age| now.checked_sub(i64::try_from(age).ok()?));
+    if let Some(cutoff) = cutoff {
+        let (expired, fresh): (Vec<_>, Vec<_>) =
+            candidates.into_iter().partition(|meta| last_used(meta) < cutoff);
+        candidates = fresh;
+        for meta in expired {
+            total -= meta.size;
+            report.expired.push(meta.key.clone());
+            doomed.push(meta);
+        }
+    }
+
+    if let Some(max_total) = policy.max_total_bytes {
+        candidates.sort_by_key(last_used);
+        for meta in candidates {
+            if total <= max_total {
+                break;
+            }
+            total -= meta.size;
+            report.evicted.push(meta.key.clone());
+            doomed.push(meta);
+        }
+    }
+
+    for meta in doomed {
+        if !dry_run {
+            store.delete(&meta.key).await?;
+        }
+        report.freed_bytes += meta.size;
+    }
+    report.remaining_bytes = total;
+
+    Ok(report)
+}
+
+/// Run GC every `interval` for as long as the returned task is alive.
+pub fn spawn_scheduled(
+    store: Arc<dyn ArtifactStore>,
+    policy: GcPolicy,
+    interval: Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match collect_garbage(store.as_ref(), &policy, false).await {
+                Ok(report) if report.freed_bytes > 0 => tracing::info!(
+                    freed_mb = %format!("{:.2}", report.freed_bytes as f64 / (1024.0 * 1024.0)),
+                    artifacts = report.expired.len() + report.evicted.len(),
+                    "🧹 Artifact GC freed space"
+                ),
+                Ok(_) => {}
+                Err(e) => tracing::error!(error = %e, "❌ Artifact GC failed"),
+            }
+        }
+    })
+}
+
+/// Parse sizes such as `512M`, `10G` or a plain byte count.
+pub fn parse_size(spec: &str) -> Result<u64> {
+    let spec = spec.trim();
+    let (number, multiplier) = match spec.char_indices().last() {
+        Some((i, 'K' | 'k')) => (&spec[..i], 1024),
+        Some((i, 'M' | 'm')) => (&spec[..i], 1024 * 1024),
+        Some((i, 'G' | 'g')) => (&spec[..i], 1024 * 1024 * 1024),
+        _ => (spec, 1),
+    };
+    let number: u64 = number.parse().with_context(|| format!("invalid size: {}", spec))?;
+    number.checked_mul(multiplier).with_context(|| format!("size too large: {}", spec))
+}
+
+/// Parse durations such as `30d`, `12h`, `45m` or plain seconds.
+pub fn parse_age(spec: &str) -> Result<u64> {
+    let spec = spec.trim();
+    let (number, multiplier) = match spec.char_indices().last() {
+        Some((i, 'd')) => (&spec[..i], 86_400),
+        Some((i, 'h')) => (&spec[..i], 3_600),
+        Some((i, 'm')) => (&spec[..i], 60),
+        Some((i, 's')) => (&spec[..i], 1),
+        _ => (spec, 1),
+    };
+    let number: u64 = number.parse().with_context(|| format!("invalid age: {}", spec))?;
+    number.checked_mul(multiplier).with_context(|| format!("age too large: {}", spec))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LocalStore;
+
+    #[test]
+    fn test_parses_sizes_and_ages_without_overflowing() {
+        assert_eq!(parse_size("512M").unwrap(), 512 * 1024 * 1024);
+        assert_eq!(parse_size(" 100 ").unwrap(), 100);
+        assert_eq!(parse_age("30d").unwrap(), 30 * 86_400);
+        assert_eq!(parse_age("45m").unwrap(), 45 * 60);
+        assert!(parse_size("10X").is_err());
+
+        let error = parse_size("99999999999999999G").unwrap_err();
+        assert_eq!(error.to_string(), "size too large: 99999999999999999G");
+        let error = parse_age("999999999999999999d").unwrap_err();
+        assert_eq!(error.to_string(), "age too large: 999999999999999999d");
+    }
+
+    #[tokio::test]
+    async fn test_gc_respects_pins_and_size_budget() {
+        let root = std::env::temp_dir().join(format!("parflow-gc-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let store = LocalStore::new(&root).unwrap();
+
+        for key in ["builds/a", "builds/b", "releases/v1/app"] {
+            store.put(key, &[0u8; 100]).await.unwrap();
+        }
+        let mut pins = PinSet::default();
+        pins.pin("releases/v1/", "release v1");
+        pins.save(&store).await.unwrap();
+
+        let policy = GcPolicy { max_total_bytes: Some(150), max_age_secs: None };
+        let report = collect_garbage(&store, &policy, false).await.unwrap();
+
+        assert_eq!(report.evicted.len(), 2);
+        assert_eq!(report.kept_pinned, 1);
+        assert!(store.head("releases/v1/app").await.unwrap().is_some());
+        assert_eq!(stats(&store).await.unwrap().artifacts, 1);
+
+        store.put("builds/c", &[0u8; 10]).await.unwrap();
+        let policy = GcPolicy { max_total_bytes: None, max_age_secs: Some(u64::MAX) };
+        let report = collect_garbage(&store, &policy, false).await.unwrap();
+        assert!(report.expired.is_empty());
+        assert_eq!(stats(&store).await.unwrap().artifacts, 2);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
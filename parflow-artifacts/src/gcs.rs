@@ -0,0 +1,232 @@
+use crate::{content_hash, validate_key, ArtifactMeta, ArtifactStore};
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use chrono::DateTime;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GcsConfig {
+    pub bucket: String,
+    #[serde(default)]
+    pub prefix: String,
+    /// OAuth2 access token, e.g. from `gcloud auth print-access-token`
+    #[serde(default)]
+    pub access_token: String,
+    #[serde(default = "default_endpoint")]
+    pub endpoint: String,
+}
+
+fn default_endpoint() -> String {
+    "https://storage.googleapis.com".to_string()
+}
+
+impl GcsConfig {
+    pub fn from_env(bucket: &str, prefix: &str) -> Self {
+        let token = std::env::var("GCS_ACCESS_TOKEN")
+            .or_else(|_| std::env::var("GOOGLE_OAUTH_ACCESS_TOKEN"))
+            .unwrap_or_default();
+        Self {
+            bucket: bucket.to_string(),
+            prefix: prefix.to_string(),
+            access_token: token,
+            endpoint: std::env::var("GCS_ENDPOINT").unwrap_or_else(|_| default_endpoint()),
+        }
+    }
+}
+
+/// Google Cloud Storage backend using the JSON API. The blake3 hash is stored as custom
+/// object metadata.
+pub struct GcsStore {
+    config: GcsConfig,
+    client: reqwest::Client,
+}
+
+#[derive(Deserialize)]
+struct ObjectResource {
+    name: String,
+    size: String,
+    updated: Option<String>,
+    #[serde(default)]
+    metadata: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ObjectList {
+    #[serde(default)]
+    items: Vec<ObjectResource>,
+    next_page_token: Option<String>,
+}
+
+impl GcsStore {
+    pub fn new(config: GcsConfig) -> Result<Self> {
+        if config.access_token.is_empty() {
+            bail!("GCS access token missing: set GCS_ACCESS_TOKEN");
+        }
+        Ok(Self { config, client: reqwest::Client::new() })
+    }
+
+    fn object_name(&self, key: &str) -> String {
+        if self.config.prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}/{}", self.config.prefix, key)
+        }
+    }
+
+    fn object_url(&self, key: &str) -> String {
+        format!(
+            "{}/storage/v1/b/{}/o/{}",
+            self.config.endpoint,
+            self.config.bucket,
+            utf8_percent_encode(&self.object_name(key), NON_ALPHANUMERIC)
+        )
+    }
+
+    fn to_meta(&self, object: ObjectResource) -> ArtifactMeta {
+        let prefix = format!("{}/", self.config.prefix);
+        let key = if self.config.prefix.is_empty() {
+            object.name
+        } else {
+            object.name.strip_prefix(&prefix).unwrap_or(&object.name).to_string()
+        };
+        ArtifactMeta {
+            key,
+            size: object.size.parse().unwrap_or(0),
+            blake3: object.metadata.get("blake3").cloned(),
+            accessed: None,
+            modified: object
+                .updated
+                .and_then(|t| DateTime::parse_from_rfc3339(&t).ok())
+                .map(|t| t.timestamp()),
+        }
+    }
+}
+
+#[async_trait]
+impl ArtifactStore for GcsStore {
+    fn backend_name(&self) -> &'static str {
+        "gcs"
+    }
+
+    async fn put(&self, key: &str, data: &[u8]) -> Result<ArtifactMeta> {
+        validate_key(key)?;
+        let hash = content_hash(data);
+        let boundary = format!("parflow-{}", &hash[..16]);
+        let metadata = serde_json::json!({
+            "name": self.object_name(key),
+            "metadata": { "blake3": hash },
+        });
+
+        let mut body = Vec::with_capacity(data.len() + 512);
+        body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Type: application/json; charset=UTF-8\r\n\r\n{}\r\n--{}\r\n\
+                 Content-Type: application/octet-stream\r\n\r\n",
+                boundary, metadata, boundary
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(data);
+        body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+
+        let response = self
+            .client
+            .post(format!(
+                "{}/upload/storage/v1/b/{}/o?uploadType=multipart",
+                self.config.endpoint, self.config.bucket
+            ))
+            .bearer_auth(&self.config.access_token)
+            .header("content-type", format!("multipart/related; boundary={}", boundary))
+            .body(body)
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            bail!(
+                "GCS upload failed with {}: {}",
+                status,
+                response.text().await.unwrap_or_default()
+            );
+        }
+
+        let object: ObjectResource =
+            response.json().await.context("parsing GCS upload response")?;
+        Ok(self.to_meta(object))
+    }
+
+    async fn fetch(&self, key: &str) -> Result<(Vec<u8>, Option<String>)> {
+        let meta = self.head(key).await?.with_context(|| format!("artifact not found: {}", key))?;
+        let response = self
+            .client
+            .get(format!("{}?alt=media", self.object_url(key)))
+            .bearer_auth(&self.config.access_token)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok((response.bytes().await?.to_vec(), meta.blake3))
+    }
+
+    async fn head(&self, key: &str) -> Result<Option<ArtifactMeta>> {
+        validate_key(key)?;
+        let response = self
+            .client
+            .get(self.object_url(key))
+            .bearer_auth(&self.config.access_token)
+            .send()
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let object: ObjectResource = response.error_for_status()?.json().await?;
+        Ok(Some(self.to_meta(object)))
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        validate_key(key)?;
+        let response = self
+            .client
+            .delete(self.object_url(key))
+            .bearer_auth(&self.config.access_token)
+            .send()
+            .await?;
+        if response.status() != StatusCode::NOT_FOUND {
+            response.error_for_status()?;
+        }
+        Ok(())
+    }
+
+    async fn list(&self) -> Result<Vec<ArtifactMeta>> {
+        let prefix = if self.config.prefix.is_empty() {
+            String::new()
+        } else {
+            format!("{}/", self.config.prefix)
+        };
+        let mut artifacts = Vec::new();
+        let mut page_token: Option<String> = None;
+
+        loop {
+            let mut request = self
+                .client
+                .get(format!("{}/storage/v1/b/{}/o", self.config.endpoint, self.config.bucket))
+                .bearer_auth(&self.config.access_token)
+                .query(&[("prefix", prefix.as_str())]);
+            if let Some(token) = &page_token {
+                request = request.query(&[("pageToken", token.as_str())]);
+            }
+
+            let page: ObjectList = request.send().await?.error_for_status()?.json().await?;
+            artifacts.extend(page.items.into_iter().map(|object| self.to_meta(object)));
+
+            page_token = page.next_page_token;
+            if page_token.is_none() {
+                break;
+            }
+        }
+
+        Ok(artifacts)
+    }
+}
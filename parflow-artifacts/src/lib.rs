@@ -0,0 +1,276 @@
+//! Pluggable artifact storage for ParFlow
+//!
+//! Artifacts are opaque blobs addressed by a slash-separated key. Every backend records the
+//! blake3 hash of an artifact when it is stored and `ArtifactStore::get` refuses to return
+//! content that no longer matches it.
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+pub mod cas;
+pub mod gc;
+pub mod gcs;
+pub mod local;
+pub mod s3;
+
+pub use cas::{ContentStore, TransferReport, Tree};
+pub use gc::{GcPolicy, PinSet};
+pub use gcs::{GcsConfig, GcsStore};
+pub use local::LocalStore;
+pub use s3::{S3Config, S3Store};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArtifactMeta {
+    pub key: String,
+    pub size: u64,
+    pub blake3: Option<String>,
+    /// Last modification time as seconds since the Unix epoch, when the backend reports it
+    pub modified: Option<i64>,
+    /// Last time the artifact was read, for backends that track it. Used for LRU eviction.
+    #[serde(default)]
+    pub accessed: Option<i64>,
+}
+
+/// Common interface implemented by every storage backend.
+#[async_trait]
+pub trait ArtifactStore: Send + Sync {
+    fn backend_name(&self) -> &'static str;
+
+    async fn put(&self, key: &str, data: &[u8]) -> Result<ArtifactMeta>;
+
+    /// Fetch raw content together with the hash recorded at upload time.
+    async fn fetch(&self, key: &str) -> Result<(Vec<u8>, Option<String>)>;
+
+    async fn head(&self, key: &str) -> Result<Option<ArtifactMeta>>;
+
+    async fn delete(&self, key: &str) -> Result<()>;
+
+    async fn list(&self) -> Result<Vec<ArtifactMeta>>;
+
+    /// Fetch content and verify it against the recorded blake3 hash.
+    async fn get(&self, key: &str) -> Result<Vec<u8>> {
+        let (data, expected) = self.fetch(key).await?;
+        verify_integrity(key, &data, expected.as_deref())?;
+        Ok(data)
+    }
+}
+
+pub fn content_hash(data: &[u8]) -> String {
+    blake3::hash(data).to_hex().to_string()
+}
+
+pub fn verify_integrity(key: &str, data: &[u8], expected: Option<&str>) -> Result<()> {
+    match expected {
+        Some(expected) => {
+            let actual = content_hash(data);
+            if actual != expected {
+                bail!("integrity check failed for {}: expected {}, got {}", key, expected, actual);
+            }
+            Ok(())
+        }
+        None => bail!("no recorded hash for {}, refusing unverified download", key),
+    }
+}
+
+pub(crate) fn validate_key(key: &str) -> Result<()> {
+    if key.is_empty()
+        || key.starts_with('/')
+        || key.split('/').any(|segment| segment.is_empty() || segment == "." || segment == "..")
+    {
+        bail!("invalid artifact key: {:?}", key);
+    }
+    Ok(())
+}
+
+/// Backend selection, as written in config files (`backend = "s3"`, ...) or parsed from a
+/// URL such as `s3://bucket/prefix`, `gs://bucket/prefix` or a local path.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "backend", rename_all = "lowercase")]
+pub enum StorageConfig {
+    Local { root: String },
+    S3(S3Config),
+    Gcs(GcsConfig),
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        StorageConfig::Local { root: ".parflow/artifacts".to_string() }
+    }
+}
+
+impl FromStr for StorageConfig {
+    type Err = anyhow::Error;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        let split_bucket = |rest: &str| -> (String, String) {
+            match rest.split_once('/') {
+                Some((bucket, prefix)) => {
+                    (bucket.to_string(), prefix.trim_end_matches('/').to_string())
+                }
+                None => (rest.to_string(), String::new()),
+            }
+        };
+
+        if let Some(rest) = spec.strip_prefix("s3://") {
+            let (bucket, prefix) = split_bucket(rest);
+            Ok(StorageConfig::S3(S3Config::from_env(&bucket, &prefix)))
+        } else if let Some(rest) = spec.strip_prefix("gs://") {
+            let (bucket, prefix) = split_bucket(rest);
+            Ok(StorageConfig::Gcs(GcsConfig::from_env(&bucket, &prefix)))
+        } else if spec.contains("://") && !spec.starts_with("file://") {
+            bail!("unsupported storage URL: {}", spec)
+        } else {
+            let root = spec.strip_prefix("file://").unwrap_or(spec);
+            Ok(StorageConfig::Local { root: root.to_string() })
+        }
+    }
+}
+
+impl fmt::Display for StorageConfig {
+    /// The path or URL the config parses from, which leaves out credentials and endpoints
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let url = |f: &mut fmt::Formatter<'_>, scheme, bucket: &str, prefix: &str| {
+            write!(f, "{}://{}", scheme, bucket)?;
+            if !prefix.is_empty() {
+                write!(f, "/{}", prefix)?;
+            }
+            Ok(())
+        };
+        match self {
+            StorageConfig::Local { root } => f.write_str(root),
+            StorageConfig::S3(s3) => url(f, "s3", &s3.bucket, &s3.prefix),
+            StorageConfig::Gcs(gcs) => url(f, "gs", &gcs.bucket, &gcs.prefix),
+        }
+    }
+}
+
+pub fn open_store(config: &StorageConfig) -> Result<Box<dyn ArtifactStore>> {
+    Ok(match config {
+        StorageConfig::Local { root } => Box::new(LocalStore::new(root)?),
+        StorageConfig::S3(s3) => Box::new(S3Store::new(s3.clone())?),
+        StorageConfig::Gcs(gcs) => Box::new(GcsStore::new(gcs.clone())?),
+    })
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct MigrationReport {
+    pub copied: usize,
+    pub skipped: usize,
+    pub bytes_copied: u64,
+    pub failed: Vec<(String, String)>,
+}
+
+/// Copy every artifact from `source` to `target`, verifying content on both ends.
+pub async fn migrate(
+    source: &dyn ArtifactStore,
+    target: &dyn ArtifactStore,
+    skip_existing: bool,
+) -> Result<MigrationReport> {
+    tracing::info!(
+        source = source.backend_name(),
+        target = target.backend_name(),
+        "📦 Migrating artifacts"
+    );
+
+    let mut report = MigrationReport::default();
+
+    for meta in source.list().await? {
+        if skip_existing {
+            if let Some(existing) = target.head(&meta.key).await? {
+                if existing.blake3.is_some() && existing.blake3 == meta.blake3 {
+                    report.skipped += 1;
+                    continue;
+                }
+            }
+        }
+
+        let copied = async {
+            let data = source.get(&meta.key).await?;
+            let stored = target.put(&meta.key, &data).await?;
+            if stored.blake3.as_deref() != Some(content_hash(&data).as_str()) {
+                bail!("target reported a different hash after upload");
+            }
+            Ok(data.len() as u64)
+        }
+        .await;
+
+        match copied {
+            Ok(bytes) => {
+                report.copied += 1;
+                report.bytes_copied += bytes;
+            }
+            Err(e) => {
+                tracing::error!(key = %meta.key, error = %e, "❌ Failed to migrate artifact");
+                report.failed.push((meta.key.clone(), e.to_string()));
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_root(name: &str) -> std::path::PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("parflow-artifacts-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        root
+    }
+
+    #[tokio::test]
+    async fn test_local_roundtrip_and_integrity() {
+        let root = temp_root("roundtrip");
+        let store = LocalStore::new(&root).unwrap();
+
+        let meta = store.put("builds/app.bin", b"hello").await.unwrap();
+        assert_eq!(meta.blake3.as_deref(), Some(content_hash(b"hello").as_str()));
+        assert_eq!(store.get("builds/app.bin").await.unwrap(), b"hello");
+
+        std::fs::write(root.join("objects/builds/app.bin"), b"tampered").unwrap();
+        assert!(store.get("builds/app.bin").await.is_err());
+        assert!(store.put("../escape", b"x").await.is_err());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_migrate_between_local_stores() {
+        let (src_root, dst_root) = (temp_root("src"), temp_root("dst"));
+        let source = LocalStore::new(&src_root).unwrap();
+        let target = LocalStore::new(&dst_root).unwrap();
+        source.put("a.txt", b"a").await.unwrap();
+        source.put("nested/b.txt", b"b").await.unwrap();
+
+        let report = migrate(&source, &target, true).await.unwrap();
+        assert_eq!(report.copied, 2);
+        assert_eq!(target.get("nested/b.txt").await.unwrap(), b"b");
+
+        let report = migrate(&source, &target, true).await.unwrap();
+        assert_eq!((report.copied, report.skipped), (0, 2));
+
+        let _ = std::fs::remove_dir_all(&src_root);
+        let _ = std::fs::remove_dir_all(&dst_root);
+    }
+
+    #[test]
+    fn test_storage_config_from_url() {
+        assert!(matches!(
+            "gs://bucket/cache".parse::<StorageConfig>().unwrap(),
+            StorageConfig::Gcs(GcsConfig { ref prefix, .. }) if prefix == "cache"
+        ));
+        assert!(matches!(
+            "./artifacts".parse::<StorageConfig>().unwrap(),
+            StorageConfig::Local { .. }
+        ));
+        assert!("ftp://host".parse::<StorageConfig>().is_err());
+        for url in ["s3://bucket/cache/builds", "gs://bucket", "./artifacts"] {
+            assert_eq!(url.parse::<StorageConfig>().unwrap().to_string(), url);
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use crate::{content_hash, validate_key, ArtifactMeta, ArtifactStore};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::fs::FileTimes;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Stores artifacts under `<root>/objects/<key>` with the recorded hash in
+/// `<root>/meta/<key>.blake3`.
+pub struct LocalStore {
+    root: PathBuf,
+}
+
+impl LocalStore {
+    pub fn new(root: impl AsRef<Path>) -> Result<Self> {
+        let root = root.as_ref().to_path_buf();
+        std::fs::create_dir_all(root.join("objects"))
+            .with_context(|| format!("creating artifact store at {}", root.display()))?;
+        std::fs::create_dir_all(root.join("meta"))?;
+        Ok(Self { root })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn object_path(&self, key: &str) -> PathBuf {
+        self.root.join("objects").join(key)
+    }
+
+    fn meta_path(&self, key: &str) -> PathBuf {
+        self.root.join("meta").join(format!("{}.blake3", key))
+    }
+
+    async fn meta_for(&self, key: &str, path: &Path) -> Result<ArtifactMeta> {
+        let metadata = tokio::fs::metadata(path).await?;
+        let blake3 =
+            tokio::fs::read_to_string(self.meta_path(key)).await.ok().map(|h| h.trim().to_string());
+        let epoch_secs = |t: std::io::Result<SystemTime>| {
+            t.ok().and_then(|t| t.duration_since(UNIX_EPOCH).ok()).map(|d| d.as_secs() as i64)
+        };
+
+        Ok(ArtifactMeta {
+            key: key.to_string(),
+            size: metadata.len(),
+            blake3,
+            modified: epoch_secs(metadata.modified()),
+            accessed: epoch_secs(metadata.accessed()),
+        })
+    }
+}
+
+/// Write through a temporary file next to `path`, named uniquely so concurrent writers of one
+/// key never share it, then rename it into place.
+async fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let name = path.file_name().context("artifact path has no file name")?.to_string_lossy();
+    let tmp = path.with_file_name(format!(".{}.{}.tmp", name, uuid::Uuid::new_v4()));
+    if let Err(e) = tokio::fs::write(&tmp, data).await {
+        let _ = tokio::fs::remove_file(&tmp).await;
+        return Err(e.into());
+    }
+    tokio::fs::rename(&tmp, path).await?;
+    Ok(())
+}
+
+/// Whether `name` is a temporary file of [`write_atomic`], left behind if it was interrupted
+fn is_temp_file(name: &str) -> bool {
+    let middle = name.strip_prefix('.').and_then(|name| name.strip_suffix(".tmp"));
+    middle
+        .and_then(|middle| middle.rsplit_once('.'))
+        .is_some_and(|(_, id)| uuid::Uuid::parse_str(id).is_ok())
+}
+
+/// Record a read explicitly: `relatime`/`noatime` mounts do not reliably update atime, and GC
+/// relies on it for LRU ordering.
+fn touch_accessed(path: &Path) {
+    if let Ok(file) = std::fs::File::options().write(true).open(path) {
+        let _ = file.set_times(FileTimes::new().set_accessed(SystemTime::now()));
+    }
+}
+
+#[async_trait]
+impl ArtifactStore for LocalStore {
+    fn backend_name(&self) -> &'static str {
+        "local"
+    }
+
+    async fn put(&self, key: &str, data: &[u8]) -> Result<ArtifactMeta> {
+        validate_key(key)?;
+        let hash = content_hash(data);
+        write_atomic(&self.object_path(key), data).await?;
+        write_atomic(&self.meta_path(key), hash.as_bytes()).await?;
+        self.meta_for(key, &self.object_path(key)).await
+    }
+
+    async fn fetch(&self, key: &str) -> Result<(Vec<u8>, Option<String>)> {
+        validate_key(key)?;
+        let data = tokio::fs::read(self.object_path(key))
+            .await
+            .with_context(|| format!("artifact not found: {}", key))?;
+        let hash = tokio::fs::read_to_string(self.meta_path(key)).await.ok();
+        touch_accessed(&self.object_path(key));
+        Ok((data, hash.map(|h| h.trim().to_string())))
+    }
+
+    async fn head(&self, key: &str) -> Result<Option<ArtifactMeta>> {
+        validate_key(key)?;
+        let path = self.object_path(key);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(self.meta_for(key, &path).await?))
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        validate_key(key)?;
+        let _ = tokio::fs::remove_file(self.meta_path(key)).await;
+        match tokio::fs::remove_file(self.object_path(key)).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    async fn list(&self) -> Result<Vec<ArtifactMeta>> {
+        let objects = self.root.join("objects");
+        let mut pending = vec![objects.clone()];
+        let mut artifacts = Vec::new();
+
+        while let Some(dir) = pending.pop() {
+            let mut entries = tokio::fs::read_dir(&dir).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                if entry.file_type().await?.is_dir() {
+                    pending.push(path);
+                    continue;
+                }
+                if is_temp_file(&entry.file_name().to_string_lossy()) {
+                    continue;
+                }
+                let key = path
+                    .strip_prefix(&objects)?
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                artifacts.push(self.meta_for(&key, &path).await?);
+            }
+        }
+
+        artifacts.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(artifacts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_only_interrupted_writes_are_left_out_of_listings() {
+        let root = std::env::temp_dir().join(format!("parflow-local-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let store = LocalStore::new(&root).unwrap();
+
+        store.put("logs/build.partial", b"kept").await.unwrap();
+        store.put("logs/build", b"kept").await.unwrap();
+        let leftover = format!(".build.{}.tmp", uuid::Uuid::new_v4());
+        std::fs::write(root.join("objects/logs").join(&leftover), b"torn").unwrap();
+
+        let keys: Vec<_> = store.list().await.unwrap().into_iter().map(|a| a.key).collect();
+        assert_eq!(keys, ["logs/build", "logs/build.partial"]);
+        assert!(is_temp_file(&leftover));
+        assert!(!is_temp_file(".build.tmp"));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
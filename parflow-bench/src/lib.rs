@@ -1,7 +1,10 @@
-use colored::*;
+use parflow_core::cancel::{CancellationToken, Partial};
+use parflow_core::languages;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::Path;
 use std::time::Duration;
+use tracing::{info, instrument};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct LanguageMetrics {
@@ -24,54 +27,56 @@ pub struct BenchmarkRunner;
 
 impl BenchmarkRunner {
     pub async fn benchmark_fibonacci() -> CrossLanguageBenchmark {
-        let mut benchmarks = HashMap::new();
+        Self::benchmark_fibonacci_cancellable(CancellationToken::new()).await.value
+    }
+
+    /// Same as `benchmark_fibonacci`, skipping the remaining languages once `cancel` fires.
+    #[instrument(name = "benchmark", skip_all, fields(suite = "fibonacci"))]
+    pub async fn benchmark_fibonacci_cancellable(
+        cancel: CancellationToken,
+    ) -> Partial<CrossLanguageBenchmark> {
+        let mut measured = Vec::new();
         let mut recommendations = Vec::new();
 
-        println!("{}", "🧪 Running Cross-Language Fibonacci Benchmark".bright_blue().bold());
+        info!("🧪 Running cross-language Fibonacci benchmark");
 
         // Generate mock data for testing (since sysinfo API changed)
-        benchmarks.insert(
-            "rust".to_string(),
-            LanguageMetrics {
-                language: "Rust".to_string(),
-                compilation_time: Duration::from_secs(5),
-                execution_time: Duration::from_millis(50),
-                memory_usage_mb: 2.5,
-                cpu_usage_percent: 45.0,
-                binary_size_mb: 3.2,
-                throughput: 20000.0,
-            },
-        );
-
-        benchmarks.insert(
-            "python".to_string(),
-            LanguageMetrics {
-                language: "Python".to_string(),
-                compilation_time: Duration::from_millis(0),
-                execution_time: Duration::from_millis(500),
-                memory_usage_mb: 50.0,
-                cpu_usage_percent: 80.0,
-                binary_size_mb: 0.1,
-                throughput: 2000.0,
-            },
-        );
-
-        benchmarks.insert(
-            "node".to_string(),
-            LanguageMetrics {
-                language: "Node.js".to_string(),
-                compilation_time: Duration::from_millis(0),
-                execution_time: Duration::from_millis(300),
-                memory_usage_mb: 70.0,
-                cpu_usage_percent: 75.0,
-                binary_size_mb: 0.1,
-                throughput: 3333.0,
-            },
-        );
+        measured.push(LanguageMetrics {
+            language: "rust".to_string(),
+            compilation_time: Duration::from_secs(5),
+            execution_time: Duration::from_millis(50),
+            memory_usage_mb: 2.5,
+            cpu_usage_percent: 45.0,
+            binary_size_mb: 3.2,
+            throughput: 20000.0,
+        });
+
+        measured.push(LanguageMetrics {
+            language: "python".to_string(),
+            compilation_time: Duration::from_millis(0),
+            execution_time: Duration::from_millis(500),
+            memory_usage_mb: 50.0,
+            cpu_usage_percent: 80.0,
+            binary_size_mb: 0.1,
+            throughput: 2000.0,
+        });
+
+        measured.push(LanguageMetrics {
+            language: "javascript".to_string(),
+            compilation_time: Duration::from_millis(0),
+            execution_time: Duration::from_millis(300),
+            memory_usage_mb: 70.0,
+            cpu_usage_percent: 75.0,
+            binary_size_mb: 0.1,
+            throughput: 3333.0,
+        });
+
+        let benchmarks = Self::collect(measured, &cancel).await;
+        Self::record_run("fibonacci", &cancel);
 
         // Generate recommendations based on mock data
         if let (Some(rust), Some(python), Some(node)) =
-            (benchmarks.get("rust"), benchmarks.get("python"), benchmarks.get("node"))
+            (benchmarks.get("rust"), benchmarks.get("python"), benchmarks.get("javascript"))
         {
             if rust.throughput > python.throughput * 10.0 {
                 recommendations.push(
@@ -103,61 +108,98 @@ impl BenchmarkRunner {
             ));
         }
 
-        CrossLanguageBenchmark { benchmarks, recommendations }
+        Partial::checked(CrossLanguageBenchmark { benchmarks, recommendations }, &cancel)
     }
 
     // Simplified version without sysinfo dependency for now
     pub async fn benchmark_simple() -> CrossLanguageBenchmark {
-        println!("{}", "🧪 Running Simple Cross-Language Benchmark".bright_blue().bold());
+        Self::benchmark_simple_cancellable(CancellationToken::new()).await.value
+    }
 
-        let mut benchmarks = HashMap::new();
+    /// Same as `benchmark_simple`, skipping the remaining languages once `cancel` fires.
+    #[instrument(name = "benchmark", skip_all, fields(suite = "simple"))]
+    pub async fn benchmark_simple_cancellable(
+        cancel: CancellationToken,
+    ) -> Partial<CrossLanguageBenchmark> {
+        info!("🧪 Running simple cross-language benchmark");
+
+        let mut measured = Vec::new();
         let mut recommendations = Vec::new();
 
         // Mock data for different scenarios
-        benchmarks.insert(
-            "rust".to_string(),
-            LanguageMetrics {
-                language: "Rust".to_string(),
-                compilation_time: Duration::from_secs(3),
-                execution_time: Duration::from_millis(10),
-                memory_usage_mb: 1.5,
-                cpu_usage_percent: 30.0,
-                binary_size_mb: 2.8,
-                throughput: 100000.0,
-            },
-        );
-
-        benchmarks.insert(
-            "go".to_string(),
-            LanguageMetrics {
-                language: "Go".to_string(),
-                compilation_time: Duration::from_secs(2),
-                execution_time: Duration::from_millis(15),
-                memory_usage_mb: 3.0,
-                cpu_usage_percent: 35.0,
-                binary_size_mb: 5.2,
-                throughput: 66666.0,
-            },
-        );
-
-        benchmarks.insert(
-            "python".to_string(),
-            LanguageMetrics {
-                language: "Python".to_string(),
-                compilation_time: Duration::from_millis(0),
-                execution_time: Duration::from_millis(100),
-                memory_usage_mb: 25.0,
-                cpu_usage_percent: 60.0,
-                binary_size_mb: 0.1,
-                throughput: 10000.0,
-            },
-        );
+        measured.push(LanguageMetrics {
+            language: "rust".to_string(),
+            compilation_time: Duration::from_secs(3),
+            execution_time: Duration::from_millis(10),
+            memory_usage_mb: 1.5,
+            cpu_usage_percent: 30.0,
+            binary_size_mb: 2.8,
+            throughput: 100000.0,
+        });
+
+        measured.push(LanguageMetrics {
+            language: "go".to_string(),
+            compilation_time: Duration::from_secs(2),
+            execution_time: Duration::from_millis(15),
+            memory_usage_mb: 3.0,
+            cpu_usage_percent: 35.0,
+            binary_size_mb: 5.2,
+            throughput: 66666.0,
+        });
+
+        measured.push(LanguageMetrics {
+            language: "python".to_string(),
+            compilation_time: Duration::from_millis(0),
+            execution_time: Duration::from_millis(100),
+            memory_usage_mb: 25.0,
+            cpu_usage_percent: 60.0,
+            binary_size_mb: 0.1,
+            throughput: 10000.0,
+        });
+
+        let benchmarks = Self::collect(measured, &cancel).await;
+        Self::record_run("simple", &cancel);
 
         recommendations
             .push("🚀 Rust offers the best performance for CPU-intensive tasks".to_string());
         recommendations.push("🐍 Python provides fastest development iteration".to_string());
         recommendations.push("⚡ Go balances performance and compilation speed".to_string());
 
-        CrossLanguageBenchmark { benchmarks, recommendations }
+        Partial::checked(CrossLanguageBenchmark { benchmarks, recommendations }, &cancel)
+    }
+
+    /// Count a finished suite run on the `metrics` facade, e.g. for parflow-rest's `/metrics`.
+    fn record_run(suite: &'static str, cancel: &CancellationToken) {
+        let outcome = if cancel.is_cancelled() { "cancelled" } else { "complete" };
+        metrics::counter!(
+            "parflow_benchmark_runs_total",
+            "subsystem" => "bench",
+            "suite" => suite,
+            "outcome" => outcome
+        )
+        .increment(1);
+    }
+
+    /// Record each language's metrics in turn, stopping at the first one after cancellation.
+    /// Languages are keyed by their plugin's name; those without a build command take no time
+    /// to compile, and those without a plugin are left out.
+    async fn collect(
+        measured: Vec<LanguageMetrics>,
+        cancel: &CancellationToken,
+    ) -> HashMap<String, LanguageMetrics> {
+        let mut benchmarks = HashMap::new();
+        for mut metrics in measured {
+            if cancel.is_cancelled() {
+                break;
+            }
+            let Some(plugin) = languages::get(&metrics.language) else { continue };
+            metrics.language = plugin.name().to_string();
+            if plugin.build_command(Path::new(".")).is_none() {
+                metrics.compilation_time = Duration::ZERO;
+            }
+            benchmarks.insert(metrics.language.clone(), metrics);
+            tokio::task::yield_now().await;
+        }
+        benchmarks
     }
 }
@@ -0,0 +1,137 @@
+#ifndef PARFLOW_H
+#define PARFLOW_H
+
+/* Generated by cbindgen from parflow-c; do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Result of every fallible `parflow_*` call.
+//
+// On anything other than `PARFLOW_STATUS_OK` or `PARFLOW_STATUS_PENDING`, a description is
+// available from `parflow_last_error_message()`.
+typedef enum ParflowStatus {
+  PARFLOW_STATUS_OK = 0,
+  // The workflow is still running; poll again later
+  PARFLOW_STATUS_PENDING = 1,
+  PARFLOW_STATUS_NULL_ARGUMENT = 2,
+  PARFLOW_STATUS_INVALID_UTF8 = 3,
+  PARFLOW_STATUS_INVALID_WORKFLOW = 4,
+  PARFLOW_STATUS_UNKNOWN_WORKFLOW = 5,
+  PARFLOW_STATUS_RUNTIME = 6,
+  // A Rust panic was caught at the API boundary
+  PARFLOW_STATUS_PANIC = 7,
+  // The orchestrator was freed before the workflow finished
+  PARFLOW_STATUS_CANCELLED = 8,
+} ParflowStatus;
+
+// An orchestrator instance with its own worker threads. Opaque to C.
+typedef struct ParflowOrchestrator ParflowOrchestrator;
+
+// Called exactly once when a workflow started with `parflow_submit_workflow_async` ends.
+//
+// `status` is `PARFLOW_STATUS_OK` with `results_json` holding the JSON array
+// `parflow_workflow_poll` would return, or `PARFLOW_STATUS_CANCELLED` with NULL results if the
+// orchestrator was freed first. `results_json` is only valid during the call.
+typedef void (*ParflowWorkflowCallback)(uint64_t workflow_id,
+                                        enum ParflowStatus status,
+                                        const char *results_json,
+                                        void *user_data);
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Create an orchestrator and store it in `*out`. Free it with `parflow_orchestrator_free`.
+//
+// # Safety
+// `out` must be NULL or valid for writes.
+enum ParflowStatus parflow_orchestrator_new(struct ParflowOrchestrator **out);
+
+// Destroy an orchestrator, cancelling workflows that are still running. NULL is ignored.
+//
+// # Safety
+// `orchestrator` must be NULL or come from `parflow_orchestrator_new`, and must not be used
+// again afterwards.
+void parflow_orchestrator_free(struct ParflowOrchestrator *orchestrator);
+
+// Start a workflow described as JSON and store its ID in `*out_id`.
+//
+// The JSON has the shape `{"name": "...", "concurrent": true, "tasks": [{"language": "rust",
+// "command": "cargo", "args": ["build"], "working_dir": null, "timeout_seconds": 300}]}`.
+//
+// # Safety
+// Pointer arguments must be NULL or valid and `workflow_json` NUL-terminated; `orchestrator`
+// must come from `parflow_orchestrator_new` and not have been freed.
+enum ParflowStatus parflow_workflow_submit(const struct ParflowOrchestrator *orchestrator,
+                                           const char *workflow_json,
+                                           uint64_t *out_id);
+
+// Check on a workflow without blocking.
+//
+// Returns `PARFLOW_STATUS_PENDING` while it runs. Once it has finished, returns
+// `PARFLOW_STATUS_OK` and stores a JSON array of task results in `*out_results`, to be freed
+// with `parflow_string_free`. Results can be collected only once.
+//
+// # Safety
+// Pointer arguments must be NULL or valid; `orchestrator` must come from
+// `parflow_orchestrator_new` and not have been freed.
+enum ParflowStatus parflow_workflow_poll(const struct ParflowOrchestrator *orchestrator,
+                                         uint64_t workflow_id,
+                                         char **out_results);
+
+// Block until a workflow finishes, then behave like `parflow_workflow_poll`.
+//
+// # Safety
+// Pointer arguments must be NULL or valid; `orchestrator` must come from
+// `parflow_orchestrator_new` and not have been freed.
+enum ParflowStatus parflow_workflow_wait(const struct ParflowOrchestrator *orchestrator,
+                                         uint64_t workflow_id,
+                                         char **out_results);
+
+// Start a workflow and return immediately; `callback` is invoked with the results on one of
+// the orchestrator's worker threads. The workflow ID is stored in `*out_id` (which may be
+// NULL) before the callback can run. Async workflows cannot be polled or waited on.
+//
+// Workflows still running when the orchestrator is freed get a `PARFLOW_STATUS_CANCELLED`
+// callback before `parflow_orchestrator_free` returns.
+//
+// # Safety
+// `orchestrator` must come from `parflow_orchestrator_new` and not have been freed,
+// `workflow_json` must be NUL-terminated, and `user_data` must stay valid and be safe to use
+// from another thread until the callback has run.
+enum ParflowStatus parflow_submit_workflow_async(const struct ParflowOrchestrator *orchestrator,
+                                                 const char *workflow_json,
+                                                 ParflowWorkflowCallback callback,
+                                                 void *user_data,
+                                                 uint64_t *out_id);
+
+// Free a string returned by the library. NULL is ignored.
+//
+// # Safety
+// `text` must be NULL or a string returned by this library that has not been freed.
+void parflow_string_free(char *text);
+
+// Run the parallel example and return the sum, or -1 on failure.
+int run_orchestrator_par(void);
+
+// Sequential version of `run_orchestrator_par`.
+int run_orchestrator_seq(void);
+
+// Message describing the last error on the calling thread, or NULL if there was none.
+//
+// The string is owned by the library and stays valid until the next failing call on the same
+// thread. Do not free it.
+const char *parflow_last_error_message(void);
+
+// Forget the last error on the calling thread.
+void parflow_clear_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* PARFLOW_H */
@@ -0,0 +1,82 @@
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// Result of every fallible `parflow_*` call.
+///
+/// On anything other than `PARFLOW_STATUS_OK` or `PARFLOW_STATUS_PENDING`, a description is
+/// available from `parflow_last_error_message()`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParflowStatus {
+    Ok = 0,
+    /// The workflow is still running; poll again later
+    Pending = 1,
+    NullArgument = 2,
+    InvalidUtf8 = 3,
+    InvalidWorkflow = 4,
+    UnknownWorkflow = 5,
+    Runtime = 6,
+    /// A Rust panic was caught at the API boundary
+    Panic = 7,
+    /// The orchestrator was freed before the workflow finished
+    Cancelled = 8,
+}
+
+/// An error on its way across the FFI boundary.
+pub(crate) struct Error {
+    pub status: ParflowStatus,
+    pub message: String,
+}
+
+impl Error {
+    pub fn new(status: ParflowStatus, message: impl Into<String>) -> Self {
+        Self { status, message: message.into() }
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+pub(crate) fn set_last_error(message: &str) {
+    // Interior NULs would truncate the message in C, so drop them
+    let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Run `body`, turning errors and panics into a status and recording the message.
+pub(crate) fn guard(body: impl FnOnce() -> Result<ParflowStatus, Error>) -> ParflowStatus {
+    match catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(status)) => status,
+        Ok(Err(error)) => {
+            set_last_error(&error.message);
+            error.status
+        }
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            set_last_error(&format!("panic: {}", message));
+            ParflowStatus::Panic
+        }
+    }
+}
+
+/// Message describing the last error on the calling thread, or NULL if there was none.
+///
+/// The string is owned by the library and stays valid until the next failing call on the same
+/// thread. Do not free it.
+#[no_mangle]
+pub extern "C" fn parflow_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(std::ptr::null(), |m| m.as_ptr()))
+}
+
+/// Forget the last error on the calling thread.
+#[no_mangle]
+pub extern "C" fn parflow_clear_last_error() {
+    LAST_ERROR.with(|last| *last.borrow_mut() = None);
+}
@@ -1,17 +1,473 @@
-use std::os::raw::c_int;
+//! C API for ParFlow.
+//!
+//! The header is generated into `include/parflow.h` by cbindgen on every build. Every fallible
+//! function returns a [`ParflowStatus`]; on failure a message is available from
+//! [`parflow_last_error_message`]. Objects created by the library are released with the matching
+//! `*_free` function.
+//!
+//! ```c
+//! ParflowOrchestrator *orch;
+//! uint64_t id;
+//! char *results;
+//! if (parflow_orchestrator_new(&orch) != PARFLOW_STATUS_OK ||
+//!     parflow_workflow_submit(orch, "{\"name\":\"build\",\"concurrent\":true,\"tasks\":[...]}",
+//!                             &id) != PARFLOW_STATUS_OK ||
+//!     parflow_workflow_wait(orch, id, &results) != PARFLOW_STATUS_OK) {
+//!     fprintf(stderr, "parflow: %s\n", parflow_last_error_message());
+//! }
+//! parflow_string_free(results);
+//! parflow_orchestrator_free(orch);
+//! ```
+//!
+//! # Threads
+//!
+//! All functions may be called from any thread, and concurrently on the same orchestrator.
+//! Each orchestrator runs workflows on its own pool of worker threads; callbacks passed to
+//! [`parflow_submit_workflow_async`] run on those threads, possibly several at once. A callback
+//! may submit more workflows but must not block on one (`parflow_workflow_wait`,
+//! `parflow_workflow_poll`) or free the orchestrator.
 
-/// Expose a simple C ABI function that runs the parallel example and returns the sum.
+use error::{guard, set_last_error, Error};
+use parflow_orchestrator::{ExecutionResult, MultiLanguageOrchestrator, MultiLanguageWorkflow};
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int, c_void};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tokio::task::JoinHandle;
+
+mod error;
+
+pub use error::{parflow_clear_last_error, parflow_last_error_message, ParflowStatus};
+
+/// An orchestrator instance with its own worker threads. Opaque to C.
+pub struct ParflowOrchestrator {
+    runtime: tokio::runtime::Runtime,
+    workflows: Mutex<HashMap<u64, JoinHandle<Vec<ExecutionResult>>>>,
+    next_id: AtomicU64,
+}
+
+fn runtime() -> Result<tokio::runtime::Runtime, Error> {
+    tokio::runtime::Runtime::new()
+        .map_err(|e| Error::new(ParflowStatus::Runtime, format!("failed to start runtime: {}", e)))
+}
+
+/// # Safety
+/// `ptr` is NULL or came from `parflow_orchestrator_new` and has not been freed.
+unsafe fn orchestrator<'a>(
+    ptr: *const ParflowOrchestrator,
+) -> Result<&'a ParflowOrchestrator, Error> {
+    ptr.as_ref().ok_or_else(|| Error::new(ParflowStatus::NullArgument, "orchestrator is NULL"))
+}
+
+/// # Safety
+/// `ptr` is NULL or valid for writes.
+unsafe fn out_param<'a, T>(ptr: *mut T, name: &str) -> Result<&'a mut T, Error> {
+    ptr.as_mut().ok_or_else(|| Error::new(ParflowStatus::NullArgument, format!("{} is NULL", name)))
+}
+
+fn to_c_string(text: String) -> Result<*mut c_char, Error> {
+    CString::new(text)
+        .map(CString::into_raw)
+        .map_err(|e| Error::new(ParflowStatus::Runtime, e.to_string()))
+}
+
+/// Create an orchestrator and store it in `*out`. Free it with `parflow_orchestrator_free`.
+///
+/// # Safety
+/// `out` must be NULL or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn parflow_orchestrator_new(
+    out: *mut *mut ParflowOrchestrator,
+) -> ParflowStatus {
+    guard(|| {
+        let out = out_param(out, "out")?;
+        let orchestrator = ParflowOrchestrator {
+            runtime: runtime()?,
+            workflows: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+        };
+        *out = Box::into_raw(Box::new(orchestrator));
+        Ok(ParflowStatus::Ok)
+    })
+}
+
+/// Destroy an orchestrator, cancelling workflows that are still running. NULL is ignored.
+///
+/// # Safety
+/// `orchestrator` must be NULL or come from `parflow_orchestrator_new`, and must not be used
+/// again afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn parflow_orchestrator_free(orchestrator: *mut ParflowOrchestrator) {
+    if !orchestrator.is_null() {
+        drop(Box::from_raw(orchestrator));
+    }
+}
+
+/// # Safety
+/// `workflow_json` is NULL or a NUL-terminated string.
+unsafe fn parse_workflow(workflow_json: *const c_char) -> Result<MultiLanguageWorkflow, Error> {
+    if workflow_json.is_null() {
+        return Err(Error::new(ParflowStatus::NullArgument, "workflow_json is NULL"));
+    }
+    let json = CStr::from_ptr(workflow_json)
+        .to_str()
+        .map_err(|e| Error::new(ParflowStatus::InvalidUtf8, e.to_string()))?;
+    let workflow: MultiLanguageWorkflow = serde_json::from_str(json)
+        .map_err(|e| Error::new(ParflowStatus::InvalidWorkflow, e.to_string()))?;
+    if workflow.tasks.is_empty() {
+        return Err(Error::new(ParflowStatus::InvalidWorkflow, "workflow has no tasks"));
+    }
+    Ok(workflow)
+}
+
+/// Start a workflow described as JSON and store its ID in `*out_id`.
+///
+/// The JSON has the shape `{"name": "...", "concurrent": true, "tasks": [{"language": "rust",
+/// "command": "cargo", "args": ["build"], "working_dir": null, "timeout_seconds": 300}]}`.
+///
+/// # Safety
+/// Pointer arguments must be NULL or valid and `workflow_json` NUL-terminated; `orchestrator`
+/// must come from `parflow_orchestrator_new` and not have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn parflow_workflow_submit(
+    orchestrator: *const ParflowOrchestrator,
+    workflow_json: *const c_char,
+    out_id: *mut u64,
+) -> ParflowStatus {
+    guard(|| {
+        let orchestrator = self::orchestrator(orchestrator)?;
+        let out_id = out_param(out_id, "out_id")?;
+        let workflow = parse_workflow(workflow_json)?;
+        let id = orchestrator.next_id.fetch_add(1, Ordering::Relaxed);
+        let handle =
+            orchestrator.runtime.spawn(MultiLanguageOrchestrator::execute_workflow(workflow));
+        orchestrator.workflows.lock().unwrap().insert(id, handle);
+        *out_id = id;
+        Ok(ParflowStatus::Ok)
+    })
+}
+
+fn finish(
+    orchestrator: &ParflowOrchestrator,
+    id: u64,
+    wait: bool,
+    out_results: &mut *mut c_char,
+) -> Result<ParflowStatus, Error> {
+    let handle = {
+        let mut workflows = orchestrator.workflows.lock().unwrap();
+        let Some(handle) = workflows.get(&id) else {
+            return Err(Error::new(
+                ParflowStatus::UnknownWorkflow,
+                format!("unknown workflow {}", id),
+            ));
+        };
+        if !wait && !handle.is_finished() {
+            return Ok(ParflowStatus::Pending);
+        }
+        workflows.remove(&id).expect("present above")
+    };
+
+    let results = orchestrator.runtime.block_on(handle).map_err(|e| {
+        Error::new(ParflowStatus::Runtime, format!("workflow {} failed: {}", id, e))
+    })?;
+    let json = serde_json::to_string(&results)
+        .map_err(|e| Error::new(ParflowStatus::Runtime, e.to_string()))?;
+    *out_results = to_c_string(json)?;
+    Ok(ParflowStatus::Ok)
+}
+
+/// Check on a workflow without blocking.
+///
+/// Returns `PARFLOW_STATUS_PENDING` while it runs. Once it has finished, returns
+/// `PARFLOW_STATUS_OK` and stores a JSON array of task results in `*out_results`, to be freed
+/// with `parflow_string_free`. Results can be collected only once.
+///
+/// # Safety
+/// Pointer arguments must be NULL or valid; `orchestrator` must come from
+/// `parflow_orchestrator_new` and not have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn parflow_workflow_poll(
+    orchestrator: *const ParflowOrchestrator,
+    workflow_id: u64,
+    out_results: *mut *mut c_char,
+) -> ParflowStatus {
+    guard(|| {
+        let orchestrator = self::orchestrator(orchestrator)?;
+        finish(orchestrator, workflow_id, false, out_param(out_results, "out_results")?)
+    })
+}
+
+/// Block until a workflow finishes, then behave like `parflow_workflow_poll`.
+///
+/// # Safety
+/// Pointer arguments must be NULL or valid; `orchestrator` must come from
+/// `parflow_orchestrator_new` and not have been freed.
+#[no_mangle]
+pub unsafe extern "C" fn parflow_workflow_wait(
+    orchestrator: *const ParflowOrchestrator,
+    workflow_id: u64,
+    out_results: *mut *mut c_char,
+) -> ParflowStatus {
+    guard(|| {
+        let orchestrator = self::orchestrator(orchestrator)?;
+        finish(orchestrator, workflow_id, true, out_param(out_results, "out_results")?)
+    })
+}
+
+/// Called exactly once when a workflow started with `parflow_submit_workflow_async` ends.
+///
+/// `status` is `PARFLOW_STATUS_OK` with `results_json` holding the JSON array
+/// `parflow_workflow_poll` would return, or `PARFLOW_STATUS_CANCELLED` with NULL results if the
+/// orchestrator was freed first. `results_json` is only valid during the call.
+pub type ParflowWorkflowCallback = Option<
+    unsafe extern "C" fn(
+        workflow_id: u64,
+        status: ParflowStatus,
+        results_json: *const c_char,
+        user_data: *mut c_void,
+    ),
+>;
+
+/// Invokes the callback exactly once: with the outcome, or as cancelled if dropped unfinished.
+struct Completion {
+    workflow_id: u64,
+    callback: unsafe extern "C" fn(u64, ParflowStatus, *const c_char, *mut c_void),
+    user_data: *mut c_void,
+    delivered: bool,
+}
+
+// SAFETY: hosts agree that `user_data` may be used from any thread, see
+// `parflow_submit_workflow_async`
+unsafe impl Send for Completion {}
+
+impl Completion {
+    fn deliver(&mut self, status: ParflowStatus, results: Option<&CStr>) {
+        self.delivered = true;
+        let results = results.map_or(std::ptr::null(), CStr::as_ptr);
+        // SAFETY: the host supplied a valid function pointer and matching `user_data`
+        unsafe { (self.callback)(self.workflow_id, status, results, self.user_data) }
+    }
+}
+
+impl Drop for Completion {
+    fn drop(&mut self) {
+        if !self.delivered {
+            self.deliver(ParflowStatus::Cancelled, None);
+        }
+    }
+}
+
+/// Start a workflow and return immediately; `callback` is invoked with the results on one of
+/// the orchestrator's worker threads. The workflow ID is stored in `*out_id` (which may be
+/// NULL) before the callback can run. Async workflows cannot be polled or waited on.
+///
+/// Workflows still running when the orchestrator is freed get a `PARFLOW_STATUS_CANCELLED`
+/// callback before `parflow_orchestrator_free` returns.
+///
+/// # Safety
+/// `orchestrator` must come from `parflow_orchestrator_new` and not have been freed,
+/// `workflow_json` must be NUL-terminated, and `user_data` must stay valid and be safe to use
+/// from another thread until the callback has run.
+#[no_mangle]
+pub unsafe extern "C" fn parflow_submit_workflow_async(
+    orchestrator: *const ParflowOrchestrator,
+    workflow_json: *const c_char,
+    callback: ParflowWorkflowCallback,
+    user_data: *mut c_void,
+    out_id: *mut u64,
+) -> ParflowStatus {
+    guard(|| {
+        let orchestrator = self::orchestrator(orchestrator)?;
+        let callback =
+            callback.ok_or_else(|| Error::new(ParflowStatus::NullArgument, "callback is NULL"))?;
+        let workflow = parse_workflow(workflow_json)?;
+
+        let workflow_id = orchestrator.next_id.fetch_add(1, Ordering::Relaxed);
+        if let Some(out_id) = out_id.as_mut() {
+            *out_id = workflow_id;
+        }
+        let mut completion = Completion { workflow_id, callback, user_data, delivered: false };
+        orchestrator.runtime.spawn(async move {
+            let results = MultiLanguageOrchestrator::execute_workflow(workflow).await;
+            match serde_json::to_string(&results).ok().and_then(|json| CString::new(json).ok()) {
+                Some(json) => completion.deliver(ParflowStatus::Ok, Some(&json)),
+                None => completion.deliver(ParflowStatus::Runtime, None),
+            }
+        });
+        Ok(ParflowStatus::Ok)
+    })
+}
+
+/// Free a string returned by the library. NULL is ignored.
+///
+/// # Safety
+/// `text` must be NULL or a string returned by this library that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn parflow_string_free(text: *mut c_char) {
+    if !text.is_null() {
+        drop(CString::from_raw(text));
+    }
+}
+
+fn run_example(parallel: bool) -> c_int {
+    match runtime() {
+        Ok(rt) => {
+            let vec = if parallel {
+                rt.block_on(parflow_core::run_example_par())
+            } else {
+                rt.block_on(parflow_core::run_example_seq())
+            };
+            vec.into_iter().sum::<i32>() as c_int
+        }
+        Err(e) => {
+            set_last_error(&e.message);
+            -1
+        }
+    }
+}
+
+/// Run the parallel example and return the sum, or -1 on failure.
 #[no_mangle]
 pub extern "C" fn run_orchestrator_par() -> c_int {
-    let rt = tokio::runtime::Runtime::new().unwrap();
-    let vec = rt.block_on(parflow_core::run_example_par());
-    vec.into_iter().sum::<i32>() as c_int
+    run_example(true)
 }
 
-/// Expose sequential version.
+/// Sequential version of `run_orchestrator_par`.
 #[no_mangle]
 pub extern "C" fn run_orchestrator_seq() -> c_int {
-    let rt = tokio::runtime::Runtime::new().unwrap();
-    let vec = rt.block_on(parflow_core::run_example_seq());
-    vec.into_iter().sum::<i32>() as c_int
+    run_example(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn last_error() -> String {
+        unsafe { CStr::from_ptr(parflow_last_error_message()) }.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_submit_poll_and_errors() {
+        unsafe {
+            let mut orch = std::ptr::null_mut();
+            assert_eq!(parflow_orchestrator_new(&mut orch), ParflowStatus::Ok);
+
+            let mut id = 0;
+            let bad = CString::new("{\"name\": 1}").unwrap();
+            assert_eq!(
+                parflow_workflow_submit(orch, bad.as_ptr(), &mut id),
+                ParflowStatus::InvalidWorkflow
+            );
+            assert!(!last_error().is_empty());
+            assert_eq!(
+                parflow_workflow_submit(std::ptr::null(), bad.as_ptr(), &mut id),
+                ParflowStatus::NullArgument
+            );
+            assert_eq!(last_error(), "orchestrator is NULL");
+
+            let workflow = CString::new(
+                r#"{"name": "c-api", "concurrent": true, "tasks": [
+                {"language": "rust", "command": "cargo", "args": [], "working_dir": null,
+                 "timeout_seconds": null}]}"#,
+            )
+            .unwrap();
+            assert_eq!(
+                parflow_workflow_submit(orch, workflow.as_ptr(), &mut id),
+                ParflowStatus::Ok
+            );
+
+            let mut results = std::ptr::null_mut();
+            assert_eq!(parflow_workflow_poll(orch, id, &mut results), ParflowStatus::Pending);
+            assert_eq!(parflow_workflow_wait(orch, id, &mut results), ParflowStatus::Ok);
+            let json = CStr::from_ptr(results).to_str().unwrap().to_string();
+            let parsed: Vec<ExecutionResult> = serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed.len(), 1);
+            assert!(parsed[0].success);
+            parflow_string_free(results);
+
+            assert_eq!(
+                parflow_workflow_poll(orch, id, &mut results),
+                ParflowStatus::UnknownWorkflow
+            );
+            parflow_orchestrator_free(orch);
+        }
+    }
+
+    unsafe extern "C" fn count_outcome(
+        _workflow_id: u64,
+        status: ParflowStatus,
+        results_json: *const c_char,
+        user_data: *mut c_void,
+    ) {
+        let counters = &*(user_data as *const [std::sync::atomic::AtomicUsize; 2]);
+        let slot = match status {
+            ParflowStatus::Ok if !results_json.is_null() => 0,
+            _ => 1,
+        };
+        counters[slot].fetch_add(1, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn test_async_callbacks_from_many_threads() {
+        use std::sync::atomic::AtomicUsize;
+
+        let counters = [AtomicUsize::new(0), AtomicUsize::new(0)];
+        let workflow = CString::new(
+            r#"{"name": "async", "concurrent": false, "tasks": [
+                {"language": "go", "command": "go", "args": [], "working_dir": null,
+                 "timeout_seconds": null}]}"#,
+        )
+        .unwrap();
+        let user_data = &counters as *const _ as *mut c_void;
+
+        unsafe {
+            let mut orch = std::ptr::null_mut();
+            assert_eq!(parflow_orchestrator_new(&mut orch), ParflowStatus::Ok);
+            let shared = orch as usize;
+            std::thread::scope(|scope| {
+                for _ in 0..4 {
+                    let workflow = &workflow;
+                    let user_data = user_data as usize;
+                    scope.spawn(move || {
+                        for _ in 0..4 {
+                            let status = parflow_submit_workflow_async(
+                                shared as *const ParflowOrchestrator,
+                                workflow.as_ptr(),
+                                Some(count_outcome),
+                                user_data as *mut c_void,
+                                std::ptr::null_mut(),
+                            );
+                            assert_eq!(status, ParflowStatus::Ok);
+                        }
+                    });
+                }
+            });
+
+            let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+            while counters[0].load(Ordering::SeqCst) < 16 {
+                assert!(std::time::Instant::now() < deadline, "callbacks did not arrive");
+                std::thread::sleep(std::time::Duration::from_millis(20));
+            }
+
+            // Freed while running: the callback still fires, as cancelled
+            let mut id = 0;
+            let status = parflow_submit_workflow_async(
+                orch,
+                workflow.as_ptr(),
+                Some(count_outcome),
+                user_data,
+                &mut id,
+            );
+            assert_eq!(status, ParflowStatus::Ok);
+            assert!(id > 0);
+            assert_eq!(
+                parflow_submit_workflow_async(orch, workflow.as_ptr(), None, user_data, &mut id),
+                ParflowStatus::NullArgument
+            );
+            parflow_orchestrator_free(orch);
+        }
+        assert_eq!(counters[0].load(Ordering::SeqCst), 16);
+        assert_eq!(counters[1].load(Ordering::SeqCst), 1);
+    }
 }
@@ -0,0 +1,118 @@
+// Stress test for parflow_submit_workflow_async, meant to run under ThreadSanitizer.
+//
+// Several host threads submit workflows to one orchestrator at once; callbacks update shared
+// state from ParFlow's worker threads. The last workflow is still running when the
+// orchestrator is freed, so it must be reported as cancelled. Built and run by
+// scripts/tsan-c-api.sh.
+
+#include <pthread.h>
+#include <stdatomic.h>
+#include <stdio.h>
+#include <string.h>
+#include <unistd.h>
+
+#include "parflow.h"
+
+#define SUBMITTERS 4
+#define PER_SUBMITTER 8
+
+static const char *WORKFLOW =
+    "{\"name\": \"tsan\", \"concurrent\": true, \"tasks\": ["
+    "{\"language\": \"rust\", \"command\": \"cargo\", \"args\": [\"build\"],"
+    " \"working_dir\": null, \"timeout_seconds\": null},"
+    "{\"language\": \"go\", \"command\": \"go\", \"args\": [\"build\"],"
+    " \"working_dir\": null, \"timeout_seconds\": null}]}";
+
+struct outcomes {
+    atomic_int ok;
+    atomic_int cancelled;
+    atomic_int other;
+    // Written by callbacks under the mutex so TSAN checks the host-side locking too
+    pthread_mutex_t lock;
+    size_t result_bytes;
+};
+
+static void on_done(uint64_t workflow_id, enum ParflowStatus status, const char *results_json,
+                    void *user_data) {
+    struct outcomes *outcomes = user_data;
+    (void)workflow_id;
+    switch (status) {
+    case PARFLOW_STATUS_OK:
+        pthread_mutex_lock(&outcomes->lock);
+        outcomes->result_bytes += strlen(results_json);
+        pthread_mutex_unlock(&outcomes->lock);
+        atomic_fetch_add(&outcomes->ok, 1);
+        break;
+    case PARFLOW_STATUS_CANCELLED:
+        atomic_fetch_add(&outcomes->cancelled, 1);
+        break;
+    default:
+        atomic_fetch_add(&outcomes->other, 1);
+    }
+}
+
+struct submitter {
+    const struct ParflowOrchestrator *orch;
+    struct outcomes *outcomes;
+    int failures;
+};
+
+static void *submit(void *arg) {
+    struct submitter *s = arg;
+    for (int i = 0; i < PER_SUBMITTER; i++) {
+        uint64_t id = 0;
+        if (parflow_submit_workflow_async(s->orch, WORKFLOW, on_done, s->outcomes, &id) !=
+            PARFLOW_STATUS_OK) {
+            fprintf(stderr, "submit failed: %s\n", parflow_last_error_message());
+            s->failures++;
+        }
+    }
+    return NULL;
+}
+
+int main(void) {
+    struct ParflowOrchestrator *orch = NULL;
+    struct outcomes outcomes = {0};
+    pthread_mutex_init(&outcomes.lock, NULL);
+
+    if (parflow_orchestrator_new(&orch) != PARFLOW_STATUS_OK) {
+        fprintf(stderr, "orchestrator_new failed: %s\n", parflow_last_error_message());
+        return 1;
+    }
+
+    pthread_t threads[SUBMITTERS];
+    struct submitter submitters[SUBMITTERS];
+    for (int i = 0; i < SUBMITTERS; i++) {
+        submitters[i] = (struct submitter){orch, &outcomes, 0};
+        pthread_create(&threads[i], NULL, submit, &submitters[i]);
+    }
+    int failures = 0;
+    for (int i = 0; i < SUBMITTERS; i++) {
+        pthread_join(threads[i], NULL);
+        failures += submitters[i].failures;
+    }
+
+    const int expected = SUBMITTERS * PER_SUBMITTER;
+    for (int waited = 0; atomic_load(&outcomes.ok) < expected && waited < 1000; waited++) {
+        usleep(10 * 1000);
+    }
+
+    if (parflow_submit_workflow_async(orch, WORKFLOW, on_done, &outcomes, NULL) !=
+        PARFLOW_STATUS_OK) {
+        failures++;
+    }
+    parflow_orchestrator_free(orch);
+
+    int ok = atomic_load(&outcomes.ok);
+    int cancelled = atomic_load(&outcomes.cancelled);
+    int other = atomic_load(&outcomes.other);
+    printf("ok=%d cancelled=%d other=%d result_bytes=%zu\n", ok, cancelled, other,
+           outcomes.result_bytes);
+    pthread_mutex_destroy(&outcomes.lock);
+
+    if (failures || ok != expected || cancelled != 1 || other) {
+        fprintf(stderr, "unexpected callback outcomes\n");
+        return 1;
+    }
+    return 0;
+}
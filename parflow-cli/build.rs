@@ -0,0 +1,20 @@
+//! Records how the binary was built so `parflow status --capabilities` can report it.
+
+fn main() {
+    let var = |name: &str| std::env::var(name).unwrap_or_default();
+
+    // musl targets link the C runtime statically by default, glibc ones only when asked
+    let static_crt = var("CARGO_CFG_TARGET_FEATURE").split(',').any(|f| f == "crt-static");
+    let mut features: Vec<String> = std::env::vars()
+        .filter_map(|(name, _)| {
+            name.strip_prefix("CARGO_FEATURE_").map(|f| f.to_lowercase().replace('_', "-"))
+        })
+        .collect();
+    features.sort();
+
+    println!("cargo:rustc-env=PARFLOW_BUILD_TARGET={}", var("TARGET"));
+    println!("cargo:rustc-env=PARFLOW_BUILD_PROFILE={}", var("PROFILE"));
+    println!("cargo:rustc-env=PARFLOW_BUILD_STATIC={}", static_crt);
+    println!("cargo:rustc-env=PARFLOW_BUILD_FEATURES={}", features.join(","));
+    println!("cargo:rerun-if-changed=build.rs");
+}
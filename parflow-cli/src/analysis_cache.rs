@@ -0,0 +1,271 @@
+//! `.parflow/analysis.json`: what `parflow analyze` found in each source file, keyed by the
+//! file's SHA-256, so unchanged files are not analyzed again and `parflow analyze --cached` can
+//! answer without reading the project at all. `parflow watch` drops the entries of files as they
+//! change, see [`invalidate`].
+
+use anyhow::{Context, Result};
+use colored::*;
+use semantic_compiler::{detect_patterns, CrossLanguageAnalyzer, PatternMatch};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+/// Where the cache lives, relative to the analyzed directory
+pub const CACHE_FILE: &str = ".parflow/analysis.json";
+
+/// Bumped when entries change shape, which discards caches written before
+const VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Suggestion {
+    pub line: usize,
+    pub function: Option<String>,
+    pub target: String,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileAnalysis {
+    /// Hex SHA-256 of the content the entry describes
+    pub sha256: String,
+    pub language: String,
+    pub patterns: Vec<PatternMatch>,
+    pub suggestions: Vec<Suggestion>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AnalysisCache {
+    version: u32,
+    /// By path relative to the analyzed directory, with `/` separators
+    pub files: BTreeMap<String, FileAnalysis>,
+}
+
+/// How [`refresh`] or [`cached`] got to the cache they return
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Refresh {
+    pub analyzed: usize,
+    pub reused: usize,
+    pub removed: usize,
+}
+
+impl AnalysisCache {
+    /// The cache under `root`; `None` when there is none or it is from another version
+    pub fn load(root: &Path) -> Option<Self> {
+        let json = std::fs::read_to_string(root.join(CACHE_FILE)).ok()?;
+        serde_json::from_str(&json).ok().filter(|cache: &Self| cache.version == VERSION)
+    }
+
+    pub fn save(&self, root: &Path) -> Result<()> {
+        let file = root.join(CACHE_FILE);
+        if let Some(dir) = file.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(&file, json).with_context(|| format!("writing {}", file.display()))
+    }
+
+    /// Languages of the cached files
+    pub fn languages(&self) -> BTreeSet<&str> {
+        self.files.values().map(|file| file.language.as_str()).collect()
+    }
+}
+
+/// Source files under `dir`, skipping hidden directories, `target` and `node_modules`
+fn source_files(dir: &Path, out: &mut Vec<PathBuf>) {
+    for path in std::fs::read_dir(dir).into_iter().flatten().flatten().map(|entry| entry.path()) {
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        if path.is_dir() {
+            if !name.starts_with('.') && name != "target" && name != "node_modules" {
+                source_files(&path, out);
+            }
+        } else if parflow_core::languages::for_path(&name).is_some() {
+            out.push(path);
+        }
+    }
+}
+
+/// Cache key of `path`, a file under `root`
+fn key(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    relative.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/")
+}
+
+/// Analyze one file's content
+pub fn analyze(path: &str, code: &str) -> Option<FileAnalysis> {
+    let language = parflow_core::languages::for_path(path)?.name();
+    let patterns = detect_patterns(code, language);
+    let analyzer = CrossLanguageAnalyzer;
+    let suggestions = patterns
+        .iter()
+        .filter_map(|found| {
+            let target = analyzer.get_optimal_language(&found.pattern)?;
+            (target != language).then(|| Suggestion {
+                line: found.line,
+                function: found.function.clone(),
+                description: format!(
+                    "{:?} pattern ({}) runs faster in {}",
+                    found.pattern, found.evidence, target
+                ),
+                target,
+            })
+        })
+        .collect();
+    Some(FileAnalysis {
+        sha256: hex::encode(Sha256::digest(code.as_bytes())),
+        language: language.to_string(),
+        patterns,
+        suggestions,
+    })
+}
+
+/// Bring `cache` up to date with the files under `root`. With `rehash`, every file is read and
+/// entries whose hash changed are redone; without, entries are trusted and only files missing
+/// from the cache are analyzed.
+fn update(root: &Path, cache: &mut AnalysisCache, rehash: bool) -> Refresh {
+    let mut files = Vec::new();
+    source_files(root, &mut files);
+    let mut refresh = Refresh::default();
+    let mut present = BTreeSet::new();
+    for file in files {
+        let key = key(root, &file);
+        present.insert(key.clone());
+        if !rehash && cache.files.contains_key(&key) {
+            refresh.reused += 1;
+            continue;
+        }
+        let Ok(code) = std::fs::read_to_string(&file) else { continue };
+        let sha256 = hex::encode(Sha256::digest(code.as_bytes()));
+        if cache.files.get(&key).is_some_and(|entry| entry.sha256 == sha256) {
+            refresh.reused += 1;
+            continue;
+        }
+        if let Some(analysis) = analyze(&key, &code) {
+            cache.files.insert(key, analysis);
+            refresh.analyzed += 1;
+        }
+    }
+    let before = cache.files.len();
+    cache.files.retain(|key, _| present.contains(key));
+    refresh.removed = before - cache.files.len();
+    refresh
+}
+
+/// Analyze the files under `root` whose content changed since the last run and save the cache
+pub fn refresh(root: &Path) -> Result<(AnalysisCache, Refresh)> {
+    let mut cache = AnalysisCache::load(root).unwrap_or_default();
+    cache.version = VERSION;
+    let refresh = update(root, &mut cache, true);
+    cache.save(root)?;
+    Ok((cache, refresh))
+}
+
+/// The cache as it stands, analyzing only files it has no entry for: new ones and those
+/// [`invalidate`] dropped. Files edited while nothing watched them keep their old entries until
+/// the next plain `parflow analyze`.
+pub fn cached(root: &Path) -> Result<(AnalysisCache, Refresh)> {
+    let Some(mut cache) = AnalysisCache::load(root) else {
+        return refresh(root);
+    };
+    let refresh = update(root, &mut cache, false);
+    if refresh.analyzed > 0 || refresh.removed > 0 {
+        cache.save(root)?;
+    }
+    Ok((cache, refresh))
+}
+
+/// Drop the entries of `paths`, files under `root`, from its cache; how many there were
+pub fn invalidate(root: &Path, paths: &[PathBuf]) -> Result<usize> {
+    let Some(mut cache) = AnalysisCache::load(root) else { return Ok(0) };
+    let before = cache.files.len();
+    for path in paths {
+        let key = key(root, path);
+        // A directory going away takes its files with it
+        let prefix = format!("{}/", key);
+        cache.files.retain(|file, _| *file != key && !file.starts_with(&prefix));
+    }
+    let dropped = before - cache.files.len();
+    if dropped > 0 {
+        cache.save(root)?;
+    }
+    Ok(dropped)
+}
+
+pub fn print(cache: &AnalysisCache, refresh: Refresh) {
+    println!("\n{}", "🗂️  CACHED ANALYSIS".bright_green().bold());
+    println!(
+        "{}: {} ({} analyzed, {} reused)",
+        "Files".bright_cyan(),
+        cache.files.len(),
+        refresh.analyzed,
+        refresh.reused
+    );
+    let languages: Vec<&str> = cache.languages().into_iter().collect();
+    println!("{}: {}", "Languages Detected".bright_cyan(), languages.join(", "));
+
+    let patterns: usize = cache.files.values().map(|file| file.patterns.len()).sum();
+    println!("\n{} ({})", "🧩 PATTERNS".bright_magenta().bold(), patterns);
+    for (path, file) in &cache.files {
+        for found in &file.patterns {
+            println!(
+                "  {} {:?} ({})",
+                format!("{}:{}", path, found.line).bright_cyan(),
+                found.pattern,
+                found.evidence
+            );
+        }
+    }
+
+    println!("\n{}", "💡 OPTIMIZATION SUGGESTIONS".bright_yellow().bold());
+    let mut any = false;
+    for (path, file) in &cache.files {
+        for suggestion in &file.suggestions {
+            any = true;
+            println!(
+                "  {} {}",
+                format!("{}:{}", path, suggestion.line).bright_cyan(),
+                suggestion.description.bright_white()
+            );
+        }
+    }
+    if !any {
+        println!("  {}", "No suggestions".bright_white());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_reuses_unchanged_files_and_drops_invalidated_ones() {
+        let root = std::env::temp_dir().join(format!("parflow-analysis-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        let fib = "def fib(n):\n    return n if n < 2 else fib(n - 1) + fib(n - 2)\n";
+        std::fs::write(root.join("src/fib.py"), fib).unwrap();
+        std::fs::write(root.join("util.js"), "function id(x) {\n  return x;\n}\n").unwrap();
+
+        let (cache, refresh) = super::refresh(&root).unwrap();
+        assert_eq!(refresh, Refresh { analyzed: 2, reused: 0, removed: 0 });
+        assert_eq!(cache.languages().into_iter().collect::<Vec<_>>(), ["javascript", "python"]);
+        let entry = &cache.files["src/fib.py"];
+        assert!(!entry.patterns.is_empty());
+        assert_eq!(entry.suggestions[0].target, "rust");
+
+        // Edits nobody watched are only seen by a full refresh
+        std::fs::write(root.join("util.js"), "function id(y) {\n  return y;\n}\n").unwrap();
+        let (_, refresh) = cached(&root).unwrap();
+        assert_eq!(refresh, Refresh { analyzed: 0, reused: 2, removed: 0 });
+        assert_eq!(invalidate(&root, &[root.join("util.js")]).unwrap(), 1);
+        let (cache, refresh) = cached(&root).unwrap();
+        assert_eq!(refresh, Refresh { analyzed: 1, reused: 1, removed: 0 });
+        assert_eq!(cache.files["src/fib.py"], *entry);
+
+        std::fs::remove_file(root.join("util.js")).unwrap();
+        assert_eq!(invalidate(&root, &[root.join("src")]).unwrap(), 1);
+        let (cache, refresh) = super::refresh(&root).unwrap();
+        assert_eq!(refresh, Refresh { analyzed: 1, reused: 0, removed: 1 });
+        assert_eq!(cache.files.keys().collect::<Vec<_>>(), ["src/fib.py"]);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,176 @@
+use clap::Subcommand;
+use colored::*;
+use parflow_artifacts::gc;
+use parflow_core::config::LiveConfig;
+use parflow_live_server::audit::{self, AuditCategory, AuditLog, ExportFilter};
+use parflow_live_server::{LiveServer, SandboxPolicy};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Subcommand)]
+pub enum AuditAction {
+    /// List sessions with audit records and any holds
+    Sessions,
+    /// Write audit records as JSONL (schema documented in parflow_live_server::audit)
+    Export {
+        /// Only these sessions (repeatable)
+        #[arg(short, long)]
+        session: Vec<String>,
+
+        /// Only these categories: session, access, terminal, edit, chat (repeatable)
+        #[arg(short, long)]
+        category: Vec<String>,
+
+        /// Only records newer than this age, e.g. 30d or 12h
+        #[arg(long)]
+        since: Option<String>,
+
+        /// File to write instead of stdout
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    /// Remove records older than the retention period from sessions that are not held
+    Purge {
+        /// Maximum age to keep, e.g. 90d [default: live.audit_retention_days]
+        #[arg(long)]
+        older_than: Option<String>,
+    },
+    /// Exempt a session from purging
+    Hold {
+        session: String,
+
+        /// Why the session is kept, e.g. a case or ticket number
+        #[arg(short, long, default_value = "manual")]
+        reason: String,
+    },
+    /// Remove a hold so the session is purged normally again
+    Release { session: String },
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+/// Stops the retention task when the live server it belongs to goes away.
+pub struct RetentionTask(tokio::task::JoinHandle<()>);
+
+impl Drop for RetentionTask {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// A live server whose sessions start under the `[live.sandbox]` policy. It audits to the
+/// default directory when `[live] audit` is on, with the scheduled purge for
+/// `[live] audit_retention_days`.
+pub fn live_server(config: &LiveConfig) -> anyhow::Result<(LiveServer, Option<RetentionTask>)> {
+    let sandbox = SandboxPolicy {
+        allowed_commands: config.sandbox.allowed_commands.clone().map(|c| c.into_iter().collect()),
+        isolation: config.sandbox.isolation.parse()?,
+        root: config.sandbox.root.clone(),
+        network: config.sandbox.network,
+        shell: config.sandbox.shell.clone(),
+    };
+    let server = LiveServer::new().with_sandbox(sandbox);
+    if !config.audit {
+        return Ok((server, None));
+    }
+    let log = match AuditLog::open(Path::new(audit::DEFAULT_DIR)) {
+        Ok(log) => Arc::new(log),
+        Err(e) => {
+            println!("{} {}", "⚠️  Audit log unavailable:".bright_yellow(), e);
+            return Ok((server, None));
+        }
+    };
+    let retention = config.audit_retention_days.map(|days| {
+        RetentionTask(audit::spawn_retention(
+            log.clone(),
+            Duration::from_secs(days * 86_400),
+            Duration::from_secs(3600),
+        ))
+    });
+    Ok((server.with_audit(log), retention))
+}
+
+pub fn run(config: &LiveConfig, action: AuditAction) -> anyhow::Result<()> {
+    let log = AuditLog::open(Path::new(audit::DEFAULT_DIR))?;
+
+    match action {
+        AuditAction::Sessions => {
+            let holds = log.holds()?;
+            println!("{}", "🗂️  Audited Live Sessions".bright_blue().bold());
+            let sessions = log.sessions()?;
+            if sessions.is_empty() {
+                println!("{}", "No audit records yet".bright_yellow());
+            }
+            for session in sessions {
+                let records = log.read(&session)?;
+                let hold = holds.iter().find(|h| h.session_id == session);
+                println!(
+                    "  • {} {} records{}",
+                    session.bright_white(),
+                    records.len(),
+                    hold.map(|h| format!(" {} ({})", "🔒 held".bright_yellow(), h.reason))
+                        .unwrap_or_default()
+                );
+            }
+            match config.audit_retention_days {
+                Some(days) => println!("{} {} days", "Retention:".bright_cyan(), days),
+                None => println!("{} keep until purged", "Retention:".bright_cyan()),
+            }
+        }
+        AuditAction::Export { session, category, since, output } => {
+            let filter = ExportFilter {
+                sessions: session,
+                categories: category
+                    .iter()
+                    .map(|c| c.parse::<AuditCategory>())
+                    .collect::<Result<_, _>>()
+                    .map_err(anyhow::Error::msg)?,
+                since: since
+                    .map(|age| gc::parse_age(&age).map(|secs| now().saturating_sub(secs)))
+                    .transpose()?,
+            };
+            match output {
+                Some(path) => {
+                    let mut file = std::io::BufWriter::new(std::fs::File::create(&path)?);
+                    let written = log.export(&filter, &mut file)?;
+                    println!("{} {} records to {}", "📤 Exported".bright_green(), written, path);
+                }
+                None => {
+                    log.export(&filter, &mut std::io::stdout().lock())?;
+                }
+            }
+        }
+        AuditAction::Purge { older_than } => {
+            let retention = match (older_than, config.audit_retention_days) {
+                (Some(age), _) => gc::parse_age(&age)?,
+                (None, Some(days)) => days * 86_400,
+                (None, None) => anyhow::bail!(
+                    "no retention period; pass --older-than or set live.audit_retention_days"
+                ),
+            };
+            let report = log.purge(Duration::from_secs(retention), now())?;
+            println!("{}", "🧹 AUDIT PURGE COMPLETE".bright_green().bold());
+            println!("{}: {}", "Records removed".bright_cyan(), report.records_removed);
+            println!("{}: {}", "Sessions removed".bright_cyan(), report.sessions_removed.len());
+            for session in &report.held {
+                println!("  {} {}", "🔒 Kept (held):".bright_yellow(), session);
+            }
+        }
+        AuditAction::Hold { session, reason } => {
+            log.hold(&session, &reason)?;
+            println!("{} {} ({})", "🔒 Held".bright_green(), session.bright_white(), reason);
+        }
+        AuditAction::Release { session } => {
+            if log.release(&session)? {
+                println!("{} {}", "✅ Released".bright_green(), session.bright_white());
+            } else {
+                println!("{} {}", "⚠️  No hold on".bright_yellow(), session);
+            }
+        }
+    }
+
+    Ok(())
+}
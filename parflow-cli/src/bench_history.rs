@@ -0,0 +1,22 @@
+use parflow_findings::FindingsDb;
+use serde::{Deserialize, Serialize};
+
+pub const BENCH_TABLE: &str = "bench";
+
+/// One `parflow benchmark` run, kept in the findings database for the dashboard.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BenchRun {
+    pub timestamp: i64,
+    pub suite: String,
+    pub results: parflow_bench::CrossLanguageBenchmark,
+}
+
+pub fn record_benchmark(suite: &str, results: parflow_bench::CrossLanguageBenchmark) {
+    let run =
+        BenchRun { timestamp: chrono::Utc::now().timestamp(), suite: suite.to_string(), results };
+    let recorded =
+        FindingsDb::open(FindingsDb::DEFAULT_DIR).and_then(|db| db.record(BENCH_TABLE, &run));
+    if let Err(e) = recorded {
+        eprintln!("⚠️  Benchmark history not saved: {}", e);
+    }
+}
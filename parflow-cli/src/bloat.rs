@@ -0,0 +1,53 @@
+use colored::*;
+use parflow_crate_orchestrator::bloat::BloatReport;
+
+/// How many crates and functions the text report lists
+const LISTED: usize = 15;
+
+fn kib(bytes: u64) -> String {
+    format!("{:.1} KiB", bytes as f64 / 1024.0)
+}
+
+pub fn print_report(report: &BloatReport) {
+    println!("\n{}", "📏 BINARY SIZE".bright_blue().bold());
+    println!("{}: {}", "Binary".bright_cyan(), report.binary.display());
+    println!(
+        "{}: {} ({} of code)",
+        "Size".bright_cyan(),
+        kib(report.file_size),
+        kib(report.text_size)
+    );
+
+    println!("\n{}", "📦 BY CRATE".bright_yellow().bold());
+    for krate in report.crates.iter().take(LISTED) {
+        let origin = if krate.pulled_in_by.is_empty() {
+            String::new()
+        } else {
+            format!("via {}", krate.pulled_in_by.join(", "))
+        };
+        println!(
+            "  {:>6.1}% {:>12}  {} {}",
+            krate.share * 100.0,
+            kib(krate.size),
+            krate.name.bright_white(),
+            origin.dimmed()
+        );
+    }
+
+    println!("\n{}", "🔧 LARGEST FUNCTIONS".bright_yellow().bold());
+    for function in report.functions.iter().take(LISTED) {
+        println!(
+            "  {:>12}  {} {}",
+            kib(function.size),
+            function.name,
+            format!("({})", function.krate).dimmed()
+        );
+    }
+
+    if !report.suggestions.is_empty() {
+        println!("\n{}", "💡 OPTIMIZATION SUGGESTIONS".bright_green().bold());
+        for suggestion in &report.suggestions {
+            println!("  • {} {}", suggestion.reason, format!("— {}", suggestion.impact).dimmed());
+        }
+    }
+}
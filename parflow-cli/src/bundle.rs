@@ -0,0 +1,322 @@
+//! Project state bundles: a signed `.tar.gz` holding everything under `.parflow/` (config,
+//! analysis history, baselines, migration state, workflow definitions) plus the project's
+//! `parflow.toml`, so a project can be moved between machines or handed to a maintainer.
+//! Symbolic links are left out, so a bundle never carries files from outside the project.
+//!
+//! The manifest lists every file with its SHA-256 and is signed with HMAC-SHA256 using a
+//! shared key from `PARFLOW_BUNDLE_KEY` or `--key-file`. Import refuses bundles whose
+//! signature or file hashes do not match.
+
+use anyhow::{bail, Context, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+pub const BUNDLE_KEY_ENV: &str = "PARFLOW_BUNDLE_KEY";
+const FORMAT_VERSION: u32 = 1;
+const MANIFEST: &str = "manifest.json";
+const SIGNATURE: &str = "manifest.sig";
+const STATE_DIR: &str = ".parflow";
+/// Large, reproducible caches that are left out unless explicitly requested
+const BULKY_DIRS: [&str; 2] = ["artifacts", "cache"];
+const ROOT_FILES: [&str; 1] = ["parflow.toml"];
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub format_version: u32,
+    pub project: String,
+    pub created_at: String,
+    pub parflow_version: String,
+    pub files: Vec<BundleEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundleEntry {
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+pub fn load_key(key_file: Option<&str>) -> Result<Vec<u8>> {
+    let key = match key_file {
+        Some(path) => std::fs::read(path).with_context(|| format!("reading key file {}", path))?,
+        None => std::env::var(BUNDLE_KEY_ENV)
+            .with_context(|| format!("no signing key: set {} or pass --key-file", BUNDLE_KEY_ENV))?
+            .into_bytes(),
+    };
+    let key: Vec<u8> = key.trim_ascii().to_vec();
+    if key.len() < 16 {
+        bail!("signing key must be at least 16 bytes");
+    }
+    Ok(key)
+}
+
+fn sign(key: &[u8], manifest: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(manifest);
+    mac
+}
+
+fn collect_files(
+    dir: &Path,
+    base: &Path,
+    include_bulky: bool,
+    out: &mut Vec<PathBuf>,
+) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        // The entry's own type: links are neither followed nor bundled
+        let file_type = entry.file_type()?;
+        let path = entry.path();
+        let relative = path.strip_prefix(base)?;
+        if file_type.is_dir() {
+            let top_level = relative.components().nth(1).map(|c| c.as_os_str().to_owned());
+            let bulky = relative.components().count() == 2
+                && top_level.is_some_and(|name| BULKY_DIRS.iter().any(|b| name == *b));
+            if include_bulky || !bulky {
+                collect_files(&path, base, include_bulky, out)?;
+            }
+        } else if file_type.is_file() {
+            out.push(relative.to_path_buf());
+        }
+    }
+    Ok(())
+}
+
+fn bundle_path(path: &Path) -> String {
+    path.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/")
+}
+
+pub fn export_bundle(
+    project_dir: &Path,
+    output: &Path,
+    key: &[u8],
+    include_bulky: bool,
+) -> Result<BundleManifest> {
+    let mut files = Vec::new();
+    let state_dir = project_dir.join(STATE_DIR);
+    if state_dir.is_dir() {
+        collect_files(&state_dir, project_dir, include_bulky, &mut files)?;
+    }
+    for name in ROOT_FILES {
+        let metadata = std::fs::symlink_metadata(project_dir.join(name));
+        if metadata.is_ok_and(|metadata| metadata.is_file()) {
+            files.push(PathBuf::from(name));
+        }
+    }
+    if files.is_empty() {
+        bail!("no ParFlow project state found in {}", project_dir.display());
+    }
+    files.sort();
+
+    let mut contents = Vec::with_capacity(files.len());
+    let mut entries = Vec::with_capacity(files.len());
+    for file in &files {
+        let data = std::fs::read(project_dir.join(file))?;
+        entries.push(BundleEntry {
+            path: bundle_path(file),
+            size: data.len() as u64,
+            sha256: hex::encode(Sha256::digest(&data)),
+        });
+        contents.push(data);
+    }
+
+    let project = project_dir
+        .canonicalize()?
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let manifest = BundleManifest {
+        format_version: FORMAT_VERSION,
+        project,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        parflow_version: env!("CARGO_PKG_VERSION").to_string(),
+        files: entries,
+    };
+    let manifest_bytes = serde_json::to_vec_pretty(&manifest)?;
+    let signature = hex::encode(sign(key, &manifest_bytes).finalize().into_bytes());
+
+    let mut archive =
+        tar::Builder::new(GzEncoder::new(std::fs::File::create(output)?, Compression::default()));
+    let mut append = |name: &str, data: &[u8]| -> Result<()> {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        archive.append_data(&mut header, name, data)?;
+        Ok(())
+    };
+    append(MANIFEST, &manifest_bytes)?;
+    append(SIGNATURE, signature.as_bytes())?;
+    for (entry, data) in manifest.files.iter().zip(&contents) {
+        append(&format!("state/{}", entry.path), data)?;
+    }
+    archive.into_inner()?.finish()?;
+
+    Ok(manifest)
+}
+
+/// Reject absolute paths and `..` so a bundle can only write inside the project.
+fn safe_relative(path: &str) -> Result<PathBuf> {
+    let path = PathBuf::from(path);
+    let normal = path.components().all(|c| matches!(c, Component::Normal(_)));
+    if path.as_os_str().is_empty() || !normal {
+        bail!("bundle contains unsafe path: {}", path.display());
+    }
+    Ok(path)
+}
+
+pub fn import_bundle(
+    bundle: &Path,
+    project_dir: &Path,
+    key: &[u8],
+    force: bool,
+) -> Result<BundleManifest> {
+    let file = std::fs::File::open(bundle)
+        .with_context(|| format!("opening bundle {}", bundle.display()))?;
+    let mut archive = tar::Archive::new(GzDecoder::new(file));
+    let mut members = BTreeMap::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().into_owned();
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data)?;
+        members.insert(name, data);
+    }
+
+    let manifest_bytes = members.remove(MANIFEST).context("bundle has no manifest")?;
+    let signature = members.remove(SIGNATURE).context("bundle is not signed")?;
+    let signature = hex::decode(signature.trim_ascii()).context("malformed bundle signature")?;
+    sign(key, &manifest_bytes)
+        .verify_slice(&signature)
+        .map_err(|_| anyhow::anyhow!("bundle signature does not match the signing key"))?;
+
+    let manifest: BundleManifest = serde_json::from_slice(&manifest_bytes)?;
+    if manifest.format_version != FORMAT_VERSION {
+        bail!("unsupported bundle format version {}", manifest.format_version);
+    }
+
+    let mut verified = Vec::with_capacity(manifest.files.len());
+    for entry in &manifest.files {
+        let data = members
+            .remove(&format!("state/{}", entry.path))
+            .with_context(|| format!("bundle is missing {}", entry.path))?;
+        if hex::encode(Sha256::digest(&data)) != entry.sha256 {
+            bail!("hash mismatch for {}", entry.path);
+        }
+        let target = project_dir.join(safe_relative(&entry.path)?);
+        if target.exists() && !force {
+            bail!("{} already exists, pass --force to overwrite", target.display());
+        }
+        verified.push((target, data));
+    }
+    if let Some(extra) = members.keys().next() {
+        bail!("bundle contains unlisted file {}", extra);
+    }
+
+    for (target, data) in verified {
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(target, data)?;
+    }
+
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &[u8] = b"0123456789abcdef";
+
+    fn project(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("parflow-bundle-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join(".parflow/workflows")).unwrap();
+        std::fs::write(dir.join("parflow.toml"), "[server]\nrest_port = 3000\n").unwrap();
+        std::fs::write(dir.join(".parflow/workflows/build.yaml"), "name: build\n").unwrap();
+        dir
+    }
+
+    /// Rewrite the bundle at `path`, passing each member through `edit`
+    fn rewrite(path: &Path, edit: impl Fn(&str, Vec<u8>) -> Vec<u8>) {
+        let mut members = Vec::new();
+        let mut archive = tar::Archive::new(GzDecoder::new(std::fs::File::open(path).unwrap()));
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let name = entry.path().unwrap().to_string_lossy().into_owned();
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data).unwrap();
+            members.push((name.clone(), edit(&name, data)));
+        }
+        let file = std::fs::File::create(path).unwrap();
+        let mut archive = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+        for (name, data) in members {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            archive.append_data(&mut header, name, &data[..]).unwrap();
+        }
+        archive.into_inner().unwrap().finish().unwrap();
+    }
+
+    #[test]
+    fn test_signed_bundles_round_trip_and_tampering_is_refused() {
+        let source = project("source");
+        let target = source.with_extension("target");
+        let bundle = source.with_extension("tar.gz");
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("/etc", source.join(".parflow/outside")).unwrap();
+
+        let manifest = export_bundle(&source, &bundle, KEY, false).unwrap();
+        let paths: Vec<&str> = manifest.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, [".parflow/workflows/build.yaml", "parflow.toml"]);
+
+        let wrong_key = import_bundle(&bundle, &target, b"fedcba9876543210", false).unwrap_err();
+        assert!(wrong_key.to_string().contains("signature does not match"));
+        assert!(!target.exists());
+
+        import_bundle(&bundle, &target, KEY, false).unwrap();
+        let imported = std::fs::read_to_string(target.join(".parflow/workflows/build.yaml"));
+        assert_eq!(imported.unwrap(), "name: build\n");
+        let again = import_bundle(&bundle, &target, KEY, false).unwrap_err();
+        assert!(again.to_string().contains("pass --force"));
+
+        // The manifest still verifies, but a file no longer matches its hash
+        rewrite(&bundle, |name, data| {
+            if name == "state/parflow.toml" {
+                b"[server]\nrest_port = 1\n".to_vec()
+            } else {
+                data
+            }
+        });
+        let tampered = import_bundle(&bundle, &target, KEY, true).unwrap_err();
+        assert_eq!(tampered.to_string(), "hash mismatch for parflow.toml");
+
+        for dir in [&source, &target] {
+            std::fs::remove_dir_all(dir).unwrap();
+        }
+        std::fs::remove_file(&bundle).unwrap();
+    }
+
+    #[test]
+    fn test_bundle_paths_cannot_leave_the_project() {
+        assert_eq!(
+            safe_relative(".parflow/config.toml").unwrap(),
+            Path::new(".parflow/config.toml")
+        );
+        for path in ["../outside", ".parflow/../../outside", "/etc/passwd", "./parflow.toml", ""] {
+            let error = safe_relative(path).map(|_| ()).unwrap_err().to_string();
+            assert!(error.contains("unsafe path"), "{:?}: {}", path, error);
+        }
+    }
+}
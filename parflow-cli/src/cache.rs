@@ -0,0 +1,217 @@
+use anyhow::{anyhow, bail};
+use clap::Subcommand;
+use colored::*;
+use parflow_artifacts::gc::{self, GcPolicy, PinSet};
+use parflow_artifacts::{open_store, ArtifactStore, GcsConfig, S3Config, StorageConfig};
+use parflow_core::config::GcConfig;
+use std::time::Duration;
+
+#[derive(Subcommand)]
+pub enum CacheAction {
+    /// Show size, age and pinning statistics for an artifact store
+    Stats,
+    /// Evict expired and least recently used artifacts
+    Gc {
+        /// Maximum total size to keep, e.g. 512M or 10G
+        #[arg(long, default_value = "10G")]
+        max_size: String,
+
+        /// Maximum age since last use, e.g. 12h or 30d
+        #[arg(long, default_value = "30d")]
+        max_age: String,
+
+        /// Report what would be removed without deleting anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Protect an artifact (or every key under a `prefix/`) from GC
+    Pin {
+        /// Artifact key or prefix ending in `/`
+        pattern: String,
+
+        /// Why the artifact is kept, e.g. "release v1.4.0"
+        #[arg(short, long, default_value = "manual")]
+        reason: String,
+    },
+    /// Remove a pin
+    Unpin {
+        /// Artifact key or prefix ending in `/`
+        pattern: String,
+    },
+}
+
+/// The store `url` names, else the one the `[storage]` config describes
+pub fn store_config(
+    config: &parflow_core::config::StorageConfig,
+    url: Option<&str>,
+) -> anyhow::Result<StorageConfig> {
+    if let Some(url) = url {
+        return url.parse();
+    }
+    let bucket = || {
+        config
+            .bucket
+            .clone()
+            .ok_or_else(|| anyhow!("storage.bucket is required for the {} backend", config.backend))
+    };
+    Ok(match config.backend.as_str() {
+        "local" => StorageConfig::Local { root: config.root.to_string_lossy().into_owned() },
+        "s3" => {
+            let mut s3 = S3Config::from_env(&bucket()?, &config.prefix);
+            if let Some(region) = &config.region {
+                s3.region = region.clone();
+            }
+            if let Some(endpoint) = &config.endpoint {
+                s3.endpoint = Some(endpoint.clone());
+            }
+            StorageConfig::S3(s3)
+        }
+        "gcs" => {
+            let mut gcs = GcsConfig::from_env(&bucket()?, &config.prefix);
+            if let Some(endpoint) = &config.endpoint {
+                gcs.endpoint = endpoint.clone();
+            }
+            StorageConfig::Gcs(gcs)
+        }
+        other => bail!("unknown storage backend '{}': expected local, s3 or gcs", other),
+    })
+}
+
+/// The policy and interval of the GC `parflow start` schedules, from the `[storage.gc]` config
+pub fn scheduled_policy(config: &GcConfig) -> anyhow::Result<(GcPolicy, Duration)> {
+    let policy = GcPolicy {
+        max_total_bytes: config.max_size.as_deref().map(gc::parse_size).transpose()?,
+        max_age_secs: config.max_age.as_deref().map(gc::parse_age).transpose()?,
+    };
+    let interval = gc::parse_age(&config.interval)?.max(1);
+    Ok((policy, Duration::from_secs(interval)))
+}
+
+fn megabytes(bytes: u64) -> String {
+    format!("{:.2}MB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+pub async fn run(store: &StorageConfig, action: CacheAction) -> anyhow::Result<()> {
+    let store = open_store(store)?;
+    let store: &dyn ArtifactStore = store.as_ref();
+
+    match action {
+        CacheAction::Stats => {
+            let stats = gc::stats(store).await?;
+            println!("{}", "📦 Artifact Cache Statistics".bright_blue().bold());
+            println!("{}", "────────────────────────────".bright_blue());
+            println!("{}: {}", "Backend".bright_cyan(), store.backend_name());
+            println!("{}: {}", "Artifacts".bright_cyan(), stats.artifacts);
+            println!("{}: {}", "Total size".bright_cyan(), megabytes(stats.total_bytes));
+            println!(
+                "{}: {} ({})",
+                "Pinned".bright_cyan(),
+                stats.pinned,
+                megabytes(stats.pinned_bytes)
+            );
+            if let (Some(oldest), Some(newest)) = (stats.oldest, stats.newest) {
+                let age_days = |t: i64| (chrono::Utc::now().timestamp() - t) / 86_400;
+                println!(
+                    "{}: oldest {}d, newest {}d",
+                    "Age".bright_cyan(),
+                    age_days(oldest),
+                    age_days(newest)
+                );
+            }
+
+            if !stats.by_namespace.is_empty() {
+                println!("\n{}", "📂 BY NAMESPACE".bright_yellow().bold());
+                for (namespace, (count, bytes)) in &stats.by_namespace {
+                    println!("  • {}: {} artifacts, {}", namespace, count, megabytes(*bytes));
+                }
+            }
+
+            let pins = PinSet::load(store).await?;
+            if !pins.pins().is_empty() {
+                println!("\n{}", "📌 PINS".bright_yellow().bold());
+                for pin in pins.pins() {
+                    println!("  • {} ({})", pin.pattern.bright_white(), pin.reason);
+                }
+            }
+        }
+        CacheAction::Gc { max_size, max_age, dry_run } => {
+            let policy = GcPolicy {
+                max_total_bytes: Some(gc::parse_size(&max_size)?),
+                max_age_secs: Some(gc::parse_age(&max_age)?),
+            };
+            let report = gc::collect_garbage(store, &policy, dry_run).await?;
+
+            let title = if dry_run { "🔍 GC DRY RUN" } else { "🧹 GC COMPLETE" };
+            println!("{}", title.bright_green().bold());
+            println!("{}: {}", "Expired".bright_cyan(), report.expired.len());
+            println!("{}: {}", "Evicted (LRU)".bright_cyan(), report.evicted.len());
+            println!("{}: {}", "Freed".bright_cyan(), megabytes(report.freed_bytes));
+            println!("{}: {}", "Remaining".bright_cyan(), megabytes(report.remaining_bytes));
+            println!("{}: {}", "Kept (pinned)".bright_cyan(), report.kept_pinned);
+            if dry_run {
+                for key in report.expired.iter().chain(report.evicted.iter()) {
+                    println!("  • {}", key);
+                }
+            }
+        }
+        CacheAction::Pin { pattern, reason } => {
+            let mut pins = PinSet::load(store).await?;
+            pins.pin(&pattern, &reason);
+            pins.save(store).await?;
+            println!("{} {} ({})", "📌 Pinned".bright_green(), pattern.bright_white(), reason);
+        }
+        CacheAction::Unpin { pattern } => {
+            let mut pins = PinSet::load(store).await?;
+            if pins.unpin(&pattern) {
+                pins.save(store).await?;
+                println!("{} {}", "✅ Unpinned".bright_green(), pattern.bright_white());
+            } else {
+                println!("{} {}", "⚠️  No pin matching".bright_yellow(), pattern);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scheduled_policy_comes_from_the_config() {
+        let (policy, interval) = scheduled_policy(&GcConfig::default()).unwrap();
+        assert_eq!(policy.max_total_bytes, GcPolicy::default().max_total_bytes);
+        assert_eq!(policy.max_age_secs, GcPolicy::default().max_age_secs);
+        assert_eq!(interval, Duration::from_secs(3600));
+
+        let config =
+            GcConfig { max_size: None, max_age: Some("12h".to_string()), ..GcConfig::default() };
+        let (policy, _) = scheduled_policy(&config).unwrap();
+        assert_eq!(policy.max_total_bytes, None);
+        assert_eq!(policy.max_age_secs, Some(12 * 3600));
+        let config = GcConfig { interval: "soon".to_string(), ..GcConfig::default() };
+        assert!(scheduled_policy(&config).is_err());
+    }
+
+    #[test]
+    fn test_store_comes_from_the_url_or_the_config() {
+        let mut config = parflow_core::config::StorageConfig::default();
+        let store = store_config(&config, None).unwrap();
+        assert!(matches!(store, StorageConfig::Local { ref root } if root == ".parflow/artifacts"));
+        let store = store_config(&config, Some("gs://shared/cache")).unwrap();
+        assert_eq!(store.to_string(), "gs://shared/cache");
+
+        config.backend = "s3".to_string();
+        assert!(store_config(&config, None).unwrap_err().to_string().contains("storage.bucket"));
+        config.bucket = Some("ci".to_string());
+        config.region = Some("eu-west-1".to_string());
+        let StorageConfig::S3(s3) = store_config(&config, None).unwrap() else {
+            panic!("expected an S3 store");
+        };
+        assert_eq!((s3.bucket.as_str(), s3.region.as_str()), ("ci", "eu-west-1"));
+
+        config.backend = "ftp".to_string();
+        assert!(store_config(&config, None).is_err());
+    }
+}
@@ -0,0 +1,85 @@
+use colored::*;
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    pub target: &'static str,
+    pub profile: &'static str,
+    /// Whether the C runtime is linked in, i.e. the binary has no shared library dependencies
+    pub static_binary: bool,
+    pub features: Vec<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Capability {
+    pub name: &'static str,
+    pub available: bool,
+    pub detail: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CapabilityReport {
+    pub build: BuildInfo,
+    pub capabilities: Vec<Capability>,
+}
+
+fn capability(name: &'static str, available: bool, detail: &'static str) -> Capability {
+    Capability { name, available, detail }
+}
+
+/// What this binary was built with. Everything here is fixed at compile time.
+pub fn report() -> CapabilityReport {
+    let build = BuildInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        target: env!("PARFLOW_BUILD_TARGET"),
+        profile: env!("PARFLOW_BUILD_PROFILE"),
+        static_binary: env!("PARFLOW_BUILD_STATIC") == "true",
+        features: env!("PARFLOW_BUILD_FEATURES").split(',').filter(|f| !f.is_empty()).collect(),
+    };
+
+    let capabilities = vec![
+        capability("tls", true, "rustls; no system OpenSSL needed"),
+        capability(
+            "graceful-shutdown",
+            cfg!(unix),
+            if cfg!(unix) {
+                "services get SIGTERM before being killed"
+            } else {
+                "services are killed immediately"
+            },
+        ),
+        capability(
+            "dashboard",
+            cfg!(feature = "dashboard"),
+            if cfg!(feature = "dashboard") {
+                "terminal dashboard with system metrics"
+            } else {
+                "built without the `dashboard` feature"
+            },
+        ),
+    ];
+
+    CapabilityReport { build, capabilities }
+}
+
+pub fn print(report: &CapabilityReport) {
+    let build = &report.build;
+    println!("{}", "🧩 ParFlow Build Capabilities".bright_blue().bold());
+    println!("{}", "─────────────────────────────".bright_blue());
+    println!("{}: {}", "Version".bright_cyan(), build.version);
+    println!("{}: {} ({})", "Target".bright_cyan(), build.target, build.profile);
+    println!(
+        "{}: {}",
+        "Linking".bright_cyan(),
+        if build.static_binary { "static".bright_green() } else { "dynamic".bright_yellow() }
+    );
+    let features =
+        if build.features.is_empty() { "none".to_string() } else { build.features.join(", ") };
+    println!("{}: {}", "Features".bright_cyan(), features);
+    println!();
+    for capability in &report.capabilities {
+        let mark = if capability.available { "✅" } else { "➖" };
+        println!("{} {:<18} {}", mark, capability.name.bright_white(), capability.detail);
+    }
+}
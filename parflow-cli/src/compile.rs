@@ -0,0 +1,108 @@
+//! `parflow build`: cargo builds through the shared compilation cache
+//!
+//! The build runs cargo with this binary as `RUSTC_WRAPPER`; cargo then starts it once per rustc
+//! invocation, which [`as_rustc_wrapper`] recognizes before any argument parsing.
+
+use colored::*;
+use parflow_artifacts::{open_store, StorageConfig};
+use parflow_crate_orchestrator::compile_cache::{self, CacheStats, RustcInvocation};
+use std::path::Path;
+use std::time::Instant;
+
+/// When cargo started this process as `RUSTC_WRAPPER`, run the rustc invocation through the
+/// cache and return its exit code.
+pub async fn as_rustc_wrapper() -> Option<i32> {
+    let spec = std::env::var(compile_cache::CACHE_ENV).ok()?;
+    let mut args = std::env::args().skip(1);
+    let rustc = args.next()?;
+    let stem = Path::new(&rustc).file_stem()?.to_string_lossy().into_owned();
+    if !stem.starts_with("rustc") {
+        return None;
+    }
+    let invocation = RustcInvocation::parse(rustc, &args.collect::<Vec<_>>());
+    // JSON from `parflow build`, or a path or URL set by hand
+    let config = serde_json::from_str::<StorageConfig>(&spec)
+        .map_err(anyhow::Error::from)
+        .or_else(|_| spec.parse::<StorageConfig>());
+    let code = match config.and_then(|c| open_store(&c)) {
+        Ok(store) => compile_cache::wrap_rustc(store.as_ref(), &invocation).await,
+        // Without its store the cache steps aside rather than failing the build
+        Err(_) => std::process::Command::new(&invocation.rustc)
+            .args(&invocation.args)
+            .status()
+            .map(|status| status.code().unwrap_or(1))
+            .map_err(Into::into),
+    };
+    Some(code.unwrap_or_else(|e| {
+        eprintln!("parflow: could not run rustc: {}", e);
+        1
+    }))
+}
+
+pub async fn run(cache: StorageConfig, cargo_args: &[String]) -> anyhow::Result<()> {
+    // Cargo starts rustc from each package's directory, so a local store needs an absolute path
+    let cache = match cache {
+        StorageConfig::Local { root } => StorageConfig::Local {
+            root: std::path::absolute(&root)?.to_string_lossy().into_owned(),
+        },
+        cache => cache,
+    };
+    open_store(&cache)?;
+    // The wrappers get the whole config, which a URL could not carry the region or endpoint of
+    let spec = serde_json::to_string(&cache)?;
+    let stats_path =
+        std::env::temp_dir().join(format!("parflow-build-{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&stats_path);
+
+    println!(
+        "{} {}",
+        "🔨 Building with compilation cache:".bright_blue().bold(),
+        cache.to_string().bright_cyan()
+    );
+    let started = Instant::now();
+    let mut cargo = tokio::process::Command::new(std::env::var("CARGO").unwrap_or("cargo".into()));
+    // Incremental builds of workspace crates are not cacheable, so trade them for the cache
+    if std::env::var_os("CARGO_INCREMENTAL").is_none() {
+        cargo.env("CARGO_INCREMENTAL", "0");
+    }
+    let status = cargo
+        .arg("build")
+        .args(cargo_args)
+        .env("RUSTC_WRAPPER", std::env::current_exe()?)
+        .env(compile_cache::CACHE_ENV, &spec)
+        .env(compile_cache::STATS_ENV, &stats_path)
+        .env(compile_cache::PARTICIPANT_ENV, compile_cache::participant())
+        .status()
+        .await?;
+    let elapsed = started.elapsed();
+    let stats = CacheStats::load(&stats_path);
+    let _ = std::fs::remove_file(&stats_path);
+    let stats = stats?;
+
+    println!("\n{}", "📦 COMPILATION SUMMARY".bright_yellow().bold());
+    if status.success() {
+        println!("{}", "✅ Build succeeded".bright_green());
+    } else {
+        println!("{} {}", "❌ Build failed:".bright_red(), status);
+    }
+    println!("{} {:.1}s", "⏱️  Time:".bright_blue(), elapsed.as_secs_f64());
+    match stats.hit_rate() {
+        Some(rate) => println!(
+            "{} {:.1}% ({} of {} crates)",
+            "🎯 Cache hit rate:".bright_blue(),
+            rate * 100.0,
+            stats.hits,
+            stats.total()
+        ),
+        None => println!("{}", "💡 No cacheable crates were compiled".bright_yellow()),
+    }
+    for (participant, hits) in &stats.reused_from {
+        println!(
+            "{} {}: {} crates",
+            "🤝 Reused from".bright_blue(),
+            participant.bright_green(),
+            hits
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,130 @@
+//! Control socket of `parflow start`.
+//!
+//! While services run, the supervisor listens on a Unix socket in the workspace. Every
+//! connection gets one JSON [`ControlSnapshot`] and is closed, so `parflow status` (or `nc -U`)
+//! can see which services are up without touching their ports.
+
+use crate::supervisor::{ServiceState, ServiceTable};
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub const DEFAULT_SOCKET: &str = ".parflow/run/control.sock";
+
+const QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControlSnapshot {
+    /// Process ID of the supervising `parflow start`
+    pub pid: u32,
+    pub uptime_secs: u64,
+    pub services: Vec<ServiceState>,
+}
+
+/// Listening control socket; the socket file is removed when this is dropped.
+pub struct ControlSocket {
+    path: PathBuf,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        self.task.abort();
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Serve snapshots of `table` on `path`. Fails if another supervisor is already listening there;
+/// a socket left behind by one that crashed is replaced.
+#[cfg(unix)]
+pub async fn serve(path: &Path, table: ServiceTable) -> io::Result<ControlSocket> {
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::{UnixListener, UnixStream};
+
+    if path.exists() {
+        if UnixStream::connect(path).await.is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("another `parflow start` is listening on {}", path.display()),
+            ));
+        }
+        std::fs::remove_file(path)?;
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    let started = std::time::Instant::now();
+
+    let task = tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let snapshot = ControlSnapshot {
+                pid: std::process::id(),
+                uptime_secs: started.elapsed().as_secs(),
+                services: table.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+            };
+            let Ok(json) = serde_json::to_vec(&snapshot) else { continue };
+            tokio::spawn(async move {
+                let _ = stream.write_all(&json).await;
+                let _ = stream.shutdown().await;
+            });
+        }
+    });
+    Ok(ControlSocket { path: path.to_path_buf(), task })
+}
+
+#[cfg(not(unix))]
+pub async fn serve(_path: &Path, _table: ServiceTable) -> io::Result<ControlSocket> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "control socket needs Unix domain sockets"))
+}
+
+/// Ask the supervisor listening on `path` for its current state.
+#[cfg(unix)]
+pub async fn query(path: &Path) -> io::Result<ControlSnapshot> {
+    use tokio::io::AsyncReadExt;
+
+    let exchange = async {
+        let mut stream = tokio::net::UnixStream::connect(path).await?;
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).await?;
+        serde_json::from_slice(&response).map_err(io::Error::from)
+    };
+    tokio::time::timeout(QUERY_TIMEOUT, exchange)
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "control socket did not answer"))?
+}
+
+#[cfg(not(unix))]
+pub async fn query(_path: &Path) -> io::Result<ControlSnapshot> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "control socket needs Unix domain sockets"))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[tokio::test]
+    async fn test_snapshot_round_trip() {
+        let dir = std::env::temp_dir().join(format!("parflow-control-{}", std::process::id()));
+        let path = dir.join("run/control.sock");
+        let table: ServiceTable = Arc::new(Mutex::new(vec![ServiceState {
+            name: "rest".to_string(),
+            running: true,
+            restarts: 2,
+            last_failure: Some("exited with 1".to_string()),
+        }]));
+
+        let socket = serve(&path, table.clone()).await.unwrap();
+        assert!(serve(&path, table.clone()).await.is_err());
+        let snapshot = query(&path).await.unwrap();
+        assert_eq!(snapshot.pid, std::process::id());
+        assert_eq!(snapshot.services[0].restarts, 2);
+
+        drop(socket);
+        assert!(!path.exists());
+        assert!(query(&path).await.is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
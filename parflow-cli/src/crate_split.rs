@@ -0,0 +1,82 @@
+use colored::*;
+use parflow_crate_orchestrator::splits::{self, SplitReport};
+use parflow_crate_orchestrator::timings::{self, BuildTimings};
+use parflow_findings::FindingsDb;
+use std::path::Path;
+
+/// Propose crate splits for the workspace at `path` from the latest compile times recorded by
+/// `crate-watch`, timing a build first when there are none or `measure` is set.
+pub fn run(path: &str, measure: bool, format: &str) -> anyhow::Result<()> {
+    let manifest = Path::new(path);
+    let root = match manifest.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let db = FindingsDb::open(root.join(FindingsDb::DEFAULT_DIR))?;
+    let recorded = db.history::<BuildTimings>(timings::TABLE)?.pop();
+    let timings = match recorded {
+        Some(timings) if !measure => timings,
+        _ => {
+            let timings = timings::measure(manifest, false)?;
+            db.record(timings::TABLE, &timings)?;
+            timings
+        }
+    };
+
+    let report = splits::analyze(manifest, &timings)?;
+    if format == "json" {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(&report);
+    }
+    Ok(())
+}
+
+fn print_report(report: &SplitReport) {
+    println!("\n{}", "✂️  CRATE SPLITS".bright_blue().bold());
+    println!(
+        "{}: {}",
+        "Timings from".bright_cyan(),
+        report.commit.get(..10).unwrap_or(&report.commit)
+    );
+    for split in &report.splits {
+        println!(
+            "\n  • {} {}",
+            split.member.bright_white().bold(),
+            format!(
+                "({:.1}s, {} modules, {} lines)",
+                split.compile_secs, split.modules, split.lines
+            )
+            .dimmed()
+        );
+        if split.proposals.is_empty() {
+            println!("    {}", "No split shortens the build".dimmed());
+        }
+        for proposal in &split.proposals {
+            println!(
+                "    📦 {} {}",
+                proposal.name.bright_green(),
+                format!("(~{:.1}s, {} lines)", proposal.secs, proposal.lines).dimmed()
+            );
+            println!("       {}", proposal.modules.join(", "));
+            if !proposal.depends_on.is_empty() {
+                println!("       {} {}", "needs".dimmed(), proposal.depends_on.join(", "));
+            }
+        }
+        if !split.proposals.is_empty() {
+            println!(
+                "    ⏱️  {:.1}s → {:.1}s ({:.1}s saved)",
+                split.compile_secs,
+                split.estimated_secs,
+                split.savings_secs()
+            );
+        }
+        if !split.pinned.is_empty() {
+            println!(
+                "    📌 {} {}",
+                split.pinned.join(", ").yellow(),
+                "refer to the crate root and stay".dimmed()
+            );
+        }
+    }
+}
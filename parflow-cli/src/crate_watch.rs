@@ -0,0 +1,111 @@
+use colored::*;
+use parflow_crate_orchestrator::timings::{self, BuildTimings, CommitRegression, Threshold};
+use parflow_findings::FindingsDb;
+use serde::Serialize;
+use std::path::Path;
+
+pub struct CrateWatchArgs {
+    pub path: String,
+    pub full: bool,
+    pub threshold: f64,
+    pub min_secs: f64,
+    pub report: bool,
+    pub format: String,
+}
+
+#[derive(Serialize)]
+struct WatchReport<'a> {
+    latest: Option<&'a BuildTimings>,
+    regressions: Vec<CommitRegression>,
+    /// Whether the latest measurement regressed against the commit measured before it
+    failed: bool,
+}
+
+/// Time the build at the current commit, record it and report the commits that made the build
+/// slower; `Ok(false)` when the latest measurement is one of them.
+pub fn run(args: CrateWatchArgs) -> anyhow::Result<bool> {
+    let manifest = Path::new(&args.path);
+    let root = match manifest.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let db = FindingsDb::open(root.join(FindingsDb::DEFAULT_DIR))?;
+    if !args.report {
+        db.record(timings::TABLE, &timings::measure(manifest, args.full)?)?;
+    }
+    let history: Vec<BuildTimings> = db.history(timings::TABLE)?;
+    let threshold = Threshold { percent: args.threshold, min_secs: args.min_secs };
+    let latest = history.last();
+    let regressions = timings::regressions(&history, threshold);
+    let failed = latest.is_some_and(|latest| {
+        regressions.iter().any(|regression| regression.commit == latest.commit)
+    });
+
+    let report = WatchReport { latest, regressions, failed };
+    if args.format == "json" {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(&report, history.len(), threshold);
+    }
+    Ok(!report.failed)
+}
+
+fn short(commit: &str) -> &str {
+    commit.get(..10).unwrap_or(commit)
+}
+
+fn print_report(report: &WatchReport, measurements: usize, threshold: Threshold) {
+    println!("\n{}", "⏱️  COMPILE-TIME WATCH".bright_blue().bold());
+    println!("{}: {}", "Measurements".bright_cyan(), measurements);
+    let Some(latest) = report.latest else {
+        println!("{}", "No builds recorded yet".yellow());
+        return;
+    };
+    println!(
+        "{}: {} {} — {:.1}s{}",
+        "Latest".bright_cyan(),
+        short(&latest.commit).bright_white(),
+        latest.subject.dimmed(),
+        latest.total_secs,
+        if latest.full { " (full build)" } else { "" }
+    );
+    let mut slowest: Vec<_> = latest.crates.iter().collect();
+    slowest.sort_by(|a, b| b.1.total_cmp(a.1));
+    for (name, secs) in slowest.iter().take(5) {
+        println!("  {:>8.2}s  {}", secs, name);
+    }
+
+    if report.regressions.is_empty() {
+        println!(
+            "\n{}",
+            format!("✅ No commit slowed the build by {}% or more", threshold.percent)
+                .bright_green()
+        );
+        return;
+    }
+    println!("\n{}", "🐢 REGRESSIONS".bright_red().bold());
+    for regression in &report.regressions {
+        println!(
+            "  • {} {} {}",
+            short(&regression.commit).bright_white(),
+            regression.subject,
+            format!("(since {})", short(&regression.previous_commit)).dimmed()
+        );
+        for change in regression.total.iter().chain(&regression.crates) {
+            println!(
+                "      {:<24} {:.2}s → {:.2}s {}",
+                change.name,
+                change.before_secs,
+                change.after_secs,
+                format!("(+{:.0}%)", change.percent()).bright_red()
+            );
+        }
+    }
+    if report.failed {
+        println!(
+            "\n{}",
+            format!("❌ The latest build regressed past the {}% threshold", threshold.percent)
+                .bright_red()
+        );
+    }
+}
@@ -0,0 +1,380 @@
+use crate::bench_history::{BenchRun, BENCH_TABLE};
+use crossterm::event::{self, Event};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use parflow_core::config::ParflowConfig;
+use parflow_findings::FindingsDb;
+use parflow_grpc::proto::parflow::{
+    workflow_event::Event as WorkflowUpdate, StreamWorkflowEventsRequest,
+};
+use parflow_grpc::OrchestratorClient;
+use parflow_live_client::keymap::{self, Action, KeyOutcome, Keymap};
+use parflow_live_server::registry::{self, SessionAnnouncement};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use sysinfo::{CpuExt, System, SystemExt};
+use tui::backend::CrosstermBackend;
+use tui::layout::{Constraint, Direction, Layout, Rect};
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Borders, Cell, Gauge, Paragraph, Row, Sparkline, Table, Tabs};
+use tui::Terminal;
+
+const TABS: [&str; 4] = ["Workflows", "Sessions", "Benchmarks", "System"];
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Default)]
+struct WorkflowView {
+    name: String,
+    tasks: u32,
+    running: u32,
+    succeeded: u32,
+    failed: u32,
+    completed: bool,
+}
+
+#[derive(Default)]
+struct DashboardState {
+    grpc: String,
+    workflows: BTreeMap<String, WorkflowView>,
+    sessions: Vec<SessionAnnouncement>,
+    bench: Vec<BenchRun>,
+    cpu_history: Vec<u64>,
+    memory: (u64, u64),
+    load: f64,
+}
+
+type Shared = Arc<Mutex<DashboardState>>;
+
+impl DashboardState {
+    fn apply(&mut self, workflow_id: String, update: WorkflowUpdate) {
+        let view = self.workflows.entry(workflow_id).or_default();
+        match update {
+            WorkflowUpdate::Started(started) => {
+                view.name = started.name;
+                view.tasks = started.task_count;
+            }
+            WorkflowUpdate::TaskStarted(_) => view.running += 1,
+            WorkflowUpdate::TaskFinished(finished) => {
+                view.running = view.running.saturating_sub(1);
+                match finished.result.map(|r| r.success) {
+                    Some(true) => view.succeeded += 1,
+                    _ => view.failed += 1,
+                }
+            }
+            WorkflowUpdate::Completed(_) => view.completed = true,
+            WorkflowUpdate::Failed(_) => {
+                // Tasks that never finished count as failed
+                view.failed = view.tasks.saturating_sub(view.succeeded);
+                view.running = 0;
+                view.completed = true;
+            }
+        }
+    }
+}
+
+async fn follow_workflows(endpoint: String, state: Shared) {
+    loop {
+        let stream = async {
+            let mut client = OrchestratorClient::connect(endpoint.clone()).await?;
+            let request = StreamWorkflowEventsRequest { workflow_id: String::new() };
+            Ok::<_, Box<dyn std::error::Error + Send + Sync>>(
+                client.stream_workflow_events(request).await?.into_inner(),
+            )
+        };
+
+        match stream.await {
+            Ok(mut events) => {
+                state.lock().unwrap().grpc = format!("connected to {}", endpoint);
+                loop {
+                    match events.message().await {
+                        Ok(Some(event)) => {
+                            if let Some(update) = event.event {
+                                state.lock().unwrap().apply(event.workflow_id, update);
+                            }
+                        }
+                        Ok(None) => break,
+                        Err(status) => {
+                            state.lock().unwrap().grpc =
+                                format!("stream error: {}", status.message());
+                            break;
+                        }
+                    }
+                }
+            }
+            Err(e) => state.lock().unwrap().grpc = format!("{} unavailable: {}", endpoint, e),
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+async fn poll_local(state: Shared) {
+    let mut system = System::new();
+    loop {
+        system.refresh_cpu();
+        system.refresh_memory();
+        let sessions = registry::list(Path::new(registry::DEFAULT_DIR));
+        let bench = FindingsDb::open(FindingsDb::DEFAULT_DIR)
+            .and_then(|db| db.history(BENCH_TABLE))
+            .unwrap_or_default();
+
+        {
+            let mut state = state.lock().unwrap();
+            state.sessions = sessions;
+            state.bench = bench;
+            state.cpu_history.push(system.global_cpu_info().cpu_usage().round() as u64);
+            if state.cpu_history.len() > 120 {
+                state.cpu_history.remove(0);
+            }
+            state.memory = (system.used_memory(), system.total_memory());
+            state.load = system.load_average().one;
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+fn header(cells: &[&'static str]) -> Row<'static> {
+    Row::new(cells.to_vec()).style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+}
+
+fn render_workflows(
+    f: &mut tui::Frame<CrosstermBackend<io::Stdout>>,
+    area: Rect,
+    state: &DashboardState,
+) {
+    let rows = state.workflows.iter().rev().map(|(id, w)| {
+        let status = if w.completed {
+            Cell::from("done").style(Style::default().fg(if w.failed == 0 {
+                Color::Green
+            } else {
+                Color::Red
+            }))
+        } else {
+            Cell::from("running").style(Style::default().fg(Color::Cyan))
+        };
+        Row::new(vec![
+            Cell::from(id.chars().take(8).collect::<String>()),
+            Cell::from(w.name.clone()),
+            status,
+            Cell::from(format!("{}/{}", w.succeeded + w.failed, w.tasks)),
+            Cell::from(w.running.to_string()),
+            Cell::from(w.failed.to_string()),
+        ])
+    });
+    let table = Table::new(rows)
+        .header(header(&["ID", "Name", "Status", "Done", "Running", "Failed"]))
+        .block(Block::default().title(format!("Workflows — {}", state.grpc)).borders(Borders::ALL))
+        .widths(&[
+            Constraint::Length(10),
+            Constraint::Percentage(40),
+            Constraint::Length(9),
+            Constraint::Length(8),
+            Constraint::Length(8),
+            Constraint::Length(8),
+        ]);
+    f.render_widget(table, area);
+}
+
+fn render_sessions(
+    f: &mut tui::Frame<CrosstermBackend<io::Stdout>>,
+    area: Rect,
+    state: &DashboardState,
+) {
+    let now = chrono::Utc::now().timestamp() as u64;
+    let rows = state.sessions.iter().map(|s| {
+        Row::new(vec![
+            s.session_id.clone(),
+            s.project.clone(),
+            s.port.to_string(),
+            s.pid.to_string(),
+            format!("{}m", now.saturating_sub(s.started_at) / 60),
+        ])
+    });
+    let table = Table::new(rows)
+        .header(header(&["Session", "Project", "Port", "PID", "Uptime"]))
+        .block(Block::default().title("Live Sessions").borders(Borders::ALL))
+        .widths(&[
+            Constraint::Length(38),
+            Constraint::Percentage(30),
+            Constraint::Length(6),
+            Constraint::Length(8),
+            Constraint::Length(8),
+        ]);
+    f.render_widget(table, area);
+}
+
+fn render_benchmarks(
+    f: &mut tui::Frame<CrosstermBackend<io::Stdout>>,
+    area: Rect,
+    state: &DashboardState,
+) {
+    let rows = state.bench.iter().rev().flat_map(|run| {
+        let when = chrono::DateTime::from_timestamp(run.timestamp, 0)
+            .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        let mut metrics: Vec<_> = run.results.benchmarks.values().collect();
+        metrics.sort_by(|a, b| a.language.cmp(&b.language));
+        metrics
+            .into_iter()
+            .map(|m| {
+                Row::new(vec![
+                    when.clone(),
+                    run.suite.clone(),
+                    m.language.clone(),
+                    format!("{:?}", m.execution_time),
+                    format!("{:.0}", m.throughput),
+                    format!("{:.1}", m.memory_usage_mb),
+                ])
+            })
+            .collect::<Vec<_>>()
+    });
+    let table = Table::new(rows)
+        .header(header(&["When", "Suite", "Language", "Execution", "Ops/s", "MB"]))
+        .block(Block::default().title("Benchmark History").borders(Borders::ALL))
+        .widths(&[
+            Constraint::Length(17),
+            Constraint::Length(10),
+            Constraint::Length(12),
+            Constraint::Length(12),
+            Constraint::Length(12),
+            Constraint::Length(8),
+        ]);
+    f.render_widget(table, area);
+}
+
+fn render_system(
+    f: &mut tui::Frame<CrosstermBackend<io::Stdout>>,
+    area: Rect,
+    state: &DashboardState,
+) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(5), Constraint::Length(3), Constraint::Length(3)].as_ref())
+        .split(area);
+
+    let cpu = state.cpu_history.last().copied().unwrap_or(0);
+    let width = chunks[0].width.saturating_sub(2) as usize;
+    let history = &state.cpu_history[state.cpu_history.len().saturating_sub(width)..];
+    let sparkline = Sparkline::default()
+        .block(Block::default().title(format!("CPU {}%", cpu)).borders(Borders::ALL))
+        .data(history)
+        .max(100)
+        .style(Style::default().fg(Color::Green));
+    f.render_widget(sparkline, chunks[0]);
+
+    let (used, total) = state.memory;
+    let gauge = Gauge::default()
+        .block(Block::default().title("Memory").borders(Borders::ALL))
+        .gauge_style(Style::default().fg(Color::Magenta))
+        .ratio(if total == 0 { 0.0 } else { (used as f64 / total as f64).min(1.0) })
+        .label(format!("{:.1} / {:.1} GB", used as f64 / 1e9, total as f64 / 1e9));
+    f.render_widget(gauge, chunks[1]);
+
+    let load = Paragraph::new(format!("Load average (1m): {:.2}", state.load))
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(load, chunks[2]);
+}
+
+fn run_ui(state: Shared, mut keymap: Keymap) -> io::Result<()> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+    let mut current_tab = 0;
+    let mut show_help = false;
+
+    let result = loop {
+        let drawn = terminal.draw(|f| {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .margin(1)
+                .constraints(
+                    [Constraint::Length(3), Constraint::Min(8), Constraint::Length(1)].as_ref(),
+                )
+                .split(f.size());
+
+            let tabs = Tabs::new(TABS.iter().map(|t| Spans::from(*t)).collect())
+                .block(Block::default().title("ParFlow Dashboard").borders(Borders::ALL))
+                .select(current_tab)
+                .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD));
+            f.render_widget(tabs, chunks[0]);
+
+            let state = state.lock().unwrap();
+            match current_tab {
+                0 => render_workflows(f, chunks[1], &state),
+                1 => render_sessions(f, chunks[1], &state),
+                2 => render_benchmarks(f, chunks[1], &state),
+                _ => render_system(f, chunks[1], &state),
+            }
+
+            let bold = Style::default().add_modifier(Modifier::BOLD);
+            let help = Paragraph::new(Spans::from(vec![
+                Span::styled(keymap.label(Action::NextTab), bold),
+                Span::raw(" next tab  "),
+                Span::styled(keymap.label(Action::Help), bold),
+                Span::raw(" keys  "),
+                Span::styled(keymap.label(Action::Quit), bold),
+                Span::raw(" quit  (read-only)"),
+            ]));
+            f.render_widget(help, chunks[2]);
+
+            if show_help {
+                keymap::render_help(f, f.size(), &keymap);
+            }
+        });
+        if let Err(e) = drawn {
+            break Err(e);
+        }
+
+        match event::poll(Duration::from_millis(250)) {
+            Ok(false) => continue,
+            Ok(true) => {}
+            Err(e) => break Err(e),
+        }
+        if let Ok(Event::Key(key)) = event::read() {
+            let KeyOutcome::Action(action) = keymap.handle(&key) else { continue };
+            match action {
+                Action::NextTab => current_tab = (current_tab + 1) % TABS.len(),
+                Action::PrevTab => current_tab = (current_tab + TABS.len() - 1) % TABS.len(),
+                Action::Help => show_help = !show_help,
+                Action::Quit => break Ok(()),
+                action => {
+                    if let Some(tab) = action.tab_index().filter(|&tab| tab < TABS.len()) {
+                        current_tab = tab;
+                    }
+                }
+            }
+        }
+    };
+
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen)?;
+    result
+}
+
+/// Read-only operations console over the gRPC workflow stream and local ParFlow state.
+pub async fn run(config: &ParflowConfig) -> anyhow::Result<()> {
+    let host = crate::status::probe_host(&config.server.host);
+    let host = if host.contains(':') { format!("[{}]", host) } else { host };
+    let endpoint = format!("http://{}:{}", host, config.server.grpc_port);
+    let state = Shared::default();
+    state.lock().unwrap().grpc = format!("connecting to {}", endpoint);
+
+    let producers = [
+        tokio::spawn(follow_workflows(endpoint, state.clone())),
+        tokio::spawn(poll_local(state.clone())),
+    ];
+    let keymap = Keymap::load_or_default(keymap::App::Dashboard);
+    let ui = tokio::task::spawn_blocking(move || run_ui(state, keymap)).await;
+    for producer in producers {
+        producer.abort();
+    }
+    ui??;
+    Ok(())
+}
@@ -0,0 +1,31 @@
+use colored::*;
+use semantic_compiler::dead_code::{DeadCodeAnalyzer, DeadCodeReport};
+use std::path::Path;
+
+/// Dead functions under `path`; an unreadable path has none
+pub fn find(path: &str) -> DeadCodeReport {
+    DeadCodeAnalyzer::from_directory(Path::new(path))
+        .map(|analyzer| analyzer.analyze())
+        .unwrap_or_default()
+}
+
+pub fn print(report: &DeadCodeReport) {
+    println!("\n{}", "🧹 DEAD CODE".bright_yellow().bold());
+    if report.functions.is_empty() {
+        println!("  {}", "No unreferenced functions found".bright_white());
+        return;
+    }
+    for function in &report.functions {
+        let suggestion = if function.safe_delete {
+            function.suggestion().bright_green()
+        } else {
+            function.suggestion().bright_yellow()
+        };
+        println!(
+            "  {} {}",
+            format!("{}:{}", function.file, function.line).bright_cyan(),
+            function.name.bright_white().bold()
+        );
+        println!("     {} {}", "→".bright_green(), suggestion);
+    }
+}
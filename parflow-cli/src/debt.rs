@@ -0,0 +1,71 @@
+use colored::*;
+use parflow_findings::debt::{self, DebtSnapshot, DebtWeights};
+use parflow_findings::{report, FindingsDb};
+use std::path::Path;
+
+pub struct DebtArgs {
+    pub path: String,
+    pub coverage: Option<String>,
+    pub weights: Option<String>,
+    pub html: Option<String>,
+    pub format: String,
+}
+
+/// Score the project, append the snapshot to the findings database and render the report.
+pub async fn run(args: DebtArgs) -> anyhow::Result<()> {
+    let root = Path::new(&args.path);
+    let weights = match &args.weights {
+        Some(file) => DebtWeights::from_toml_file(Path::new(file))?,
+        None => DebtWeights::default(),
+    };
+    let coverage = match &args.coverage {
+        Some(lcov) => Some(debt::parse_lcov(&std::fs::read_to_string(lcov)?, root)),
+        None => None,
+    };
+
+    let snapshot = debt::analyze(root, &weights, coverage.as_ref())?;
+
+    let db = FindingsDb::open(root.join(FindingsDb::DEFAULT_DIR))?;
+    db.record(debt::TABLE, &snapshot)?;
+    let history: Vec<DebtSnapshot> = db.history(debt::TABLE)?;
+
+    match args.format.as_str() {
+        "json" => println!("{}", serde_json::to_string_pretty(&snapshot)?),
+        "markdown" => println!("{}", report::render_markdown(&snapshot, root, 10)),
+        _ => print_summary(&snapshot, &history),
+    }
+
+    if let Some(html) = &args.html {
+        std::fs::write(html, report::render_html(&history, root))?;
+        println!("{} {}", "📄 HTML report written to".bright_green(), html.bright_cyan());
+    }
+    Ok(())
+}
+
+fn print_summary(snapshot: &DebtSnapshot, history: &[DebtSnapshot]) {
+    println!("\n{}", "📉 TECHNICAL DEBT".bright_yellow().bold());
+    println!("{}: {:.1}/100", "Overall Score".bright_cyan(), snapshot.overall);
+    if let [.., previous, _] = history {
+        let delta = snapshot.overall - previous.overall;
+        let trend = if delta > 0.0 {
+            format!("+{:.1} (growing)", delta).bright_red()
+        } else if delta < 0.0 {
+            format!("{:.1} (shrinking)", delta).bright_green()
+        } else {
+            "unchanged".normal()
+        };
+        println!("{}: {}", "Since Last Snapshot".bright_cyan(), trend);
+    }
+
+    let mut modules: Vec<_> = snapshot.modules.iter().collect();
+    modules.sort_by(|a, b| b.score.total_cmp(&a.score));
+    println!("\n{}", "🔥 HIGHEST-DEBT MODULES".bright_red().bold());
+    for module in modules.iter().take(10) {
+        println!(
+            "  • {} [{}]: {:.1}",
+            module.module.bright_white(),
+            module.language.bright_yellow(),
+            module.score
+        );
+    }
+}
@@ -0,0 +1,60 @@
+use colored::*;
+use parflow_crate_orchestrator::features::{self, FeatureReport};
+use std::path::Path;
+
+/// Analyze the dependency features of the workspace at `path` and print the suggestions as text
+/// or JSON.
+pub fn run(path: &str, measure: bool, format: &str) -> anyhow::Result<()> {
+    let report = features::analyze(Path::new(path), measure)?;
+    if format == "json" {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(&report);
+    }
+    Ok(())
+}
+
+fn print_report(report: &FeatureReport) {
+    println!("\n{}", "🧩 FEATURE MINIMIZATION".bright_blue().bold());
+    println!("{}: {}", "Dependencies scanned".bright_cyan(), report.dependencies_scanned);
+    if report.suggestions.is_empty() {
+        println!("{}", "✅ Every enabled feature is in use".bright_green());
+        return;
+    }
+
+    for suggestion in &report.suggestions {
+        println!(
+            "\n  • {} → {} {}",
+            suggestion.member.bright_white(),
+            suggestion.dependency.bright_white().bold(),
+            format!("(unused: {})", suggestion.unused.join(", ")).dimmed()
+        );
+        println!("    {}", suggestion.manifest_entry.bright_green());
+        match &suggestion.trial {
+            Some(trial) if trial.builds => println!(
+                "    ⏱️  {:.1}s → {:.1}s ({:.1}s saved)",
+                trial.baseline_secs,
+                trial.trial_secs,
+                trial.savings_secs()
+            ),
+            Some(_) => println!(
+                "    {}",
+                "⚠️  The member no longer builds with this feature set".bright_yellow()
+            ),
+            None => {}
+        }
+    }
+
+    let saved: f64 = report
+        .suggestions
+        .iter()
+        .filter_map(|suggestion| suggestion.trial.as_ref())
+        .filter(|trial| trial.builds)
+        .map(|trial| trial.savings_secs())
+        .sum();
+    if report.suggestions.iter().any(|suggestion| suggestion.trial.is_some()) {
+        println!("\n{} {:.1}s of clean build time", "💰 Estimated savings:".bright_cyan(), saved);
+    } else {
+        println!("\n{}", "Run with --measure to time trial builds of each suggestion".dimmed());
+    }
+}
@@ -0,0 +1,81 @@
+use colored::*;
+use parflow_orchestrator::fleet::AGENT_TOKEN_ENV;
+use parflow_orchestrator::{
+    AgentControl, AgentEndpoint, FleetCoordinator, HttpAgentControl, RollingUpgradePolicy,
+    UpgradeReport,
+};
+
+/// Roll `version` out to the agents listed in `agents_file`, a JSON list of
+/// `{"id", "url", "capacity"}` entries, over their control API.
+pub async fn upgrade(
+    agents_file: &str,
+    version: &str,
+    policy: &RollingUpgradePolicy,
+) -> anyhow::Result<UpgradeReport> {
+    let endpoints: Vec<AgentEndpoint> =
+        serde_json::from_str(&std::fs::read_to_string(agents_file)?)?;
+    if endpoints.is_empty() {
+        anyhow::bail!("{} lists no agents", agents_file);
+    }
+    let control =
+        HttpAgentControl::new(&endpoints)?.with_token(std::env::var(AGENT_TOKEN_ENV).ok());
+
+    let mut fleet = FleetCoordinator::new();
+    for agent in &endpoints {
+        let current = control.handshake(&agent.id).await?;
+        println!("  {} {} {}", "•".bright_cyan(), agent.id.bright_white(), current.dimmed());
+        fleet.register_agent(&agent.id, &current, agent.capacity);
+    }
+    fleet.publish_version(version);
+
+    let report = fleet.rolling_upgrade(&control, policy).await?;
+    print_report(&report);
+    Ok(report)
+}
+
+fn print_report(report: &UpgradeReport) {
+    println!("\n{}", "🚚 FLEET UPGRADE".bright_blue().bold());
+    println!(
+        "{}: {} ({} batches)",
+        "Target".bright_cyan(),
+        report.target_version.bright_white(),
+        report.batches
+    );
+    if !report.upgraded.is_empty() {
+        println!("{}: {}", "Upgraded".bright_green(), report.upgraded.join(", "));
+    }
+    if !report.failed.is_empty() {
+        println!("{}: {}", "Failed".bright_red(), report.failed.join(", "));
+    }
+
+    if !report.rolled_back {
+        println!("{}", "✅ Fleet upgraded".bright_green());
+    } else if report.rollback_failed.is_empty() {
+        println!("{}", "↩️  Too many failures, agents rolled back".yellow());
+    } else {
+        println!(
+            "{} {}",
+            "❌ Rolled back, but these agents did not return to their previous version:"
+                .bright_red(),
+            report.rollback_failed.join(", ")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_upgrade_needs_agents() {
+        let file = std::env::temp_dir().join(format!("parflow-fleet-{}.json", std::process::id()));
+        let policy = RollingUpgradePolicy::default();
+        std::fs::write(&file, "[]").unwrap();
+        let path = file.to_str().unwrap();
+        assert!(upgrade(path, "2.0", &policy).await.unwrap_err().to_string().contains("no agents"));
+
+        std::fs::write(&file, r#"[{"id": "a1", "url": "not a url", "capacity": 4}]"#).unwrap();
+        assert!(upgrade(path, "2.0", &policy).await.is_err());
+        std::fs::remove_file(&file).unwrap();
+    }
+}
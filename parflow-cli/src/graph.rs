@@ -0,0 +1,129 @@
+//! `parflow graph`: DOT and Mermaid renderings of what the orchestrator will run
+//!
+//! Workflows render as their task DAG, see [`TaskGraph`]. Tasks are weighted by the durations
+//! of a previous run when its results are given, and otherwise count one each, so the
+//! highlighted critical path is then simply the longest chain. Crates render as the dependency
+//! tree found by `crate-analyze`.
+
+use anyhow::{bail, Result};
+use parflow_crate_orchestrator::CrateAnalysis;
+use parflow_orchestrator::{CriticalPathReport, ExecutionResult, MultiLanguageWorkflow, TaskGraph};
+use std::fmt::Write;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    Dot,
+    Mermaid,
+}
+
+impl std::str::FromStr for GraphFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "dot" => Ok(Self::Dot),
+            "mermaid" => Ok(Self::Mermaid),
+            _ => bail!("unknown graph format '{}' (expected dot or mermaid)", s),
+        }
+    }
+}
+
+/// A rendered workflow graph and its critical path
+pub struct WorkflowGraph {
+    pub rendered: String,
+    pub critical_path: Vec<String>,
+    /// Timing of the critical path and how to shorten it, when durations were known
+    pub report: Option<CriticalPathReport>,
+}
+
+/// Render the workflow in `workflow_file`, weighting tasks by the `ExecutionResult`s (a JSON
+/// array) in `results_file` if given.
+pub fn workflow_graph(
+    workflow_file: &Path,
+    results_file: Option<&Path>,
+    format: GraphFormat,
+) -> Result<WorkflowGraph> {
+    let workflow = MultiLanguageWorkflow::load(workflow_file)?;
+    let graph = TaskGraph::new(&workflow)?;
+    let durations: Vec<u128> = match results_file {
+        Some(path) => {
+            let results: Vec<ExecutionResult> =
+                serde_json::from_str(&std::fs::read_to_string(path)?)?;
+            graph
+                .tasks
+                .iter()
+                .map(|name| {
+                    results.iter().find(|r| r.task_name == *name).map_or(0, |r| r.execution_time)
+                })
+                .collect()
+        }
+        None => vec![1; graph.tasks.len()],
+    };
+    let path = graph.critical_path(&durations);
+    let rendered = match format {
+        GraphFormat::Dot => graph.to_dot(&path),
+        GraphFormat::Mermaid => graph.to_mermaid(&path),
+    };
+    Ok(WorkflowGraph {
+        rendered,
+        critical_path: path.iter().map(|&i| graph.tasks[i].clone()).collect(),
+        report: results_file.map(|_| graph.critical_path_report(&durations)),
+    })
+}
+
+/// Render the dependency tree of an analyzed crate. Unused dependencies are dashed and
+/// deprecated ones red.
+pub fn crate_graph(analysis: &CrateAnalysis, format: GraphFormat) -> String {
+    let mut out = String::new();
+    match format {
+        GraphFormat::Dot => {
+            let _ = writeln!(
+                out,
+                "digraph \"{}\" {{\n  rankdir=LR;\n  node [shape=box];",
+                analysis.name
+            );
+            let _ = writeln!(
+                out,
+                "  root [label=\"{} {}\", penwidth=2];",
+                analysis.name, analysis.version
+            );
+            for (i, dependency) in analysis.dependencies.iter().enumerate() {
+                let mut style = Vec::new();
+                if !dependency.used {
+                    style.push("style=dashed");
+                }
+                if dependency.deprecated {
+                    style.push("color=red");
+                }
+                let style = if style.is_empty() {
+                    String::new()
+                } else {
+                    format!(", {}", style.join(", "))
+                };
+                let _ = writeln!(
+                    out,
+                    "  d{} [label=\"{} {}\"{}];\n  root -> d{};",
+                    i, dependency.name, dependency.version, style, i
+                );
+            }
+            out.push_str("}\n");
+        }
+        GraphFormat::Mermaid => {
+            let _ =
+                writeln!(out, "flowchart LR\n  root[\"{} {}\"]", analysis.name, analysis.version);
+            for (i, dependency) in analysis.dependencies.iter().enumerate() {
+                let arrow = if dependency.used { "-->" } else { "-.->" };
+                let _ = writeln!(
+                    out,
+                    "  root {} d{}[\"{} {}\"]",
+                    arrow, i, dependency.name, dependency.version
+                );
+                if dependency.deprecated {
+                    let _ = writeln!(out, "  style d{} stroke:#d00", i);
+                }
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,363 @@
+//! `parflow history`: completed workflow runs and how they compare
+//!
+//! Every `parflow run` is recorded in the findings database with the status, exit code and
+//! duration of each task, and the files it wrote when the workflow tracks changes. Task output goes to `.parflow/logs/<run id>/`, one file per task, so
+//! the database stays small while the logs of any past run can still be looked up.
+
+use anyhow::{bail, Result};
+use chrono::{TimeZone, Utc};
+use clap::Subcommand;
+use colored::*;
+use parflow_findings::FindingsDb;
+use parflow_orchestrator::{ExecutionResult, FileChanges};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub const RUNS_TABLE: &str = "workflow_runs";
+pub const LOG_DIR: &str = ".parflow/logs";
+
+/// A task that got at least this much slower, relatively and absolutely, is a slowdown
+const SLOWDOWN_SHARE: f64 = 0.2;
+const SLOWDOWN_MS: u128 = 100;
+
+#[derive(Subcommand)]
+pub enum HistoryAction {
+    /// List recorded runs, newest first
+    List {
+        /// Only runs of this workflow
+        #[arg(short, long)]
+        workflow: Option<String>,
+
+        /// Show at most this many runs
+        #[arg(short, long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Show the tasks of a run
+    Show {
+        /// Run id, or a unique prefix of one
+        id: String,
+    },
+    /// Compare the tasks of two runs, flagging slowdowns
+    Diff {
+        /// The earlier run
+        before: String,
+
+        /// The later run
+        after: String,
+    },
+}
+
+/// One completed `parflow run`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowRun {
+    pub run_id: String,
+    pub workflow: String,
+    /// Journal the run belongs to, see [`parflow_orchestrator::JobQueue`]
+    pub workflow_id: Option<String>,
+    pub started_at: i64,
+    pub finished_at: i64,
+    pub cancelled: bool,
+    /// Directory holding the output of each task
+    pub logs: String,
+    pub tasks: Vec<TaskRun>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskRun {
+    pub name: String,
+    pub language: String,
+    pub success: bool,
+    pub exit_code: Option<i32>,
+    pub duration_ms: u128,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changes: Option<FileChanges>,
+}
+
+impl WorkflowRun {
+    pub fn success(&self) -> bool {
+        !self.cancelled && self.tasks.iter().all(|task| task.success)
+    }
+}
+
+/// How one task changed between two runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskDiff {
+    pub name: String,
+    /// Duration in each run, `None` where the task did not run
+    pub before_ms: Option<u128>,
+    pub after_ms: Option<u128>,
+    pub before_success: Option<bool>,
+    pub after_success: Option<bool>,
+    pub slowdown: bool,
+}
+
+impl TaskDiff {
+    pub fn delta_ms(&self) -> Option<i128> {
+        Some(self.after_ms? as i128 - self.before_ms? as i128)
+    }
+}
+
+/// Tasks of `before` and `after` side by side, in the order of `after` with tasks that only
+/// ran before at the end.
+pub fn diff(before: &WorkflowRun, after: &WorkflowRun) -> Vec<TaskDiff> {
+    let mut names: Vec<&str> = after.tasks.iter().map(|task| task.name.as_str()).collect();
+    for task in &before.tasks {
+        if !names.contains(&task.name.as_str()) {
+            names.push(&task.name);
+        }
+    }
+    names
+        .into_iter()
+        .map(|name| {
+            let old = before.tasks.iter().find(|task| task.name == name);
+            let new = after.tasks.iter().find(|task| task.name == name);
+            let slowdown = match (old, new) {
+                (Some(old), Some(new)) => {
+                    let delta = new.duration_ms.saturating_sub(old.duration_ms);
+                    delta >= SLOWDOWN_MS && delta as f64 >= old.duration_ms as f64 * SLOWDOWN_SHARE
+                }
+                _ => false,
+            };
+            TaskDiff {
+                name: name.to_string(),
+                before_ms: old.map(|task| task.duration_ms),
+                after_ms: new.map(|task| task.duration_ms),
+                before_success: old.map(|task| task.success),
+                after_success: new.map(|task| task.success),
+                slowdown,
+            }
+        })
+        .collect()
+}
+
+/// Record a run of `workflow` that started at `started_at`, writing the output of each task to
+/// its log directory. Returns the run id.
+pub fn record_run(
+    workflow: &str,
+    workflow_id: Option<&str>,
+    started_at: i64,
+    results: &[ExecutionResult],
+    cancelled: bool,
+) -> Result<String> {
+    let run_id = Utc::now().format("%Y%m%d-%H%M%S%3f").to_string();
+    let logs = Path::new(LOG_DIR).join(&run_id);
+    std::fs::create_dir_all(&logs)?;
+    for (index, result) in results.iter().enumerate() {
+        let file = format!("{}-{}.log", index, result.task_name.replace(['/', '\\'], "_"));
+        std::fs::write(logs.join(file), &result.output)?;
+    }
+    let run = WorkflowRun {
+        run_id: run_id.clone(),
+        workflow: workflow.to_string(),
+        workflow_id: workflow_id.map(str::to_string),
+        started_at,
+        finished_at: Utc::now().timestamp(),
+        cancelled,
+        logs: logs.display().to_string(),
+        tasks: results
+            .iter()
+            .map(|result| TaskRun {
+                name: result.task_name.clone(),
+                language: result.language.clone(),
+                success: result.success,
+                exit_code: result.exit_code,
+                duration_ms: result.execution_time,
+                changes: result.changes.clone(),
+            })
+            .collect(),
+    };
+    FindingsDb::open(FindingsDb::DEFAULT_DIR)?.record(RUNS_TABLE, &run)?;
+    Ok(run_id)
+}
+
+/// The run whose id is or starts with `id`
+fn find<'a>(runs: &'a [WorkflowRun], id: &str) -> Result<&'a WorkflowRun> {
+    if let Some(run) = runs.iter().find(|run| run.run_id == id) {
+        return Ok(run);
+    }
+    let matching: Vec<&WorkflowRun> =
+        runs.iter().filter(|run| run.run_id.starts_with(id)).collect();
+    match matching.as_slice() {
+        [run] => Ok(run),
+        [] => bail!("no recorded run {}", id),
+        _ => bail!("{} matches {} runs; use more of the id", id, matching.len()),
+    }
+}
+
+fn timestamp(secs: i64) -> String {
+    Utc.timestamp_opt(secs, 0)
+        .single()
+        .map(|at| at.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default()
+}
+
+/// The files a task created, modified and deleted, one per line
+pub fn print_changes(changes: &FileChanges) {
+    if changes.is_empty() {
+        println!("      {}", "no files written".dimmed());
+    }
+    for (sign, files) in
+        [("+", &changes.created), ("~", &changes.modified), ("-", &changes.deleted)]
+    {
+        for file in files {
+            println!("      {} {}", sign, file.dimmed());
+        }
+    }
+}
+
+fn status(success: bool) -> &'static str {
+    if success {
+        "✅"
+    } else {
+        "❌"
+    }
+}
+
+pub fn run(action: HistoryAction) -> Result<()> {
+    let runs: Vec<WorkflowRun> = FindingsDb::open(FindingsDb::DEFAULT_DIR)?.history(RUNS_TABLE)?;
+
+    match action {
+        HistoryAction::List { workflow, limit } => {
+            println!("{}", "📜 Workflow Runs".bright_blue().bold());
+            let listed: Vec<&WorkflowRun> = runs
+                .iter()
+                .rev()
+                .filter(|run| workflow.as_ref().is_none_or(|name| run.workflow == *name))
+                .take(limit)
+                .collect();
+            if listed.is_empty() {
+                println!("{}", "No recorded runs yet".bright_yellow());
+            }
+            for run in listed {
+                let total: u128 = run.tasks.iter().map(|task| task.duration_ms).sum();
+                println!(
+                    "  {} {} {} {} ({} tasks, {}ms{})",
+                    status(run.success()),
+                    run.run_id.bright_white(),
+                    timestamp(run.started_at),
+                    run.workflow.bright_cyan(),
+                    run.tasks.len(),
+                    total,
+                    if run.cancelled { ", cancelled" } else { "" }
+                );
+            }
+        }
+        HistoryAction::Show { id } => {
+            let run = find(&runs, &id)?;
+            println!("{} {}", "📜 Run".bright_blue().bold(), run.run_id.bright_white());
+            println!("{}: {}", "Workflow".bright_cyan(), run.workflow);
+            if let Some(workflow_id) = &run.workflow_id {
+                println!("{}: {}", "Journal".bright_cyan(), workflow_id);
+            }
+            println!(
+                "{}: {} → {} ({}s)",
+                "Time".bright_cyan(),
+                timestamp(run.started_at),
+                timestamp(run.finished_at),
+                run.finished_at - run.started_at
+            );
+            if run.cancelled {
+                println!("{}", "⏹️  Cancelled".bright_yellow());
+            }
+            for task in &run.tasks {
+                println!(
+                    "  {} {} [{}] {}ms, exit code {}",
+                    status(task.success),
+                    task.name,
+                    task.language,
+                    task.duration_ms,
+                    task.exit_code.map_or("none".to_string(), |code| code.to_string())
+                );
+                if let Some(changes) = &task.changes {
+                    print_changes(changes);
+                }
+            }
+            println!("{}: {}", "Logs".bright_cyan(), run.logs.bright_yellow());
+        }
+        HistoryAction::Diff { before, after } => {
+            let (before, after) = (find(&runs, &before)?, find(&runs, &after)?);
+            println!(
+                "{} {} → {}",
+                "📊 Comparing".bright_blue().bold(),
+                before.run_id.bright_white(),
+                after.run_id.bright_white()
+            );
+            let tasks = diff(before, after);
+            for task in &tasks {
+                let timing = match (task.before_ms, task.after_ms, task.delta_ms()) {
+                    (Some(old), Some(new), Some(delta)) => {
+                        format!("{}ms → {}ms ({:+}ms)", old, new, delta)
+                    }
+                    (Some(old), None, _) => format!("{}ms → not run", old),
+                    (None, Some(new), _) => format!("new, {}ms", new),
+                    _ => String::new(),
+                };
+                let change = match (task.before_success, task.after_success) {
+                    (Some(true), Some(false)) => " now failing".bright_red().to_string(),
+                    (Some(false), Some(true)) => " now passing".bright_green().to_string(),
+                    _ => String::new(),
+                };
+                let line = format!("  {} {}{}", task.name, timing, change);
+                if task.slowdown {
+                    println!("{} {}", line.bright_red(), "🐢 slower".bright_red());
+                } else {
+                    println!("{}", line);
+                }
+            }
+            let slower = tasks.iter().filter(|task| task.slowdown).count();
+            if slower > 0 {
+                println!("{} {} tasks got slower", "⚠️ ".bright_yellow(), slower);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(tasks: &[(&str, u128, bool)]) -> WorkflowRun {
+        WorkflowRun {
+            run_id: "20261016-120000000".to_string(),
+            workflow: "build".to_string(),
+            workflow_id: None,
+            started_at: 0,
+            finished_at: 0,
+            cancelled: false,
+            logs: String::new(),
+            tasks: tasks
+                .iter()
+                .map(|&(name, duration_ms, success)| TaskRun {
+                    name: name.to_string(),
+                    language: "Rust".to_string(),
+                    success,
+                    exit_code: Some(if success { 0 } else { 1 }),
+                    duration_ms,
+                    changes: None,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_diff_flags_slowdowns_and_missing_tasks() {
+        let before = run(&[("lib", 1000, true), ("app", 200, true), ("docs", 50, true)]);
+        let after = run(&[("lib", 1500, true), ("app", 250, false), ("bench", 10, true)]);
+        let tasks = diff(&before, &after);
+        let names: Vec<&str> = tasks.iter().map(|task| task.name.as_str()).collect();
+        assert_eq!(names, ["lib", "app", "bench", "docs"]);
+        assert!(tasks[0].slowdown);
+        assert_eq!(tasks[0].delta_ms(), Some(500));
+        // 25% slower but only by 50ms
+        assert!(!tasks[1].slowdown);
+        assert_eq!(tasks[1].after_success, Some(false));
+        assert_eq!((tasks[2].before_ms, tasks[2].delta_ms()), (None, None));
+        assert_eq!(tasks[3].after_ms, None);
+
+        assert!(!after.success());
+        let runs = [before, run(&[])];
+        assert!(find(&runs, "2026").is_err());
+        assert!(find(&runs, "nope").is_err());
+    }
+}
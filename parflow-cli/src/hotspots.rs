@@ -0,0 +1,83 @@
+use colored::*;
+use parflow_transpiler::complexity::{self, FunctionComplexity};
+use semantic_compiler::ownership::language_for_path;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// A function worth simplifying, located by file and line
+#[derive(Debug, Serialize)]
+pub struct Hotspot {
+    pub file: String,
+    #[serde(flatten)]
+    pub function: FunctionComplexity,
+}
+
+fn collect_sources(dir: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else { return };
+    for path in entries.flatten().map(|entry| entry.path()) {
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        if path.is_dir() {
+            if !name.starts_with('.') && name != "target" && name != "node_modules" {
+                collect_sources(&path, out);
+            }
+        } else if language_for_path(&name).is_some() {
+            out.push(path);
+        }
+    }
+}
+
+/// The `count` most complex functions under `path`, a directory or a single file
+pub fn find(path: &str, count: usize) -> Vec<Hotspot> {
+    let root = Path::new(path);
+    let mut files = Vec::new();
+    if root.is_dir() {
+        collect_sources(root, &mut files);
+    } else {
+        files.push(root.to_path_buf());
+    }
+
+    let mut hotspots = Vec::new();
+    for file in files {
+        let Some(language) = language_for_path(&file.to_string_lossy()) else { continue };
+        let Ok(code) = std::fs::read_to_string(&file) else { continue };
+        let Some(report) = complexity::analyze_functions(&code, language) else { continue };
+        let relative = file.strip_prefix(root).unwrap_or(&file);
+        let relative = if relative.as_os_str().is_empty() { file.as_path() } else { relative };
+        hotspots.extend(
+            report
+                .functions
+                .into_iter()
+                .map(|function| Hotspot { file: relative.display().to_string(), function }),
+        );
+    }
+    hotspots.sort_by(|a, b| {
+        (b.function.cognitive, b.function.cyclomatic)
+            .cmp(&(a.function.cognitive, a.function.cyclomatic))
+            .then_with(|| (&a.file, a.function.line).cmp(&(&b.file, b.function.line)))
+    });
+    hotspots.truncate(count);
+    hotspots
+}
+
+pub fn print(hotspots: &[Hotspot]) {
+    println!("\n{}", "🔥 COMPLEXITY HOTSPOTS".bright_red().bold());
+    if hotspots.is_empty() {
+        println!("  {}", "No functions found".bright_white());
+        return;
+    }
+    for hotspot in hotspots {
+        let function = &hotspot.function;
+        println!(
+            "  {} {}",
+            format!("{}:{}", hotspot.file, function.line).bright_cyan(),
+            function.name.bright_white().bold()
+        );
+        println!(
+            "     cognitive {}, cyclomatic {}, nesting {}, {} parameters",
+            function.cognitive.to_string().bright_yellow(),
+            function.cyclomatic,
+            function.max_nesting,
+            function.parameters
+        );
+    }
+}
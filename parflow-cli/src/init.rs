@@ -0,0 +1,586 @@
+//! `parflow init`: skeletons of multi-language projects
+//!
+//! A template writes one subproject per language, each in its own directory so `parflow
+//! test-run` finds every suite, plus:
+//!
+//! - `parflow.toml`, the project config
+//! - `.parflow/workflows/build.json` and `test.json`, [`MultiLanguageWorkflow`]s that build and
+//!   test the subprojects, in the form `SubmitWorkflow` takes. The test workflow has watch
+//!   rules, so `parflow watch --workflow .parflow/workflows/test.json` re-runs suites on save
+//!
+//! Files are written relative to the target directory; existing ones are only overwritten with
+//! `--force`.
+
+use anyhow::{bail, Context, Result};
+use parflow_orchestrator::{LanguageTask, MultiLanguageWorkflow, WatchConfig, WatchRule};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+pub const WORKFLOW_DIR: &str = ".parflow/workflows";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Template {
+    /// Rust API, Python worker and Node.js frontend
+    PolyglotService,
+    /// Rust library with a C ABI, called from Python through ctypes
+    RustPythonBridge,
+    /// Rust compiled to WebAssembly, loaded from Node.js
+    WasmApp,
+}
+
+impl Template {
+    pub const ALL: [Template; 3] =
+        [Template::PolyglotService, Template::RustPythonBridge, Template::WasmApp];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Template::PolyglotService => "polyglot-service",
+            Template::RustPythonBridge => "rust-python-bridge",
+            Template::WasmApp => "wasm-app",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Template::PolyglotService => "Rust API, Python worker and Node.js frontend",
+            Template::RustPythonBridge => "Rust library called from Python through ctypes",
+            Template::WasmApp => "Rust compiled to WebAssembly, loaded from Node.js",
+        }
+    }
+}
+
+impl fmt::Display for Template {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Template {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Template::ALL.into_iter().find(|template| template.name() == s).ok_or_else(|| {
+            let names: Vec<_> = Template::ALL.iter().map(|t| t.name()).collect();
+            anyhow::anyhow!("unknown template '{}' (expected {})", s, names.join(", "))
+        })
+    }
+}
+
+/// A file of the skeleton, relative to the project directory
+#[derive(Debug, Clone)]
+pub struct ScaffoldFile {
+    pub path: PathBuf,
+    pub content: String,
+}
+
+/// Files of `template` for a project called `name`
+pub fn scaffold(template: Template, name: &str) -> Result<Vec<ScaffoldFile>> {
+    let crate_name = crate_name(name);
+    let module = crate_name.replace('-', "_");
+    let fill = |text: &str| {
+        text.replace("{name}", name).replace("{crate}", &crate_name).replace("{module}", &module)
+    };
+
+    let wasm_build = task(
+        "wasm-build",
+        "Rust",
+        "cargo",
+        &["build", "--release", "--target", "wasm32-unknown-unknown"],
+        "wasm",
+        600,
+    );
+    // Watch rules of the test workflow: a changed library also re-runs the suites loading it
+    type Rules = &'static [(&'static str, &'static [&'static str])];
+    let (sources, build, test, rules): (&[(&str, &str)], _, _, Rules) = match template {
+        Template::PolyglotService => (
+            &[
+                ("api/Cargo.toml", RUST_BIN_MANIFEST),
+                ("api/src/main.rs", RUST_API_MAIN),
+                ("worker/pyproject.toml", PYTHON_PYPROJECT),
+                ("worker/{module}_worker.py", PYTHON_WORKER),
+                ("worker/tests/test_worker.py", PYTHON_WORKER_TEST),
+                ("web/package.json", NODE_PACKAGE),
+                ("web/index.js", NODE_INDEX),
+                ("web/index.test.js", NODE_INDEX_TEST),
+            ],
+            vec![
+                task("api-build", "Rust", "cargo", &["build", "--release"], "api", 600),
+                task(
+                    "worker-build",
+                    "Python",
+                    "python",
+                    &["-m", "compileall", "-q", "."],
+                    "worker",
+                    60,
+                ),
+                task("web-install", "Node.js", "npm", &["install"], "web", 300),
+            ],
+            vec![
+                task("api-test", "Rust", "cargo", &["test"], "api", 600),
+                task("worker-test", "Python", "python", &["-m", "pytest"], "worker", 300),
+                task("web-test", "Node.js", "npx", &["jest"], "web", 300),
+            ],
+            &[
+                ("api/**", &["api-test"]),
+                ("worker/**", &["worker-test"]),
+                ("web/**", &["web-test"]),
+            ],
+        ),
+        Template::RustPythonBridge => (
+            &[
+                ("core/Cargo.toml", RUST_CDYLIB_MANIFEST),
+                ("core/src/lib.rs", RUST_BRIDGE_LIB),
+                ("bindings/pyproject.toml", PYTHON_PYPROJECT),
+                ("bindings/{module}.py", PYTHON_BINDINGS),
+                ("bindings/tests/test_bindings.py", PYTHON_BINDINGS_TEST),
+            ],
+            vec![task("core-build", "Rust", "cargo", &["build", "--release"], "core", 600)],
+            vec![
+                task("core-test", "Rust", "cargo", &["test"], "core", 600),
+                task("core-build", "Rust", "cargo", &["build", "--release"], "core", 600),
+                task("bindings-test", "Python", "python", &["-m", "pytest"], "bindings", 300),
+            ],
+            &[
+                ("core/**", &["core-test", "core-build", "bindings-test"]),
+                ("bindings/**", &["bindings-test"]),
+            ],
+        ),
+        Template::WasmApp => (
+            &[
+                ("wasm/Cargo.toml", RUST_CDYLIB_MANIFEST),
+                ("wasm/src/lib.rs", RUST_WASM_LIB),
+                ("web/package.json", NODE_PACKAGE),
+                ("web/index.js", NODE_WASM_INDEX),
+                ("web/index.test.js", NODE_WASM_INDEX_TEST),
+            ],
+            vec![
+                wasm_build.clone(),
+                task("web-install", "Node.js", "npm", &["install"], "web", 300),
+            ],
+            vec![
+                task("wasm-test", "Rust", "cargo", &["test"], "wasm", 600),
+                wasm_build,
+                task("web-test", "Node.js", "npx", &["jest"], "web", 300),
+            ],
+            &[("wasm/**", &["wasm-test", "wasm-build", "web-test"]), ("web/**", &["web-test"])],
+        ),
+    };
+
+    // Builds of different languages are independent. Tests run one at a time, so suites that
+    // load a Rust library run after the step building it
+    let workflows = [
+        MultiLanguageWorkflow {
+            name: format!("{} build", name),
+            tasks: build,
+            concurrent: true,
+            watch: WatchConfig::default(),
+            track_changes: false,
+            upstream_outputs: Default::default(),
+        },
+        MultiLanguageWorkflow {
+            name: format!("{} test", name),
+            tasks: test,
+            concurrent: false,
+            watch: WatchConfig {
+                rules: rules
+                    .iter()
+                    .map(|(pattern, tasks)| WatchRule {
+                        pattern: pattern.to_string(),
+                        tasks: tasks.iter().map(|task| task.to_string()).collect(),
+                    })
+                    .collect(),
+                ..Default::default()
+            },
+            track_changes: false,
+            upstream_outputs: Default::default(),
+        },
+    ];
+
+    let mut files: Vec<ScaffoldFile> = sources
+        .iter()
+        .map(|(path, content)| ScaffoldFile { path: fill(path).into(), content: fill(content) })
+        .collect();
+    for (file, workflow) in ["build.json", "test.json"].iter().zip(&workflows) {
+        files.push(ScaffoldFile {
+            path: Path::new(WORKFLOW_DIR).join(file),
+            content: serde_json::to_string_pretty(workflow)? + "\n",
+        });
+    }
+    files.push(ScaffoldFile { path: "parflow.toml".into(), content: fill(PARFLOW_TOML) });
+    files.push(ScaffoldFile { path: ".gitignore".into(), content: GITIGNORE.to_string() });
+    Ok(files)
+}
+
+/// Write the skeleton of `template` into `dir`, named after the directory unless `name` is
+/// given. Returns the files written.
+pub fn init(
+    dir: &Path,
+    template: Template,
+    name: Option<&str>,
+    force: bool,
+) -> Result<Vec<PathBuf>> {
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => std::path::absolute(dir)?
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "parflow-project".to_string()),
+    };
+    let files = scaffold(template, &name)?;
+
+    if !force {
+        let existing: Vec<String> = files
+            .iter()
+            .filter(|file| dir.join(&file.path).exists())
+            .map(|file| file.path.display().to_string())
+            .collect();
+        if !existing.is_empty() {
+            bail!("{} already exist; pass --force to overwrite", existing.join(", "));
+        }
+    }
+
+    let mut written = Vec::new();
+    for file in files {
+        let path = dir.join(&file.path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("creating {}", parent.display()))?;
+        }
+        std::fs::write(&path, file.content)
+            .with_context(|| format!("writing {}", path.display()))?;
+        written.push(file.path);
+    }
+    Ok(written)
+}
+
+/// `name` as a Cargo package name: lowercase ASCII, digits and dashes, starting with a letter
+fn crate_name(name: &str) -> String {
+    let mut crate_name = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            crate_name.push(c.to_ascii_lowercase());
+        } else if !crate_name.is_empty() && !crate_name.ends_with('-') {
+            crate_name.push('-');
+        }
+    }
+    let crate_name = crate_name.trim_end_matches('-');
+    if crate_name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        crate_name.to_string()
+    } else {
+        format!("app-{}", crate_name).trim_end_matches('-').to_string()
+    }
+}
+
+fn task(
+    name: &str,
+    language: &str,
+    command: &str,
+    args: &[&str],
+    dir: &str,
+    timeout: u64,
+) -> LanguageTask {
+    LanguageTask {
+        name: Some(name.to_string()),
+        language: language.to_string(),
+        command: command.to_string(),
+        args: args.iter().map(|arg| arg.to_string()).collect(),
+        working_dir: Some(dir.to_string()),
+        timeout_seconds: Some(timeout),
+        depends_on: Vec::new(),
+        image: None,
+        resources: None,
+        outputs: Vec::new(),
+        inputs_from: Vec::new(),
+        env: Default::default(),
+        policy: Default::default(),
+    }
+}
+
+const PARFLOW_TOML: &str = r#"# ParFlow project config for {name}
+# Build and test workflows are in .parflow/workflows
+
+[orchestrator]
+default_timeout_secs = 600
+"#;
+
+const GITIGNORE: &str = "target/
+node_modules/
+__pycache__/
+.pytest_cache/
+.parflow/artifacts/
+.parflow/cache/
+.parflow/run/
+";
+
+const RUST_BIN_MANIFEST: &str = r#"[package]
+name = "{crate}"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+"#;
+
+const RUST_API_MAIN: &str = r##"use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+
+/// Body of the response to `path`
+fn respond(path: &str) -> (u16, String) {
+    match path {
+        "/health" => (200, r#"{"status":"ok"}"#.to_string()),
+        _ => (404, r#"{"error":"not found"}"#.to_string()),
+    }
+}
+
+fn main() -> std::io::Result<()> {
+    let listener = TcpListener::bind("127.0.0.1:8000")?;
+    println!("{name} api listening on http://127.0.0.1:8000");
+    for stream in listener.incoming() {
+        let mut stream = stream?;
+        let mut request = String::new();
+        BufReader::new(&stream).read_line(&mut request)?;
+        let path = request.split_whitespace().nth(1).unwrap_or("/");
+        let (status, body) = respond(path);
+        write!(
+            stream,
+            "HTTP/1.1 {} OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn health_is_ok() {
+        assert_eq!(respond("/health").0, 200);
+        assert_eq!(respond("/missing").0, 404);
+    }
+}
+"##;
+
+const RUST_CDYLIB_MANIFEST: &str = r#"[package]
+name = "{crate}"
+version = "0.1.0"
+edition = "2021"
+
+[lib]
+crate-type = ["cdylib", "rlib"]
+
+[dependencies]
+"#;
+
+const RUST_BRIDGE_LIB: &str = r#"/// Sum of `len` doubles at `values`, callable from Python through ctypes
+///
+/// # Safety
+/// `values` must point to `len` initialized doubles.
+#[no_mangle]
+pub unsafe extern "C" fn sum(values: *const f64, len: usize) -> f64 {
+    if values.is_null() {
+        return 0.0;
+    }
+    std::slice::from_raw_parts(values, len).iter().sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sums_values() {
+        let values = [1.0, 2.5, 3.5];
+        assert_eq!(unsafe { sum(values.as_ptr(), values.len()) }, 7.0);
+    }
+}
+"#;
+
+const RUST_WASM_LIB: &str = r#"/// Exported to JavaScript as `fibonacci`
+#[no_mangle]
+pub extern "C" fn fibonacci(n: u32) -> u64 {
+    let (mut a, mut b) = (0u64, 1u64);
+    for _ in 0..n {
+        (a, b) = (b, a.wrapping_add(b));
+    }
+    a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computes_fibonacci() {
+        assert_eq!(fibonacci(0), 0);
+        assert_eq!(fibonacci(10), 55);
+    }
+}
+"#;
+
+const PYTHON_PYPROJECT: &str = r#"[project]
+name = "{module}"
+version = "0.1.0"
+requires-python = ">=3.8"
+
+[tool.pytest.ini_options]
+testpaths = ["tests"]
+pythonpath = ["."]
+"#;
+
+const PYTHON_WORKER: &str = r#""""Background jobs of {name}"""
+
+
+def process(job):
+    """Normalize the words of a job's text"""
+    return {"id": job["id"], "words": [word.lower() for word in job["text"].split()]}
+
+
+if __name__ == "__main__":
+    print(process({"id": 1, "text": "Hello from the {name} worker"}))
+"#;
+
+const PYTHON_WORKER_TEST: &str = r#"from {module}_worker import process
+
+
+def test_process_lowercases_words():
+    assert process({"id": 7, "text": "Hello World"}) == {"id": 7, "words": ["hello", "world"]}
+"#;
+
+const PYTHON_BINDINGS: &str = r#""""Python bindings of the {crate} Rust library"""
+
+import ctypes
+import pathlib
+import sys
+
+_SUFFIX = {"darwin": ".dylib", "win32": ".dll"}.get(sys.platform, ".so")
+_PREFIX = "" if sys.platform == "win32" else "lib"
+LIBRARY = (
+    pathlib.Path(__file__).resolve().parent.parent
+    / "core" / "target" / "release" / f"{_PREFIX}{module}{_SUFFIX}"
+)
+
+_lib = ctypes.CDLL(str(LIBRARY))
+_lib.sum.argtypes = [ctypes.POINTER(ctypes.c_double), ctypes.c_size_t]
+_lib.sum.restype = ctypes.c_double
+
+
+def total(values):
+    """Sum `values` in Rust"""
+    array = (ctypes.c_double * len(values))(*values)
+    return _lib.sum(array, len(values))
+"#;
+
+const PYTHON_BINDINGS_TEST: &str = r#"import {module}
+
+
+def test_total_calls_rust():
+    assert {module}.total([1.0, 2.5, 3.5]) == 7.0
+"#;
+
+const NODE_PACKAGE: &str = r#"{
+  "name": "{crate}-web",
+  "version": "0.1.0",
+  "private": true,
+  "main": "index.js",
+  "scripts": {
+    "start": "node index.js",
+    "test": "jest"
+  },
+  "devDependencies": {
+    "jest": "^29.7.0"
+  }
+}
+"#;
+
+const NODE_INDEX: &str = r#"const API = process.env.API_URL || "http://127.0.0.1:8000";
+
+async function health() {
+  const response = await fetch(`${API}/health`);
+  return response.json();
+}
+
+function greeting(who) {
+  return `Hello from {name}, ${who}!`;
+}
+
+if (require.main === module) {
+  health().then(console.log, (e) => console.error(`api unreachable: ${e.message}`));
+}
+
+module.exports = { greeting, health };
+"#;
+
+const NODE_INDEX_TEST: &str = r#"const { greeting } = require("./index");
+
+test("greets by name", () => {
+  expect(greeting("Ada")).toBe("Hello from {name}, Ada!");
+});
+"#;
+
+const NODE_WASM_INDEX: &str = r#"const fs = require("fs");
+const path = require("path");
+
+const WASM = path.join(
+  __dirname, "..", "wasm", "target", "wasm32-unknown-unknown", "release", "{module}.wasm"
+);
+
+async function load() {
+  const { instance } = await WebAssembly.instantiate(fs.readFileSync(WASM));
+  return instance.exports;
+}
+
+if (require.main === module) {
+  load().then((wasm) => console.log(`fibonacci(50) = ${wasm.fibonacci(50)}`));
+}
+
+module.exports = { load };
+"#;
+
+const NODE_WASM_INDEX_TEST: &str = r#"const { load } = require("./index");
+
+test("calls into WebAssembly", async () => {
+  const wasm = await load();
+  expect(wasm.fibonacci(10)).toBe(55n);
+});
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_writes_templates_without_clobbering() {
+        assert_eq!(crate_name("My Service_2"), "my-service-2");
+        assert_eq!(crate_name("2fast"), "app-2fast");
+        assert!("rails-app".parse::<Template>().is_err());
+
+        let dir = std::env::temp_dir().join(format!("parflow-init-{}", std::process::id()));
+        for template in Template::ALL {
+            let project = dir.join(template.name());
+            let written = init(&project, template, Some("Demo App"), false).unwrap();
+            assert!(written.contains(&PathBuf::from("parflow.toml")));
+
+            let config = project.join("parflow.toml");
+            parflow_core::config::ParflowConfig::load_from(&[config], &[]).unwrap();
+            for workflow in ["build.json", "test.json"] {
+                let json =
+                    std::fs::read_to_string(project.join(WORKFLOW_DIR).join(workflow)).unwrap();
+                let workflow: MultiLanguageWorkflow = serde_json::from_str(&json).unwrap();
+                parflow_orchestrator::watch::WatchPlan::new(&workflow).unwrap();
+                assert!(workflow
+                    .tasks
+                    .iter()
+                    .all(|task| { project.join(task.working_dir.as_deref().unwrap()).is_dir() }));
+            }
+            assert!(!written.iter().any(|path| path.to_string_lossy().contains('{')));
+
+            assert!(init(&project, template, None, false).is_err());
+            assert!(init(&project, template, None, true).is_ok());
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,85 @@
+use colored::*;
+use parflow_crate_orchestrator::licenses::{
+    self, LicensePolicy, LicenseReport, LicenseSource, Severity,
+};
+use std::path::Path;
+
+pub struct LicenseCheckArgs {
+    pub path: String,
+    pub policy: Option<String>,
+    pub offline: bool,
+    pub format: String,
+}
+
+/// Check the dependencies under `path` against the policy and print the findings; `Ok(false)`
+/// when any dependency violates it.
+pub async fn run(args: LicenseCheckArgs) -> anyhow::Result<bool> {
+    let root = Path::new(&args.path);
+    let policy = match &args.policy {
+        Some(file) => LicensePolicy::from_toml_file(Path::new(file))?,
+        None if root.join(licenses::DEFAULT_POLICY).is_file() => {
+            LicensePolicy::from_toml_file(&root.join(licenses::DEFAULT_POLICY))?
+        }
+        None => LicensePolicy::default(),
+    };
+    let report = licenses::check(root, &policy, args.offline).await?;
+    if args.format == "json" {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(&report);
+    }
+    Ok(report.passed())
+}
+
+fn print_report(report: &LicenseReport) {
+    println!("\n{}", "⚖️  LICENSE CHECK".bright_blue().bold());
+    if report.lockfiles.is_empty() {
+        println!(
+            "{}",
+            "No Cargo.lock, package-lock.json, poetry.lock or requirements.txt found".yellow()
+        );
+        return;
+    }
+    let count = |source: Option<LicenseSource>| {
+        report.dependencies.iter().filter(|dependency| dependency.source == source).count()
+    };
+    println!("{}: {}", "Lockfiles".bright_cyan(), report.lockfiles.join(", "));
+    println!(
+        "{}: {} ({} from local metadata, {} from registries, {} unknown)",
+        "Dependencies".bright_cyan(),
+        report.dependencies.len(),
+        count(Some(LicenseSource::Local)),
+        count(Some(LicenseSource::Registry)),
+        count(None)
+    );
+
+    for (severity, title) in [
+        (Severity::Violation, "🚫 VIOLATIONS".bright_red()),
+        (Severity::Warning, "⚠️  WARNINGS".bright_yellow()),
+    ] {
+        let findings: Vec<_> =
+            report.findings.iter().filter(|finding| finding.severity == severity).collect();
+        if findings.is_empty() {
+            continue;
+        }
+        println!("\n{}", title.bold());
+        for finding in findings {
+            println!(
+                "  • {} {} ({}) {} {}",
+                finding.package.name.bright_white(),
+                finding.package.version,
+                finding.package.ecosystem,
+                finding.license.as_deref().unwrap_or("?").bright_yellow(),
+                format!("— {}", finding.reason).dimmed()
+            );
+        }
+    }
+
+    if report.passed() {
+        println!("\n{}", "✅ Every dependency complies with the license policy".bright_green());
+    } else {
+        let violations =
+            report.findings.iter().filter(|f| f.severity == Severity::Violation).count();
+        println!("\n{} {} dependencies violate the license policy", "❌".bright_red(), violations);
+    }
+}
@@ -0,0 +1,33 @@
+use colored::*;
+use semantic_compiler::lints::{Diagnostic, Linter, Severity};
+use std::path::Path;
+
+/// Lint diagnostics under `path`; an unreadable path has none
+pub fn find(path: &str) -> Vec<Diagnostic> {
+    Linter::default().lint_directory(Path::new(path)).unwrap_or_default()
+}
+
+pub fn print(diagnostics: &[Diagnostic]) {
+    println!("\n{}", "🚨 LINTS".bright_red().bold());
+    if diagnostics.is_empty() {
+        println!("  {}", "No lint findings".bright_white());
+        return;
+    }
+    for diagnostic in diagnostics {
+        let severity = match diagnostic.severity {
+            Severity::Error => "error".bright_red(),
+            Severity::Warning => "warning".bright_yellow(),
+            Severity::Info => "info".bright_blue(),
+        };
+        println!(
+            "  {} {} {}: {}",
+            format!("{}:{}", diagnostic.path, diagnostic.line).bright_cyan(),
+            severity,
+            diagnostic.rule.bright_white().bold(),
+            diagnostic.message
+        );
+        if let Some(fix) = &diagnostic.fix {
+            println!("     {} {}", "→".bright_green(), fix);
+        }
+    }
+}
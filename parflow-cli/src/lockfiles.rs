@@ -0,0 +1,65 @@
+use colored::*;
+use parflow_crate_orchestrator::lockfiles::{self, LockfileReport};
+use std::path::Path;
+
+/// Analyze the lockfiles under `path` and print the report as text or JSON.
+pub fn run(path: &str, format: &str) -> anyhow::Result<()> {
+    let report = lockfiles::analyze(Path::new(path))?;
+    if format == "json" {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(&report);
+    }
+    Ok(())
+}
+
+fn print_report(report: &LockfileReport) {
+    println!("\n{}", "🔒 LOCKFILE UNIFICATION".bright_blue().bold());
+    if report.lockfiles.is_empty() {
+        println!(
+            "{}",
+            "No Cargo.lock, package-lock.json, poetry.lock or requirements.txt found".yellow()
+        );
+        return;
+    }
+    println!(
+        "{}: {} ({} packages)",
+        "Lockfiles".bright_cyan(),
+        report.lockfiles.join(", "),
+        report.packages
+    );
+
+    if !report.conflicts.is_empty() {
+        println!("\n{}", "⚔️  VERSION CONFLICTS".bright_red().bold());
+        for conflict in &report.conflicts {
+            println!("  • {}", conflict.library.bright_white().bold());
+            for pin in &conflict.pins {
+                println!(
+                    "      {} {} {} → {} {}",
+                    pin.package.ecosystem.to_string().bright_yellow(),
+                    pin.package.name,
+                    pin.package.version,
+                    pin.library_version.bright_white(),
+                    format!("({})", pin.package.lockfile).dimmed()
+                );
+            }
+            println!("    {} {}", "💡".bright_green(), conflict.suggestion);
+        }
+    }
+
+    if !report.duplicates.is_empty() {
+        println!("\n{}", "🔁 DUPLICATE FUNCTIONALITY".bright_yellow().bold());
+        for duplicate in &report.duplicates {
+            let scope = match duplicate.ecosystem {
+                Some(ecosystem) => ecosystem.to_string(),
+                None => "across ecosystems".to_string(),
+            };
+            println!("  • {} [{}]", duplicate.functionality.bright_white().bold(), scope);
+            println!("    {} {}", "💡".bright_green(), duplicate.suggestion);
+        }
+    }
+
+    if report.conflicts.is_empty() && report.duplicates.is_empty() {
+        println!("{}", "✅ No conflicting or duplicated libraries".bright_green());
+    }
+}
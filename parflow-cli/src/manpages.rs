@@ -0,0 +1,26 @@
+use clap::Command;
+use std::path::Path;
+
+/// Write `parflow.1` plus a `parflow-<subcommand>.1` page per subcommand, returning the count.
+pub fn generate(command: Command, out_dir: &Path) -> std::io::Result<usize> {
+    std::fs::create_dir_all(out_dir)?;
+    let command = command.name("parflow");
+    let version = command.get_version().unwrap_or_default().to_string();
+    let mut pages = 0;
+
+    for subcommand in command.get_subcommands().filter(|s| !s.is_hide_set()) {
+        let name = format!("parflow-{}", subcommand.get_name());
+        let page = subcommand.clone().name(name.clone()).version(version.clone());
+        write_page(page, &out_dir.join(format!("{}.1", name)))?;
+        pages += 1;
+    }
+
+    write_page(command, &out_dir.join("parflow.1"))?;
+    Ok(pages + 1)
+}
+
+fn write_page(command: Command, path: &Path) -> std::io::Result<()> {
+    let mut buffer = Vec::new();
+    clap_mangen::Man::new(command).render(&mut buffer)?;
+    std::fs::write(path, buffer)
+}
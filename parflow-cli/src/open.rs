@@ -0,0 +1,93 @@
+use colored::*;
+use parflow_findings::links::{Location, SCHEME};
+use std::path::PathBuf;
+
+/// Editor command template; `{file}`, `{line}` and `{col}` are substituted,
+/// e.g. `code --goto {file}:{line}:{col}` or `idea --line {line} {file}`.
+pub const EDITOR_ENV: &str = "PARFLOW_EDITOR";
+
+fn editor_command(location: &Location) -> Option<Vec<String>> {
+    let line = location.line.unwrap_or(1).to_string();
+    let col = location.column.unwrap_or(1).to_string();
+
+    if let Ok(template) = std::env::var(EDITOR_ENV) {
+        let args: Vec<String> = template
+            .split_whitespace()
+            .map(|arg| {
+                arg.replace("{file}", &location.file)
+                    .replace("{line}", &line)
+                    .replace("{col}", &col)
+            })
+            .collect();
+        return (!args.is_empty()).then_some(args);
+    }
+
+    // vi, emacs, nano and most terminal editors accept `+LINE FILE`
+    let editor = std::env::var("VISUAL").or_else(|_| std::env::var("EDITOR")).ok()?;
+    let mut args: Vec<String> = editor.split_whitespace().map(String::from).collect();
+    if location.line.is_some() {
+        args.push(format!("+{}", line));
+    }
+    args.push(location.file.clone());
+    Some(args)
+}
+
+/// Open `location` in the user's editor, falling back to the desktop's default application.
+pub fn open_local(location: &Location) -> std::io::Result<()> {
+    let args = editor_command(location).unwrap_or_else(|| {
+        let opener = if cfg!(target_os = "macos") { "open" } else { "xdg-open" };
+        vec![opener.to_string(), location.file.clone()]
+    });
+
+    println!("{} {}", "📂 Opening with".bright_blue(), args.join(" ").bright_cyan());
+    let status = std::process::Command::new(&args[0]).args(&args[1..]).status()?;
+    if !status.success() {
+        println!("{} {} exited with {}", "❌".bright_red(), args[0], status);
+    }
+    Ok(())
+}
+
+fn applications_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .map(|dir| dir.join("applications"))
+}
+
+/// Register this binary as the desktop handler for `parflow://` links (freedesktop only).
+pub fn register_handler() -> anyhow::Result<()> {
+    if !cfg!(target_os = "linux") {
+        anyhow::bail!(
+            "automatic registration is only supported on Linux; \
+             point your OS's {}:// handler at `parflow open %u`",
+            SCHEME
+        );
+    }
+
+    let dir = applications_dir().ok_or_else(|| anyhow::anyhow!("HOME is not set"))?;
+    std::fs::create_dir_all(&dir)?;
+    let desktop_file = "parflow-url-handler.desktop";
+    let exe = std::env::current_exe()?;
+    std::fs::write(
+        dir.join(desktop_file),
+        format!(
+            "[Desktop Entry]\nType=Application\nName=ParFlow\nExec=\"{}\" open %u\n\
+             NoDisplay=true\nMimeType=x-scheme-handler/{};\n",
+            exe.display(),
+            SCHEME
+        ),
+    )?;
+
+    let status = std::process::Command::new("xdg-mime")
+        .args(["default", desktop_file, &format!("x-scheme-handler/{}", SCHEME)])
+        .status()?;
+    if !status.success() {
+        anyhow::bail!("xdg-mime exited with {}", status);
+    }
+    println!(
+        "{} {}",
+        "✅ Registered parflow:// handler:".bright_green(),
+        dir.join(desktop_file).display()
+    );
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use colored::*;
+use semantic_compiler::ownership::{MigrationReviewers, OwnershipMap, OwnershipRisk};
+
+pub fn print_report(map: &OwnershipMap) {
+    println!("\n{}", "👥 MODULE OWNERSHIP".bright_blue().bold());
+    for module in &map.modules {
+        let owners: Vec<String> = module
+            .owners
+            .iter()
+            .take(3)
+            .map(|o| format!("{} {:.0}%", o.email, o.share * 100.0))
+            .collect();
+        println!(
+            "  • {} [{}] bus factor {}: {}",
+            module.component.bright_white(),
+            module.language.bright_yellow(),
+            module.bus_factor,
+            owners.join(", ")
+        );
+    }
+
+    if !map.patterns.is_empty() {
+        println!("\n{}", "🧠 PATTERN EXPERTISE".bright_magenta().bold());
+        for pattern in &map.patterns {
+            let owners: Vec<&str> =
+                pattern.owners.iter().take(3).map(|o| o.email.as_str()).collect();
+            println!(
+                "  • {} [{}]: {}",
+                pattern.component.bright_white(),
+                pattern.language.bright_yellow(),
+                owners.join(", ")
+            );
+        }
+    }
+
+    let findings = map.bus_factor_report();
+    if findings.is_empty() {
+        println!("\n{}", "✅ No bus-factor risks found".bright_green());
+        return;
+    }
+    println!("\n{}", "⚠️  BUS-FACTOR REPORT".bright_yellow().bold());
+    for finding in findings {
+        let label = match finding.risk {
+            OwnershipRisk::Orphaned => "ORPHANED".bright_red().bold(),
+            OwnershipRisk::SingleOwner => "SINGLE OWNER".bright_yellow(),
+        };
+        println!(
+            "  • {} {} [{}] ({})",
+            label,
+            finding.component,
+            finding.language,
+            finding.primary_owners.join(", ")
+        );
+    }
+}
+
+pub fn print_reviewers(reviewers: &MigrationReviewers) {
+    let or_none = |people: &[String]| {
+        if people.is_empty() {
+            "nobody active".bright_red().to_string()
+        } else {
+            people.join(", ")
+        }
+    };
+    println!(
+        "  • {} {} → {}",
+        reviewers.pattern.bright_white(),
+        reviewers.current_language.bright_yellow(),
+        reviewers.suggested_language.bright_green()
+    );
+    println!("      source owners: {}", or_none(&reviewers.source_owners));
+    println!("      target experts: {}", or_none(&reviewers.target_experts));
+    if reviewers.orphaned {
+        println!("      {}", "⚠️  original owners have left - review carefully".bright_red());
+    }
+}
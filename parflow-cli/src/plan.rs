@@ -0,0 +1,315 @@
+//! `parflow run --plan`: what a run would do, without running anything
+//!
+//! The plan lists the tasks in the order they start, grouped into waves of tasks that can run
+//! side by side, with the toolchain each one runs on and the environment variables it gets on
+//! top of the current environment. Durations are estimated from the recorded runs of the same
+//! workflow (see `parflow history`), averaging the last successful runs of each task.
+
+use crate::history::{self, WorkflowRun};
+use anyhow::Result;
+use colored::*;
+use parflow_core::toolchains::{Runtime, Toolchains};
+use parflow_findings::FindingsDb;
+use parflow_orchestrator::{staging, JobQueue, MultiLanguageWorkflow, TaskGraph};
+use std::collections::HashMap;
+
+/// Successful runs of a task averaged into its estimate
+const ESTIMATE_RUNS: usize = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvChange {
+    pub name: String,
+    /// The current value, if the variable is set
+    pub before: Option<String>,
+    pub after: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct PlannedTask {
+    pub name: String,
+    pub language: String,
+    pub command: String,
+    pub working_dir: Option<String>,
+    /// Tasks of the wave before run first; tasks of one wave may run side by side
+    pub wave: usize,
+    pub needs: Vec<String>,
+    /// Average duration of the task's last successful runs
+    pub estimate_ms: Option<u128>,
+    /// Container image, or else the detected toolchain, e.g. `rustc 1.80.0`
+    pub runs_on: Option<String>,
+    pub env: Vec<EnvChange>,
+    /// Succeeded in the run being resumed, so it will not run again
+    pub done: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Plan {
+    pub workflow: String,
+    pub concurrent: bool,
+    pub tasks: Vec<PlannedTask>,
+    /// Wall time along the critical path, counting tasks without estimates as instant
+    pub estimated_ms: u128,
+    pub critical_path: Vec<String>,
+}
+
+/// Plan `workflow` from the recorded `runs`, skipping the tasks at `done`. `runs_on` names the
+/// toolchain of a language, and `env` looks up the current value of a variable.
+pub fn plan(
+    workflow: &MultiLanguageWorkflow,
+    done: &[usize],
+    runs: &[WorkflowRun],
+    mut runs_on: impl FnMut(&str) -> Option<String>,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<Plan> {
+    let graph = TaskGraph::new(workflow)?;
+    let mut order = graph.order();
+    let mut waves = vec![0; graph.tasks.len()];
+    for &index in &order {
+        let wave = graph.dependencies[index].iter().map(|&d| waves[d] + 1).max();
+        waves[index] = wave.unwrap_or(0);
+    }
+    order.sort_by_key(|&index| waves[index]);
+
+    let mut estimates = Vec::with_capacity(graph.tasks.len());
+    for name in &graph.tasks {
+        let durations: Vec<u128> = runs
+            .iter()
+            .rev()
+            .filter(|run| run.workflow == workflow.name)
+            .filter_map(|run| run.tasks.iter().find(|task| task.name == *name && task.success))
+            .map(|task| task.duration_ms)
+            .take(ESTIMATE_RUNS)
+            .collect();
+        let estimate = match durations.len() {
+            0 => None,
+            count => Some(durations.iter().sum::<u128>() / count as u128),
+        };
+        estimates.push(estimate);
+    }
+
+    let mut tasks = Vec::with_capacity(order.len());
+    for &index in &order {
+        let task = &workflow.tasks[index];
+        let mut variables = task.env.clone();
+        for upstream in &task.inputs_from {
+            variables.insert(
+                staging::env_var(upstream),
+                format!("{}/{}", staging::INPUTS_DIR, upstream),
+            );
+        }
+        let env = variables
+            .into_iter()
+            .map(|(name, after)| EnvChange { before: env(&name), name, after })
+            .filter(|change| change.before.as_ref() != Some(&change.after))
+            .collect();
+        tasks.push(PlannedTask {
+            name: graph.tasks[index].clone(),
+            language: task.language.clone(),
+            command: std::iter::once(&task.command)
+                .chain(&task.args)
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(" "),
+            working_dir: task.working_dir.clone(),
+            wave: waves[index],
+            needs: graph.dependencies[index].iter().map(|&d| graph.tasks[d].clone()).collect(),
+            estimate_ms: estimates[index],
+            runs_on: match &task.image {
+                Some(image) => Some(format!("container {}", image)),
+                None => runs_on(&task.language),
+            },
+            env,
+            done: done.contains(&index),
+        });
+    }
+
+    // Tasks that already succeeded take no time
+    let durations: Vec<u128> = (0..graph.tasks.len())
+        .map(|index| if done.contains(&index) { 0 } else { estimates[index].unwrap_or(0) })
+        .collect();
+    let path = graph.critical_path(&durations);
+    Ok(Plan {
+        workflow: workflow.name.clone(),
+        concurrent: workflow.concurrent,
+        tasks,
+        estimated_ms: path.iter().map(|&index| durations[index]).sum(),
+        critical_path: path.iter().map(|&index| graph.tasks[index].clone()).collect(),
+    })
+}
+
+/// Print the plan of the workflow file `workflow`, or of the queued run `resume`.
+pub fn run(workflow: Option<&str>, resume: Option<&str>, queue: &str) -> Result<()> {
+    let (workflow, done) = match (workflow, resume) {
+        (_, Some(workflow_id)) => {
+            let queued = JobQueue::open(queue)?.load(workflow_id)?;
+            let remaining = queued.remaining();
+            let done = (0..queued.states.len()).filter(|i| !remaining.contains(i)).collect();
+            (queued.workflow, done)
+        }
+        (Some(path), None) => (MultiLanguageWorkflow::load(std::path::Path::new(path))?, vec![]),
+        (None, None) => unreachable!("clap requires --workflow or --resume"),
+    };
+    let runs: Vec<WorkflowRun> =
+        FindingsDb::open(FindingsDb::DEFAULT_DIR)?.history(history::RUNS_TABLE)?;
+
+    let toolchains = Toolchains::new();
+    let mut detected: HashMap<String, Option<String>> = HashMap::new();
+    let runs_on = |language: &str| {
+        detected
+            .entry(language.to_string())
+            .or_insert_with(|| detect(&toolchains, language))
+            .clone()
+    };
+    let plan = plan(&workflow, &done, &runs, runs_on, |name| std::env::var(name).ok())?;
+    print_plan(&plan);
+    Ok(())
+}
+
+/// The toolchain tasks of `language` run on, or why there is none
+fn detect(toolchains: &Toolchains, language: &str) -> Option<String> {
+    let name: String = language.chars().filter(char::is_ascii_alphanumeric).collect();
+    let runtime: Runtime = name.parse().ok()?;
+    Some(match toolchains.detect(runtime) {
+        Some(installed) => format!("{} {}", runtime, installed.version),
+        None => format!("{} missing", runtime),
+    })
+}
+
+fn print_plan(plan: &Plan) {
+    println!(
+        "\n{} {} {}",
+        "🗺️  PLAN".bright_blue().bold(),
+        plan.workflow.bright_white().bold(),
+        format!(
+            "({}, {} tasks)",
+            if plan.concurrent { "concurrent" } else { "sequential" },
+            plan.tasks.len()
+        )
+        .dimmed()
+    );
+    let mut wave = None;
+    for task in &plan.tasks {
+        if plan.concurrent && wave != Some(task.wave) {
+            wave = Some(task.wave);
+            println!("\n  {} {}", "Wave".bright_cyan(), task.wave + 1);
+        }
+        let estimate = match task.estimate_ms {
+            Some(ms) => format!("~{}ms", ms),
+            None => "no history".to_string(),
+        };
+        let status = if task.done { "✅" } else { "▶️ " };
+        println!(
+            "  {} {} [{}] {} {}",
+            status,
+            task.name.bright_white(),
+            task.language,
+            task.command.bright_yellow(),
+            estimate.dimmed()
+        );
+        if task.done {
+            println!("      {}", "succeeded before, skipped on resume".dimmed());
+        }
+        if let Some(dir) = &task.working_dir {
+            println!("      {} {}", "in".dimmed(), dir);
+        }
+        if !task.needs.is_empty() {
+            println!("      {} {}", "after".dimmed(), task.needs.join(", "));
+        }
+        match &task.runs_on {
+            Some(runs_on) if runs_on.ends_with(" missing") => {
+                println!("      {} {}", "on".dimmed(), runs_on.bright_red())
+            }
+            Some(runs_on) => println!("      {} {}", "on".dimmed(), runs_on.bright_green()),
+            None => {}
+        }
+        for change in &task.env {
+            match &change.before {
+                Some(before) => {
+                    println!(
+                        "      ~ {}={} {}",
+                        change.name,
+                        change.after,
+                        format!("(was {})", before).dimmed()
+                    )
+                }
+                None => println!("      + {}={}", change.name, change.after),
+            }
+        }
+    }
+
+    let unestimated =
+        plan.tasks.iter().filter(|task| !task.done && task.estimate_ms.is_none()).count();
+    println!(
+        "\n{} ~{}ms along {}",
+        "⏱️  Estimated:".bright_cyan(),
+        plan.estimated_ms,
+        plan.critical_path.join(" → ")
+    );
+    if unestimated > 0 {
+        println!(
+            "{}",
+            format!("{} tasks without recorded runs are not counted", unestimated).yellow()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::TaskRun;
+
+    #[test]
+    fn test_plans_waves_estimates_and_environment() {
+        let workflow: MultiLanguageWorkflow = serde_json::from_str(
+            r#"{"name": "release", "concurrent": true, "tasks": [
+                {"name": "lib", "language": "Rust", "command": "cargo", "args": ["build"],
+                 "outputs": ["target/debug/lib.so"]},
+                {"name": "docs", "language": "Node.js", "command": "npm", "args": ["run", "docs"],
+                 "env": {"CI": "true"}},
+                {"name": "test", "language": "Python", "command": "pytest", "args": [],
+                 "inputs_from": ["lib"]}
+            ]}"#,
+        )
+        .unwrap();
+        let run = |duration_ms: u128, success: bool| WorkflowRun {
+            run_id: String::new(),
+            workflow: "release".to_string(),
+            workflow_id: None,
+            started_at: 0,
+            finished_at: 0,
+            cancelled: false,
+            logs: String::new(),
+            tasks: vec![TaskRun {
+                name: "lib".to_string(),
+                language: "Rust".to_string(),
+                success,
+                exit_code: None,
+                duration_ms,
+                changes: None,
+            }],
+        };
+        let runs = [run(300, true), run(9000, false), run(500, true)];
+        let runs_on = |language: &str| (language == "Rust").then(|| "rustc 1.80.0".to_string());
+        let env = |name: &str| (name == "CI").then(|| "1".to_string());
+
+        let plan = plan(&workflow, &[], &runs, runs_on, env).unwrap();
+        let names: Vec<&str> = plan.tasks.iter().map(|task| task.name.as_str()).collect();
+        assert_eq!(names, ["lib", "docs", "test"]);
+        let waves: Vec<usize> = plan.tasks.iter().map(|task| task.wave).collect();
+        assert_eq!(waves, [0, 0, 1]);
+        assert_eq!(plan.tasks[0].estimate_ms, Some(400));
+        assert_eq!(plan.tasks[0].runs_on.as_deref(), Some("rustc 1.80.0"));
+        assert_eq!(plan.tasks[2].needs, ["lib"]);
+        assert_eq!(
+            plan.tasks[1].env,
+            [EnvChange { name: "CI".into(), before: Some("1".into()), after: "true".into() }]
+        );
+        assert_eq!(plan.tasks[2].env[0].name, "PARFLOW_INPUTS_LIB");
+        assert_eq!(plan.estimated_ms, 400);
+        assert_eq!(plan.critical_path, ["lib"]);
+
+        let resumed = super::plan(&workflow, &[0], &runs, |_| None, |_| None).unwrap();
+        assert!(resumed.tasks[0].done);
+        assert_eq!(resumed.estimated_ms, 0);
+    }
+}
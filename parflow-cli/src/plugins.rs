@@ -0,0 +1,94 @@
+//! WebAssembly plugins: everything under `.parflow/plugins` is loaded at startup and registers
+//! its languages and transpile passes; `parflow analyze --plugin` adds more for one run.
+
+use colored::*;
+use parflow_plugins::{Finding, WasmPlugin};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Load and register the plugins under `.parflow/plugins`
+pub fn load_project() -> Vec<Arc<WasmPlugin>> {
+    register(parflow_plugins::load_dir(parflow_plugins::PLUGIN_DIR))
+}
+
+/// Load and register plugins given on the command line
+pub fn load_files(paths: &[String]) -> Vec<Arc<WasmPlugin>> {
+    register(paths.iter().map(|path| WasmPlugin::load(path).map(Arc::new)).collect())
+}
+
+/// Register the plugins that loaded, reporting the ones that did not
+fn register(results: Vec<anyhow::Result<Arc<WasmPlugin>>>) -> Vec<Arc<WasmPlugin>> {
+    let mut plugins = Vec::new();
+    for result in results {
+        match result {
+            Ok(plugin) => {
+                plugin.register();
+                plugins.push(plugin);
+            }
+            Err(e) => println!("{} {:#}", "⚠️  Skipping plugin:".bright_yellow(), e),
+        }
+    }
+    plugins
+}
+
+fn source_files(dir: &Path, out: &mut Vec<PathBuf>) {
+    for path in std::fs::read_dir(dir).into_iter().flatten().flatten().map(|entry| entry.path()) {
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        if path.is_dir() {
+            if !name.starts_with('.') && name != "target" && name != "node_modules" {
+                source_files(&path, out);
+            }
+        } else {
+            out.push(path);
+        }
+    }
+}
+
+/// Findings of every plugin analyzer over the files under `path`
+pub fn analyze(plugins: &[Arc<WasmPlugin>], path: &str) -> Vec<Finding> {
+    if plugins.is_empty() {
+        return Vec::new();
+    }
+    let mut files = Vec::new();
+    source_files(Path::new(path), &mut files);
+    files.sort();
+    let mut findings = Vec::new();
+    for file in files {
+        let name = file.display().to_string();
+        let Ok(code) = std::fs::read_to_string(&file) else {
+            continue;
+        };
+        for result in parflow_plugins::analyze_file(plugins, &name, &code) {
+            match result {
+                Ok(found) => findings.extend(found),
+                Err(e) => println!("{} {}: {:#}", "⚠️  Plugin failed on".bright_yellow(), name, e),
+            }
+        }
+    }
+    findings
+}
+
+pub fn print(plugins: &[Arc<WasmPlugin>], findings: &[Finding]) {
+    if plugins.is_empty() {
+        return;
+    }
+    let names: Vec<&str> = plugins.iter().map(|plugin| plugin.name()).collect();
+    println!("\n{} ({})", "🧩 PLUGIN FINDINGS".bright_magenta().bold(), names.join(", "));
+    if findings.is_empty() {
+        println!("  {}", "No findings".bright_white());
+    }
+    for finding in findings {
+        let severity = match finding.severity.as_str() {
+            "error" => finding.severity.bright_red(),
+            "info" => finding.severity.bright_blue(),
+            _ => finding.severity.bright_yellow(),
+        };
+        println!(
+            "  {} {} {}",
+            format!("{}:{}", finding.path, finding.line).bright_cyan(),
+            severity,
+            finding.rule.bright_white().bold()
+        );
+        println!("     {} {}", "→".bright_green(), finding.message);
+    }
+}
@@ -0,0 +1,260 @@
+//! Checks that the subsystems a command needs are present before it runs.
+//!
+//! [`requirements`] is the degradation matrix: for each command, what it cannot run without and
+//! what it merely works worse without. A missing required subsystem stops the command with a
+//! [`PreflightError`] listing the exact command that fixes each gap; a missing optional one is
+//! reported as a warning saying what is lost.
+
+use crate::{capabilities, server, Commands};
+use colored::*;
+use parflow_core::config::ParflowConfig;
+use parflow_core::languages;
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Exit status of a command stopped by preflight checks
+pub const EXIT_CODE: i32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", content = "name", rename_all = "kebab-case")]
+pub enum Subsystem {
+    /// Executable on PATH
+    Tool(&'static str),
+    /// Server binary installed next to the CLI
+    Server(&'static str),
+    /// Cargo feature of parflow-cli
+    Feature(&'static str),
+    /// A live session hosted on this machine
+    LiveSession,
+}
+
+impl Subsystem {
+    pub fn available(&self) -> bool {
+        match self {
+            Subsystem::Tool(name) => on_path(name).is_some(),
+            Subsystem::Server(binary) => server::sibling_binary(binary).ok().flatten().is_some(),
+            Subsystem::Feature(feature) => capabilities::report().build.features.contains(feature),
+            Subsystem::LiveSession => !parflow_live_server::registry::list(Path::new(
+                parflow_live_server::registry::DEFAULT_DIR,
+            ))
+            .is_empty(),
+        }
+    }
+
+    fn reason(&self) -> String {
+        match self {
+            Subsystem::Tool(name) => format!("{} not found on PATH", name),
+            Subsystem::Server(binary) => format!("{} is not installed next to the CLI", binary),
+            Subsystem::Feature(feature) => format!("this build has no `{}` feature", feature),
+            Subsystem::LiveSession => "no live session is hosted on this machine".to_string(),
+        }
+    }
+
+    /// Command that makes the subsystem available.
+    fn remediation(&self) -> String {
+        match self {
+            Subsystem::Tool("git") => "sudo apt install git  # or: brew install git".to_string(),
+            Subsystem::Tool("cargo") => {
+                "curl --proto '=https' --tlsv1.2 -sSf https://sh.rustup.rs | sh".to_string()
+            }
+            Subsystem::Tool("python3") => {
+                "sudo apt install python3  # or: brew install python".to_string()
+            }
+            Subsystem::Tool("node") => {
+                "sudo apt install nodejs  # or: brew install node".to_string()
+            }
+            Subsystem::Tool("go") => "sudo apt install golang  # or: brew install go".to_string(),
+            Subsystem::Tool("xdg-mime") => "sudo apt install xdg-utils".to_string(),
+            Subsystem::Tool("docker") => "curl -fsSL https://get.docker.com | sh".to_string(),
+            Subsystem::Tool("cargo-semver-checks") => {
+                "cargo install cargo-semver-checks --locked".to_string()
+            }
+            Subsystem::Tool(name) => format!("install {} and add it to PATH", name),
+            Subsystem::Server(binary) => format!("cargo build --release -p {}", binary),
+            Subsystem::Feature(feature) => {
+                format!("cargo install --path parflow-cli --features {}", feature)
+            }
+            Subsystem::LiveSession => "parflow live-start --project <name>".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Requirement {
+    pub subsystem: Subsystem,
+    /// What the command does without it; `None` if it cannot run at all
+    pub without: Option<&'static str>,
+}
+
+fn required(subsystem: Subsystem) -> Requirement {
+    Requirement { subsystem, without: None }
+}
+
+fn optional(subsystem: Subsystem, without: &'static str) -> Requirement {
+    Requirement { subsystem, without: Some(without) }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Missing {
+    pub subsystem: Subsystem,
+    pub required: bool,
+    pub reason: String,
+    pub remediation: String,
+    /// What the command does without it, for optional subsystems
+    pub impact: Option<&'static str>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PreflightError {
+    pub command: String,
+    pub missing: Vec<Missing>,
+}
+
+impl fmt::Display for PreflightError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`parflow {}` cannot run:", self.command)?;
+        for missing in self.missing.iter().filter(|m| m.required) {
+            write!(f, "\n  {}; fix: {}", missing.reason, missing.remediation)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for PreflightError {}
+
+/// Toolchain binary a test language needs, as its plugin names it.
+fn language_tool(language: &str) -> Option<&'static str> {
+    languages::get(language)?.toolchain()
+}
+
+/// What `command` needs; see the module docs.
+pub fn requirements(command: &Commands, config: &ParflowConfig) -> Vec<Requirement> {
+    match command {
+        Commands::Serve { .. } => vec![required(Subsystem::Server("parflow-rest"))],
+        Commands::Grpc { .. } => vec![required(Subsystem::Server("parflow-grpc"))],
+        Commands::Lsp => vec![required(Subsystem::Server("parflow-lsp"))],
+        Commands::Start { .. } => vec![
+            optional(Subsystem::Server("parflow-rest"), "runs without the REST API"),
+            optional(Subsystem::Server("parflow-grpc"), "runs without the gRPC server"),
+        ],
+        Commands::Dashboard => vec![required(Subsystem::Feature("dashboard"))],
+        Commands::Ownership { .. } => vec![required(Subsystem::Tool("git"))],
+        Commands::Debt { .. } => vec![optional(
+            Subsystem::Tool("git"),
+            "records debt snapshots without the commit they were taken at",
+        )],
+        Commands::Open { register: true, .. } if cfg!(target_os = "linux") => {
+            vec![required(Subsystem::Tool("xdg-mime"))]
+        }
+        Commands::TestRun { languages, .. } => {
+            let mut tools: Vec<_> = languages.iter().filter_map(|l| language_tool(l)).collect();
+            tools.sort();
+            tools.dedup();
+            tools.into_iter().map(|tool| required(Subsystem::Tool(tool))).collect()
+        }
+        Commands::LiveJoin { server, .. } => {
+            let server = server.as_deref().unwrap_or(&config.live.server);
+            let local = ["localhost", "127.0.0.1", "[::1]"].iter().any(|h| server.contains(h));
+            if local {
+                vec![optional(Subsystem::LiveSession, "the client waits until one is started")]
+            } else {
+                Vec::new()
+            }
+        }
+        Commands::SelfCheck { .. } => vec![optional(
+            Subsystem::Tool("cargo-semver-checks"),
+            "compares the API snapshots only",
+        )],
+        _ => Vec::new(),
+    }
+}
+
+/// Missing optional subsystems, or an error if a required one is missing.
+pub fn evaluate(
+    command: &str,
+    requirements: &[Requirement],
+    available: impl Fn(&Subsystem) -> bool,
+) -> Result<Vec<Missing>, PreflightError> {
+    let missing: Vec<Missing> = requirements
+        .iter()
+        .filter(|r| !available(&r.subsystem))
+        .map(|r| Missing {
+            subsystem: r.subsystem,
+            required: r.without.is_none(),
+            reason: r.subsystem.reason(),
+            remediation: r.subsystem.remediation(),
+            impact: r.without,
+        })
+        .collect();
+    if missing.iter().any(|m| m.required) {
+        Err(PreflightError { command: command.to_string(), missing })
+    } else {
+        Ok(missing)
+    }
+}
+
+/// Check `command` against this machine and build.
+pub fn check(
+    name: &str,
+    command: &Commands,
+    config: &ParflowConfig,
+) -> Result<Vec<Missing>, PreflightError> {
+    evaluate(name, &requirements(command, config), Subsystem::available)
+}
+
+pub fn print_degraded(missing: &[Missing]) {
+    for missing in missing {
+        println!(
+            "{} {}, {}",
+            "⚠️  Degraded:".bright_yellow(),
+            missing.reason,
+            missing.impact.unwrap_or("some output is missing")
+        );
+        println!("   {} {}", "fix:".bright_cyan(), missing.remediation.bright_white());
+    }
+}
+
+pub fn print_error(error: &PreflightError) {
+    println!("{} `parflow {}`", "❌ Cannot run".bright_red().bold(), error.command);
+    for missing in error.missing.iter().filter(|m| m.required) {
+        println!("  {} {}", "✗".bright_red(), missing.reason);
+        println!("    {} {}", "fix:".bright_cyan(), missing.remediation.bright_white());
+    }
+    print_degraded(&error.missing.iter().filter(|m| !m.required).cloned().collect::<Vec<_>>());
+}
+
+fn on_path(name: &str) -> Option<PathBuf> {
+    let file = format!("{}{}", name, std::env::consts::EXE_SUFFIX);
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(&file))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_required_and_optional_subsystems() {
+        let requirements = [
+            required(Subsystem::Tool("git")),
+            optional(Subsystem::Server("parflow-rest"), "runs without the REST API"),
+        ];
+
+        let degraded = evaluate("start", &requirements, |s| *s == Subsystem::Tool("git")).unwrap();
+        assert_eq!(degraded.len(), 1);
+        assert_eq!(degraded[0].remediation, "cargo build --release -p parflow-rest");
+        assert_eq!(degraded[0].impact, Some("runs without the REST API"));
+
+        let error = evaluate("ownership", &requirements, |_| false).unwrap_err();
+        assert_eq!(error.missing.len(), 2);
+        assert!(error.to_string().contains("git not found on PATH"));
+        assert!(!error.to_string().contains("parflow-rest"));
+        assert!(evaluate("ownership", &requirements, |_| true).unwrap().is_empty());
+
+        let json = serde_json::to_value(&error.missing[0]).unwrap();
+        assert_eq!(json["subsystem"]["kind"], "tool");
+        assert_eq!(json["subsystem"]["name"], "git");
+    }
+}
@@ -0,0 +1,88 @@
+use anyhow::Context;
+use colored::*;
+use semantic_compiler::profiling::{Profile, Profiler, RankedMigration, Target};
+use std::path::Path;
+use std::time::Duration;
+
+pub struct ProfileArgs {
+    pub pid: Option<u32>,
+    pub command: Vec<String>,
+    pub duration: u64,
+    pub profiler: Option<String>,
+    /// Collapsed stacks recorded earlier, instead of sampling
+    pub stacks: Option<String>,
+    pub project: String,
+    pub format: String,
+}
+
+fn record(args: &ProfileArgs) -> anyhow::Result<Profile> {
+    if let Some(stacks) = &args.stacks {
+        let text =
+            std::fs::read_to_string(stacks).with_context(|| format!("reading {}", stacks))?;
+        return Ok(Profile::from_collapsed(&text));
+    }
+    let target = match (args.pid, args.command.is_empty()) {
+        (Some(pid), true) => Target::Process { pid, duration: Duration::from_secs(args.duration) },
+        (None, false) => Target::Command(args.command.clone()),
+        _ => anyhow::bail!("give either --pid or a command after --"),
+    };
+    let profiler = match &args.profiler {
+        Some(name) => name.parse()?,
+        None => Profiler::detect(&target).context("no sampling profiler for this platform")?,
+    };
+    println!("{} {:?}", "⏱️  Sampling with".bright_blue().bold(), profiler);
+    profiler.record(&target)
+}
+
+/// Sample the target, then rank the suggested migrations by the time they would save.
+pub fn run(args: ProfileArgs) -> anyhow::Result<()> {
+    let profile = record(&args)?;
+    let graph = semantic_compiler::SemanticGraph::new("mixed");
+    let suggestions = semantic_compiler::CrossLanguageAnalyzer.suggest_migration_targets(&graph);
+    let ranked = profile.rank_migrations(suggestions, Path::new(&args.project))?;
+    let functions = profile.functions();
+
+    if args.format == "json" {
+        let value = serde_json::json!({
+            "samples": profile.samples(),
+            "functions": functions.iter().take(20).collect::<Vec<_>>(),
+            "migrations": ranked,
+        });
+        println!("{}", serde_json::to_string_pretty(&value)?);
+        return Ok(());
+    }
+
+    println!("\n{} ({} samples)", "🔥 HOT FUNCTIONS".bright_red().bold(), profile.samples());
+    if functions.is_empty() {
+        println!("  {}", "No samples recorded".bright_white());
+    }
+    for function in functions.iter().take(10) {
+        println!(
+            "  {:>5.1}% {:>5.1}% self  {}{}",
+            function.total * 100.0,
+            function.own * 100.0,
+            function.function.bright_white().bold(),
+            function.file.as_ref().map(|file| format!(" ({})", file)).unwrap_or_default()
+        );
+    }
+    print_migrations(&ranked);
+    Ok(())
+}
+
+fn print_migrations(ranked: &[RankedMigration]) {
+    println!("\n{}", "🎯 MIGRATIONS BY MEASURED IMPACT".bright_blue().bold());
+    for migration in ranked {
+        let suggestion = &migration.suggestion;
+        println!(
+            "  {:?}: {} → {}  {:.1}% of samples, {}",
+            suggestion.pattern_type,
+            suggestion.current_language.bright_yellow(),
+            suggestion.suggested_language.bright_green(),
+            migration.hotness * 100.0,
+            format!("{:.2}x overall", migration.expected_speedup).bright_cyan()
+        );
+        if !migration.functions.is_empty() {
+            println!("     in {}", migration.functions.join(", "));
+        }
+    }
+}
@@ -0,0 +1,725 @@
+//! Checks of ParFlow's own library crates before a release.
+//!
+//! `parflow self-check api` compares the public API of each crate in [`API_CRATES`] with the
+//! snapshot committed under `api/`, then runs cargo-semver-checks against a baseline revision
+//! when it is installed. The snapshot is read straight from the source: one line per public item,
+//! method, field, variant and protobuf declaration, so a removed or changed line is a likely
+//! breaking change and shows up in review as a plain text diff.
+
+use clap::Subcommand;
+use colored::*;
+use std::collections::{BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Library crates whose public API other projects build on
+pub const API_CRATES: &[&str] = &[
+    "parflow-core",
+    "parflow-grpc",
+    "parflow-live-client",
+    "parflow-orchestrator",
+    "parflow-artifacts",
+    "parflow-findings",
+];
+
+/// Snapshot directory, relative to the workspace root
+pub const SNAPSHOT_DIR: &str = "api";
+
+#[derive(Subcommand)]
+pub enum SelfCheckAction {
+    /// Catch breaking changes to the public API of the library crates
+    Api {
+        /// Crate to check (repeatable; defaults to all library crates)
+        #[arg(short, long)]
+        package: Vec<String>,
+
+        /// Git revision cargo-semver-checks compares against
+        #[arg(short, long, default_value = "main")]
+        baseline: String,
+
+        /// Rewrite the snapshots to match the current API
+        #[arg(long)]
+        update: bool,
+    },
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct ApiDiff {
+    /// Items in the snapshot that are gone or changed
+    pub removed: Vec<String>,
+    pub added: Vec<String>,
+}
+
+impl ApiDiff {
+    pub fn between(snapshot: &BTreeSet<String>, current: &BTreeSet<String>) -> Self {
+        Self {
+            removed: snapshot.difference(current).cloned().collect(),
+            added: current.difference(snapshot).cloned().collect(),
+        }
+    }
+
+    pub fn is_breaking(&self) -> bool {
+        !self.removed.is_empty()
+    }
+}
+
+/// `source` with comments removed and string and char literals emptied, so that braces and
+/// semicolons inside them are not mistaken for structure.
+fn strip_literals(source: &str) -> String {
+    let chars: Vec<char> = source.chars().collect();
+    let ident = |c: Option<&char>| c.is_some_and(|c| c.is_alphanumeric() || *c == '_');
+    let mut out = String::with_capacity(source.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let prev = i.checked_sub(1).and_then(|p| chars.get(p));
+        match c {
+            '/' if next == Some('/') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '/' if next == Some('*') => {
+                let mut depth = 0;
+                while i < chars.len() {
+                    if chars[i] == '/' && chars.get(i + 1) == Some(&'*') {
+                        depth += 1;
+                        i += 2;
+                    } else if chars[i] == '*' && chars.get(i + 1) == Some(&'/') {
+                        depth -= 1;
+                        i += 2;
+                        if depth == 0 {
+                            break;
+                        }
+                    } else {
+                        i += 1;
+                    }
+                }
+                out.push(' ');
+            }
+            'r' if matches!(next, Some('"' | '#'))
+                && (!ident(prev)
+                    || (prev == Some(&'b') && !ident(chars.get(i.wrapping_sub(2))))) =>
+            {
+                let hashes = chars[i + 1..].iter().take_while(|c| **c == '#').count();
+                if chars.get(i + 1 + hashes) != Some(&'"') {
+                    // raw identifier such as r#type
+                    out.push(c);
+                    i += 1;
+                    continue;
+                }
+                i += hashes + 2;
+                while i < chars.len() {
+                    if chars[i] == '"' && chars[i + 1..].iter().take(hashes).all(|c| *c == '#') {
+                        i += hashes + 1;
+                        break;
+                    }
+                    i += 1;
+                }
+                out.push_str("\"\"");
+            }
+            '"' => {
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    i += if chars[i] == '\\' { 2 } else { 1 };
+                }
+                i += 1;
+                out.push_str("\"\"");
+            }
+            '\'' if next == Some('\\') => {
+                i += 2;
+                while i < chars.len() && chars[i] != '\'' {
+                    i += 1;
+                }
+                i += 1;
+                out.push_str("' '");
+            }
+            '\'' if chars.get(i + 2) == Some(&'\'') => {
+                i += 3;
+                out.push_str("' '");
+            }
+            _ => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// Collapse whitespace and drop attributes other than derives.
+fn normalize(text: &str) -> String {
+    let mut kept = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('#') {
+        let after = rest[start + 1..].trim_start_matches('!');
+        if !after.starts_with('[') {
+            kept.push_str(&rest[..start + 1]);
+            rest = &rest[start + 1..];
+            continue;
+        }
+        kept.push_str(&rest[..start]);
+        let open = rest.len() - after.len();
+        let mut depth = 0;
+        let end = rest[open..]
+            .char_indices()
+            .find_map(|(i, c)| {
+                match c {
+                    '[' => depth += 1,
+                    ']' => depth -= 1,
+                    _ => {}
+                }
+                (depth == 0).then_some(open + i + 1)
+            })
+            .unwrap_or(rest.len());
+        if rest[open..end].starts_with("[derive(") {
+            kept.push_str(&rest[start..end]);
+            kept.push(' ');
+        }
+        rest = &rest[end..];
+    }
+    kept.push_str(rest);
+
+    let mut out = kept.split_whitespace().collect::<Vec<_>>().join(" ");
+    for (from, to) in [("( ", "("), (" )", ")"), ("< ", "<"), (" >", ">"), (" ,", ","), (",)", ")")]
+    {
+        out = out.replace(from, to);
+    }
+    out.trim_end_matches(',').trim().to_string()
+}
+
+/// `header` without the derive attribute [`normalize`] keeps.
+fn without_derive(header: &str) -> &str {
+    match header.strip_prefix("#[") {
+        Some(rest) => rest.split_once("] ").map_or(header, |(_, item)| item),
+        None => header,
+    }
+}
+
+/// Public item kind and name of a normalized header, if it is `pub` at all.
+fn public_item(header: &str) -> Option<(&str, &str)> {
+    let rest = without_derive(header);
+    let mut words = rest.strip_prefix("pub ")?.split_whitespace().peekable();
+    while let Some(word) = words.next() {
+        let kind = match word {
+            "async" | "unsafe" | "extern" | "\"\"" | "default" => continue,
+            "const" if matches!(words.peek(), Some(&("fn" | "unsafe" | "async" | "extern"))) => {
+                continue
+            }
+            "struct" | "enum" | "trait" | "union" | "type" | "fn" | "mod" | "use" | "const"
+            | "static" => word,
+            _ => return None,
+        };
+        let name = words.next().unwrap_or_default();
+        let end = name.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(name.len());
+        return Some((kind, &name[..end]));
+    }
+    None
+}
+
+/// Self type of an inherent `impl` header; `None` for trait impls.
+fn impl_self_type(header: &str) -> Option<String> {
+    let mut rest = without_derive(header);
+    rest = rest.strip_prefix("unsafe ").unwrap_or(rest).strip_prefix("impl")?;
+    if rest.starts_with('<') {
+        let mut depth = 0;
+        let end = rest.char_indices().find_map(|(i, c)| {
+            match c {
+                '<' => depth += 1,
+                '>' => depth -= 1,
+                _ => {}
+            }
+            (depth == 0).then_some(i + 1)
+        })?;
+        rest = &rest[end..];
+    }
+    let rest = rest.split(" where ").next()?.trim();
+    (!rest.contains(" for ")).then(|| rest.to_string())
+}
+
+fn base_name(ty: &str) -> &str {
+    ty.split('<').next().unwrap_or(ty).rsplit("::").next().unwrap_or(ty).trim()
+}
+
+/// Cut a const or static declaration at its value; other items are returned whole.
+fn declaration(header: &str) -> &str {
+    let kind = public_item(header).map(|(kind, _)| kind).or_else(|| header.split(' ').next());
+    match kind {
+        Some("const" | "static") => header.split(" = ").next().unwrap_or(header),
+        _ => header,
+    }
+}
+
+/// Whether `text` so far is a `use` declaration, whose braces group paths.
+fn is_use(text: &str) -> bool {
+    let text = normalize(text);
+    let text =
+        text.strip_prefix("pub ").or_else(|| text.strip_prefix("pub(crate) ")).unwrap_or(&text);
+    text.starts_with("use ")
+}
+
+enum Block {
+    Module(String),
+    Impl(String, String),
+    Trait(String, String),
+    Struct(String, String),
+    Enum(String, String),
+    Variant(String, String, String),
+    Skip,
+}
+
+#[derive(Default)]
+struct Scan {
+    items: BTreeSet<String>,
+    /// Methods by self type, kept once the type turns out to be public
+    methods: Vec<(String, String)>,
+    public_types: HashSet<String>,
+    /// Out-of-line `pub mod` declarations: (module path, file declaring it, name)
+    submodules: Vec<(String, PathBuf, String)>,
+}
+
+impl Scan {
+    fn file(&mut self, path: &Path, module: &str) {
+        let Ok(source) = std::fs::read_to_string(path) else { return };
+        let source = strip_literals(&source);
+        let mut stack = vec![Block::Module(module.to_string())];
+        let mut buf = String::new();
+        // Open () and [] pairs, and open <> pairs on top of them for splitting fields
+        let (mut parens, mut angles, mut use_braces) = (0i32, 0i32, 0i32);
+        let mut prev = ' ';
+
+        for c in source.chars() {
+            match c {
+                '{' if use_braces > 0 || is_use(&buf) => {
+                    use_braces += 1;
+                    buf.push(c);
+                }
+                '}' if use_braces > 0 => {
+                    use_braces -= 1;
+                    buf.push(c);
+                }
+                '{' => {
+                    let header = normalize(&buf);
+                    buf.clear();
+                    (parens, angles) = (0, 0);
+                    let block = self.open(stack.last().unwrap_or(&Block::Skip), &header);
+                    stack.push(block);
+                }
+                '}' => {
+                    self.element(stack.last(), &normalize(&buf));
+                    buf.clear();
+                    (parens, angles) = (0, 0);
+                    if stack.len() > 1 {
+                        stack.pop();
+                    }
+                }
+                ';' if parens == 0 => {
+                    self.statement(stack.last(), &normalize(&buf), path);
+                    buf.clear();
+                    (parens, angles) = (0, 0);
+                }
+                ',' if parens == 0 && angles == 0 && use_braces == 0 => {
+                    self.element(stack.last(), &normalize(&buf));
+                    buf.clear();
+                }
+                _ => {
+                    match c {
+                        '(' | '[' => parens += 1,
+                        ')' | ']' => parens -= 1,
+                        '<' => angles += 1,
+                        '>' if prev != '-' && prev != '=' => angles -= 1,
+                        _ => {}
+                    }
+                    buf.push(c);
+                }
+            }
+            prev = c;
+        }
+    }
+
+    fn record(&mut self, module: &str, item: String) {
+        self.items.insert(format!("{}: {}", module, item));
+    }
+
+    /// Handle the header of a `{` block and say what the block holds.
+    fn open(&mut self, parent: &Block, header: &str) -> Block {
+        if header.contains("#[cfg(test)]") {
+            return Block::Skip;
+        }
+        match parent {
+            Block::Module(module) => {
+                if let Some(ty) = impl_self_type(header) {
+                    return Block::Impl(module.clone(), ty);
+                }
+                let Some((kind, name)) = public_item(header) else { return Block::Skip };
+                let (module, name) = (module.clone(), name.to_string());
+                match kind {
+                    "mod" => Block::Module(format!("{}::{}", module, name)),
+                    "struct" | "enum" | "trait" | "union" => {
+                        self.public_types.insert(name.clone());
+                        self.record(&module, header.to_string());
+                        match kind {
+                            "enum" => Block::Enum(module, header.to_string()),
+                            "trait" => Block::Trait(module, header.to_string()),
+                            _ => Block::Struct(module, header.to_string()),
+                        }
+                    }
+                    "const" | "static" => {
+                        self.record(&module, declaration(header).to_string());
+                        Block::Skip
+                    }
+                    _ => {
+                        self.record(&module, header.to_string());
+                        Block::Skip
+                    }
+                }
+            }
+            Block::Impl(module, ty) => {
+                if matches!(public_item(header), Some(("fn", _))) {
+                    let method = format!("{}: impl {} {{ {} }}", module, ty, header);
+                    self.methods.push((base_name(ty).to_string(), method));
+                }
+                Block::Skip
+            }
+            Block::Trait(module, trait_header) => {
+                let item = format!("{} {{ {} }}", trait_header, header);
+                self.record(&module.clone(), item);
+                Block::Skip
+            }
+            Block::Enum(module, enum_header) => {
+                let variant = header.to_string();
+                let item = format!("{} {{ {} {{..}} }}", enum_header, variant);
+                let (module, enum_header) = (module.clone(), enum_header.clone());
+                self.record(&module, item);
+                Block::Variant(module, enum_header, variant)
+            }
+            _ => Block::Skip,
+        }
+    }
+
+    /// Handle a `;`-terminated statement.
+    fn statement(&mut self, block: Option<&Block>, text: &str, file: &Path) {
+        match block {
+            Some(Block::Module(module)) => {
+                if text.contains("#[cfg(test)]") {
+                    return;
+                }
+                let Some((kind, name)) = public_item(text) else { return };
+                if kind == "mod" {
+                    self.submodules.push((module.clone(), file.to_path_buf(), name.to_string()));
+                    return;
+                }
+                // Aliases and unit structs can carry inherent methods too
+                if matches!(kind, "type" | "struct") {
+                    self.public_types.insert(name.to_string());
+                }
+                self.record(&module.clone(), declaration(text).to_string());
+            }
+            Some(Block::Impl(module, ty)) if public_item(text).is_some() => {
+                let method = format!("{}: impl {} {{ {} }}", module, ty, declaration(text));
+                self.methods.push((base_name(ty).to_string(), method));
+            }
+            Some(Block::Trait(module, header)) if !text.is_empty() => {
+                let item = format!("{} {{ {} }}", header, declaration(text));
+                self.record(&module.clone(), item);
+            }
+            _ => {}
+        }
+    }
+
+    /// Handle a `,`-separated field or variant.
+    fn element(&mut self, block: Option<&Block>, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        let item = match block {
+            Some(Block::Struct(module, header)) if text.starts_with("pub ") => {
+                (module.clone(), format!("{} {{ {} }}", header, text))
+            }
+            Some(Block::Enum(module, header)) => {
+                (module.clone(), format!("{} {{ {} }}", header, text))
+            }
+            Some(Block::Variant(module, header, variant)) => {
+                (module.clone(), format!("{} {{ {} {{ {} }} }}", header, variant, text))
+            }
+            _ => return,
+        };
+        self.record(&item.0, item.1);
+    }
+}
+
+/// File defining module `name`, declared in `parent`.
+fn module_file(parent: &Path, name: &str) -> Option<PathBuf> {
+    let stem = parent.file_stem()?.to_str()?;
+    let dir = match stem {
+        "lib" | "mod" | "main" => parent.parent()?.to_path_buf(),
+        _ => parent.with_extension(""),
+    };
+    [dir.join(format!("{}.rs", name)), dir.join(name).join("mod.rs")]
+        .into_iter()
+        .find(|path| path.is_file())
+}
+
+/// Declarations of the `.proto` files in `dir`, one line per field, value and rpc.
+fn proto_api(dir: &Path) -> BTreeSet<String> {
+    let mut items = BTreeSet::new();
+    let Ok(entries) = std::fs::read_dir(dir) else { return items };
+    let mut files: Vec<_> = entries.flatten().map(|e| e.path()).collect();
+    files.sort();
+    for file in files.iter().filter(|f| f.extension().is_some_and(|e| e == "proto")) {
+        let Ok(source) = std::fs::read_to_string(file) else { continue };
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        let mut scopes: Vec<String> = Vec::new();
+        for line in strip_literals(&source).lines() {
+            let line = normalize(line);
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(scope) = line.strip_suffix('{') {
+                scopes.push(scope.trim().to_string());
+            } else if line == "}" {
+                scopes.pop();
+            } else {
+                let mut item = line.clone();
+                for scope in scopes.iter().rev() {
+                    item = format!("{} {{ {} }}", scope, item);
+                }
+                items.insert(format!("proto {}: {}", name, item));
+            }
+        }
+    }
+    items
+}
+
+/// Public API of the crate in `crate_dir`, one sorted line per item.
+pub fn public_api(crate_dir: &Path) -> BTreeSet<String> {
+    let name = crate_dir.file_name().unwrap_or_default().to_string_lossy().replace('-', "_");
+    let mut scan = Scan::default();
+    scan.file(&crate_dir.join("src/lib.rs"), &name);
+    while let Some((parent, file, module)) = scan.submodules.pop() {
+        if let Some(path) = module_file(&file, &module) {
+            scan.file(&path, &format!("{}::{}", parent, module));
+        }
+    }
+
+    let mut items = std::mem::take(&mut scan.items);
+    for (ty, method) in scan.methods {
+        if scan.public_types.contains(&ty) {
+            items.insert(method);
+        }
+    }
+    items.extend(proto_api(&crate_dir.join("proto")));
+    items
+}
+
+fn snapshot_path(root: &Path, krate: &str) -> PathBuf {
+    root.join(SNAPSHOT_DIR).join(format!("{}.txt", krate))
+}
+
+pub fn load_snapshot(root: &Path, krate: &str) -> std::io::Result<BTreeSet<String>> {
+    let text = std::fs::read_to_string(snapshot_path(root, krate))?;
+    Ok(text.lines().filter(|l| !l.is_empty()).map(str::to_string).collect())
+}
+
+fn write_snapshot(root: &Path, krate: &str, api: &BTreeSet<String>) -> std::io::Result<()> {
+    let path = snapshot_path(root, krate);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut text = api.iter().cloned().collect::<Vec<_>>().join("\n");
+    text.push('\n');
+    std::fs::write(path, text)
+}
+
+/// Nearest directory at or above the current one whose Cargo.toml declares the workspace.
+fn workspace_root() -> anyhow::Result<PathBuf> {
+    let cwd = std::env::current_dir()?;
+    cwd.ancestors()
+        .find(|dir| {
+            std::fs::read_to_string(dir.join("Cargo.toml"))
+                .is_ok_and(|manifest| manifest.contains("[workspace]"))
+        })
+        .map(Path::to_path_buf)
+        .ok_or_else(|| anyhow::anyhow!("run this inside the ParFlow workspace"))
+}
+
+/// Run `action`; `Ok(false)` means a breaking change was found.
+pub fn run(action: SelfCheckAction) -> anyhow::Result<bool> {
+    let SelfCheckAction::Api { package, baseline, update } = action;
+    let root = workspace_root()?;
+    let crates: Vec<String> = if package.is_empty() {
+        API_CRATES.iter().map(|c| c.to_string()).collect()
+    } else {
+        package
+    };
+
+    println!("{}", "🔍 Public API Check".bright_blue().bold());
+    println!("{}", "───────────────────".bright_blue());
+    let mut ok = true;
+    for krate in &crates {
+        let dir = root.join(krate);
+        if !dir.join("src/lib.rs").is_file() {
+            anyhow::bail!("{} is not a library crate of this workspace", krate);
+        }
+        let current = public_api(&dir);
+        if update {
+            write_snapshot(&root, krate, &current)?;
+            println!("{} {}: {} items", "📝 Updated".bright_green(), krate, current.len());
+            continue;
+        }
+
+        let snapshot = match load_snapshot(&root, krate) {
+            Ok(snapshot) => snapshot,
+            Err(_) => {
+                println!(
+                    "{} {}: no snapshot, run `parflow self-check api --update`",
+                    "⚠️ ".bright_yellow(),
+                    krate
+                );
+                continue;
+            }
+        };
+        let diff = ApiDiff::between(&snapshot, &current);
+        if diff.is_breaking() {
+            ok = false;
+            println!(
+                "{} {}: {} items removed or changed",
+                "❌".bright_red(),
+                krate,
+                diff.removed.len()
+            );
+        } else if !diff.added.is_empty() {
+            println!("{} {}: {} items added", "➕".bright_cyan(), krate, diff.added.len());
+        } else {
+            println!("{} {}: unchanged", "✅".bright_green(), krate);
+        }
+        for item in &diff.removed {
+            println!("  {} {}", "-".bright_red(), item);
+        }
+        for item in &diff.added {
+            println!("  {} {}", "+".bright_green(), item);
+        }
+    }
+    if update {
+        return Ok(true);
+    }
+    if !ok {
+        println!(
+            "{} bump the crate versions for a breaking release, then run with --update",
+            "💡".bright_yellow()
+        );
+    }
+
+    if crate::preflight::Subsystem::Tool("cargo-semver-checks").available() {
+        println!("\n{} against {}", "🦀 cargo-semver-checks".bright_blue().bold(), baseline);
+        let mut command = Command::new("cargo");
+        command.current_dir(&root).args(["semver-checks", "check-release", "--baseline-rev"]);
+        command.arg(&baseline);
+        for krate in &crates {
+            command.args(["-p", krate]);
+        }
+        ok &= command.status()?.success();
+    }
+    Ok(ok)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extracts_public_items() {
+        let dir = std::env::temp_dir().join(format!("parflow-self-check-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(
+            dir.join("src/lib.rs"),
+            r#"//! Docs { not code
+pub mod store;
+mod private;
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub name: String,
+    pub limits: HashMap<String, u32>,
+    secret: u8,
+}
+
+pub enum Mode { Fast, Slow { delay_ms: u64 } }
+
+impl Config {
+    /// Makes one
+    pub fn new(name: &str) -> Self {
+        let _ = "}{";
+        Self { name: name.to_string(), limits: HashMap::new(), secret: '}' as u8 }
+    }
+    fn hidden(&self) {}
+}
+
+struct Private;
+impl Private {
+    pub fn invisible() {}
+}
+
+pub const LIMIT: usize = 1 << 4;
+
+#[cfg(test)]
+mod tests {
+    pub fn helper() {}
+}
+"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("src/store.rs"),
+            "pub trait Store {\n    fn get(&self, key: &str) -> Option<Vec<u8>>;\n}\n",
+        )
+        .unwrap();
+
+        let api = public_api(&dir);
+        let name = dir.file_name().unwrap().to_string_lossy().replace('-', "_");
+        let expected: BTreeSet<String> = [
+            "#[derive(Debug, Clone)] pub struct Config",
+            "#[derive(Debug, Clone)] pub struct Config { pub limits: HashMap<String, u32> }",
+            "#[derive(Debug, Clone)] pub struct Config { pub name: String }",
+            "impl Config { pub fn new(name: &str) -> Self }",
+            "pub const LIMIT: usize",
+            "pub enum Mode",
+            "pub enum Mode { Fast }",
+            "pub enum Mode { Slow {..} }",
+            "pub enum Mode { Slow { delay_ms: u64 } }",
+        ]
+        .iter()
+        .map(|item| format!("{}: {}", name, item))
+        .chain(std::iter::once(format!(
+            "{}::store: pub trait Store {{ fn get(&self, key: &str) -> Option<Vec<u8>> }}",
+            name
+        )))
+        .chain(std::iter::once(format!("{}::store: pub trait Store", name)))
+        .collect();
+        assert_eq!(api, expected);
+
+        let mut changed = api.clone();
+        changed.remove(&format!("{}: pub enum Mode {{ Fast }}", name));
+        changed.insert(format!("{}: pub fn extra()", name));
+        let diff = ApiDiff::between(&api, &changed);
+        assert!(diff.is_breaking());
+        assert_eq!(diff.added, vec![format!("{}: pub fn extra()", name)]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_public_api_matches_snapshots() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+        for krate in API_CRATES {
+            let snapshot = load_snapshot(root, krate).unwrap();
+            let diff = ApiDiff::between(&snapshot, &public_api(&root.join(krate)));
+            assert_eq!(
+                diff,
+                ApiDiff::default(),
+                "public API of {} changed; review it and run `parflow self-check api --update`",
+                krate
+            );
+        }
+    }
+}
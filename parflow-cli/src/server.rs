@@ -0,0 +1,65 @@
+use clap::Args;
+use colored::*;
+use parflow_core::tls;
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct TlsArgs {
+    /// PEM certificate chain to serve over TLS
+    #[arg(long, requires = "tls_key")]
+    pub tls_cert: Option<String>,
+
+    /// PEM private key for --tls-cert
+    #[arg(long, requires = "tls_cert")]
+    pub tls_key: Option<String>,
+
+    /// Serve a generated self-signed certificate (development only)
+    #[arg(long, conflicts_with = "tls_cert")]
+    pub tls_self_signed: bool,
+}
+
+impl TlsArgs {
+    pub fn enabled(&self) -> bool {
+        self.tls_cert.is_some() || self.tls_self_signed
+    }
+
+    /// Environment understood by the server binaries, see `parflow_core::tls`.
+    pub fn env(&self, client_ca: Option<&str>) -> Vec<(&'static str, String)> {
+        let mut env = Vec::new();
+        if let (Some(cert), Some(key)) = (&self.tls_cert, &self.tls_key) {
+            env.push((tls::CERT_ENV, cert.clone()));
+            env.push((tls::KEY_ENV, key.clone()));
+        }
+        if self.tls_self_signed {
+            env.push((tls::SELF_SIGNED_ENV, "1".to_string()));
+        }
+        if let Some(ca) = client_ca {
+            env.push((tls::CLIENT_CA_ENV, ca.to_string()));
+        }
+        env
+    }
+}
+
+/// Path of a server binary installed next to the CLI, if present.
+pub fn sibling_binary(binary: &str) -> std::io::Result<Option<std::path::PathBuf>> {
+    let path = std::env::current_exe()?.with_file_name(binary);
+    Ok(path.exists().then_some(path))
+}
+
+/// Run a server binary installed next to the CLI until it exits.
+pub async fn launch(binary: &str, env: &[(&'static str, String)]) -> std::io::Result<()> {
+    let Some(path) = sibling_binary(binary)? else {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("{} is not installed next to the CLI", binary),
+        ));
+    };
+
+    let status = tokio::process::Command::new(&path)
+        .envs(env.iter().map(|(k, v)| (*k, v.as_str())))
+        .status()
+        .await?;
+    if !status.success() {
+        println!("{} {} exited with {}", "❌".bright_red(), binary, status);
+    }
+    Ok(())
+}
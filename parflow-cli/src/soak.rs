@@ -0,0 +1,397 @@
+//! Soak mode of `parflow daemon`.
+//!
+//! Drives synthetic live-collaboration traffic through an in-process [`LiveServer`] for hours
+//! while sampling the process (RSS, open file descriptors, runtime tasks) and the server's
+//! session state. Once the warm-up is over, any metric whose least-squares trend rises past its
+//! threshold fails the run, and every sample is written to a diagnostic dump.
+
+use colored::*;
+use parflow_live_server::invites::InviteScope;
+use parflow_live_server::LiveServer;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Rounds of joins, input and cursor moves before a session is ended and replaced
+const ROUNDS_PER_SESSION: usize = 50;
+
+/// Post-warm-up samples needed before trends are judged
+const MIN_SAMPLES: usize = 5;
+
+#[derive(Debug, Clone)]
+pub struct SoakConfig {
+    pub duration: Duration,
+    pub interval: Duration,
+    /// Growth before this point (allocator pools, lazy statics) is ignored
+    pub warmup: Duration,
+    /// Sessions kept busy at once
+    pub sessions: usize,
+    pub dump: PathBuf,
+    pub thresholds: Thresholds,
+}
+
+/// Largest growth of each metric tolerated across the judged part of the run
+#[derive(Debug, Clone, Serialize)]
+pub struct Thresholds {
+    pub rss_bytes: f64,
+    pub open_fds: f64,
+    pub alive_tasks: f64,
+    pub subscribers: f64,
+    pub sessions: f64,
+    pub invitations: f64,
+    pub terminal_bytes: f64,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            rss_bytes: 64.0 * 1024.0 * 1024.0,
+            open_fds: 16.0,
+            alive_tasks: 64.0,
+            subscribers: 32.0,
+            sessions: 4.0,
+            // Redeemed invitations stay listed until their session ends
+            invitations: 512.0,
+            terminal_bytes: 1024.0 * 1024.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Sample {
+    pub elapsed_secs: f64,
+    /// Not available on every platform
+    pub rss_bytes: Option<u64>,
+    pub open_fds: Option<u64>,
+    /// Tasks alive on the runtime, i.e. the task backlog
+    pub alive_tasks: u64,
+    pub subscribers: u64,
+    pub sessions: u64,
+    pub participants: u64,
+    pub invitations: u64,
+    pub terminal_bytes: u64,
+    /// Workload rounds completed so far
+    pub rounds: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Trend {
+    pub metric: &'static str,
+    /// Least-squares growth across the judged samples
+    pub growth: f64,
+    pub threshold: f64,
+    pub first: f64,
+    pub last: f64,
+}
+
+impl Trend {
+    pub fn exceeded(&self) -> bool {
+        self.growth > self.threshold
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SoakReport {
+    pub passed: bool,
+    pub elapsed_secs: f64,
+    pub warmup_secs: f64,
+    pub thresholds: Thresholds,
+    pub trends: Vec<Trend>,
+    pub samples: Vec<Sample>,
+}
+
+/// Reads one metric from a sample, if it was measured
+type Reader = fn(&Sample) -> Option<u64>;
+
+/// Metrics judged for growth, with how to read each from a sample.
+fn metrics(thresholds: &Thresholds) -> Vec<(&'static str, f64, Reader)> {
+    vec![
+        ("rss_bytes", thresholds.rss_bytes, |s| s.rss_bytes),
+        ("open_fds", thresholds.open_fds, |s| s.open_fds),
+        ("alive_tasks", thresholds.alive_tasks, |s| Some(s.alive_tasks)),
+        ("subscribers", thresholds.subscribers, |s| Some(s.subscribers)),
+        ("sessions", thresholds.sessions, |s| Some(s.sessions)),
+        ("invitations", thresholds.invitations, |s| Some(s.invitations)),
+        ("terminal_bytes", thresholds.terminal_bytes, |s| Some(s.terminal_bytes)),
+    ]
+}
+
+/// Slope of the least-squares line through `points`.
+fn slope(points: &[(f64, f64)]) -> f64 {
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let covariance: f64 = points.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let variance: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    if variance == 0.0 {
+        0.0
+    } else {
+        covariance / variance
+    }
+}
+
+/// Trend of every metric over the samples taken after `warmup_secs`; empty until there are
+/// enough of them to judge.
+pub fn trends(samples: &[Sample], warmup_secs: f64, thresholds: &Thresholds) -> Vec<Trend> {
+    let judged: Vec<&Sample> = samples.iter().filter(|s| s.elapsed_secs >= warmup_secs).collect();
+    if judged.len() < MIN_SAMPLES {
+        return Vec::new();
+    }
+    let span = judged[judged.len() - 1].elapsed_secs - judged[0].elapsed_secs;
+
+    metrics(thresholds)
+        .into_iter()
+        .filter_map(|(metric, threshold, read)| {
+            let points: Vec<(f64, f64)> = judged
+                .iter()
+                .filter_map(|s| read(s).map(|value| (s.elapsed_secs, value as f64)))
+                .collect();
+            let (first, last) = (points.first()?.1, points.last()?.1);
+            Some(Trend { metric, growth: slope(&points) * span, threshold, first, last })
+        })
+        .collect()
+}
+
+/// Resident set size of this process.
+fn rss_bytes() -> Option<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+        let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+        // SAFETY: sysconf has no preconditions
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        Some(pages * u64::try_from(page_size).ok()?)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+fn open_fds() -> Option<u64> {
+    let dir = if cfg!(target_os = "linux") { "/proc/self/fd" } else { "/dev/fd" };
+    std::fs::read_dir(dir).ok().map(|entries| entries.count() as u64)
+}
+
+fn sample(server: &LiveServer, started: Instant, rounds: u64) -> Sample {
+    let stats = server.stats();
+    Sample {
+        elapsed_secs: started.elapsed().as_secs_f64(),
+        rss_bytes: rss_bytes(),
+        open_fds: open_fds(),
+        alive_tasks: tokio::runtime::Handle::current().metrics().num_alive_tasks() as u64,
+        subscribers: stats.subscribers as u64,
+        sessions: stats.sessions as u64,
+        participants: stats.participants as u64,
+        invitations: stats.invitations as u64,
+        terminal_bytes: stats.terminal_bytes as u64,
+        rounds,
+    }
+}
+
+/// One worker's traffic: a session that guests keep joining, typing into and leaving, replaced
+/// by a fresh one every [`ROUNDS_PER_SESSION`] rounds.
+async fn workload(server: Arc<LiveServer>, stop: Arc<AtomicBool>, rounds: Arc<AtomicU64>) {
+    while !stop.load(Ordering::Relaxed) {
+        let session = server.create_session("soak").await;
+        let Some(owner) = server.join_session(&session, "owner").await.and_then(|s| s.owner_id)
+        else {
+            return;
+        };
+
+        for round in 0..ROUNDS_PER_SESSION {
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+            let mut updates = server.subscribe_to_updates(&session);
+            let guest_name = format!("guest-{}", round);
+            let invitation = server.create_invitation(
+                &session,
+                &owner,
+                &guest_name,
+                InviteScope::Edit,
+                Duration::from_secs(60),
+            );
+            let guest = match invitation {
+                Ok(invitation) => server
+                    .redeem_invitation(&invitation.token, &guest_name)
+                    .await
+                    .ok()
+                    .and_then(|s| s.participants.into_iter().rev().find(|p| p.name == guest_name))
+                    .map(|p| p.id),
+                Err(_) => None,
+            };
+
+            let _ = server.handle_terminal_input(&session, &owner, "status").await;
+            if let Some(guest) = &guest {
+                let _ = server.handle_terminal_input(&session, guest, "echo soak").await;
+                let _ = server
+                    .update_cursor_position(&session, guest, "main.rs", round as u32, 1)
+                    .await;
+                server.leave_session(&session, guest).await;
+            }
+            if let Some(updates) = &mut updates {
+                while updates.try_recv().is_ok() {}
+            }
+            rounds.fetch_add(1, Ordering::Relaxed);
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        server.end_session(&session).await;
+    }
+}
+
+fn print_sample(sample: &Sample) {
+    let elapsed = sample.elapsed_secs as u64;
+    let mb = |bytes: Option<u64>| {
+        bytes.map_or("n/a".to_string(), |b| format!("{:.1}MB", b as f64 / (1024.0 * 1024.0)))
+    };
+    println!(
+        "{} {:02}:{:02}:{:02}  rss {}  fds {}  tasks {}  subscribers {}  sessions {}  rounds {}",
+        "⏱️ ".bright_blue(),
+        elapsed / 3600,
+        elapsed / 60 % 60,
+        elapsed % 60,
+        mb(sample.rss_bytes),
+        sample.open_fds.map_or("n/a".to_string(), |n| n.to_string()),
+        sample.alive_tasks,
+        sample.subscribers,
+        sample.sessions,
+        sample.rounds
+    );
+}
+
+fn write_dump(path: &Path, report: &SoakReport) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(report)?)?;
+    Ok(())
+}
+
+/// Run the soak; the report says whether every metric stayed flat.
+pub async fn run(config: &SoakConfig, verbose: bool) -> anyhow::Result<SoakReport> {
+    let server = Arc::new(LiveServer::new());
+    let stop = Arc::new(AtomicBool::new(false));
+    let rounds = Arc::new(AtomicU64::new(0));
+    let workers: Vec<_> = (0..config.sessions.max(1))
+        .map(|_| tokio::spawn(workload(server.clone(), stop.clone(), rounds.clone())))
+        .collect();
+
+    let started = Instant::now();
+    let warmup_secs = config.warmup.as_secs_f64();
+    let mut samples = Vec::new();
+    let mut failed = Vec::new();
+    let mut ticker = tokio::time::interval(config.interval);
+    while started.elapsed() < config.duration {
+        ticker.tick().await;
+        let sample = sample(&server, started, rounds.load(Ordering::Relaxed));
+        if verbose {
+            print_sample(&sample);
+        }
+        samples.push(sample);
+        failed = trends(&samples, warmup_secs, &config.thresholds);
+        failed.retain(Trend::exceeded);
+        if !failed.is_empty() {
+            break;
+        }
+    }
+
+    stop.store(true, Ordering::Relaxed);
+    for worker in workers {
+        worker.await?;
+    }
+    let trends = trends(&samples, warmup_secs, &config.thresholds);
+    let report = SoakReport {
+        passed: failed.is_empty(),
+        elapsed_secs: started.elapsed().as_secs_f64(),
+        warmup_secs,
+        thresholds: config.thresholds.clone(),
+        trends,
+        samples,
+    };
+    if !report.passed {
+        write_dump(&config.dump, &report)?;
+    }
+    Ok(report)
+}
+
+pub fn print_report(report: &SoakReport, dump: &Path) {
+    println!("\n{}", "📈 TRENDS AFTER WARM-UP".bright_yellow().bold());
+    for trend in &report.trends {
+        let mark = if trend.exceeded() { "❌".bright_red() } else { "✅".bright_green() };
+        println!(
+            "  {} {:<15} {:>+14.1} (limit {}, {} → {})",
+            mark, trend.metric, trend.growth, trend.threshold, trend.first, trend.last
+        );
+    }
+    if report.passed {
+        println!(
+            "\n{} {:.0}s without upward trends",
+            "✅ Soak passed:".bright_green().bold(),
+            report.elapsed_secs
+        );
+    } else {
+        println!(
+            "\n{} diagnostic dump written to {}",
+            "❌ Soak failed:".bright_red().bold(),
+            dump.display().to_string().bright_yellow()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples(growth: impl Fn(f64) -> u64) -> Vec<Sample> {
+        (0..20)
+            .map(|i| {
+                let t = i as f64 * 10.0;
+                Sample {
+                    elapsed_secs: t,
+                    open_fds: Some(12),
+                    sessions: growth(t),
+                    ..Sample::default()
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_trend_detection() {
+        let thresholds = Thresholds::default();
+        let leaking = samples(|t| 4 + (t / 10.0) as u64);
+        // Too few samples after a long warm-up to judge
+        assert!(trends(&leaking, 170.0, &thresholds).is_empty());
+
+        let judged = trends(&leaking, 50.0, &thresholds);
+        let sessions = judged.iter().find(|t| t.metric == "sessions").unwrap();
+        assert!(sessions.exceeded());
+        assert!((sessions.growth - 14.0).abs() < 1e-9);
+        assert!(judged.iter().filter(|t| t.metric != "sessions").all(|t| !t.exceeded()));
+        // RSS is not sampled here, so it is not judged
+        assert!(judged.iter().all(|t| t.metric != "rss_bytes"));
+
+        let steady = samples(|t| if (t as u64 / 10).is_multiple_of(2) { 4 } else { 6 });
+        assert!(trends(&steady, 0.0, &thresholds).iter().all(|t| !t.exceeded()));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_short_soak_stays_flat() {
+        let dump = std::env::temp_dir().join(format!("parflow-soak-{}.json", std::process::id()));
+        let config = SoakConfig {
+            duration: Duration::from_secs(2),
+            interval: Duration::from_millis(100),
+            warmup: Duration::from_millis(500),
+            sessions: 2,
+            dump: dump.clone(),
+            thresholds: Thresholds::default(),
+        };
+        let report = run(&config, false).await.unwrap();
+        assert!(report.passed, "{:?}", report.trends);
+        assert!(report.samples.last().unwrap().rounds > 0);
+        assert!(!dump.exists());
+    }
+}
@@ -0,0 +1,336 @@
+use crate::{capabilities, control};
+use colored::*;
+use parflow_core::config::ParflowConfig;
+use parflow_kernel_compat::SystemInfo;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Where `wasm-pack build` and `cargo build --target wasm32-unknown-unknown` put the module
+const WASM_ARTIFACTS: &[&str] = &[
+    "parflow-wasm/pkg/parflow_wasm_bg.wasm",
+    "target/wasm32-unknown-unknown/release/parflow_wasm.wasm",
+    "target/wasm32-unknown-unknown/debug/parflow_wasm.wasm",
+];
+
+/// Toolchain name, binaries to try, and the argument that prints the version
+const TOOLCHAINS: &[(&str, &[&str], &str)] = &[
+    ("cargo", &["cargo"], "--version"),
+    ("python", &["python3", "python"], "--version"),
+    ("node", &["node"], "--version"),
+    ("go", &["go"], "version"),
+];
+
+/// Tools some commands use when present; missing ones are reported but not failures
+const OPTIONAL_TOOLCHAINS: &[(&str, &[&str], &str)] =
+    &[("wasm-pack", &["wasm-pack"], "--version"), ("docker", &["docker"], "--version")];
+
+/// Server binaries `parflow start` runs when they are installed next to the CLI
+const SERVER_BINARIES: &[&str] = &["parflow-rest", "parflow-grpc", "parflow-lsp"];
+
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub name: String,
+    pub category: &'static str,
+    pub ok: bool,
+    /// Something that may legitimately be absent; `ok: false` then is not a failure
+    pub optional: bool,
+    pub latency_ms: f64,
+    pub detail: String,
+}
+
+impl Check {
+    fn new(
+        name: &str,
+        category: &'static str,
+        started: Instant,
+        result: Result<String, String>,
+    ) -> Self {
+        let (ok, detail) = match result {
+            Ok(detail) => (true, detail),
+            Err(detail) => (false, detail),
+        };
+        Self {
+            name: name.to_string(),
+            category,
+            ok,
+            optional: false,
+            latency_ms: started.elapsed().as_secs_f64() * 1000.0,
+            detail,
+        }
+    }
+}
+
+impl Check {
+    fn optional(mut self) -> Self {
+        self.optional = true;
+        self
+    }
+
+    pub fn failed(&self) -> bool {
+        !self.ok && !self.optional
+    }
+}
+
+/// Config hosts may be bracketed IPv6 (`[::1]`) or wildcard binds; probe loopback for the latter.
+pub fn probe_host(host: &str) -> String {
+    match host.trim_start_matches('[').trim_end_matches(']') {
+        "0.0.0.0" | "" => "127.0.0.1".to_string(),
+        "::" => "::1".to_string(),
+        other => other.to_string(),
+    }
+}
+
+async fn connect(host: &str, port: u16) -> Result<TcpStream, String> {
+    let connecting = TcpStream::connect((host, port));
+    match tokio::time::timeout(PROBE_TIMEOUT, connecting).await {
+        Ok(Ok(stream)) => Ok(stream),
+        Ok(Err(e)) => Err(format!("{}:{} {}", host, port, e)),
+        Err(_) => Err(format!("{}:{} timed out", host, port)),
+    }
+}
+
+async fn probe_http(host: &str, port: u16, path: &str) -> Result<String, String> {
+    let mut stream = connect(host, port).await?;
+    let request = format!("GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", path, host);
+    let exchange = async {
+        stream.write_all(request.as_bytes()).await?;
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).await?;
+        Ok::<_, std::io::Error>(response)
+    };
+    let response = match tokio::time::timeout(PROBE_TIMEOUT, exchange).await {
+        Ok(Ok(response)) => response,
+        Ok(Err(e)) => return Err(e.to_string()),
+        Err(_) => return Err("no response".to_string()),
+    };
+
+    let status_line = String::from_utf8_lossy(&response).lines().next().unwrap_or("").to_string();
+    match status_line.split_whitespace().nth(1) {
+        Some(code) if code.starts_with('2') => Ok(format!("GET {} → {}", path, code)),
+        Some(code) => Err(format!("GET {} → {}", path, code)),
+        None if response.is_empty() => Err("connection closed (TLS enabled?)".to_string()),
+        None => Err("malformed HTTP response".to_string()),
+    }
+}
+
+async fn probe_toolchain(candidates: &[&str], version_arg: &str) -> Result<String, String> {
+    for binary in candidates {
+        let output = tokio::process::Command::new(binary).arg(version_arg).output();
+        let Ok(Ok(output)) = tokio::time::timeout(PROBE_TIMEOUT, output).await else { continue };
+        if output.status.success() {
+            // Python 2 prints its version on stderr
+            let text = if output.stdout.is_empty() { &output.stderr } else { &output.stdout };
+            return Ok(String::from_utf8_lossy(text)
+                .lines()
+                .next()
+                .unwrap_or("")
+                .trim()
+                .to_string());
+        }
+    }
+    Err(format!("{} not found on PATH", candidates.join(" / ")))
+}
+
+fn probe_wasm() -> Result<String, String> {
+    WASM_ARTIFACTS
+        .iter()
+        .find_map(|path| {
+            let size = std::fs::metadata(path).ok()?.len();
+            Some(format!("{} ({} KB)", path, size / 1024))
+        })
+        .ok_or_else(|| "no WASM build found, run `wasm-pack build` in parflow-wasm".to_string())
+}
+
+/// Services of a running `parflow start`, as reported on its control socket.
+async fn probe_supervisor(checks: &mut Vec<Check>) {
+    let started = Instant::now();
+    let snapshot = match control::query(std::path::Path::new(control::DEFAULT_SOCKET)).await {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            let detail = match e.kind() {
+                std::io::ErrorKind::NotFound | std::io::ErrorKind::ConnectionRefused => {
+                    "not running, start it with `parflow start`".to_string()
+                }
+                _ => e.to_string(),
+            };
+            checks.push(Check::new("supervisor", "service", started, Err(detail)).optional());
+            return;
+        }
+    };
+    let detail = format!(
+        "pid {}, up {}s, {} service(s)",
+        snapshot.pid,
+        snapshot.uptime_secs,
+        snapshot.services.len()
+    );
+    checks.push(Check::new("supervisor", "service", started, Ok(detail)));
+    for service in snapshot.services {
+        let restarts = format!("{} restart(s)", service.restarts);
+        let result = match (service.running, service.last_failure) {
+            (true, _) => Ok(format!("running, {}", restarts)),
+            (false, Some(failure)) => Err(format!("restarting after: {}, {}", failure, restarts)),
+            (false, None) => Err("starting".to_string()),
+        };
+        checks.push(Check::new(&service.name, "supervised", started, result));
+    }
+}
+
+/// `parflow-*` executables next to the CLI: the server binaries plus anything installed as an
+/// extension.
+fn probe_plugins(checks: &mut Vec<Check>) {
+    let started = Instant::now();
+    let Some(dir) =
+        std::env::current_exe().ok().and_then(|exe| exe.parent().map(std::path::Path::to_path_buf))
+    else {
+        return;
+    };
+    let mut found: Vec<String> = std::fs::read_dir(&dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_file()))
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .map(|name| name.trim_end_matches(std::env::consts::EXE_SUFFIX).to_string())
+        .filter(|name| name.starts_with("parflow-") && name != "parflow-cli" && !name.contains('.'))
+        .collect();
+    found.sort();
+    found.dedup();
+
+    for binary in SERVER_BINARIES {
+        let result = if found.iter().any(|name| name == binary) {
+            Ok(dir.join(binary).display().to_string())
+        } else {
+            Err(format!("not installed, build it with `cargo build -p {}`", binary))
+        };
+        checks.push(Check::new(binary, "plugin", started, result).optional());
+    }
+    for name in found.iter().filter(|name| !SERVER_BINARIES.contains(&name.as_str())) {
+        checks.push(Check::new(name, "plugin", started, Ok(dir.join(name).display().to_string())));
+    }
+}
+
+/// CPU features the compilers and kernels can take advantage of.
+fn probe_hardware(checks: &mut Vec<Check>) {
+    let started = Instant::now();
+    let system = SystemInfo::gather().map(|info| info.summary()).map_err(|e| e.to_string());
+    checks.push(Check::new("system", "hardware", started, system));
+
+    let started = Instant::now();
+    let cores = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    checks.push(Check::new(
+        "cpu",
+        "hardware",
+        started,
+        Ok(format!("{} with {} hardware thread(s)", std::env::consts::ARCH, cores)),
+    ));
+
+    #[allow(unused_mut)]
+    let mut features: Vec<(&str, bool)> = Vec::new();
+    #[cfg(target_arch = "x86_64")]
+    features.extend([
+        ("sse4.2", std::arch::is_x86_feature_detected!("sse4.2")),
+        ("avx2", std::arch::is_x86_feature_detected!("avx2")),
+        ("avx512f", std::arch::is_x86_feature_detected!("avx512f")),
+        ("aes", std::arch::is_x86_feature_detected!("aes")),
+        ("sha", std::arch::is_x86_feature_detected!("sha")),
+    ]);
+    #[cfg(target_arch = "aarch64")]
+    features.extend([
+        ("neon", std::arch::is_aarch64_feature_detected!("neon")),
+        ("sve", std::arch::is_aarch64_feature_detected!("sve")),
+        ("aes", std::arch::is_aarch64_feature_detected!("aes")),
+        ("sha2", std::arch::is_aarch64_feature_detected!("sha2")),
+    ]);
+    for (name, available) in features {
+        let result =
+            if available { Ok("supported".to_string()) } else { Err("not supported".to_string()) };
+        checks.push(Check::new(name, "hardware", started, result).optional());
+    }
+}
+
+/// Optional features compiled into this binary, see `parflow status --capabilities`.
+fn probe_features(checks: &mut Vec<Check>) {
+    let started = Instant::now();
+    for capability in capabilities::report().capabilities {
+        let detail = capability.detail.to_string();
+        let result = if capability.available { Ok(detail) } else { Err(detail) };
+        checks.push(Check::new(capability.name, "feature", started, result).optional());
+    }
+}
+
+pub async fn run_checks(config: &ParflowConfig) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    let started = Instant::now();
+    let results = parflow_core::run_example_par().await;
+    checks.push(Check::new(
+        "core",
+        "engine",
+        started,
+        Ok(format!("parallel example {:?}", results)),
+    ));
+
+    let started = Instant::now();
+    let rest = probe_http("127.0.0.1", config.server.rest_port, "/health").await;
+    checks.push(Check::new("rest", "service", started, rest));
+
+    let started = Instant::now();
+    let host = probe_host(&config.server.host);
+    let grpc = connect(&host, config.server.grpc_port)
+        .await
+        .map(|_| format!("{}:{} accepting connections", host, config.server.grpc_port));
+    checks.push(Check::new("grpc", "service", started, grpc));
+    probe_supervisor(&mut checks).await;
+
+    for (name, candidates, version_arg) in TOOLCHAINS {
+        let started = Instant::now();
+        let result = probe_toolchain(candidates, version_arg).await;
+        checks.push(Check::new(name, "toolchain", started, result));
+    }
+    for (name, candidates, version_arg) in OPTIONAL_TOOLCHAINS {
+        let started = Instant::now();
+        let result = probe_toolchain(candidates, version_arg).await;
+        checks.push(Check::new(name, "toolchain", started, result).optional());
+    }
+
+    let started = Instant::now();
+    checks.push(Check::new("wasm", "artifact", started, probe_wasm()));
+    probe_plugins(&mut checks);
+    probe_hardware(&mut checks);
+    probe_features(&mut checks);
+    checks
+}
+
+pub fn print_checks(checks: &[Check]) {
+    let mut category = "";
+    for check in checks {
+        if check.category != category {
+            category = check.category;
+            println!("\n{}", category.to_uppercase().bright_blue().bold());
+        }
+        let (mark, detail) = match (check.ok, check.optional) {
+            (true, _) => ("✅", check.detail.normal()),
+            (false, true) => ("➖", check.detail.dimmed()),
+            (false, false) => ("❌", check.detail.bright_red()),
+        };
+        println!(
+            "  {} {:<18} {:>8.1}ms  {}",
+            mark,
+            check.name.bright_white(),
+            check.latency_ms,
+            detail
+        );
+    }
+
+    let failed = checks.iter().filter(|c| c.failed()).count();
+    println!();
+    if failed == 0 {
+        println!("{}", "✅ All checks passed".bright_green().bold());
+    } else {
+        println!("{} {}/{}", "⚠️  Checks failing:".bright_yellow().bold(), failed, checks.len());
+    }
+}
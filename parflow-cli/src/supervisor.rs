@@ -0,0 +1,309 @@
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::process::ExitStatus;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+/// Future of an in-process service; it should only return on failure.
+pub type ServiceFuture = Pin<Box<dyn Future<Output = anyhow::Result<()>> + Send>>;
+
+pub enum ServiceKind {
+    /// Server binary installed next to the CLI
+    Process { binary: PathBuf, env: Vec<(&'static str, String)> },
+    /// Task run on the CLI's runtime, recreated by the factory after each crash
+    InProcess(Arc<dyn Fn() -> ServiceFuture + Send + Sync>),
+}
+
+pub struct ServiceSpec {
+    pub name: &'static str,
+    pub kind: ServiceKind,
+}
+
+/// Exponential restart delay, reset once a service has stayed up for `stable_after`.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+    pub stable_after: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_millis(500),
+            max: Duration::from_secs(30),
+            stable_after: Duration::from_secs(30),
+        }
+    }
+}
+
+impl Backoff {
+    /// Delay before the next restart, given the previous delay (zero before the first crash).
+    fn next(&self, previous: Duration, uptime: Duration) -> Duration {
+        if previous.is_zero() || uptime >= self.stable_after {
+            self.initial
+        } else {
+            (previous * 2).min(self.max)
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ServiceReport {
+    pub name: &'static str,
+    pub restarts: u32,
+    pub uptime: Duration,
+    pub last_failure: Option<String>,
+}
+
+/// Live view of a supervised service, served on the control socket.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceState {
+    pub name: String,
+    pub running: bool,
+    pub restarts: u32,
+    pub last_failure: Option<String>,
+}
+
+/// Current state of every service, updated by the supervisor as they start and crash.
+pub type ServiceTable = Arc<Mutex<Vec<ServiceState>>>;
+
+fn update(table: &ServiceTable, name: &str, change: impl FnOnce(&mut ServiceState)) {
+    let mut services = table.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(state) = services.iter_mut().find(|s| s.name == name) {
+        change(state);
+    }
+}
+
+/// Grace period between SIGTERM and SIGKILL on shutdown
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+enum Outcome {
+    Exited(String),
+    Shutdown,
+}
+
+async fn stop_child(child: &mut tokio::process::Child) {
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        // SAFETY: signalling a child process we spawned and have not yet reaped
+        unsafe {
+            libc::kill(pid as libc::pid_t, libc::SIGTERM);
+        }
+        if tokio::time::timeout(STOP_TIMEOUT, child.wait()).await.is_ok() {
+            return;
+        }
+    }
+    let _ = child.kill().await;
+}
+
+fn describe(status: std::io::Result<ExitStatus>) -> String {
+    match status {
+        Ok(status) => format!("exited with {}", status),
+        Err(e) => format!("wait failed: {}", e),
+    }
+}
+
+async fn run_once(kind: &ServiceKind, shutdown: &mut watch::Receiver<bool>) -> Outcome {
+    match kind {
+        ServiceKind::Process { binary, env } => {
+            let spawned = tokio::process::Command::new(binary)
+                .envs(env.iter().map(|(k, v)| (*k, v.as_str())))
+                .kill_on_drop(true)
+                .spawn();
+            let mut child = match spawned {
+                Ok(child) => child,
+                Err(e) => return Outcome::Exited(format!("failed to start: {}", e)),
+            };
+            tokio::select! {
+                status = child.wait() => Outcome::Exited(describe(status)),
+                _ = shutdown.changed() => {
+                    stop_child(&mut child).await;
+                    Outcome::Shutdown
+                }
+            }
+        }
+        ServiceKind::InProcess(factory) => {
+            let task = tokio::spawn(factory());
+            let abort = task.abort_handle();
+            tokio::select! {
+                result = task => Outcome::Exited(match result {
+                    Ok(Ok(())) => "stopped unexpectedly".to_string(),
+                    Ok(Err(e)) => format!("failed: {}", e),
+                    Err(e) => format!("panicked: {}", e),
+                }),
+                _ = shutdown.changed() => {
+                    abort.abort();
+                    Outcome::Shutdown
+                }
+            }
+        }
+    }
+}
+
+async fn supervise(
+    spec: ServiceSpec,
+    backoff: Backoff,
+    table: ServiceTable,
+    mut shutdown: watch::Receiver<bool>,
+) -> ServiceReport {
+    let mut report =
+        ServiceReport { name: spec.name, restarts: 0, uptime: Duration::ZERO, last_failure: None };
+    let mut delay = Duration::ZERO;
+
+    loop {
+        println!("{} {}", "▶️  Starting".bright_blue(), spec.name.bright_cyan());
+        update(&table, spec.name, |state| state.running = true);
+        let started = Instant::now();
+        let outcome = run_once(&spec.kind, &mut shutdown).await;
+        let uptime = started.elapsed();
+        report.uptime += uptime;
+        update(&table, spec.name, |state| state.running = false);
+
+        let Outcome::Exited(reason) = outcome else { return report };
+        delay = backoff.next(delay, uptime);
+        println!(
+            "{} {} {}, restarting in {:.1}s",
+            "💥".bright_red(),
+            spec.name.bright_cyan(),
+            reason,
+            delay.as_secs_f64()
+        );
+        update(&table, spec.name, |state| state.last_failure = Some(reason.clone()));
+        report.last_failure = Some(reason);
+
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {
+                report.restarts += 1;
+                update(&table, spec.name, |state| state.restarts = report.restarts);
+            }
+            _ = shutdown.changed() => return report,
+        }
+    }
+}
+
+/// Run `services` until Ctrl+C, restarting any that exit, then stop them all. Their state is
+/// kept in `table` while they run.
+pub async fn run(
+    services: Vec<ServiceSpec>,
+    backoff: Backoff,
+    table: ServiceTable,
+) -> Vec<ServiceReport> {
+    *table.lock().unwrap_or_else(|e| e.into_inner()) = services
+        .iter()
+        .map(|spec| ServiceState {
+            name: spec.name.to_string(),
+            running: false,
+            restarts: 0,
+            last_failure: None,
+        })
+        .collect();
+    let (stop, shutdown) = watch::channel(false);
+    let handles: Vec<_> = services
+        .into_iter()
+        .map(|spec| tokio::spawn(supervise(spec, backoff.clone(), table.clone(), shutdown.clone())))
+        .collect();
+
+    let _ = tokio::signal::ctrl_c().await;
+    println!("\n{}", "🛑 Stopping services...".bright_yellow());
+    let _ = stop.send(true);
+
+    let mut reports = Vec::new();
+    for handle in handles {
+        if let Ok(report) = handle.await {
+            reports.push(report);
+        }
+    }
+    reports
+}
+
+pub fn print_summary(reports: &[ServiceReport]) {
+    println!("\n{}", "📋 SERVICE SUMMARY".bright_blue().bold());
+    for report in reports {
+        let health = if report.restarts == 0 { "✅".to_string() } else { "⚠️ ".to_string() };
+        println!(
+            "  {} {}: up {}s, {} restart(s){}",
+            health,
+            report.name.bright_white(),
+            report.uptime.as_secs(),
+            report.restarts,
+            report
+                .last_failure
+                .as_ref()
+                .map(|f| format!(", last failure: {}", f))
+                .unwrap_or_default()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_max_and_resets_once_stable() {
+        let backoff = Backoff {
+            initial: Duration::from_millis(500),
+            max: Duration::from_secs(3),
+            stable_after: Duration::from_secs(30),
+        };
+        let quick = Duration::from_secs(1);
+        let mut delays = Vec::new();
+        let mut delay = Duration::ZERO;
+        for _ in 0..5 {
+            delay = backoff.next(delay, quick);
+            delays.push(delay.as_millis());
+        }
+        assert_eq!(delays, [500, 1000, 2000, 3000, 3000]);
+
+        // A service that stayed up long enough starts over from the initial delay
+        assert_eq!(backoff.next(delay, Duration::from_secs(30)), backoff.initial);
+        assert_eq!(backoff.next(delay, Duration::from_secs(29)), backoff.max);
+    }
+
+    #[tokio::test]
+    async fn test_counts_restarts_until_shutdown() {
+        let starts = Arc::new(AtomicU32::new(0));
+        let counter = starts.clone();
+        let spec = ServiceSpec {
+            name: "flaky",
+            kind: ServiceKind::InProcess(Arc::new(move || {
+                let attempt = counter.fetch_add(1, Ordering::SeqCst) + 1;
+                Box::pin(async move { Err(anyhow::anyhow!("boom {}", attempt)) })
+            })),
+        };
+        let backoff = Backoff {
+            initial: Duration::from_millis(1),
+            max: Duration::from_millis(4),
+            stable_after: Duration::from_secs(3600),
+        };
+        let table: ServiceTable = Arc::new(Mutex::new(vec![ServiceState {
+            name: "flaky".to_string(),
+            running: false,
+            restarts: 0,
+            last_failure: None,
+        }]));
+        let (stop, shutdown) = watch::channel(false);
+        let supervisor = tokio::spawn(supervise(spec, backoff, table.clone(), shutdown));
+
+        while table.lock().unwrap()[0].restarts < 3 {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        stop.send(true).unwrap();
+        let report = supervisor.await.unwrap();
+
+        let state = table.lock().unwrap()[0].clone();
+        assert_eq!(report.restarts, state.restarts);
+        assert!(report.restarts >= 3);
+        // Every restart started the service again, after the first start
+        assert_eq!(starts.load(Ordering::SeqCst), report.restarts + 1);
+        assert!(report.last_failure.as_deref().unwrap().starts_with("failed: boom"));
+        assert_eq!(state.last_failure, report.last_failure);
+        assert!(!state.running);
+    }
+}
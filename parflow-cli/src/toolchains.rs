@@ -0,0 +1,87 @@
+use colored::*;
+use parflow_core::toolchains::{InstallPlan, Runtime, Toolchains};
+use std::io::{BufRead, IsTerminal, Write};
+
+pub struct ToolchainsArgs {
+    /// `runtime` or `runtime@version`
+    pub install: Option<String>,
+    pub yes: bool,
+    pub refresh: bool,
+    pub format: String,
+}
+
+/// Ask on the terminal whether to run `plan`; without a terminal only `--yes` consents.
+fn confirm(plan: &InstallPlan) -> bool {
+    println!(
+        "\n{} {} {} through {}:",
+        "📥 Installing".bright_blue().bold(),
+        plan.runtime.to_string().bright_white(),
+        plan.version.bright_cyan(),
+        plan.installer
+    );
+    for command in &plan.commands {
+        println!("  $ {}", command.bright_yellow());
+    }
+    if !std::io::stdin().is_terminal() {
+        println!("{}", "Not a terminal; pass --yes to install".yellow());
+        return false;
+    }
+    print!("Run {}? [y/N] ", if plan.commands.len() == 1 { "it" } else { "them" });
+    let _ = std::io::stdout().flush();
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer).is_ok()
+        && matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// List the detected runtimes, installing one first when asked to.
+pub fn run(args: ToolchainsArgs) -> anyhow::Result<()> {
+    let toolchains = Toolchains::new();
+    if args.refresh {
+        toolchains.refresh();
+    }
+    if let Some(install) = &args.install {
+        let (runtime, version) = match install.split_once('@') {
+            Some((runtime, version)) => (runtime, Some(version)),
+            None => (install.as_str(), None),
+        };
+        let runtime: Runtime = runtime.parse()?;
+        let installed = toolchains.install(runtime, version, |plan| args.yes || confirm(plan))?;
+        println!(
+            "{} {} {} ({})",
+            "✅ Installed".bright_green(),
+            installed.runtime,
+            installed.version.bright_cyan(),
+            installed.binary.display()
+        );
+    }
+
+    let detected = toolchains.detect_all();
+    if args.format == "json" {
+        println!("{}", serde_json::to_string_pretty(&detected)?);
+        return Ok(());
+    }
+    println!("\n{}", "🧰 TOOLCHAINS".bright_blue().bold());
+    for (runtime, installed) in &detected {
+        match installed {
+            Some(installed) => println!(
+                "  ✅ {:<8} {:<10} {}",
+                runtime.to_string(),
+                installed.version.bright_green(),
+                installed.binary.display().to_string().dimmed()
+            ),
+            None => {
+                let hint = match runtime.installer() {
+                    Some(_) => format!("parflow toolchains --install {}", runtime),
+                    None => "install it manually".to_string(),
+                };
+                println!(
+                    "  ❌ {:<8} {:<10} {}",
+                    runtime.to_string(),
+                    "missing".red(),
+                    hint.dimmed()
+                )
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,80 @@
+//! `parflow watch`: re-run workflow tasks as their files change, see
+//! [`parflow_orchestrator::watch`] for how changes map to tasks. Changed files also leave the
+//! analysis cache, so `parflow analyze --cached` redoes just those.
+
+use crate::analysis_cache;
+use anyhow::{Context, Result};
+use colored::*;
+use notify::{RecursiveMode, Watcher};
+use parflow_orchestrator::watch::{Debouncer, WatchPlan};
+use parflow_orchestrator::{MultiLanguageOrchestrator, MultiLanguageWorkflow};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tokio::sync::mpsc;
+
+/// Watch `dir` and run the tasks of `workflow_file` its changes call for, until ctrl-c.
+pub async fn watch(workflow_file: &Path, dir: &Path, initial_run: bool) -> Result<()> {
+    let workflow = MultiLanguageWorkflow::load(workflow_file)?;
+    let plan = WatchPlan::new(&workflow)?;
+    let root = dir.canonicalize().with_context(|| format!("watching {}", dir.display()))?;
+
+    let (changes, mut changed) = mpsc::unbounded_channel::<PathBuf>();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event {
+            if !event.kind.is_access() {
+                for path in event.paths {
+                    let _ = changes.send(path);
+                }
+            }
+        }
+    })?;
+    watcher.watch(&root, RecursiveMode::Recursive)?;
+
+    println!(
+        "{} {} ({} tasks, {} rules)",
+        "👀 Watching".bright_blue(),
+        root.display().to_string().bright_cyan(),
+        workflow.tasks.len(),
+        workflow.watch.rules.len()
+    );
+    let mut debouncer = Debouncer::default();
+    if initial_run {
+        run(&workflow, &(0..workflow.tasks.len()).collect::<Vec<_>>()).await;
+    }
+
+    loop {
+        let wake = debouncer.next_due().map(tokio::time::Instant::from_std);
+        tokio::select! {
+            Some(path) = changed.recv() => {
+                if let Err(e) = analysis_cache::invalidate(&root, std::slice::from_ref(&path)) {
+                    println!("{} {}", "⚠️  Analysis cache not updated:".bright_yellow(), e);
+                }
+                let relative = path.strip_prefix(&root).unwrap_or(&path);
+                let tasks = plan.tasks_for(relative);
+                if !tasks.is_empty() {
+                    debouncer.record(&plan, &tasks, Instant::now());
+                }
+            }
+            _ = tokio::time::sleep_until(wake.unwrap_or_else(tokio::time::Instant::now)),
+                if wake.is_some() =>
+            {
+                let due = debouncer.take_due(Instant::now());
+                if !due.is_empty() {
+                    run(&workflow, &due).await;
+                }
+            }
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+    println!("{}", "👋 Stopped watching".bright_blue());
+    Ok(())
+}
+
+async fn run(workflow: &MultiLanguageWorkflow, tasks: &[usize]) {
+    let names: Vec<String> = tasks.iter().map(|&i| workflow.tasks[i].name()).collect();
+    println!("\n{} {}", "🔁 Running".bright_yellow(), names.join(", ").bright_cyan());
+    for result in MultiLanguageOrchestrator::execute_workflow(workflow.subset(tasks)).await {
+        let status = if result.success { "✅" } else { "❌" };
+        println!("  {} {} ({}ms)", status, result.task_name, result.execution_time);
+    }
+}
@@ -0,0 +1,67 @@
+use colored::*;
+use parflow_crate_orchestrator::workspace::WorkspaceAnalysis;
+use parflow_crate_orchestrator::CrateOrchestrator;
+
+/// Analyze the workspace containing the manifest at `path` and print it as text or JSON.
+pub async fn run(path: &str, format: &str) -> anyhow::Result<()> {
+    let analysis = CrateOrchestrator::new().analyze_workspace(path).await?;
+    if format == "json" {
+        println!("{}", serde_json::to_string_pretty(&analysis)?);
+    } else {
+        print_analysis(&analysis);
+    }
+    Ok(())
+}
+
+fn print_analysis(analysis: &WorkspaceAnalysis) {
+    println!("\n{}", "🗂️  WORKSPACE ANALYSIS".bright_green().bold());
+    println!("{}: {}", "Root".bright_cyan(), analysis.root);
+    println!(
+        "{}: {} ({} external dependencies, {}KB downloaded)",
+        "Members".bright_cyan(),
+        analysis.members.len(),
+        analysis.metrics.dependency_count,
+        analysis.metrics.download_size_kb
+    );
+
+    println!("\n{}", "📦 MEMBERS".bright_yellow().bold());
+    for member in &analysis.members {
+        println!(
+            "  • {} {} {}",
+            member.name.bright_white(),
+            member.version,
+            format!(
+                "({} direct, {} total dependencies)",
+                member.dependencies.len(),
+                member.performance_metrics.dependency_count
+            )
+            .dimmed()
+        );
+        if !member.unused_dependencies.is_empty() {
+            println!("    🗑️  unused: {}", member.unused_dependencies.join(", ").bright_red());
+        }
+    }
+
+    if !analysis.duplicate_versions.is_empty() {
+        println!("\n{}", "🔀 DUPLICATED VERSIONS".bright_red().bold());
+        for duplicate in &analysis.duplicate_versions {
+            println!("  • {}", duplicate.name.bright_white());
+            for (version, members) in &duplicate.versions {
+                println!("      {} {}", version, format!("← {}", members.join(", ")).dimmed());
+            }
+        }
+    }
+
+    if !analysis.shared_dependencies.is_empty() {
+        println!("\n{}", "💡 MOVE TO [workspace.dependencies]".bright_green().bold());
+        for shared in &analysis.shared_dependencies {
+            println!(
+                "  {} {}",
+                shared.workspace_entry.bright_green(),
+                format!("# {} ({})", shared.members.join(", "), shared.requirements.join(", "))
+                    .dimmed()
+            );
+        }
+        println!("\n{}", "Members then declare them with `{ workspace = true }`".dimmed());
+    }
+}
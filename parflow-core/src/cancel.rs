@@ -0,0 +1,49 @@
+//! Cooperative cancellation shared by ParFlow's async APIs
+//!
+//! Long-running calls take a [`CancellationToken`] and return a [`Partial`]: once the token is
+//! cancelled they stop starting new work, abandon what is in flight and hand back whatever had
+//! already finished.
+
+pub use tokio_util::sync::CancellationToken;
+
+/// Result of a cancellable call: everything that finished, and whether it was cut short
+#[derive(Debug, Clone, PartialEq)]
+pub struct Partial<T> {
+    pub value: T,
+    pub cancelled: bool,
+}
+
+impl<T> Partial<T> {
+    pub fn complete(value: T) -> Self {
+        Self { value, cancelled: false }
+    }
+
+    pub fn cancelled(value: T) -> Self {
+        Self { value, cancelled: true }
+    }
+
+    /// Complete unless `token` was cancelled by the time the work returned
+    pub fn checked(value: T, token: &CancellationToken) -> Self {
+        Self { value, cancelled: token.is_cancelled() }
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Partial<U> {
+        Partial { value: f(self.value), cancelled: self.cancelled }
+    }
+}
+
+/// Run `work` unless `token` is cancelled first.
+///
+/// # Returns
+///
+/// `None` if cancellation won; `work` is dropped at its current await point
+pub async fn run_until_cancelled<F: std::future::Future>(
+    token: &CancellationToken,
+    work: F,
+) -> Option<F::Output> {
+    tokio::select! {
+        biased;
+        _ = token.cancelled() => None,
+        output = work => Some(output),
+    }
+}
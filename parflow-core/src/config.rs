@@ -0,0 +1,436 @@
+//! Unified ParFlow configuration
+//!
+//! Settings are resolved from these sources, each overriding the one before it:
+//!
+//! 1. Built-in defaults
+//! 2. The user config: `$PARFLOW_CONFIG`, else `$XDG_CONFIG_HOME/parflow/config.toml`,
+//!    else `~/.config/parflow/config.toml`
+//! 3. The project config: `parflow.toml` in the working directory
+//! 4. Environment variables listed in [`ENV_OVERRIDES`]
+//! 5. Command-line flags, applied by the caller on top of the loaded config
+//!
+//! Files only need the keys they change; tables are merged key by key.
+//!
+//! ```toml
+//! [server]
+//! host = "[::]"
+//! rest_port = 3000
+//! grpc_port = 50051
+//! schedules_dir = ".parflow/schedules"
+//!
+//! [orchestrator]
+//! default_timeout_secs = 300
+//!
+//! [bench]
+//! suite = "fibonacci"
+//!
+//! [live]
+//! port = 8080
+//! server = "localhost:8080"
+//! audit = true
+//! audit_retention_days = 90
+//!
+//! [live.sandbox]
+//! allowed_commands = ["cargo", "ls", "git"]
+//! isolation = "bubblewrap"
+//! root = "/srv/parflow/sessions"
+//! network = false
+//!
+//! [storage]
+//! backend = "s3"
+//! bucket = "ci-artifacts"
+//! prefix = "parflow"
+//! region = "eu-west-1"
+//!
+//! [storage.gc]
+//! max_size = "10G"
+//! max_age = "30d"
+//! interval = "1h"
+//!
+//! [log]
+//! level = "info"
+//! format = "pretty"
+//! file = "parflow.log"
+//!
+//! [hooks.github-push]
+//! workflow = ".parflow/workflows/test.json"
+//! secret_env = "GITHUB_WEBHOOK_SECRET"
+//! filter = { ref = "refs/heads/main" }
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Explicit path to the user config file
+pub const CONFIG_ENV: &str = "PARFLOW_CONFIG";
+/// Project config file, looked up in the working directory
+pub const PROJECT_CONFIG_FILE: &str = "parflow.toml";
+
+/// Environment variables and the config keys they override.
+pub const ENV_OVERRIDES: &[(&str, &str)] = &[
+    ("PARFLOW_HOST", "server.host"),
+    ("PARFLOW_REST_PORT", "server.rest_port"),
+    ("PARFLOW_GRPC_PORT", "server.grpc_port"),
+    ("PARFLOW_SCHEDULES_DIR", "server.schedules_dir"),
+    ("PARFLOW_TASK_TIMEOUT", "orchestrator.default_timeout_secs"),
+    ("PARFLOW_BENCH_SUITE", "bench.suite"),
+    ("PARFLOW_LIVE_PORT", "live.port"),
+    ("PARFLOW_LIVE_SERVER", "live.server"),
+    ("PARFLOW_LIVE_AUDIT_RETENTION_DAYS", "live.audit_retention_days"),
+    ("PARFLOW_LOG", "log.level"),
+    ("PARFLOW_LOG_FORMAT", "log.format"),
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ParflowConfig {
+    pub server: ServerConfig,
+    pub orchestrator: OrchestratorConfig,
+    pub bench: BenchConfig,
+    pub live: LiveConfig,
+    pub storage: StorageConfig,
+    pub log: LogConfig,
+    /// Webhooks the REST server accepts, by name
+    pub hooks: BTreeMap<String, HookConfig>,
+}
+
+/// REST and gRPC server settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// Interface the gRPC server binds to
+    pub host: String,
+    pub rest_port: u16,
+    pub grpc_port: u16,
+    /// Where the servers keep cron-scheduled workflows, each in its own subdirectory (`rest`,
+    /// `grpc`), since a directory's schedules are run by one scheduler only
+    pub schedules_dir: PathBuf,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: "[::1]".to_string(),
+            rest_port: 3000,
+            grpc_port: 50051,
+            schedules_dir: PathBuf::from(".parflow/schedules"),
+        }
+    }
+}
+
+/// Defaults applied to submitted workflows
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OrchestratorConfig {
+    /// Timeout for tasks that do not set their own
+    pub default_timeout_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BenchConfig {
+    /// Suite run by `parflow benchmark` when none is given
+    pub suite: String,
+}
+
+impl Default for BenchConfig {
+    fn default() -> Self {
+        Self { suite: "simple".to_string() }
+    }
+}
+
+/// Live coding session settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LiveConfig {
+    /// Port `parflow live-start` listens on
+    pub port: u16,
+    /// Server `parflow live-join` connects to
+    pub server: String,
+    /// Keep an audit log of hosted sessions for `parflow audit export`
+    pub audit: bool,
+    /// Purge audit records older than this; unset keeps them until purged by hand
+    pub audit_retention_days: Option<u64>,
+    pub sandbox: SandboxConfig,
+}
+
+impl Default for LiveConfig {
+    fn default() -> Self {
+        Self {
+            port: 8080,
+            server: "localhost:8080".to_string(),
+            audit: true,
+            audit_retention_days: None,
+            sandbox: SandboxConfig::default(),
+        }
+    }
+}
+
+/// What participants may run in the terminals of hosted live sessions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SandboxConfig {
+    /// Programs participants may run; unset allows any
+    pub allowed_commands: Option<Vec<String>>,
+    /// `none`, `bubblewrap` or `container:IMAGE`
+    pub isolation: String,
+    /// Directory terminal shells are confined to; unset uses the working directory
+    pub root: Option<PathBuf>,
+    /// Let isolated shells reach the network
+    pub network: bool,
+    /// Program behind terminal tabs; unset uses the user's shell
+    pub shell: Option<String>,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            allowed_commands: None,
+            isolation: "none".to_string(),
+            root: None,
+            network: false,
+            shell: None,
+        }
+    }
+}
+
+/// Artifact store used by `parflow start`, `parflow cache` and `parflow build` when no store is
+/// given on the command line. Credentials always come from the environment: `AWS_*` for S3,
+/// `GCS_ACCESS_TOKEN` for GCS.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    /// `local`, `s3` or `gcs`
+    pub backend: String,
+    /// Directory of the local backend
+    pub root: PathBuf,
+    /// Bucket of the S3 and GCS backends
+    pub bucket: Option<String>,
+    /// Prefix of the keys in the bucket
+    pub prefix: String,
+    /// S3 region; unset uses `AWS_REGION`
+    pub region: Option<String>,
+    /// S3-compatible or GCS endpoint; unset uses `AWS_ENDPOINT_URL` or `GCS_ENDPOINT`, else the
+    /// provider's
+    pub endpoint: Option<String>,
+    pub gc: GcConfig,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            backend: "local".to_string(),
+            root: PathBuf::from(".parflow/artifacts"),
+            bucket: None,
+            prefix: String::new(),
+            region: None,
+            endpoint: None,
+            gc: GcConfig::default(),
+        }
+    }
+}
+
+/// Garbage collection `parflow start` runs on the artifact store. Sizes and ages take the
+/// forms of `parflow cache gc`, e.g. `512M` or `12h`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GcConfig {
+    /// Maximum total size to keep; unset keeps any size
+    pub max_size: Option<String>,
+    /// Maximum age since last use; unset keeps artifacts of any age
+    pub max_age: Option<String>,
+    /// Time between runs
+    pub interval: String,
+}
+
+impl Default for GcConfig {
+    fn default() -> Self {
+        Self {
+            max_size: Some("10G".to_string()),
+            max_age: Some("30d".to_string()),
+            interval: "1h".to_string(),
+        }
+    }
+}
+
+/// Log output of the CLI and servers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// Filter directives, e.g. `info` or `warn,parflow_orchestrator=debug`
+    pub level: String,
+    /// `pretty` or `json`
+    pub format: String,
+    /// Also append JSON logs to this file
+    pub file: Option<PathBuf>,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self { level: "info".to_string(), format: "pretty".to_string(), file: None }
+    }
+}
+
+/// A webhook served by the REST server at `POST /hooks/<name>` that starts a workflow
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HookConfig {
+    /// Workflow file to run; `{{payload.<field>}}` in task arguments is filled from the payload
+    pub workflow: PathBuf,
+    /// Environment variable holding the HMAC-SHA256 secret requests are signed with
+    pub secret_env: String,
+    /// Header carrying the `sha256=<hex>` signature
+    #[serde(default = "default_signature_header")]
+    pub signature_header: String,
+    /// Only run when these payload fields, e.g. `ref`, have these values
+    #[serde(default)]
+    pub filter: BTreeMap<String, String>,
+}
+
+fn default_signature_header() -> String {
+    "X-Hub-Signature-256".to_string()
+}
+
+/// The merged configuration and the files it was read from
+#[derive(Debug, Clone)]
+pub struct LoadedConfig {
+    pub config: ParflowConfig,
+    pub files: Vec<PathBuf>,
+    /// Environment variables that overrode file settings
+    pub env: Vec<&'static str>,
+}
+
+fn invalid(path: &Path, error: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), error))
+}
+
+/// Location of the user config file, whether or not it exists.
+pub fn user_config_path() -> Option<PathBuf> {
+    let var = |name| std::env::var_os(name).filter(|v| !v.is_empty()).map(PathBuf::from);
+    var(CONFIG_ENV)
+        .or_else(|| var("XDG_CONFIG_HOME").map(|dir| dir.join("parflow/config.toml")))
+        .or_else(|| var("HOME").map(|home| home.join(".config/parflow/config.toml")))
+}
+
+/// Recursively merge `overlay` into `base`, replacing non-table values.
+fn merge(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => merge(base, overlay),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Interpret an environment value as a TOML scalar, falling back to a plain string.
+fn env_value(raw: &str) -> toml::Value {
+    raw.parse::<i64>()
+        .map(toml::Value::Integer)
+        .or_else(|_| raw.parse::<bool>().map(toml::Value::Boolean))
+        .unwrap_or_else(|_| toml::Value::String(raw.to_string()))
+}
+
+impl ParflowConfig {
+    /// Load from the standard locations and environment, see the module docs.
+    pub fn load() -> io::Result<LoadedConfig> {
+        let files: Vec<PathBuf> =
+            user_config_path().into_iter().chain([PathBuf::from(PROJECT_CONFIG_FILE)]).collect();
+        let env = ENV_OVERRIDES
+            .iter()
+            .filter_map(|(name, key)| {
+                std::env::var(name).ok().filter(|v| !v.is_empty()).map(|v| (*name, *key, v))
+            })
+            .collect::<Vec<_>>();
+        Self::load_from(&files, &env)
+    }
+
+    /// Merge the given files (missing ones are skipped) and `(variable, key, value)` overrides.
+    pub fn load_from(
+        files: &[PathBuf],
+        env: &[(&'static str, &str, String)],
+    ) -> io::Result<LoadedConfig> {
+        let mut merged = toml::Table::new();
+        let mut loaded = Vec::new();
+        for path in files.iter().filter(|p| p.is_file()) {
+            let text = std::fs::read_to_string(path)?;
+            merge(&mut merged, text.parse::<toml::Table>().map_err(|e| invalid(path, e))?);
+            loaded.push(path.clone());
+        }
+
+        for (_, key, value) in env {
+            let (section, field) = key.split_once('.').expect("override keys are section.field");
+            let mut overlay = toml::Table::new();
+            overlay.insert(field.to_string(), env_value(value));
+            merge(&mut merged, toml::Table::from_iter([(section.to_string(), overlay.into())]));
+        }
+
+        let config = merged.try_into().map_err(|e| {
+            let source = loaded.last().map(PathBuf::as_path).unwrap_or(Path::new("environment"));
+            invalid(source, e)
+        })?;
+        Ok(LoadedConfig {
+            config,
+            files: loaded,
+            env: env.iter().map(|(name, ..)| *name).collect(),
+        })
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string_pretty(self).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_precedence() {
+        let dir = std::env::temp_dir().join(format!("parflow-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let user = dir.join("config.toml");
+        let project = dir.join("parflow.toml");
+        std::fs::write(&user, "[server]\nrest_port = 4000\ngrpc_port = 4001\n").unwrap();
+        std::fs::write(&project, "[server]\ngrpc_port = 5001\n[bench]\nsuite = \"fibonacci\"\n")
+            .unwrap();
+
+        let env = [("PARFLOW_LIVE_PORT", "live.port", "9000".to_string())];
+        let loaded =
+            ParflowConfig::load_from(&[user, project, dir.join("missing.toml")], &env).unwrap();
+        let config = loaded.config;
+        assert_eq!(loaded.files.len(), 2);
+        assert_eq!(config.server.rest_port, 4000);
+        assert_eq!(config.server.grpc_port, 5001);
+        assert_eq!(config.server.host, "[::1]");
+        assert_eq!(config.bench.suite, "fibonacci");
+        assert_eq!(config.live.port, 9000);
+        assert_eq!(config.live.server, "localhost:8080");
+
+        std::fs::write(
+            dir.join("hooks.toml"),
+            "[hooks.push]\nworkflow = \"test.json\"\nsecret_env = \"HOOK_SECRET\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("storage.toml"),
+            "[storage]\nbackend = \"s3\"\nbucket = \"ci\"\n[storage.gc]\nmax_size = \"512M\"\n",
+        )
+        .unwrap();
+        let storage =
+            ParflowConfig::load_from(&[dir.join("storage.toml")], &[]).unwrap().config.storage;
+        assert_eq!((storage.backend.as_str(), storage.bucket.as_deref()), ("s3", Some("ci")));
+        assert_eq!(storage.gc.max_size.as_deref(), Some("512M"));
+        assert_eq!(storage.gc.interval, "1h");
+
+        let hooks = ParflowConfig::load_from(&[dir.join("hooks.toml")], &[]).unwrap().config.hooks;
+        assert_eq!(hooks["push"].signature_header, "X-Hub-Signature-256");
+
+        std::fs::write(dir.join("bad.toml"), "[server]\nrest_prot = 1\n").unwrap();
+        assert!(ParflowConfig::load_from(&[dir.join("bad.toml")], &[]).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,362 @@
+//! Language support as plugins
+//!
+//! Everything parflow needs to know about a language sits behind [`LanguagePlugin`]: how to
+//! recognize its files and projects, the commands that build and test a project, the keywords
+//! that start a function for the line-based analyzers, and an optional transpile hook. The
+//! crates ask the global registry instead of keeping their own language lists, so supporting
+//! another language takes one [`register`] call at startup:
+//!
+//! ```
+//! use parflow_core::languages::{self, LanguagePlugin};
+//!
+//! struct CSharp;
+//!
+//! impl LanguagePlugin for CSharp {
+//!     fn name(&self) -> &'static str {
+//!         "csharp"
+//!     }
+//!     fn extensions(&self) -> &'static [&'static str] {
+//!         &["cs"]
+//!     }
+//!     fn aliases(&self) -> &'static [&'static str] {
+//!         &["c#", "cs"]
+//!     }
+//! }
+//!
+//! languages::register(CSharp);
+//! assert_eq!(languages::for_path("Program.cs").map(|l| l.name()), Some("csharp"));
+//! ```
+
+use std::path::Path;
+use std::sync::{Arc, OnceLock, RwLock};
+
+pub trait LanguagePlugin: Send + Sync {
+    /// Lowercase name used throughout parflow, e.g. `python`
+    fn name(&self) -> &'static str;
+
+    /// File extensions, without the dot
+    fn extensions(&self) -> &'static [&'static str];
+
+    /// Other names accepted on the command line, e.g. `js` or `golang`
+    fn aliases(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Files whose presence marks a directory as a project in this language
+    fn project_markers(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    fn detect_project(&self, dir: &Path) -> bool {
+        self.project_markers().iter().any(|marker| dir.join(marker).exists())
+    }
+
+    /// Executable that must be on `PATH` to build or test a project
+    fn toolchain(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Program and arguments that build the project in `dir`; `None` when nothing is compiled
+    fn build_command(&self, _dir: &Path) -> Option<Vec<String>> {
+        None
+    }
+
+    /// Program and arguments that run the test suite of the project in `dir`
+    fn test_command(&self, _dir: &Path) -> Option<Vec<String>> {
+        None
+    }
+
+    /// Parser hook for the line-based analyzers: prefixes of a line that declares a function,
+    /// followed by its name
+    fn function_keywords(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Transpile hook: `code` rewritten in the `target` language, `None` when unsupported
+    fn transpile(&self, _code: &str, _target: &str) -> Option<String> {
+        None
+    }
+}
+
+/// A plugin described entirely by data, which is all the languages parflow ships need
+#[derive(Debug, Clone)]
+pub struct BuiltinLanguage {
+    pub name: &'static str,
+    pub extensions: &'static [&'static str],
+    pub aliases: &'static [&'static str],
+    pub project_markers: &'static [&'static str],
+    pub toolchain: Option<&'static str>,
+    pub build_command: &'static [&'static str],
+    pub test_command: &'static [&'static str],
+    pub function_keywords: &'static [&'static str],
+}
+
+fn command(args: &[&str]) -> Option<Vec<String>> {
+    (!args.is_empty()).then(|| args.iter().map(|arg| arg.to_string()).collect())
+}
+
+impl LanguagePlugin for BuiltinLanguage {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        self.extensions
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        self.aliases
+    }
+
+    fn project_markers(&self) -> &'static [&'static str] {
+        self.project_markers
+    }
+
+    fn toolchain(&self) -> Option<&'static str> {
+        self.toolchain
+    }
+
+    fn build_command(&self, _dir: &Path) -> Option<Vec<String>> {
+        command(self.build_command)
+    }
+
+    fn test_command(&self, _dir: &Path) -> Option<Vec<String>> {
+        command(self.test_command)
+    }
+
+    fn function_keywords(&self) -> &'static [&'static str] {
+        self.function_keywords
+    }
+}
+
+const JAVASCRIPT_FUNCTIONS: &[&str] =
+    &["export async function ", "export function ", "async function ", "function "];
+
+pub const BUILTIN: &[BuiltinLanguage] = &[
+    BuiltinLanguage {
+        name: "rust",
+        extensions: &["rs"],
+        aliases: &["rs"],
+        project_markers: &["Cargo.toml"],
+        toolchain: Some("cargo"),
+        build_command: &["cargo", "build"],
+        test_command: &["cargo", "test"],
+        function_keywords: &[
+            "pub async fn ",
+            "pub(crate) async fn ",
+            "pub(crate) fn ",
+            "pub fn ",
+            "async fn ",
+            "fn ",
+        ],
+    },
+    BuiltinLanguage {
+        name: "python",
+        extensions: &["py"],
+        aliases: &["py", "python3"],
+        project_markers: &["pyproject.toml", "setup.py", "requirements.txt"],
+        toolchain: Some("python3"),
+        build_command: &[],
+        test_command: &["python3", "-m", "pytest"],
+        function_keywords: &["async def ", "def "],
+    },
+    BuiltinLanguage {
+        name: "typescript",
+        extensions: &["ts", "tsx"],
+        aliases: &["ts"],
+        project_markers: &["tsconfig.json"],
+        toolchain: Some("node"),
+        build_command: &["npx", "tsc"],
+        test_command: &["npm", "test"],
+        function_keywords: JAVASCRIPT_FUNCTIONS,
+    },
+    BuiltinLanguage {
+        name: "javascript",
+        extensions: &["js", "jsx", "mjs", "cjs"],
+        aliases: &["js", "node"],
+        project_markers: &["package.json"],
+        toolchain: Some("node"),
+        build_command: &[],
+        test_command: &["npm", "test"],
+        function_keywords: JAVASCRIPT_FUNCTIONS,
+    },
+    BuiltinLanguage {
+        name: "go",
+        extensions: &["go"],
+        aliases: &["golang"],
+        project_markers: &["go.mod"],
+        toolchain: Some("go"),
+        build_command: &["go", "build", "./..."],
+        test_command: &["go", "test", "./..."],
+        function_keywords: &["func "],
+    },
+    BuiltinLanguage {
+        name: "c",
+        extensions: &["c", "h"],
+        aliases: &[],
+        project_markers: &["Makefile"],
+        toolchain: Some("cc"),
+        build_command: &["make"],
+        test_command: &["make", "test"],
+        function_keywords: &[],
+    },
+    BuiltinLanguage {
+        name: "cpp",
+        extensions: &["cc", "cpp", "cxx", "hpp"],
+        aliases: &["c++"],
+        project_markers: &["CMakeLists.txt"],
+        toolchain: Some("cmake"),
+        build_command: &["cmake", "--build", "build"],
+        test_command: &["ctest", "--test-dir", "build"],
+        function_keywords: &[],
+    },
+    BuiltinLanguage {
+        name: "java",
+        extensions: &["java"],
+        aliases: &[],
+        project_markers: &["pom.xml", "build.gradle", "build.gradle.kts"],
+        toolchain: Some("mvn"),
+        build_command: &["mvn", "-q", "compile"],
+        test_command: &["mvn", "-q", "test"],
+        function_keywords: &[],
+    },
+];
+
+/// Plugins in lookup order: later registrations first, so they can override a builtin
+#[derive(Clone)]
+pub struct LanguageRegistry {
+    plugins: Vec<Arc<dyn LanguagePlugin>>,
+}
+
+impl Default for LanguageRegistry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl LanguageRegistry {
+    /// The languages parflow ships with
+    pub fn builtin() -> Self {
+        let plugins = BUILTIN
+            .iter()
+            .map(|language| Arc::new(language.clone()) as Arc<dyn LanguagePlugin>)
+            .collect();
+        Self { plugins }
+    }
+
+    /// Add `plugin`, replacing any plugin of the same name
+    pub fn register(&mut self, plugin: Arc<dyn LanguagePlugin>) {
+        self.plugins.retain(|existing| existing.name() != plugin.name());
+        self.plugins.insert(0, plugin);
+    }
+
+    pub fn plugins(&self) -> &[Arc<dyn LanguagePlugin>] {
+        &self.plugins
+    }
+
+    /// The plugin called `name` or one of its aliases, ignoring case
+    pub fn get(&self, name: &str) -> Option<Arc<dyn LanguagePlugin>> {
+        let name = name.to_lowercase();
+        self.plugins
+            .iter()
+            .find(|plugin| plugin.name() == name || plugin.aliases().contains(&name.as_str()))
+            .cloned()
+    }
+
+    /// The plugin for a file, by its extension
+    pub fn for_path(&self, path: &str) -> Option<Arc<dyn LanguagePlugin>> {
+        let extension = Path::new(path).extension()?.to_str()?;
+        self.plugins.iter().find(|plugin| plugin.extensions().contains(&extension)).cloned()
+    }
+
+    /// The plugin whose projects are marked by a file named like `path`, e.g. `Cargo.toml`
+    pub fn for_marker(&self, path: &str) -> Option<Arc<dyn LanguagePlugin>> {
+        let name = Path::new(path).file_name()?.to_str()?;
+        self.plugins.iter().find(|plugin| plugin.project_markers().contains(&name)).cloned()
+    }
+}
+
+fn global() -> &'static RwLock<LanguageRegistry> {
+    static REGISTRY: OnceLock<RwLock<LanguageRegistry>> = OnceLock::new();
+    REGISTRY.get_or_init(|| RwLock::new(LanguageRegistry::builtin()))
+}
+
+/// A snapshot of the global registry
+pub fn registry() -> LanguageRegistry {
+    global().read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Add `plugin` to the global registry, replacing any plugin of the same name
+pub fn register(plugin: impl LanguagePlugin + 'static) {
+    global().write().unwrap_or_else(|e| e.into_inner()).register(Arc::new(plugin));
+}
+
+/// The plugin called `name` or one of its aliases, ignoring case
+pub fn get(name: &str) -> Option<Arc<dyn LanguagePlugin>> {
+    global().read().unwrap_or_else(|e| e.into_inner()).get(name)
+}
+
+/// The plugin for a file, by its extension
+pub fn for_path(path: &str) -> Option<Arc<dyn LanguagePlugin>> {
+    global().read().unwrap_or_else(|e| e.into_inner()).for_path(path)
+}
+
+/// The plugin whose projects are marked by a file named like `path`
+pub fn for_marker(path: &str) -> Option<Arc<dyn LanguagePlugin>> {
+    global().read().unwrap_or_else(|e| e.into_inner()).for_marker(path)
+}
+
+/// The canonical name of `language`, resolving aliases; `None` for an unknown language
+pub fn canonical(language: &str) -> Option<&'static str> {
+    get(language).map(|plugin| plugin.name())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Kotlin;
+
+    impl LanguagePlugin for Kotlin {
+        fn name(&self) -> &'static str {
+            "kotlin"
+        }
+        fn extensions(&self) -> &'static [&'static str] {
+            &["kt"]
+        }
+        fn transpile(&self, code: &str, target: &str) -> Option<String> {
+            (target == "java").then(|| format!("// from Kotlin\n{}", code))
+        }
+    }
+
+    #[test]
+    fn test_registry_resolves_builtins_and_registered_plugins() {
+        let mut registry = LanguageRegistry::builtin();
+        assert_eq!(registry.for_path("src/app.tsx").unwrap().name(), "typescript");
+        assert_eq!(registry.get("Golang").unwrap().name(), "go");
+        assert!(registry.for_path("notes.txt").is_none());
+        let rust = registry.get("rust").unwrap();
+        assert_eq!(rust.test_command(Path::new(".")).unwrap(), ["cargo", "test"]);
+        assert!(registry.get("python").unwrap().build_command(Path::new(".")).is_none());
+
+        registry.register(Arc::new(Kotlin));
+        let kotlin = registry.for_path("Main.kt").unwrap();
+        assert_eq!(
+            kotlin.transpile("fun main() {}", "java").unwrap(),
+            "// from Kotlin\nfun main() {}"
+        );
+        assert!(kotlin.test_command(Path::new(".")).is_none());
+
+        let dir = std::env::temp_dir().join(format!("parflow-languages-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("go.mod"), "module example\n").unwrap();
+        let detected: Vec<&str> = registry
+            .plugins()
+            .iter()
+            .filter(|plugin| plugin.detect_project(&dir))
+            .map(|plugin| plugin.name())
+            .collect();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(detected, ["go"]);
+    }
+}
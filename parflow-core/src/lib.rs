@@ -5,6 +5,29 @@
 #[cfg(not(target_arch = "wasm32"))]
 use std::time::Duration;
 
+#[cfg(not(target_arch = "wasm32"))]
+pub mod cancel;
+
+pub mod languages;
+
+#[cfg(all(feature = "tls", not(target_arch = "wasm32")))]
+pub mod tls;
+
+#[cfg(all(feature = "config", not(target_arch = "wasm32")))]
+pub mod config;
+
+#[cfg(all(feature = "logging", not(target_arch = "wasm32")))]
+pub mod logging;
+
+#[cfg(all(feature = "otel", not(target_arch = "wasm32")))]
+pub mod telemetry;
+
+#[cfg(all(feature = "toolchains", not(target_arch = "wasm32")))]
+pub mod toolchains;
+
+/// Simulated work of the example tasks: (duration in milliseconds, result)
+pub const EXAMPLE_TASKS: [(u64, i32); 2] = [(100, 1), (50, 2)];
+
 #[cfg(not(target_arch = "wasm32"))]
 /// Run example parallel computation
 /// 
@@ -14,19 +37,37 @@ use std::time::Duration;
 /// 
 /// Vector of computed results from parallel tasks
 pub async fn run_example_par() -> Vec<i32> {
-    use tokio::time::sleep;
+    run_example_par_cancellable(&cancel::CancellationToken::new()).await.value
+}
 
-    let result1 = tokio::spawn(async {
-        sleep(Duration::from_millis(100)).await;
-        1
-    });
+#[cfg(not(target_arch = "wasm32"))]
+/// Run example parallel computation until `cancel` fires
+/// 
+/// # Returns
+/// 
+/// Results of the tasks that finished, in task order
+pub async fn run_example_par_cancellable(
+    cancel: &cancel::CancellationToken,
+) -> cancel::Partial<Vec<i32>> {
+    use tokio::time::sleep;
 
-    let result2 = tokio::spawn(async {
-        sleep(Duration::from_millis(50)).await;
-        2
-    });
+    let handles: Vec<_> = EXAMPLE_TASKS
+        .iter()
+        .map(|&(ms, value)| {
+            let cancel = cancel.clone();
+            tokio::spawn(async move {
+                cancel::run_until_cancelled(&cancel, sleep(Duration::from_millis(ms)))
+                    .await
+                    .map(|_| value)
+            })
+        })
+        .collect();
 
-    vec![result1.await.unwrap(), result2.await.unwrap()]
+    let mut results = Vec::with_capacity(handles.len());
+    for handle in handles {
+        results.extend(handle.await.unwrap());
+    }
+    cancel::Partial { cancelled: results.len() < EXAMPLE_TASKS.len(), value: results }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -38,35 +79,42 @@ pub async fn run_example_par() -> Vec<i32> {
 /// 
 /// Vector of computed results from sequential tasks
 pub async fn run_example_seq() -> Vec<i32> {
-    use tokio::time::sleep;
+    run_example_seq_cancellable(&cancel::CancellationToken::new()).await.value
+}
 
-    let result1 = async {
-        sleep(Duration::from_millis(100)).await;
-        1
-    }
-    .await;
+#[cfg(not(target_arch = "wasm32"))]
+/// Run example sequential computation until `cancel` fires
+/// 
+/// # Returns
+/// 
+/// Results of the tasks that finished; later tasks are not started
+pub async fn run_example_seq_cancellable(
+    cancel: &cancel::CancellationToken,
+) -> cancel::Partial<Vec<i32>> {
+    use tokio::time::sleep;
 
-    let result2 = async {
-        sleep(Duration::from_millis(50)).await;
-        2
+    let mut results = Vec::with_capacity(EXAMPLE_TASKS.len());
+    for &(ms, value) in &EXAMPLE_TASKS {
+        if cancel::run_until_cancelled(cancel, sleep(Duration::from_millis(ms))).await.is_none() {
+            return cancel::Partial::cancelled(results);
+        }
+        results.push(value);
     }
-    .await;
-
-    vec![result1, result2]
+    cancel::Partial::complete(results)
 }
 
 // For WASM platforms - simplified version
 #[cfg(target_arch = "wasm32")]
 /// Run example parallel computation (WASM)
 /// 
-/// Simplified parallel execution for WebAssembly targets
+/// Without threads the tasks cannot overlap, so this returns their results directly;
+/// parflow-wasm fans them out over its Web Worker pool when the page allows shared memory.
 /// 
 /// # Returns
 /// 
 /// Vector of computed results
 pub async fn run_example_par() -> Vec<i32> {
-    // For WASM, we'll use a simple delay simulation
-    vec![1, 2]
+    EXAMPLE_TASKS.iter().map(|&(_, value)| value).collect()
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -78,7 +126,7 @@ pub async fn run_example_par() -> Vec<i32> {
 /// 
 /// Vector of computed results
 pub async fn run_example_seq() -> Vec<i32> {
-    vec![1, 2]
+    EXAMPLE_TASKS.iter().map(|&(_, value)| value).collect()
 }
 
 #[cfg(test)]
@@ -96,4 +144,21 @@ mod tests {
         let res = run_example_par().await;
         assert_eq!(res, vec![1, 2]);
     }
+
+    #[tokio::test]
+    async fn test_cancel_returns_partial_results() {
+        let token = cancel::CancellationToken::new();
+        let canceller = token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(75)).await;
+            canceller.cancel();
+        });
+        // Task 2 takes 50ms and finishes; task 1 takes 100ms and is abandoned
+        let partial = run_example_par_cancellable(&token).await;
+        assert!(partial.cancelled);
+        assert_eq!(partial.value, vec![2]);
+
+        let partial = run_example_seq_cancellable(&token).await;
+        assert_eq!(partial, cancel::Partial::cancelled(Vec::new()));
+    }
 }
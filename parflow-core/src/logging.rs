@@ -0,0 +1,181 @@
+//! Structured logging for ParFlow binaries
+//!
+//! Libraries emit `tracing` events inside spans for each workflow task, live session and
+//! benchmark; binaries call [`init`] once to decide where they go. The pretty layer prints the
+//! colored one-line messages ParFlow has always shown, the JSON layer writes one object per event
+//! with its span stack, and a log file always gets JSON so it stays machine-readable whatever the
+//! terminal shows. With the `otel` feature, spans are also exported once
+//! [`crate::telemetry::init_from_env`] has run.
+
+use colored::*;
+use std::fmt::{self, Write as _};
+use std::fs::OpenOptions;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Mutex;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt as tracing_fmt, EnvFilter, Layer};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Colored messages for a terminal
+    #[default]
+    Pretty,
+    /// One JSON object per line
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pretty" => Ok(Self::Pretty),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("unknown log format '{}', expected pretty or json", s)),
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Pretty => "pretty",
+            Self::Json => "json",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LogOptions {
+    /// `EnvFilter` directives, e.g. `info` or `warn,parflow_orchestrator=debug`
+    pub level: String,
+    pub format: LogFormat,
+    /// Also append JSON lines here
+    pub file: Option<PathBuf>,
+}
+
+impl Default for LogOptions {
+    fn default() -> Self {
+        Self { level: "info".to_string(), format: LogFormat::Pretty, file: None }
+    }
+}
+
+#[cfg(feature = "config")]
+impl LogOptions {
+    /// Options from the `[log]` config section
+    pub fn from_config(config: &crate::config::LogConfig) -> io::Result<Self> {
+        let format = config
+            .format
+            .parse()
+            .map_err(|e: String| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(Self { level: config.level.clone(), format, file: config.file.clone() })
+    }
+}
+
+/// Install the global subscriber described by `options`.
+///
+/// # Errors
+///
+/// Invalid filter directives, an unwritable log file, or a subscriber already being installed
+pub fn init(options: &LogOptions) -> io::Result<()> {
+    let invalid = |e: &dyn fmt::Display| io::Error::new(io::ErrorKind::InvalidInput, e.to_string());
+    let filter = EnvFilter::try_new(&options.level)
+        .map_err(|e| invalid(&format!("invalid log level '{}': {}", options.level, e)))?;
+
+    let file = match &options.file {
+        Some(path) => {
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            Some(tracing_fmt::layer().json().with_span_list(true).with_writer(Mutex::new(file)))
+        }
+        None => None,
+    };
+    #[cfg(feature = "otel")]
+    let otel =
+        crate::telemetry::tracer().map(|tracer| tracing_opentelemetry::layer().with_tracer(tracer));
+    #[cfg(not(feature = "otel"))]
+    let otel: Option<tracing_subscriber::layer::Identity> = None;
+    let (pretty, json) = match options.format {
+        LogFormat::Pretty => (Some(PrettyLayer), None),
+        LogFormat::Json => (None, Some(tracing_fmt::layer().json().with_span_list(true))),
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(pretty)
+        .with(json)
+        .with(file)
+        .with(otel)
+        .try_init()
+        .map_err(|e| invalid(&e))
+}
+
+/// Prints each event's message colored by level, followed by its fields.
+pub struct PrettyLayer;
+
+impl<S: Subscriber> Layer<S> for PrettyLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        println!("{}", render(*event.metadata().level(), &fields.message, &fields.rest));
+    }
+}
+
+#[derive(Default)]
+struct Fields {
+    message: String,
+    rest: Vec<(&'static str, String)>,
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "message" => self.message = value.to_string(),
+            // Source location of records bridged from the `log` crate
+            name if name.starts_with("log.") => {}
+            name => self.rest.push((name, value.to_string())),
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        match field.name() {
+            "message" => self.message = format!("{:?}", value),
+            name if name.starts_with("log.") => {}
+            name => self.rest.push((name, format!("{:?}", value))),
+        }
+    }
+}
+
+fn render(level: Level, message: &str, fields: &[(&str, String)]) -> String {
+    let mut line = match level {
+        Level::ERROR => message.bright_red().to_string(),
+        Level::WARN => message.bright_yellow().to_string(),
+        Level::INFO => message.to_string(),
+        _ => message.dimmed().to_string(),
+    };
+    for (name, value) in fields {
+        let _ = write!(line, " {}={}", name.dimmed(), value.bright_cyan());
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_and_format() {
+        colored::control::set_override(false);
+        let line = render(Level::INFO, "▶️  Executing task", &[("language", "rust".to_string())]);
+        assert_eq!(line, "▶️  Executing task language=rust");
+        assert_eq!("json".parse::<LogFormat>(), Ok(LogFormat::Json));
+        assert!("xml".parse::<LogFormat>().is_err());
+        assert!(
+            init(&LogOptions { level: "not a=level=".to_string(), ..Default::default() }).is_err()
+        );
+    }
+}
@@ -0,0 +1,72 @@
+//! OpenTelemetry export for ParFlow servers
+//!
+//! Off unless `OTEL_EXPORTER_OTLP_ENDPOINT` is set. [`init_from_env`] then installs OTLP/gRPC
+//! exporters for traces and metrics: [`crate::logging::init`] forwards every `tracing` span to
+//! the tracer, and the instruments libraries create from `opentelemetry_api::global` (task
+//! durations, queue depth, session participants, profiled operations) start exporting. The
+//! standard `OTEL_SERVICE_NAME`, `OTEL_EXPORTER_OTLP_TIMEOUT` and `OTEL_METRIC_EXPORT_INTERVAL`
+//! variables apply.
+
+use opentelemetry_api::{global, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::metrics::MeterProvider;
+use opentelemetry_sdk::{runtime, trace, Resource};
+use std::io;
+use std::sync::OnceLock;
+
+/// Collector address, e.g. `http://localhost:4317`
+pub const ENDPOINT_ENV: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+/// Overrides the service name passed to [`init_from_env`]
+pub const SERVICE_NAME_ENV: &str = "OTEL_SERVICE_NAME";
+
+static TRACER: OnceLock<trace::Tracer> = OnceLock::new();
+
+/// Flushes and stops the exporters when dropped; keep it alive for the life of the process.
+pub struct Telemetry {
+    meters: MeterProvider,
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        let _ = self.meters.shutdown();
+        global::shutdown_tracer_provider();
+    }
+}
+
+/// Start exporting to the collector named by `OTEL_EXPORTER_OTLP_ENDPOINT`. Call before
+/// [`crate::logging::init`] and from inside the Tokio runtime.
+///
+/// # Returns
+///
+/// `None` when no endpoint is configured
+pub fn init_from_env(service: &str) -> io::Result<Option<Telemetry>> {
+    let endpoint = match std::env::var(ENDPOINT_ENV) {
+        Ok(endpoint) if !endpoint.is_empty() => endpoint,
+        _ => return Ok(None),
+    };
+    let service = std::env::var(SERVICE_NAME_ENV).unwrap_or_else(|_| service.to_string());
+    let resource = Resource::new([KeyValue::new("service.name", service)]);
+    let exporter =
+        || opentelemetry_otlp::new_exporter().tonic().with_env().with_endpoint(&endpoint);
+
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(exporter())
+        .with_trace_config(trace::config().with_resource(resource.clone()))
+        .install_batch(runtime::Tokio)
+        .map_err(io::Error::other)?;
+    let meters = opentelemetry_otlp::new_pipeline()
+        .metrics(runtime::Tokio)
+        .with_exporter(exporter())
+        .with_resource(resource)
+        .build()
+        .map_err(io::Error::other)?;
+
+    let _ = TRACER.set(tracer);
+    Ok(Some(Telemetry { meters }))
+}
+
+/// Tracer installed by [`init_from_env`], if exporting is on
+pub(crate) fn tracer() -> Option<trace::Tracer> {
+    TRACER.get().cloned()
+}
@@ -0,0 +1,88 @@
+//! TLS settings shared by the REST and gRPC servers
+//!
+//! Servers read these from the environment, like `PORT`, so the CLI and the process
+//! supervisor can configure them without knowing each server's flags.
+
+use std::io;
+use std::path::PathBuf;
+
+/// Path to a PEM certificate chain
+pub const CERT_ENV: &str = "PARFLOW_TLS_CERT";
+/// Path to the PEM private key for `PARFLOW_TLS_CERT`
+pub const KEY_ENV: &str = "PARFLOW_TLS_KEY";
+/// Path to a PEM CA bundle; when set, clients must present a certificate signed by it
+pub const CLIENT_CA_ENV: &str = "PARFLOW_TLS_CLIENT_CA";
+/// Set to `1` to serve a throwaway self-signed certificate for local development
+pub const SELF_SIGNED_ENV: &str = "PARFLOW_TLS_SELF_SIGNED";
+
+/// Where a server gets its certificate from
+#[derive(Debug, Clone)]
+pub enum TlsSource {
+    /// Certificate and key files on disk
+    Files { cert: PathBuf, key: PathBuf },
+    /// Certificate generated at startup for `localhost`
+    SelfSigned,
+}
+
+/// TLS configuration for a server
+#[derive(Debug, Clone)]
+pub struct TlsSettings {
+    /// Server identity
+    pub source: TlsSource,
+    /// CA used to verify client certificates (mutual TLS)
+    pub client_ca: Option<PathBuf>,
+}
+
+/// PEM-encoded server identity
+pub struct PemIdentity {
+    /// Certificate chain
+    pub cert: Vec<u8>,
+    /// Private key
+    pub key: Vec<u8>,
+}
+
+impl TlsSettings {
+    /// Read settings from the environment. Returns `Ok(None)` when TLS is not configured.
+    pub fn from_env() -> io::Result<Option<Self>> {
+        let var = |name| std::env::var(name).ok().filter(|v| !v.is_empty());
+        let source = match (var(CERT_ENV), var(KEY_ENV)) {
+            (Some(cert), Some(key)) => {
+                TlsSource::Files { cert: PathBuf::from(cert), key: PathBuf::from(key) }
+            }
+            (None, None) if var(SELF_SIGNED_ENV).is_some_and(|v| v == "1" || v == "true") => {
+                TlsSource::SelfSigned
+            }
+            (None, None) => return Ok(None),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{} and {} must be set together", CERT_ENV, KEY_ENV),
+                ))
+            }
+        };
+
+        Ok(Some(Self { source, client_ca: var(CLIENT_CA_ENV).map(PathBuf::from) }))
+    }
+
+    /// Load or generate the server certificate and key
+    pub fn identity(&self) -> io::Result<PemIdentity> {
+        match &self.source {
+            TlsSource::Files { cert, key } => {
+                Ok(PemIdentity { cert: std::fs::read(cert)?, key: std::fs::read(key)? })
+            }
+            TlsSource::SelfSigned => {
+                let names = vec!["localhost".to_string(), "127.0.0.1".to_string(), "::1".into()];
+                let cert = rcgen::generate_simple_self_signed(names).map_err(io::Error::other)?;
+                Ok(PemIdentity {
+                    cert: cert.serialize_pem().map_err(io::Error::other)?.into_bytes(),
+                    key: cert.serialize_private_key_pem().into_bytes(),
+                })
+            }
+        }
+    }
+
+    /// Load the client CA bundle, if mutual TLS is enabled
+    pub fn client_ca_pem(&self) -> io::Result<Option<Vec<u8>>> {
+        self.client_ca.as_ref().map(std::fs::read).transpose()
+    }
+}
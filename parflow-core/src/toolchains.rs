@@ -0,0 +1,508 @@
+//! Installed language runtimes
+//!
+//! [`Toolchains`] finds the compilers and interpreters parflow drives (rustc, python, node, go
+//! and tsc) on the `PATH` and where their version managers install them, and asks each for its
+//! version. Probes are cached on disk by binary path and modification time, and expire after a
+//! day since version-manager shims keep their timestamp when the version behind them changes.
+//!
+//! Missing runtimes can be installed through rustup, pyenv, nvm or npm. [`Toolchains::install`]
+//! only runs the installer after the caller's consent callback has approved the plan.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Environment variable overriding where probe results are cached
+pub const CACHE_ENV: &str = "PARFLOW_TOOLCHAIN_CACHE";
+
+/// Seconds a cached probe stays valid for a binary whose timestamp did not change
+const CACHE_TTL_SECS: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Runtime {
+    Rustc,
+    Python,
+    Node,
+    Go,
+    Tsc,
+}
+
+impl Runtime {
+    pub const ALL: [Runtime; 5] = [Self::Rustc, Self::Python, Self::Node, Self::Go, Self::Tsc];
+
+    pub fn language(self) -> &'static str {
+        match self {
+            Self::Rustc => "rust",
+            Self::Python => "python",
+            Self::Node => "javascript",
+            Self::Go => "go",
+            Self::Tsc => "typescript",
+        }
+    }
+
+    /// Binary names to look for, preferred first
+    fn binaries(self) -> &'static [&'static str] {
+        match self {
+            Self::Rustc => &["rustc"],
+            Self::Python => &["python3", "python"],
+            Self::Node => &["node"],
+            Self::Go => &["go"],
+            Self::Tsc => &["tsc"],
+        }
+    }
+
+    fn version_args(self) -> &'static [&'static str] {
+        match self {
+            Self::Go => &["version"],
+            _ => &["--version"],
+        }
+    }
+
+    /// The version manager that installs this runtime, if parflow knows one
+    pub fn installer(self) -> Option<Installer> {
+        match self {
+            Self::Rustc => Some(Installer::Rustup),
+            Self::Python => Some(Installer::Pyenv),
+            Self::Node => Some(Installer::Nvm),
+            Self::Tsc => Some(Installer::Npm),
+            Self::Go => None,
+        }
+    }
+
+    /// Directories version managers install this runtime to, besides the `PATH`
+    fn managed_dirs(self) -> Vec<PathBuf> {
+        match self {
+            Self::Rustc => cargo_home().map(|home| home.join("bin")).into_iter().collect(),
+            Self::Python => pyenv_root()
+                .map(|root| {
+                    let mut dirs = vec![root.join("shims")];
+                    dirs.extend(newest_first(&root.join("versions")).map(|dir| dir.join("bin")));
+                    dirs
+                })
+                .unwrap_or_default(),
+            Self::Node | Self::Tsc => nvm_dir()
+                .map(|nvm| {
+                    newest_first(&nvm.join("versions/node")).map(|dir| dir.join("bin")).collect()
+                })
+                .unwrap_or_default(),
+            Self::Go => vec![PathBuf::from("/usr/local/go/bin")],
+        }
+    }
+}
+
+impl fmt::Display for Runtime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Rustc => "rustc",
+            Self::Python => "python",
+            Self::Node => "node",
+            Self::Go => "go",
+            Self::Tsc => "tsc",
+        })
+    }
+}
+
+impl FromStr for Runtime {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> io::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "rustc" | "rust" => Ok(Self::Rustc),
+            "python" | "python3" => Ok(Self::Python),
+            "node" | "nodejs" | "javascript" => Ok(Self::Node),
+            "go" | "golang" => Ok(Self::Go),
+            "tsc" | "typescript" => Ok(Self::Tsc),
+            other => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown runtime '{}' (expected rustc, python, node, go or tsc)", other),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Installer {
+    Rustup,
+    Pyenv,
+    Nvm,
+    Npm,
+}
+
+impl Installer {
+    /// Version installed when the caller does not ask for one
+    fn default_version(self) -> &'static str {
+        match self {
+            Self::Rustup => "stable",
+            Self::Pyenv => "3.12",
+            Self::Nvm => "lts/*",
+            Self::Npm => "latest",
+        }
+    }
+
+    fn available(self) -> bool {
+        match self {
+            Self::Rustup => find_binary(&["rustup"], Runtime::Rustc.managed_dirs()).is_some(),
+            Self::Pyenv => {
+                let dirs = pyenv_root().map(|root| root.join("bin")).into_iter().collect();
+                find_binary(&["pyenv"], dirs).is_some()
+            }
+            Self::Nvm => nvm_dir().is_some_and(|nvm| nvm.join("nvm.sh").is_file()),
+            Self::Npm => find_binary(&["npm"], Runtime::Node.managed_dirs()).is_some(),
+        }
+    }
+
+    fn command(self, version: &str) -> String {
+        match self {
+            Self::Rustup => format!("rustup toolchain install {} --profile minimal", version),
+            // Installed next to the other pyenv versions without changing the global one
+            Self::Pyenv => format!("pyenv install --skip-existing {}", version),
+            Self::Nvm => format!(
+                ". \"${{NVM_DIR:-$HOME/.nvm}}/nvm.sh\" && nvm install '{}' --no-progress",
+                version
+            ),
+            Self::Npm => format!("npm install --global typescript@{}", version),
+        }
+    }
+}
+
+/// Whether `version` is safe to pass to an installer: `[A-Za-z0-9._+-]+`
+fn valid_version(version: &str) -> bool {
+    !version.is_empty()
+        && version.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '+' | '-'))
+}
+
+impl fmt::Display for Installer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Rustup => "rustup",
+            Self::Pyenv => "pyenv",
+            Self::Nvm => "nvm",
+            Self::Npm => "npm",
+        })
+    }
+}
+
+/// A runtime found on this machine
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Installed {
+    pub runtime: Runtime,
+    pub binary: PathBuf,
+    pub version: String,
+}
+
+/// What [`Toolchains::install`] would run, shown to the user before they consent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstallPlan {
+    pub runtime: Runtime,
+    pub installer: Installer,
+    pub version: String,
+    /// Shell commands, run with `sh -c`
+    pub commands: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedProbe {
+    /// Modification time of the binary, seconds since the epoch
+    modified: u64,
+    probed_at: u64,
+    version: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ProbeCache {
+    #[serde(default)]
+    probes: BTreeMap<String, CachedProbe>,
+}
+
+/// Detects installed runtimes, caching what it learns
+pub struct Toolchains {
+    cache_path: Option<PathBuf>,
+    cache: Mutex<ProbeCache>,
+}
+
+impl Default for Toolchains {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Toolchains {
+    /// Cache probes in the user cache directory (or [`CACHE_ENV`])
+    pub fn new() -> Self {
+        Self::with_cache(default_cache_path())
+    }
+
+    /// Cache probes in `path`, or only in memory when `None`
+    pub fn with_cache(path: Option<PathBuf>) -> Self {
+        let cache = path
+            .as_deref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|text| toml::from_str(&text).ok())
+            .unwrap_or_default();
+        Self { cache_path: path, cache: Mutex::new(cache) }
+    }
+
+    /// Forget every cached probe.
+    pub fn refresh(&self) {
+        self.lock().probes.clear();
+        self.save();
+    }
+
+    /// The first working `runtime` on the `PATH` or in its version manager's directories
+    pub fn detect(&self, runtime: Runtime) -> Option<Installed> {
+        let binary = find_binary(runtime.binaries(), runtime.managed_dirs())?;
+        let version = self.probe(runtime, &binary)?;
+        Some(Installed { runtime, binary, version })
+    }
+
+    /// Every known runtime, `None` for the missing ones
+    pub fn detect_all(&self) -> BTreeMap<Runtime, Option<Installed>> {
+        Runtime::ALL.into_iter().map(|runtime| (runtime, self.detect(runtime))).collect()
+    }
+
+    /// How `runtime` would be installed, `version` defaulting to the installer's usual choice
+    pub fn install_plan(&self, runtime: Runtime, version: Option<&str>) -> io::Result<InstallPlan> {
+        let unsupported = |message: String| io::Error::new(io::ErrorKind::Unsupported, message);
+        let installer = runtime
+            .installer()
+            .ok_or_else(|| unsupported(format!("parflow cannot install {}", runtime)))?;
+        // Versions end up in a shell command
+        if let Some(version) = version.filter(|version| !valid_version(version)) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("'{}' is not a version (letters, digits and ._+- only)", version),
+            ));
+        }
+        if cfg!(windows) {
+            return Err(unsupported(format!(
+                "installing through {} needs a Unix shell",
+                installer
+            )));
+        }
+        if !installer.available() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} is needed to install {} but is not installed", installer, runtime),
+            ));
+        }
+        let version = version.unwrap_or(installer.default_version()).to_string();
+        Ok(InstallPlan { runtime, installer, commands: vec![installer.command(&version)], version })
+    }
+
+    /// Install `runtime` once `consent` approves the plan, returning what is then detected.
+    pub fn install(
+        &self,
+        runtime: Runtime,
+        version: Option<&str>,
+        consent: impl FnOnce(&InstallPlan) -> bool,
+    ) -> io::Result<Installed> {
+        let plan = self.install_plan(runtime, version)?;
+        if !consent(&plan) {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("installing {} was declined", runtime),
+            ));
+        }
+        for command in &plan.commands {
+            let status = Command::new("sh").arg("-c").arg(command).status()?;
+            if !status.success() {
+                return Err(io::Error::other(format!("`{}` exited with {}", command, status)));
+            }
+        }
+        self.detect(runtime).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} installed {} but it is not on the PATH", plan.installer, runtime),
+            )
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, ProbeCache> {
+        self.cache.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// The version `binary` reports, from the cache while it is fresh
+    fn probe(&self, runtime: Runtime, binary: &Path) -> Option<String> {
+        let modified = std::fs::metadata(binary)
+            .and_then(|metadata| metadata.modified())
+            .map(epoch_secs)
+            .unwrap_or(0);
+        let now = epoch_secs(SystemTime::now());
+        let key = binary.to_string_lossy().into_owned();
+        if let Some(cached) = self.lock().probes.get(&key) {
+            if cached.modified == modified && now.saturating_sub(cached.probed_at) < CACHE_TTL_SECS
+            {
+                return Some(cached.version.clone());
+            }
+        }
+
+        let output = Command::new(binary).args(runtime.version_args()).output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        // Older Pythons print their version to stderr
+        let text = format!(
+            "{} {}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let version = parse_version(&text)?;
+        self.lock()
+            .probes
+            .insert(key, CachedProbe { modified, probed_at: now, version: version.clone() });
+        self.save();
+        Some(version)
+    }
+
+    /// Write the cache out; a cache that cannot be written only costs a probe next time
+    fn save(&self) {
+        let Some(path) = &self.cache_path else { return };
+        let Ok(text) = toml::to_string(&*self.lock()) else { return };
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let _ = std::fs::write(path, text);
+    }
+}
+
+/// The first version number in a `--version` output: `rustc 1.79.0 (...)`, `Python 3.12.4`,
+/// `v20.15.0`, `go version go1.22.5 linux/amd64`, `Version 5.5.3`
+pub fn parse_version(output: &str) -> Option<String> {
+    output.split_whitespace().find_map(|word| {
+        let word = word.strip_prefix("go").unwrap_or(word);
+        let word = word.strip_prefix('v').unwrap_or(word);
+        let version: String =
+            word.chars().take_while(|c| c.is_ascii_digit() || *c == '.').collect();
+        let version = version.trim_end_matches('.');
+        (version.starts_with(|c: char| c.is_ascii_digit()) && version.contains('.'))
+            .then(|| version.to_string())
+    })
+}
+
+fn default_cache_path() -> Option<PathBuf> {
+    let var = |name| std::env::var_os(name).filter(|v| !v.is_empty()).map(PathBuf::from);
+    var(CACHE_ENV)
+        .or_else(|| var("XDG_CACHE_HOME").map(|dir| dir.join("parflow/toolchains.toml")))
+        .or_else(|| var("HOME").map(|home| home.join(".cache/parflow/toolchains.toml")))
+}
+
+fn home_or(variable: &str, default: &str) -> Option<PathBuf> {
+    std::env::var_os(variable)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(default)))
+}
+
+fn cargo_home() -> Option<PathBuf> {
+    home_or("CARGO_HOME", ".cargo")
+}
+
+fn pyenv_root() -> Option<PathBuf> {
+    home_or("PYENV_ROOT", ".pyenv")
+}
+
+fn nvm_dir() -> Option<PathBuf> {
+    home_or("NVM_DIR", ".nvm")
+}
+
+fn epoch_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or(0)
+}
+
+/// Subdirectories of `dir` named after versions, the highest version first
+fn newest_first(dir: &Path) -> impl Iterator<Item = PathBuf> {
+    let mut versions: Vec<(Vec<u64>, PathBuf)> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let key = name.trim_start_matches('v').split('.').map_while(|n| n.parse().ok());
+            (key.collect(), entry.path())
+        })
+        .collect();
+    versions.sort_by(|a, b| b.0.cmp(&a.0));
+    versions.into_iter().map(|(_, path)| path)
+}
+
+/// The first of `names` in a `PATH` directory, then in `extra` directories
+fn find_binary(names: &[&str], extra: Vec<PathBuf>) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let dirs = std::env::split_paths(&path).chain(extra);
+    let suffixes: &[&str] = if cfg!(windows) { &[".exe", ".cmd"] } else { &[""] };
+    for dir in dirs {
+        for name in names {
+            for suffix in suffixes {
+                let candidate = dir.join(format!("{}{}", name, suffix));
+                if candidate.is_file() {
+                    return Some(candidate);
+                }
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_install_versions_cannot_inject_shell() {
+        let toolchains = Toolchains::with_cache(None);
+        for version in ["1.80; rm -rf ~", "$(id)", "3.12 && true", "'", ""] {
+            let error = toolchains.install_plan(Runtime::Rustc, Some(version)).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput, "{:?}", version);
+        }
+        assert!(valid_version("1.79.0"));
+        assert!(valid_version("nightly-2024-06-10"));
+        assert!(valid_version("5.4.0-beta+build_1"));
+        // The installers' own defaults are not user input
+        assert!(!valid_version(Installer::Nvm.default_version()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_probes_versions_and_caches_them_per_binary() {
+        use std::os::unix::fs::PermissionsExt;
+
+        assert_eq!(parse_version("rustc 1.79.0 (129f3b996 2024-06-10)").unwrap(), "1.79.0");
+        assert_eq!(parse_version("Python 3.12.4\n").unwrap(), "3.12.4");
+        assert_eq!(parse_version("v20.15.0").unwrap(), "20.15.0");
+        assert_eq!(parse_version("go version go1.22.5 linux/amd64").unwrap(), "1.22.5");
+        assert_eq!(parse_version("Version 5.5.3").unwrap(), "5.5.3");
+        assert_eq!(parse_version("command not found"), None);
+
+        let dir = std::env::temp_dir().join(format!("parflow-toolchains-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let binary = dir.join("python3");
+        let cache = dir.join("cache.toml");
+        let write = |version: &str| {
+            std::fs::write(&binary, format!("#!/bin/sh\necho 'Python {}' >&2\n", version)).unwrap();
+            std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+        };
+
+        write("3.11.7");
+        let toolchains = Toolchains::with_cache(Some(cache.clone()));
+        assert_eq!(toolchains.probe(Runtime::Python, &binary).unwrap(), "3.11.7");
+
+        // Same timestamp, so a new instance answers from the cache on disk
+        let modified = std::fs::metadata(&binary).unwrap().modified().unwrap();
+        write("3.12.4");
+        std::fs::File::options().write(true).open(&binary).unwrap().set_modified(modified).unwrap();
+        let toolchains = Toolchains::with_cache(Some(cache.clone()));
+        assert_eq!(toolchains.probe(Runtime::Python, &binary).unwrap(), "3.11.7");
+
+        toolchains.refresh();
+        assert_eq!(toolchains.probe(Runtime::Python, &binary).unwrap(), "3.12.4");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
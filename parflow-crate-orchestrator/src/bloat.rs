@@ -0,0 +1,336 @@
+//! Binary size attribution
+//!
+//! Builds a binary in release mode and attributes the code in it to crates and functions the way
+//! `cargo bloat` does: every function symbol in the symbol table is demangled, and the crate is
+//! the first path segment of the demangled name, or of the self type for trait impls
+//! (`<serde_json::Value as Display>::fmt` belongs to `serde_json`). Symbols without a Rust path
+//! (C code, linker stubs) are reported as unknown.
+
+use crate::{OptimizationAction, OptimizationSuggestion};
+use anyhow::{bail, Context, Result};
+use cargo_metadata::{Message, Metadata, MetadataCommand, PackageId};
+use object::{Object, ObjectSection, ObjectSymbol, SectionKind, SymbolKind};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Crates of the Rust distribution, reported together as `std`
+const STD_CRATES: &[&str] =
+    &["std", "core", "alloc", "panic_unwind", "panic_abort", "compiler_builtins", "std_detect"];
+
+/// Bucket for symbols that cannot be traced to a crate
+pub const UNKNOWN: &str = "[unknown]";
+
+/// How many functions the report keeps
+const TOP_FUNCTIONS: usize = 30;
+
+/// Share of the code a dependency needs before it earns a suggestion
+const SUGGESTION_THRESHOLD: f64 = 0.05;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrateSize {
+    /// Package name, or the crate name when no package provides it
+    pub name: String,
+    pub size: u64,
+    /// Share of the `.text` section
+    pub share: f64,
+    /// Whether the package depends on the crate itself
+    pub direct: bool,
+    /// Direct dependencies of the package that pull the crate in when it is a transitive one
+    pub pulled_in_by: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionSize {
+    pub name: String,
+    pub krate: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BloatReport {
+    pub binary: PathBuf,
+    pub file_size: u64,
+    pub text_size: u64,
+    /// Largest first
+    pub crates: Vec<CrateSize>,
+    /// The largest functions, largest first
+    pub functions: Vec<FunctionSize>,
+    pub suggestions: Vec<OptimizationSuggestion>,
+}
+
+/// Build the binary of the package at `manifest_path` in release mode and attribute its size.
+pub fn analyze(manifest_path: &Path) -> Result<BloatReport> {
+    let metadata = MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .exec()
+        .with_context(|| format!("running cargo metadata on {}", manifest_path.display()))?;
+    let (package, binary) = build(manifest_path, &metadata)?;
+    let data = std::fs::read(&binary).with_context(|| format!("reading {}", binary.display()))?;
+    let mut report = attribute(&binary, &data)?;
+    resolve_packages(&mut report, &metadata, &package);
+    report.suggestions = suggestions(&report);
+    Ok(report)
+}
+
+/// Run a release build and return the package and path of its first executable.
+fn build(manifest_path: &Path, metadata: &Metadata) -> Result<(PackageId, PathBuf)> {
+    let mut child = Command::new(std::env::var("CARGO").unwrap_or_else(|_| "cargo".into()))
+        .args(["build", "--release", "--message-format=json-render-diagnostics"])
+        .arg("--manifest-path")
+        .arg(manifest_path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .context("running cargo build")?;
+    let stdout = child.stdout.take().context("capturing cargo output")?;
+    let mut executables = Vec::new();
+    for message in Message::parse_stream(BufReader::new(stdout)) {
+        if let Message::CompilerArtifact(artifact) = message? {
+            if let Some(executable) = artifact.executable {
+                executables.push((artifact.package_id, executable.into_std_path_buf()));
+            }
+        }
+    }
+    if !child.wait()?.success() {
+        bail!("cargo build --release failed");
+    }
+    // Prefer the binary of the package the manifest belongs to over other members'
+    let root = metadata.root_package().map(|package| &package.id);
+    executables.sort_by_key(|(package, path)| (Some(package) != root, path.clone()));
+    executables.into_iter().next().context("the build produced no executable")
+}
+
+/// Attribute the function symbols of `data`, the contents of `binary`, to crates.
+pub fn attribute(binary: &Path, data: &[u8]) -> Result<BloatReport> {
+    let file = object::File::parse(data).context("parsing the binary")?;
+    let text_size: u64 = file
+        .sections()
+        .filter(|section| section.kind() == SectionKind::Text)
+        .map(|section| section.size())
+        .sum();
+
+    let mut seen = HashSet::new();
+    let mut crates: BTreeMap<String, u64> = BTreeMap::new();
+    let mut functions = Vec::new();
+    for symbol in file.symbols() {
+        if symbol.kind() != SymbolKind::Text || symbol.size() == 0 {
+            continue;
+        }
+        // Aliases share an address; count the code once
+        if !seen.insert(symbol.address()) {
+            continue;
+        }
+        let Ok(raw) = symbol.name() else { continue };
+        let name = format!("{:#}", rustc_demangle::demangle(raw));
+        let krate = crate_of(&name).unwrap_or(UNKNOWN).to_string();
+        *crates.entry(krate.clone()).or_default() += symbol.size();
+        functions.push(FunctionSize { name, krate, size: symbol.size() });
+    }
+    if functions.is_empty() {
+        bail!("{} has no symbol table; is the release profile stripping it?", binary.display());
+    }
+
+    functions.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+    functions.truncate(TOP_FUNCTIONS);
+    let mut crates: Vec<CrateSize> = crates
+        .into_iter()
+        .map(|(name, size)| CrateSize {
+            name,
+            size,
+            share: size as f64 / text_size.max(1) as f64,
+            direct: false,
+            pulled_in_by: Vec::new(),
+        })
+        .collect();
+    crates.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+
+    Ok(BloatReport {
+        binary: binary.to_path_buf(),
+        file_size: data.len() as u64,
+        text_size,
+        crates,
+        functions,
+        suggestions: Vec::new(),
+    })
+}
+
+/// The crate a demangled symbol belongs to
+pub fn crate_of(symbol: &str) -> Option<&str> {
+    let path = match symbol.strip_prefix('<') {
+        // `<Type as Trait>::method` belongs to the crate of `Type`, unless `Type` is a primitive
+        // or a reference to one, then to that of `Trait`
+        Some(qualified) => {
+            let (self_type, rest) = qualified.split_once(" as ").unwrap_or((qualified, ""));
+            let self_type = self_type
+                .trim_start_matches(['&', '*', '['])
+                .trim_start_matches("mut ")
+                .trim_start_matches("const ")
+                .trim_start_matches("dyn ");
+            if self_type.contains("::") {
+                self_type
+            } else {
+                rest
+            }
+        }
+        None => symbol,
+    };
+    let (krate, _) = path.split_once("::")?;
+    let krate = krate.trim_start_matches('<');
+    if krate.is_empty() || !krate.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return None;
+    }
+    Some(if STD_CRATES.contains(&krate) { "std" } else { krate })
+}
+
+/// Rename crates to the packages providing them and note which direct dependencies of
+/// `package` pull each one in.
+fn resolve_packages(report: &mut BloatReport, metadata: &Metadata, package: &PackageId) {
+    let Some(resolve) = &metadata.resolve else { return };
+    let mut lib_names: BTreeMap<String, &PackageId> = BTreeMap::new();
+    for dependency in &metadata.packages {
+        for target in dependency.targets.iter().filter(|target| target.is_lib() || target.is_bin())
+        {
+            lib_names.insert(target.name.replace('-', "_"), &dependency.id);
+        }
+    }
+    let name_of = |id: &PackageId| {
+        metadata
+            .packages
+            .iter()
+            .find(|package| package.id == *id)
+            .map(|package| package.name.clone())
+    };
+    let dependencies_of = |id: &PackageId| {
+        resolve
+            .nodes
+            .iter()
+            .find(|node| node.id == *id)
+            .map(|node| node.dependencies.clone())
+            .unwrap_or_default()
+    };
+    // Everything each direct dependency reaches
+    let direct = dependencies_of(package);
+    let reach: Vec<(String, BTreeSet<PackageId>)> = direct
+        .iter()
+        .map(|dependency| {
+            let mut reached = BTreeSet::from([dependency.clone()]);
+            let mut queue = VecDeque::from([dependency.clone()]);
+            while let Some(id) = queue.pop_front() {
+                for next in dependencies_of(&id) {
+                    if reached.insert(next.clone()) {
+                        queue.push_back(next);
+                    }
+                }
+            }
+            (name_of(dependency).unwrap_or_default(), reached)
+        })
+        .collect();
+
+    // Crates no package provides are the standard library's own dependencies (gimli,
+    // addr2line, ...)
+    let in_std = |krate: &str| krate != UNKNOWN && !lib_names.contains_key(krate);
+    let mut merged: BTreeMap<String, CrateSize> = BTreeMap::new();
+    for mut krate in std::mem::take(&mut report.crates) {
+        if in_std(&krate.name) {
+            krate.name = "std".to_string();
+        }
+        match merged.get_mut(&krate.name) {
+            Some(existing) => {
+                existing.size += krate.size;
+                existing.share += krate.share;
+            }
+            None => {
+                merged.insert(krate.name.clone(), krate);
+            }
+        }
+    }
+    report.crates = merged.into_values().collect();
+    report.crates.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+
+    for krate in &mut report.crates {
+        let Some(id) = lib_names.get(&krate.name) else { continue };
+        if let Some(name) = name_of(id) {
+            krate.name = name;
+        }
+        if *id == package {
+            continue;
+        }
+        krate.direct = direct.contains(id);
+        if !krate.direct {
+            krate.pulled_in_by = reach
+                .iter()
+                .filter(|(_, reached)| reached.contains(*id))
+                .map(|(name, _)| name.clone())
+                .collect();
+        }
+    }
+    for function in &mut report.functions {
+        if in_std(&function.krate) {
+            function.krate = "std".to_string();
+        } else if let Some(name) = lib_names.get(&function.krate).and_then(|id| name_of(id)) {
+            function.krate = name;
+        }
+    }
+}
+
+/// Suggestions for the dependencies that take up the most code
+fn suggestions(report: &BloatReport) -> Vec<OptimizationSuggestion> {
+    report
+        .crates
+        .iter()
+        .filter(|krate| krate.share >= SUGGESTION_THRESHOLD)
+        .filter_map(|krate| {
+            let size = format!("{} KiB ({:.1}%)", krate.size / 1024, krate.share * 100.0);
+            let (target, reason) = if krate.direct {
+                (krate.name.clone(), format!("{} accounts for {} of the code", krate.name, size))
+            } else if !krate.pulled_in_by.is_empty() {
+                let pulled_in_by = krate.pulled_in_by.join(" and ");
+                let reason = format!(
+                    "{} pulls in {}, which accounts for {} of the code",
+                    pulled_in_by, krate.name, size
+                );
+                (krate.pulled_in_by.join(", "), reason)
+            } else {
+                // The package itself, std or code of unknown origin
+                return None;
+            };
+            Some(OptimizationSuggestion {
+                action: OptimizationAction::ReplaceDependency,
+                target,
+                reason,
+                impact: format!("Up to {} smaller binary", size),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attributes_symbols_to_crates() {
+        assert_eq!(crate_of("serde_json::de::from_str"), Some("serde_json"));
+        assert_eq!(crate_of("<serde_json::Value as core::fmt::Display>::fmt"), Some("serde_json"));
+        assert_eq!(
+            crate_of("<&mut tokio::net::TcpStream as core::fmt::Debug>::fmt"),
+            Some("tokio")
+        );
+        assert_eq!(crate_of("<u32 as blake3::Foo>::bar"), Some("blake3"));
+        assert_eq!(crate_of("core::ptr::drop_in_place<alloc::string::String>"), Some("std"));
+        assert_eq!(crate_of("memcpy"), None);
+
+        // The test binary itself has a symbol table
+        let binary = std::env::current_exe().unwrap();
+        let report = attribute(&binary, &std::fs::read(&binary).unwrap()).unwrap();
+        assert!(report.text_size > 0 && report.text_size <= report.file_size);
+        let name = |krate: &CrateSize| krate.name.clone();
+        let crates: Vec<String> = report.crates.iter().map(name).collect();
+        assert!(crates.contains(&"std".to_string()));
+        assert!(crates.contains(&"parflow_crate_orchestrator".to_string()));
+        assert!(report.functions.windows(2).all(|pair| pair[0].size >= pair[1].size));
+    }
+}
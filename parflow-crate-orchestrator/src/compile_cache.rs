@@ -0,0 +1,458 @@
+//! Compilation cache for Rust builds, shared through an artifact store
+//!
+//! `parflow build` runs cargo with the parflow binary as `RUSTC_WRAPPER`, and every rustc
+//! invocation goes through [`wrap_rustc`]. A library crate's fingerprint hashes the compiler
+//! version, its arguments with machine-specific paths left out, the `CARGO_*` environment, the
+//! files of its package and build script output, and the content of every `--extern` dependency.
+//! On a hit the crate's outputs are fetched from the store, along with the diagnostics rustc
+//! printed when it built them; on a miss rustc runs and its outputs are stored. Pointing every
+//! participant of a live session at the same S3 or GCS store lets their builds reuse each
+//! other's crates, and each entry remembers who built it.
+//!
+//! Binaries, proc macros and incremental builds are passed to rustc untouched. Each cached
+//! invocation appends a [`CacheEvent`] to the stats file, from which [`CacheStats`] reports
+//! hit rates once the build is over.
+
+use anyhow::{bail, Context, Result};
+use parflow_artifacts::cas::ContentStore;
+use parflow_artifacts::ArtifactStore;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Artifact store (path, URL or JSON storage config) the wrapper caches into; set by
+/// `parflow build`
+pub const CACHE_ENV: &str = "PARFLOW_COMPILE_CACHE";
+/// JSON lines file the wrapper appends a [`CacheEvent`] to per cached invocation
+pub const STATS_ENV: &str = "PARFLOW_COMPILE_CACHE_STATS";
+/// Name recorded as the builder of stored entries; defaults to `$USER`
+pub const PARTICIPANT_ENV: &str = "PARFLOW_PARTICIPANT";
+
+const ENTRIES_PREFIX: &str = "compile-cache";
+/// Bumped whenever what goes into a fingerprint changes
+const FINGERPRINT_VERSION: &str = "parflow-compile-cache-1";
+/// Cargo variables that hold paths or jobserver details of this machine
+const MACHINE_ENV: &[&str] = &[
+    "CARGO",
+    "CARGO_HOME",
+    "CARGO_MAKEFLAGS",
+    "CARGO_MANIFEST_DIR",
+    "CARGO_MANIFEST_PATH",
+    "CARGO_TARGET_DIR",
+    "CARGO_TARGET_TMPDIR",
+];
+
+/// A rustc command line, as cargo hands it to the wrapper
+#[derive(Debug, Clone)]
+pub struct RustcInvocation {
+    pub rustc: PathBuf,
+    pub args: Vec<String>,
+    pub crate_name: Option<String>,
+    pub crate_types: Vec<String>,
+    pub extra_filename: String,
+    pub out_dir: Option<PathBuf>,
+    pub input: Option<PathBuf>,
+    pub incremental: bool,
+}
+
+impl RustcInvocation {
+    pub fn parse(rustc: impl Into<PathBuf>, args: &[String]) -> Self {
+        let mut invocation = Self {
+            rustc: rustc.into(),
+            args: args.to_vec(),
+            crate_name: None,
+            crate_types: Vec::new(),
+            extra_filename: String::new(),
+            out_dir: None,
+            input: None,
+            incremental: false,
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = || inline.clone().or_else(|| args.next().cloned()).unwrap_or_default();
+            match flag {
+                "--crate-name" => invocation.crate_name = Some(value()),
+                "--crate-type" => {
+                    let types = value();
+                    invocation.crate_types.extend(types.split(',').map(str::to_string));
+                }
+                "--out-dir" => invocation.out_dir = Some(PathBuf::from(value())),
+                "-C" | "--codegen" => {
+                    let option = value();
+                    if let Some(extra) = option.strip_prefix("extra-filename=") {
+                        invocation.extra_filename = extra.to_string();
+                    }
+                    invocation.incremental |= option.starts_with("incremental=");
+                }
+                "--edition" | "--emit" | "--error-format" | "--json" | "--cfg" | "--cap-lints"
+                | "--target" | "--extern" | "--check-cfg" | "-L" | "-l" | "-A" | "-W" | "-D"
+                | "-o" => {
+                    value();
+                }
+                other if !other.starts_with('-') && other.ends_with(".rs") => {
+                    invocation.input = Some(PathBuf::from(other));
+                }
+                _ => {}
+            }
+        }
+        invocation
+    }
+
+    /// Whether the outputs are libraries this cache can store: no linking, no incremental
+    /// state, and a known place to find them
+    pub fn is_cacheable(&self) -> bool {
+        self.crate_name.is_some()
+            && self.out_dir.is_some()
+            && self.input.is_some()
+            && !self.incremental
+            && !self.crate_types.is_empty()
+            && self.crate_types.iter().all(|kind| kind == "lib" || kind == "rlib")
+    }
+
+    /// Whether `file_name` in the output directory was produced by this invocation
+    fn owns_output(&self, file_name: &str) -> bool {
+        let Some(crate_name) = &self.crate_name else { return false };
+        let stem = format!("{}{}.", crate_name, self.extra_filename);
+        file_name.starts_with(&stem)
+            || file_name.strip_prefix("lib").is_some_and(|rest| rest.starts_with(&stem))
+    }
+}
+
+/// What the cache stores per fingerprint, under `compile-cache/<fingerprint>.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub crate_name: String,
+    pub produced_by: String,
+    pub created: i64,
+    /// Output file names in the out dir, with the blake3 digest of their content
+    pub outputs: Vec<(String, String)>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEvent {
+    pub crate_name: String,
+    pub hit: bool,
+    /// Who built the reused entry, on a hit
+    pub produced_by: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    /// Hits per participant whose build produced the entry
+    pub reused_from: BTreeMap<String, usize>,
+}
+
+impl CacheStats {
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut stats = Self::default();
+        let Ok(text) = std::fs::read_to_string(path) else { return Ok(stats) };
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let event: CacheEvent = serde_json::from_str(line)
+                .with_context(|| format!("parsing cache event in {}", path.display()))?;
+            if event.hit {
+                stats.hits += 1;
+                let from = event.produced_by.unwrap_or_else(|| "unknown".to_string());
+                *stats.reused_from.entry(from).or_default() += 1;
+            } else {
+                stats.misses += 1;
+            }
+        }
+        Ok(stats)
+    }
+
+    pub fn total(&self) -> usize {
+        self.hits + self.misses
+    }
+
+    /// Share of cached invocations served from the cache, from 0 to 1
+    pub fn hit_rate(&self) -> Option<f64> {
+        (self.total() > 0).then(|| self.hits as f64 / self.total() as f64)
+    }
+}
+
+pub fn participant() -> String {
+    std::env::var(PARTICIPANT_ENV)
+        .or_else(|_| std::env::var("USER"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// Run `invocation` through the cache and return rustc's exit code. The cache never fails a
+/// build: if it cannot be used, rustc simply runs.
+pub async fn wrap_rustc(store: &dyn ArtifactStore, invocation: &RustcInvocation) -> Result<i32> {
+    if !invocation.is_cacheable() {
+        let status = Command::new(&invocation.rustc).args(&invocation.args).status()?;
+        return Ok(status.code().unwrap_or(1));
+    }
+    let cas = ContentStore::new(store);
+    let fingerprint = fingerprint(invocation).ok();
+    if let Some(fingerprint) = &fingerprint {
+        if let Ok(entry) = restore(store, &cas, fingerprint, invocation).await {
+            print!("{}", entry.stdout);
+            eprint!("{}", entry.stderr);
+            record(&CacheEvent {
+                crate_name: entry.crate_name,
+                hit: true,
+                produced_by: Some(entry.produced_by),
+            });
+            return Ok(0);
+        }
+    }
+
+    let output =
+        Command::new(&invocation.rustc).args(&invocation.args).stdin(Stdio::inherit()).output()?;
+    std::io::stdout().write_all(&output.stdout)?;
+    std::io::stderr().write_all(&output.stderr)?;
+    let code = output.status.code().unwrap_or(1);
+    if let (Some(fingerprint), 0) = (&fingerprint, code) {
+        let stored = save(
+            store,
+            &cas,
+            fingerprint,
+            invocation,
+            String::from_utf8_lossy(&output.stdout).into_owned(),
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        )
+        .await;
+        if stored.is_ok() {
+            record(&CacheEvent {
+                crate_name: invocation.crate_name.clone().unwrap_or_default(),
+                hit: false,
+                produced_by: None,
+            });
+        }
+    }
+    Ok(code)
+}
+
+async fn restore(
+    store: &dyn ArtifactStore,
+    cas: &ContentStore<'_>,
+    fingerprint: &str,
+    invocation: &RustcInvocation,
+) -> Result<CacheEntry> {
+    let entry: CacheEntry = serde_json::from_slice(&store.get(&entry_key(fingerprint)).await?)?;
+    let out_dir = invocation.out_dir.as_deref().context("no --out-dir")?;
+    for (name, digest) in &entry.outputs {
+        if name.contains(['/', '\\']) || name.starts_with('.') {
+            bail!("unsafe output name in cache entry: {:?}", name);
+        }
+        cas.get_file(digest, &out_dir.join(name)).await?;
+    }
+    Ok(entry)
+}
+
+async fn save(
+    store: &dyn ArtifactStore,
+    cas: &ContentStore<'_>,
+    fingerprint: &str,
+    invocation: &RustcInvocation,
+    stdout: String,
+    stderr: String,
+) -> Result<()> {
+    let out_dir = invocation.out_dir.as_deref().context("no --out-dir")?;
+    let mut outputs = Vec::new();
+    for entry in std::fs::read_dir(out_dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if entry.file_type()?.is_file() && invocation.owns_output(&name) {
+            outputs.push((name, cas.put_file(&entry.path()).await?.digest));
+        }
+    }
+    if outputs.is_empty() {
+        bail!("rustc produced no outputs in {}", out_dir.display());
+    }
+    outputs.sort();
+    let entry = CacheEntry {
+        crate_name: invocation.crate_name.clone().unwrap_or_default(),
+        produced_by: participant(),
+        created: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or_default(),
+        outputs,
+        stdout,
+        stderr,
+    };
+    store.put(&entry_key(fingerprint), &serde_json::to_vec(&entry)?).await?;
+    Ok(())
+}
+
+fn entry_key(fingerprint: &str) -> String {
+    format!("{}/{}.json", ENTRIES_PREFIX, fingerprint)
+}
+
+fn record(event: &CacheEvent) {
+    let Some(path) = std::env::var_os(STATS_ENV) else { return };
+    let Ok(line) = serde_json::to_string(event) else { return };
+    // Cargo runs rustc in parallel; one short append per event keeps lines whole
+    if let Ok(mut file) = std::fs::OpenOptions::new().create(true).append(true).open(path) {
+        let _ = file.write_all(format!("{}\n", line).as_bytes());
+    }
+}
+
+/// The crate fingerprint of a cacheable invocation
+pub fn fingerprint(invocation: &RustcInvocation) -> Result<String> {
+    let mut hasher = blake3::Hasher::new();
+    let mut field = |label: &str, value: &[u8]| {
+        hasher.update(label.as_bytes());
+        hasher.update(&(value.len() as u64).to_le_bytes());
+        hasher.update(value);
+    };
+    field("version", FINGERPRINT_VERSION.as_bytes());
+
+    let version = Command::new(&invocation.rustc).arg("-vV").output()?;
+    if !version.status.success() {
+        bail!("{} -vV failed", invocation.rustc.display());
+    }
+    field("rustc", &version.stdout);
+
+    let manifest_dir = std::env::var_os("CARGO_MANIFEST_DIR").map(PathBuf::from);
+    let package = manifest_dir
+        .clone()
+        .or_else(|| invocation.input.as_ref()?.parent().map(Path::to_path_buf))
+        .context("no package directory")?;
+    let package = package.canonicalize().unwrap_or(package);
+    let cwd = std::env::current_dir()?;
+    let machine_paths =
+        [package.to_string_lossy().into_owned(), cwd.to_string_lossy().into_owned()];
+    let portable = |arg: &str| {
+        machine_paths
+            .iter()
+            .filter(|path| !path.is_empty())
+            .fold(arg.to_string(), |arg, path| arg.replace(path.as_str(), "<dir>"))
+    };
+
+    let mut args = invocation.args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            // Where outputs and dependencies live differs between machines; dependencies
+            // count through their content below
+            "--out-dir" | "-L" => {
+                args.next();
+            }
+            "--extern" => {
+                let value = args.next().map(String::as_str).unwrap_or_default();
+                let Some((name, path)) = value.split_once('=') else {
+                    // A sysroot crate such as `proc_macro`, covered by the compiler version
+                    field("extern", value.as_bytes());
+                    continue;
+                };
+                let content =
+                    std::fs::read(path).with_context(|| format!("reading dependency {}", path))?;
+                field("extern", name.as_bytes());
+                field("extern-content", blake3::hash(&content).as_bytes());
+            }
+            arg if arg.starts_with("--out-dir=") || arg.starts_with("-Ldependency=") => {}
+            arg => field("arg", portable(arg).as_bytes()),
+        }
+    }
+
+    let mut env: Vec<(String, String)> = std::env::vars()
+        .filter(|(name, _)| name.starts_with("CARGO_") || name == "CARGO")
+        .filter(|(name, _)| !MACHINE_ENV.contains(&name.as_str()))
+        .collect();
+    env.sort();
+    for (name, value) in env {
+        field("env", format!("{}={}", name, portable(&value)).as_bytes());
+    }
+
+    hash_tree(&mut field, "source", &package)?;
+    if let Some(out_dir) = std::env::var_os("OUT_DIR") {
+        hash_tree(&mut field, "build-script", Path::new(&out_dir))?;
+    }
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Feed the relative path and content of every file under `dir` to `field`, skipping build
+/// output and hidden directories
+fn hash_tree(field: &mut impl FnMut(&str, &[u8]), label: &str, dir: &Path) -> Result<()> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&current) else { continue };
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            let Ok(kind) = entry.file_type() else { continue };
+            if kind.is_dir() {
+                if !name.starts_with('.') && name != "target" {
+                    pending.push(entry.path());
+                }
+            } else if kind.is_file() {
+                files.push(entry.path());
+            }
+        }
+    }
+    files.sort();
+    for file in files {
+        let relative = file.strip_prefix(dir).unwrap_or(&file).to_string_lossy().replace('\\', "/");
+        field(label, relative.as_bytes());
+        field("content", blake3::hash(&std::fs::read(&file)?).as_bytes());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_parses_cargo_invocations() {
+        let lib = RustcInvocation::parse(
+            "rustc",
+            &args(
+                "--crate-name demo --edition=2021 src/lib.rs --error-format=json \
+                 --crate-type lib --emit=dep-info,metadata,link -C embed-bitcode=no \
+                 -C extra-filename=-1a2b --out-dir /t/debug/deps -L dependency=/t/debug/deps \
+                 --extern serde=/t/debug/deps/libserde-9f.rmeta",
+            ),
+        );
+        assert!(lib.is_cacheable());
+        assert_eq!(lib.crate_name.as_deref(), Some("demo"));
+        assert_eq!(lib.input.as_deref(), Some(Path::new("src/lib.rs")));
+        assert!(lib.owns_output("libdemo-1a2b.rlib"));
+        assert!(lib.owns_output("demo-1a2b.d"));
+        assert!(!lib.owns_output("libdemo_macros-77.rlib"));
+
+        let bin = RustcInvocation::parse(
+            "rustc",
+            &args("--crate-name demo src/main.rs --crate-type bin --out-dir /t/debug/deps"),
+        );
+        assert!(!bin.is_cacheable());
+        let incremental = RustcInvocation::parse(
+            "rustc",
+            &args(
+                "--crate-name demo src/lib.rs --crate-type lib --out-dir /t -C incremental=/t/inc",
+            ),
+        );
+        assert!(!incremental.is_cacheable());
+        assert!(!RustcInvocation::parse("rustc", &args("-vV")).is_cacheable());
+
+        let stats = std::env::temp_dir()
+            .join(format!("parflow-compile-cache-stats-{}.jsonl", std::process::id()));
+        let events = [
+            CacheEvent { crate_name: "a".into(), hit: true, produced_by: Some("ana".into()) },
+            CacheEvent { crate_name: "b".into(), hit: false, produced_by: None },
+        ];
+        let lines: Vec<String> = events.iter().map(|e| serde_json::to_string(e).unwrap()).collect();
+        std::fs::write(&stats, lines.join("\n")).unwrap();
+        let stats_loaded = CacheStats::load(&stats).unwrap();
+        assert_eq!(stats_loaded.hit_rate(), Some(0.5));
+        assert_eq!(stats_loaded.reused_from["ana"], 1);
+        let _ = std::fs::remove_file(&stats);
+    }
+}
@@ -0,0 +1,726 @@
+//! Reproducible development environments for `parflow mirror-env`
+//!
+//! The source directory and its immediate subdirectories are searched for Rust, Python,
+//! JavaScript and Go projects. Each becomes a [`Toolchain`] with the version it pins
+//! (`rust-toolchain.toml`, `.python-version`, `.nvmrc`, `go.mod`, or failing those the minimum
+//! its manifest requires) and its direct dependencies, at their locked versions where a lockfile
+//! has them. The toolchains are written out as a `flake.nix` dev shell or a
+//! `.devcontainer/devcontainer.json`, together with the commands that set the projects up.
+//!
+//! [`verify`] runs those commands: first one per toolchain checking that the version on the
+//! `PATH` is the pinned one, then the setup of every project. Run it inside the generated
+//! environment (`nix develop --command ...`, or in the dev container) to check that the
+//! environment really builds the source.
+
+use crate::lockfiles::Ecosystem;
+use crate::DependencyInfo;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Directories never searched for projects
+const SKIPPED_DIRS: &[&str] = &["target", "node_modules", "venv", ".venv", "dist", "build"];
+/// Nixpkgs release the generated flake follows; `nix flake lock` pins the exact revision
+const NIXPKGS: &str = "github:NixOS/nixpkgs/nixos-24.05";
+const DEVCONTAINER_IMAGE: &str = "mcr.microsoft.com/devcontainers/base:bookworm";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolchainKind {
+    Rust,
+    Python,
+    Node,
+    Go,
+}
+
+impl ToolchainKind {
+    pub const ALL: [ToolchainKind; 4] = [Self::Rust, Self::Python, Self::Node, Self::Go];
+
+    pub fn language(self) -> &'static str {
+        match self {
+            Self::Rust => "rust",
+            Self::Python => "python",
+            Self::Node => "javascript",
+            Self::Go => "go",
+        }
+    }
+
+    /// Where the packages of this toolchain come from; Go modules have no license metadata
+    pub fn ecosystem(self) -> Option<Ecosystem> {
+        match self {
+            Self::Rust => Some(Ecosystem::Cargo),
+            Self::Python => Some(Ecosystem::Python),
+            Self::Node => Some(Ecosystem::Npm),
+            Self::Go => None,
+        }
+    }
+
+    /// Whether `dir` holds a project built with this toolchain
+    pub fn detect(self, dir: &Path) -> bool {
+        let has = |name: &str| dir.join(name).is_file();
+        match self {
+            Self::Rust => has("Cargo.toml"),
+            Self::Python => {
+                has("pyproject.toml")
+                    || has("requirements.txt")
+                    || has("setup.py")
+                    || has(".python-version")
+            }
+            Self::Node => has("package.json"),
+            Self::Go => has("go.mod"),
+        }
+    }
+
+    /// Shell command that fails unless the toolchain on the `PATH` is `version`
+    pub fn version_check(self, version: Option<&str>) -> String {
+        let (command, prefix) = match self {
+            Self::Rust => ("rustc --version", "rustc "),
+            Self::Python => ("python3 --version", "Python "),
+            Self::Node => ("node --version", "v"),
+            Self::Go => ("go version", "go version go"),
+        };
+        match version.filter(|version| version.starts_with(|c: char| c.is_ascii_digit())) {
+            Some(version) => format!("{} | grep -q '^{}{}'", command, prefix, version),
+            None if version == Some("nightly") => format!("{} | grep -q nightly", command),
+            None => command.to_string(),
+        }
+    }
+
+    /// Nix expression for the toolchain package
+    fn nix_package(self, version: Option<&str>) -> String {
+        let numeric = version.filter(|version| version.starts_with(|c: char| c.is_ascii_digit()));
+        let major_minor = |version: &str| version.split('.').take(2).collect::<Vec<_>>().join("_");
+        match self {
+            Self::Rust => match version {
+                Some("nightly") => "pkgs.rust-bin.nightly.latest.default".to_string(),
+                Some(version) if numeric.is_some() => {
+                    let full = if version.split('.').count() == 2 {
+                        format!("{}.0", version)
+                    } else {
+                        version.to_string()
+                    };
+                    format!("pkgs.rust-bin.stable.\"{}\".default", full)
+                }
+                _ => "pkgs.rust-bin.stable.latest.default".to_string(),
+            },
+            Self::Python => match numeric {
+                Some(version) => format!("pkgs.python{}", major_minor(version).replace('_', "")),
+                None => "pkgs.python3".to_string(),
+            },
+            Self::Node => match numeric {
+                Some(version) => format!("pkgs.nodejs_{}", version.split('.').next().unwrap_or("")),
+                None => "pkgs.nodejs".to_string(),
+            },
+            Self::Go => match numeric {
+                Some(version) => format!("pkgs.go_{}", major_minor(version)),
+                None => "pkgs.go".to_string(),
+            },
+        }
+    }
+
+    /// Dev container feature installing the toolchain, with its options
+    fn devcontainer_feature(self, version: Option<&str>) -> (&'static str, Value) {
+        let feature = match self {
+            Self::Rust => "ghcr.io/devcontainers/features/rust:1",
+            Self::Python => "ghcr.io/devcontainers/features/python:1",
+            Self::Node => "ghcr.io/devcontainers/features/node:1",
+            Self::Go => "ghcr.io/devcontainers/features/go:1",
+        };
+        let version = match version {
+            None | Some("stable") => "latest",
+            Some(version) => version,
+        };
+        (feature, serde_json::json!({ "version": version }))
+    }
+}
+
+impl fmt::Display for ToolchainKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Rust => "rust",
+            Self::Python => "python",
+            Self::Node => "node",
+            Self::Go => "go",
+        })
+    }
+}
+
+/// A project found in the source and the toolchain that builds it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Toolchain {
+    pub kind: ToolchainKind,
+    /// Pinned version or release channel, `None` when the project does not say
+    pub version: Option<String>,
+    /// Project directory relative to the source, `.` for the source itself
+    pub dir: String,
+    pub dependencies: Vec<DependencyInfo>,
+    /// Shell commands, run from the source directory, that fetch dependencies and build
+    pub setup: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EnvironmentFormat {
+    Nix,
+    #[default]
+    Devcontainer,
+}
+
+impl EnvironmentFormat {
+    /// Where the spec is written, relative to the target directory
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Self::Nix => "flake.nix",
+            Self::Devcontainer => ".devcontainer/devcontainer.json",
+        }
+    }
+}
+
+impl FromStr for EnvironmentFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "nix" | "flake" => Ok(Self::Nix),
+            "devcontainer" => Ok(Self::Devcontainer),
+            other => bail!("unknown environment format '{}' (expected nix or devcontainer)", other),
+        }
+    }
+}
+
+/// Outcome of one setup command under [`verify`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetupCheck {
+    pub command: String,
+    pub success: bool,
+    pub exit_code: Option<i32>,
+    pub output: String,
+}
+
+/// Every project in `source` and its immediate, non-hidden subdirectories. Members of a Cargo
+/// workspace at the root are folded into the root project.
+pub fn detect(source: &Path) -> Vec<Toolchain> {
+    let root_workspace = read(source, "Cargo.toml").contains("[workspace]");
+    let mut toolchains = Vec::new();
+    for dir in project_dirs(source) {
+        let relative = match dir.strip_prefix(source) {
+            Ok(relative) if !relative.as_os_str().is_empty() => {
+                relative.to_string_lossy().replace('\\', "/")
+            }
+            _ => ".".to_string(),
+        };
+        for kind in ToolchainKind::ALL {
+            if !kind.detect(&dir)
+                || (kind == ToolchainKind::Rust && root_workspace && relative != ".")
+            {
+                continue;
+            }
+            let (version, dependencies) = match kind {
+                ToolchainKind::Rust => {
+                    let members = if root_workspace && relative == "." {
+                        project_dirs(source)
+                    } else {
+                        vec![dir.clone()]
+                    };
+                    (rust_version(&dir), rust_dependencies(&dir, &members))
+                }
+                ToolchainKind::Python => (python_version(&dir), python_dependencies(&dir)),
+                ToolchainKind::Node => (node_version(&dir), node_dependencies(&dir)),
+                ToolchainKind::Go => go_module(&dir),
+            };
+            let setup = setup_commands(kind, &dir)
+                .into_iter()
+                .map(|command| match relative.as_str() {
+                    "." => command,
+                    dir => format!("cd {} && {}", dir, command),
+                })
+                .collect();
+            toolchains.push(Toolchain {
+                kind,
+                version,
+                dir: relative.clone(),
+                dependencies,
+                setup,
+            });
+        }
+    }
+    toolchains
+}
+
+/// The version each kind of toolchain is pinned to: the first project that names one wins
+pub fn pinned_versions(toolchains: &[Toolchain]) -> BTreeMap<ToolchainKind, Option<String>> {
+    let mut versions = BTreeMap::new();
+    for toolchain in toolchains {
+        let pinned = versions.entry(toolchain.kind).or_insert(None);
+        if pinned.is_none() {
+            *pinned = toolchain.version.clone();
+        }
+    }
+    versions
+}
+
+/// Projects whose version differs from the one the environment pins
+pub fn version_conflicts(toolchains: &[Toolchain]) -> Vec<String> {
+    let pinned = pinned_versions(toolchains);
+    toolchains
+        .iter()
+        .filter_map(|toolchain| {
+            let version = toolchain.version.as_ref()?;
+            let chosen = pinned.get(&toolchain.kind)?.as_ref()?;
+            (version != chosen).then(|| {
+                format!(
+                    "{} asks for {} {}, the environment provides {}",
+                    toolchain.dir, toolchain.kind, version, chosen
+                )
+            })
+        })
+        .collect()
+}
+
+/// Commands that check the toolchain versions, followed by the setup of every project
+pub fn setup_commands_for(toolchains: &[Toolchain]) -> Vec<String> {
+    let mut commands: Vec<String> = pinned_versions(toolchains)
+        .into_iter()
+        .map(|(kind, version)| kind.version_check(version.as_deref()))
+        .collect();
+    commands.extend(toolchains.iter().flat_map(|toolchain| toolchain.setup.iter().cloned()));
+    commands
+}
+
+/// A `flake.nix` whose default dev shell provides the toolchains
+pub fn render_flake(name: &str, toolchains: &[Toolchain]) -> String {
+    let pinned = pinned_versions(toolchains);
+    let rust = pinned.contains_key(&ToolchainKind::Rust);
+    let mut flake = String::new();
+    flake.push_str("# Generated by `parflow mirror-env`; run `nix flake lock` to pin nixpkgs\n");
+    flake.push_str("{\n");
+    flake.push_str(&format!("  description = \"Development environment for {}\";\n\n", name));
+    flake.push_str("  inputs = {\n");
+    flake.push_str(&format!("    nixpkgs.url = \"{}\";\n", NIXPKGS));
+    flake.push_str("    flake-utils.url = \"github:numtide/flake-utils\";\n");
+    if rust {
+        flake.push_str("    rust-overlay.url = \"github:oxalica/rust-overlay\";\n");
+        flake.push_str("    rust-overlay.inputs.nixpkgs.follows = \"nixpkgs\";\n");
+    }
+    flake.push_str("  };\n\n");
+    if rust {
+        flake.push_str("  outputs = { nixpkgs, flake-utils, rust-overlay, ... }:\n");
+    } else {
+        flake.push_str("  outputs = { nixpkgs, flake-utils, ... }:\n");
+    }
+    flake.push_str("    flake-utils.lib.eachDefaultSystem (system:\n");
+    flake.push_str("      let\n");
+    if rust {
+        flake.push_str(
+            "        pkgs = import nixpkgs { inherit system; overlays = [ rust-overlay.overlays.default ]; };\n",
+        );
+    } else {
+        flake.push_str("        pkgs = import nixpkgs { inherit system; };\n");
+    }
+    flake.push_str("      in {\n");
+    flake.push_str("        devShells.default = pkgs.mkShell {\n");
+    flake.push_str("          packages = [\n");
+    for (kind, version) in &pinned {
+        flake.push_str(&format!("            {}\n", kind.nix_package(version.as_deref())));
+    }
+    flake.push_str("          ];\n");
+    flake.push_str("        };\n");
+    flake.push_str("      });\n");
+    flake.push_str("}\n");
+    for toolchain in toolchains.iter().filter(|toolchain| !toolchain.dependencies.is_empty()) {
+        flake.push_str(&format!("\n# {} dependencies of {}:\n", toolchain.kind, toolchain.dir));
+        for dependency in &toolchain.dependencies {
+            flake.push_str(&format!("#   {} {}\n", dependency.name, dependency.version));
+        }
+    }
+    flake
+}
+
+/// A `devcontainer.json` installing the toolchains as features and setting the projects up
+/// once the container is created
+pub fn render_devcontainer(name: &str, toolchains: &[Toolchain]) -> Value {
+    let features: serde_json::Map<String, Value> = pinned_versions(toolchains)
+        .into_iter()
+        .map(|(kind, version)| {
+            let (feature, options) = kind.devcontainer_feature(version.as_deref());
+            (feature.to_string(), options)
+        })
+        .collect();
+    let setup: Vec<String> =
+        toolchains.iter().flat_map(|toolchain| toolchain.setup.iter().cloned()).collect();
+    let dependencies: serde_json::Map<String, Value> = toolchains
+        .iter()
+        .filter(|toolchain| !toolchain.dependencies.is_empty())
+        .map(|toolchain| {
+            let versions: serde_json::Map<String, Value> = toolchain
+                .dependencies
+                .iter()
+                .map(|dependency| (dependency.name.clone(), dependency.version.clone().into()))
+                .collect();
+            (format!("{}:{}", toolchain.kind, toolchain.dir), Value::Object(versions))
+        })
+        .collect();
+    let mut spec = serde_json::json!({
+        "name": name,
+        "image": DEVCONTAINER_IMAGE,
+        "features": features,
+        "customizations": { "parflow": { "dependencies": dependencies } },
+    });
+    if !setup.is_empty() {
+        spec["postCreateCommand"] = setup.join(" && ").into();
+    }
+    spec
+}
+
+/// Write the spec for `format` under `target`, returning the path written.
+pub fn write_spec(
+    target: &Path,
+    format: EnvironmentFormat,
+    name: &str,
+    toolchains: &[Toolchain],
+) -> Result<PathBuf> {
+    let path = target.join(format.file_name());
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("creating {}", parent.display()))?;
+    }
+    let contents = match format {
+        EnvironmentFormat::Nix => render_flake(name, toolchains),
+        EnvironmentFormat::Devcontainer => {
+            serde_json::to_string_pretty(&render_devcontainer(name, toolchains))? + "\n"
+        }
+    };
+    std::fs::write(&path, contents).with_context(|| format!("writing {}", path.display()))?;
+    Ok(path)
+}
+
+/// Run `commands` in order from `source`, each through `sh -c`.
+pub async fn verify(source: &Path, commands: &[String]) -> Vec<SetupCheck> {
+    let mut checks = Vec::new();
+    for command in commands {
+        tracing::info!(%command, "🧪 Verifying setup command");
+        let output = tokio::process::Command::new("sh")
+            .args(["-c", command])
+            .current_dir(source)
+            .output()
+            .await;
+        checks.push(match output {
+            Ok(output) => {
+                let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+                text.push_str(&String::from_utf8_lossy(&output.stderr));
+                SetupCheck {
+                    command: command.clone(),
+                    success: output.status.success(),
+                    exit_code: output.status.code(),
+                    output: text,
+                }
+            }
+            Err(e) => SetupCheck {
+                command: command.clone(),
+                success: false,
+                exit_code: None,
+                output: format!("could not start sh: {}", e),
+            },
+        });
+    }
+    checks
+}
+
+pub(crate) fn project_dirs(root: &Path) -> Vec<PathBuf> {
+    let mut dirs = vec![root.to_path_buf()];
+    if let Ok(entries) = std::fs::read_dir(root) {
+        let mut children: Vec<PathBuf> = entries
+            .flatten()
+            .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+            .filter(|entry| {
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_ref())
+            })
+            .map(|entry| entry.path())
+            .collect();
+        children.sort();
+        dirs.extend(children);
+    }
+    dirs
+}
+
+fn read(dir: &Path, name: &str) -> String {
+    std::fs::read_to_string(dir.join(name)).unwrap_or_default()
+}
+
+fn first_line(dir: &Path, name: &str) -> Option<String> {
+    let contents = read(dir, name);
+    let line = contents.lines().map(str::trim).find(|line| !line.is_empty())?;
+    Some(line.trim_start_matches('v').to_string())
+}
+
+/// The version in a requirement such as `>=3.9,<4` or `^20.1`
+fn minimum_version(requirement: &str) -> Option<String> {
+    let first = requirement.split([',', ' ', '|']).find(|part| !part.is_empty())?;
+    let version = first.trim_start_matches(['>', '=', '~', '^', 'v']);
+    version.starts_with(|c: char| c.is_ascii_digit()).then(|| version.trim_end_matches(".x").into())
+}
+
+fn dependency(name: &str, version: String) -> DependencyInfo {
+    DependencyInfo {
+        name: name.to_string(),
+        version,
+        used: true,
+        deprecated: false,
+        alternative: None,
+        license: None,
+    }
+}
+
+fn setup_commands(kind: ToolchainKind, dir: &Path) -> Vec<String> {
+    let has = |name: &str| dir.join(name).is_file();
+    let commands: Vec<&str> = match kind {
+        ToolchainKind::Rust if has("Cargo.lock") => vec!["cargo fetch --locked", "cargo build"],
+        ToolchainKind::Rust => vec!["cargo fetch", "cargo build"],
+        ToolchainKind::Python if has("requirements.txt") => {
+            vec!["python3 -m venv .venv", ".venv/bin/pip install -r requirements.txt"]
+        }
+        ToolchainKind::Python if has("pyproject.toml") || has("setup.py") => {
+            vec!["python3 -m venv .venv", ".venv/bin/pip install -e ."]
+        }
+        ToolchainKind::Python => vec!["python3 -m venv .venv"],
+        ToolchainKind::Node if has("package-lock.json") => vec!["npm ci"],
+        ToolchainKind::Node if has("yarn.lock") => vec!["yarn install --frozen-lockfile"],
+        ToolchainKind::Node if has("pnpm-lock.yaml") => vec!["pnpm install --frozen-lockfile"],
+        ToolchainKind::Node => vec!["npm install"],
+        ToolchainKind::Go => vec!["go mod download", "go build ./..."],
+    };
+    commands.into_iter().map(String::from).collect()
+}
+
+fn rust_version(dir: &Path) -> Option<String> {
+    let channel = toml::from_str::<toml::Value>(&read(dir, "rust-toolchain.toml"))
+        .ok()
+        .and_then(|toolchain| Some(toolchain.get("toolchain")?.get("channel")?.as_str()?.into()));
+    let manifest = toml::from_str::<toml::Value>(&read(dir, "Cargo.toml")).ok();
+    let rust_version = manifest.as_ref().and_then(|manifest| {
+        let package = manifest.get("package").or_else(|| manifest.get("workspace")?.get("package"));
+        Some(package?.get("rust-version")?.as_str()?.to_string())
+    });
+    channel.or_else(|| first_line(dir, "rust-toolchain")).or(rust_version)
+}
+
+/// Direct dependencies declared by the `members` manifests, at the versions `Cargo.lock` in
+/// `dir` locks them to
+fn rust_dependencies(dir: &Path, members: &[PathBuf]) -> Vec<DependencyInfo> {
+    let locked: BTreeMap<String, String> = toml::from_str::<toml::Value>(&read(dir, "Cargo.lock"))
+        .ok()
+        .and_then(|lock| lock.get("package")?.as_array().cloned())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|package| {
+            let name = package.get("name")?.as_str()?;
+            Some((name.to_string(), package.get("version")?.as_str()?.to_string()))
+        })
+        .collect();
+    let mut dependencies = BTreeMap::new();
+    for member in members {
+        let Ok(manifest) = toml::from_str::<toml::Value>(&read(member, "Cargo.toml")) else {
+            continue;
+        };
+        let tables = [
+            manifest.get("dependencies"),
+            manifest.get("workspace").and_then(|workspace| workspace.get("dependencies")),
+        ];
+        for (name, spec) in tables.into_iter().flatten().filter_map(toml::Value::as_table).flatten()
+        {
+            let required = match spec {
+                toml::Value::String(version) => Some(version.clone()),
+                spec => spec.get("version").and_then(toml::Value::as_str).map(String::from),
+            };
+            // Path dependencies without a version are part of the source
+            let Some(required) = required else { continue };
+            let version = locked.get(name).cloned().unwrap_or(required);
+            dependencies.entry(name.clone()).or_insert(version);
+        }
+    }
+    dependencies.into_iter().map(|(name, version)| dependency(&name, version)).collect()
+}
+
+fn python_version(dir: &Path) -> Option<String> {
+    first_line(dir, ".python-version").or_else(|| {
+        let project: toml::Value = toml::from_str(&read(dir, "pyproject.toml")).ok()?;
+        minimum_version(project.get("project")?.get("requires-python")?.as_str()?)
+    })
+}
+
+/// A requirement line such as `requests==2.31.0 ; python_version > "3"` as name and version
+pub(crate) fn python_requirement(line: &str) -> Option<(String, String)> {
+    let line = line.split(['#', ';']).next()?.trim();
+    if line.is_empty() || line.starts_with('-') {
+        return None;
+    }
+    let end = line.find(['=', '<', '>', '~', '!', '[', ' ']).unwrap_or(line.len());
+    let name = line[..end].trim();
+    let rest = line[end..].trim();
+    let rest = match rest.strip_prefix('[') {
+        Some(extras) => extras.split_once(']').map_or("", |(_, rest)| rest).trim(),
+        None => rest,
+    };
+    let version = match rest.strip_prefix("==") {
+        Some(pinned) => pinned.trim().to_string(),
+        None if rest.is_empty() => "*".to_string(),
+        None => rest.replace(' ', ""),
+    };
+    (!name.is_empty()).then(|| (name.to_string(), version))
+}
+
+fn python_dependencies(dir: &Path) -> Vec<DependencyInfo> {
+    let mut lines: Vec<String> = read(dir, "requirements.txt").lines().map(String::from).collect();
+    if let Ok(project) = toml::from_str::<toml::Value>(&read(dir, "pyproject.toml")) {
+        let declared = project
+            .get("project")
+            .and_then(|project| project.get("dependencies"))
+            .and_then(toml::Value::as_array);
+        lines.extend(declared.into_iter().flatten().filter_map(|d| d.as_str().map(String::from)));
+    }
+    let requirements: BTreeMap<String, String> =
+        lines.iter().filter_map(|line| python_requirement(line)).collect();
+    requirements.into_iter().map(|(name, version)| dependency(&name, version)).collect()
+}
+
+fn node_version(dir: &Path) -> Option<String> {
+    first_line(dir, ".nvmrc").or_else(|| {
+        let manifest: Value = serde_json::from_str(&read(dir, "package.json")).ok()?;
+        minimum_version(manifest.get("engines")?.get("node")?.as_str()?)
+    })
+}
+
+/// `dependencies` and `devDependencies`, at the versions `package-lock.json` installs
+fn node_dependencies(dir: &Path) -> Vec<DependencyInfo> {
+    let Ok(manifest) = serde_json::from_str::<Value>(&read(dir, "package.json")) else {
+        return Vec::new();
+    };
+    let lock = serde_json::from_str::<Value>(&read(dir, "package-lock.json")).unwrap_or_default();
+    let mut dependencies = BTreeMap::new();
+    for table in ["dependencies", "devDependencies"] {
+        let Some(declared) = manifest.get(table).and_then(Value::as_object) else { continue };
+        for (name, required) in declared {
+            let locked = lock["packages"][format!("node_modules/{}", name)]["version"].as_str();
+            let version = locked.or(required.as_str()).unwrap_or("*").to_string();
+            dependencies.entry(name.clone()).or_insert(version);
+        }
+    }
+    dependencies.into_iter().map(|(name, version)| dependency(&name, version)).collect()
+}
+
+/// The Go version (`toolchain` over `go`) and the required modules of `go.mod`
+fn go_module(dir: &Path) -> (Option<String>, Vec<DependencyInfo>) {
+    let manifest = read(dir, "go.mod");
+    let mut go = None;
+    let mut toolchain = None;
+    let mut dependencies = Vec::new();
+    let mut in_require = false;
+    for line in manifest.lines() {
+        let line = line.split("//").next().unwrap_or("").trim();
+        if in_require {
+            if line == ")" {
+                in_require = false;
+            } else if let Some((module, version)) = line.split_once(char::is_whitespace) {
+                dependencies.push(dependency(module, version.trim().to_string()));
+            }
+        } else if line == "require (" {
+            in_require = true;
+        } else if let Some(required) = line.strip_prefix("require ") {
+            if let Some((module, version)) = required.trim().split_once(char::is_whitespace) {
+                dependencies.push(dependency(module, version.trim().to_string()));
+            }
+        } else if let Some(version) = line.strip_prefix("go ") {
+            go = Some(version.trim().to_string());
+        } else if let Some(version) = line.strip_prefix("toolchain go") {
+            toolchain = Some(version.trim().to_string());
+        }
+    }
+    (toolchain.or(go), dependencies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_toolchains_and_renders_specs() {
+        let dir = std::env::temp_dir().join(format!("parflow-environment-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("web")).unwrap();
+        std::fs::create_dir_all(dir.join("worker")).unwrap();
+        std::fs::write(
+            dir.join("Cargo.toml"),
+            "[package]\nname = \"app\"\nrust-version = \"1.75\"\n\n[dependencies]\n\
+             serde = { version = \"1\", features = [\"derive\"] }\nlocal = { path = \"local\" }\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("Cargo.lock"),
+            "[[package]]\nname = \"serde\"\nversion = \"1.0.200\"\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("worker/.python-version"), "3.12\n").unwrap();
+        std::fs::write(
+            dir.join("worker/requirements.txt"),
+            "# pinned\nrequests==2.31.0\nuvicorn[standard]>=0.29 ; python_version > '3'\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("web/package.json"),
+            r#"{"engines": {"node": ">=20"}, "devDependencies": {"jest": "^29.0.0"}}"#,
+        )
+        .unwrap();
+
+        let toolchains = detect(&dir);
+        let summary: Vec<String> =
+            toolchains.iter().map(|t| format!("{} {} {:?}", t.dir, t.kind, t.version)).collect();
+        assert_eq!(
+            summary,
+            [". rust Some(\"1.75\")", "web node Some(\"20\")", "worker python Some(\"3.12\")"]
+        );
+        let versions = |t: &Toolchain| -> Vec<String> {
+            t.dependencies.iter().map(|d| format!("{} {}", d.name, d.version)).collect()
+        };
+        assert_eq!(versions(&toolchains[0]), ["serde 1.0.200"]);
+        assert_eq!(versions(&toolchains[1]), ["jest ^29.0.0"]);
+        assert_eq!(versions(&toolchains[2]), ["requests 2.31.0", "uvicorn >=0.29"]);
+
+        assert_eq!(
+            setup_commands_for(&toolchains),
+            [
+                "rustc --version | grep -q '^rustc 1.75'",
+                "python3 --version | grep -q '^Python 3.12'",
+                "node --version | grep -q '^v20'",
+                "cargo fetch --locked",
+                "cargo build",
+                "cd web && npm install",
+                "cd worker && python3 -m venv .venv",
+                "cd worker && .venv/bin/pip install -r requirements.txt",
+            ]
+        );
+
+        let flake = render_flake("app", &toolchains);
+        assert!(flake.contains("pkgs.rust-bin.stable.\"1.75.0\".default"));
+        assert!(flake.contains("pkgs.python312"));
+        assert!(flake.contains("pkgs.nodejs_20"));
+        assert!(flake.contains("#   requests 2.31.0"));
+
+        let devcontainer = render_devcontainer("app", &toolchains);
+        assert_eq!(
+            devcontainer["features"]["ghcr.io/devcontainers/features/python:1"]["version"],
+            "3.12"
+        );
+        assert_eq!(
+            devcontainer["customizations"]["parflow"]["dependencies"]["rust:."]["serde"],
+            "1.0.200"
+        );
+        assert!(devcontainer["postCreateCommand"].as_str().unwrap().starts_with("cargo fetch"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,639 @@
+//! Cargo feature minimization
+//!
+//! Every feature a workspace turns on for a dependency costs compile time whether or not the
+//! workspace uses what it enables. [`analyze`] finds out, per member and dependency, which of the
+//! enabled features are needed:
+//!
+//! 1. The dependency's source is scanned from its library root, following `mod` declarations,
+//!    for public items under `#[cfg(feature = "...")]` (directly, through `#![cfg]` on a module
+//!    file, or inside a gated `mod` or `impl` block). That maps each feature to the names it
+//!    gates.
+//! 2. The member's source files that mention the dependency are tokenized into the identifiers
+//!    they use.
+//! 3. An enabled feature is unused when it gates names and the member uses none of them.
+//!    Features that only turn on other features (`full`, `default`) are judged by those, and
+//!    features whose effect the scan cannot see (code generated by macros, optional
+//!    dependencies without gated items) are kept.
+//!
+//! The scan over-approximates usage, so a suggestion errs on keeping features. Feature
+//! unification still means another dependent can enable a feature again; with `measure`, each
+//! suggestion is tried on a copy of the workspace, timing a clean build of the member before and
+//! after, which both confirms the member still builds and measures what the change saves.
+
+use anyhow::{Context, Result};
+use cargo_metadata::{DependencyKind, Metadata, MetadataCommand, Package};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Instant;
+
+/// Directories left out of the workspace copy trial builds run in
+const COPY_SKIPPED: &[&str] = &["target", ".git", "node_modules", ".parflow"];
+
+/// Feature names mapped to the public item names they gate
+pub type GatedItems = BTreeMap<String, BTreeSet<String>>;
+
+/// What scanning a dependency's source finds out about its features
+#[derive(Debug, Clone, Default)]
+pub struct FeatureScan {
+    pub gated: GatedItems,
+    /// Features code is also compiled without (`#[cfg(not(feature = ...))]`), which switch
+    /// implementations rather than add items; `std` in most `no_std` crates
+    pub switches: BTreeSet<String>,
+}
+
+/// A smaller feature set for one dependency of one member
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeatureSuggestion {
+    pub member: String,
+    pub dependency: String,
+    /// Features the member's declaration enables, `default` expanded
+    pub enabled: Vec<String>,
+    pub unused: Vec<String>,
+    /// Features to ask for instead
+    pub minimal: Vec<String>,
+    pub default_features: bool,
+    /// The suggested `[dependencies]` entry
+    pub manifest_entry: String,
+    pub trial: Option<TrialBuild>,
+}
+
+/// Clean builds of the member with its current and its suggested features
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrialBuild {
+    pub baseline_secs: f64,
+    pub trial_secs: f64,
+    /// Whether the member still builds with the suggestion
+    pub builds: bool,
+}
+
+impl TrialBuild {
+    pub fn savings_secs(&self) -> f64 {
+        self.baseline_secs - self.trial_secs
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FeatureReport {
+    pub dependencies_scanned: usize,
+    pub suggestions: Vec<FeatureSuggestion>,
+}
+
+/// Analyze the workspace containing `manifest_path`; `measure` runs the trial builds.
+pub fn analyze(manifest_path: &Path, measure: bool) -> Result<FeatureReport> {
+    let metadata = MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .exec()
+        .with_context(|| format!("running cargo metadata on {}", manifest_path.display()))?;
+    let mut report = suggest_for_workspace(&metadata)?;
+    if measure && !report.suggestions.is_empty() {
+        measure_suggestions(&metadata, &mut report.suggestions)?;
+    }
+    Ok(report)
+}
+
+fn suggest_for_workspace(metadata: &Metadata) -> Result<FeatureReport> {
+    let resolve = metadata.resolve.as_ref().context("cargo metadata did not resolve the graph")?;
+    let packages: BTreeMap<&str, &Package> =
+        metadata.packages.iter().map(|package| (package.id.repr.as_str(), package)).collect();
+    let mut scans: BTreeMap<&str, FeatureScan> = BTreeMap::new();
+    let mut report = FeatureReport::default();
+
+    for member_id in &metadata.workspace_members {
+        let member = packages[member_id.repr.as_str()];
+        let Some(node) = resolve.nodes.iter().find(|node| node.id == *member_id) else { continue };
+        let Some(member_dir) = member.manifest_path.parent() else { continue };
+        let sources = rust_files(member_dir.as_std_path());
+
+        for declared in &member.dependencies {
+            // Workspace crates' features are the workspace's own business
+            if declared.kind != DependencyKind::Normal || declared.path.is_some() {
+                continue;
+            }
+            let lib_name = declared.rename.clone().unwrap_or_else(|| declared.name.clone());
+            let lib_name = lib_name.replace('-', "_");
+            let Some(resolved) = node.deps.iter().find(|dep| dep.name == lib_name) else {
+                continue;
+            };
+            let Some(dependency) = packages.get(resolved.pkg.repr.as_str()) else { continue };
+            let Some(lib) = dependency.targets.iter().find(|target| target.is_lib()) else {
+                continue;
+            };
+            report.dependencies_scanned += 1;
+            let used = used_identifiers(&sources, &lib_name);
+            if used.is_empty() {
+                // Unused dependencies are reported by the dependency analysis
+                continue;
+            }
+            let scan = scans
+                .entry(dependency.id.repr.as_str())
+                .or_insert_with(|| scan_features(lib.src_path.as_std_path()));
+            let Some((unused, minimal, default_features)) = suggest(
+                &declared.features,
+                declared.uses_default_features,
+                &dependency.features,
+                scan,
+                &used,
+            ) else {
+                continue;
+            };
+            let enabled = expand(
+                &requested(
+                    &declared.features,
+                    declared.uses_default_features,
+                    &dependency.features,
+                ),
+                &dependency.features,
+            );
+            let manifest_entry =
+                manifest_entry(&declared.name, &declared.req.to_string(), &minimal);
+            report.suggestions.push(FeatureSuggestion {
+                member: member.name.clone(),
+                dependency: declared.name.clone(),
+                enabled: enabled.into_iter().collect(),
+                unused,
+                minimal,
+                default_features,
+                manifest_entry,
+                trial: None,
+            });
+        }
+    }
+    Ok(report)
+}
+
+/// The features a declaration asks for, with `default` when it keeps default features
+fn requested(
+    features: &[String],
+    uses_default_features: bool,
+    table: &BTreeMap<String, Vec<String>>,
+) -> BTreeSet<String> {
+    let mut requested: BTreeSet<String> = features.iter().cloned().collect();
+    if uses_default_features && table.contains_key("default") {
+        requested.insert("default".to_string());
+    }
+    requested
+}
+
+/// `features` and every feature of `table` they turn on
+fn expand(features: &BTreeSet<String>, table: &BTreeMap<String, Vec<String>>) -> BTreeSet<String> {
+    let mut enabled = BTreeSet::new();
+    let mut pending: Vec<String> = features.iter().cloned().collect();
+    while let Some(feature) = pending.pop() {
+        if !table.contains_key(&feature) || !enabled.insert(feature.clone()) {
+            continue;
+        }
+        pending.extend(table[&feature].iter().filter(|entry| table.contains_key(*entry)).cloned());
+    }
+    enabled
+}
+
+/// Whether `feature` only turns on other features of the crate
+fn is_bundle(feature: &str, table: &BTreeMap<String, Vec<String>>) -> bool {
+    table.get(feature).is_some_and(|entries| {
+        !entries.is_empty() && entries.iter().all(|entry| table.contains_key(entry))
+    })
+}
+
+/// Whether `feature` is the implicit feature of an optional dependency
+fn is_optional_dependency(feature: &str, table: &BTreeMap<String, Vec<String>>) -> bool {
+    table.get(feature).is_some_and(|entries| *entries == [format!("dep:{}", feature)])
+}
+
+/// The features unused by a member among those its declaration enables, the minimal set to ask
+/// for instead and whether to keep default features; `None` when nothing can go.
+pub fn suggest(
+    features: &[String],
+    uses_default_features: bool,
+    table: &BTreeMap<String, Vec<String>>,
+    scan: &FeatureScan,
+    used: &BTreeSet<String>,
+) -> Option<(Vec<String>, Vec<String>, bool)> {
+    let enabled = expand(&requested(features, uses_default_features, table), table);
+    let needed = |feature: &str| match scan.gated.get(feature) {
+        _ if scan.switches.contains(feature) => true,
+        Some(names) if !names.is_empty() => names.iter().any(|name| used.contains(name)),
+        // Nothing visible to the scan depends on it
+        _ => true,
+    };
+    // Bundles are judged by what they turn on unless they gate items of their own, and optional
+    // dependencies come and go with the features that turn them on unless asked for
+    let (kept, unused): (Vec<&String>, Vec<&String>) = enabled
+        .iter()
+        .filter(|feature| *feature != "default")
+        .filter(|feature| !is_bundle(feature, table) || scan.gated.contains_key(*feature))
+        .filter(|feature| !is_optional_dependency(feature, table) || features.contains(*feature))
+        .partition(|feature| needed(feature));
+    // Leave out features another kept feature turns on anyway
+    let kept: BTreeSet<String> = kept.into_iter().cloned().collect();
+    let implied = expand(&kept, table);
+    let unused: Vec<String> =
+        unused.into_iter().filter(|feature| !implied.contains(*feature)).cloned().collect();
+    if unused.is_empty() {
+        return None;
+    }
+    let minimal: Vec<String> = kept
+        .iter()
+        .filter(|feature| {
+            !kept.iter().any(|other| {
+                other != *feature
+                    && expand(&BTreeSet::from([other.clone()]), table).contains(*feature)
+            })
+        })
+        .cloned()
+        .collect();
+    Some((unused, minimal, false))
+}
+
+fn manifest_entry(name: &str, requirement: &str, features: &[String]) -> String {
+    let requirement = requirement.trim_start_matches('^');
+    let features: Vec<String> = features.iter().map(|feature| format!("\"{}\"", feature)).collect();
+    if features.is_empty() {
+        format!("{} = {{ version = \"{}\", default-features = false }}", name, requirement)
+    } else {
+        format!(
+            "{} = {{ version = \"{}\", default-features = false, features = [{}] }}",
+            name,
+            requirement,
+            features.join(", ")
+        )
+    }
+}
+
+/// Every `.rs` file under `dir`, leaving out build output
+pub(crate) fn rust_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let Ok(entries) = std::fs::read_dir(dir) else { return files };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name();
+        if path.is_dir() {
+            if !COPY_SKIPPED.contains(&name.to_string_lossy().as_ref()) {
+                files.extend(rust_files(&path));
+            }
+        } else if path.extension().is_some_and(|extension| extension == "rs") {
+            files.push(path);
+        }
+    }
+    files
+}
+
+fn identifiers(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .filter(|word| word.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_'))
+}
+
+/// Identifiers in the files of `sources` that refer to the crate `lib_name`
+pub fn used_identifiers(sources: &[PathBuf], lib_name: &str) -> BTreeSet<String> {
+    let mut used = BTreeSet::new();
+    for source in sources {
+        let Ok(text) = std::fs::read_to_string(source) else { continue };
+        if identifiers(&text).any(|word| word == lib_name) {
+            used.extend(identifiers(&text).map(String::from));
+        }
+    }
+    used
+}
+
+/// Map each feature of the crate rooted at `lib_root` (its `lib.rs`) to the public items it
+/// gates.
+pub fn scan_features(lib_root: &Path) -> FeatureScan {
+    let mut scan = FeatureScan::default();
+    let mut visited = BTreeSet::new();
+    scan_module(lib_root, true, &BTreeSet::new(), &mut scan, &mut visited);
+    scan
+}
+
+/// Features a `cfg` attribute gates on, recording those it gates on the absence of as switches
+fn gating_features(attribute: &str, switches: &mut BTreeSet<String>) -> BTreeSet<String> {
+    if attribute.contains("not(") {
+        switches.extend(cfg_features(attribute));
+        return BTreeSet::new();
+    }
+    cfg_features(attribute)
+}
+
+/// Features named by a `cfg` attribute
+fn cfg_features(attribute: &str) -> BTreeSet<String> {
+    let mut features = BTreeSet::new();
+    let mut rest = attribute;
+    while let Some(start) = rest.find("feature") {
+        rest = &rest[start + "feature".len()..];
+        let value = rest.trim_start().strip_prefix('=').map(str::trim_start);
+        if let Some(value) = value.and_then(|value| value.strip_prefix('"')) {
+            if let Some(end) = value.find('"') {
+                features.insert(value[..end].to_string());
+            }
+        }
+    }
+    features
+}
+
+/// The name an item declaration introduces, for public items
+fn item_names(line: &str) -> Vec<String> {
+    let Some(rest) = line.strip_prefix("pub") else {
+        return line
+            .strip_prefix("macro_rules!")
+            .and_then(|rest| identifiers(rest).next())
+            .map(|name| vec![name.to_string()])
+            .unwrap_or_default();
+    };
+    let rest = match rest.trim_start().strip_prefix('(') {
+        Some(scoped) => scoped.split_once(')').map_or("", |(_, rest)| rest),
+        None => rest,
+    };
+    let mut words = identifiers(rest).skip_while(|word| {
+        matches!(*word, "async" | "unsafe" | "extern" | "default" | "C")
+            || *word == "const" && {
+                // `const fn` qualifies a function, `const NAME` is the item
+                rest.trim_start().starts_with("const fn") || rest.contains("const unsafe fn")
+            }
+    });
+    match words.next() {
+        Some("use") => {
+            let keywords = ["use", "crate", "self", "super", "as"];
+            identifiers(rest).filter(|word| !keywords.contains(word)).map(String::from).collect()
+        }
+        Some(
+            "fn" | "struct" | "enum" | "trait" | "mod" | "type" | "const" | "static" | "union",
+        ) => words.next().map(|name| vec![name.to_string()]).unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+fn scan_module(
+    file: &Path,
+    owns_directory: bool,
+    inherited: &BTreeSet<String>,
+    scan: &mut FeatureScan,
+    visited: &mut BTreeSet<PathBuf>,
+) {
+    if !visited.insert(file.to_path_buf()) {
+        return;
+    }
+    let Ok(text) = std::fs::read_to_string(file) else { return };
+    // Submodules of lib.rs and mod.rs live next to them, those of foo.rs in foo/
+    let module_dir = match (owns_directory, file.parent()) {
+        (true, Some(parent)) => parent.to_path_buf(),
+        (false, Some(parent)) => parent.join(file.file_stem().unwrap_or_default()),
+        (_, None) => PathBuf::new(),
+    };
+
+    let mut inherited = inherited.clone();
+    let mut pending: BTreeSet<String> = BTreeSet::new();
+    let mut attribute: Option<String> = None;
+    // Gated blocks still open, by the brace depth they close at
+    let mut blocks: Vec<(i64, BTreeSet<String>)> = Vec::new();
+    let mut depth: i64 = 0;
+
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some(open) = &mut attribute {
+            open.push_str(line);
+            if line.ends_with(")]") {
+                pending.extend(gating_features(open, &mut scan.switches));
+                attribute = None;
+            }
+            continue;
+        }
+        // `#![cfg_attr(not(feature = "std"), no_std)]` and the like
+        if line.starts_with("#![cfg_attr(not(") || line.starts_with("#[cfg_attr(not(") {
+            scan.switches.extend(cfg_features(line));
+            continue;
+        }
+        if line.starts_with("#![cfg(") && depth == 0 {
+            inherited.extend(gating_features(line, &mut scan.switches));
+            continue;
+        }
+        if line.starts_with("#[cfg(") {
+            if line.ends_with(")]") {
+                pending.extend(gating_features(line, &mut scan.switches));
+            } else {
+                attribute = Some(line.to_string());
+            }
+            continue;
+        }
+        if line.is_empty() || line.starts_with("//") || line.starts_with("#[") {
+            continue;
+        }
+
+        let mut features = inherited.clone();
+        features.extend(pending.iter().cloned());
+        for (_, block) in &blocks {
+            features.extend(block.iter().cloned());
+        }
+        if !features.is_empty() {
+            for name in item_names(line) {
+                for feature in &features {
+                    scan.gated.entry(feature.clone()).or_default().insert(name.clone());
+                }
+            }
+        }
+
+        let declaration = line.trim_start_matches("pub(crate) ").trim_start_matches("pub ");
+        if let Some(module) =
+            declaration.strip_prefix("mod ").and_then(|rest| rest.strip_suffix(';'))
+        {
+            let module = module.trim();
+            let candidates = [
+                (module_dir.join(format!("{}.rs", module)), false),
+                (module_dir.join(module).join("mod.rs"), true),
+            ];
+            if let Some((path, owns)) = candidates.into_iter().find(|(path, _)| path.is_file()) {
+                let mut module_features = inherited.clone();
+                module_features.extend(pending.iter().cloned());
+                for (_, block) in &blocks {
+                    module_features.extend(block.iter().cloned());
+                }
+                scan_module(&path, owns, &module_features, scan, visited);
+            }
+        }
+
+        let opened = line.matches('{').count() as i64;
+        let closed = line.matches('}').count() as i64;
+        if !pending.is_empty() && opened > closed {
+            blocks.push((depth, pending.clone()));
+        }
+        depth += opened - closed;
+        blocks.retain(|(closes_at, _)| depth > *closes_at);
+        pending.clear();
+    }
+}
+
+/// Copy `from` to `to`, leaving out build output and version control
+fn copy_tree(from: &Path, to: &Path) -> Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let target = to.join(&name);
+        if entry.file_type()?.is_dir() {
+            if !COPY_SKIPPED.contains(&name.to_string_lossy().as_ref()) {
+                copy_tree(&entry.path(), &target)?;
+            }
+        } else if entry.file_type()?.is_file() {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Seconds a clean `cargo build -p <member>` takes in `workspace`, and whether it succeeded
+fn timed_build(workspace: &Path, member: &str) -> Result<(f64, bool)> {
+    let target = workspace.join("target");
+    if target.exists() {
+        std::fs::remove_dir_all(&target)?;
+    }
+    let started = Instant::now();
+    let status = Command::new(std::env::var("CARGO").unwrap_or_else(|_| "cargo".into()))
+        .args(["build", "--quiet", "-p", member])
+        .current_dir(workspace)
+        .env("CARGO_TARGET_DIR", &target)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .context("running cargo build")?;
+    Ok((started.elapsed().as_secs_f64(), status.success()))
+}
+
+/// Replace the member's declaration of `dependency` with the suggested features.
+fn apply_suggestion(manifest: &str, suggestion: &FeatureSuggestion) -> Result<String> {
+    let mut document: toml::Table = toml::from_str(manifest)?;
+    let dependencies = document
+        .get_mut("dependencies")
+        .and_then(toml::Value::as_table_mut)
+        .context("manifest has no [dependencies]")?;
+    let entry = dependencies
+        .get_mut(&suggestion.dependency)
+        .context("dependency is not declared under [dependencies]")?;
+    let mut table = match entry {
+        toml::Value::String(version) => {
+            toml::Table::from_iter([("version".to_string(), toml::Value::String(version.clone()))])
+        }
+        toml::Value::Table(table) => table.clone(),
+        _ => anyhow::bail!("unexpected declaration of {}", suggestion.dependency),
+    };
+    table.insert("default-features".into(), toml::Value::Boolean(suggestion.default_features));
+    table.insert(
+        "features".into(),
+        toml::Value::Array(suggestion.minimal.iter().cloned().map(toml::Value::String).collect()),
+    );
+    *entry = toml::Value::Table(table);
+    Ok(toml::to_string(&document)?)
+}
+
+/// Time clean builds of each member before and after its suggestion, on a copy of the
+/// workspace.
+fn measure_suggestions(metadata: &Metadata, suggestions: &mut [FeatureSuggestion]) -> Result<()> {
+    let copy = std::env::temp_dir().join(format!("parflow-features-{}", std::process::id()));
+    if copy.exists() {
+        std::fs::remove_dir_all(&copy)?;
+    }
+    copy_tree(metadata.workspace_root.as_std_path(), &copy)?;
+    let manifests: BTreeMap<String, PathBuf> = metadata
+        .workspace_packages()
+        .into_iter()
+        .filter_map(|package| {
+            let relative = package.manifest_path.strip_prefix(&metadata.workspace_root).ok()?;
+            Some((package.name.clone(), copy.join(relative.as_std_path())))
+        })
+        .collect();
+
+    let mut baselines: BTreeMap<String, f64> = BTreeMap::new();
+    let measured = (|| -> Result<()> {
+        for suggestion in suggestions.iter_mut() {
+            let Some(manifest) = manifests.get(&suggestion.member) else { continue };
+            let baseline = match baselines.get(&suggestion.member) {
+                Some(secs) => *secs,
+                None => {
+                    tracing::info!(member = %suggestion.member, "⏱️  Baseline build");
+                    let (secs, _) = timed_build(&copy, &suggestion.member)?;
+                    baselines.insert(suggestion.member.clone(), secs);
+                    secs
+                }
+            };
+            let original = std::fs::read_to_string(manifest)?;
+            std::fs::write(manifest, apply_suggestion(&original, suggestion)?)?;
+            tracing::info!(
+                member = %suggestion.member,
+                dependency = %suggestion.dependency,
+                "⏱️  Trial build"
+            );
+            let trial = timed_build(&copy, &suggestion.member);
+            std::fs::write(manifest, original)?;
+            let (trial_secs, builds) = trial?;
+            suggestion.trial = Some(TrialBuild { baseline_secs: baseline, trial_secs, builds });
+        }
+        Ok(())
+    })();
+    let _ = std::fs::remove_dir_all(&copy);
+    measured
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggests_minimal_feature_sets() {
+        let dir =
+            std::env::temp_dir().join(format!("parflow-features-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(
+            dir.join("src/lib.rs"),
+            "#[cfg(feature = \"json\")]\npub mod json;\n\n\
+             #[cfg(any(feature = \"net\", feature = \"full\"))]\npub struct TcpStream;\n\n\
+             #[cfg(feature = \"time\")]\nimpl Clock {\n    pub fn sleep() {}\n}\n\n\
+             #[cfg(not(feature = \"net\"))]\npub fn no_sleep() {}\n\
+             pub fn always() {}\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("src/json.rs"), "pub fn to_string() {}\nfn private() {}\n")
+            .unwrap();
+
+        let scan = scan_features(&dir.join("src/lib.rs"));
+        let names = |feature: &str| scan.gated[feature].iter().cloned().collect::<Vec<_>>();
+        assert_eq!(names("json"), ["json", "to_string"]);
+        assert_eq!(names("net"), ["TcpStream"]);
+        assert_eq!(names("time"), ["sleep"]);
+        assert_eq!(scan.switches, BTreeSet::from(["net".to_string()]));
+        assert!(!scan
+            .gated
+            .values()
+            .any(|names| names.contains("no_sleep") || names.contains("always")));
+
+        let table: BTreeMap<String, Vec<String>> = [
+            ("default", vec!["json", "time"]),
+            ("full", vec!["json", "net", "time"]),
+            ("json", vec![]),
+            ("net", vec![]),
+            ("time", vec![]),
+            ("tracing", vec!["dep:tracing"]),
+        ]
+        .into_iter()
+        .map(|(name, entries)| (name.into(), entries.into_iter().map(String::from).collect()))
+        .collect();
+
+        std::fs::write(dir.join("src/main.rs"), "use dep::json::to_string;\nfn main() {}\n")
+            .unwrap();
+        let used = used_identifiers(&[dir.join("src/main.rs")], "dep");
+        let (unused, minimal, default_features) =
+            suggest(&["tracing".into()], true, &table, &scan, &used).unwrap();
+        assert_eq!(unused, ["time"]);
+        // `tracing` gates nothing the scan sees, so it stays
+        assert_eq!(minimal, ["json", "tracing"]);
+        assert!(!default_features);
+        assert_eq!(
+            manifest_entry("dep", "^1.2", &minimal),
+            "dep = { version = \"1.2\", default-features = false, features = [\"json\", \"tracing\"] }"
+        );
+
+        // Everything `full` turns on is used
+        let used: BTreeSet<String> =
+            ["to_string", "TcpStream", "sleep"].into_iter().map(String::from).collect();
+        assert!(suggest(&["full".into()], false, &table, &scan, &used).is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,8 +1,20 @@
 use anyhow::Result;
-use colored::*;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+pub mod bloat;
+pub mod compile_cache;
+pub mod environment;
+pub mod features;
+pub mod licenses;
+pub mod lockfiles;
+pub mod sbom;
+pub mod splits;
+pub mod timings;
+pub mod workspace;
+
+pub use environment::{EnvironmentFormat, SetupCheck, Toolchain, ToolchainKind};
+
 // Basic structs to make CLI compile
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CrateAnalysis {
@@ -13,15 +25,21 @@ pub struct CrateAnalysis {
     pub outdated_dependencies: Vec<OutdatedCrate>,
     pub security_vulnerabilities: Vec<SecurityVulnerability>,
     pub performance_metrics: CrateMetrics,
+    /// Size attribution of the release binary, when it was built
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bloat: Option<bloat::BloatReport>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DependencyInfo {
     pub name: String,
     pub version: String,
     pub used: bool,
     pub deprecated: bool,
     pub alternative: Option<String>,
+    /// SPDX license expression, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -56,7 +74,7 @@ pub struct CrateMetrics {
     pub download_size_kb: u64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OptimizationSuggestion {
     pub action: OptimizationAction,
     pub target: String,
@@ -64,7 +82,7 @@ pub struct OptimizationSuggestion {
     pub impact: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum OptimizationAction {
     RemoveDependency,
     UpdateDependency,
@@ -117,8 +135,11 @@ pub struct PerformanceEstimate {
 pub struct EnvironmentMirroringResult {
     pub source_analysis: CrossLanguageDependencyAnalysis,
     pub target_recommendations: CrateRecommendations,
+    pub toolchains: Vec<Toolchain>,
     pub configuration_files: Vec<String>,
     pub setup_commands: Vec<String>,
+    /// Outcome of each setup command, empty unless verification was asked for
+    pub verification: Vec<SetupCheck>,
 }
 
 // Main orchestrator with mock implementations
@@ -130,8 +151,8 @@ impl CrateOrchestrator {
         Self
     }
 
-    pub async fn analyze_cargo_toml(&self, _path: &str) -> Result<CrateAnalysis> {
-        println!("{}", "🔍 Analyzing Cargo.toml...".bright_blue());
+    pub async fn analyze_cargo_toml(&self, path: &str) -> Result<CrateAnalysis> {
+        tracing::info!(path, "🔍 Analyzing Cargo.toml");
 
         Ok(CrateAnalysis {
             name: "parflow-cli".to_string(),
@@ -142,6 +163,7 @@ impl CrateOrchestrator {
                 used: true,
                 deprecated: false,
                 alternative: None,
+                license: None,
             }],
             unused_dependencies: vec!["old-crate".to_string()],
             outdated_dependencies: vec![],
@@ -152,15 +174,36 @@ impl CrateOrchestrator {
                 dependency_count: 45,
                 download_size_kb: 89000,
             },
+            bloat: None,
         })
     }
 
+    /// Analyze the manifest at `path`, then build its binary in release mode and attribute the
+    /// binary's size to crates and functions.
+    pub async fn analyze_binary_size(&self, path: &str) -> Result<CrateAnalysis> {
+        let mut analysis = self.analyze_cargo_toml(path).await?;
+        tracing::info!(path, "📏 Building release binary for size attribution");
+        let manifest = std::path::PathBuf::from(path);
+        let report = tokio::task::spawn_blocking(move || bloat::analyze(&manifest)).await??;
+        analysis.performance_metrics.binary_size_kb = report.file_size / 1024;
+        analysis.bloat = Some(report);
+        Ok(analysis)
+    }
+
+    /// Analyze every member of the workspace containing the manifest at `path`, with metrics
+    /// aggregated over the workspace and duplicated or shareable dependencies.
+    pub async fn analyze_workspace(&self, path: &str) -> Result<workspace::WorkspaceAnalysis> {
+        tracing::info!(path, "🔍 Analyzing Cargo workspace");
+        let manifest = std::path::PathBuf::from(path);
+        tokio::task::spawn_blocking(move || workspace::analyze(&manifest)).await?
+    }
+
     pub async fn optimize_dependencies(
         &self,
         path: &str,
         dry_run: bool,
     ) -> Result<OptimizationResult> {
-        println!("{} {}", "⚡ Optimizing dependencies for:".bright_green(), path);
+        tracing::info!(path, "⚡ Optimizing dependencies");
 
         let analysis = self.analyze_cargo_toml(path).await?;
 
@@ -179,31 +222,67 @@ impl CrateOrchestrator {
         })
     }
 
+    /// Detect the toolchains of `source_path`, write a reproducible environment for them to
+    /// `target_path` and, when `verify` is set, run the setup commands against the source.
     pub async fn mirror_development_environment(
         &self,
         source_path: &str,
         target_path: &str,
         target_language: &str,
+        format: EnvironmentFormat,
+        verify: bool,
     ) -> Result<EnvironmentMirroringResult> {
-        println!(
-            "{} {} → {}",
-            "🔄 Mirroring development environment:".bright_blue(),
-            source_path,
-            target_path
+        tracing::info!(
+            source = source_path,
+            target = target_path,
+            "🔄 Mirroring development environment"
         );
 
+        let source = std::path::Path::new(source_path);
+        if !source.is_dir() {
+            anyhow::bail!("{} is not a directory", source_path);
+        }
+        let mut toolchains = environment::detect(source);
+        if toolchains.is_empty() {
+            anyhow::bail!("no Rust, Python, JavaScript or Go project found in {}", source_path);
+        }
+        for toolchain in &mut toolchains {
+            let Some(ecosystem) = toolchain.kind.ecosystem() else { continue };
+            let dir = source.join(&toolchain.dir);
+            for dependency in &mut toolchain.dependencies {
+                dependency.license =
+                    licenses::local_license(ecosystem, &dir, &dependency.name, &dependency.version);
+            }
+        }
+
+        let mut languages: Vec<String> = Vec::new();
+        let mut dependencies: HashMap<String, Vec<DependencyInfo>> = HashMap::new();
+        for toolchain in &toolchains {
+            let language = toolchain.kind.language().to_string();
+            if !languages.contains(&language) {
+                languages.push(language.clone());
+            }
+            dependencies.entry(language).or_default().extend(toolchain.dependencies.clone());
+        }
+        let duplicate_functionality = match lockfiles::analyze(source) {
+            Ok(report) => report.duplicates.into_iter().map(|d| d.suggestion).collect(),
+            Err(e) => {
+                tracing::warn!(error = %e, "⚠️  Could not read the lockfiles");
+                vec![]
+            }
+        };
         let analysis = CrossLanguageDependencyAnalysis {
-            languages: vec!["rust".to_string()],
-            dependencies: HashMap::new(),
-            total_dependencies: 10,
+            languages,
+            total_dependencies: dependencies.values().map(Vec::len).sum(),
+            dependencies,
             vulnerable_dependencies: 0,
-            duplicate_functionality: vec![],
+            duplicate_functionality,
         };
 
         let recommendations = CrateRecommendations {
             target_language: target_language.to_string(),
             crate_suggestions: vec![],
-            compatibility_notes: vec![],
+            compatibility_notes: environment::version_conflicts(&toolchains),
             performance_estimates: PerformanceEstimate {
                 estimated_compile_time_reduction: 0.0,
                 estimated_binary_size_reduction: 0.0,
@@ -211,11 +290,21 @@ impl CrateOrchestrator {
             },
         };
 
+        let canonical = source.canonicalize()?;
+        let name = canonical.file_name().map_or("workspace".into(), |n| n.to_string_lossy());
+        let spec =
+            environment::write_spec(std::path::Path::new(target_path), format, &name, &toolchains)?;
+        let setup_commands = environment::setup_commands_for(&toolchains);
+        let verification =
+            if verify { environment::verify(source, &setup_commands).await } else { Vec::new() };
+
         Ok(EnvironmentMirroringResult {
             source_analysis: analysis,
             target_recommendations: recommendations,
-            configuration_files: vec!["Cargo.toml".to_string()],
-            setup_commands: vec!["cargo build".to_string()],
+            toolchains,
+            configuration_files: vec![spec.display().to_string()],
+            setup_commands,
+            verification,
         })
     }
 }
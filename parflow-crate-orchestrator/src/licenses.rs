@@ -0,0 +1,549 @@
+//! License compliance across Cargo, npm and Python dependencies
+//!
+//! [`check`] takes every package locked by the lockfiles [`crate::lockfiles`] reads and finds its
+//! SPDX license expression: first in local metadata (the cargo registry cache, `node_modules`,
+//! the `dist-info` of a project's `.venv`), then, unless offline, from the crates.io, npm and
+//! PyPI registries. Each expression is held against a [`LicensePolicy`], usually read from
+//! `.parflow/license-policy.toml`:
+//!
+//! ```toml
+//! # Licenses nothing may depend on
+//! deny = ["AGPL-3.0"]
+//! # Licenses only dev dependencies may use
+//! deny_in_binaries = ["GPL-2.0", "GPL-3.0"]
+//! # When set, the only licenses allowed
+//! allow = ["MIT", "Apache-2.0", "BSD-3-Clause", "ISC", "Unicode-3.0"]
+//! # allow, warn or deny dependencies whose license is unknown
+//! unknown = "warn"
+//! # Packages exempt from the policy
+//! exceptions = ["ring"]
+//! ```
+//!
+//! A license matches a policy entry of the same identifier or one of its variants, so `GPL-3.0`
+//! covers `GPL-3.0-only`, `GPL-3.0-or-later` and `GPL-3.0+`. An expression such as
+//! `MIT OR Apache-2.0` complies when one of its alternatives does. Dev dependencies are those a
+//! lockfile marks as such (`package-lock.json`, `poetry.lock`) and the direct
+//! `[dev-dependencies]` of the Cargo manifests; everything else counts as linked into binaries.
+
+use crate::lockfiles::{self, Ecosystem, LockedPackage};
+use anyhow::{Context, Result};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Where `parflow license-check` looks for the policy by default
+pub const DEFAULT_POLICY: &str = ".parflow/license-policy.toml";
+/// Registry requests in flight at once
+const REGISTRY_CONCURRENCY: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnknownLicense {
+    Allow,
+    #[default]
+    Warn,
+    Deny,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LicensePolicy {
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+    pub deny_in_binaries: Vec<String>,
+    pub unknown: UnknownLicense,
+    pub exceptions: Vec<String>,
+}
+
+impl Default for LicensePolicy {
+    /// Strong copyleft stays out of binaries; everything else is allowed
+    fn default() -> Self {
+        Self {
+            allow: Vec::new(),
+            deny: Vec::new(),
+            deny_in_binaries: vec!["GPL-2.0".into(), "GPL-3.0".into(), "AGPL-3.0".into()],
+            unknown: UnknownLicense::Warn,
+            exceptions: Vec::new(),
+        }
+    }
+}
+
+impl LicensePolicy {
+    pub fn from_toml_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("reading license policy from {}", path.display()))?;
+        Ok(toml::from_str(&text)?)
+    }
+
+    /// Why `id` may not be used by a dependency, if it may not
+    fn refuses(&self, id: &str, dev: bool) -> Option<String> {
+        let listed = |entries: &[String]| entries.iter().any(|entry| covers(entry, id));
+        if listed(&self.deny) {
+            Some(format!("{} is denied", id))
+        } else if !dev && listed(&self.deny_in_binaries) {
+            Some(format!("{} is denied in binaries", id))
+        } else if !self.allow.is_empty() && !listed(&self.allow) {
+            Some(format!("{} is not allowed", id))
+        } else {
+            None
+        }
+    }
+
+    /// Why `license` breaks the policy: `None` when one of its alternatives complies
+    pub fn evaluate(&self, license: &str, dev: bool) -> Option<String> {
+        let mut reasons = Vec::new();
+        for alternative in alternatives(license) {
+            let refused: Vec<String> =
+                alternative.iter().filter_map(|id| self.refuses(id, dev)).collect();
+            if refused.is_empty() {
+                return None;
+            }
+            reasons.extend(refused);
+        }
+        reasons.dedup();
+        Some(reasons.join(", "))
+    }
+}
+
+/// Whether policy entry `entry` covers license identifier `id`
+fn covers(entry: &str, id: &str) -> bool {
+    let (entry, id) = (entry.to_lowercase(), id.to_lowercase());
+    id == entry || id.starts_with(&format!("{}-", entry)) || id == format!("{}+", entry)
+}
+
+/// The alternatives of an SPDX expression, each the identifiers it needs together. Parentheses
+/// are flattened and `WITH` exceptions dropped, which is exact for the `A OR B` and
+/// `(A OR B) AND C` forms packages use in practice.
+fn alternatives(license: &str) -> Vec<Vec<String>> {
+    // Old crates separate alternatives with a slash
+    let license = license.replace('/', " OR ").replace(['(', ')'], " ");
+    let words: Vec<&str> = license.split_whitespace().collect();
+    let mut conjunctions: Vec<Vec<Vec<String>>> = vec![vec![Vec::new()]];
+    let mut skip_next = false;
+    for word in words {
+        if skip_next {
+            skip_next = false;
+            continue;
+        }
+        match word.to_uppercase().as_str() {
+            "OR" => conjunctions.last_mut().unwrap().push(Vec::new()),
+            "AND" => conjunctions.push(vec![Vec::new()]),
+            "WITH" => skip_next = true,
+            _ => conjunctions.last_mut().unwrap().last_mut().unwrap().push(word.to_string()),
+        }
+    }
+    // (A OR B) AND (C OR D) = AC OR AD OR BC OR BD
+    conjunctions.into_iter().fold(vec![Vec::new()], |products, options| {
+        let options: Vec<Vec<String>> = options.into_iter().filter(|o| !o.is_empty()).collect();
+        if options.is_empty() {
+            return products;
+        }
+        products
+            .iter()
+            .flat_map(|product| {
+                options.iter().map(move |option| [product.clone(), option.clone()].concat())
+            })
+            .collect()
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LicenseSource {
+    Local,
+    Registry,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LicensedDependency {
+    #[serde(flatten)]
+    pub package: LockedPackage,
+    pub dev: bool,
+    pub license: Option<String>,
+    pub source: Option<LicenseSource>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Violation,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LicenseFinding {
+    pub severity: Severity,
+    pub package: LockedPackage,
+    pub license: Option<String>,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LicenseReport {
+    pub lockfiles: Vec<String>,
+    pub dependencies: Vec<LicensedDependency>,
+    pub findings: Vec<LicenseFinding>,
+}
+
+impl LicenseReport {
+    /// No dependency violates the policy; warnings do not count
+    pub fn passed(&self) -> bool {
+        self.findings.iter().all(|finding| finding.severity == Severity::Warning)
+    }
+}
+
+/// Find the license of every locked package under `root` and check it against `policy`.
+/// `offline` skips the registries, leaving packages without local metadata unknown.
+pub async fn check(root: &Path, policy: &LicensePolicy, offline: bool) -> Result<LicenseReport> {
+    let (lockfiles, packages) = lockfiles::locked_packages(root)?;
+    let mut dev = BTreeSet::new();
+    for lockfile in &lockfiles {
+        dev.extend(dev_packages(root, lockfile)?);
+    }
+
+    let mut dependencies: Vec<LicensedDependency> = packages
+        .into_iter()
+        .map(|package| {
+            let dir = root.join(&package.lockfile);
+            let dir = dir.parent().unwrap_or(root);
+            let license = local_license(package.ecosystem, dir, &package.name, &package.version);
+            LicensedDependency {
+                dev: dev.contains(&(package.lockfile.clone(), package.name.clone())),
+                source: license.as_ref().map(|_| LicenseSource::Local),
+                license,
+                package,
+            }
+        })
+        .collect();
+
+    if !offline {
+        let client = reqwest::Client::builder()
+            .user_agent(concat!("parflow/", env!("CARGO_PKG_VERSION")))
+            .timeout(Duration::from_secs(20))
+            .build()?;
+        let missing: Vec<usize> =
+            (0..dependencies.len()).filter(|&i| dependencies[i].license.is_none()).collect();
+        tracing::info!(packages = missing.len(), "🌐 Fetching licenses from the registries");
+        let fetched: Vec<(usize, Option<String>)> = futures::stream::iter(missing)
+            .map(|i| {
+                let client = &client;
+                let package = &dependencies[i].package;
+                async move { (i, registry_license(client, package).await) }
+            })
+            .buffer_unordered(REGISTRY_CONCURRENCY)
+            .collect()
+            .await;
+        for (i, license) in fetched {
+            if license.is_some() {
+                dependencies[i].source = Some(LicenseSource::Registry);
+                dependencies[i].license = license;
+            }
+        }
+    }
+
+    let findings = dependencies
+        .iter()
+        .filter(|dependency| !policy.exceptions.contains(&dependency.package.name))
+        .filter_map(|dependency| {
+            let (severity, reason) = match &dependency.license {
+                Some(license) => (Severity::Violation, policy.evaluate(license, dependency.dev)?),
+                None => match policy.unknown {
+                    UnknownLicense::Allow => return None,
+                    UnknownLicense::Warn => (Severity::Warning, "license unknown".to_string()),
+                    UnknownLicense::Deny => (Severity::Violation, "license unknown".to_string()),
+                },
+            };
+            Some(LicenseFinding {
+                severity,
+                package: dependency.package.clone(),
+                license: dependency.license.clone(),
+                reason,
+            })
+        })
+        .collect();
+
+    Ok(LicenseReport { lockfiles, dependencies, findings })
+}
+
+/// `(lockfile, package)` pairs that are only needed for development
+fn dev_packages(root: &Path, lockfile: &str) -> Result<BTreeSet<(String, String)>> {
+    let path = root.join(lockfile);
+    let dir = path.parent().unwrap_or(root);
+    let contents = std::fs::read_to_string(&path)?;
+    let mut dev = BTreeSet::new();
+    let mut add = |name: &str| dev.insert((lockfile.to_string(), name.to_string()));
+    match path.file_name().and_then(|name| name.to_str()) {
+        Some("Cargo.lock") => {
+            let mut runtime = BTreeSet::new();
+            let mut development = BTreeSet::new();
+            for member in crate::environment::project_dirs(dir) {
+                let Ok(text) = std::fs::read_to_string(member.join("Cargo.toml")) else {
+                    continue;
+                };
+                let manifest: toml::Value = toml::from_str(&text)?;
+                let names = |table: &str| {
+                    let declared = manifest.get(table).and_then(toml::Value::as_table);
+                    declared.into_iter().flat_map(|declared| declared.keys().cloned())
+                };
+                runtime.extend(names("dependencies").chain(names("build-dependencies")));
+                development.extend(names("dev-dependencies"));
+            }
+            development.difference(&runtime).for_each(|name| {
+                add(name);
+            });
+        }
+        Some("package-lock.json") => {
+            let lock: Value = serde_json::from_str(&contents)?;
+            let flagged =
+                |package: &Value| package["dev"] == true || package["devOptional"] == true;
+            if let Some(packages) = lock.get("packages").and_then(Value::as_object) {
+                for (path, package) in packages {
+                    if let Some((_, name)) = path.rsplit_once("node_modules/") {
+                        if flagged(package) {
+                            add(name);
+                        }
+                    }
+                }
+            } else if let Some(dependencies) = lock.get("dependencies").and_then(Value::as_object) {
+                for (name, package) in dependencies {
+                    if flagged(package) {
+                        add(name);
+                    }
+                }
+            }
+        }
+        Some("poetry.lock") => {
+            let lock: toml::Value = toml::from_str(&contents)?;
+            let packages = lock.get("package").and_then(toml::Value::as_array);
+            for package in packages.into_iter().flatten() {
+                let category = package.get("category").and_then(toml::Value::as_str);
+                if let (Some(name), Some("dev")) =
+                    (package.get("name").and_then(toml::Value::as_str), category)
+                {
+                    add(name);
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(dev)
+}
+
+/// The license a package's locally installed metadata declares
+pub fn local_license(
+    ecosystem: Ecosystem,
+    dir: &Path,
+    name: &str,
+    version: &str,
+) -> Option<String> {
+    match ecosystem {
+        Ecosystem::Cargo => {
+            let cargo_home = std::env::var_os("CARGO_HOME")
+                .map(PathBuf::from)
+                .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cargo")))?;
+            let registries = std::fs::read_dir(cargo_home.join("registry").join("src")).ok()?;
+            registries.flatten().find_map(|registry| {
+                let manifest =
+                    registry.path().join(format!("{}-{}", name, version)).join("Cargo.toml");
+                let manifest: toml::Value =
+                    toml::from_str(&std::fs::read_to_string(manifest).ok()?).ok()?;
+                let package = manifest.get("package")?;
+                match package.get("license").and_then(toml::Value::as_str) {
+                    Some(license) => Some(license.to_string()),
+                    None => package.get("license-file").map(|_| "LicenseRef-file".to_string()),
+                }
+            })
+        }
+        Ecosystem::Npm => {
+            let manifest = dir.join("node_modules").join(name).join("package.json");
+            let manifest: Value =
+                serde_json::from_str(&std::fs::read_to_string(manifest).ok()?).ok()?;
+            npm_license(&manifest)
+        }
+        Ecosystem::Python => {
+            let wanted = format!("{}-{}", python_name(name), version);
+            [".venv", "venv"].iter().find_map(|venv| {
+                let site_packages = std::fs::read_dir(dir.join(venv).join("lib"))
+                    .into_iter()
+                    .flatten()
+                    .flatten()
+                    .map(|python| python.path().join("site-packages"))
+                    .chain([dir.join(venv).join("Lib").join("site-packages")]);
+                site_packages.filter(|path| path.is_dir()).find_map(|site_packages| {
+                    let dist_info =
+                        std::fs::read_dir(site_packages).ok()?.flatten().find(|entry| {
+                            let file_name = entry.file_name().to_string_lossy().to_lowercase();
+                            file_name
+                                .strip_suffix(".dist-info")
+                                .is_some_and(|stem| python_name(stem) == wanted)
+                        })?;
+                    let metadata =
+                        std::fs::read_to_string(dist_info.path().join("METADATA")).ok()?;
+                    python_metadata_license(&metadata)
+                })
+            })
+        }
+    }
+}
+
+/// Lowercase with `_` and `.` folded into `-`, as PyPI compares distribution names
+fn python_name(name: &str) -> String {
+    name.to_lowercase().replace(['_', '.'], "-")
+}
+
+fn npm_license(manifest: &Value) -> Option<String> {
+    match manifest.get("license")? {
+        Value::String(license) => Some(license.clone()),
+        license => license.get("type")?.as_str().map(String::from),
+    }
+}
+
+/// SPDX identifiers of the `License ::` trove classifiers
+const CLASSIFIERS: &[(&str, &str)] = &[
+    ("MIT License", "MIT"),
+    ("Apache Software License", "Apache-2.0"),
+    ("BSD License", "BSD-3-Clause"),
+    ("ISC License (ISCL)", "ISC"),
+    ("Mozilla Public License 2.0 (MPL 2.0)", "MPL-2.0"),
+    ("Python Software Foundation License", "PSF-2.0"),
+    ("GNU General Public License v2 (GPLv2)", "GPL-2.0-only"),
+    ("GNU General Public License v2 or later (GPLv2+)", "GPL-2.0-or-later"),
+    ("GNU General Public License v3 (GPLv3)", "GPL-3.0-only"),
+    ("GNU General Public License v3 or later (GPLv3+)", "GPL-3.0-or-later"),
+    ("GNU Lesser General Public License v2 (LGPLv2)", "LGPL-2.0-only"),
+    ("GNU Lesser General Public License v2 or later (LGPLv2+)", "LGPL-2.0-or-later"),
+    ("GNU Lesser General Public License v3 (LGPLv3)", "LGPL-3.0-only"),
+    ("GNU Lesser General Public License v3 or later (LGPLv3+)", "LGPL-3.0-or-later"),
+    ("GNU Affero General Public License v3", "AGPL-3.0-only"),
+    ("GNU Affero General Public License v3 or later (AGPLv3+)", "AGPL-3.0-or-later"),
+    ("The Unlicense (Unlicense)", "Unlicense"),
+];
+
+/// An SPDX expression from a `License` field that holds one rather than the license text
+fn spdx_like(license: &str) -> Option<String> {
+    let license = license.trim();
+    if license.is_empty() || license.len() > 64 || license.eq_ignore_ascii_case("unknown") {
+        return None;
+    }
+    // Identifiers and operators alternate, which rules out names such as `Apache 2.0`
+    let flat = license.replace(['(', ')'], " ");
+    let words: Vec<&str> = flat.split_whitespace().collect();
+    let well_formed = words.len() % 2 == 1
+        && words.iter().enumerate().all(|(i, word)| match i % 2 {
+            0 => word.chars().all(|c| c.is_ascii_alphanumeric() || "-.+".contains(c)),
+            _ => matches!(*word, "OR" | "AND" | "WITH"),
+        });
+    well_formed.then(|| license.to_string())
+}
+
+fn from_classifiers<'a>(classifiers: impl Iterator<Item = &'a str>) -> Option<String> {
+    let licenses: Vec<&str> = classifiers
+        .filter_map(|classifier| classifier.strip_prefix("License :: "))
+        .filter_map(|license| {
+            let name = license.rsplit(" :: ").next()?;
+            CLASSIFIERS.iter().find(|(classifier, _)| *classifier == name).map(|(_, id)| *id)
+        })
+        .collect();
+    (!licenses.is_empty()).then(|| licenses.join(" OR "))
+}
+
+/// The license of a `METADATA` file: `License-Expression`, a short `License`, or classifiers
+fn python_metadata_license(metadata: &str) -> Option<String> {
+    let headers: Vec<(&str, &str)> = metadata
+        .lines()
+        .take_while(|line| !line.is_empty())
+        .filter_map(|line| line.split_once(": "))
+        .collect();
+    let field = |key: &str| headers.iter().find(|(k, _)| *k == key).map(|(_, v)| *v);
+    field("License-Expression")
+        .map(String::from)
+        .or_else(|| field("License").and_then(spdx_like))
+        .or_else(|| {
+            from_classifiers(headers.iter().filter(|(k, _)| *k == "Classifier").map(|(_, v)| *v))
+        })
+}
+
+async fn registry_license(client: &reqwest::Client, package: &LockedPackage) -> Option<String> {
+    let url = match package.ecosystem {
+        Ecosystem::Cargo => {
+            format!("https://crates.io/api/v1/crates/{}/{}", package.name, package.version)
+        }
+        Ecosystem::Npm => {
+            format!("https://registry.npmjs.org/{}/{}", package.name, package.version)
+        }
+        Ecosystem::Python => {
+            format!("https://pypi.org/pypi/{}/{}/json", package.name, package.version)
+        }
+    };
+    let response = match client.get(&url).send().await {
+        Ok(response) if response.status().is_success() => response,
+        Ok(response) => {
+            tracing::debug!(%url, status = %response.status(), "Registry has no metadata");
+            return None;
+        }
+        Err(e) => {
+            tracing::warn!(%url, error = %e, "⚠️  Registry request failed");
+            return None;
+        }
+    };
+    let metadata: Value = response.json().await.ok()?;
+    match package.ecosystem {
+        Ecosystem::Cargo => metadata["version"]["license"].as_str().map(String::from),
+        Ecosystem::Npm => npm_license(&metadata),
+        Ecosystem::Python => {
+            let info = &metadata["info"];
+            info["license_expression"]
+                .as_str()
+                .map(String::from)
+                .or_else(|| info["license"].as_str().and_then(spdx_like))
+                .or_else(|| {
+                    let classifiers = info["classifiers"].as_array()?;
+                    from_classifiers(classifiers.iter().filter_map(Value::as_str))
+                })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluates_expressions_against_the_policy() {
+        let policy: LicensePolicy =
+            toml::from_str("deny = [\"AGPL-3.0\"]\nunknown = \"deny\"").unwrap();
+        assert_eq!(policy.deny_in_binaries, ["GPL-2.0", "GPL-3.0", "AGPL-3.0"]);
+        assert_eq!(policy.unknown, UnknownLicense::Deny);
+
+        assert_eq!(policy.evaluate("MIT OR Apache-2.0", false), None);
+        assert_eq!(policy.evaluate("MIT/Apache-2.0", false), None);
+        assert_eq!(
+            policy.evaluate("GPL-3.0-or-later", false).as_deref(),
+            Some("GPL-3.0-or-later is denied in binaries")
+        );
+        assert_eq!(policy.evaluate("GPL-3.0-only", true), None);
+        assert_eq!(policy.evaluate("GPL-3.0+ OR MIT", false), None);
+        assert_eq!(
+            policy.evaluate("(MIT OR Apache-2.0) AND AGPL-3.0-only", true).as_deref(),
+            Some("AGPL-3.0-only is denied")
+        );
+        assert_eq!(policy.evaluate("GPL-2.0-only WITH Classpath-exception-2.0", true), None);
+        // LGPL is not GPL
+        assert_eq!(policy.evaluate("LGPL-3.0-only", false), None);
+
+        let allowlist = LicensePolicy { allow: vec!["MIT".into()], ..LicensePolicy::default() };
+        assert_eq!(
+            allowlist.evaluate("BSD-3-Clause", true).as_deref(),
+            Some("BSD-3-Clause is not allowed")
+        );
+
+        let metadata = "Metadata-Version: 2.1\nName: requests\nLicense: Apache 2.0\n\
+                        Classifier: License :: OSI Approved :: Apache Software License\n\n\
+                        Long description";
+        assert_eq!(python_metadata_license(metadata).as_deref(), Some("Apache-2.0"));
+        assert_eq!(python_metadata_license("License-Expression: MIT\n").as_deref(), Some("MIT"));
+    }
+}
@@ -0,0 +1,522 @@
+//! Lockfile unification across languages
+//!
+//! A polyglot repository usually links the same native library several times: the Rust side
+//! vendors OpenSSL through `openssl-src`, the Python wheels bundle their own, and protobuf runs
+//! at one release in `Cargo.lock` and another in `package-lock.json`. [`analyze`] reads every
+//! `Cargo.lock`, `package-lock.json`, `poetry.lock` and `requirements.txt` in a directory and its
+//! immediate subdirectories and matches their packages against a catalog of well-known
+//! libraries. It reports
+//!
+//! - [`VersionConflict`]s: one library locked at different major releases, where a package's
+//!   version tells which release it carries (`openssl-src 300.2.1+3.2.1` carries OpenSSL 3.2.1,
+//!   Python `protobuf 4.25.3` and npm `google-protobuf 3.21.2` carry protobuf 25.3 and 21.2)
+//! - [`DuplicateFunctionality`]: several packages doing the same job in one ecosystem, or
+//!   several ecosystems shipping their own copy of one native library
+//!
+//! each with a consolidation suggestion.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Ecosystem {
+    Cargo,
+    Npm,
+    Python,
+}
+
+impl fmt::Display for Ecosystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Cargo => "cargo",
+            Self::Npm => "npm",
+            Self::Python => "python",
+        })
+    }
+}
+
+/// A package at the version a lockfile pins it to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedPackage {
+    pub ecosystem: Ecosystem,
+    pub name: String,
+    pub version: String,
+    /// Lockfile relative to the analyzed directory
+    pub lockfile: String,
+}
+
+/// A library locked at different major releases
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VersionConflict {
+    pub library: String,
+    pub pins: Vec<LibraryPin>,
+    pub suggestion: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryPin {
+    pub package: LockedPackage,
+    /// Release of the library the package carries
+    pub library_version: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateFunctionality {
+    pub functionality: String,
+    /// `None` when the duplicates span ecosystems
+    pub ecosystem: Option<Ecosystem>,
+    pub packages: Vec<LockedPackage>,
+    pub suggestion: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LockfileReport {
+    pub lockfiles: Vec<String>,
+    pub packages: usize,
+    pub conflicts: Vec<VersionConflict>,
+    pub duplicates: Vec<DuplicateFunctionality>,
+}
+
+/// How a package relates to the library it belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    /// An API for the library; two in one ecosystem do the same job twice
+    Binding,
+    /// Ships a copy of the library's native code
+    Vendored,
+}
+
+/// How a package version relates to the library release it carries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Versioning {
+    /// Versioned independently of the library
+    Own,
+    /// The library's own version
+    Same,
+    /// The library version as build metadata, as in `300.2.1+3.2.1` or `2.0.10+zstd.1.5.6`
+    BuildMetadata,
+    /// A language-specific major in front of the library version, as in `4.25.3` for 25.3
+    DropMajor,
+}
+
+struct Library {
+    name: &'static str,
+    functionality: &'static str,
+    packages: &'static [(Ecosystem, &'static str, Role, Versioning)],
+}
+
+use Ecosystem::{Cargo, Npm, Python};
+use Role::{Binding, Vendored};
+use Versioning::{BuildMetadata, DropMajor, Own, Same};
+
+/// Libraries the analyzer knows, by functionality. Within a functionality, earlier packages are
+/// the ones duplicates are consolidated on.
+const LIBRARIES: &[Library] = &[
+    Library {
+        name: "openssl",
+        functionality: "TLS",
+        packages: &[
+            (Cargo, "openssl", Binding, Own),
+            (Cargo, "openssl-src", Vendored, BuildMetadata),
+            (Python, "pyopenssl", Binding, Own),
+            (Python, "cryptography", Vendored, Own),
+        ],
+    },
+    Library { name: "rustls", functionality: "TLS", packages: &[(Cargo, "rustls", Binding, Own)] },
+    Library {
+        name: "boringssl",
+        functionality: "TLS",
+        packages: &[(Cargo, "boring", Binding, Own)],
+    },
+    Library {
+        name: "protobuf",
+        functionality: "Protocol Buffers",
+        packages: &[
+            (Cargo, "prost", Binding, Own),
+            (Cargo, "protobuf", Binding, Own),
+            (Cargo, "protobuf-src", Vendored, BuildMetadata),
+            (Python, "protobuf", Binding, DropMajor),
+            (Npm, "google-protobuf", Binding, DropMajor),
+            (Npm, "protobufjs", Binding, Own),
+        ],
+    },
+    Library {
+        name: "icu",
+        functionality: "Unicode and localization",
+        packages: &[
+            (Cargo, "icu", Binding, Own),
+            (Cargo, "rust_icu_sys", Binding, Own),
+            (Python, "pyicu", Binding, Own),
+            (Npm, "full-icu", Vendored, Own),
+        ],
+    },
+    Library {
+        name: "zstd",
+        functionality: "Zstandard compression",
+        packages: &[
+            (Cargo, "zstd", Binding, Own),
+            (Cargo, "zstd-sys", Vendored, BuildMetadata),
+            (Python, "zstandard", Binding, Own),
+            (Python, "zstd", Binding, Same),
+        ],
+    },
+    Library {
+        name: "sqlite",
+        functionality: "Embedded SQL database",
+        packages: &[
+            (Cargo, "rusqlite", Binding, Own),
+            (Npm, "better-sqlite3", Binding, Own),
+            (Npm, "sqlite3", Binding, Own),
+        ],
+    },
+    Library {
+        name: "libgit2",
+        functionality: "Git",
+        packages: &[
+            (Cargo, "git2", Binding, Own),
+            (Cargo, "libgit2-sys", Vendored, BuildMetadata),
+            (Python, "pygit2", Binding, Own),
+            (Npm, "nodegit", Binding, Own),
+        ],
+    },
+    Library { name: "gitoxide", functionality: "Git", packages: &[(Cargo, "gix", Binding, Own)] },
+    Library {
+        name: "reqwest",
+        functionality: "HTTP client",
+        packages: &[(Cargo, "reqwest", Binding, Own)],
+    },
+    Library {
+        name: "ureq",
+        functionality: "HTTP client",
+        packages: &[(Cargo, "ureq", Binding, Own)],
+    },
+    Library {
+        name: "isahc",
+        functionality: "HTTP client",
+        packages: &[(Cargo, "isahc", Binding, Own)],
+    },
+    Library {
+        name: "httpx",
+        functionality: "HTTP client",
+        packages: &[(Python, "httpx", Binding, Own)],
+    },
+    Library {
+        name: "requests",
+        functionality: "HTTP client",
+        packages: &[(Python, "requests", Binding, Own)],
+    },
+    Library {
+        name: "aiohttp",
+        functionality: "HTTP client",
+        packages: &[(Python, "aiohttp", Binding, Own)],
+    },
+    Library {
+        name: "axios",
+        functionality: "HTTP client",
+        packages: &[(Npm, "axios", Binding, Own)],
+    },
+    Library {
+        name: "node-fetch",
+        functionality: "HTTP client",
+        packages: &[(Npm, "node-fetch", Binding, Own)],
+    },
+    Library { name: "got", functionality: "HTTP client", packages: &[(Npm, "got", Binding, Own)] },
+    Library {
+        name: "superagent",
+        functionality: "HTTP client",
+        packages: &[(Npm, "superagent", Binding, Own)],
+    },
+];
+
+/// Lockfiles [`analyze`] reads, and their ecosystems
+const LOCKFILES: &[(&str, Ecosystem)] = &[
+    ("Cargo.lock", Cargo),
+    ("package-lock.json", Npm),
+    ("poetry.lock", Python),
+    ("requirements.txt", Python),
+];
+
+/// Read the lockfiles of `root` and report conflicts and duplicates between them.
+pub fn analyze(root: &Path) -> Result<LockfileReport> {
+    let (lockfiles, packages) = locked_packages(root)?;
+    let mut report = unify(&packages);
+    report.lockfiles = lockfiles;
+    Ok(report)
+}
+
+/// The lockfiles in `root` and its immediate subdirectories, and every package they lock
+pub fn locked_packages(root: &Path) -> Result<(Vec<String>, Vec<LockedPackage>)> {
+    let mut lockfiles = Vec::new();
+    let mut packages = Vec::new();
+    for dir in crate::environment::project_dirs(root) {
+        for (file, ecosystem) in LOCKFILES {
+            let path = dir.join(file);
+            if !path.is_file() {
+                continue;
+            }
+            let relative = path.strip_prefix(root).unwrap_or(&path).to_string_lossy().into_owned();
+            let contents = std::fs::read_to_string(&path)
+                .with_context(|| format!("reading {}", path.display()))?;
+            let locked = parse_lockfile(file, &contents)
+                .with_context(|| format!("parsing {}", path.display()))?;
+            packages.extend(locked.into_iter().map(|(name, version)| LockedPackage {
+                ecosystem: *ecosystem,
+                name,
+                version,
+                lockfile: relative.clone(),
+            }));
+            lockfiles.push(relative);
+        }
+    }
+    Ok((lockfiles, packages))
+}
+
+/// Package names and versions locked by the lockfile called `file`
+pub fn parse_lockfile(file: &str, contents: &str) -> Result<Vec<(String, String)>> {
+    let mut locked: BTreeSet<(String, String)> = BTreeSet::new();
+    match file {
+        "Cargo.lock" | "poetry.lock" => {
+            let lock: toml::Value = toml::from_str(contents)?;
+            let packages = lock.get("package").and_then(toml::Value::as_array);
+            for package in packages.into_iter().flatten() {
+                // Cargo.lock lists workspace members without a source
+                if file == "Cargo.lock" && package.get("source").is_none() {
+                    continue;
+                }
+                let name = package.get("name").and_then(toml::Value::as_str);
+                let version = package.get("version").and_then(toml::Value::as_str);
+                if let (Some(name), Some(version)) = (name, version) {
+                    locked.insert((name.to_string(), version.to_string()));
+                }
+            }
+        }
+        "package-lock.json" => {
+            let lock: Value = serde_json::from_str(contents)?;
+            if let Some(packages) = lock.get("packages").and_then(Value::as_object) {
+                for (path, package) in packages {
+                    let Some((_, name)) = path.rsplit_once("node_modules/") else { continue };
+                    if let Some(version) = package.get("version").and_then(Value::as_str) {
+                        locked.insert((name.to_string(), version.to_string()));
+                    }
+                }
+            } else {
+                npm_v1_dependencies(&lock, &mut locked);
+            }
+        }
+        _ => {
+            for (name, version) in
+                contents.lines().filter_map(crate::environment::python_requirement)
+            {
+                // Only exact pins say what is installed
+                if version.starts_with(|c: char| c.is_ascii_digit()) {
+                    locked.insert((name, version));
+                }
+            }
+        }
+    }
+    Ok(locked.into_iter().collect())
+}
+
+/// The nested `dependencies` of a version 1 `package-lock.json`
+fn npm_v1_dependencies(node: &Value, locked: &mut BTreeSet<(String, String)>) {
+    let Some(dependencies) = node.get("dependencies").and_then(Value::as_object) else { return };
+    for (name, dependency) in dependencies {
+        if let Some(version) = dependency.get("version").and_then(Value::as_str) {
+            locked.insert((name.clone(), version.to_string()));
+        }
+        npm_v1_dependencies(dependency, locked);
+    }
+}
+
+/// Python distribution names compare case-insensitively, with `-` and `_` alike
+fn normalize(ecosystem: Ecosystem, name: &str) -> String {
+    match ecosystem {
+        Python => name.to_lowercase().replace('_', "-"),
+        _ => name.to_string(),
+    }
+}
+
+/// The library release a package `version` carries
+fn library_version(versioning: Versioning, version: &str) -> Option<String> {
+    let release = match versioning {
+        Own => return None,
+        Same => version,
+        BuildMetadata => {
+            let metadata = version.split_once('+')?.1;
+            metadata.trim_start_matches(|c: char| !c.is_ascii_digit())
+        }
+        DropMajor => version.split_once('.')?.1,
+    };
+    release.starts_with(|c: char| c.is_ascii_digit()).then(|| release.to_string())
+}
+
+fn numeric(version: &str) -> Vec<u64> {
+    version.split(['.', '-', '+']).map_while(|part| part.parse().ok()).collect()
+}
+
+fn describe(package: &LockedPackage) -> String {
+    format!("{} {} ({})", package.name, package.version, package.ecosystem)
+}
+
+/// Match `packages` against the library catalog.
+pub fn unify(packages: &[LockedPackage]) -> LockfileReport {
+    let mut conflicts = Vec::new();
+    let mut duplicates = Vec::new();
+    // Bindings per functionality and ecosystem, in catalog order
+    let mut bindings: BTreeMap<(&str, Ecosystem), Vec<(usize, &LockedPackage)>> = BTreeMap::new();
+
+    for library in LIBRARIES {
+        let mut pins = Vec::new();
+        let mut copies: Vec<&LockedPackage> = Vec::new();
+        for (rank, (ecosystem, name, role, versioning)) in library.packages.iter().enumerate() {
+            let matching = packages
+                .iter()
+                .filter(|p| p.ecosystem == *ecosystem && normalize(*ecosystem, &p.name) == *name);
+            for package in matching {
+                if let Some(version) = library_version(*versioning, &package.version) {
+                    pins.push(LibraryPin { package: package.clone(), library_version: version });
+                }
+                match role {
+                    Binding => bindings
+                        .entry((library.functionality, *ecosystem))
+                        .or_default()
+                        .push((rank, package)),
+                    Vendored => copies.push(package),
+                }
+            }
+        }
+
+        let majors: BTreeSet<u64> =
+            pins.iter().filter_map(|pin| numeric(&pin.library_version).first().copied()).collect();
+        if majors.len() > 1 {
+            pins.sort_by_key(|pin| std::cmp::Reverse(numeric(&pin.library_version)));
+            let newest = pins[0].library_version.clone();
+            let behind: Vec<String> = pins
+                .iter()
+                .filter(|pin| numeric(&pin.library_version)[0] != numeric(&newest)[0])
+                .map(|pin| format!("{} at {}", describe(&pin.package), pin.library_version))
+                .collect();
+            conflicts.push(VersionConflict {
+                library: library.name.to_string(),
+                suggestion: format!(
+                    "align every ecosystem on {} {}: upgrade {}",
+                    library.name,
+                    newest,
+                    behind.join(", ")
+                ),
+                pins,
+            });
+        }
+
+        let ecosystems: BTreeSet<Ecosystem> = copies.iter().map(|p| p.ecosystem).collect();
+        if ecosystems.len() > 1 {
+            duplicates.push(DuplicateFunctionality {
+                functionality: library.functionality.to_string(),
+                ecosystem: None,
+                suggestion: format!(
+                    "{} copies of {} ship with the project ({}): build them against one \
+                     system {} instead",
+                    copies.len(),
+                    library.name,
+                    copies.iter().map(|p| describe(p)).collect::<Vec<_>>().join(", "),
+                    library.name
+                ),
+                packages: copies.into_iter().cloned().collect(),
+            });
+        }
+    }
+
+    for ((functionality, ecosystem), mut found) in bindings {
+        found.sort_by_key(|(rank, package)| (*rank, package.name.clone()));
+        let names: BTreeSet<&str> = found.iter().map(|(_, p)| p.name.as_str()).collect();
+        if names.len() < 2 {
+            continue;
+        }
+        let keep = &found[0].1.name;
+        let others: Vec<&str> = names.iter().copied().filter(|name| name != keep).collect();
+        duplicates.push(DuplicateFunctionality {
+            functionality: functionality.to_string(),
+            ecosystem: Some(ecosystem),
+            suggestion: format!(
+                "{} in {} comes from {} packages: consolidate on {} and drop {}",
+                functionality,
+                ecosystem,
+                names.len(),
+                keep,
+                others.join(", ")
+            ),
+            packages: found.into_iter().map(|(_, package)| package.clone()).collect(),
+        });
+    }
+
+    LockfileReport { lockfiles: Vec::new(), packages: packages.len(), conflicts, duplicates }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locked(ecosystem: Ecosystem, name: &str, version: &str) -> LockedPackage {
+        LockedPackage {
+            ecosystem,
+            name: name.into(),
+            version: version.into(),
+            lockfile: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_reports_conflicts_and_duplicates_across_lockfiles() {
+        let cargo = parse_lockfile(
+            "Cargo.lock",
+            "[[package]]\nname = \"app\"\nversion = \"0.1.0\"\n\n\
+             [[package]]\nname = \"protobuf-src\"\nversion = \"1.1.0+21.5\"\nsource = \"registry\"\n",
+        )
+        .unwrap();
+        assert_eq!(cargo, [("protobuf-src".to_string(), "1.1.0+21.5".to_string())]);
+        let npm = parse_lockfile(
+            "package-lock.json",
+            r#"{"packages": {"": {"version": "1.0.0"},
+                "node_modules/google-protobuf": {"version": "3.21.2"},
+                "node_modules/a/node_modules/axios": {"version": "1.6.0"}}}"#,
+        )
+        .unwrap();
+        assert_eq!(npm.len(), 2);
+        let python = parse_lockfile("requirements.txt", "protobuf==4.25.3\nhttpx>=0.27\n").unwrap();
+        assert_eq!(python, [("protobuf".to_string(), "4.25.3".to_string())]);
+
+        let report = unify(&[
+            locked(Cargo, "protobuf-src", "1.1.0+21.5"),
+            locked(Npm, "google-protobuf", "3.21.2"),
+            locked(Python, "protobuf", "4.25.3"),
+            locked(Cargo, "openssl-src", "300.2.1+3.2.1"),
+            locked(Python, "cryptography", "42.0.5"),
+            locked(Cargo, "reqwest", "0.12.4"),
+            locked(Cargo, "ureq", "2.9.7"),
+            locked(Npm, "axios", "1.6.0"),
+        ]);
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].library, "protobuf");
+        assert_eq!(
+            report.conflicts[0].suggestion,
+            "align every ecosystem on protobuf 25.3: upgrade protobuf-src 1.1.0+21.5 (cargo) at \
+             21.5, google-protobuf 3.21.2 (npm) at 21.2"
+        );
+
+        let suggestions: Vec<&str> =
+            report.duplicates.iter().map(|d| d.suggestion.as_str()).collect();
+        assert_eq!(
+            suggestions,
+            [
+                "2 copies of openssl ship with the project (openssl-src 300.2.1+3.2.1 (cargo), \
+                 cryptography 42.0.5 (python)): build them against one system openssl instead",
+                "HTTP client in cargo comes from 2 packages: consolidate on reqwest and drop ureq",
+            ]
+        );
+    }
+}
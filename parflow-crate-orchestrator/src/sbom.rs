@@ -0,0 +1,446 @@
+//! Software bills of materials for a cargo workspace
+//!
+//! [`collect`] runs `cargo metadata` on a workspace and gathers every package of its resolved
+//! dependency graph, members and transitive dependencies alike, with the SHA-256 checksum
+//! `Cargo.lock` records for registry packages. [`render`] writes them out as a CycloneDX 1.5 or
+//! an SPDX 2.3 JSON document. Packages only reachable through dev-dependencies do not ship in
+//! the workspace's binaries; CycloneDX marks them `optional`.
+
+use anyhow::{bail, Context, Result};
+use cargo_metadata::{DependencyKind, Metadata, MetadataCommand};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::path::Path;
+use std::str::FromStr;
+
+const CRATES_IO: &str = "registry+https://github.com/rust-lang/crates.io-index";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SbomFormat {
+    CycloneDx,
+    Spdx,
+}
+
+impl FromStr for SbomFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "cyclonedx" => Ok(Self::CycloneDx),
+            "spdx" => Ok(Self::Spdx),
+            other => bail!("unknown SBOM format '{}' (expected cyclonedx or spdx)", other),
+        }
+    }
+}
+
+/// A package of the resolved graph
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SbomPackage {
+    /// Cargo package id, unique within the workspace
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    pub description: Option<String>,
+    /// License expression as the manifest declares it
+    pub license: Option<String>,
+    pub repository: Option<String>,
+    /// Where the package comes from, `None` for path dependencies and members
+    pub source: Option<String>,
+    /// SHA-256 of the `.crate` file, from `Cargo.lock`
+    pub checksum: Option<String>,
+    pub member: bool,
+    /// Only needed to build tests, examples and benchmarks of the members
+    pub dev_only: bool,
+    /// Whether the package builds an executable
+    pub binary: bool,
+    /// Ids of the packages it depends on
+    pub dependencies: Vec<String>,
+}
+
+impl SbomPackage {
+    /// Package URL, `pkg:cargo/<name>@<version>`
+    pub fn purl(&self) -> String {
+        match &self.source {
+            Some(source) if source.starts_with("git+") => format!(
+                "pkg:cargo/{}@{}?vcs_url={}",
+                self.name,
+                self.version,
+                source.replace(':', "%3A").replace('?', "%3F").replace('#', "%23")
+            ),
+            _ => format!("pkg:cargo/{}@{}", self.name, self.version),
+        }
+    }
+
+    /// SPDX expression of the license; manifests of old crates separate alternatives with `/`
+    pub fn spdx_license(&self) -> Option<String> {
+        self.license
+            .as_ref()
+            .map(|license| license.split('/').map(str::trim).collect::<Vec<_>>().join(" OR "))
+    }
+}
+
+/// The resolved packages of the workspace containing `manifest_path`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Workspace {
+    pub name: String,
+    pub packages: Vec<SbomPackage>,
+}
+
+/// Run `cargo metadata` on `manifest_path` and gather the packages of its workspace.
+pub fn collect(manifest_path: &Path) -> Result<Workspace> {
+    let metadata = MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .exec()
+        .with_context(|| format!("running cargo metadata on {}", manifest_path.display()))?;
+    let lock = std::fs::read_to_string(metadata.workspace_root.join("Cargo.lock"))
+        .context("reading Cargo.lock")?;
+    from_metadata(&metadata, &lock)
+}
+
+/// The packages of `metadata`, with checksums from the `Cargo.lock` text `lock`
+pub fn from_metadata(metadata: &Metadata, lock: &str) -> Result<Workspace> {
+    let lock: toml::Value = toml::from_str(lock).context("parsing Cargo.lock")?;
+    let checksums: BTreeMap<(String, String), String> = lock
+        .get("package")
+        .and_then(toml::Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|package| {
+            let field = |key: &str| package.get(key)?.as_str().map(String::from);
+            Some(((field("name")?, field("version")?), field("checksum")?))
+        })
+        .collect();
+
+    let resolve = metadata.resolve.as_ref().context("cargo metadata did not resolve the graph")?;
+    let nodes: BTreeMap<&str, &cargo_metadata::Node> =
+        resolve.nodes.iter().map(|node| (node.id.repr.as_str(), node)).collect();
+    let members: BTreeSet<&str> =
+        metadata.workspace_members.iter().map(|id| id.repr.as_str()).collect();
+
+    // Everything reachable from a member without going through a dev-dependency ships
+    let mut shipped: BTreeSet<&str> = members.clone();
+    let mut queue: VecDeque<&str> = members.iter().copied().collect();
+    while let Some(id) = queue.pop_front() {
+        let Some(node) = nodes.get(id) else { continue };
+        for dep in &node.deps {
+            let dev = dep.dep_kinds.iter().all(|kind| kind.kind == DependencyKind::Development);
+            if !dev && shipped.insert(dep.pkg.repr.as_str()) {
+                queue.push_back(dep.pkg.repr.as_str());
+            }
+        }
+    }
+
+    let packages = metadata
+        .packages
+        .iter()
+        .filter(|package| nodes.contains_key(package.id.repr.as_str()))
+        .map(|package| {
+            let id = package.id.repr.as_str();
+            let version = package.version.to_string();
+            SbomPackage {
+                id: id.to_string(),
+                name: package.name.clone(),
+                checksum: checksums.get(&(package.name.clone(), version.clone())).cloned(),
+                version,
+                description: package.description.clone(),
+                license: package.license.clone(),
+                repository: package.repository.clone(),
+                source: package.source.as_ref().map(|source| source.repr.clone()),
+                member: members.contains(id),
+                dev_only: !shipped.contains(id),
+                binary: package.targets.iter().any(|target| target.is_bin()),
+                dependencies: nodes[id].deps.iter().map(|dep| dep.pkg.repr.clone()).collect(),
+            }
+        })
+        .collect();
+
+    let name = metadata
+        .root_package()
+        .map(|package| package.name.clone())
+        .or_else(|| metadata.workspace_root.file_name().map(String::from))
+        .unwrap_or_else(|| "workspace".to_string());
+    Ok(Workspace { name, packages })
+}
+
+/// The SBOM of `workspace` as a JSON document
+pub fn render(workspace: &Workspace, format: SbomFormat) -> Value {
+    match format {
+        SbomFormat::CycloneDx => cyclonedx(workspace),
+        SbomFormat::Spdx => spdx(workspace),
+    }
+}
+
+fn tool_version() -> &'static str {
+    env!("CARGO_PKG_VERSION")
+}
+
+fn cyclonedx(workspace: &Workspace) -> Value {
+    let components: Vec<Value> = workspace
+        .packages
+        .iter()
+        .map(|package| {
+            let mut component = json!({
+                "type": if package.member && package.binary { "application" } else { "library" },
+                "bom-ref": package.id,
+                "name": package.name,
+                "version": package.version,
+                "purl": package.purl(),
+                "scope": if package.dev_only { "optional" } else { "required" },
+            });
+            if let Some(description) = &package.description {
+                component["description"] = description.trim().into();
+            }
+            if let Some(license) = package.spdx_license() {
+                component["licenses"] = json!([{ "expression": license }]);
+            }
+            if let Some(checksum) = &package.checksum {
+                component["hashes"] = json!([{ "alg": "SHA-256", "content": checksum }]);
+            }
+            let mut references = Vec::new();
+            if package.source.as_deref() == Some(CRATES_IO) {
+                references.push(json!({
+                    "type": "distribution",
+                    "url": format!(
+                        "https://crates.io/api/v1/crates/{}/{}/download",
+                        package.name, package.version
+                    ),
+                }));
+            }
+            if let Some(repository) = &package.repository {
+                references.push(json!({ "type": "vcs", "url": repository }));
+            }
+            if !references.is_empty() {
+                component["externalReferences"] = references.into();
+            }
+            component
+        })
+        .collect();
+
+    let root_ref = format!("workspace:{}", workspace.name);
+    let mut dependencies = vec![json!({
+        "ref": root_ref,
+        "dependsOn": workspace
+            .packages
+            .iter()
+            .filter(|package| package.member)
+            .map(|package| package.id.clone())
+            .collect::<Vec<_>>(),
+    })];
+    dependencies.extend(
+        workspace
+            .packages
+            .iter()
+            .map(|package| json!({ "ref": package.id, "dependsOn": package.dependencies })),
+    );
+
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "serialNumber": format!("urn:uuid:{}", uuid::Uuid::new_v4()),
+        "version": 1,
+        "metadata": {
+            "timestamp": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            "tools": {
+                "components": [
+                    { "type": "application", "name": "parflow", "version": tool_version() }
+                ],
+            },
+            "component": { "type": "application", "bom-ref": root_ref, "name": workspace.name },
+        },
+        "components": components,
+        "dependencies": dependencies,
+    })
+}
+
+/// An SPDX element id: letters, digits, `.` and `-` only
+fn spdx_id(index: usize, package: &SbomPackage) -> String {
+    let name: String = package
+        .name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' { c } else { '-' })
+        .collect();
+    format!("SPDXRef-Package-{}-{}", index, name)
+}
+
+fn spdx(workspace: &Workspace) -> Value {
+    let ids: BTreeMap<&str, String> = workspace
+        .packages
+        .iter()
+        .enumerate()
+        .map(|(index, package)| (package.id.as_str(), spdx_id(index, package)))
+        .collect();
+    let packages: Vec<Value> = workspace
+        .packages
+        .iter()
+        .map(|package| {
+            let download = match package.source.as_deref() {
+                Some(CRATES_IO) => format!(
+                    "https://crates.io/api/v1/crates/{}/{}/download",
+                    package.name, package.version
+                ),
+                Some(source) if source.starts_with("git+") => source.to_string(),
+                _ => "NOASSERTION".to_string(),
+            };
+            let mut element = json!({
+                "SPDXID": ids[package.id.as_str()],
+                "name": package.name,
+                "versionInfo": package.version,
+                "downloadLocation": download,
+                "filesAnalyzed": false,
+                "licenseConcluded": "NOASSERTION",
+                "licenseDeclared": package.spdx_license().unwrap_or_else(|| "NOASSERTION".into()),
+                "copyrightText": "NOASSERTION",
+                "primaryPackagePurpose": if package.member && package.binary {
+                    "APPLICATION"
+                } else {
+                    "LIBRARY"
+                },
+                "externalRefs": [{
+                    "referenceCategory": "PACKAGE-MANAGER",
+                    "referenceType": "purl",
+                    "referenceLocator": package.purl(),
+                }],
+            });
+            if let Some(description) = &package.description {
+                element["summary"] = description.trim().into();
+            }
+            if let Some(checksum) = &package.checksum {
+                element["checksums"] =
+                    json!([{ "algorithm": "SHA256", "checksumValue": checksum }]);
+            }
+            element
+        })
+        .collect();
+
+    let mut relationships: Vec<Value> = workspace
+        .packages
+        .iter()
+        .filter(|package| package.member)
+        .map(|package| {
+            json!({
+                "spdxElementId": "SPDXRef-DOCUMENT",
+                "relationshipType": "DESCRIBES",
+                "relatedSpdxElement": ids[package.id.as_str()],
+            })
+        })
+        .collect();
+    let dev_only: BTreeSet<&str> = workspace
+        .packages
+        .iter()
+        .filter(|package| package.dev_only)
+        .map(|package| package.id.as_str())
+        .collect();
+    for package in &workspace.packages {
+        let id = &ids[package.id.as_str()];
+        for dependency in &package.dependencies {
+            let Some(related) = ids.get(dependency.as_str()) else { continue };
+            // DEV_DEPENDENCY_OF points from the dependency to its dependent
+            let relationship = if dev_only.contains(dependency.as_str()) && !package.dev_only {
+                json!({
+                    "spdxElementId": related,
+                    "relationshipType": "DEV_DEPENDENCY_OF",
+                    "relatedSpdxElement": id,
+                })
+            } else {
+                json!({
+                    "spdxElementId": id,
+                    "relationshipType": "DEPENDS_ON",
+                    "relatedSpdxElement": related,
+                })
+            };
+            relationships.push(relationship);
+        }
+    }
+
+    json!({
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": workspace.name,
+        "documentNamespace": format!(
+            "https://spdx.org/spdxdocs/{}-{}",
+            workspace.name,
+            uuid::Uuid::new_v4()
+        ),
+        "creationInfo": {
+            "created": chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+            "creators": [format!("Tool: parflow-{}", tool_version())],
+        },
+        "packages": packages,
+        "relationships": relationships,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str, member: bool, dev_only: bool, dependencies: &[&str]) -> SbomPackage {
+        SbomPackage {
+            id: format!("{} 1.0.0", name),
+            name: name.into(),
+            version: "1.0.0".into(),
+            description: None,
+            license: Some("MIT/Apache-2.0".into()),
+            repository: None,
+            source: (!member).then(|| CRATES_IO.to_string()),
+            checksum: (!member).then(|| "ab".repeat(32)),
+            member,
+            dev_only,
+            binary: member,
+            dependencies: dependencies.iter().map(|d| format!("{} 1.0.0", d)).collect(),
+        }
+    }
+
+    #[test]
+    fn test_renders_cyclonedx_and_spdx() {
+        let workspace = Workspace {
+            name: "app".into(),
+            packages: vec![
+                package("app", true, false, &["serde", "proptest"]),
+                package("serde", false, false, &[]),
+                package("proptest", false, true, &[]),
+            ],
+        };
+
+        let bom = render(&workspace, SbomFormat::CycloneDx);
+        assert_eq!(bom["bomFormat"], "CycloneDX");
+        let components = bom["components"].as_array().unwrap();
+        assert_eq!(components.len(), 3);
+        assert_eq!(components[0]["type"], "application");
+        assert_eq!(components[1]["purl"], "pkg:cargo/serde@1.0.0");
+        assert_eq!(components[1]["licenses"][0]["expression"], "MIT OR Apache-2.0");
+        assert_eq!(components[1]["hashes"][0]["content"], "ab".repeat(32));
+        assert_eq!(components[2]["scope"], "optional");
+        assert_eq!(bom["dependencies"][0]["dependsOn"], json!(["app 1.0.0"]));
+        assert_eq!(bom["dependencies"][1]["dependsOn"], json!(["serde 1.0.0", "proptest 1.0.0"]));
+
+        let document = render(&workspace, SbomFormat::Spdx);
+        assert_eq!(document["spdxVersion"], "SPDX-2.3");
+        assert_eq!(document["packages"][1]["SPDXID"], "SPDXRef-Package-1-serde");
+        assert_eq!(document["packages"][1]["checksums"][0]["algorithm"], "SHA256");
+        let relationships: Vec<String> = document["relationships"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| {
+                format!(
+                    "{} {} {}",
+                    r["spdxElementId"].as_str().unwrap(),
+                    r["relationshipType"].as_str().unwrap(),
+                    r["relatedSpdxElement"].as_str().unwrap()
+                )
+            })
+            .collect();
+        assert_eq!(
+            relationships,
+            [
+                "SPDXRef-DOCUMENT DESCRIBES SPDXRef-Package-0-app",
+                "SPDXRef-Package-0-app DEPENDS_ON SPDXRef-Package-1-serde",
+                "SPDXRef-Package-2-proptest DEV_DEPENDENCY_OF SPDXRef-Package-0-app",
+            ]
+        );
+    }
+}
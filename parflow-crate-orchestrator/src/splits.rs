@@ -0,0 +1,341 @@
+//! Crate split proposals
+//!
+//! Uses the module dependency graph of each workspace member (from the semantic compiler) and
+//! the member's measured compile time to propose which top-level modules to extract into
+//! sub-crates so that rustc can build them in parallel. A module's share of the compile time is
+//! estimated from its share of the crate's lines.
+//!
+//! Modules that refer back to the crate root cannot leave without it and are reported as
+//! pinned; modules that refer to each other in a cycle can only move together. Among the rest,
+//! extractions are chosen greedily by how much they shorten the estimated build. The estimate
+//! follows cargo's pipelining: a crate starts once the crates it needs have their metadata,
+//! which takes the measured frontend share of their compile time, so the remaining crate's
+//! frontend overlaps with the code generation of everything extracted from it.
+
+use crate::timings::BuildTimings;
+use anyhow::{Context, Result};
+use cargo_metadata::MetadataCommand;
+use semantic_compiler::module_graph::{self, ModuleGraph};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// Fixed cost of each extra crate: its own rustc invocation, metadata and scheduling
+pub const CRATE_OVERHEAD_SECS: f64 = 0.3;
+
+/// Smallest share of the compile time an extraction has to save to be proposed
+const MIN_GAIN: f64 = 0.02;
+
+/// Frontend share assumed for crates cargo reported no sections for
+const DEFAULT_FRONTEND_SHARE: f64 = 0.5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProposedCrate {
+    pub name: String,
+    pub modules: Vec<String>,
+    pub lines: usize,
+    pub secs: f64,
+    /// Other proposed crates this one needs
+    pub depends_on: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrateSplit {
+    pub member: String,
+    pub compile_secs: f64,
+    pub lines: usize,
+    pub modules: usize,
+    /// In the order they were chosen, most effective first
+    pub proposals: Vec<ProposedCrate>,
+    /// Top-level modules that refer to items of the crate root
+    pub pinned: Vec<String>,
+    pub estimated_secs: f64,
+}
+
+impl CrateSplit {
+    pub fn savings_secs(&self) -> f64 {
+        self.compile_secs - self.estimated_secs
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SplitReport {
+    /// Commit the compile times were measured at
+    pub commit: String,
+    /// Largest savings first
+    pub splits: Vec<CrateSplit>,
+}
+
+/// Propose splits for every member of the workspace of `manifest_path` that `timings` has a
+/// compile time for.
+pub fn analyze(manifest_path: &Path, timings: &BuildTimings) -> Result<SplitReport> {
+    let metadata = MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .no_deps()
+        .exec()
+        .with_context(|| format!("running cargo metadata on {}", manifest_path.display()))?;
+    let mut splits = Vec::new();
+    for member in metadata.workspace_packages() {
+        let Some(&secs) = timings.crates.get(member.name.as_str()) else { continue };
+        let frontend_share = match timings.frontend.get(member.name.as_str()) {
+            Some(frontend) if secs > 0.0 => (frontend / secs).clamp(0.0, 1.0),
+            _ => DEFAULT_FRONTEND_SHARE,
+        };
+        // Libraries built as `cdylib`/`rlib` and proc macros don't report the plain `lib` kind
+        let library = |target: &&cargo_metadata::Target| {
+            target.kind.iter().any(|kind| kind.ends_with("lib") || kind == "proc-macro")
+        };
+        let target = member
+            .targets
+            .iter()
+            .find(library)
+            .or_else(|| member.targets.iter().find(|target| target.is_bin()));
+        let Some(target) = target else { continue };
+        let graph = ModuleGraph::from_crate_root(target.src_path.as_std_path())
+            .with_context(|| format!("reading the modules of {}", member.name))?;
+        splits.push(propose(&member.name, &graph, secs, frontend_share));
+    }
+    splits.sort_by(|a, b| b.savings_secs().total_cmp(&a.savings_secs()));
+    Ok(SplitReport { commit: timings.commit.clone(), splits })
+}
+
+/// The top-level module `module` belongs to, or the crate root
+fn group_of(module: &str) -> String {
+    module.splitn(3, "::").take(2).collect::<Vec<_>>().join("::")
+}
+
+fn reachable(from: &str, edges: &BTreeMap<String, BTreeSet<String>>) -> BTreeSet<String> {
+    let mut seen = BTreeSet::new();
+    let mut stack = vec![from.to_string()];
+    while let Some(group) = stack.pop() {
+        for next in edges.get(&group).into_iter().flatten() {
+            if seen.insert(next.clone()) {
+                stack.push(next.clone());
+            }
+        }
+    }
+    seen
+}
+
+/// Groups of top-level modules that can only be extracted together
+struct Component {
+    groups: BTreeSet<String>,
+    secs: f64,
+    dependencies: BTreeSet<usize>,
+}
+
+/// Estimated build time with `crates` (sets of components) extracted
+fn estimate(
+    compile_secs: f64,
+    frontend_share: f64,
+    components: &[Component],
+    crates: &[BTreeSet<usize>],
+) -> f64 {
+    let owner: BTreeMap<usize, usize> = crates
+        .iter()
+        .enumerate()
+        .flat_map(|(index, members)| members.iter().map(move |&component| (component, index)))
+        .collect();
+    // Crates only depend on crates extracted before them
+    let mut metadata: Vec<f64> = Vec::new();
+    let mut finish: f64 = 0.0;
+    let mut extracted = 0.0;
+    for members in crates {
+        let secs: f64 = members.iter().map(|&component| components[component].secs).sum();
+        let start = members
+            .iter()
+            .flat_map(|&component| &components[component].dependencies)
+            .filter_map(|dependency| owner.get(dependency))
+            .filter_map(|&index| metadata.get(index))
+            .fold(0.0_f64, |start, &ready| start.max(ready));
+        metadata.push(start + CRATE_OVERHEAD_SECS + secs * frontend_share);
+        finish = finish.max(start + CRATE_OVERHEAD_SECS + secs);
+        extracted += secs;
+    }
+    let start = metadata.iter().fold(0.0_f64, |start, &ready| start.max(ready));
+    finish.max(start + compile_secs - extracted)
+}
+
+/// Propose sub-crates for `member`, whose build took `compile_secs`, `frontend_share` of it
+/// before its metadata was ready.
+pub fn propose(
+    member: &str,
+    graph: &ModuleGraph,
+    compile_secs: f64,
+    frontend_share: f64,
+) -> CrateSplit {
+    let total_lines = graph.total_lines().max(1);
+    let mut lines: BTreeMap<String, usize> = BTreeMap::new();
+    let mut modules: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut edges: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for (path, module) in &graph.modules {
+        let group = group_of(path);
+        *lines.entry(group.clone()).or_default() += module.lines;
+        modules.entry(group.clone()).or_default().push(path.clone());
+        let targets = graph.dependencies.get(path).into_iter().flatten();
+        let targets: BTreeSet<String> =
+            targets.map(|target| group_of(target)).filter(|target| *target != group).collect();
+        edges.entry(group).or_default().extend(targets);
+    }
+    let reach: BTreeMap<&String, BTreeSet<String>> =
+        lines.keys().map(|group| (group, reachable(group, &edges))).collect();
+    let pinned: Vec<String> = reach
+        .iter()
+        .filter(|(group, reached)| {
+            group.as_str() != module_graph::ROOT && reached.contains(module_graph::ROOT)
+        })
+        .map(|(group, _)| group.to_string())
+        .collect();
+
+    let mut components: Vec<Component> = Vec::new();
+    let mut component_of: BTreeMap<&String, usize> = BTreeMap::new();
+    for (group, reached) in &reach {
+        if group.as_str() == module_graph::ROOT || pinned.contains(group) {
+            continue;
+        }
+        if component_of.contains_key(group) {
+            continue;
+        }
+        // Members of a cycle reach each other
+        let mut groups: BTreeSet<String> = reached
+            .iter()
+            .filter(|other| reach.get(other).is_some_and(|back| back.contains(*group)))
+            .cloned()
+            .collect();
+        groups.insert(group.to_string());
+        let secs = groups.iter().map(|group| lines[group]).sum::<usize>() as f64
+            / total_lines as f64
+            * compile_secs;
+        for member in &groups {
+            if let Some((key, _)) = reach.get_key_value(member) {
+                component_of.insert(key, components.len());
+            }
+        }
+        components.push(Component { groups, secs, dependencies: BTreeSet::new() });
+    }
+    for component in &mut components {
+        let targets = component.groups.iter().flat_map(|group| &edges[group]);
+        component.dependencies =
+            targets.filter_map(|target| component_of.get(target)).copied().collect();
+    }
+
+    let mut crates: Vec<BTreeSet<usize>> = Vec::new();
+    let mut estimated = compile_secs;
+    loop {
+        let taken: BTreeSet<usize> = crates.iter().flatten().copied().collect();
+        let mut best: Option<(f64, BTreeSet<usize>)> = None;
+        for start in (0..components.len()).filter(|component| !taken.contains(component)) {
+            // The component moves with whatever it needs that has not moved yet
+            let mut candidate = BTreeSet::from([start]);
+            let mut stack = vec![start];
+            while let Some(component) = stack.pop() {
+                for &dependency in &components[component].dependencies {
+                    if !taken.contains(&dependency) && candidate.insert(dependency) {
+                        stack.push(dependency);
+                    }
+                }
+            }
+            let mut trial = crates.clone();
+            trial.push(candidate.clone());
+            let secs = estimate(compile_secs, frontend_share, &components, &trial);
+            if best.as_ref().is_none_or(|(best, _)| secs < *best) {
+                best = Some((secs, candidate));
+            }
+        }
+        match best {
+            Some((secs, candidate)) if estimated - secs >= MIN_GAIN * compile_secs => {
+                estimated = secs;
+                crates.push(candidate);
+            }
+            _ => break,
+        }
+    }
+
+    let names: Vec<String> = crates
+        .iter()
+        .map(|members| {
+            let largest = members
+                .iter()
+                .flat_map(|&component| &components[component].groups)
+                .max_by_key(|group| lines[*group])
+                .map(|group| group.rsplit("::").next().unwrap_or(group))
+                .unwrap_or_default();
+            format!("{}-{}", member, largest.replace('_', "-"))
+        })
+        .collect();
+    let proposals = crates
+        .iter()
+        .enumerate()
+        .map(|(index, members)| {
+            let groups: Vec<&String> =
+                members.iter().flat_map(|&component| &components[component].groups).collect();
+            let depends_on: BTreeSet<&String> = members
+                .iter()
+                .flat_map(|&component| &components[component].dependencies)
+                .filter_map(|dependency| crates.iter().position(|other| other.contains(dependency)))
+                .filter(|&other| other != index)
+                .map(|other| &names[other])
+                .collect();
+            ProposedCrate {
+                name: names[index].clone(),
+                modules: groups.iter().flat_map(|group| modules[*group].iter().cloned()).collect(),
+                lines: groups.iter().map(|group| lines[*group]).sum(),
+                secs: members.iter().map(|&component| components[component].secs).sum(),
+                depends_on: depends_on.into_iter().cloned().collect(),
+            }
+        })
+        .collect();
+
+    CrateSplit {
+        member: member.to_string(),
+        compile_secs,
+        lines: graph.total_lines(),
+        modules: graph.modules.len(),
+        proposals,
+        pinned,
+        estimated_secs: estimated,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use semantic_compiler::module_graph::ModuleNode;
+
+    #[test]
+    fn test_extracts_independent_modules_and_keeps_pinned_ones() {
+        let mut graph = ModuleGraph::default();
+        let modules = [
+            ("crate", 100, vec!["crate::parser", "crate::eval"]),
+            ("crate::parser", 300, vec!["crate::ast"]),
+            ("crate::parser::lexer", 100, vec![]),
+            ("crate::ast", 200, vec![]),
+            ("crate::eval", 290, vec!["crate"]),
+            ("crate::a", 5, vec!["crate::b"]),
+            ("crate::b", 5, vec!["crate::a"]),
+        ];
+        for (path, lines, dependencies) in modules {
+            graph.modules.insert(
+                path.to_string(),
+                ModuleNode { path: path.to_string(), file: Default::default(), lines },
+            );
+            graph
+                .dependencies
+                .insert(path.to_string(), dependencies.into_iter().map(String::from).collect());
+        }
+
+        let split = propose("engine", &graph, 10.0, 0.5);
+        assert_eq!(split.pinned, ["crate::eval"]);
+        let names: Vec<&str> = split.proposals.iter().map(|p| p.name.as_str()).collect();
+        // The small a/b cycle is not worth a crate of its own
+        assert_eq!(names, ["engine-parser"]);
+        // The parser needs the AST, which has nowhere else to go
+        assert_eq!(
+            split.proposals[0].modules,
+            ["crate::ast", "crate::parser", "crate::parser::lexer"]
+        );
+        assert_eq!(split.proposals[0].lines, 600);
+        // The remaining 4s start once the extracted 6s have their metadata, after 0.3 + 3s
+        assert!((split.estimated_secs - 7.3).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,302 @@
+//! Compile-time tracking per commit
+//!
+//! [`measure`] builds the workspace with `cargo build --timings` and reads the per-unit timings
+//! from the HTML report cargo writes (the `UNIT_DATA` table its charts are drawn from), summed
+//! per crate. Snapshots are tagged with the commit they were measured at, and
+//! [`regressions`] walks a history of them to find the commits, and the crates within them,
+//! whose compile time grew past a threshold.
+//!
+//! Only the workspace's own crates are cleaned before measuring unless a full build is asked
+//! for, so dependencies come from the cache and only crates rebuilt in both snapshots are
+//! compared.
+
+use anyhow::{bail, Context, Result};
+use cargo_metadata::MetadataCommand;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Findings database table snapshots are recorded in
+pub const TABLE: &str = "build_timings";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BuildTimings {
+    /// Commit hash, suffixed with `-dirty` when the tree had uncommitted changes
+    pub commit: String,
+    pub subject: String,
+    pub timestamp: i64,
+    /// Whether dependencies were rebuilt too
+    pub full: bool,
+    pub total_secs: f64,
+    /// Seconds spent compiling each crate, build scripts included
+    pub crates: BTreeMap<String, f64>,
+    /// Seconds of each crate's compilation spent before its metadata was ready for dependents,
+    /// for the crates cargo reports sections for
+    #[serde(default)]
+    pub frontend: BTreeMap<String, f64>,
+}
+
+/// Compile-time growth of one crate, or of the whole build
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Regression {
+    pub name: String,
+    pub before_secs: f64,
+    pub after_secs: f64,
+}
+
+impl Regression {
+    pub fn delta_secs(&self) -> f64 {
+        self.after_secs - self.before_secs
+    }
+
+    pub fn percent(&self) -> f64 {
+        self.delta_secs() / self.before_secs.max(f64::EPSILON) * 100.0
+    }
+}
+
+/// A commit that made the build slower than the one measured before it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitRegression {
+    pub commit: String,
+    pub subject: String,
+    pub previous_commit: String,
+    /// Set when the total build time regressed
+    pub total: Option<Regression>,
+    /// Largest regression first
+    pub crates: Vec<Regression>,
+}
+
+/// When a change in compile time counts as a regression
+#[derive(Debug, Clone, Copy)]
+pub struct Threshold {
+    pub percent: f64,
+    /// Growth below this many seconds is noise however large in relative terms
+    pub min_secs: f64,
+}
+
+impl Threshold {
+    fn exceeded(&self, before: f64, after: f64) -> Option<Regression> {
+        let regression = Regression { name: String::new(), before_secs: before, after_secs: after };
+        (regression.delta_secs() >= self.min_secs && regression.percent() >= self.percent)
+            .then_some(regression)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Section {
+    start: f64,
+    end: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct Unit {
+    name: String,
+    duration: f64,
+    start: f64,
+    #[serde(default)]
+    sections: Option<Vec<(String, Section)>>,
+}
+
+/// The timings in a cargo timing report, not yet tagged with a commit
+pub fn parse_report(html: &str) -> Result<BuildTimings> {
+    let start = html.find("const UNIT_DATA = ").context("no UNIT_DATA in the timing report")?;
+    let data = &html[start + "const UNIT_DATA = ".len()..];
+    let units: Vec<Unit> = serde_json::Deserializer::from_str(data)
+        .into_iter()
+        .next()
+        .context("empty UNIT_DATA in the timing report")??;
+    let mut timings = BuildTimings {
+        commit: String::new(),
+        subject: String::new(),
+        timestamp: 0,
+        full: false,
+        total_secs: 0.0,
+        crates: BTreeMap::new(),
+        frontend: BTreeMap::new(),
+    };
+    for unit in units {
+        let frontend = unit.sections.iter().flatten().find(|(name, _)| name == "frontend");
+        if let Some((_, section)) = frontend {
+            *timings.frontend.entry(unit.name.clone()).or_default() += section.end - section.start;
+        }
+        *timings.crates.entry(unit.name).or_default() += unit.duration;
+        timings.total_secs = timings.total_secs.max(unit.start + unit.duration);
+    }
+    Ok(timings)
+}
+
+fn git(root: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).current_dir(root).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The commit checked out in `root` and its subject
+pub fn head_commit(root: &Path) -> Result<(String, String)> {
+    let commit = git(root, &["rev-parse", "HEAD"]).context("not a git repository")?;
+    let subject = git(root, &["log", "-1", "--format=%s"]).unwrap_or_default();
+    let dirty = git(root, &["status", "--porcelain", "--untracked-files=no"])
+        .is_some_and(|status| !status.is_empty());
+    Ok((if dirty { format!("{}-dirty", commit) } else { commit }, subject))
+}
+
+/// Build the workspace of `manifest_path` with `--timings` at the current commit; `full`
+/// rebuilds dependencies as well as the workspace's own crates.
+pub fn measure(manifest_path: &Path, full: bool) -> Result<BuildTimings> {
+    let metadata = MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .no_deps()
+        .exec()
+        .with_context(|| format!("running cargo metadata on {}", manifest_path.display()))?;
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".into());
+    let root = metadata.workspace_root.as_std_path();
+    let (commit, subject) = head_commit(root)?;
+
+    let mut clean = Command::new(&cargo);
+    clean.arg("clean").arg("--manifest-path").arg(manifest_path);
+    if !full {
+        for member in metadata.workspace_packages() {
+            clean.args(["-p", &member.name]);
+        }
+    }
+    if !clean.stdout(Stdio::null()).stderr(Stdio::null()).status()?.success() {
+        bail!("cargo clean failed");
+    }
+
+    tracing::info!(commit = %commit, "⏱️  Timing build");
+    let status = Command::new(&cargo)
+        .args(["build", "--workspace", "--timings"])
+        .arg("--manifest-path")
+        .arg(manifest_path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .context("running cargo build --timings")?;
+    if !status.success() {
+        bail!("cargo build --timings failed");
+    }
+    let report = metadata.target_directory.as_std_path().join("cargo-timings/cargo-timing.html");
+    let html = std::fs::read_to_string(&report)
+        .with_context(|| format!("reading {}", report.display()))?;
+    Ok(BuildTimings {
+        commit,
+        subject,
+        timestamp: chrono::Utc::now().timestamp(),
+        full,
+        ..parse_report(&html)?
+    })
+}
+
+/// The latest snapshot per commit, in the order commits were first measured
+fn per_commit(history: &[BuildTimings]) -> Vec<&BuildTimings> {
+    let mut order: Vec<&str> = Vec::new();
+    let mut latest: BTreeMap<&str, &BuildTimings> = BTreeMap::new();
+    for snapshot in history {
+        if latest.insert(&snapshot.commit, snapshot).is_none() {
+            order.push(&snapshot.commit);
+        }
+    }
+    order.into_iter().map(|commit| latest[commit]).collect()
+}
+
+/// Compare `current` with `previous`, `None` when nothing regressed past the threshold.
+pub fn compare(
+    previous: &BuildTimings,
+    current: &BuildTimings,
+    threshold: Threshold,
+) -> Option<CommitRegression> {
+    let total = threshold.exceeded(previous.total_secs, current.total_secs).map(|mut total| {
+        total.name = "total".to_string();
+        total
+    });
+    let mut crates: Vec<Regression> = current
+        .crates
+        .iter()
+        .filter_map(|(name, after)| {
+            let before = previous.crates.get(name)?;
+            let mut regression = threshold.exceeded(*before, *after)?;
+            regression.name = name.clone();
+            Some(regression)
+        })
+        .collect();
+    crates.sort_by(|a, b| b.delta_secs().total_cmp(&a.delta_secs()));
+    if total.is_none() && crates.is_empty() {
+        return None;
+    }
+    Some(CommitRegression {
+        commit: current.commit.clone(),
+        subject: current.subject.clone(),
+        previous_commit: previous.commit.clone(),
+        total,
+        crates,
+    })
+}
+
+/// Every commit in `history` that regressed against the commit measured before it, only
+/// comparing snapshots taken the same way (full or workspace-only builds).
+pub fn regressions(history: &[BuildTimings], threshold: Threshold) -> Vec<CommitRegression> {
+    let snapshots = per_commit(history);
+    let mut found = Vec::new();
+    for (i, current) in snapshots.iter().enumerate() {
+        let previous = snapshots[..i].iter().rev().find(|previous| previous.full == current.full);
+        if let Some(regression) =
+            previous.and_then(|previous| compare(previous, current, threshold))
+        {
+            found.push(regression);
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(commit: &str, total: f64, crates: &[(&str, f64)]) -> BuildTimings {
+        BuildTimings {
+            commit: commit.to_string(),
+            subject: format!("Commit {}", commit),
+            timestamp: 0,
+            full: false,
+            total_secs: total,
+            crates: crates.iter().map(|(name, secs)| (name.to_string(), *secs)).collect(),
+            frontend: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_finds_the_commit_and_crate_that_regressed() {
+        let html = r#"<script>
+const UNIT_DATA = [
+  {"i": 0, "name": "core-lib", "version": "0.1.0", "target": "", "start": 0.0, "duration": 2.0,
+   "sections": [["frontend", {"start": 0.0, "end": 1.5}], ["codegen", {"start": 1.5, "end": 2.0}]]},
+  {"i": 1, "name": "core-lib", "version": "0.1.0", "target": " build-script", "start": 0.0, "duration": 0.5},
+  {"i": 2, "name": "cli", "version": "0.1.0", "target": " cli \"bin\"", "start": 2.0, "duration": 3.0}
+];
+const CONCURRENCY_DATA = [];
+</script>"#;
+        let report = parse_report(html).unwrap();
+        assert_eq!(report.total_secs, 5.0);
+        assert_eq!(report.crates["core-lib"], 2.5);
+        assert_eq!(report.crates["cli"], 3.0);
+        assert_eq!(report.frontend["core-lib"], 1.5);
+
+        let threshold = Threshold { percent: 10.0, min_secs: 0.5 };
+        let history = [
+            snapshot("a", 10.0, &[("core-lib", 4.0), ("cli", 6.0)]),
+            // Small absolute growth is noise
+            snapshot("b", 10.3, &[("core-lib", 4.3), ("cli", 6.0)]),
+            snapshot("c", 14.0, &[("core-lib", 4.3), ("cli", 9.7)]),
+            // Re-measuring a commit replaces its earlier snapshot
+            snapshot("c", 13.0, &[("core-lib", 4.3), ("cli", 8.7)]),
+        ];
+        let found = regressions(&history, threshold);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].commit, "c");
+        assert_eq!(found[0].previous_commit, "b");
+        assert_eq!(found[0].total.as_ref().unwrap().after_secs, 13.0);
+        let crates: Vec<&str> = found[0].crates.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(crates, ["cli"]);
+        assert!((found[0].crates[0].percent() - 45.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,392 @@
+//! Workspace-wide dependency analysis
+//!
+//! [`analyze`] runs `cargo metadata` once for a whole workspace and produces a [`CrateAnalysis`]
+//! per member, metrics aggregated over the workspace, the dependencies the workspace resolves to
+//! more than one version (and which members pull in each), and the dependencies several members
+//! declare on their own that could be inherited from `[workspace.dependencies]` instead.
+
+use crate::features;
+use crate::{CrateAnalysis, CrateMetrics, DependencyInfo};
+use anyhow::{Context, Result};
+use cargo_metadata::semver::VersionReq;
+use cargo_metadata::{DependencyKind, Metadata, MetadataCommand, Package, PackageId};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorkspaceAnalysis {
+    pub root: String,
+    pub members: Vec<CrateAnalysis>,
+    /// Over the whole workspace; each dependency counts once however many members use it
+    pub metrics: CrateMetrics,
+    pub duplicate_versions: Vec<DuplicateDependency>,
+    pub shared_dependencies: Vec<SharedDependency>,
+}
+
+/// A dependency the workspace builds at more than one version
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateDependency {
+    pub name: String,
+    /// Each version with the members that pull it in
+    pub versions: BTreeMap<String, Vec<String>>,
+}
+
+/// A dependency declared by several members that belongs in `[workspace.dependencies]`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedDependency {
+    pub name: String,
+    /// Members declaring it themselves rather than with `workspace = true`
+    pub members: Vec<String>,
+    /// The requirements they declare
+    pub requirements: Vec<String>,
+    /// The `[workspace.dependencies]` entry to add
+    pub workspace_entry: String,
+}
+
+/// Analyze every member of the workspace containing `manifest_path`.
+pub fn analyze(manifest_path: &Path) -> Result<WorkspaceAnalysis> {
+    let metadata = MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .exec()
+        .with_context(|| format!("running cargo metadata on {}", manifest_path.display()))?;
+    let graph = Graph::new(&metadata)?;
+
+    let members: Vec<&Package> = metadata.workspace_packages();
+    let mut analyses = Vec::new();
+    for member in &members {
+        analyses.push(analyze_member(member, &graph));
+    }
+
+    let external: BTreeSet<&PackageId> =
+        graph.packages.keys().filter(|id| !graph.is_member(id)).copied().collect();
+    let metrics = CrateMetrics {
+        compile_time_ms: 0,
+        binary_size_kb: 0,
+        dependency_count: external.len(),
+        download_size_kb: external.iter().map(|id| download_size(graph.packages[*id])).sum::<u64>()
+            / 1024,
+    };
+
+    Ok(WorkspaceAnalysis {
+        root: metadata.workspace_root.to_string(),
+        members: analyses,
+        metrics,
+        duplicate_versions: duplicate_versions(&graph),
+        shared_dependencies: shared_dependencies(&members),
+    })
+}
+
+/// The resolved dependency graph, by package
+struct Graph<'a> {
+    packages: BTreeMap<&'a PackageId, &'a Package>,
+    dependencies: BTreeMap<&'a PackageId, Vec<&'a PackageId>>,
+    /// The packages each member depends on directly, by the name it uses for them
+    direct: BTreeMap<&'a PackageId, BTreeMap<String, &'a PackageId>>,
+    members: &'a [PackageId],
+}
+
+impl<'a> Graph<'a> {
+    fn new(metadata: &'a Metadata) -> Result<Self> {
+        let resolve =
+            metadata.resolve.as_ref().context("cargo metadata did not resolve the graph")?;
+        let packages = metadata.packages.iter().map(|package| (&package.id, package)).collect();
+        let dependencies = resolve
+            .nodes
+            .iter()
+            .map(|node| (&node.id, node.dependencies.iter().collect()))
+            .collect();
+        let direct = resolve
+            .nodes
+            .iter()
+            .map(|node| {
+                (&node.id, node.deps.iter().map(|dep| (dep.name.clone(), &dep.pkg)).collect())
+            })
+            .collect();
+        Ok(Self { packages, dependencies, direct, members: &metadata.workspace_members })
+    }
+
+    fn is_member(&self, id: &PackageId) -> bool {
+        self.members.contains(id)
+    }
+
+    /// Every package `from` depends on, directly or not
+    fn closure(&self, from: &'a PackageId) -> BTreeSet<&'a PackageId> {
+        let mut reached = BTreeSet::new();
+        let mut queue = VecDeque::from([from]);
+        while let Some(id) = queue.pop_front() {
+            for next in self.dependencies.get(id).into_iter().flatten() {
+                if reached.insert(*next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+        reached
+    }
+}
+
+/// Size of the downloaded `.crate` archive, when the registry cache has it
+fn download_size(package: &Package) -> u64 {
+    let Some(cargo_home) = std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cargo")))
+    else {
+        return 0;
+    };
+    let Ok(registries) = std::fs::read_dir(cargo_home.join("registry").join("cache")) else {
+        return 0;
+    };
+    let archive = format!("{}-{}.crate", package.name, package.version);
+    registries
+        .flatten()
+        .find_map(|registry| std::fs::metadata(registry.path().join(&archive)).ok())
+        .map_or(0, |metadata| metadata.len())
+}
+
+fn analyze_member(member: &Package, graph: &Graph) -> CrateAnalysis {
+    let sources = member
+        .manifest_path
+        .parent()
+        .map(|dir| features::rust_files(dir.as_std_path()))
+        .unwrap_or_default();
+    let direct = graph.direct.get(&member.id).cloned().unwrap_or_default();
+
+    let mut dependencies = Vec::new();
+    for declared in member.dependencies.iter().filter(|d| d.kind == DependencyKind::Normal) {
+        let lib_name = declared.rename.clone().unwrap_or_else(|| declared.name.clone());
+        let lib_name = lib_name.replace('-', "_");
+        // Optional dependencies nothing enables are not in the graph
+        let Some(resolved) = direct.get(&lib_name).map(|id| graph.packages[id]) else { continue };
+        dependencies.push(DependencyInfo {
+            name: declared.name.clone(),
+            version: resolved.version.to_string(),
+            used: !features::used_identifiers(&sources, &lib_name).is_empty(),
+            deprecated: false,
+            alternative: None,
+            license: resolved.license.clone(),
+        });
+    }
+
+    let closure: Vec<&PackageId> =
+        graph.closure(&member.id).into_iter().filter(|id| !graph.is_member(id)).collect();
+    CrateAnalysis {
+        name: member.name.clone(),
+        version: member.version.to_string(),
+        unused_dependencies: dependencies
+            .iter()
+            .filter(|dependency| !dependency.used)
+            .map(|dependency| dependency.name.clone())
+            .collect(),
+        dependencies,
+        outdated_dependencies: Vec::new(),
+        security_vulnerabilities: Vec::new(),
+        performance_metrics: CrateMetrics {
+            compile_time_ms: 0,
+            binary_size_kb: 0,
+            dependency_count: closure.len(),
+            download_size_kb: closure
+                .iter()
+                .map(|id| download_size(graph.packages[*id]))
+                .sum::<u64>()
+                / 1024,
+        },
+        bloat: None,
+    }
+}
+
+fn duplicate_versions(graph: &Graph) -> Vec<DuplicateDependency> {
+    let closures: Vec<(&str, BTreeSet<&PackageId>)> = graph
+        .members
+        .iter()
+        .map(|id| (graph.packages[id].name.as_str(), graph.closure(id)))
+        .collect();
+    let mut by_name: BTreeMap<&str, Vec<&Package>> = BTreeMap::new();
+    for (id, package) in &graph.packages {
+        if !graph.is_member(id) {
+            by_name.entry(&package.name).or_default().push(package);
+        }
+    }
+    by_name
+        .into_iter()
+        .filter(|(_, packages)| packages.len() > 1)
+        .map(|(name, packages)| DuplicateDependency {
+            name: name.to_string(),
+            versions: packages
+                .iter()
+                .map(|package| {
+                    let users = closures
+                        .iter()
+                        .filter(|(_, closure)| closure.contains(&package.id))
+                        .map(|(member, _)| member.to_string())
+                        .collect();
+                    (package.version.to_string(), users)
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Names of the dependencies a manifest inherits with `workspace = true`
+fn inherited(manifest_path: &Path) -> BTreeSet<String> {
+    let Ok(manifest) = std::fs::read_to_string(manifest_path) else { return BTreeSet::new() };
+    let Ok(manifest) = toml::from_str::<toml::Table>(&manifest) else { return BTreeSet::new() };
+    // The manifest itself and each `[target.'cfg(..)']` table hold dependency tables
+    let targets = manifest.get("target").and_then(toml::Value::as_table);
+    let owners = std::iter::once(&manifest)
+        .chain(targets.into_iter().flat_map(|t| t.values().filter_map(toml::Value::as_table)));
+    let tables = owners.flat_map(|owner| {
+        ["dependencies", "dev-dependencies", "build-dependencies"]
+            .into_iter()
+            .filter_map(|kind| owner.get(kind).and_then(toml::Value::as_table))
+    });
+    tables
+        .flat_map(|table| table.iter())
+        .filter(|(_, entry)| entry.get("workspace").and_then(toml::Value::as_bool) == Some(true))
+        .map(|(name, _)| name.clone())
+        .collect()
+}
+
+/// The minimum version a requirement accepts, for picking the strictest of several
+fn minimum(requirement: &VersionReq) -> (u64, u64, u64) {
+    requirement
+        .comparators
+        .first()
+        .map_or((0, 0, 0), |c| (c.major, c.minor.unwrap_or(0), c.patch.unwrap_or(0)))
+}
+
+fn shared_dependencies(members: &[&Package]) -> Vec<SharedDependency> {
+    struct Declaration<'a> {
+        member: &'a str,
+        requirement: &'a VersionReq,
+        features: BTreeSet<&'a String>,
+        default_features: bool,
+    }
+    let mut declarations: BTreeMap<&str, Vec<Declaration>> = BTreeMap::new();
+    for member in members {
+        let inherited = inherited(member.manifest_path.as_std_path());
+        // One declaration per member, whichever kind it is
+        let mut seen = BTreeSet::new();
+        for declared in &member.dependencies {
+            if declared.path.is_some()
+                || inherited.contains(&declared.name)
+                || !seen.insert(&declared.name)
+            {
+                continue;
+            }
+            declarations.entry(&declared.name).or_default().push(Declaration {
+                member: &member.name,
+                requirement: &declared.req,
+                features: declared.features.iter().collect(),
+                default_features: declared.uses_default_features,
+            });
+        }
+    }
+
+    declarations
+        .into_iter()
+        .filter(|(_, declarations)| declarations.len() > 1)
+        .map(|(name, declarations)| {
+            let strictest = declarations
+                .iter()
+                .map(|declaration| declaration.requirement)
+                .max_by_key(|requirement| minimum(requirement))
+                .map(|requirement| requirement.to_string().trim_start_matches('^').to_string())
+                .unwrap_or_else(|| "*".to_string());
+            // Features every member asks for go to the workspace, the rest stay with members
+            let common = declarations
+                .iter()
+                .map(|declaration| declaration.features.clone())
+                .reduce(|common, features| &common & &features)
+                .unwrap_or_default();
+            let default_features = declarations.iter().any(|d| d.default_features);
+            let mut requirements: Vec<String> =
+                declarations.iter().map(|d| d.requirement.to_string()).collect();
+            requirements.sort();
+            requirements.dedup();
+            SharedDependency {
+                name: name.to_string(),
+                members: declarations.iter().map(|d| d.member.to_string()).collect(),
+                requirements,
+                workspace_entry: workspace_entry(name, &strictest, &common, default_features),
+            }
+        })
+        .collect()
+}
+
+fn workspace_entry(
+    name: &str,
+    requirement: &str,
+    features: &BTreeSet<&String>,
+    default_features: bool,
+) -> String {
+    if features.is_empty() && default_features {
+        return format!("{} = \"{}\"", name, requirement);
+    }
+    let mut entry = format!("{} = {{ version = \"{}\"", name, requirement);
+    if !default_features {
+        entry.push_str(", default-features = false");
+    }
+    if !features.is_empty() {
+        let features: Vec<String> = features.iter().map(|f| format!("\"{}\"", f)).collect();
+        entry.push_str(&format!(", features = [{}]", features.join(", ")));
+    }
+    entry.push_str(" }");
+    entry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyzes_a_workspace() {
+        let dir =
+            std::env::temp_dir().join(format!("parflow-workspace-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let member = |name: &str, dependencies: &str, source: &str| {
+            std::fs::create_dir_all(dir.join(name).join("src")).unwrap();
+            std::fs::write(
+                dir.join(name).join("Cargo.toml"),
+                format!(
+                    "[package]\nname = \"{}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n\
+                     [dependencies]\n{}",
+                    name, dependencies
+                ),
+            )
+            .unwrap();
+            std::fs::write(dir.join(name).join("src/lib.rs"), source).unwrap();
+        };
+        std::fs::write(
+            dir.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"a\", \"b\", \"c\"]\nresolver = \"2\"\n\n\
+             [workspace.dependencies]\nb = { path = \"b\" }\n",
+        )
+        .unwrap();
+        member("a", "b = { workspace = true }\n", "pub use b::*;\n");
+        member("b", "c = { path = \"../c\" }\n", "pub fn b() {}\n");
+        member("c", "", "pub fn c() {}\n");
+
+        let analysis = analyze(&dir.join("Cargo.toml")).unwrap();
+        let names: Vec<&str> = analysis.members.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, ["a", "b", "c"]);
+        let a = &analysis.members[0];
+        assert_eq!(a.dependencies.len(), 1);
+        assert!(a.unused_dependencies.is_empty());
+        // b never mentions c
+        assert_eq!(analysis.members[1].unused_dependencies, ["c"]);
+        assert_eq!(analysis.metrics.dependency_count, 0);
+        assert!(analysis.duplicate_versions.is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let requirement = |text: &str| VersionReq::parse(text).unwrap();
+        assert!(minimum(&requirement("1.35")) > minimum(&requirement("1")));
+        let features: BTreeSet<&String> = BTreeSet::new();
+        assert_eq!(workspace_entry("tokio", "1.35", &features, true), "tokio = \"1.35\"");
+        let full = "full".to_string();
+        assert_eq!(
+            workspace_entry("tokio", "1", &BTreeSet::from([&full]), false),
+            "tokio = { version = \"1\", default-features = false, features = [\"full\"] }"
+        );
+    }
+}
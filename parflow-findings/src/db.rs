@@ -0,0 +1,45 @@
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Append-only store of analysis snapshots, one JSON Lines file per kind of finding.
+pub struct FindingsDb {
+    dir: PathBuf,
+}
+
+impl FindingsDb {
+    pub const DEFAULT_DIR: &'static str = ".parflow/findings";
+
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("creating findings database at {}", dir.display()))?;
+        Ok(Self { dir })
+    }
+
+    fn table(&self, kind: &str) -> PathBuf {
+        self.dir.join(format!("{}.jsonl", kind))
+    }
+
+    pub fn record<T: Serialize>(&self, kind: &str, snapshot: &T) -> Result<()> {
+        let mut file =
+            std::fs::OpenOptions::new().create(true).append(true).open(self.table(kind))?;
+        writeln!(file, "{}", serde_json::to_string(snapshot)?)?;
+        Ok(())
+    }
+
+    /// All snapshots of `kind`, oldest first. Lines that fail to parse (e.g. from an older
+    /// schema) are skipped rather than failing the whole history.
+    pub fn history<T: DeserializeOwned>(&self, kind: &str) -> Result<Vec<T>> {
+        let path = self.table(kind);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        Ok(std::fs::read_to_string(path)?
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+}
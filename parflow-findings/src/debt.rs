@@ -0,0 +1,239 @@
+//! Technical-debt scoring per module.
+//!
+//! Each module (a source directory, per language) gets three signals normalized to `0..=1` —
+//! complexity, dead code as [`semantic_compiler::dead_code`] finds it and the test coverage gap —
+//! combined into a `0..=100` score with configurable weights.
+
+use anyhow::{Context, Result};
+use parflow_transpiler::CodeTranspiler;
+use semantic_compiler::dead_code::DeadCodeAnalyzer;
+use semantic_compiler::ownership::language_for_path;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+pub const TABLE: &str = "debt";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DebtWeights {
+    pub complexity: f64,
+    pub dead_code: f64,
+    pub coverage_gap: f64,
+}
+
+impl Default for DebtWeights {
+    fn default() -> Self {
+        Self { complexity: 0.4, dead_code: 0.2, coverage_gap: 0.4 }
+    }
+}
+
+impl DebtWeights {
+    pub fn from_toml_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("reading weights from {}", path.display()))?;
+        Ok(toml::from_str(&text)?)
+    }
+}
+
+/// Raw measurements for one module before normalization.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct DebtSignals {
+    pub code_lines: usize,
+    pub complexity: f64,
+    /// Private functions no entry point reaches
+    pub dead_code_items: usize,
+    /// Line coverage in `0..=1`, when a coverage report was provided
+    pub coverage: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModuleDebt {
+    pub module: String,
+    pub language: String,
+    pub signals: DebtSignals,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DebtSnapshot {
+    pub timestamp: i64,
+    pub commit: Option<String>,
+    /// Line-weighted mean of the module scores
+    pub overall: f64,
+    pub modules: Vec<ModuleDebt>,
+}
+
+pub fn score(signals: &DebtSignals, weights: &DebtWeights) -> f64 {
+    let per_100_lines = |count: f64| count * 100.0 / signals.code_lines.max(1) as f64;
+    let components = [
+        (weights.complexity, (per_100_lines(signals.complexity) / 20.0).min(1.0)),
+        (weights.dead_code, (signals.dead_code_items as f64 / 10.0).min(1.0)),
+        // Unknown coverage counts as half covered rather than hiding the gap
+        (weights.coverage_gap, 1.0 - signals.coverage.unwrap_or(0.5).clamp(0.0, 1.0)),
+    ];
+
+    let total_weight: f64 = components.iter().map(|(w, _)| w).sum();
+    if total_weight <= 0.0 {
+        return 0.0;
+    }
+    100.0 * components.iter().map(|(w, s)| w * s).sum::<f64>() / total_weight
+}
+
+fn module_of(path: &Path, root: &Path) -> String {
+    path.parent()
+        .and_then(|p| p.strip_prefix(root).ok())
+        .map(|p| p.to_string_lossy().into_owned())
+        .filter(|p| !p.is_empty())
+        .unwrap_or_else(|| ".".to_string())
+}
+
+fn collect_sources(dir: &Path, out: &mut Vec<std::path::PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        if path.is_dir() {
+            if !name.starts_with('.') && name != "target" && name != "node_modules" {
+                collect_sources(&path, out)?;
+            }
+        } else if language_for_path(&name).is_some() {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Parse an lcov report into per-module (covered, found) line counts.
+pub fn parse_lcov(lcov: &str, root: &Path) -> HashMap<String, (usize, usize)> {
+    let mut modules: HashMap<String, (usize, usize)> = HashMap::new();
+    let mut current = None;
+    for line in lcov.lines() {
+        if let Some(file) = line.strip_prefix("SF:") {
+            let path = Path::new(file);
+            let path = if path.is_absolute() { path.to_path_buf() } else { root.join(path) };
+            current = Some(module_of(&path, root));
+        } else if let (Some(module), Some(hit)) = (&current, line.strip_prefix("DA:")) {
+            let entry = modules.entry(module.clone()).or_default();
+            entry.1 += 1;
+            if hit.split(',').nth(1).is_some_and(|count| count.trim() != "0") {
+                entry.0 += 1;
+            }
+        }
+    }
+    modules
+}
+
+/// Measure every module under `root` and score it.
+pub fn analyze(
+    root: &Path,
+    weights: &DebtWeights,
+    coverage: Option<&HashMap<String, (usize, usize)>>,
+) -> Result<DebtSnapshot> {
+    let mut files = Vec::new();
+    collect_sources(root, &mut files)?;
+
+    let mut modules: BTreeMap<(String, String), DebtSignals> = BTreeMap::new();
+    for file in files {
+        let Ok(code) = std::fs::read_to_string(&file) else { continue };
+        let language = language_for_path(&file.to_string_lossy()).unwrap_or("unknown");
+        let metrics = CodeTranspiler::analyze_code_complexity(&code, language);
+        let signals = modules.entry((module_of(&file, root), language.to_string())).or_default();
+
+        signals.code_lines += metrics.get("code_lines").copied().unwrap_or(0.0) as usize;
+        signals.complexity += metrics.get("complexity_score").copied().unwrap_or(0.0);
+    }
+    // Exports may have users outside the project
+    let dead_code = DeadCodeAnalyzer::from_directory(root)?.analyze().functions;
+    for dead in dead_code.into_iter().filter(|dead| !dead.exported) {
+        let module = module_of(&root.join(&dead.file), root);
+        if let Some(signals) = modules.get_mut(&(module, dead.language)) {
+            signals.dead_code_items += 1;
+        }
+    }
+
+    let mut total_lines = 0;
+    let mut weighted = 0.0;
+    let modules: Vec<ModuleDebt> = modules
+        .into_iter()
+        .map(|((module, language), mut signals)| {
+            signals.coverage = coverage
+                .and_then(|c| c.get(&module))
+                .and_then(|(hit, found)| (*found > 0).then(|| *hit as f64 / *found as f64));
+            let score = score(&signals, weights);
+            total_lines += signals.code_lines;
+            weighted += score * signals.code_lines as f64;
+            ModuleDebt { module, language, signals, score }
+        })
+        .collect();
+
+    let commit = std::process::Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string());
+
+    Ok(DebtSnapshot {
+        timestamp: chrono::Utc::now().timestamp(),
+        commit,
+        overall: if total_lines == 0 { 0.0 } else { weighted / total_lines as f64 },
+        modules,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FindingsDb;
+
+    #[test]
+    fn test_score_weights_and_history() {
+        let clean = DebtSignals { code_lines: 100, coverage: Some(1.0), ..Default::default() };
+        let messy = DebtSignals {
+            code_lines: 100,
+            complexity: 40.0,
+            dead_code_items: 10,
+            coverage: Some(0.0),
+        };
+        let weights = DebtWeights::default();
+        assert_eq!(score(&clean, &weights), 0.0);
+        assert!((score(&messy, &weights) - 100.0).abs() < 1e-9);
+
+        let coverage_only = DebtWeights { complexity: 0.0, dead_code: 0.0, coverage_gap: 1.0 };
+        assert_eq!(score(&DebtSignals { coverage: Some(0.25), ..clean }, &coverage_only), 75.0);
+
+        let dir = std::env::temp_dir().join(format!("parflow-findings-{}", std::process::id()));
+        let db = FindingsDb::open(&dir).unwrap();
+        for overall in [40.0, 35.0] {
+            let snapshot = DebtSnapshot { timestamp: 0, commit: None, overall, modules: vec![] };
+            db.record(TABLE, &snapshot).unwrap();
+        }
+        let history: Vec<DebtSnapshot> = db.history(TABLE).unwrap();
+        assert_eq!(history.iter().map(|s| s.overall).collect::<Vec<_>>(), vec![40.0, 35.0]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_dead_code_is_counted_per_module() {
+        let root = std::env::temp_dir().join(format!("parflow-debt-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("app")).unwrap();
+        std::fs::write(
+            root.join("app/main.py"),
+            "def _unused():\n    return 1\n\ndef main():\n    print('hi')\n\nmain()\n",
+        )
+        .unwrap();
+        // Suppressions are not dead code by themselves, and exports may be used elsewhere
+        std::fs::write(root.join("lib.rs"), "#[allow(dead_code)]\npub fn api() {}\n").unwrap();
+
+        let snapshot = analyze(&root, &DebtWeights::default(), None).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+        let dead: Vec<(&str, usize)> = snapshot
+            .modules
+            .iter()
+            .map(|m| (m.module.as_str(), m.signals.dead_code_items))
+            .collect();
+        assert_eq!(dead, [(".", 0), ("app", 1)]);
+    }
+}
@@ -0,0 +1,13 @@
+//! Findings storage and reporting for ParFlow analyses
+//!
+//! Analyses write snapshots to a findings database under `.parflow/findings` so results can be
+//! compared over time; `report` renders that history as a standalone HTML page.
+
+pub mod db;
+pub mod debt;
+pub mod links;
+pub mod report;
+
+pub use db::FindingsDb;
+pub use debt::{DebtSnapshot, DebtWeights, ModuleDebt};
+pub use links::{DeepLink, Location};
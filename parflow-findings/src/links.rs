@@ -0,0 +1,130 @@
+//! `parflow://` deep links to code locations.
+//!
+//! * `parflow://open?file=src/lib.rs&line=42&col=5` opens a file locally
+//! * `parflow://session/<id>?file=src/lib.rs&line=42` opens the file inside a live session
+//!
+//! Links are editor-agnostic: `parflow open <url>` resolves them and hands the location to the
+//! configured editor, so reports, PR comments and chat messages all use the same format.
+
+use anyhow::{bail, Context, Result};
+use std::fmt;
+use url::Url;
+
+pub const SCHEME: &str = "parflow";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub file: String,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+}
+
+impl Location {
+    pub fn new(file: impl Into<String>, line: Option<u32>) -> Self {
+        Self { file: file.into(), line, column: None }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeepLink {
+    Local(Location),
+    Session { session: String, location: Location },
+}
+
+impl DeepLink {
+    pub fn location(&self) -> &Location {
+        match self {
+            DeepLink::Local(location) | DeepLink::Session { location, .. } => location,
+        }
+    }
+
+    pub fn to_url(&self) -> String {
+        let (base, location) = match self {
+            DeepLink::Local(location) => (format!("{}://open", SCHEME), location),
+            DeepLink::Session { session, location } => {
+                (format!("{}://session/{}", SCHEME, session), location)
+            }
+        };
+        let mut url = Url::parse(&base).expect("static deep link base is valid");
+        {
+            let mut query = url.query_pairs_mut();
+            query.append_pair("file", &location.file);
+            if let Some(line) = location.line {
+                query.append_pair("line", &line.to_string());
+            }
+            if let Some(column) = location.column {
+                query.append_pair("col", &column.to_string());
+            }
+        }
+        url.into()
+    }
+
+    pub fn parse(link: &str) -> Result<Self> {
+        let url = Url::parse(link).with_context(|| format!("invalid deep link {}", link))?;
+        if url.scheme() != SCHEME {
+            bail!("expected a {}:// link, got {}://", SCHEME, url.scheme());
+        }
+
+        let mut location = Location { file: String::new(), line: None, column: None };
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "file" => location.file = value.into_owned(),
+                "line" => location.line = Some(value.parse().context("invalid line")?),
+                "col" => location.column = Some(value.parse().context("invalid column")?),
+                _ => {}
+            }
+        }
+        if location.file.is_empty() {
+            bail!("deep link has no file");
+        }
+
+        match url.host_str() {
+            Some("open") => Ok(DeepLink::Local(location)),
+            Some("session") => {
+                let session = url.path().trim_matches('/');
+                if session.is_empty() {
+                    bail!("session link has no session id");
+                }
+                Ok(DeepLink::Session { session: session.to_string(), location })
+            }
+            other => bail!("unknown deep link target {:?}", other.unwrap_or("")),
+        }
+    }
+
+    /// `[label](url)`, for PR comments and chat notifications.
+    pub fn markdown(&self, label: &str) -> String {
+        format!("[{}]({})", label.replace(']', "\\]"), self.to_url())
+    }
+}
+
+impl fmt::Display for DeepLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_url())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let local = DeepLink::Local(Location {
+            file: "/work/my project/src/lib.rs".to_string(),
+            line: Some(42),
+            column: Some(7),
+        });
+        let url = local.to_url();
+        assert!(url.starts_with("parflow://open?file=%2Fwork%2Fmy+project"));
+        assert_eq!(DeepLink::parse(&url).unwrap(), local);
+
+        let session = DeepLink::Session {
+            session: "abc-123".to_string(),
+            location: Location::new("src/main.rs", Some(3)),
+        };
+        assert_eq!(DeepLink::parse(&session.to_url()).unwrap(), session);
+
+        assert!(DeepLink::parse("https://open?file=x").is_err());
+        assert!(DeepLink::parse("parflow://open?line=3").is_err());
+    }
+}
@@ -0,0 +1,103 @@
+//! Standalone HTML report for the findings database.
+
+use crate::debt::DebtSnapshot;
+use crate::links::{DeepLink, Location};
+use std::fmt::Write;
+use std::path::Path;
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Deep link opening `module` (relative to the analyzed `root`) in the local editor.
+pub fn module_link(root: &Path, module: &str) -> DeepLink {
+    let path = std::fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf()).join(module);
+    DeepLink::Local(Location::new(path.to_string_lossy(), None))
+}
+
+/// Inline SVG line chart of the overall debt score across snapshots.
+fn trend_chart(history: &[DebtSnapshot]) -> String {
+    const WIDTH: f64 = 640.0;
+    const HEIGHT: f64 = 200.0;
+
+    let step = WIDTH / (history.len().max(2) - 1) as f64;
+    let points: Vec<String> = history
+        .iter()
+        .enumerate()
+        .map(|(i, s)| format!("{:.1},{:.1}", i as f64 * step, HEIGHT - s.overall / 100.0 * HEIGHT))
+        .collect();
+
+    format!(
+        r##"<svg viewBox="-10 -10 {w} {h}" width="{w}" height="{h}" role="img" aria-label="Debt score trend">
+<rect x="0" y="0" width="{iw}" height="{ih}" fill="#fafafa" stroke="#ddd"/>
+<polyline fill="none" stroke="#c0392b" stroke-width="2" points="{points}"/>
+</svg>"##,
+        w = WIDTH + 20.0,
+        h = HEIGHT + 20.0,
+        iw = WIDTH,
+        ih = HEIGHT,
+        points = points.join(" "),
+    )
+}
+
+/// Render the debt history of the project at `root`; module names link into the editor.
+pub fn render_html(history: &[DebtSnapshot], root: &Path) -> String {
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>ParFlow findings</title>\n\
+         <style>body{font-family:sans-serif;margin:2em}table{border-collapse:collapse}\
+         td,th{border:1px solid #ddd;padding:4px 8px;text-align:right}td:first-child{text-align:left}</style>\n\
+         </head><body>\n<h1>Technical debt</h1>\n",
+    );
+
+    let Some(latest) = history.last() else {
+        html.push_str("<p>No snapshots recorded yet.</p>\n</body></html>\n");
+        return html;
+    };
+
+    let trend = match history.len() {
+        1 => String::new(),
+        n => {
+            let delta = latest.overall - history[n - 2].overall;
+            format!(" ({}{:.1} since previous)", if delta >= 0.0 { "+" } else { "" }, delta)
+        }
+    };
+    let _ = writeln!(html, "<p>Overall score: <strong>{:.1}</strong>{}</p>", latest.overall, trend);
+    let _ = writeln!(html, "<h2>Trend</h2>\n{}", trend_chart(history));
+
+    html.push_str(
+        "<h2>Modules</h2>\n<table><tr><th>Module</th><th>Language</th><th>Score</th><th>Lines</th>\
+         <th>Complexity</th><th>Dead code</th><th>Coverage</th></tr>\n",
+    );
+    let mut modules: Vec<_> = latest.modules.iter().collect();
+    modules.sort_by(|a, b| b.score.total_cmp(&a.score));
+    for module in modules {
+        let s = &module.signals;
+        let _ = writeln!(
+            html,
+            "<tr><td><a href=\"{}\">{}</a></td><td>{}</td><td>{:.1}</td><td>{}</td><td>{:.0}</td><td>{}</td><td>{}</td></tr>",
+            escape(&module_link(root, &module.module).to_url()),
+            escape(&module.module),
+            module.language,
+            module.score,
+            s.code_lines,
+            s.complexity,
+            s.dead_code_items,
+            s.coverage.map(|c| format!("{:.0}%", c * 100.0)).unwrap_or_else(|| "n/a".to_string()),
+        );
+    }
+    html.push_str("</table>\n</body></html>\n");
+    html
+}
+
+/// Markdown summary of the latest snapshot, for PR comments and chat notifications.
+pub fn render_markdown(snapshot: &DebtSnapshot, root: &Path, limit: usize) -> String {
+    let mut md = format!("### Technical debt: {:.1}/100\n\n", snapshot.overall);
+    md.push_str("| Module | Language | Score |\n|---|---|---|\n");
+    let mut modules: Vec<_> = snapshot.modules.iter().collect();
+    modules.sort_by(|a, b| b.score.total_cmp(&a.score));
+    for module in modules.into_iter().take(limit) {
+        let link = module_link(root, &module.module).markdown(&module.module);
+        let _ = writeln!(md, "| {} | {} | {:.1} |", link, module.language, module.score);
+    }
+    md
+}
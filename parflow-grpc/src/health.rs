@@ -0,0 +1,96 @@
+use crate::workflows::WorkflowRegistry;
+use parflow_orchestrator::CronScheduler;
+use std::future::Future;
+use std::time::Duration;
+use tonic_health::server::HealthReporter;
+use tonic_health::ServingStatus;
+
+/// Health check service names reported alongside the overall (`""`) server status.
+///
+/// Live sessions have no entry: this server does not host them, and their hosts, `parflow
+/// live-start` and `parflow start --live`, keep them in process without a listener to probe. The
+/// session registry only tells whether a host process still exists, which `parflow dashboard`
+/// already shows.
+pub const SUBSYSTEMS: [&str; 3] = ["parflow.core", "parflow.orchestrator", "parflow.scheduler"];
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+async fn probe<F: Future<Output = bool>>(check: F) -> ServingStatus {
+    match tokio::time::timeout(PROBE_TIMEOUT, check).await {
+        Ok(true) => ServingStatus::Serving,
+        _ => ServingStatus::NotServing,
+    }
+}
+
+/// Whether the core runs the `Run` RPC's parallel tasks to completion, in a task of its own so
+/// a panic counts as a failure instead of stopping the probes
+async fn core_runs_tasks() -> bool {
+    let expected: Vec<i32> = parflow_core::EXAMPLE_TASKS.iter().map(|&(_, value)| value).collect();
+    tokio::spawn(parflow_core::run_example_par()).await.is_ok_and(|results| results == expected)
+}
+
+/// Probe the state the server's requests go through: the core's task runtime, and the workflow
+/// runs and schedules shared with the handlers.
+async fn check_subsystems(
+    workflows: &WorkflowRegistry,
+    schedules: &CronScheduler,
+) -> [ServingStatus; 3] {
+    let core = probe(core_runs_tasks()).await;
+    let orchestrator = probe(async { workflows.is_healthy() }).await;
+    let scheduler = probe(async { schedules.is_healthy() }).await;
+
+    [core, orchestrator, scheduler]
+}
+
+fn overall(statuses: &[ServingStatus]) -> ServingStatus {
+    if statuses.iter().all(|s| *s == ServingStatus::Serving) {
+        ServingStatus::Serving
+    } else {
+        ServingStatus::NotServing
+    }
+}
+
+/// Re-run subsystem probes every `interval` and publish the results. The overall status and
+/// the `parflow.Orchestrator` service are only `SERVING` while every subsystem is.
+pub fn spawn_probes(
+    mut reporter: HealthReporter,
+    workflows: WorkflowRegistry,
+    schedules: CronScheduler,
+    interval: Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let statuses = check_subsystems(&workflows, &schedules).await;
+            for (name, status) in SUBSYSTEMS.iter().zip(statuses) {
+                reporter.set_service_status(*name, status).await;
+            }
+
+            let overall = overall(&statuses);
+            reporter.set_service_status("", overall).await;
+            reporter.set_service_status("parflow.Orchestrator", overall).await;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_failing_or_hanging_probes_are_not_serving() {
+        let dir = std::env::temp_dir().join(format!("parflow-grpc-health-{}", std::process::id()));
+        let schedules = CronScheduler::open(&dir).unwrap();
+        let statuses = check_subsystems(&WorkflowRegistry::default(), &schedules).await;
+        assert_eq!(statuses, [ServingStatus::Serving; 3]);
+        assert_eq!(overall(&statuses), ServingStatus::Serving);
+
+        assert_eq!(probe(async { false }).await, ServingStatus::NotServing);
+        assert_eq!(probe(std::future::pending()).await, ServingStatus::NotServing);
+        let statuses = [ServingStatus::Serving, ServingStatus::NotServing, ServingStatus::Serving];
+        assert_eq!(overall(&statuses), ServingStatus::NotServing);
+        drop(schedules);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,13 @@
+//! Generated protocol types and client for the ParFlow gRPC service
+
+pub mod proto {
+    pub mod parflow {
+        tonic::include_proto!("parflow");
+
+        /// Encoded descriptors for parflow.proto, served through gRPC reflection.
+        pub const FILE_DESCRIPTOR_SET: &[u8] =
+            include_bytes!(concat!(env!("OUT_DIR"), "/parflow_descriptor.bin"));
+    }
+}
+
+pub use proto::parflow::orchestrator_client::OrchestratorClient;
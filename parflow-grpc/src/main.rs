@@ -1,17 +1,49 @@
-use tonic::transport::Server;
+use parflow_core::config::ParflowConfig;
+use parflow_core::logging::LogOptions;
+use parflow_core::tls::TlsSettings;
+use std::time::Duration;
+use tonic::transport::{Certificate, Identity, Server, ServerTlsConfig};
 use tonic::{Request, Response, Status};
 
-// Import the generated proto code
-mod proto {
-    pub mod parflow {
-        tonic::include_proto!("parflow");
-    }
-}
+use tokio_stream::wrappers::ReceiverStream;
+
+// Generated proto code lives in the library so clients can share it
+use parflow_grpc::proto;
+mod health;
+mod schedules;
+mod workflows;
+
+use parflow_orchestrator::CronScheduler;
 use proto::parflow::orchestrator_server::{Orchestrator, OrchestratorServer};
-use proto::parflow::{OrchestratorRequest, OrchestratorResponse};
+use proto::parflow::{
+    AnalyzeCrateRequest, AnalyzeCrateResponse, CreateScheduleRequest, DeleteScheduleRequest,
+    DeleteScheduleResponse, Dependency, GetScheduleRequest, LanguageMetrics, ListSchedulesRequest,
+    ListSchedulesResponse, OrchestratorRequest, OrchestratorResponse, RunBenchmarkRequest,
+    RunBenchmarkResponse, Schedule, StreamWorkflowEventsRequest, SubmitWorkflowRequest,
+    SubmitWorkflowResponse, TranspileRequest, TranspileResponse, UpdateScheduleRequest,
+    WorkflowEvent,
+};
+use workflows::WorkflowRegistry;
 
-#[derive(Default)]
-pub struct MyOrchestrator {}
+pub struct MyOrchestrator {
+    workflows: WorkflowRegistry,
+    schedules: CronScheduler,
+    /// Whether clients prove who they are with certificates. Scheduled workflows run with the
+    /// server's permissions, so schedules are read-only otherwise.
+    authenticated: bool,
+}
+
+impl MyOrchestrator {
+    /// Why the schedules cannot be changed, if they cannot
+    fn schedules_read_only(&self) -> Option<Status> {
+        (!self.authenticated).then(|| {
+            Status::permission_denied(format!(
+                "schedules are read-only without client certificates, set {}",
+                parflow_core::tls::CLIENT_CA_ENV
+            ))
+        })
+    }
+}
 
 #[tonic::async_trait]
 impl Orchestrator for MyOrchestrator {
@@ -24,13 +56,238 @@ impl Orchestrator for MyOrchestrator {
         let reply = OrchestratorResponse { results };
         Ok(Response::new(reply))
     }
+
+    async fn submit_workflow(
+        &self,
+        request: Request<SubmitWorkflowRequest>,
+    ) -> Result<Response<SubmitWorkflowResponse>, Status> {
+        let request = request.into_inner();
+        if request.tasks.is_empty() {
+            return Err(Status::invalid_argument("workflow has no tasks"));
+        }
+
+        let workflow_id = self.workflows.submit(request.name, request.tasks, request.concurrent);
+        Ok(Response::new(SubmitWorkflowResponse { workflow_id }))
+    }
+
+    type StreamWorkflowEventsStream = ReceiverStream<Result<WorkflowEvent, Status>>;
+
+    async fn stream_workflow_events(
+        &self,
+        request: Request<StreamWorkflowEventsRequest>,
+    ) -> Result<Response<Self::StreamWorkflowEventsStream>, Status> {
+        let workflow_id = request.into_inner().workflow_id;
+        if workflow_id.is_empty() {
+            return Ok(Response::new(ReceiverStream::new(self.workflows.subscribe_all())));
+        }
+        let events = self
+            .workflows
+            .subscribe(&workflow_id)
+            .ok_or_else(|| Status::not_found(format!("unknown workflow {}", workflow_id)))?;
+        Ok(Response::new(ReceiverStream::new(events)))
+    }
+
+    async fn run_benchmark(
+        &self,
+        request: Request<RunBenchmarkRequest>,
+    ) -> Result<Response<RunBenchmarkResponse>, Status> {
+        let benchmark = match request.into_inner().suite.as_str() {
+            "" | "fibonacci" => parflow_bench::BenchmarkRunner::benchmark_fibonacci().await,
+            "simple" => parflow_bench::BenchmarkRunner::benchmark_simple().await,
+            other => {
+                return Err(Status::invalid_argument(format!("unknown benchmark suite {}", other)))
+            }
+        };
+
+        let mut metrics: Vec<LanguageMetrics> = benchmark
+            .benchmarks
+            .into_values()
+            .map(|m| LanguageMetrics {
+                language: m.language,
+                compilation_time_ms: m.compilation_time.as_millis() as u64,
+                execution_time_ms: m.execution_time.as_millis() as u64,
+                memory_usage_mb: m.memory_usage_mb,
+                cpu_usage_percent: m.cpu_usage_percent,
+                binary_size_mb: m.binary_size_mb,
+                throughput: m.throughput,
+            })
+            .collect();
+        metrics.sort_by(|a, b| a.language.cmp(&b.language));
+
+        Ok(Response::new(RunBenchmarkResponse {
+            metrics,
+            recommendations: benchmark.recommendations,
+        }))
+    }
+
+    async fn analyze_crate(
+        &self,
+        request: Request<AnalyzeCrateRequest>,
+    ) -> Result<Response<AnalyzeCrateResponse>, Status> {
+        let path = request.into_inner().path;
+        let path = if path.is_empty() { "Cargo.toml".to_string() } else { path };
+        let analysis = parflow_crate_orchestrator::CrateOrchestrator::new()
+            .analyze_cargo_toml(&path)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        Ok(Response::new(AnalyzeCrateResponse {
+            name: analysis.name,
+            version: analysis.version,
+            dependencies: analysis
+                .dependencies
+                .into_iter()
+                .map(|d| Dependency {
+                    name: d.name,
+                    version: d.version,
+                    used: d.used,
+                    deprecated: d.deprecated,
+                    alternative: d.alternative,
+                })
+                .collect(),
+            unused_dependencies: analysis.unused_dependencies,
+            outdated_dependencies: analysis.outdated_dependencies.len() as u32,
+            security_vulnerabilities: analysis.security_vulnerabilities.len() as u32,
+            compile_time_ms: analysis.performance_metrics.compile_time_ms,
+            binary_size_kb: analysis.performance_metrics.binary_size_kb,
+        }))
+    }
+
+    async fn create_schedule(
+        &self,
+        request: Request<CreateScheduleRequest>,
+    ) -> Result<Response<Schedule>, Status> {
+        if let Some(denied) = self.schedules_read_only() {
+            return Err(denied);
+        }
+        let spec =
+            schedules::to_spec(request.into_inner().spec).map_err(Status::invalid_argument)?;
+        let created =
+            self.schedules.create(spec).map_err(|e| Status::invalid_argument(e.to_string()))?;
+        Ok(Response::new(schedules::to_message(created)))
+    }
+
+    async fn list_schedules(
+        &self,
+        _request: Request<ListSchedulesRequest>,
+    ) -> Result<Response<ListSchedulesResponse>, Status> {
+        let schedules = self.schedules.list().into_iter().map(schedules::to_message).collect();
+        Ok(Response::new(ListSchedulesResponse { schedules }))
+    }
+
+    async fn get_schedule(
+        &self,
+        request: Request<GetScheduleRequest>,
+    ) -> Result<Response<Schedule>, Status> {
+        let id = request.into_inner().id;
+        let schedule = self
+            .schedules
+            .get(&id)
+            .ok_or_else(|| Status::not_found(format!("unknown schedule {}", id)))?;
+        Ok(Response::new(schedules::to_message(schedule)))
+    }
+
+    async fn update_schedule(
+        &self,
+        request: Request<UpdateScheduleRequest>,
+    ) -> Result<Response<Schedule>, Status> {
+        if let Some(denied) = self.schedules_read_only() {
+            return Err(denied);
+        }
+        let request = request.into_inner();
+        let spec = schedules::to_spec(request.spec).map_err(Status::invalid_argument)?;
+        let updated = self
+            .schedules
+            .update(&request.id, spec)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?
+            .ok_or_else(|| Status::not_found(format!("unknown schedule {}", request.id)))?;
+        Ok(Response::new(schedules::to_message(updated)))
+    }
+
+    async fn delete_schedule(
+        &self,
+        request: Request<DeleteScheduleRequest>,
+    ) -> Result<Response<DeleteScheduleResponse>, Status> {
+        if let Some(denied) = self.schedules_read_only() {
+            return Err(denied);
+        }
+        let id = request.into_inner().id;
+        match self.schedules.delete(&id) {
+            Ok(true) => Ok(Response::new(DeleteScheduleResponse {})),
+            Ok(false) => Err(Status::not_found(format!("unknown schedule {}", id))),
+            Err(e) => Err(Status::internal(e.to_string())),
+        }
+    }
+
+    async fn transpile(
+        &self,
+        request: Request<TranspileRequest>,
+    ) -> Result<Response<TranspileResponse>, Status> {
+        use parflow_transpiler::service::{self, Rejection};
+
+        let TranspileRequest { code, from, to } = request.into_inner();
+        let transpiling =
+            tokio::task::spawn_blocking(move || service::transpile(&code, &from, &to));
+        match tokio::time::timeout(service::TIMEOUT, transpiling).await {
+            Ok(Ok(Ok(code))) => Ok(Response::new(TranspileResponse { code })),
+            Ok(Ok(Err(rejection @ Rejection::TooLarge(_)))) => {
+                Err(Status::resource_exhausted(rejection.to_string()))
+            }
+            Ok(Ok(Err(rejection))) => Err(Status::invalid_argument(rejection.to_string())),
+            Ok(Err(e)) => Err(Status::internal(e.to_string())),
+            Err(_) => Err(Status::deadline_exceeded(format!(
+                "transpilation took longer than {}s",
+                service::TIMEOUT.as_secs()
+            ))),
+        }
+    }
 }
 
-pub async fn run_grpc_server(port: u16) -> Result<(), Box<dyn std::error::Error>> {
-    let addr = format!("[::1]:{}", port).parse()?;
-    println!("🔌 gRPC server listening on {}", addr);
-    Server::builder()
-        .add_service(OrchestratorServer::new(MyOrchestrator::default()))
+pub async fn run_grpc_server(
+    host: &str,
+    port: u16,
+    tls: Option<TlsSettings>,
+    workflows: WorkflowRegistry,
+    schedules: CronScheduler,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let addr = format!("{}:{}", host, port).parse()?;
+
+    let authenticated = tls.as_ref().is_some_and(|tls| tls.client_ca.is_some());
+    if !authenticated {
+        tracing::warn!("⚠️  No client certificates required, schedules are read-only");
+    }
+    let mut builder = Server::builder();
+    match tls {
+        Some(tls) => {
+            let identity = tls.identity()?;
+            let mut config =
+                ServerTlsConfig::new().identity(Identity::from_pem(identity.cert, identity.key));
+            if let Some(ca) = tls.client_ca_pem()? {
+                config = config.client_ca_root(Certificate::from_pem(ca));
+                tracing::info!("🔐 Requiring client certificates (mTLS)");
+            }
+            builder = builder.tls_config(config)?;
+            tracing::info!(%addr, "🔒 gRPC server listening (TLS)");
+        }
+        None => tracing::info!(%addr, "🔌 gRPC server listening"),
+    }
+
+    let (reporter, health_service) = tonic_health::server::health_reporter();
+    health::spawn_probes(reporter, workflows.clone(), schedules.clone(), Duration::from_secs(10));
+
+    let reflection_service = tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(proto::parflow::FILE_DESCRIPTOR_SET)
+        .register_encoded_file_descriptor_set(tonic_health::pb::FILE_DESCRIPTOR_SET)
+        .build()?;
+
+    builder
+        .add_service(health_service)
+        .add_service(reflection_service)
+        .add_service(OrchestratorServer::new(MyOrchestrator {
+            workflows,
+            schedules,
+            authenticated,
+        }))
         .serve(addr)
         .await?;
     Ok(())
@@ -38,9 +295,52 @@ pub async fn run_grpc_server(port: u16) -> Result<(), Box<dyn std::error::Error>
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    println!("🚀 Starting ParFlow gRPC Server");
+    let config = ParflowConfig::load()?.config;
+    let _telemetry = parflow_core::telemetry::init_from_env("parflow-grpc")?;
+    parflow_core::logging::init(&LogOptions::from_config(&config.log)?)?;
+    tracing::info!("🚀 Starting ParFlow gRPC server");
+
+    // PORT and HOST (set by the CLI and most hosting platforms) win over the config file
+    let port =
+        std::env::var("PORT").ok().and_then(|p| p.parse().ok()).unwrap_or(config.server.grpc_port);
+    // Bind to all interfaces (HOST=[::]) when running behind Kubernetes probes
+    let host = std::env::var("HOST").unwrap_or(config.server.host);
+
+    let workflows =
+        WorkflowRegistry::with_default_timeout(config.orchestrator.default_timeout_secs);
+    let schedules = CronScheduler::open(config.server.schedules_dir.join("grpc"))?;
+    let registry = workflows.clone();
+    schedules.start(move |schedule| schedules::run(registry.clone(), schedule));
+    run_grpc_server(&host, port, TlsSettings::from_env()?, workflows, schedules).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let port = std::env::var("PORT").ok().and_then(|p| p.parse().ok()).unwrap_or(50051);
+    #[tokio::test]
+    async fn test_schedules_are_read_only_without_client_certificates() {
+        let dir =
+            std::env::temp_dir().join(format!("parflow-grpc-schedules-{}", std::process::id()));
+        let orchestrator = MyOrchestrator {
+            workflows: WorkflowRegistry::default(),
+            schedules: CronScheduler::open(&dir).unwrap(),
+            authenticated: false,
+        };
+        fn denied<T>(result: Result<T, Status>) {
+            assert_eq!(result.err().unwrap().code(), tonic::Code::PermissionDenied);
+        }
+        denied(orchestrator.create_schedule(Request::new(CreateScheduleRequest::default())).await);
+        denied(orchestrator.update_schedule(Request::new(UpdateScheduleRequest::default())).await);
+        denied(orchestrator.delete_schedule(Request::new(DeleteScheduleRequest::default())).await);
+        let listed = orchestrator.list_schedules(Request::new(ListSchedulesRequest {})).await;
+        assert!(listed.unwrap().into_inner().schedules.is_empty());
 
-    run_grpc_server(port).await
+        // With certificates, requests get as far as validating the schedule
+        let orchestrator = MyOrchestrator { authenticated: true, ..orchestrator };
+        let created = orchestrator.create_schedule(Request::new(CreateScheduleRequest::default()));
+        assert_eq!(created.await.unwrap_err().code(), tonic::Code::InvalidArgument);
+        drop(orchestrator);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
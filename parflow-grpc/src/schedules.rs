@@ -0,0 +1,63 @@
+//! Conversions between the schedule messages and [`parflow_orchestrator::schedule`]
+//!
+//! Scheduled workflows run through the [`WorkflowRegistry`], so their events stream to
+//! `StreamWorkflowEvents` subscribers like those of submitted workflows.
+
+use crate::proto::parflow::{self, LanguageTask, SubmitWorkflowRequest};
+use crate::workflows::{to_workflow, WorkflowRegistry};
+use parflow_orchestrator::{Schedule, ScheduleSpec, ScheduleStatus};
+
+/// Fails with the message of an invalid argument status.
+pub fn to_spec(spec: Option<parflow::ScheduleSpec>) -> Result<ScheduleSpec, String> {
+    let spec = spec.ok_or("missing schedule spec")?;
+    let workflow = spec.workflow.ok_or("schedule has no workflow")?;
+    Ok(ScheduleSpec {
+        name: spec.name,
+        cron: spec.cron,
+        workflow: to_workflow(workflow.name, workflow.tasks, workflow.concurrent),
+        overlap: spec.overlap.parse().map_err(|e| format!("{}", e))?,
+        enabled: !spec.disabled,
+    })
+}
+
+pub fn to_message(status: ScheduleStatus) -> parflow::Schedule {
+    let ScheduleStatus { schedule, next_run_at, running, queued } = status;
+    let spec = schedule.spec;
+    parflow::Schedule {
+        id: schedule.id,
+        spec: Some(parflow::ScheduleSpec {
+            name: spec.name,
+            cron: spec.cron,
+            workflow: Some(SubmitWorkflowRequest {
+                name: spec.workflow.name,
+                tasks: spec
+                    .workflow
+                    .tasks
+                    .into_iter()
+                    .map(|task| LanguageTask {
+                        language: task.language,
+                        command: task.command,
+                        args: task.args,
+                        working_dir: task.working_dir,
+                        timeout_seconds: task.timeout_seconds,
+                    })
+                    .collect(),
+                concurrent: spec.workflow.concurrent,
+            }),
+            overlap: spec.overlap.name().to_string(),
+            disabled: !spec.enabled,
+        }),
+        created_at: schedule.created_at,
+        last_run_at: schedule.last_run_at,
+        next_run_at,
+        running: running as u32,
+        queued,
+    }
+}
+
+/// Run the workflow of a schedule that came due and wait for it to finish.
+pub async fn run(workflows: WorkflowRegistry, schedule: Schedule) {
+    let (workflow_id, finished) = workflows.start(schedule.spec.workflow);
+    tracing::info!(schedule = %schedule.spec.name, %workflow_id, "⏰ Scheduled workflow started");
+    let _ = finished.await;
+}
@@ -0,0 +1,355 @@
+use crate::proto::parflow::{
+    workflow_event::Event, LanguageTask, TaskFinished, TaskResult, TaskStarted, WorkflowCompleted,
+    WorkflowEvent, WorkflowFailed, WorkflowStarted,
+};
+use parflow_orchestrator::{ExecutionResult, MultiLanguageOrchestrator, MultiLanguageWorkflow};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::{JoinError, JoinHandle};
+use tonic::Status;
+
+/// How long finished workflows can still be subscribed to
+const FINISHED_TTL: Duration = Duration::from_secs(60 * 60);
+/// Finished workflows kept at most, the oldest are dropped first
+const MAX_FINISHED: usize = 1000;
+
+struct WorkflowRun {
+    history: Vec<WorkflowEvent>,
+    live: broadcast::Sender<WorkflowEvent>,
+    finished_at: Option<Instant>,
+}
+
+fn is_final(event: &WorkflowEvent) -> bool {
+    matches!(event.event, Some(Event::Completed(_) | Event::Failed(_)))
+}
+
+/// Tracks submitted workflows and fans their events out to any number of subscribers.
+/// Subscribers that join late first receive the events they missed.
+#[derive(Clone)]
+pub struct WorkflowRegistry {
+    runs: Arc<Mutex<HashMap<String, WorkflowRun>>>,
+    /// Every event of every workflow, for dashboards
+    all: broadcast::Sender<WorkflowEvent>,
+    /// Applied to tasks submitted without a timeout
+    default_timeout_secs: Option<u64>,
+    finished_ttl: Duration,
+    max_finished: usize,
+}
+
+impl Default for WorkflowRegistry {
+    fn default() -> Self {
+        Self {
+            runs: Arc::default(),
+            all: broadcast::channel(1024).0,
+            default_timeout_secs: None,
+            finished_ttl: FINISHED_TTL,
+            max_finished: MAX_FINISHED,
+        }
+    }
+}
+
+impl WorkflowRegistry {
+    pub fn with_default_timeout(default_timeout_secs: Option<u64>) -> Self {
+        Self { default_timeout_secs, ..Default::default() }
+    }
+
+    pub fn submit(&self, name: String, tasks: Vec<LanguageTask>, concurrent: bool) -> String {
+        self.start(to_workflow(name, tasks, concurrent)).0
+    }
+
+    /// Run `workflow`, returning its id and the task that finishes with it.
+    pub fn start(&self, mut workflow: MultiLanguageWorkflow) -> (String, JoinHandle<()>) {
+        let workflow_id = uuid::Uuid::new_v4().to_string();
+        let (live, _) = broadcast::channel(256);
+        self.runs.lock().unwrap().insert(
+            workflow_id.clone(),
+            WorkflowRun { history: Vec::new(), live, finished_at: None },
+        );
+        for task in &mut workflow.tasks {
+            task.timeout_seconds = task.timeout_seconds.or(self.default_timeout_secs);
+        }
+
+        let registry = self.clone();
+        let id = workflow_id.clone();
+        let name = workflow.name.clone();
+        let run = tokio::spawn(async move {
+            registry.publish(
+                &id,
+                Event::Started(WorkflowStarted { name, task_count: workflow.tasks.len() as u32 }),
+            );
+
+            let (events, mut progress) = mpsc::unbounded_channel();
+            let execution = tokio::spawn(MultiLanguageOrchestrator::execute_workflow_with_events(
+                workflow, events,
+            ));
+
+            while let Some(event) = progress.recv().await {
+                registry.publish(&id, convert_event(event));
+            }
+
+            registry.publish(&id, outcome(execution.await));
+        });
+
+        (workflow_id, run)
+    }
+
+    /// Replays past events for `workflow_id` and then follows it until completion.
+    pub fn subscribe(
+        &self,
+        workflow_id: &str,
+    ) -> Option<mpsc::Receiver<Result<WorkflowEvent, Status>>> {
+        let (history, mut live, finished) = {
+            let runs = self.runs.lock().unwrap();
+            let run = runs.get(workflow_id)?;
+            (run.history.clone(), run.live.subscribe(), run.finished_at.is_some())
+        };
+
+        let (tx, rx) = mpsc::channel(64);
+        tokio::spawn(async move {
+            for event in history {
+                if tx.send(Ok(event)).await.is_err() {
+                    return;
+                }
+            }
+            if finished {
+                return;
+            }
+
+            loop {
+                match live.recv().await {
+                    Ok(event) => {
+                        let done = is_final(&event);
+                        if tx.send(Ok(event)).await.is_err() || done {
+                            return;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        let status = Status::data_loss(format!("{} events dropped", skipped));
+                        let _ = tx.send(Err(status)).await;
+                        return;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                }
+            }
+        });
+
+        Some(rx)
+    }
+
+    /// Replays unfinished workflows and then follows events from all workflows. Lagging
+    /// subscribers skip the dropped events instead of being disconnected.
+    pub fn subscribe_all(&self) -> mpsc::Receiver<Result<WorkflowEvent, Status>> {
+        let (history, mut live) = {
+            let runs = self.runs.lock().unwrap();
+            let history: Vec<WorkflowEvent> = runs
+                .values()
+                .filter(|run| run.finished_at.is_none())
+                .flat_map(|run| run.history.clone())
+                .collect();
+            (history, self.all.subscribe())
+        };
+
+        let (tx, rx) = mpsc::channel(64);
+        tokio::spawn(async move {
+            for event in history {
+                if tx.send(Ok(event)).await.is_err() {
+                    return;
+                }
+            }
+            loop {
+                match live.recv().await {
+                    Ok(event) => {
+                        if tx.send(Ok(event)).await.is_err() {
+                            return;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return,
+                }
+            }
+        });
+        rx
+    }
+
+    /// False once a panic while holding the registry lock has poisoned it.
+    pub fn is_healthy(&self) -> bool {
+        !self.runs.is_poisoned()
+    }
+
+    fn publish(&self, workflow_id: &str, event: Event) {
+        let event = WorkflowEvent { workflow_id: workflow_id.to_string(), event: Some(event) };
+        let mut runs = self.runs.lock().unwrap();
+        let finished = is_final(&event);
+        if let Some(run) = runs.get_mut(workflow_id) {
+            if finished {
+                run.finished_at = Some(Instant::now());
+            }
+            run.history.push(event.clone());
+            let _ = run.live.send(event.clone());
+            let _ = self.all.send(event);
+        }
+        if finished {
+            self.evict(&mut runs);
+        }
+    }
+
+    /// Forget finished workflows older than the TTL, and the oldest beyond the count limit
+    fn evict(&self, runs: &mut HashMap<String, WorkflowRun>) {
+        let now = Instant::now();
+        runs.retain(|_, run| {
+            run.finished_at.is_none_or(|at| now.duration_since(at) < self.finished_ttl)
+        });
+        let mut finished: Vec<(Instant, String)> =
+            runs.iter().filter_map(|(id, run)| Some((run.finished_at?, id.clone()))).collect();
+        if finished.len() > self.max_finished {
+            finished.sort();
+            for (_, id) in &finished[..finished.len() - self.max_finished] {
+                runs.remove(id);
+            }
+        }
+    }
+}
+
+/// The event ending a workflow whose execution returned `results`
+fn outcome(results: Result<Vec<ExecutionResult>, JoinError>) -> Event {
+    match results {
+        Ok(results) => {
+            let succeeded = results.iter().filter(|r| r.success).count() as u32;
+            Event::Completed(WorkflowCompleted {
+                succeeded,
+                failed: results.len() as u32 - succeeded,
+            })
+        }
+        Err(e) if e.is_panic() => {
+            Event::Failed(WorkflowFailed { error: "the workflow execution panicked".to_string() })
+        }
+        Err(_) => Event::Failed(WorkflowFailed { error: "the workflow was cancelled".to_string() }),
+    }
+}
+
+pub fn to_workflow(
+    name: String,
+    tasks: Vec<LanguageTask>,
+    concurrent: bool,
+) -> MultiLanguageWorkflow {
+    MultiLanguageWorkflow {
+        name,
+        tasks: tasks
+            .into_iter()
+            .map(|task| parflow_orchestrator::LanguageTask {
+                name: None,
+                language: task.language,
+                command: task.command,
+                args: task.args,
+                working_dir: task.working_dir,
+                timeout_seconds: task.timeout_seconds,
+                depends_on: Vec::new(),
+                image: None,
+                resources: None,
+                outputs: Vec::new(),
+                inputs_from: Vec::new(),
+                env: Default::default(),
+                policy: Default::default(),
+            })
+            .collect(),
+        concurrent,
+        watch: Default::default(),
+        track_changes: false,
+        upstream_outputs: Default::default(),
+    }
+}
+
+fn convert_event(event: parflow_orchestrator::WorkflowEvent) -> Event {
+    match event {
+        parflow_orchestrator::WorkflowEvent::TaskStarted { index, language } => {
+            Event::TaskStarted(TaskStarted { index: index as u32, language })
+        }
+        parflow_orchestrator::WorkflowEvent::TaskFinished { index, result } => {
+            Event::TaskFinished(TaskFinished {
+                index: index as u32,
+                result: Some(TaskResult {
+                    task_name: result.task_name,
+                    language: result.language,
+                    success: result.success,
+                    output: result.output,
+                    execution_time_ms: result.execution_time as u64,
+                    exit_code: result.exit_code,
+                }),
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn python_task() -> LanguageTask {
+        LanguageTask {
+            language: "python".to_string(),
+            command: "python".to_string(),
+            args: vec!["main.py".to_string()],
+            ..Default::default()
+        }
+    }
+
+    async fn events(mut rx: mpsc::Receiver<Result<WorkflowEvent, Status>>) -> Vec<Event> {
+        let mut events = Vec::new();
+        while let Some(event) = rx.recv().await {
+            events.extend(event.unwrap().event);
+        }
+        events
+    }
+
+    #[tokio::test]
+    async fn test_late_subscribers_replay_finished_workflows() {
+        let registry = WorkflowRegistry::default();
+        let workflow = to_workflow("demo".to_string(), vec![python_task()], false);
+        let (id, run) = registry.start(workflow);
+        let live = registry.subscribe(&id).unwrap();
+        run.await.unwrap();
+
+        let replayed = events(registry.subscribe(&id).unwrap()).await;
+        assert_eq!(events(live).await, replayed);
+        assert!(matches!(replayed[0], Event::Started(ref started) if started.task_count == 1));
+        assert!(matches!(replayed[1], Event::TaskStarted(_)));
+        assert!(matches!(replayed[2], Event::TaskFinished(_)));
+        assert_eq!(replayed[3], Event::Completed(WorkflowCompleted { succeeded: 1, failed: 0 }));
+        assert!(registry.subscribe("unknown").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_finished_workflows_are_evicted() {
+        let registry = WorkflowRegistry { max_finished: 1, ..Default::default() };
+        let mut ids = Vec::new();
+        for name in ["first", "second"] {
+            let (id, run) =
+                registry.start(to_workflow(name.to_string(), vec![python_task()], true));
+            run.await.unwrap();
+            ids.push(id);
+        }
+        assert!(registry.subscribe(&ids[0]).is_none());
+        assert!(registry.subscribe(&ids[1]).is_some());
+
+        let registry = WorkflowRegistry { finished_ttl: Duration::ZERO, ..Default::default() };
+        let (id, run) = registry.start(to_workflow("once".to_string(), vec![python_task()], true));
+        run.await.unwrap();
+        assert!(registry.subscribe(&id).is_none());
+        assert!(registry.runs.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_panicking_executions_end_in_a_failure() {
+        let panicked = tokio::spawn(async { panic!("boom") }).await.map(|()| Vec::new());
+        assert_eq!(
+            outcome(panicked),
+            Event::Failed(WorkflowFailed { error: "the workflow execution panicked".to_string() })
+        );
+        assert_eq!(
+            outcome(Ok(Vec::new())),
+            Event::Completed(WorkflowCompleted { succeeded: 0, failed: 0 })
+        );
+    }
+}
@@ -1,9 +1,15 @@
 //! Kernel-compatible error handling and system interfaces for ParFlow
 //! Inspired by Linux kernel Rust integration patterns
 
-use std::time::Instant;
 use thiserror::Error;
 
+pub mod numa;
+mod platform;
+pub mod profile;
+pub mod sched;
+
+pub use profile::{KernelProfiler, ProfileReport};
+
 /// Kernel-style error types for system-level operations
 #[derive(Error, Debug, Clone)]
 pub enum KernelError {
@@ -35,40 +41,68 @@ pub type KResult<T> = Result<T, KernelError>;
 #[derive(Debug, Clone)]
 pub struct SystemInfo {
     pub architecture: String,
+    /// `uname -r` on Unix, build.revision on Windows, "unknown" if neither could be read
     pub kernel_version: String,
+    /// Distribution or product name, e.g. "Debian GNU/Linux" or "macOS"
+    pub os_name: Option<String>,
+    pub os_version: Option<String>,
+    pub total_memory_bytes: Option<u64>,
+    /// Size of one memory page in bytes
     pub memory_pages: usize,
     pub cpu_cores: usize,
     pub cache_line_size: usize,
+    /// The explicit hugepage pool, where the OS has one
+    pub hugepages: Option<HugePages>,
+}
+
+/// Linux hugetlb pool from `/proc/meminfo`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HugePages {
+    pub page_size_kb: u64,
+    pub total: u64,
+    pub free: u64,
 }
 
 impl SystemInfo {
     /// Gather system information in a kernel-compatible way
     pub fn gather() -> KResult<Self> {
+        let os = platform::gather();
         Ok(Self {
             architecture: std::env::consts::ARCH.to_string(),
-            kernel_version: "unknown".to_string(),
+            kernel_version: os.kernel_version.unwrap_or_else(|| "unknown".to_string()),
+            os_name: os.os_name,
+            os_version: os.os_version,
+            total_memory_bytes: os.total_memory_bytes,
             memory_pages: page_size::get(),
             cpu_cores: num_cpus::get(),
             cache_line_size: 64,
+            hugepages: os.hugepages,
         })
     }
-}
-
-/// Performance profiling inspired by kernel instrumentation
-pub struct KernelProfiler {
-    start: Instant,
-    operation: String,
-    module: &'static str,
-}
-
-impl KernelProfiler {
-    pub fn new(operation: impl Into<String>, module: &'static str) -> Self {
-        Self { start: Instant::now(), operation: operation.into(), module }
-    }
 
-    pub fn done(self) {
-        let duration = self.start.elapsed();
-        log::info!("[KERNEL_PROFILE] {}::{} took {:?}", self.module, self.operation, duration);
+    /// One line for `parflow status`, e.g.
+    /// `Debian GNU/Linux 12, kernel 6.1.0, x86_64, 8 cores, 15.6 GiB RAM, hugepages 2048 kB (0/0 free)`
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        match (&self.os_name, &self.os_version) {
+            (Some(name), Some(version)) => parts.push(format!("{} {}", name, version)),
+            (Some(name), None) => parts.push(name.clone()),
+            _ => parts.push(std::env::consts::OS.to_string()),
+        }
+        parts.push(format!("kernel {}", self.kernel_version));
+        parts.push(self.architecture.clone());
+        parts.push(format!("{} cores", self.cpu_cores));
+        if let Some(bytes) = self.total_memory_bytes {
+            parts.push(format!("{:.1} GiB RAM", bytes as f64 / (1u64 << 30) as f64));
+        }
+        match self.hugepages {
+            Some(pool) => parts.push(format!(
+                "hugepages {} kB ({}/{} free)",
+                pool.page_size_kb, pool.free, pool.total
+            )),
+            None => parts.push("no hugepages".to_string()),
+        }
+        parts.join(", ")
     }
 }
 
@@ -89,6 +123,7 @@ mod tests {
         let info = SystemInfo::gather().unwrap();
         assert!(!info.architecture.is_empty());
         assert!(info.cpu_cores > 0);
+        assert!(info.summary().contains(&info.architecture));
     }
 
     #[test]
@@ -0,0 +1,278 @@
+//! NUMA-aware memory allocation and thread placement
+//!
+//! On Linux, nodes come from `/sys/devices/system/node`, [`alloc_on_node`] maps anonymous memory
+//! and asks the kernel to prefer the node with `mbind(MPOL_PREFERRED)`, and
+//! [`pin_current_thread_to_node`] restricts the thread to that node's CPUs. Elsewhere, or on a
+//! kernel without NUMA support, the machine is one node holding every CPU, allocations come from
+//! the global allocator and pinning does nothing, so callers never need their own fallback.
+
+use crate::{KResult, KernelError};
+use std::ops::{Deref, DerefMut};
+
+/// One memory node and the CPUs attached to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NumaNode {
+    pub id: usize,
+    pub cpus: Vec<usize>,
+    /// `None` where the OS does not report per-node memory
+    pub memory_bytes: Option<u64>,
+}
+
+/// All NUMA nodes, lowest id first; always at least one.
+pub fn nodes() -> Vec<NumaNode> {
+    #[cfg(target_os = "linux")]
+    if let Some(nodes) = linux::nodes().filter(|nodes| !nodes.is_empty()) {
+        return nodes;
+    }
+    vec![NumaNode { id: 0, cpus: (0..num_cpus::get()).collect(), memory_bytes: None }]
+}
+
+/// The node of the CPU the calling thread is running on right now.
+pub fn current_node() -> usize {
+    #[cfg(target_os = "linux")]
+    if let Some(cpu) = linux::current_cpu() {
+        if let Some(node) = nodes().into_iter().find(|node| node.cpus.contains(&cpu)) {
+            return node.id;
+        }
+    }
+    0
+}
+
+fn node(id: usize) -> KResult<NumaNode> {
+    nodes()
+        .into_iter()
+        .find(|node| node.id == id)
+        .ok_or_else(|| KernelError::HardwareUnsupported { feature: format!("NUMA node {}", id) })
+}
+
+/// Zeroed memory whose pages the kernel places on one node where it can.
+pub struct NodeBuffer {
+    inner: Backing,
+    node: usize,
+    bound: bool,
+}
+
+enum Backing {
+    Heap(Vec<u8>),
+    #[cfg(target_os = "linux")]
+    Mapped(linux::Mapping),
+}
+
+impl NodeBuffer {
+    pub fn node(&self) -> usize {
+        self.node
+    }
+
+    /// Whether the kernel accepted the placement policy, rather than the buffer being an
+    /// ordinary allocation
+    pub fn is_bound(&self) -> bool {
+        self.bound
+    }
+}
+
+impl Deref for NodeBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match &self.inner {
+            Backing::Heap(bytes) => bytes,
+            #[cfg(target_os = "linux")]
+            Backing::Mapped(mapping) => mapping.as_slice(),
+        }
+    }
+}
+
+impl DerefMut for NodeBuffer {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        match &mut self.inner {
+            Backing::Heap(bytes) => bytes,
+            #[cfg(target_os = "linux")]
+            Backing::Mapped(mapping) => mapping.as_mut_slice(),
+        }
+    }
+}
+
+/// Allocate `size` zeroed bytes preferring memory on `node`.
+///
+/// # Errors
+///
+/// `HardwareUnsupported` for a node that does not exist, `AllocationError` if the memory cannot
+/// be mapped. A kernel that refuses the placement policy is not an error; the buffer is then
+/// unbound, see [`NodeBuffer::is_bound`].
+pub fn alloc_on_node(size: usize, node_id: usize) -> KResult<NodeBuffer> {
+    node(node_id)?;
+    #[cfg(target_os = "linux")]
+    if size > 0 {
+        let mapping = linux::Mapping::new(size)?;
+        let bound = mapping.prefer_node(node_id);
+        return Ok(NodeBuffer { inner: Backing::Mapped(mapping), node: node_id, bound });
+    }
+    Ok(NodeBuffer { inner: Backing::Heap(vec![0; size]), node: node_id, bound: false })
+}
+
+/// Keep the calling thread on the CPUs of `node`, so its allocations stay local.
+///
+/// # Errors
+///
+/// `HardwareUnsupported` for a node that does not exist, `SyscallError` if the kernel rejects
+/// the CPU set (e.g. a cgroup excludes all of them).
+pub fn pin_current_thread_to_node(node_id: usize) -> KResult<()> {
+    let node = node(node_id)?;
+    if cfg!(target_os = "linux") {
+        crate::sched::set_thread_affinity(&node.cpus)?;
+    }
+    Ok(())
+}
+
+/// Parse a sysfs CPU list such as `0-3,8,10-11`.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_cpu_list(list: &str) -> Vec<usize> {
+    let mut cpus = Vec::new();
+    for range in list.trim().split(',').filter(|range| !range.is_empty()) {
+        match range.split_once('-') {
+            Some((start, end)) => {
+                if let (Ok(start), Ok(end)) = (start.parse::<usize>(), end.parse::<usize>()) {
+                    cpus.extend(start..=end);
+                }
+            }
+            None => cpus.extend(range.parse::<usize>().ok()),
+        }
+    }
+    cpus
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::{parse_cpu_list, NumaNode};
+    use crate::{KResult, KernelError};
+    use std::io;
+
+    const NODE_DIR: &str = "/sys/devices/system/node";
+    /// `MPOL_PREFERRED` from `<linux/mempolicy.h>`
+    const MPOL_PREFERRED: libc::c_long = 1;
+
+    pub(super) fn nodes() -> Option<Vec<NumaNode>> {
+        let mut nodes = Vec::new();
+        for entry in std::fs::read_dir(NODE_DIR).ok()?.flatten() {
+            let name = entry.file_name();
+            let Some(id) = name.to_str().and_then(|name| name.strip_prefix("node")) else {
+                continue;
+            };
+            let Ok(id) = id.parse() else { continue };
+            let path = entry.path();
+            let cpus = std::fs::read_to_string(path.join("cpulist"))
+                .map(|list| parse_cpu_list(&list))
+                .unwrap_or_default();
+            // "Node 0 MemTotal:       16318412 kB"
+            let memory_bytes =
+                std::fs::read_to_string(path.join("meminfo")).ok().and_then(|text| {
+                    let line = text.lines().find(|line| line.contains("MemTotal:"))?;
+                    let kb: u64 = line.split_whitespace().rev().nth(1)?.parse().ok()?;
+                    Some(kb * 1024)
+                });
+            nodes.push(NumaNode { id, cpus, memory_bytes });
+        }
+        nodes.sort_by_key(|node| node.id);
+        Some(nodes)
+    }
+
+    pub(super) fn current_cpu() -> Option<usize> {
+        // SAFETY: sched_getcpu has no preconditions
+        let cpu = unsafe { libc::sched_getcpu() };
+        usize::try_from(cpu).ok()
+    }
+
+    /// Anonymous private mapping, unmapped on drop
+    pub(super) struct Mapping {
+        ptr: *mut u8,
+        len: usize,
+    }
+
+    // SAFETY: the mapping is exclusively owned, like a Box<[u8]>
+    unsafe impl Send for Mapping {}
+    unsafe impl Sync for Mapping {}
+
+    impl Mapping {
+        pub(super) fn new(len: usize) -> KResult<Self> {
+            // SAFETY: a fresh anonymous mapping does not alias anything
+            let ptr = unsafe {
+                libc::mmap(
+                    std::ptr::null_mut(),
+                    len,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                    -1,
+                    0,
+                )
+            };
+            if ptr == libc::MAP_FAILED {
+                return Err(KernelError::AllocationError {
+                    context: format!("mmap of {} bytes: {}", len, io::Error::last_os_error()),
+                });
+            }
+            Ok(Self { ptr: ptr.cast(), len })
+        }
+
+        /// Ask for the pages to come from `node`; pages are only placed on first touch, so
+        /// this must run before the buffer is written.
+        pub(super) fn prefer_node(&self, node: usize) -> bool {
+            let bits = libc::c_ulong::BITS as usize;
+            let mut mask = vec![0 as libc::c_ulong; node / bits + 1];
+            mask[node / bits] |= 1 << (node % bits);
+            // SAFETY: the range is our own mapping and the mask holds maxnode bits
+            let result = unsafe {
+                libc::syscall(
+                    libc::SYS_mbind,
+                    self.ptr,
+                    self.len,
+                    MPOL_PREFERRED,
+                    mask.as_ptr(),
+                    mask.len() * bits,
+                    0,
+                )
+            };
+            result == 0
+        }
+
+        pub(super) fn as_slice(&self) -> &[u8] {
+            // SAFETY: ptr..ptr+len is a live mapping owned by self
+            unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+        }
+
+        pub(super) fn as_mut_slice(&mut self) -> &mut [u8] {
+            // SAFETY: as above, and &mut self guarantees exclusive access
+            unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) }
+        }
+    }
+
+    impl Drop for Mapping {
+        fn drop(&mut self) {
+            // SAFETY: unmapping exactly the range mmap returned
+            unsafe {
+                libc::munmap(self.ptr.cast(), self.len);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nodes_and_node_local_allocation() {
+        assert_eq!(parse_cpu_list("0-3,8,10-11\n"), vec![0, 1, 2, 3, 8, 10, 11]);
+        assert!(parse_cpu_list("").is_empty());
+
+        let nodes = nodes();
+        assert!(!nodes.is_empty());
+        assert!(nodes.iter().any(|node| node.id == current_node()));
+
+        let mut buffer = alloc_on_node(1 << 16, nodes[0].id).unwrap();
+        assert_eq!(buffer.len(), 1 << 16);
+        assert!(buffer.iter().all(|&byte| byte == 0));
+        buffer[1234] = 42;
+        assert_eq!(buffer[1234], 42);
+        assert!(alloc_on_node(16, usize::MAX).is_err());
+    }
+}
@@ -0,0 +1,176 @@
+//! Per-platform sources for [`crate::SystemInfo`]
+//!
+//! Linux reads procfs and `/etc/os-release`, macOS and the BSDs ask `sysctl` (and `sw_vers`),
+//! Windows queries the registry. Anything a platform cannot answer stays `None` rather than
+//! failing the whole gather.
+
+use crate::HugePages;
+use std::process::Command;
+
+#[derive(Debug, Default)]
+pub(crate) struct OsDetails {
+    pub kernel_version: Option<String>,
+    pub os_name: Option<String>,
+    pub os_version: Option<String>,
+    pub total_memory_bytes: Option<u64>,
+    pub hugepages: Option<HugePages>,
+}
+
+/// First line of a command's stdout, if it ran and printed something
+#[cfg_attr(windows, allow(dead_code))]
+fn command_line(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let text = String::from_utf8_lossy(&output.stdout);
+    let line = text.lines().next()?.trim();
+    (!line.is_empty()).then(|| line.to_string())
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn gather() -> OsDetails {
+    let read = |path: &str| std::fs::read_to_string(path).ok();
+    let release = read("/etc/os-release").map(|text| parse_os_release(&text)).unwrap_or_default();
+    let meminfo = read("/proc/meminfo").map(|text| parse_meminfo(&text)).unwrap_or_default();
+    OsDetails {
+        kernel_version: read("/proc/sys/kernel/osrelease")
+            .map(|text| text.trim().to_string())
+            .or_else(|| command_line("uname", &["-r"])),
+        os_name: release.0.or_else(|| Some("Linux".to_string())),
+        os_version: release.1,
+        total_memory_bytes: meminfo.0,
+        hugepages: meminfo.1,
+    }
+}
+
+#[cfg(any(
+    target_os = "macos",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd",
+    target_os = "dragonfly"
+))]
+pub(crate) fn gather() -> OsDetails {
+    let memory_key = if cfg!(target_os = "macos") { "hw.memsize" } else { "hw.physmem" };
+    let (os_name, os_version) = if cfg!(target_os = "macos") {
+        (command_line("sw_vers", &["-productName"]), command_line("sw_vers", &["-productVersion"]))
+    } else {
+        (command_line("sysctl", &["-n", "kern.ostype"]), command_line("uname", &["-r"]))
+    };
+    OsDetails {
+        kernel_version: command_line("sysctl", &["-n", "kern.osrelease"]),
+        os_name,
+        os_version,
+        total_memory_bytes: command_line("sysctl", &["-n", memory_key])
+            .and_then(|bytes| bytes.parse().ok()),
+        // Superpages are managed by the kernel there, nothing to reserve up front
+        hugepages: None,
+    }
+}
+
+#[cfg(windows)]
+pub(crate) fn gather() -> OsDetails {
+    const CURRENT_VERSION: &str = r"HKLM\SOFTWARE\Microsoft\Windows NT\CurrentVersion";
+    let value = |name: &str| {
+        let output =
+            Command::new("reg").args(["query", CURRENT_VERSION, "/v", name]).output().ok()?;
+        parse_reg_value(&String::from_utf8_lossy(&output.stdout), name)
+    };
+    let build = value("CurrentBuild");
+    let revision = value("UBR").and_then(|ubr| {
+        u64::from_str_radix(ubr.trim_start_matches("0x"), 16).ok().map(|n| n.to_string())
+    });
+    OsDetails {
+        kernel_version: match (&build, revision) {
+            (Some(build), Some(revision)) => Some(format!("{}.{}", build, revision)),
+            (build, _) => build.clone(),
+        },
+        os_name: value("ProductName"),
+        os_version: value("DisplayVersion").or_else(|| value("ReleaseId")),
+        total_memory_bytes: None,
+        hugepages: None,
+    }
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "macos",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd",
+    target_os = "dragonfly",
+    windows
+)))]
+pub(crate) fn gather() -> OsDetails {
+    OsDetails { kernel_version: command_line("uname", &["-r"]), ..Default::default() }
+}
+
+/// `NAME` and `VERSION_ID` from os-release(5)
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_os_release(text: &str) -> (Option<String>, Option<String>) {
+    let field = |key: &str| {
+        text.lines().find_map(|line| {
+            let value = line.strip_prefix(key)?.strip_prefix('=')?;
+            Some(value.trim().trim_matches('"').to_string())
+        })
+    };
+    (field("NAME"), field("VERSION_ID").or_else(|| field("BUILD_ID")))
+}
+
+/// Total RAM and the hugepage pool from `/proc/meminfo`
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_meminfo(text: &str) -> (Option<u64>, Option<HugePages>) {
+    let field = |key: &str| {
+        text.lines().find_map(|line| {
+            let value = line.strip_prefix(key)?.strip_prefix(':')?;
+            value.split_whitespace().next()?.parse::<u64>().ok()
+        })
+    };
+    let hugepages = field("Hugepagesize").map(|page_size_kb| HugePages {
+        page_size_kb,
+        total: field("HugePages_Total").unwrap_or(0),
+        free: field("HugePages_Free").unwrap_or(0),
+    });
+    (field("MemTotal").map(|kb| kb * 1024), hugepages)
+}
+
+/// The data of `name` in `reg query` output (`    name    REG_SZ    data`)
+#[cfg_attr(not(windows), allow(dead_code))]
+fn parse_reg_value(output: &str, name: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        if parts.next()? != name {
+            return None;
+        }
+        parts.next()?;
+        let data = parts.collect::<Vec<_>>().join(" ");
+        (!data.is_empty()).then_some(data)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_platform_sources() {
+        let release = "PRETTY_NAME=\"Debian GNU/Linux 12 (bookworm)\"\nNAME=\"Debian GNU/Linux\"\n\
+                       VERSION_ID=\"12\"\n";
+        assert_eq!(
+            parse_os_release(release),
+            (Some("Debian GNU/Linux".to_string()), Some("12".to_string()))
+        );
+
+        let meminfo = "MemTotal:       16318412 kB\nAnonHugePages:         0 kB\n\
+                       HugePages_Total:      64\nHugePages_Free:       60\nHugepagesize:       2048 kB\n";
+        let (total, hugepages) = parse_meminfo(meminfo);
+        assert_eq!(total, Some(16318412 * 1024));
+        assert_eq!(hugepages, Some(HugePages { page_size_kb: 2048, total: 64, free: 60 }));
+
+        let reg = "\r\nHKEY_LOCAL_MACHINE\\SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion\r\n    \
+                   ProductName    REG_SZ    Windows 10 Pro\r\n";
+        assert_eq!(parse_reg_value(reg, "ProductName"), Some("Windows 10 Pro".to_string()));
+        assert_eq!(parse_reg_value(reg, "UBR"), None);
+    }
+}
@@ -0,0 +1,318 @@
+//! Hierarchical profiling inspired by kernel instrumentation
+//!
+//! Each thread keeps a stack of the profilers currently open on it, so a [`KernelProfiler`]
+//! created while another is alive on the same thread becomes its child. Finished spans are folded
+//! into one process-wide tree keyed by their path, where every node aggregates call count and
+//! min/max/mean duration. [`ProfileReport::capture`] snapshots that tree and
+//! [`ProfileReport::render`] prints it the way `perf report` prints a call graph.
+
+use opentelemetry_api::metrics::Histogram;
+use opentelemetry_api::{global, KeyValue};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+thread_local! {
+    /// Names of the profilers open on this thread, outermost first
+    static STACK: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Aggregated timings of every finished span, shared by all threads
+static TREE: Mutex<Tree> = Mutex::new(Tree { nodes: Vec::new() });
+
+/// Arena of profile nodes; roots have no parent
+struct Tree {
+    nodes: Vec<Node>,
+}
+
+struct Node {
+    name: String,
+    parent: Option<usize>,
+    children: HashMap<String, usize>,
+    stats: OperationStats,
+}
+
+impl Tree {
+    fn record(&mut self, path: &[String], duration: Duration) {
+        let mut parent: Option<usize> = None;
+        for name in path {
+            let existing = match parent {
+                Some(index) => self.nodes[index].children.get(name).copied(),
+                None => {
+                    self.nodes.iter().position(|node| node.parent.is_none() && &node.name == name)
+                }
+            };
+            let index = existing.unwrap_or_else(|| {
+                self.nodes.push(Node {
+                    name: name.clone(),
+                    parent,
+                    children: HashMap::new(),
+                    stats: OperationStats::default(),
+                });
+                let index = self.nodes.len() - 1;
+                if let Some(parent) = parent {
+                    self.nodes[parent].children.insert(name.clone(), index);
+                }
+                index
+            });
+            parent = Some(index);
+        }
+        if let Some(index) = parent {
+            self.nodes[index].stats.add(duration);
+        }
+    }
+
+    fn snapshot(&self, index: usize) -> ProfileNode {
+        let node = &self.nodes[index];
+        let mut children: Vec<_> =
+            node.children.values().map(|&child| self.snapshot(child)).collect();
+        sort_by_total(&mut children);
+        ProfileNode { name: node.name.clone(), stats: node.stats, children }
+    }
+}
+
+fn sort_by_total(nodes: &mut [ProfileNode]) {
+    nodes.sort_by(|a, b| b.stats.total.cmp(&a.stats.total).then_with(|| a.name.cmp(&b.name)));
+}
+
+/// Call count and durations of one operation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OperationStats {
+    pub calls: u64,
+    pub total: Duration,
+    pub min: Duration,
+    pub max: Duration,
+}
+
+impl OperationStats {
+    fn add(&mut self, duration: Duration) {
+        self.min = if self.calls == 0 { duration } else { self.min.min(duration) };
+        self.max = self.max.max(duration);
+        self.total += duration;
+        self.calls += 1;
+    }
+
+    fn merge(&mut self, other: &OperationStats) {
+        if other.calls == 0 {
+            return;
+        }
+        self.min = if self.calls == 0 { other.min } else { self.min.min(other.min) };
+        self.max = self.max.max(other.max);
+        self.total += other.total;
+        self.calls += other.calls;
+    }
+
+    pub fn mean(&self) -> Duration {
+        match u32::try_from(self.calls) {
+            Ok(0) => Duration::ZERO,
+            Ok(calls) => self.total / calls,
+            Err(_) => Duration::from_secs_f64(self.total.as_secs_f64() / self.calls as f64),
+        }
+    }
+}
+
+/// One operation at one position in the call tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileNode {
+    /// `module::operation`
+    pub name: String,
+    pub stats: OperationStats,
+    /// Heaviest first
+    pub children: Vec<ProfileNode>,
+}
+
+/// Snapshot of the profile tree
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProfileReport {
+    /// Heaviest first
+    pub roots: Vec<ProfileNode>,
+}
+
+impl ProfileReport {
+    /// Snapshot everything profiled so far; spans still open are not included.
+    pub fn capture() -> Self {
+        let tree = TREE.lock().unwrap_or_else(|e| e.into_inner());
+        let mut roots: Vec<_> = (0..tree.nodes.len())
+            .filter(|&index| tree.nodes[index].parent.is_none())
+            .map(|index| tree.snapshot(index))
+            .collect();
+        sort_by_total(&mut roots);
+        Self { roots }
+    }
+
+    /// Snapshot and clear, so the next report starts from zero.
+    pub fn take() -> Self {
+        let report = Self::capture();
+        TREE.lock().unwrap_or_else(|e| e.into_inner()).nodes.clear();
+        report
+    }
+
+    /// Statistics per operation wherever it appeared in the tree, heaviest first.
+    pub fn by_operation(&self) -> Vec<(String, OperationStats)> {
+        fn visit(node: &ProfileNode, totals: &mut HashMap<String, OperationStats>) {
+            totals.entry(node.name.clone()).or_default().merge(&node.stats);
+            for child in &node.children {
+                visit(child, totals);
+            }
+        }
+        let mut totals = HashMap::new();
+        for root in &self.roots {
+            visit(root, &mut totals);
+        }
+        let mut flat: Vec<_> = totals.into_iter().collect();
+        flat.sort_by(|a, b| b.1.total.cmp(&a.1.total).then_with(|| a.0.cmp(&b.0)));
+        flat
+    }
+
+    /// The call tree with each node's share of the total profiled time, like `perf report`.
+    pub fn render(&self) -> String {
+        let total: Duration = self.roots.iter().map(|root| root.stats.total).sum();
+        let mut out = format!(
+            "{:>8}  {:>10}  {:>6}  {:>10}  {:>10}  {:>10}  operation\n",
+            "total%", "total", "calls", "min", "mean", "max"
+        );
+        for root in &self.roots {
+            render_node(&mut out, root, total, "", "");
+        }
+        out
+    }
+}
+
+fn render_node(out: &mut String, node: &ProfileNode, total: Duration, lead: &str, rest: &str) {
+    let share = if total.is_zero() {
+        0.0
+    } else {
+        node.stats.total.as_secs_f64() / total.as_secs_f64() * 100.0
+    };
+    let _ = writeln!(
+        out,
+        "{:>7.2}%  {:>10}  {:>6}  {:>10}  {:>10}  {:>10}  {}{}",
+        share,
+        format!("{:.3?}", node.stats.total),
+        node.stats.calls,
+        format!("{:.3?}", node.stats.min),
+        format!("{:.3?}", node.stats.mean()),
+        format!("{:.3?}", node.stats.max),
+        lead,
+        node.name
+    );
+    for (i, child) in node.children.iter().enumerate() {
+        let last = i + 1 == node.children.len();
+        let (branch, next) = if last { ("└─ ", "   ") } else { ("├─ ", "│  ") };
+        render_node(
+            out,
+            child,
+            total,
+            &format!("{}{}", rest, branch),
+            &format!("{}{}", rest, next),
+        );
+    }
+}
+
+fn profile_duration() -> &'static Histogram<f64> {
+    static HISTOGRAM: OnceLock<Histogram<f64>> = OnceLock::new();
+    HISTOGRAM.get_or_init(|| {
+        global::meter("parflow-kernel-compat")
+            .f64_histogram("parflow.profile.duration")
+            .with_description("Duration of profiled operations in milliseconds")
+            .init()
+    })
+}
+
+/// Performance profiling inspired by kernel instrumentation
+///
+/// The operation is measured until the profiler is dropped (or [`KernelProfiler::done`] is
+/// called), nested under whichever profilers are open on the same thread, and recorded both in
+/// the [`ProfileReport`] tree and the `parflow.profile.duration` histogram, exported over OTLP
+/// when the binary has enabled it.
+pub struct KernelProfiler {
+    start: Instant,
+    operation: String,
+    module: &'static str,
+    /// Stack depth below this span, restored on drop even if inner spans leaked
+    depth: usize,
+}
+
+impl KernelProfiler {
+    pub fn new(operation: impl Into<String>, module: &'static str) -> Self {
+        let operation = operation.into();
+        let depth = STACK.with(|stack| {
+            let mut stack = stack.borrow_mut();
+            stack.push(format!("{}::{}", module, operation));
+            stack.len() - 1
+        });
+        Self { start: Instant::now(), operation, module, depth }
+    }
+
+    /// Stop measuring now rather than at the end of the scope
+    pub fn done(self) {
+        drop(self);
+    }
+}
+
+impl Drop for KernelProfiler {
+    fn drop(&mut self) {
+        let duration = self.start.elapsed();
+        // A profiler moved to another thread finds a different stack; it still gets recorded,
+        // under whatever that thread had open at the same depth.
+        let path = STACK.with(|stack| {
+            let mut stack = stack.borrow_mut();
+            let end = (self.depth + 1).min(stack.len());
+            let path = stack[..end].to_vec();
+            let depth = self.depth.min(stack.len());
+            stack.truncate(depth);
+            path
+        });
+        if !path.is_empty() {
+            TREE.lock().unwrap_or_else(|e| e.into_inner()).record(&path, duration);
+        }
+
+        log::info!("[KERNEL_PROFILE] {}::{} took {:?}", self.module, self.operation, duration);
+        profile_duration().record(
+            duration.as_secs_f64() * 1000.0,
+            &[
+                KeyValue::new("operation", self.operation.clone()),
+                KeyValue::new("module", self.module),
+            ],
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_spans_aggregate_into_tree() {
+        // Other tests in this binary may profile too, so look only at this test's operations.
+        std::thread::spawn(|| {
+            let outer = KernelProfiler::new("outer", "profile_test");
+            for _ in 0..3 {
+                let _inner = KernelProfiler::new("inner", "profile_test");
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            outer.done();
+        })
+        .join()
+        .unwrap();
+
+        let report = ProfileReport::capture();
+        let outer = report.roots.iter().find(|root| root.name == "profile_test::outer").unwrap();
+        assert_eq!(outer.stats.calls, 1);
+        assert_eq!(outer.children.len(), 1);
+        let inner = &outer.children[0];
+        assert_eq!(inner.name, "profile_test::inner");
+        assert_eq!(inner.stats.calls, 3);
+        assert!(inner.stats.min <= inner.stats.mean() && inner.stats.mean() <= inner.stats.max);
+        assert!(inner.stats.total <= outer.stats.total);
+
+        let rendered = report.render();
+        assert!(rendered.contains("└─ profile_test::inner"));
+        assert!(report
+            .by_operation()
+            .iter()
+            .any(|(name, stats)| { name == "profile_test::inner" && stats.calls == 3 }));
+    }
+}
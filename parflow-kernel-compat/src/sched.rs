@@ -0,0 +1,576 @@
+//! Thread and process affinity, priority and CPU frequency policy
+//!
+//! Linux uses `sched_setaffinity`, `sched_setscheduler`/`setpriority` on every thread of the
+//! target and the cpufreq sysfs files; Windows uses the thread and process affinity masks and
+//! priority classes. Other platforms report `HardwareUnsupported` rather than silently ignoring
+//! the request.
+
+use crate::{KResult, KernelError};
+use std::fmt;
+use std::time::Duration;
+
+/// Scheduling class for [`set_thread_priority`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreadPriority {
+    /// Real-time round-robin (`SCHED_RR` / time critical); usually needs privileges
+    Realtime,
+    /// Ordinary scheduling with a raised share (nice -10 / highest)
+    High,
+    /// The default
+    Normal,
+}
+
+impl fmt::Display for ThreadPriority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Realtime => "realtime",
+            Self::High => "high",
+            Self::Normal => "normal",
+        })
+    }
+}
+
+/// Restrict the calling thread to `core_ids`.
+///
+/// # Errors
+///
+/// `OptimizationError` for an empty set, `SyscallError` if the OS rejects it (e.g. every core is
+/// outside the process's cgroup), `HardwareUnsupported` where affinity cannot be set.
+pub fn set_thread_affinity(core_ids: &[usize]) -> KResult<()> {
+    if core_ids.is_empty() {
+        return Err(KernelError::OptimizationError { reason: "no cores to pin to".to_string() });
+    }
+    imp::set_thread_affinity(core_ids)
+}
+
+/// Move the calling thread to `priority`.
+///
+/// # Errors
+///
+/// `SyscallError` when the OS refuses, typically `Realtime` or `High` without `CAP_SYS_NICE` or
+/// administrator rights, `HardwareUnsupported` where priorities cannot be set.
+pub fn set_thread_priority(priority: ThreadPriority) -> KResult<()> {
+    imp::set_thread_priority(priority)
+}
+
+/// Restrict every thread of process `pid` to `core_ids`.
+///
+/// # Errors
+///
+/// As [`set_thread_affinity`], plus `SyscallError` for a process that does not exist or belongs
+/// to another user.
+pub fn set_process_affinity(pid: u32, core_ids: &[usize]) -> KResult<()> {
+    if core_ids.is_empty() {
+        return Err(KernelError::OptimizationError { reason: "no cores to pin to".to_string() });
+    }
+    imp::set_process_affinity(pid, core_ids)
+}
+
+/// Move every thread of process `pid` to `priority`.
+///
+/// # Errors
+///
+/// As [`set_thread_priority`], plus `SyscallError` for a process that does not exist or belongs
+/// to another user.
+pub fn set_process_priority(pid: u32, priority: ThreadPriority) -> KResult<()> {
+    imp::set_process_priority(pid, priority)
+}
+
+/// Switch every CPU to the cpufreq `governor` (e.g. `performance`), returning how many changed.
+///
+/// # Errors
+///
+/// `SyscallError` when the sysfs files cannot be written, usually for lack of root,
+/// `HardwareUnsupported` without cpufreq (most VMs and containers, and every non-Linux OS).
+pub fn set_cpu_governor(governor: &str) -> KResult<usize> {
+    imp::set_cpu_governor(governor)
+}
+
+/// The cpufreq governor of the first CPU, `None` without cpufreq.
+pub fn cpu_governor() -> Option<String> {
+    imp::cpu_governor()
+}
+
+/// User plus system CPU time process `pid` has consumed so far, across all its threads.
+///
+/// # Errors
+///
+/// `SyscallError` for a process that does not exist or cannot be inspected,
+/// `HardwareUnsupported` where CPU time cannot be read.
+pub fn process_cpu_time(pid: u32) -> KResult<Duration> {
+    imp::process_cpu_time(pid)
+}
+
+/// Priority and affinity of a process, captured so a boost can be undone.
+///
+/// On Linux this is per thread; threads started after [`ProcessSchedule::capture`] keep
+/// whatever they inherited when restored.
+pub struct ProcessSchedule {
+    pid: u32,
+    saved: imp::Saved,
+}
+
+impl ProcessSchedule {
+    /// # Errors
+    ///
+    /// As [`set_process_priority`]
+    pub fn capture(pid: u32) -> KResult<Self> {
+        Ok(Self { pid, saved: imp::capture(pid)? })
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    /// Put the captured priority and affinity back.
+    ///
+    /// # Errors
+    ///
+    /// As [`set_process_priority`]; a process that has exited meanwhile is an error too.
+    pub fn restore(&self) -> KResult<()> {
+        imp::restore(self.pid, &self.saved)
+    }
+}
+
+#[cfg(any(target_os = "linux", windows))]
+fn syscall_error(call: &str) -> KernelError {
+    KernelError::SyscallError { context: format!("{}: {}", call, std::io::Error::last_os_error()) }
+}
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use super::{syscall_error, ThreadPriority};
+    use crate::{KResult, KernelError};
+    use std::time::Duration;
+
+    /// Nice value for [`ThreadPriority::High`]
+    const HIGH_NICE: libc::c_int = -10;
+    const CPU_DIR: &str = "/sys/devices/system/cpu";
+
+    pub(super) fn set_thread_affinity(core_ids: &[usize]) -> KResult<()> {
+        // SAFETY: gettid has no preconditions
+        set_task_affinity(unsafe { libc::gettid() }, core_ids)
+    }
+
+    pub(super) fn set_thread_priority(priority: ThreadPriority) -> KResult<()> {
+        // SAFETY: as above
+        set_task_priority(unsafe { libc::gettid() }, priority)
+    }
+
+    pub(super) fn set_process_affinity(pid: u32, core_ids: &[usize]) -> KResult<()> {
+        for tid in tasks(pid)? {
+            set_task_affinity(tid, core_ids)?;
+        }
+        Ok(())
+    }
+
+    pub(super) fn set_process_priority(pid: u32, priority: ThreadPriority) -> KResult<()> {
+        for tid in tasks(pid)? {
+            set_task_priority(tid, priority)?;
+        }
+        Ok(())
+    }
+
+    pub(super) fn set_cpu_governor(governor: &str) -> KResult<usize> {
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(CPU_DIR).map_err(|_| unsupported())?.flatten() {
+            let name = entry.file_name();
+            let is_cpu = name
+                .to_str()
+                .and_then(|name| name.strip_prefix("cpu"))
+                .is_some_and(|id| !id.is_empty() && id.bytes().all(|b| b.is_ascii_digit()));
+            let file = entry.path().join("cpufreq/scaling_governor");
+            if is_cpu && file.exists() {
+                files.push(file);
+            }
+        }
+        if files.is_empty() {
+            return Err(unsupported());
+        }
+
+        let mut changed = 0;
+        for file in files {
+            if std::fs::read_to_string(&file).is_ok_and(|current| current.trim() == governor) {
+                continue;
+            }
+            std::fs::write(&file, governor).map_err(|e| KernelError::SyscallError {
+                context: format!("writing {}: {}", file.display(), e),
+            })?;
+            changed += 1;
+        }
+        Ok(changed)
+    }
+
+    fn unsupported() -> KernelError {
+        KernelError::HardwareUnsupported { feature: "cpufreq governors".to_string() }
+    }
+
+    pub(super) fn cpu_governor() -> Option<String> {
+        let path = format!("{}/cpu0/cpufreq/scaling_governor", CPU_DIR);
+        Some(std::fs::read_to_string(path).ok()?.trim().to_string())
+    }
+
+    pub(super) fn process_cpu_time(pid: u32) -> KResult<Duration> {
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).map_err(|e| {
+            KernelError::SyscallError { context: format!("process {}: {}", pid, e) }
+        })?;
+        // The command name may contain spaces and parentheses, so count fields from its end;
+        // utime and stime are fields 14 and 15 of proc(5), the 12th and 13th after it
+        let mut fields =
+            stat.rsplit_once(')').map(|(_, rest)| rest).unwrap_or("").split_whitespace();
+        let mut ticks =
+            |field: usize| fields.nth(field).and_then(|value| value.parse::<u64>().ok());
+        let (Some(utime), Some(stime)) = (ticks(11), ticks(0)) else {
+            return Err(KernelError::SyscallError {
+                context: format!("malformed /proc/{}/stat", pid),
+            });
+        };
+        // SAFETY: sysconf has no preconditions
+        let per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) }.max(1) as f64;
+        Ok(Duration::from_secs_f64((utime + stime) as f64 / per_second))
+    }
+
+    pub(super) struct Saved {
+        tasks: Vec<Task>,
+    }
+
+    struct Task {
+        tid: libc::pid_t,
+        policy: libc::c_int,
+        param: libc::sched_param,
+        nice: libc::c_int,
+        affinity: libc::cpu_set_t,
+    }
+
+    pub(super) fn capture(pid: u32) -> KResult<Saved> {
+        let mut saved = Vec::new();
+        for tid in tasks(pid)? {
+            // SAFETY: plain queries on a thread id into fully initialised out-parameters
+            unsafe {
+                let policy = libc::sched_getscheduler(tid);
+                if policy < 0 {
+                    return Err(syscall_error("sched_getscheduler"));
+                }
+                let mut param = libc::sched_param { sched_priority: 0 };
+                if libc::sched_getparam(tid, &mut param) != 0 {
+                    return Err(syscall_error("sched_getparam"));
+                }
+                // -1 is a valid nice value, so errors show only through errno
+                *libc::__errno_location() = 0;
+                let nice = libc::getpriority(libc::PRIO_PROCESS, tid as libc::id_t);
+                if nice == -1 && *libc::__errno_location() != 0 {
+                    return Err(syscall_error("getpriority"));
+                }
+                let mut affinity: libc::cpu_set_t = std::mem::zeroed();
+                let size = std::mem::size_of::<libc::cpu_set_t>();
+                if libc::sched_getaffinity(tid, size, &mut affinity) != 0 {
+                    return Err(syscall_error("sched_getaffinity"));
+                }
+                saved.push(Task { tid, policy, param, nice, affinity });
+            }
+        }
+        Ok(Saved { tasks: saved })
+    }
+
+    pub(super) fn restore(pid: u32, saved: &Saved) -> KResult<()> {
+        let alive = tasks(pid)?;
+        for task in saved.tasks.iter().filter(|task| alive.contains(&task.tid)) {
+            // SAFETY: as in capture, with values the kernel handed out for this thread
+            unsafe {
+                if libc::sched_setscheduler(task.tid, task.policy, &task.param) != 0 {
+                    return Err(syscall_error("sched_setscheduler"));
+                }
+                if libc::setpriority(libc::PRIO_PROCESS, task.tid as libc::id_t, task.nice) != 0 {
+                    return Err(syscall_error("setpriority"));
+                }
+                let size = std::mem::size_of::<libc::cpu_set_t>();
+                if libc::sched_setaffinity(task.tid, size, &task.affinity) != 0 {
+                    return Err(syscall_error("sched_setaffinity"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Thread ids of `pid`; Linux schedules each one separately
+    fn tasks(pid: u32) -> KResult<Vec<libc::pid_t>> {
+        let dir = format!("/proc/{}/task", pid);
+        let entries = std::fs::read_dir(&dir).map_err(|e| KernelError::SyscallError {
+            context: format!("process {}: {}", pid, e),
+        })?;
+        Ok(entries.flatten().filter_map(|entry| entry.file_name().to_str()?.parse().ok()).collect())
+    }
+
+    fn set_task_affinity(tid: libc::pid_t, core_ids: &[usize]) -> KResult<()> {
+        let max = libc::CPU_SETSIZE as usize;
+        if let Some(core) = core_ids.iter().find(|&&core| core >= max) {
+            return Err(KernelError::OptimizationError {
+                reason: format!("core {} is beyond the {} cores Linux can address", core, max),
+            });
+        }
+        // SAFETY: cpu_set_t is plain data and every id was checked to be in range
+        let result = unsafe {
+            let mut set: libc::cpu_set_t = std::mem::zeroed();
+            for &core in core_ids {
+                libc::CPU_SET(core, &mut set);
+            }
+            libc::sched_setaffinity(tid, std::mem::size_of::<libc::cpu_set_t>(), &set)
+        };
+        if result != 0 {
+            return Err(syscall_error("sched_setaffinity"));
+        }
+        Ok(())
+    }
+
+    fn set_task_priority(tid: libc::pid_t, priority: ThreadPriority) -> KResult<()> {
+        let (policy, nice) = match priority {
+            ThreadPriority::Realtime => (libc::SCHED_RR, 0),
+            ThreadPriority::High => (libc::SCHED_OTHER, HIGH_NICE),
+            ThreadPriority::Normal => (libc::SCHED_OTHER, 0),
+        };
+        // SAFETY: plain syscalls on a thread id with a fully initialised sched_param
+        unsafe {
+            let min = libc::sched_get_priority_min(policy);
+            let max = libc::sched_get_priority_max(policy);
+            let param = libc::sched_param { sched_priority: min + (max - min) / 2 };
+            if libc::sched_setscheduler(tid, policy, &param) != 0 {
+                return Err(syscall_error("sched_setscheduler"));
+            }
+            // On Linux the nice value is per thread when addressed by thread id
+            if policy == libc::SCHED_OTHER
+                && libc::setpriority(libc::PRIO_PROCESS, tid as libc::id_t, nice) != 0
+            {
+                return Err(syscall_error("setpriority"));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use super::{syscall_error, ThreadPriority};
+    use crate::{KResult, KernelError};
+    use std::time::Duration;
+    use windows_sys::Win32::Foundation::{CloseHandle, FILETIME, HANDLE};
+    use windows_sys::Win32::System::Threading::{
+        GetCurrentThread, GetPriorityClass, GetProcessAffinityMask, GetProcessTimes, OpenProcess,
+        SetPriorityClass, SetProcessAffinityMask, SetThreadAffinityMask, SetThreadPriority,
+        HIGH_PRIORITY_CLASS, NORMAL_PRIORITY_CLASS, PROCESS_CREATION_FLAGS,
+        PROCESS_QUERY_LIMITED_INFORMATION, PROCESS_SET_INFORMATION, REALTIME_PRIORITY_CLASS,
+        THREAD_PRIORITY_HIGHEST, THREAD_PRIORITY_NORMAL, THREAD_PRIORITY_TIME_CRITICAL,
+    };
+
+    fn mask(core_ids: &[usize]) -> KResult<usize> {
+        let bits = usize::BITS as usize;
+        if let Some(core) = core_ids.iter().find(|&&core| core >= bits) {
+            return Err(KernelError::OptimizationError {
+                reason: format!("core {} is outside the current processor group", core),
+            });
+        }
+        Ok(core_ids.iter().fold(0usize, |mask, &core| mask | (1 << core)))
+    }
+
+    /// Run `f` on a handle to `pid` that may change its scheduling
+    fn with_process<T>(pid: u32, f: impl FnOnce(HANDLE) -> KResult<T>) -> KResult<T> {
+        let access = PROCESS_SET_INFORMATION | PROCESS_QUERY_LIMITED_INFORMATION;
+        // SAFETY: OpenProcess has no preconditions; the handle is closed below
+        let handle = unsafe { OpenProcess(access, 0, pid) };
+        if handle == 0 {
+            return Err(syscall_error("OpenProcess"));
+        }
+        let result = f(handle);
+        // SAFETY: handle came from OpenProcess and is closed once
+        unsafe { CloseHandle(handle) };
+        result
+    }
+
+    pub(super) fn set_process_affinity(pid: u32, core_ids: &[usize]) -> KResult<()> {
+        let mask = mask(core_ids)?;
+        with_process(pid, |handle| {
+            // SAFETY: handle is a live process handle with PROCESS_SET_INFORMATION
+            if unsafe { SetProcessAffinityMask(handle, mask) } == 0 {
+                return Err(syscall_error("SetProcessAffinityMask"));
+            }
+            Ok(())
+        })
+    }
+
+    pub(super) fn set_process_priority(pid: u32, priority: ThreadPriority) -> KResult<()> {
+        let class = match priority {
+            ThreadPriority::Realtime => REALTIME_PRIORITY_CLASS,
+            ThreadPriority::High => HIGH_PRIORITY_CLASS,
+            ThreadPriority::Normal => NORMAL_PRIORITY_CLASS,
+        };
+        with_process(pid, |handle| {
+            // SAFETY: as above
+            if unsafe { SetPriorityClass(handle, class) } == 0 {
+                return Err(syscall_error("SetPriorityClass"));
+            }
+            Ok(())
+        })
+    }
+
+    pub(super) fn set_cpu_governor(_governor: &str) -> KResult<usize> {
+        // Power plans are not per-process settings ParFlow should switch
+        Err(KernelError::HardwareUnsupported { feature: "cpufreq governors".to_string() })
+    }
+
+    pub(super) fn cpu_governor() -> Option<String> {
+        None
+    }
+
+    pub(super) fn process_cpu_time(pid: u32) -> KResult<Duration> {
+        with_process(pid, |handle| {
+            let zero = FILETIME { dwLowDateTime: 0, dwHighDateTime: 0 };
+            let (mut created, mut exited, mut kernel, mut user) = (zero, zero, zero, zero);
+            // SAFETY: handle has query rights and the out-parameters are valid FILETIMEs
+            let ok = unsafe {
+                GetProcessTimes(handle, &mut created, &mut exited, &mut kernel, &mut user)
+            };
+            if ok == 0 {
+                return Err(syscall_error("GetProcessTimes"));
+            }
+            // FILETIME counts 100ns intervals
+            let ticks =
+                |t: FILETIME| (u64::from(t.dwHighDateTime) << 32) | u64::from(t.dwLowDateTime);
+            Ok(Duration::from_nanos((ticks(kernel) + ticks(user)) * 100))
+        })
+    }
+
+    pub(super) struct Saved {
+        class: PROCESS_CREATION_FLAGS,
+        affinity: usize,
+    }
+
+    pub(super) fn capture(pid: u32) -> KResult<Saved> {
+        with_process(pid, |handle| {
+            // SAFETY: handle has query rights and the out-parameters are valid
+            unsafe {
+                let class = GetPriorityClass(handle);
+                if class == 0 {
+                    return Err(syscall_error("GetPriorityClass"));
+                }
+                let (mut affinity, mut system) = (0usize, 0usize);
+                if GetProcessAffinityMask(handle, &mut affinity, &mut system) == 0 {
+                    return Err(syscall_error("GetProcessAffinityMask"));
+                }
+                Ok(Saved { class, affinity })
+            }
+        })
+    }
+
+    pub(super) fn restore(pid: u32, saved: &Saved) -> KResult<()> {
+        with_process(pid, |handle| {
+            // SAFETY: handle has set rights and the values came from capture
+            unsafe {
+                if SetPriorityClass(handle, saved.class) == 0 {
+                    return Err(syscall_error("SetPriorityClass"));
+                }
+                if SetProcessAffinityMask(handle, saved.affinity) == 0 {
+                    return Err(syscall_error("SetProcessAffinityMask"));
+                }
+            }
+            Ok(())
+        })
+    }
+
+    pub(super) fn set_thread_affinity(core_ids: &[usize]) -> KResult<()> {
+        let mask = mask(core_ids)?;
+        // SAFETY: GetCurrentThread returns a pseudo handle valid for this thread
+        if unsafe { SetThreadAffinityMask(GetCurrentThread(), mask) } == 0 {
+            return Err(syscall_error("SetThreadAffinityMask"));
+        }
+        Ok(())
+    }
+
+    pub(super) fn set_thread_priority(priority: ThreadPriority) -> KResult<()> {
+        let level = match priority {
+            ThreadPriority::Realtime => THREAD_PRIORITY_TIME_CRITICAL,
+            ThreadPriority::High => THREAD_PRIORITY_HIGHEST,
+            ThreadPriority::Normal => THREAD_PRIORITY_NORMAL,
+        };
+        // SAFETY: as above
+        if unsafe { SetThreadPriority(GetCurrentThread(), level) } == 0 {
+            return Err(syscall_error("SetThreadPriority"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod imp {
+    use super::ThreadPriority;
+    use crate::{KResult, KernelError};
+    use std::time::Duration;
+
+    pub(super) fn set_thread_affinity(_core_ids: &[usize]) -> KResult<()> {
+        Err(KernelError::HardwareUnsupported { feature: "thread affinity".to_string() })
+    }
+
+    pub(super) fn set_thread_priority(_priority: ThreadPriority) -> KResult<()> {
+        Err(KernelError::HardwareUnsupported { feature: "thread priority".to_string() })
+    }
+
+    pub(super) fn set_process_affinity(_pid: u32, _core_ids: &[usize]) -> KResult<()> {
+        Err(KernelError::HardwareUnsupported { feature: "process affinity".to_string() })
+    }
+
+    pub(super) fn set_process_priority(_pid: u32, _priority: ThreadPriority) -> KResult<()> {
+        Err(KernelError::HardwareUnsupported { feature: "process priority".to_string() })
+    }
+
+    pub(super) fn set_cpu_governor(_governor: &str) -> KResult<usize> {
+        Err(KernelError::HardwareUnsupported { feature: "cpufreq governors".to_string() })
+    }
+
+    pub(super) fn cpu_governor() -> Option<String> {
+        None
+    }
+
+    pub(super) fn process_cpu_time(_pid: u32) -> KResult<Duration> {
+        Err(KernelError::HardwareUnsupported { feature: "process CPU time".to_string() })
+    }
+
+    pub(super) struct Saved;
+
+    pub(super) fn capture(_pid: u32) -> KResult<Saved> {
+        Err(KernelError::HardwareUnsupported { feature: "process priority".to_string() })
+    }
+
+    pub(super) fn restore(_pid: u32, _saved: &Saved) -> KResult<()> {
+        Err(KernelError::HardwareUnsupported { feature: "process priority".to_string() })
+    }
+}
+
+#[cfg(all(test, any(target_os = "linux", windows)))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_affinity_and_priority() {
+        std::thread::spawn(|| {
+            let cores: Vec<usize> = crate::numa::nodes().into_iter().flat_map(|n| n.cpus).collect();
+            set_thread_affinity(&cores).unwrap();
+            assert!(set_thread_affinity(&[]).is_err());
+            set_thread_priority(ThreadPriority::Normal).unwrap();
+        })
+        .join()
+        .unwrap();
+
+        let pid = std::process::id();
+        let schedule = ProcessSchedule::capture(pid).unwrap();
+        let cores: Vec<usize> = crate::numa::nodes().into_iter().flat_map(|n| n.cpus).collect();
+        set_process_affinity(pid, &cores).unwrap();
+        set_process_priority(pid, ThreadPriority::Normal).unwrap();
+        assert!(set_process_priority(u32::MAX, ThreadPriority::Normal).is_err());
+        schedule.restore().unwrap();
+
+        let spent = process_cpu_time(pid).unwrap();
+        let busy = std::time::Instant::now();
+        while busy.elapsed() < Duration::from_millis(50) {
+            std::hint::black_box(busy.elapsed());
+        }
+        assert!(process_cpu_time(pid).unwrap() > spent);
+    }
+}
@@ -0,0 +1,313 @@
+//! Text buffer of the code editor tab
+//!
+//! The buffer is a rope with a cursor and an optional selection, both as char offsets. Every
+//! change is recorded for undo/redo and queued as [`EditOp`]s for the session, which
+//! [`Editor::take_ops`] drains.
+
+use parflow_live_server::EditOp;
+use ropey::Rope;
+use std::ops::Range;
+
+/// Consecutive typing within this many chars undoes as one step
+const UNDO_GROUP: usize = 32;
+
+/// One undoable change: `removed` was replaced by `inserted` at `offset`
+#[derive(Debug, Clone)]
+struct Change {
+    offset: usize,
+    removed: String,
+    inserted: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Editor {
+    text: Rope,
+    cursor: usize,
+    /// Other end of the selection, if there is one
+    anchor: Option<usize>,
+    /// Column kept while moving up and down through shorter lines
+    goal_column: Option<usize>,
+    undo: Vec<Change>,
+    redo: Vec<Change>,
+    ops: Vec<EditOp>,
+}
+
+impl Editor {
+    /// Replace the whole buffer, e.g. with a file opened or changed by someone else. The cursor
+    /// stays where it was as far as possible; history is dropped since it no longer applies.
+    pub fn set_text(&mut self, text: &str) {
+        self.text = Rope::from_str(text);
+        self.cursor = self.cursor.min(self.text.len_chars());
+        self.anchor = None;
+        self.goal_column = None;
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    pub fn text(&self) -> String {
+        self.text.to_string()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.len_chars() == 0
+    }
+
+    /// 0-based line and column of the cursor
+    pub fn cursor(&self) -> (usize, usize) {
+        let line = self.text.char_to_line(self.cursor);
+        (line, self.cursor - self.text.line_to_char(line))
+    }
+
+    /// Selected chars, if any
+    pub fn selection(&self) -> Option<Range<usize>> {
+        let anchor = self.anchor.filter(|anchor| *anchor != self.cursor)?;
+        Some(anchor.min(self.cursor)..anchor.max(self.cursor))
+    }
+
+    pub fn selected_text(&self) -> Option<String> {
+        self.selection().map(|range| self.text.slice(range).to_string())
+    }
+
+    /// Selected columns of `line`, if any
+    pub fn selected_columns(&self, line: usize) -> Option<Range<usize>> {
+        let selection = self.selection()?;
+        if line >= self.text.len_lines() {
+            return None;
+        }
+        let start = self.text.line_to_char(line);
+        let from = selection.start.max(start);
+        let to = selection.end.min(start + self.line_len(line));
+        (from < to).then(|| from - start..to - start)
+    }
+
+    /// Position of `line` and `column`, clamped to the buffer
+    pub fn offset(&self, line: usize, column: usize) -> usize {
+        if line >= self.text.len_lines() {
+            return self.text.len_chars();
+        }
+        self.text.line_to_char(line) + column.min(self.line_len(line))
+    }
+
+    /// Edits made since the last call, oldest first
+    pub fn take_ops(&mut self) -> Vec<EditOp> {
+        std::mem::take(&mut self.ops)
+    }
+
+    /// Type `text` over the selection, or at the cursor.
+    pub fn insert(&mut self, text: &str) {
+        let range = self.selection().unwrap_or(self.cursor..self.cursor);
+        self.replace(range, text);
+    }
+
+    /// Delete the selection, or the char before the cursor.
+    pub fn backspace(&mut self) {
+        match self.selection() {
+            Some(range) => self.replace(range, ""),
+            None if self.cursor > 0 => self.replace(self.cursor - 1..self.cursor, ""),
+            None => {}
+        }
+    }
+
+    /// Delete the selection, or the char under the cursor.
+    pub fn delete(&mut self) {
+        match self.selection() {
+            Some(range) => self.replace(range, ""),
+            None if self.cursor < self.text.len_chars() => {
+                self.replace(self.cursor..self.cursor + 1, "")
+            }
+            None => {}
+        }
+    }
+
+    pub fn undo(&mut self) {
+        if let Some(change) = self.undo.pop() {
+            let inserted = change.offset..change.offset + change.inserted.chars().count();
+            self.apply(inserted, &change.removed);
+            self.redo.push(change);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(change) = self.redo.pop() {
+            let removed = change.offset..change.offset + change.removed.chars().count();
+            self.apply(removed, &change.inserted);
+            self.undo.push(change);
+        }
+    }
+
+    pub fn move_left(&mut self, select: bool) {
+        let to = match self.selection() {
+            Some(range) if !select => range.start,
+            _ => self.cursor.saturating_sub(1),
+        };
+        self.move_to(to, select);
+    }
+
+    pub fn move_right(&mut self, select: bool) {
+        let to = match self.selection() {
+            Some(range) if !select => range.end,
+            _ => (self.cursor + 1).min(self.text.len_chars()),
+        };
+        self.move_to(to, select);
+    }
+
+    pub fn move_up(&mut self, select: bool) {
+        self.move_lines(-1, select);
+    }
+
+    pub fn move_down(&mut self, select: bool) {
+        self.move_lines(1, select);
+    }
+
+    pub fn page_up(&mut self, rows: usize, select: bool) {
+        self.move_lines(-(rows.max(1) as isize), select);
+    }
+
+    pub fn page_down(&mut self, rows: usize, select: bool) {
+        self.move_lines(rows.max(1) as isize, select);
+    }
+
+    /// Start of the line, or of its indentation when the cursor is not already there
+    pub fn home(&mut self, select: bool) {
+        let (line, column) = self.cursor();
+        let start = self.text.line_to_char(line);
+        let indent = self.text.line(line).chars().take_while(|c| *c == ' ' || *c == '\t').count();
+        let to = if column == indent { start } else { start + indent };
+        self.move_to(to, select);
+    }
+
+    pub fn end(&mut self, select: bool) {
+        let (line, _) = self.cursor();
+        let to = self.text.line_to_char(line) + self.line_len(line);
+        self.move_to(to, select);
+    }
+
+    pub fn select_all(&mut self) {
+        self.anchor = Some(0);
+        self.cursor = self.text.len_chars();
+        self.goal_column = None;
+    }
+
+    fn move_to(&mut self, to: usize, select: bool) {
+        if !select {
+            self.anchor = None;
+        } else if self.anchor.is_none() {
+            self.anchor = Some(self.cursor);
+        }
+        self.cursor = to;
+        self.goal_column = None;
+    }
+
+    fn move_lines(&mut self, delta: isize, select: bool) {
+        let (line, column) = self.cursor();
+        let goal = self.goal_column.unwrap_or(column);
+        let last = self.text.len_lines() - 1;
+        let target = line.saturating_add_signed(delta).min(last);
+        let to = if target == line && delta < 0 {
+            0
+        } else if target == line && delta > 0 {
+            self.text.len_chars()
+        } else {
+            self.offset(target, goal)
+        };
+        self.move_to(to, select);
+        self.goal_column = Some(goal);
+    }
+
+    /// Chars on `line`, without its line ending
+    fn line_len(&self, line: usize) -> usize {
+        let slice = self.text.line(line);
+        let mut len = slice.len_chars();
+        for ending in ['\n', '\r'] {
+            if len > 0 && slice.char(len - 1) == ending {
+                len -= 1;
+            }
+        }
+        len
+    }
+
+    fn replace(&mut self, range: Range<usize>, text: &str) {
+        let removed = self.text.slice(range.clone()).to_string();
+        self.apply(range.clone(), text);
+        self.redo.clear();
+        // Merge typing that continues the previous insertion
+        if let Some(last) = self.undo.last_mut() {
+            let typing = removed.is_empty() && last.removed.is_empty() && !text.contains('\n');
+            if typing
+                && range.start == last.offset + last.inserted.chars().count()
+                && last.inserted.chars().count() < UNDO_GROUP
+                && !last.inserted.ends_with('\n')
+            {
+                last.inserted.push_str(text);
+                return;
+            }
+        }
+        self.undo.push(Change { offset: range.start, removed, inserted: text.to_string() });
+    }
+
+    /// Change the buffer and queue the edit for the session, leaving the cursor after `text`
+    fn apply(&mut self, range: Range<usize>, text: &str) {
+        if !range.is_empty() {
+            self.text.remove(range.clone());
+            self.ops.push(EditOp::Delete { offset: range.start, len: range.len() });
+        }
+        if !text.is_empty() {
+            self.text.insert(range.start, text);
+            self.ops.push(EditOp::Insert { offset: range.start, text: text.to_string() });
+        }
+        self.cursor = range.start + text.chars().count();
+        self.anchor = None;
+        self.goal_column = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edits_selections_undo_and_ops() {
+        let mut editor = Editor::default();
+        editor.set_text("fn main() {\n    todo!()\n}\n");
+        editor.move_down(false);
+        editor.home(false);
+        assert_eq!(editor.cursor(), (1, 4));
+        editor.end(true);
+        assert_eq!(editor.selected_text().as_deref(), Some("todo!()"));
+        editor.insert("p");
+        editor.insert("rintln!()");
+        editor.backspace();
+        editor.insert(");");
+        assert_eq!(editor.text(), "fn main() {\n    println!();\n}\n");
+        assert_eq!(
+            editor.take_ops()[..2],
+            [
+                EditOp::Delete { offset: 16, len: 7 },
+                EditOp::Insert { offset: 16, text: "p".to_string() }
+            ]
+        );
+
+        editor.undo();
+        editor.undo();
+        assert_eq!(editor.text(), "fn main() {\n    println!()\n}\n");
+        editor.undo();
+        editor.undo();
+        assert_eq!(editor.text(), "fn main() {\n    todo!()\n}\n");
+        editor.redo();
+        editor.redo();
+        assert_eq!(editor.text(), "fn main() {\n    println!()\n}\n");
+
+        // Vertical moves keep the column through shorter lines
+        editor.move_up(false);
+        editor.move_down(false);
+        assert_eq!(editor.cursor(), (1, 14));
+        editor.page_down(10, false);
+        assert_eq!(editor.cursor(), (3, 0));
+
+        let mut replayed = "fn main() {\n    println!();\n}\n".to_string();
+        for op in editor.take_ops() {
+            op.apply(&mut replayed).unwrap();
+        }
+        assert_eq!(replayed, editor.text());
+    }
+}
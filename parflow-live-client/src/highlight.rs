@@ -0,0 +1,95 @@
+//! Syntax highlighting for the code editor tab
+
+use std::path::Path;
+use std::sync::OnceLock;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{FontStyle, Theme, ThemeSet};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+use tui::style::{Color, Modifier, Style};
+
+const THEME: &str = "base16-ocean.dark";
+
+/// A line as runs of styled text, without its line ending
+pub type StyledLine = Vec<(Style, String)>;
+
+struct Highlighter {
+    syntaxes: SyntaxSet,
+    theme: Theme,
+}
+
+/// Loading the syntaxes takes a while, so it happens once, on first use
+fn highlighter() -> &'static Highlighter {
+    static HIGHLIGHTER: OnceLock<Highlighter> = OnceLock::new();
+    HIGHLIGHTER.get_or_init(|| Highlighter {
+        syntaxes: SyntaxSet::load_defaults_newlines(),
+        theme: ThemeSet::load_defaults().themes.remove(THEME).unwrap_or_default(),
+    })
+}
+
+/// Highlight `code` written in `language`, a `CodeFile::language` such as `rust`. Languages the
+/// server does not know are guessed from the extension of `filename`; code without a syntax is
+/// returned unstyled.
+pub fn highlight(code: &str, language: &str, filename: Option<&str>) -> Vec<StyledLine> {
+    let highlighter = highlighter();
+    let extension = filename.and_then(|name| Path::new(name).extension()?.to_str());
+    let syntax = highlighter
+        .syntaxes
+        .find_syntax_by_token(language)
+        .or_else(|| highlighter.syntaxes.find_syntax_by_extension(extension?));
+    let Some(syntax) = syntax else {
+        return code.lines().map(|line| vec![(Style::default(), line.to_string())]).collect();
+    };
+    let mut lines = HighlightLines::new(syntax, &highlighter.theme);
+    LinesWithEndings::from(code)
+        .map(|line| {
+            let text = line.trim_end_matches(['\n', '\r']);
+            match lines.highlight_line(line, &highlighter.syntaxes) {
+                Ok(ranges) => ranges
+                    .into_iter()
+                    .map(|(style, run)| (convert(style), run.trim_end_matches(['\n', '\r'])))
+                    .filter(|(_, run)| !run.is_empty())
+                    .map(|(style, run)| (style, run.to_string()))
+                    .collect(),
+                Err(_) => vec![(Style::default(), text.to_string())],
+            }
+        })
+        .collect()
+}
+
+fn convert(style: syntect::highlighting::Style) -> Style {
+    let mut converted =
+        Style::default().fg(Color::Rgb(style.foreground.r, style.foreground.g, style.foreground.b));
+    for (font, modifier) in [
+        (FontStyle::BOLD, Modifier::BOLD),
+        (FontStyle::ITALIC, Modifier::ITALIC),
+        (FontStyle::UNDERLINE, Modifier::UNDERLINED),
+    ] {
+        if style.font_style.contains(font) {
+            converted = converted.add_modifier(modifier);
+        }
+    }
+    converted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlights_known_languages_only() {
+        let code = "fn main() {\n    let answer = 42;\n}\n";
+        let rust = highlight(code, "rust", None);
+        assert_eq!(rust.len(), 3);
+        let text: String = rust[1].iter().map(|(_, run)| run.as_str()).collect();
+        assert_eq!(text, "    let answer = 42;");
+        let styles: Vec<Style> = rust.iter().flatten().map(|(style, _)| *style).collect();
+        assert!(styles.iter().any(|style| *style != styles[0]));
+
+        assert_eq!(highlight(code, "unknown", Some("src/main.rs")), rust);
+
+        let plain = highlight(code, "unknown", Some("notes"));
+        assert_eq!(plain[0], [(Style::default(), "fn main() {".to_string())]);
+        assert_eq!(highlight("", "python", None), Vec::<StyledLine>::new());
+    }
+}
@@ -0,0 +1,553 @@
+//! Configurable key bindings for ParFlow's terminal UIs
+//!
+//! Bindings are read from `keys.toml` next to the user config file (see
+//! `parflow_core::config`). Each app has a table mapping actions to key sequences; the
+//! `[global]` table applies to every app and app tables override it per action:
+//!
+//! ```toml
+//! [global]
+//! help = ["f1", "ctrl+h"]
+//!
+//! [live]
+//! quit = ["ctrl+q"]
+//! next_tab = ["ctrl+n", "tab"]
+//!
+//! [dashboard]
+//! tab1 = ["g w"]   # space-separated keys form a multi-key sequence
+//! ```
+//!
+//! Keys are written as `[ctrl+][alt+][shift+]<key>`, where `<key>` is a character or one of
+//! `tab`, `backtab`, `enter`, `esc`, `space`, `backspace`, `up`, `down`, `left`, `right`,
+//! `home`, `end`, `pageup`, `pagedown`, `delete` or `f1`..`f12`.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tui::backend::Backend;
+use tui::layout::Rect;
+use tui::style::{Color, Modifier, Style};
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Borders, Clear, Paragraph};
+use tui::Frame;
+
+pub const KEYS_FILE: &str = "keys.toml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    NextTab,
+    PrevTab,
+    Tab1,
+    Tab2,
+    Tab3,
+    Tab4,
+    Tab5,
+    Help,
+    Quit,
+    /// Run the command typed in the live terminal
+    Submit,
+    Undo,
+    Redo,
+    SelectAll,
+}
+
+impl Action {
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::NextTab => "Next tab",
+            Action::PrevTab => "Previous tab",
+            Action::Tab1 => "Go to tab 1",
+            Action::Tab2 => "Go to tab 2",
+            Action::Tab3 => "Go to tab 3",
+            Action::Tab4 => "Go to tab 4",
+            Action::Tab5 => "Go to tab 5",
+            Action::Help => "Toggle this help",
+            Action::Quit => "Quit",
+            Action::Submit => "Run terminal command, send chat message or break line",
+            Action::Undo => "Undo edit",
+            Action::Redo => "Redo edit",
+            Action::SelectAll => "Select all code",
+        }
+    }
+
+    /// Index for the `TabN` actions
+    pub fn tab_index(self) -> Option<usize> {
+        match self {
+            Action::Tab1 => Some(0),
+            Action::Tab2 => Some(1),
+            Action::Tab3 => Some(2),
+            Action::Tab4 => Some(3),
+            Action::Tab5 => Some(4),
+            _ => None,
+        }
+    }
+}
+
+/// Which UI a keymap is for; also the table name in `keys.toml`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum App {
+    Live,
+    Dashboard,
+}
+
+impl App {
+    pub fn name(self) -> &'static str {
+        match self {
+            App::Live => "live",
+            App::Dashboard => "dashboard",
+        }
+    }
+
+    /// Whether unmodified printable keys are typed as text, so binding them would shadow input
+    fn takes_text_input(self) -> bool {
+        self == App::Live
+    }
+
+    fn defaults(self) -> Vec<(Action, &'static [&'static str])> {
+        let mut defaults: Vec<(Action, &'static [&'static str])> = vec![
+            (Action::NextTab, &["tab"]),
+            (Action::PrevTab, &["backtab"]),
+            (Action::Help, &["f1"]),
+        ];
+        match self {
+            App::Live => defaults.extend([
+                (Action::Quit, &["esc", "ctrl+c"][..]),
+                (Action::Submit, &["enter"][..]),
+                (Action::Undo, &["ctrl+z"][..]),
+                (Action::Redo, &["ctrl+y", "ctrl+shift+z"][..]),
+                (Action::SelectAll, &["ctrl+a"][..]),
+            ]),
+            App::Dashboard => defaults.extend([
+                (Action::Quit, &["q", "esc", "ctrl+c"][..]),
+                (Action::Help, &["?", "f1"][..]),
+                (Action::Tab1, &["1"][..]),
+                (Action::Tab2, &["2"][..]),
+                (Action::Tab3, &["3"][..]),
+                (Action::Tab4, &["4"][..]),
+            ]),
+        }
+        defaults
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    /// Shift is implied by the character for printable keys and by `BackTab`, and terminals
+    /// disagree on whether they report it, so it is dropped there.
+    fn normalized(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let modifiers = match code {
+            KeyCode::Char(_) | KeyCode::BackTab => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        Self {
+            code,
+            modifiers: modifiers
+                & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT),
+        }
+    }
+
+    pub fn from_event(event: &KeyEvent) -> Self {
+        Self::normalized(event.code, event.modifiers)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut parts: Vec<&str> = text.split('+').collect();
+        // A literal `+` key, e.g. "ctrl++"
+        if text.ends_with("++") || text == "+" {
+            parts.retain(|p| !p.is_empty());
+            parts.push("+");
+        }
+        let key =
+            parts.pop().filter(|k| !k.is_empty()).ok_or(format!("empty key in {:?}", text))?;
+        for modifier in parts {
+            modifiers |= match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                other => return Err(format!("unknown modifier {:?} in {:?}", other, text)),
+            };
+        }
+
+        let lower = key.to_ascii_lowercase();
+        let code = match lower.as_str() {
+            "tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "space" => KeyCode::Char(' '),
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            f if f.len() > 1 && f.starts_with('f') && f[1..].parse::<u8>().is_ok() => {
+                let n: u8 = f[1..].parse().unwrap();
+                if !(1..=12).contains(&n) {
+                    return Err(format!("unknown key {:?}", key));
+                }
+                KeyCode::F(n)
+            }
+            _ => {
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) if modifiers.contains(KeyModifiers::SHIFT) => {
+                        KeyCode::Char(c.to_ascii_uppercase())
+                    }
+                    (Some(c), None) => KeyCode::Char(c),
+                    _ => return Err(format!("unknown key {:?}", key)),
+                }
+            }
+        };
+        Ok(Self::normalized(code, modifiers))
+    }
+
+    fn is_text_input(&self) -> bool {
+        matches!(self.code, KeyCode::Char(_)) && self.modifiers.is_empty()
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            f.write_str("Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            f.write_str("Alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            f.write_str("Shift+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("Space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            KeyCode::BackTab => f.write_str("Shift+Tab"),
+            KeyCode::PageUp => f.write_str("PgUp"),
+            KeyCode::PageDown => f.write_str("PgDn"),
+            other => write!(f, "{:?}", other),
+        }
+    }
+}
+
+/// One or more chords pressed in order
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeySequence(pub Vec<KeyChord>);
+
+impl KeySequence {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let chords = text.split_whitespace().map(KeyChord::parse).collect::<Result<Vec<_>, _>>()?;
+        if chords.is_empty() {
+            return Err("empty key binding".to_string());
+        }
+        Ok(Self(chords))
+    }
+}
+
+impl fmt::Display for KeySequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let keys: Vec<String> = self.0.iter().map(ToString::to_string).collect();
+        f.write_str(&keys.join(" "))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Conflict {
+    /// The same keys trigger two actions
+    Duplicate { keys: KeySequence, actions: (Action, Action) },
+    /// `keys` can never fire because it starts with the complete binding `prefix`
+    Shadowed { keys: KeySequence, action: Action, prefix: KeySequence },
+    /// An unmodified character would stop that key from being typed
+    TextInput { keys: KeySequence, action: Action },
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Conflict::Duplicate { keys, actions } => {
+                write!(f, "{} is bound to both {:?} and {:?}", keys, actions.0, actions.1)
+            }
+            Conflict::Shadowed { keys, action, prefix } => {
+                write!(f, "{} ({:?}) is unreachable because {} is bound", keys, action, prefix)
+            }
+            Conflict::TextInput { keys, action } => {
+                write!(f, "{} ({:?}) shadows typing that character", keys, action)
+            }
+        }
+    }
+}
+
+/// What a key press resolved to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyOutcome {
+    Action(Action),
+    /// The press started a multi-key sequence; wait for the next key
+    Pending,
+    /// Not bound; the press should be handled as input
+    Unbound,
+}
+
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    pub app: App,
+    bindings: BTreeMap<Action, Vec<KeySequence>>,
+    pending: Vec<KeyChord>,
+}
+
+type KeysFile = HashMap<String, BTreeMap<Action, Vec<String>>>;
+
+/// Default location of `keys.toml`, next to the user config file.
+pub fn keys_path() -> Option<PathBuf> {
+    parflow_core::config::user_config_path()
+        .and_then(|config| config.parent().map(|dir| dir.join(KEYS_FILE)))
+}
+
+impl Keymap {
+    pub fn defaults(app: App) -> Self {
+        let bindings = app
+            .defaults()
+            .into_iter()
+            .map(|(action, keys)| {
+                let keys =
+                    keys.iter().map(|k| KeySequence::parse(k).expect("valid default")).collect();
+                (action, keys)
+            })
+            .collect();
+        Self { app, bindings, pending: Vec::new() }
+    }
+
+    /// Defaults overridden by `[global]` and then the app's table in `text`.
+    pub fn from_toml(app: App, text: &str) -> Result<Self, String> {
+        let file: KeysFile = toml::from_str(text).map_err(|e| e.to_string())?;
+        let mut keymap = Self::defaults(app);
+        for table in ["global", app.name()] {
+            for (action, keys) in file.get(table).into_iter().flatten() {
+                let keys = keys
+                    .iter()
+                    .map(|k| {
+                        KeySequence::parse(k)
+                            .map_err(|e| format!("[{}] {:?}: {}", table, action, e))
+                    })
+                    .collect::<Result<_, _>>()?;
+                keymap.bindings.insert(*action, keys);
+            }
+        }
+        Ok(keymap)
+    }
+
+    /// Load from `path` (or [`keys_path`]), falling back to the defaults if there is no file.
+    pub fn load(app: App, path: Option<&Path>) -> Result<Self, String> {
+        let path = match path {
+            Some(path) => Some(path.to_path_buf()),
+            None => keys_path(),
+        };
+        match path.filter(|p| p.is_file()) {
+            Some(path) => {
+                let text = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+                Self::from_toml(app, &text).map_err(|e| format!("{}: {}", path.display(), e))
+            }
+            None => Ok(Self::defaults(app)),
+        }
+    }
+
+    /// Like [`Keymap::load`], but reports problems on stderr and keeps going with the defaults.
+    pub fn load_or_default(app: App) -> Self {
+        let keymap = Self::load(app, None).unwrap_or_else(|e| {
+            eprintln!("⚠️  Ignoring key bindings: {}", e);
+            Self::defaults(app)
+        });
+        for conflict in keymap.conflicts() {
+            eprintln!("⚠️  Key binding conflict: {}", conflict);
+        }
+        keymap
+    }
+
+    pub fn bindings(&self) -> impl Iterator<Item = (Action, &[KeySequence])> {
+        self.bindings.iter().map(|(action, keys)| (*action, keys.as_slice()))
+    }
+
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        let all: Vec<(Action, &KeySequence)> =
+            self.bindings.iter().flat_map(|(a, keys)| keys.iter().map(move |k| (*a, k))).collect();
+        let mut conflicts = Vec::new();
+
+        for (i, (action, keys)) in all.iter().enumerate() {
+            if self.app.takes_text_input() && keys.0[0].is_text_input() {
+                conflicts.push(Conflict::TextInput { keys: (*keys).clone(), action: *action });
+            }
+            for (other_action, other) in &all[i + 1..] {
+                if keys == other {
+                    conflicts.push(Conflict::Duplicate {
+                        keys: (*keys).clone(),
+                        actions: (*action, *other_action),
+                    });
+                } else if keys.0.starts_with(&other.0) || other.0.starts_with(&keys.0) {
+                    let (longer, longer_action, prefix) = if keys.0.len() > other.0.len() {
+                        (keys, action, other)
+                    } else {
+                        (other, other_action, keys)
+                    };
+                    conflicts.push(Conflict::Shadowed {
+                        keys: (*longer).clone(),
+                        action: *longer_action,
+                        prefix: (*prefix).clone(),
+                    });
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Feed one key press. Multi-key sequences report `Pending` until complete; a press that
+    /// breaks a sequence is resolved on its own.
+    pub fn handle(&mut self, event: &KeyEvent) -> KeyOutcome {
+        let chord = KeyChord::from_event(event);
+        self.pending.push(chord);
+
+        let mut prefix_match = false;
+        for (action, keys) in &self.bindings {
+            for sequence in keys {
+                if sequence.0 == self.pending {
+                    self.pending.clear();
+                    return KeyOutcome::Action(*action);
+                }
+                prefix_match |= sequence.0.starts_with(&self.pending);
+            }
+        }
+        if prefix_match {
+            return KeyOutcome::Pending;
+        }
+
+        let was_pending = self.pending.len() > 1;
+        self.pending.clear();
+        if was_pending {
+            self.handle(event)
+        } else {
+            KeyOutcome::Unbound
+        }
+    }
+
+    /// `(keys, description)` rows for the help overlay, in action order.
+    pub fn help_lines(&self) -> Vec<(String, &'static str)> {
+        self.bindings
+            .iter()
+            .filter(|(_, keys)| !keys.is_empty())
+            .map(|(action, keys)| {
+                let keys: Vec<String> = keys.iter().map(ToString::to_string).collect();
+                (keys.join(", "), action.description())
+            })
+            .collect()
+    }
+
+    /// Keys for `action` as shown in status bars, e.g. `Tab`.
+    pub fn label(&self, action: Action) -> String {
+        self.bindings
+            .get(&action)
+            .and_then(|keys| keys.first())
+            .map(ToString::to_string)
+            .unwrap_or_else(|| "unbound".to_string())
+    }
+}
+
+/// Draw the help overlay listing the active bindings, centred in `area`.
+pub fn render_help<B: Backend>(f: &mut Frame<B>, area: Rect, keymap: &Keymap) {
+    let lines = keymap.help_lines();
+    let key_width = lines.iter().map(|(keys, _)| keys.chars().count()).max().unwrap_or(0);
+    let text: Vec<Spans> = lines
+        .iter()
+        .map(|(keys, description)| {
+            Spans::from(vec![
+                Span::styled(
+                    format!("{:<width$}  ", keys, width = key_width),
+                    Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(*description),
+            ])
+        })
+        .collect();
+
+    let width = (key_width as u16 + 28).min(area.width);
+    let height = (text.len() as u16 + 2).min(area.height);
+    let popup = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+    let help = Paragraph::new(text).block(
+        Block::default().title(format!("Keys ({})", keymap.app.name())).borders(Borders::ALL),
+    );
+    f.render_widget(Clear, popup);
+    f.render_widget(help, popup);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn test_overrides_sequences_and_conflicts() {
+        let keys = r#"
+            [global]
+            help = ["ctrl+h"]
+
+            [live]
+            quit = ["ctrl+x ctrl+c"]
+            next_tab = ["ctrl+n"]
+            submit = ["enter", "s"]
+        "#;
+        let mut keymap = Keymap::from_toml(App::Live, keys).unwrap();
+
+        let ctrl = |c| press(KeyCode::Char(c), KeyModifiers::CONTROL);
+        assert_eq!(keymap.handle(&ctrl('n')), KeyOutcome::Action(Action::NextTab));
+        assert_eq!(keymap.handle(&ctrl('x')), KeyOutcome::Pending);
+        assert_eq!(keymap.handle(&ctrl('c')), KeyOutcome::Action(Action::Quit));
+        assert_eq!(keymap.handle(&ctrl('x')), KeyOutcome::Pending);
+        assert_eq!(keymap.handle(&ctrl('h')), KeyOutcome::Action(Action::Help));
+        assert_eq!(
+            keymap.handle(&press(KeyCode::BackTab, KeyModifiers::SHIFT)),
+            KeyOutcome::Action(Action::PrevTab)
+        );
+        assert_eq!(
+            keymap.handle(&press(KeyCode::Char('a'), KeyModifiers::NONE)),
+            KeyOutcome::Unbound
+        );
+
+        assert_eq!(
+            keymap.conflicts(),
+            vec![Conflict::TextInput {
+                keys: KeySequence::parse("s").unwrap(),
+                action: Action::Submit
+            }]
+        );
+
+        let clash = Keymap::from_toml(
+            App::Dashboard,
+            "[dashboard]\nhelp = [\"q\"]\ntab1 = [\"g\"]\ntab2 = [\"g w\"]",
+        )
+        .unwrap();
+        let conflicts = clash.conflicts();
+        assert!(conflicts.iter().any(|c| matches!(c, Conflict::Duplicate { .. })));
+        assert!(conflicts
+            .iter()
+            .any(|c| matches!(c, Conflict::Shadowed { action: Action::Tab2, .. })));
+        assert!(Keymap::from_toml(App::Live, "[live]\nquit = [\"hyper+q\"]").is_err());
+    }
+}
@@ -0,0 +1,104 @@
+//! Pane layout of the live client, remembered per user
+//!
+//! The code editor tab shows the shared terminal under the editor; the splitter between them
+//! can be dragged with the mouse. The split and the open tab are saved in `layout.toml` next to
+//! the user config file, with a table per user name:
+//!
+//! ```toml
+//! [alice]
+//! current_tab = 1
+//! editor_percent = 65
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+pub const LAYOUT_FILE: &str = "layout.toml";
+/// Bounds of the editor's share of its tab, so neither pane can be dragged out of sight
+pub const EDITOR_PERCENT: std::ops::RangeInclusive<u16> = 20..=90;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PaneLayout {
+    pub current_tab: usize,
+    /// Height of the editor pane as a percentage of the code editor tab
+    pub editor_percent: u16,
+}
+
+impl Default for PaneLayout {
+    fn default() -> Self {
+        Self { current_tab: 0, editor_percent: 70 }
+    }
+}
+
+type LayoutFile = BTreeMap<String, PaneLayout>;
+
+/// Default location of `layout.toml`, next to the user config file.
+pub fn layout_path() -> Option<PathBuf> {
+    parflow_core::config::user_config_path()
+        .and_then(|config| config.parent().map(|dir| dir.join(LAYOUT_FILE)))
+}
+
+impl PaneLayout {
+    /// The layout `user` last saved to `path` (default [`layout_path`]), or the default one.
+    pub fn load(path: Option<&Path>, user: &str) -> Result<Self, String> {
+        let Some(path) = path.map(Path::to_path_buf).or_else(layout_path) else {
+            return Ok(Self::default());
+        };
+        let mut layout = read(&path)?.remove(user).unwrap_or_default();
+        layout.editor_percent = clamp_editor_percent(layout.editor_percent);
+        Ok(layout)
+    }
+
+    /// Save as `user`'s layout, keeping other users' ones.
+    pub fn save(&self, path: Option<&Path>, user: &str) -> Result<(), String> {
+        let path = path
+            .map(Path::to_path_buf)
+            .or_else(layout_path)
+            .ok_or("no config directory to save the layout in")?;
+        let mut layouts = read(&path)?;
+        layouts.insert(user.to_string(), *self);
+        let text = toml::to_string(&layouts).map_err(|e| e.to_string())?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        }
+        std::fs::write(&path, text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+pub fn clamp_editor_percent(percent: u16) -> u16 {
+    percent.clamp(*EDITOR_PERCENT.start(), *EDITOR_PERCENT.end())
+}
+
+fn read(path: &Path) -> Result<LayoutFile, String> {
+    match std::fs::read_to_string(path) {
+        Ok(text) => toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(LayoutFile::new()),
+        Err(e) => Err(format!("{}: {}", path.display(), e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layouts_are_saved_per_user() {
+        let path = std::env::temp_dir()
+            .join(format!("parflow-layout-{}", std::process::id()))
+            .join(LAYOUT_FILE);
+        assert_eq!(PaneLayout::load(Some(&path), "alice"), Ok(PaneLayout::default()));
+
+        let alice = PaneLayout { current_tab: 1, editor_percent: clamp_editor_percent(5) };
+        assert_eq!(alice.editor_percent, 20);
+        alice.save(Some(&path), "alice").unwrap();
+        PaneLayout { current_tab: 5, editor_percent: 50 }.save(Some(&path), "bob").unwrap();
+
+        assert_eq!(PaneLayout::load(Some(&path), "alice"), Ok(alice));
+        assert_eq!(PaneLayout::load(Some(&path), "bob").unwrap().current_tab, 5);
+        std::fs::write(&path, "[carol]\neditor_percent = 100\n").unwrap();
+        assert_eq!(PaneLayout::load(Some(&path), "carol").unwrap().editor_percent, 90);
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
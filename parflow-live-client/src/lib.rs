@@ -1,29 +1,226 @@
-use crossterm::event::{self, Event, KeyCode};
+use crossterm::event::{
+    self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers,
+    MouseButton, MouseEvent, MouseEventKind,
+};
 use crossterm::execute;
 use crossterm::terminal::{
     disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
 };
+use editor::Editor;
+use highlight::StyledLine;
+use keymap::{Action, App, KeyOutcome, Keymap};
+use layout::PaneLayout;
+use parflow_live_server::{CodeFile, EditOp, LiveUpdate, PresenceState, ShardProgress};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::io;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+use sync::{Backoff, ConnectionState, SessionLink};
+use tokio::sync::{broadcast, mpsc};
 use tui::backend::CrosstermBackend;
-use tui::layout::{Constraint, Direction, Layout};
+use tui::layout::{Constraint, Direction, Layout, Rect};
 use tui::style::{Color, Modifier, Style};
 use tui::text::{Span, Spans};
 use tui::widgets::{Block, Borders, Paragraph, Tabs};
 use tui::Terminal;
 
+pub mod editor;
+pub mod highlight;
+pub mod keymap;
+pub mod layout;
+pub mod sync;
+
+const TAB_TITLES: [&str; TAB_COUNT] =
+    ["Terminal", "Code Editor", "Participants", "Resources", "Compilation", "Chat"];
+const TAB_COUNT: usize = 6;
+const EDITOR_TAB: usize = 1;
+const CHAT_TAB: usize = 5;
+/// Colours remote cursors cycle through, in the order participants are first seen
+const CURSOR_COLORS: [Color; 6] =
+    [Color::Magenta, Color::Cyan, Color::Yellow, Color::LightRed, Color::LightBlue, Color::Green];
+
+/// Where another participant's cursor is, as last reported by the session
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemoteCursor {
+    pub user_name: String,
+    pub filename: String,
+    /// 0-based, like `Editor::cursor`
+    pub line: u32,
+    pub column: u32,
+}
+
+/// Lines the terminal scrolls per mouse wheel step
+const SCROLL_LINES: u16 = 3;
+
+/// Where the last frame put things, for telling what the mouse points at
+#[derive(Debug, Default, Clone, Copy)]
+struct Areas {
+    tabs: Rect,
+    content: Rect,
+    status: Rect,
+    /// Empty unless the code editor tab is open
+    editor: Rect,
+    /// Empty unless the terminal is shown, in its tab or under the editor
+    terminal: Rect,
+}
+
+impl Areas {
+    fn new(screen: Rect, tab: usize, editor_percent: u16) -> Self {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints(
+                [
+                    Constraint::Length(3), // Tabs
+                    Constraint::Min(10),   // Content
+                    Constraint::Length(3), // Status
+                ]
+                .as_ref(),
+            )
+            .split(screen);
+        let mut areas =
+            Self { tabs: chunks[0], content: chunks[1], status: chunks[2], ..Self::default() };
+        match tab {
+            0 => areas.terminal = areas.content,
+            EDITOR_TAB => {
+                let panes = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([
+                        Constraint::Percentage(editor_percent),
+                        Constraint::Percentage(100 - editor_percent),
+                    ])
+                    .split(areas.content);
+                areas.editor = panes[0];
+                areas.terminal = panes[1];
+            }
+            _ => {}
+        }
+        areas
+    }
+
+    /// Whether `row` is on the borders between the editor and the terminal
+    fn on_splitter(&self, column: u16, row: u16) -> bool {
+        self.editor.height > 0
+            && (self.editor.left()..self.editor.right()).contains(&column)
+            && (row + 1 == self.editor.bottom() || row == self.terminal.top())
+    }
+
+    /// Tab whose title is at `column` of the tab bar
+    fn tab_at(&self, column: u16, row: u16) -> Option<usize> {
+        if !contains(self.tabs, column, row) {
+            return None;
+        }
+        // Titles are padded by a space on each side and divided by `|`, inside the border
+        let mut start = self.tabs.left() + 1;
+        for (i, title) in TAB_TITLES.iter().enumerate() {
+            let end = start + title.chars().count() as u16 + 2;
+            if (start..end).contains(&column) {
+                return Some(i);
+            }
+            start = end + 1;
+        }
+        None
+    }
+}
+
+fn contains(area: Rect, column: u16, row: u16) -> bool {
+    (area.left()..area.right()).contains(&column) && (area.top()..area.bottom()).contains(&row)
+}
+
+/// Highlighted editor lines, kept until the content or language changes
+#[derive(Debug, Default)]
+struct HighlightCache {
+    content: String,
+    language: String,
+    lines: Vec<StyledLine>,
+}
+
+/// Edits made in the code editor, for whoever embeds the client to apply to the session with
+/// `LiveServer::apply_edits`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EditPost {
+    pub filename: String,
+    pub ops: Vec<EditOp>,
+}
+
+/// Something typed in the Chat tab, for whoever embeds the client to post to the session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChatPost {
+    Message(String),
+    /// Typed as `@file:line text`
+    Comment {
+        file: String,
+        line: u32,
+        text: String,
+    },
+}
+
+impl ChatPost {
+    pub fn parse(input: &str) -> Self {
+        let input = input.trim();
+        let comment = input.strip_prefix('@').and_then(|rest| {
+            let (anchor, text) = rest.split_once(char::is_whitespace)?;
+            let (file, line) = anchor.rsplit_once(':')?;
+            Some(ChatPost::Comment {
+                file: file.to_string(),
+                line: line.parse().ok().filter(|line| *line > 0)?,
+                text: text.trim().to_string(),
+            })
+        });
+        comment.unwrap_or_else(|| ChatPost::Message(input.to_string()))
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct LiveClient {
     pub server_url: String,
     pub session_id: String,
     pub user_name: String,
     pub current_tab: usize,
+    /// Height of the editor pane as a percentage of the code editor tab
+    pub editor_percent: u16,
     pub terminal_content: String,
-    pub code_editor_content: String,
+    /// Lines the terminal is scrolled back from its latest output
+    pub terminal_scroll: u16,
+    /// File shown in the editor, if one was opened
+    pub editor_file: Option<String>,
+    /// `CodeFile::language` of the editor content, used for highlighting
+    pub editor_language: String,
     pub participants: Vec<String>,
+    /// Presence the session last reported for participants, by name
+    pub presence: BTreeMap<String, PresenceState>,
     pub compilation_status: String,
-    pub cursor_line: u32,
-    pub cursor_column: u32,
+    pub remote_cursors: Vec<RemoteCursor>,
+    /// Per-participant progress of the session's sharded test run
+    pub test_progress: Vec<ShardProgress>,
+    /// Chat messages and code comments, oldest first
+    pub chat: Vec<String>,
+    pub chat_input: String,
+    #[serde(skip)]
+    updates: Option<broadcast::Receiver<LiveUpdate>>,
+    #[serde(skip)]
+    chat_outbox: Option<mpsc::UnboundedSender<ChatPost>>,
+    #[serde(skip)]
+    pub editor: Editor,
+    #[serde(skip)]
+    edit_outbox: Option<mpsc::UnboundedSender<EditPost>>,
+    #[serde(skip)]
+    pub connection: ConnectionState,
+    /// Edits made while offline, oldest first
+    #[serde(skip)]
+    pub pending_edits: Vec<EditPost>,
+    /// The open file as last sent to or received from the session
+    #[serde(skip)]
+    synced: String,
+    #[serde(skip)]
+    link: Option<Box<dyn SessionLink>>,
+    #[serde(skip)]
+    highlighted: HighlightCache,
+    #[serde(skip)]
+    areas: Areas,
+    #[serde(skip)]
+    dragging_splitter: bool,
 }
 
 impl LiveClient {
@@ -33,72 +230,285 @@ impl LiveClient {
             session_id,
             user_name,
             current_tab: 0,
+            editor_percent: PaneLayout::default().editor_percent,
             terminal_content: String::new(),
-            code_editor_content: String::new(),
+            terminal_scroll: 0,
+            editor_file: None,
+            editor_language: "unknown".to_string(),
             participants: vec!["Alice".to_string(), "Bob".to_string()], // Mock participants
+            presence: BTreeMap::new(),
             compilation_status: "Ready".to_string(),
-            cursor_line: 0,
-            cursor_column: 0,
+            remote_cursors: Vec::new(),
+            test_progress: Vec::new(),
+            chat: Vec::new(),
+            chat_input: String::new(),
+            updates: None,
+            chat_outbox: None,
+            editor: Editor::default(),
+            edit_outbox: None,
+            connection: ConnectionState::Connected,
+            pending_edits: Vec::new(),
+            synced: String::new(),
+            link: None,
+            highlighted: HighlightCache::default(),
+            areas: Areas::default(),
+            dragging_splitter: false,
+        }
+    }
+
+    /// Follow a session's updates, as returned by `LiveServer::subscribe_to_updates`.
+    pub fn with_updates(mut self, updates: broadcast::Receiver<LiveUpdate>) -> Self {
+        self.updates = Some(updates);
+        self
+    }
+
+    /// Hand messages typed in the Chat tab to `outbox` instead of only showing them locally.
+    /// They then appear once the session broadcasts them.
+    pub fn with_chat(mut self, outbox: mpsc::UnboundedSender<ChatPost>) -> Self {
+        self.chat_outbox = Some(outbox);
+        self
+    }
+
+    /// Hand edits made to an opened file to `outbox`. Edits to a buffer that is not a session
+    /// file stay local.
+    pub fn with_edits(mut self, outbox: mpsc::UnboundedSender<EditPost>) -> Self {
+        self.edit_outbox = Some(outbox);
+        self
+    }
+
+    /// Connect through `link`, and reconnect through it whenever the connection drops.
+    pub fn with_link(mut self, link: impl SessionLink + 'static) -> Self {
+        self.link = Some(Box::new(link));
+        self.go_offline();
+        if let ConnectionState::Offline { retry_at, .. } = &mut self.connection {
+            *retry_at = Instant::now();
+        }
+        self
+    }
+
+    /// Show `file` in the editor, highlighted as its language.
+    pub fn open_file(&mut self, file: &CodeFile) {
+        self.editor_file = Some(file.filename.clone());
+        self.editor_language = file.language.clone();
+        self.editor = Editor::default();
+        self.editor.set_text(&file.content);
+        self.synced = file.content.clone();
+    }
+
+    pub fn apply_update(&mut self, update: &LiveUpdate) {
+        match update {
+            LiveUpdate::UserJoined { user_name, .. }
+                if *user_name != self.user_name && !self.participants.contains(user_name) =>
+            {
+                self.participants.push(user_name.clone());
+            }
+            LiveUpdate::UserLeft { user_name, .. } => {
+                self.participants.retain(|participant| participant != user_name);
+                self.remote_cursors.retain(|cursor| cursor.user_name != *user_name);
+                self.presence.remove(user_name);
+            }
+            LiveUpdate::PresenceChanged { user_name, state, .. } => {
+                self.presence.insert(user_name.clone(), *state);
+            }
+            // Our own edits come back too, and leave nothing to do
+            LiveUpdate::CodeChanged { filename, content, .. }
+                if self.editor_file.as_ref() == Some(filename)
+                    && *content != self.editor.text() =>
+            {
+                self.editor.set_text(content);
+                self.synced = content.clone();
+            }
+            LiveUpdate::CursorMoved { user_name, filename, position, .. }
+                if *user_name != self.user_name =>
+            {
+                let cursor = RemoteCursor {
+                    user_name: user_name.clone(),
+                    filename: filename.clone(),
+                    line: position.line,
+                    column: position.column,
+                };
+                match self.remote_cursors.iter_mut().find(|c| c.user_name == *user_name) {
+                    Some(existing) => *existing = cursor,
+                    None => self.remote_cursors.push(cursor),
+                }
+            }
+            LiveUpdate::TestRunStarted { shards, .. } => self.test_progress = shards.clone(),
+            LiveUpdate::TestShardProgress { progress, .. } => {
+                match self
+                    .test_progress
+                    .iter_mut()
+                    .find(|shard| shard.participant_id == progress.participant_id)
+                {
+                    Some(shard) => *shard = progress.clone(),
+                    None => self.test_progress.push(progress.clone()),
+                }
+            }
+            LiveUpdate::ChatMessage { user_name, text, .. } => {
+                self.chat.push(format!("{}: {}", user_name, text));
+            }
+            LiveUpdate::CodeComment { user_name, file, line, text, .. } => {
+                self.chat.push(format!("{} on {}:{}: {}", user_name, file, line, text));
+            }
+            _ => {}
+        }
+    }
+
+    fn refresh_highlighting(&mut self) {
+        let cache = &mut self.highlighted;
+        let content = self.editor.text();
+        if cache.content != content || cache.language != self.editor_language {
+            cache.lines =
+                highlight::highlight(&content, &self.editor_language, self.editor_file.as_deref());
+            cache.content = content;
+            cache.language = self.editor_language.clone();
+        }
+    }
+
+    fn drain_updates(&mut self) {
+        let mut pending = Vec::new();
+        if let Some(updates) = &mut self.updates {
+            loop {
+                match updates.try_recv() {
+                    Ok(update) => pending.push(update),
+                    Err(broadcast::error::TryRecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::TryRecvError::Empty) => break,
+                    Err(broadcast::error::TryRecvError::Closed) => {
+                        self.updates = None;
+                        break;
+                    }
+                }
+            }
+        }
+        for update in &pending {
+            self.apply_update(update);
+        }
+        if self.updates.is_none() && self.link.is_some() {
+            self.go_offline();
+        }
+    }
+
+    fn go_offline(&mut self) {
+        if self.connection == ConnectionState::Connected {
+            let delay = Backoff::default().next(Duration::ZERO);
+            self.connection =
+                ConnectionState::Offline { retry_at: Instant::now() + delay, delay, attempts: 0 };
+            self.updates = None;
+            self.edit_outbox = None;
+        }
+    }
+
+    /// Try to reconnect once the backoff delay has passed.
+    fn maintain_connection(&mut self) {
+        let ConnectionState::Offline { retry_at, delay, attempts } = self.connection.clone() else {
+            return;
+        };
+        let Some(link) = &mut self.link else { return };
+        if Instant::now() < retry_at {
+            return;
+        }
+        match link.connect(self.editor_file.as_deref()) {
+            Ok(connection) => {
+                self.updates = Some(connection.updates);
+                self.edit_outbox = Some(connection.edits);
+                self.connection = ConnectionState::Connected;
+                self.resync(connection.file);
+            }
+            Err(_) => {
+                let delay = Backoff::default().next(delay);
+                self.connection = ConnectionState::Offline {
+                    retry_at: Instant::now() + delay,
+                    delay,
+                    attempts: attempts + 1,
+                };
+            }
+        }
+    }
+
+    /// Catch up with the session after reconnecting: merge what was typed offline into the
+    /// session's version of the open file and send the difference, then the edits to other
+    /// files as they were.
+    fn resync(&mut self, file: Option<CodeFile>) {
+        let mut posts = std::mem::take(&mut self.pending_edits);
+        if let (Some(file), Some(filename)) = (file, self.editor_file.clone()) {
+            posts.retain(|post| post.filename != filename);
+            let merged = sync::merge(&self.synced, &self.editor.text(), &file.content);
+            let ops = sync::diff(&file.content, &merged);
+            if merged != self.editor.text() {
+                self.editor.set_text(&merged);
+            }
+            self.editor_language = file.language;
+            self.synced = file.content;
+            if !ops.is_empty() {
+                posts.push(EditPost { filename, ops });
+            }
+        }
+        for post in posts {
+            self.post_edits(post);
         }
     }
 
     pub async fn run(&mut self) -> Result<(), anyhow::Error> {
+        // Load bindings first so warnings about keys.toml are not lost to the alternate screen
+        let mut keymap = Keymap::load_or_default(App::Live);
+        let mut show_help = false;
+        match PaneLayout::load(None, &self.user_name) {
+            Ok(layout) => {
+                self.current_tab = layout.current_tab.min(TAB_COUNT - 1);
+                self.editor_percent = layout.editor_percent;
+            }
+            Err(e) => eprintln!("⚠️  Ignoring saved layout: {}", e),
+        }
+
         // Setup terminal
         enable_raw_mode()?;
         let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen)?;
+        execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
         let backend = CrosstermBackend::new(stdout);
         let mut terminal = Terminal::new(backend)?;
 
         // Main event loop
         let mut running = true;
         while running {
+            self.maintain_connection();
+            self.refresh_highlighting();
+            self.areas = Areas::new(terminal.size()?, self.current_tab, self.editor_percent);
+            let areas = self.areas;
             terminal.draw(|f| {
-                let chunks = Layout::default()
-                    .direction(Direction::Vertical)
-                    .margin(1)
-                    .constraints(
-                        [
-                            Constraint::Length(3), // Tabs
-                            Constraint::Min(10),   // Content
-                            Constraint::Length(3), // Status
-                        ]
-                        .as_ref(),
-                    )
-                    .split(f.size());
-
                 // Tabs
-                let tabs = Tabs::new(vec![
-                    Spans::from("Terminal"),
-                    Spans::from("Code Editor"),
-                    Spans::from("Participants"),
-                    Spans::from("Resources"),
-                    Spans::from("Compilation"),
-                ])
-                .block(Block::default().title("ParFlow Live").borders(Borders::ALL))
-                .select(self.current_tab)
-                .style(Style::default().fg(Color::White))
-                .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD));
-
-                f.render_widget(tabs, chunks[0]);
+                let tabs = Tabs::new(TAB_TITLES.iter().map(|title| Spans::from(*title)).collect())
+                    .block(Block::default().title("ParFlow Live").borders(Borders::ALL))
+                    .select(self.current_tab)
+                    .style(Style::default().fg(Color::White))
+                    .highlight_style(
+                        Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+                    );
+
+                f.render_widget(tabs, areas.tabs);
 
                 // Content based on current tab
                 match self.current_tab {
-                    0 => self.render_terminal_tab(f, chunks[1]),
-                    1 => self.render_code_editor_tab(f, chunks[1]),
-                    2 => self.render_participants_tab(f, chunks[1]),
-                    3 => self.render_resources_tab(f, chunks[1]),
-                    4 => self.render_compilation_tab(f, chunks[1]),
+                    0 => self.render_terminal_tab(f, areas.terminal),
+                    EDITOR_TAB => {
+                        self.render_code_editor_tab(f, areas.editor);
+                        self.render_terminal_tab(f, areas.terminal);
+                    }
+                    2 => self.render_participants_tab(f, areas.content),
+                    3 => self.render_resources_tab(f, areas.content),
+                    4 => self.render_compilation_tab(f, areas.content),
+                    CHAT_TAB => self.render_chat_tab(f, areas.content),
                     _ => {}
                 }
 
                 // Status bar
-                let status = Paragraph::new(Spans::from(vec![
+                let bold = Style::default().add_modifier(Modifier::BOLD);
+                let mut status = vec![
                     Span::raw("Press "),
-                    Span::styled("Tab", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::styled(keymap.label(Action::NextTab), bold),
                     Span::raw(" to switch tabs, "),
-                    Span::styled("Ctrl+C", Style::default().add_modifier(Modifier::BOLD)),
-                    Span::raw(" to exit"),
+                    Span::styled(keymap.label(Action::Quit), bold),
+                    Span::raw(" to exit, "),
+                    Span::styled(keymap.label(Action::Help), bold),
+                    Span::raw(" for help"),
                     Span::raw(" | "),
                     Span::styled(
                         format!("User: {}", self.user_name),
@@ -109,81 +519,225 @@ impl LiveClient {
                         format!("Session: {}", self.session_id),
                         Style::default().fg(Color::Magenta),
                     ),
-                ]));
-                f.render_widget(status, chunks[2]);
+                ];
+                if let ConnectionState::Offline { retry_at, .. } = &self.connection {
+                    let retry = retry_at.saturating_duration_since(Instant::now());
+                    status.push(Span::raw(" | "));
+                    status.push(Span::styled(
+                        format!(
+                            "🔌 Offline, reconnecting in {}s, {} edits pending",
+                            retry.as_secs(),
+                            self.pending_edits.iter().map(|post| post.ops.len()).sum::<usize>()
+                        ),
+                        Style::default().fg(Color::Red),
+                    ));
+                }
+                f.render_widget(Paragraph::new(Spans::from(status)), areas.status);
+
+                if show_help {
+                    keymap::render_help(f, f.size(), &keymap);
+                }
             })?;
 
+            // Redraw for session updates even while no key is pressed
+            self.drain_updates();
+            if !event::poll(Duration::from_millis(200))? {
+                continue;
+            }
+
             // Handle input
-            if let Event::Key(key) = event::read()? {
-                match key.code {
-                    KeyCode::Tab => {
-                        self.current_tab = (self.current_tab + 1) % 5;
-                    }
-                    KeyCode::Char('q') | KeyCode::Esc => {
-                        running = false;
-                    }
-                    KeyCode::Char(c) => {
-                        match self.current_tab {
-                            0 => {
-                                self.terminal_content.push(c);
-                            }
-                            1 => {
-                                self.code_editor_content.push(c);
-                                // Update cursor position
-                                if c == '\n' {
-                                    self.cursor_line += 1;
-                                    self.cursor_column = 0;
-                                } else {
-                                    self.cursor_column += 1;
-                                }
-                            }
-                            _ => {}
-                        }
-                    }
-                    KeyCode::Enter => {
-                        if self.current_tab == 0 {
-                            self.execute_terminal_command().await?;
-                        }
-                    }
-                    KeyCode::Up => {
-                        if self.cursor_line > 0 {
-                            self.cursor_line -= 1;
-                        }
-                    }
-                    KeyCode::Down => {
-                        self.cursor_line += 1;
-                    }
-                    KeyCode::Left => {
-                        if self.cursor_column > 0 {
-                            self.cursor_column -= 1;
-                        }
+            let key = match event::read()? {
+                Event::Key(key) => key,
+                Event::Mouse(mouse) => {
+                    self.handle_mouse(&mouse);
+                    continue;
+                }
+                _ => continue,
+            };
+            let action = match keymap.handle(&key) {
+                KeyOutcome::Action(action) => action,
+                KeyOutcome::Pending => continue,
+                KeyOutcome::Unbound => {
+                    self.handle_input(&key);
+                    continue;
+                }
+            };
+            match action {
+                Action::NextTab => self.current_tab = (self.current_tab + 1) % TAB_COUNT,
+                Action::PrevTab => {
+                    self.current_tab = (self.current_tab + TAB_COUNT - 1) % TAB_COUNT
+                }
+                Action::Quit => running = false,
+                Action::Help => show_help = !show_help,
+                Action::Submit => match self.current_tab {
+                    0 => {
+                        self.execute_terminal_command().await?;
                     }
-                    KeyCode::Right => {
-                        self.cursor_column += 1;
+                    CHAT_TAB => self.send_chat(),
+                    EDITOR_TAB => {
+                        self.editor.insert("\n");
+                        self.send_edits();
                     }
                     _ => {}
+                },
+                Action::Undo if self.current_tab == EDITOR_TAB => {
+                    self.editor.undo();
+                    self.send_edits();
+                }
+                Action::Redo if self.current_tab == EDITOR_TAB => {
+                    self.editor.redo();
+                    self.send_edits();
+                }
+                Action::SelectAll if self.current_tab == EDITOR_TAB => self.editor.select_all(),
+                action => {
+                    if let Some(tab) = action.tab_index() {
+                        self.current_tab = tab;
+                    }
                 }
             }
         }
 
         // Cleanup
         disable_raw_mode()?;
-        execute!(io::stdout(), LeaveAlternateScreen)?;
+        execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture)?;
+        let layout =
+            PaneLayout { current_tab: self.current_tab, editor_percent: self.editor_percent };
+        if let Err(e) = layout.save(None, &self.user_name) {
+            eprintln!("⚠️  Could not save layout: {}", e);
+        }
         Ok(())
     }
 
+    fn handle_mouse(&mut self, mouse: &MouseEvent) {
+        let (column, row) = (mouse.column, mouse.row);
+        let areas = self.areas;
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                if let Some(tab) = areas.tab_at(column, row) {
+                    self.current_tab = tab;
+                } else {
+                    self.dragging_splitter = areas.on_splitter(column, row);
+                }
+            }
+            MouseEventKind::Drag(MouseButton::Left) if self.dragging_splitter => {
+                let content = areas.content;
+                let editor_rows = (row + 1).saturating_sub(content.top()) as u32;
+                let percent = editor_rows * 100 / content.height.max(1) as u32;
+                self.editor_percent = layout::clamp_editor_percent(percent as u16);
+            }
+            MouseEventKind::Up(MouseButton::Left) => self.dragging_splitter = false,
+            MouseEventKind::ScrollUp if contains(areas.terminal, column, row) => {
+                self.scroll_terminal(SCROLL_LINES as i32);
+            }
+            MouseEventKind::ScrollDown if contains(areas.terminal, column, row) => {
+                self.scroll_terminal(-(SCROLL_LINES as i32));
+            }
+            MouseEventKind::ScrollUp if contains(areas.editor, column, row) => {
+                self.editor.page_up(SCROLL_LINES as usize, false);
+            }
+            MouseEventKind::ScrollDown if contains(areas.editor, column, row) => {
+                self.editor.page_down(SCROLL_LINES as usize, false);
+            }
+            _ => {}
+        }
+    }
+
+    /// Scroll the terminal back by `lines`, or forward when negative.
+    fn scroll_terminal(&mut self, lines: i32) {
+        let visible = self.areas.terminal.height.saturating_sub(2) as usize;
+        let history = self.terminal_content.lines().count().saturating_sub(visible);
+        let scroll = (self.terminal_scroll as i32 + lines).max(0) as usize;
+        self.terminal_scroll = scroll.min(history) as u16;
+    }
+
+    /// Keys not bound to an action edit the current tab.
+    fn handle_input(&mut self, key: &KeyEvent) {
+        if self.current_tab == EDITOR_TAB {
+            self.edit(key);
+            return;
+        }
+        match key.code {
+            KeyCode::Char(c) => match self.current_tab {
+                0 => {
+                    self.terminal_content.push(c);
+                    self.terminal_scroll = 0;
+                }
+                CHAT_TAB => self.chat_input.push(c),
+                _ => {}
+            },
+            KeyCode::Backspace if self.current_tab == CHAT_TAB => {
+                self.chat_input.pop();
+            }
+            _ => {}
+        }
+    }
+
+    fn edit(&mut self, key: &KeyEvent) {
+        let select = key.modifiers.contains(KeyModifiers::SHIFT);
+        // Rows of the editor tab: the screen less tabs, status bar, margins and borders
+        let page = crossterm::terminal::size().map_or(20, |(_, rows)| rows.saturating_sub(10));
+        let editor = &mut self.editor;
+        match key.code {
+            KeyCode::Char(c) => editor.insert(c.encode_utf8(&mut [0; 4])),
+            KeyCode::Backspace => editor.backspace(),
+            KeyCode::Delete => editor.delete(),
+            KeyCode::Left => editor.move_left(select),
+            KeyCode::Right => editor.move_right(select),
+            KeyCode::Up => editor.move_up(select),
+            KeyCode::Down => editor.move_down(select),
+            KeyCode::Home => editor.home(select),
+            KeyCode::End => editor.end(select),
+            KeyCode::PageUp => editor.page_up(page as usize, select),
+            KeyCode::PageDown => editor.page_down(page as usize, select),
+            _ => {}
+        }
+        self.send_edits();
+    }
+
+    fn send_edits(&mut self) {
+        let ops = self.editor.take_ops();
+        if let (false, Some(filename)) = (ops.is_empty(), &self.editor_file) {
+            let post = EditPost { filename: filename.clone(), ops };
+            self.post_edits(post);
+        }
+    }
+
+    /// Send edits to the session, or keep them for when the connection is back. Without an
+    /// outbox the buffer is local only.
+    fn post_edits(&mut self, post: EditPost) {
+        if self.connection != ConnectionState::Connected {
+            self.pending_edits.push(post);
+            return;
+        }
+        let Some(outbox) = &self.edit_outbox else { return };
+        match outbox.send(post) {
+            Ok(()) => self.synced = self.editor.text(),
+            Err(mpsc::error::SendError(post)) if self.link.is_some() => {
+                self.go_offline();
+                self.pending_edits.push(post);
+            }
+            Err(_) => {}
+        }
+    }
+
     fn render_terminal_tab(
         &self,
         f: &mut tui::Frame<CrosstermBackend<io::Stdout>>,
         area: tui::layout::Rect,
     ) {
-        let terminal_block = Block::default()
-            .title("Shared Terminal - Type commands and press Enter")
-            .borders(Borders::ALL);
+        let mut title = "Shared Terminal - Type commands and press Enter".to_string();
+        if self.terminal_scroll > 0 {
+            title.push_str(&format!(" (scrolled back {} lines)", self.terminal_scroll));
+        }
+        let terminal_block = Block::default().title(title).borders(Borders::ALL);
 
+        // Show the latest output, less however far the terminal is scrolled back
+        let lines = self.terminal_content.lines().count() as u16;
+        let top = lines.saturating_sub(area.height.saturating_sub(2) + self.terminal_scroll);
         let terminal_content = Paragraph::new(self.terminal_content.as_str())
             .block(terminal_block)
-            .style(Style::default().fg(Color::White));
+            .style(Style::default().fg(Color::White))
+            .scroll((top, 0));
 
         f.render_widget(terminal_content, area);
     }
@@ -193,24 +747,75 @@ impl LiveClient {
         f: &mut tui::Frame<CrosstermBackend<io::Stdout>>,
         area: tui::layout::Rect,
     ) {
+        let (cursor_line, cursor_column) = self.editor.cursor();
         let editor_block = Block::default()
             .title(format!(
-                "Collaborative Code Editor - Line: {}, Column: {}",
-                self.cursor_line, self.cursor_column
+                "Collaborative Code Editor - {} ({}) - Line: {}, Column: {}",
+                self.editor_file.as_deref().unwrap_or("untitled"),
+                self.editor_language,
+                cursor_line,
+                cursor_column
             ))
             .borders(Borders::ALL);
 
-        let editor_content = if self.code_editor_content.is_empty() {
-            "// Start typing your code here...\n// Multiple users can edit simultaneously!\n// \
-             Cursor position is shared in real-time"
-        } else {
-            &self.code_editor_content
-        };
-
-        let editor_paragraph = Paragraph::new(editor_content)
+        if self.editor.is_empty() {
+            let placeholder = Paragraph::new(
+                "// Start typing your code here...\n// Multiple users can edit simultaneously!\n// \
+                 Cursor position is shared in real-time",
+            )
             .block(editor_block)
             .style(Style::default().fg(Color::White));
+            f.render_widget(placeholder, area);
+            return;
+        }
 
+        let lines = &self.highlighted.lines;
+        let line_count = lines.len().max(cursor_line + 1);
+        let gutter = line_count.to_string().len();
+        // Keep the local cursor in view
+        let height = area.height.saturating_sub(2) as usize;
+        let first = (cursor_line + 1).saturating_sub(height);
+        let remote: Vec<(Color, &RemoteCursor)> = self
+            .remote_cursors
+            .iter()
+            .enumerate()
+            .map(|(i, cursor)| (CURSOR_COLORS[i % CURSOR_COLORS.len()], cursor))
+            .filter(|(_, cursor)| self.editor_file.as_ref() == Some(&cursor.filename))
+            .collect();
+
+        let text: Vec<Spans> = (first..line_count.min(first + height))
+            .map(|i| {
+                let on_line: Vec<&(Color, &RemoteCursor)> =
+                    remote.iter().filter(|(_, cursor)| cursor.line as usize == i).collect();
+                let mut marks: Vec<(Range<usize>, Style)> = Vec::new();
+                if let Some(columns) = self.editor.selected_columns(i) {
+                    marks.push((columns, Style::default().bg(Color::DarkGray)));
+                }
+                for (color, cursor) in &on_line {
+                    let column = cursor.column as usize;
+                    marks.push((column..column + 1, Style::default().fg(Color::Black).bg(*color)));
+                }
+                if cursor_line == i {
+                    let cursor = cursor_column..cursor_column + 1;
+                    marks.push((cursor, Style::default().add_modifier(Modifier::REVERSED)));
+                }
+
+                let mut spans = vec![Span::styled(
+                    format!("{:>width$} │ ", i + 1, width = gutter),
+                    Style::default().fg(Color::DarkGray),
+                )];
+                spans.extend(with_marks(lines.get(i).map_or(&[], Vec::as_slice), &marks));
+                for (color, cursor) in on_line {
+                    spans.push(Span::styled(
+                        format!("  ◂ {}", cursor.user_name),
+                        Style::default().fg(*color),
+                    ));
+                }
+                Spans::from(spans)
+            })
+            .collect();
+
+        let editor_paragraph = Paragraph::new(text).block(editor_block);
         f.render_widget(editor_paragraph, area);
     }
 
@@ -224,7 +829,27 @@ impl LiveClient {
         let mut participants_text = String::new();
         participants_text.push_str(&format!("👤 {} (You)\n", self.user_name));
         for participant in &self.participants {
-            participants_text.push_str(&format!("👤 {}\n", participant));
+            match self.presence.get(participant).copied().unwrap_or_default() {
+                PresenceState::Active => {
+                    participants_text.push_str(&format!("🟢 {}\n", participant))
+                }
+                state => participants_text.push_str(&format!("⚪ {} ({})\n", participant, state)),
+            }
+        }
+        if !self.test_progress.is_empty() {
+            participants_text.push_str("\n🧪 Test run:\n");
+            for shard in &self.test_progress {
+                participants_text.push_str(&format!(
+                    "{} {} ({} cores) {}/{} units ✅ {} ❌ {}\n",
+                    progress_bar(shard.completed, shard.units),
+                    shard.participant_name,
+                    shard.cores,
+                    shard.completed,
+                    shard.units,
+                    shard.passed,
+                    shard.failed
+                ));
+            }
         }
         participants_text.push_str(
             "\n💡 Other users can see your cursor position\nand code changes in real-time!",
@@ -288,14 +913,70 @@ impl LiveClient {
         f.render_widget(compilation_content, area);
     }
 
+    fn render_chat_tab(
+        &self,
+        f: &mut tui::Frame<CrosstermBackend<io::Stdout>>,
+        area: tui::layout::Rect,
+    ) {
+        let chat_block =
+            Block::default().title("Chat - Comment on code with @file:line").borders(Borders::ALL);
+
+        // Newest messages that fit above the input line
+        let visible = (area.height as usize).saturating_sub(3);
+        let mut chat_text: Vec<Spans> = self.chat[self.chat.len().saturating_sub(visible)..]
+            .iter()
+            .map(|message| Spans::from(message.as_str()))
+            .collect();
+        chat_text.push(Spans::from(Span::styled(
+            format!("> {}", self.chat_input),
+            Style::default().fg(Color::Yellow),
+        )));
+
+        let chat_content =
+            Paragraph::new(chat_text).block(chat_block).style(Style::default().fg(Color::White));
+
+        f.render_widget(chat_content, area);
+    }
+
+    fn send_chat(&mut self) {
+        let input = std::mem::take(&mut self.chat_input);
+        if input.trim().is_empty() {
+            return;
+        }
+        let post = ChatPost::parse(&input);
+        match &self.chat_outbox {
+            Some(outbox) if outbox.send(post.clone()).is_ok() => {}
+            _ => self.chat.push(match post {
+                ChatPost::Message(text) => format!("{}: {}", self.user_name, text),
+                ChatPost::Comment { file, line, text } => {
+                    format!("{} on {}:{}: {}", self.user_name, file, line, text)
+                }
+            }),
+        }
+    }
+
     async fn execute_terminal_command(&mut self) -> Result<(), anyhow::Error> {
         let command = self.terminal_content.lines().last().unwrap_or("").trim();
 
+        if command == "debug" {
+            let response = match self.link.as_ref().map(|link| link.debug_token()) {
+                Some(Ok(token)) => format!(
+                    "Your debug token, shown only to you: {}\nPut it in the \"token\" of your \
+                     launch.json attach configuration; debug in the shared terminal shows the rest",
+                    token
+                ),
+                Some(Err(e)) => format!("No debug token: {}", e),
+                None => "No debug token: not connected to a session".to_string(),
+            };
+            self.terminal_content.push_str(&format!("\n$ {}\n{}\n$ ", command, response));
+            return Ok(());
+        }
+
         let response = match command {
             "help" => {
                 "Available commands:\n• status - Show session status\n• resources - Show shared \
                  resources\n• compile - Start distributed compilation\n• clear - Clear terminal\n• \
-                 participants - List participants"
+                 participants - List participants\n• debug - Show your debug token"
             }
             "status" => {
                 "Session: ParFlow Live Demo\nParticipants: 3 active\nFiles: 5 Rust \
@@ -327,3 +1008,42 @@ impl LiveClient {
         Ok(())
     }
 }
+
+/// `[#####-----]` for `done` of `total`
+/// Split a highlighted line into spans, drawing `marks` (columns and style) over it, later ones
+/// on top. Marks past the end of the line, like a cursor there, pad it with spaces.
+fn with_marks(line: &[(Style, String)], marks: &[(Range<usize>, Style)]) -> Vec<Span<'static>> {
+    let mut cells: Vec<(Style, char)> =
+        line.iter().flat_map(|(style, run)| run.chars().map(move |c| (*style, c))).collect();
+    for (columns, style) in marks {
+        if columns.end > cells.len() {
+            cells.resize(columns.end, (Style::default(), ' '));
+        }
+        for cell in &mut cells[columns.clone()] {
+            cell.0 = cell.0.patch(*style);
+        }
+    }
+
+    let mut spans = Vec::new();
+    let mut run = String::new();
+    let mut current = None;
+    for (style, c) in cells {
+        if current != Some(style) {
+            if let Some(previous) = current {
+                spans.push(Span::styled(std::mem::take(&mut run), previous));
+            }
+            current = Some(style);
+        }
+        run.push(c);
+    }
+    if let Some(style) = current {
+        spans.push(Span::styled(run, style));
+    }
+    spans
+}
+
+fn progress_bar(done: usize, total: usize) -> String {
+    const WIDTH: usize = 10;
+    let filled = (done.min(total) * WIDTH).checked_div(total).unwrap_or(WIDTH);
+    format!("[{}{}]", "#".repeat(filled), "-".repeat(WIDTH - filled))
+}
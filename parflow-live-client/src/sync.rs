@@ -0,0 +1,319 @@
+//! Staying in step with the session across dropped connections
+//!
+//! The client notices a drop when its update stream closes or an edit cannot be sent. From then
+//! on edits queue up locally while it reconnects through its [`SessionLink`], waiting longer
+//! after each failed attempt. Once back, it fetches the open file and reconciles the queued
+//! edits with whatever the others changed meanwhile using a three-way [`merge`]: the common
+//! ancestor is the file as last sent. Edits that touch text someone else changed are kept, after
+//! that change, rather than dropped.
+
+use crate::EditPost;
+use anyhow::{anyhow, Result};
+use parflow_live_server::{CodeFile, EditOp, LiveServer, LiveUpdate};
+use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc};
+
+/// A live session, as reached by the client
+pub struct Connection {
+    pub updates: broadcast::Receiver<LiveUpdate>,
+    pub edits: mpsc::UnboundedSender<EditPost>,
+    /// Current version of the file open in the editor, if the session has it
+    pub file: Option<CodeFile>,
+}
+
+/// How the client reaches its session again after a drop
+pub trait SessionLink: fmt::Debug + Send {
+    /// Follow the session, fetching `filename` to re-sync the editor with.
+    fn connect(&mut self, filename: Option<&str>) -> Result<Connection>;
+
+    /// This participant's token for the session's debug adapter. It comes over the link rather
+    /// than the shared terminal, so only they see it.
+    fn debug_token(&self) -> Result<String> {
+        Err(anyhow!("this connection cannot issue debug tokens"))
+    }
+}
+
+/// Link to a session of a server in this process. Edits are applied by a task of its own, so
+/// connecting needs a Tokio runtime.
+pub struct ServerLink {
+    pub server: Arc<LiveServer>,
+    pub session_id: String,
+    pub user_id: String,
+}
+
+impl fmt::Debug for ServerLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServerLink")
+            .field("session_id", &self.session_id)
+            .field("user_id", &self.user_id)
+            .finish()
+    }
+}
+
+impl SessionLink for ServerLink {
+    fn connect(&mut self, filename: Option<&str>) -> Result<Connection> {
+        let updates = self
+            .server
+            .subscribe_to_updates(&self.session_id)
+            .ok_or_else(|| anyhow!("session {} has ended", self.session_id))?;
+        let file = filename.and_then(|filename| {
+            self.server.open_file(&self.session_id, &self.user_id, filename).ok()
+        });
+        let (edits, mut posts) = mpsc::unbounded_channel::<EditPost>();
+        let (server, session_id, user_id) =
+            (self.server.clone(), self.session_id.clone(), self.user_id.clone());
+        tokio::spawn(async move {
+            while let Some(post) = posts.recv().await {
+                let _ = server.apply_edits(&session_id, &user_id, &post.filename, &post.ops).await;
+            }
+        });
+        Ok(Connection { updates, edits, file })
+    }
+
+    fn debug_token(&self) -> Result<String> {
+        self.server.debug_token(&self.session_id, &self.user_id)
+    }
+}
+
+/// Exponential delay between reconnection attempts
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self { initial: Duration::from_millis(500), max: Duration::from_secs(30) }
+    }
+}
+
+impl Backoff {
+    /// Delay after an attempt that followed `previous` (zero for the first attempt)
+    pub fn next(&self, previous: Duration) -> Duration {
+        if previous.is_zero() {
+            self.initial
+        } else {
+            (previous * 2).min(self.max)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ConnectionState {
+    #[default]
+    Connected,
+    Offline {
+        retry_at: Instant,
+        /// Wait before this attempt, doubled after each failure
+        delay: Duration,
+        attempts: u32,
+    },
+}
+
+/// Merge `local` and `remote`, two edits of `base`. Each is taken as one changed region; when
+/// the regions overlap, the remote text wins and the local insertion follows it.
+pub fn merge(base: &str, local: &str, remote: &str) -> String {
+    let base: Vec<char> = base.chars().collect();
+    let local: Vec<char> = local.chars().collect();
+    let remote: Vec<char> = remote.chars().collect();
+    let l = changed(&base, &local);
+    let r = changed(&base, &remote);
+    let local_text = &local[l.start..l.start + local.len() + l.len() - base.len()];
+    let remote_text = &remote[r.start..r.start + remote.len() + r.len() - base.len()];
+
+    let mut merged: Vec<char> = Vec::with_capacity(base.len() + local.len() + remote.len());
+    if l.end <= r.start {
+        merged.extend(&base[..l.start]);
+        merged.extend(local_text);
+        merged.extend(&base[l.end..r.start]);
+        merged.extend(remote_text);
+        merged.extend(&base[r.end..]);
+    } else if r.end <= l.start {
+        merged.extend(&base[..r.start]);
+        merged.extend(remote_text);
+        merged.extend(&base[r.end..l.start]);
+        merged.extend(local_text);
+        merged.extend(&base[l.end..]);
+    } else {
+        let end = l.end.max(r.end);
+        merged.extend(&base[..r.start]);
+        merged.extend(remote_text);
+        merged.extend(&base[r.end..end]);
+        merged.extend(local_text);
+        merged.extend(&base[end..]);
+    }
+    merged.into_iter().collect()
+}
+
+/// Edits turning `from` into `to`
+pub fn diff(from: &str, to: &str) -> Vec<EditOp> {
+    let from: Vec<char> = from.chars().collect();
+    let to: Vec<char> = to.chars().collect();
+    let removed = changed(&from, &to);
+    let inserted = &to[removed.start..removed.start + to.len() + removed.len() - from.len()];
+    let mut ops = Vec::new();
+    if !removed.is_empty() {
+        ops.push(EditOp::Delete { offset: removed.start, len: removed.len() });
+    }
+    if !inserted.is_empty() {
+        ops.push(EditOp::Insert { offset: removed.start, text: inserted.iter().collect() });
+    }
+    ops
+}
+
+/// Chars of `base` replaced to get `edited`, between their common prefix and suffix
+fn changed(base: &[char], edited: &[char]) -> Range<usize> {
+    let prefix = base.iter().zip(edited).take_while(|(a, b)| a == b).count();
+    let suffix = base[prefix..]
+        .iter()
+        .rev()
+        .zip(edited[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    prefix..base.len() - suffix
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merges_offline_edits() {
+        let base = "fn main() {\n    run();\n}\n";
+        // Apart: both kept
+        let local = "fn main() {\n    run();\n    done();\n}\n";
+        let remote = "fn main() -> Result<()> {\n    run();\n}\n";
+        let merged = merge(base, local, remote);
+        assert_eq!(merged, "fn main() -> Result<()> {\n    run();\n    done();\n}\n");
+        assert_eq!(merge(base, remote, local), merged);
+
+        // Overlapping: the remote change, then what was typed locally
+        assert_eq!(
+            merge(base, "fn main() {\n    walk();\n}\n", "fn main() {\n    sprint();\n}\n"),
+            "fn main() {\n    sprintwalk();\n}\n"
+        );
+        assert_eq!(merge(base, base, remote), remote);
+
+        let mut replayed = remote.to_string();
+        for op in diff(remote, &merged) {
+            op.apply(&mut replayed).unwrap();
+        }
+        assert_eq!(replayed, merged);
+        assert!(diff(base, base).is_empty());
+
+        let backoff = Backoff::default();
+        let delays: Vec<_> =
+            std::iter::successors(Some(Duration::ZERO), |d| Some(backoff.next(*d)))
+                .take(9)
+                .collect();
+        assert_eq!(delays[1], Duration::from_millis(500));
+        assert_eq!(delays[8], Duration::from_secs(30));
+    }
+
+    #[tokio::test]
+    async fn test_offline_edits_reach_the_session_after_reconnecting() {
+        let server = Arc::new(LiveServer::new());
+        let session = server.create_session("demo").await;
+        let owner =
+            server.join_session(&session, "owner").await.unwrap().participants[0].id.clone();
+        server.handle_code_edit(&session, &owner, "main.rs", "a\nb\nc\n").await.unwrap();
+
+        let link = ServerLink {
+            server: server.clone(),
+            session_id: session.clone(),
+            user_id: owner.clone(),
+        };
+        let mut client =
+            crate::LiveClient::new(String::new(), session.clone(), "owner".to_string())
+                .with_link(link);
+        client.open_file(&server.open_file(&session, &owner, "main.rs").unwrap());
+        client.maintain_connection();
+        assert_eq!(client.connection, ConnectionState::Connected);
+
+        client.go_offline();
+        client.editor.insert("// ");
+        client.send_edits();
+        assert_eq!(client.pending_edits.len(), 1);
+        server.handle_code_edit(&session, &owner, "main.rs", "a\nb\nc\nd\n").await.unwrap();
+
+        client.connection = ConnectionState::Offline {
+            retry_at: Instant::now(),
+            delay: Duration::ZERO,
+            attempts: 0,
+        };
+        client.maintain_connection();
+        assert!(client.pending_edits.is_empty());
+        assert_eq!(client.editor.text(), "// a\nb\nc\nd\n");
+        for _ in 0..100 {
+            if server.open_file(&session, &owner, "main.rs").unwrap().content
+                == client.editor.text()
+            {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("offline edits did not reach the session");
+    }
+
+    #[tokio::test]
+    async fn test_debug_tokens_reach_only_their_participant() {
+        let server = Arc::new(LiveServer::new());
+        let session = server.create_session("demo").await;
+        let owner =
+            server.join_session(&session, "owner").await.unwrap().participants[0].id.clone();
+        let invitation = server
+            .create_invitation(
+                &session,
+                &owner,
+                "guest",
+                parflow_live_server::invites::InviteScope::Edit,
+                parflow_live_server::invites::DEFAULT_TTL,
+            )
+            .unwrap();
+        let guest = server.redeem_invitation(&invitation.token, "guest").await.unwrap();
+        let guest = guest.participants[1].id.clone();
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let debugger = tokio::spawn({
+            let (server, session) = (server.clone(), session.clone());
+            async move { server.serve_debug_adapter(&session, listener, vec!["true".to_string()]).await }
+        });
+        // Runs the server up to its first accept, with the bridge and its endpoint in place
+        tokio::task::yield_now().await;
+
+        let mut tokens = Vec::new();
+        for (name, user_id) in [("owner", &owner), ("guest", &guest)] {
+            let link = ServerLink {
+                server: server.clone(),
+                session_id: session.clone(),
+                user_id: user_id.clone(),
+            };
+            let mut client =
+                crate::LiveClient::new(String::new(), session.clone(), name.to_string())
+                    .with_link(link);
+            client.terminal_content = "debug".to_string();
+            client.execute_terminal_command().await.unwrap();
+            let token = server.debug_token(&session, user_id).unwrap();
+            assert!(client.terminal_content.contains(&token), "{}", client.terminal_content);
+            tokens.push(token);
+        }
+        assert_ne!(tokens[0], tokens[1]);
+
+        let mut updates = server.subscribe_to_updates(&session).unwrap();
+        server.handle_terminal_input(&session, &owner, "debug").await.unwrap();
+        let Ok(LiveUpdate::TerminalOutput { content, .. }) = updates.recv().await else {
+            panic!("debug did not reach the shared terminal");
+        };
+        assert!(content.contains("launch.json"), "{}", content);
+        assert!(tokens.iter().all(|token| !content.contains(token.as_str())), "{}", content);
+
+        let mut offline = crate::LiveClient::new(String::new(), session, "owner".to_string());
+        offline.terminal_content = "debug".to_string();
+        offline.execute_terminal_command().await.unwrap();
+        assert!(offline.terminal_content.contains("No debug token"));
+        debugger.abort();
+    }
+}
@@ -0,0 +1,109 @@
+//! Throughput measurement around a hardware boost
+//!
+//! A generic process exposes no frame counter, so throughput is the CPU time its processes
+//! consume per second of wall time, in percent of one core: for CPU-bound work, more CPU per
+//! second is more work done. Each measurement takes the median of several short windows so a
+//! single hiccup does not decide whether a boost is kept.
+
+use parflow_kernel_compat::sched;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// How a measurement samples its processes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeasureOptions {
+    pub window: Duration,
+    pub rounds: usize,
+    /// A boost whose throughput falls by more than this percentage is rolled back
+    pub regression_tolerance_percent: f64,
+}
+
+impl Default for MeasureOptions {
+    fn default() -> Self {
+        Self { window: Duration::from_millis(500), rounds: 3, regression_tolerance_percent: 5.0 }
+    }
+}
+
+/// Result of one measurement
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Throughput {
+    /// Median over the windows, in percent of one core
+    pub cpu_percent: f64,
+    /// Every window's value, in order
+    pub samples: Vec<f64>,
+}
+
+impl Throughput {
+    /// Relative change from `baseline` in percent, 0 when the baseline is idle.
+    pub fn change_from(&self, baseline: &Throughput) -> f64 {
+        if baseline.cpu_percent <= 0.0 {
+            return 0.0;
+        }
+        (self.cpu_percent - baseline.cpu_percent) / baseline.cpu_percent * 100.0
+    }
+}
+
+/// Sample the combined CPU time of `pids`. Processes that exit or cannot be read stop counting
+/// from that point on.
+pub async fn measure(pids: &[u32], options: &MeasureOptions) -> Throughput {
+    if pids.is_empty() {
+        return Throughput::default();
+    }
+    let mut samples = Vec::with_capacity(options.rounds);
+    for _ in 0..options.rounds.max(1) {
+        let before = cpu_time(pids);
+        let started = Instant::now();
+        tokio::time::sleep(options.window).await;
+        let after = cpu_time(pids);
+        let wall = started.elapsed().as_secs_f64();
+        let spent: Duration = after
+            .iter()
+            .filter_map(|(pid, time)| Some(time.saturating_sub(*before.get(pid)?)))
+            .sum();
+        samples.push(spent.as_secs_f64() / wall * 100.0);
+    }
+    Throughput { cpu_percent: median(&samples), samples }
+}
+
+fn cpu_time(pids: &[u32]) -> std::collections::HashMap<u32, Duration> {
+    pids.iter().filter_map(|&pid| Some((pid, sched::process_cpu_time(pid).ok()?))).collect()
+}
+
+fn median(samples: &[f64]) -> f64 {
+    let mut sorted = samples.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    match sorted.len() {
+        0 => 0.0,
+        n if n % 2 == 1 => sorted[n / 2],
+        n => (sorted[n / 2 - 1] + sorted[n / 2]) / 2.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_measure_sees_own_cpu_time() {
+        assert_eq!(median(&[3.0, 1.0, 2.0]), 2.0);
+        assert_eq!(median(&[4.0, 1.0, 2.0, 3.0]), 2.5);
+
+        let spinning = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(true));
+        let spin = spinning.clone();
+        let worker = std::thread::spawn(move || {
+            while spin.load(std::sync::atomic::Ordering::Relaxed) {
+                std::hint::spin_loop();
+            }
+        });
+        let options = MeasureOptions { window: Duration::from_millis(100), ..Default::default() };
+        let busy = measure(&[std::process::id()], &options).await;
+        spinning.store(false, std::sync::atomic::Ordering::Relaxed);
+        worker.join().unwrap();
+
+        assert_eq!(busy.samples.len(), 3);
+        assert!(busy.cpu_percent > 10.0, "{:?}", busy);
+        assert_eq!(busy.change_from(&Throughput::default()), 0.0);
+        let half = Throughput { cpu_percent: busy.cpu_percent / 2.0, samples: vec![] };
+        assert!((half.change_from(&busy) + 50.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,373 @@
+//! Audit trail of live sessions, with compliance export and retention.
+//!
+//! Each session's records are appended to `<dir>/<session id>.jsonl`. Exports use the same
+//! format: one JSON object per line with these fields (schema version 1):
+//!
+//! | field        | type           | description                                          |
+//! |--------------|----------------|------------------------------------------------------|
+//! | `schema`     | integer        | [`SCHEMA_VERSION`]                                   |
+//! | `session_id` | string         | live session the record belongs to                   |
+//! | `timestamp`  | integer        | Unix time in seconds                                 |
+//! | `actor`      | string or null | participant ID; null for the server itself           |
+//! | `category`   | string         | `session`, `access`, `terminal`, `edit` or `chat`    |
+//! | `event`      | string         | event name, followed by its fields (see below)       |
+//!
+//! | event                | category   | fields                                          |
+//! |----------------------|------------|-------------------------------------------------|
+//! | `session_created`    | `session`  | `project`                                       |
+//! | `joined`             | `access`   | `name`, `scope`, `invitation` (ID or null)      |
+//! | `left`               | `access`   |                                                 |
+//! | `invitation_created` | `access`   | `invitation`, `invitee`, `scope`, `expires_at`  |
+//! | `invitation_revoked` | `access`   | `invitation`                                    |
+//! | `terminal_input`     | `terminal` | `input`                                         |
+//! | `code_edited`        | `edit`     | `filename`, `content` (full file after the edit)|
+//! | `chat_message`       | `chat`     | `message`                                       |
+//! | `code_comment`       | `chat`     | `file`, `line`, `text`                          |
+//! | `files_imported`     | `edit`     | `root`, `files` (number imported)               |
+//! | `file_renamed`       | `edit`     | `from`, `to`                                    |
+//! | `file_deleted`       | `edit`     | `filename`                                      |
+//! | `committed`          | `edit`     | `commit` (ID), `subject`                        |
+//! | `sandbox_changed`    | `access`   | `policy` (see [`SandboxPolicy`])                |
+//!
+//! Records older than the retention period are purged, except for sessions under a legal hold.
+
+use crate::invites::InviteScope;
+use crate::sandbox::SandboxPolicy;
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub const DEFAULT_DIR: &str = ".parflow/live/audit";
+pub const SCHEMA_VERSION: u32 = 1;
+const HOLDS_FILE: &str = "holds.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditCategory {
+    Session,
+    Access,
+    Terminal,
+    Edit,
+    Chat,
+}
+
+impl std::str::FromStr for AuditCategory {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "session" => Ok(AuditCategory::Session),
+            "access" => Ok(AuditCategory::Access),
+            "terminal" => Ok(AuditCategory::Terminal),
+            "edit" => Ok(AuditCategory::Edit),
+            "chat" => Ok(AuditCategory::Chat),
+            other => Err(format!("unknown audit category {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AuditEvent {
+    SessionCreated { project: String },
+    SessionEnded,
+    Joined { name: String, scope: InviteScope, invitation: Option<String> },
+    Left,
+    InvitationCreated { invitation: String, invitee: String, scope: InviteScope, expires_at: u64 },
+    InvitationRevoked { invitation: String },
+    TerminalInput { input: String },
+    CodeEdited { filename: String, content: String },
+    FilesImported { root: String, files: usize },
+    FileRenamed { from: String, to: String },
+    FileDeleted { filename: String },
+    Committed { commit: String, subject: String },
+    ChatMessage { message: String },
+    CodeComment { file: String, line: u32, text: String },
+    SandboxChanged { policy: SandboxPolicy },
+}
+
+impl AuditEvent {
+    pub fn category(&self) -> AuditCategory {
+        match self {
+            AuditEvent::SessionCreated { .. } | AuditEvent::SessionEnded => AuditCategory::Session,
+            AuditEvent::Joined { .. }
+            | AuditEvent::Left
+            | AuditEvent::InvitationCreated { .. }
+            | AuditEvent::InvitationRevoked { .. }
+            | AuditEvent::SandboxChanged { .. } => AuditCategory::Access,
+            AuditEvent::TerminalInput { .. } => AuditCategory::Terminal,
+            AuditEvent::CodeEdited { .. }
+            | AuditEvent::FilesImported { .. }
+            | AuditEvent::FileRenamed { .. }
+            | AuditEvent::FileDeleted { .. }
+            | AuditEvent::Committed { .. } => AuditCategory::Edit,
+            AuditEvent::ChatMessage { .. } | AuditEvent::CodeComment { .. } => AuditCategory::Chat,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub schema: u32,
+    pub session_id: String,
+    pub timestamp: u64,
+    pub actor: Option<String>,
+    pub category: AuditCategory,
+    #[serde(flatten)]
+    pub event: AuditEvent,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hold {
+    pub session_id: String,
+    /// Why the session is kept, e.g. a case or ticket number
+    pub reason: String,
+    pub held_at: u64,
+}
+
+/// Which records to export; empty lists match everything.
+#[derive(Debug, Clone, Default)]
+pub struct ExportFilter {
+    pub sessions: Vec<String>,
+    pub categories: Vec<AuditCategory>,
+    /// Only records at or after this Unix timestamp
+    pub since: Option<u64>,
+}
+
+impl ExportFilter {
+    fn matches(&self, record: &AuditRecord) -> bool {
+        (self.sessions.is_empty() || self.sessions.contains(&record.session_id))
+            && (self.categories.is_empty() || self.categories.contains(&record.category))
+            && self.since.is_none_or(|since| record.timestamp >= since)
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct PurgeReport {
+    pub records_removed: usize,
+    /// Sessions whose whole log was removed
+    pub sessions_removed: Vec<String>,
+    /// Sessions skipped because of a hold
+    pub held: Vec<String>,
+}
+
+pub struct AuditLog {
+    dir: PathBuf,
+}
+
+impl AuditLog {
+    pub fn open(dir: &Path) -> io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        Ok(Self { dir: dir.to_path_buf() })
+    }
+
+    fn session_path(&self, session_id: &str) -> PathBuf {
+        self.dir.join(format!("{}.jsonl", session_id))
+    }
+
+    pub fn record(
+        &self,
+        session_id: &str,
+        actor: Option<&str>,
+        event: AuditEvent,
+    ) -> io::Result<()> {
+        let record = AuditRecord {
+            schema: SCHEMA_VERSION,
+            session_id: session_id.to_string(),
+            timestamp: crate::invites::now(),
+            actor: actor.map(str::to_string),
+            category: event.category(),
+            event,
+        };
+        let mut line = serde_json::to_vec(&record)?;
+        line.push(b'\n');
+        // A single write per record keeps lines intact when several sessions share a process
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.session_path(session_id))?
+            .write_all(&line)
+    }
+
+    /// Sessions with an audit log, sorted by ID.
+    pub fn sessions(&self) -> io::Result<Vec<String>> {
+        let mut sessions: Vec<String> = std::fs::read_dir(&self.dir)?
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                (path.extension()? == "jsonl")
+                    .then(|| path.file_stem()?.to_str().map(str::to_string))?
+            })
+            .collect();
+        sessions.sort();
+        Ok(sessions)
+    }
+
+    /// Records of one session, oldest first. Lines that do not parse are skipped.
+    pub fn read(&self, session_id: &str) -> io::Result<Vec<AuditRecord>> {
+        let file = match std::fs::File::open(self.session_path(session_id)) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        Ok(io::BufReader::new(file)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| serde_json::from_str(&line).ok())
+            .collect())
+    }
+
+    /// Write matching records as JSONL and return how many were written.
+    pub fn export(&self, filter: &ExportFilter, out: &mut dyn Write) -> io::Result<usize> {
+        let mut written = 0;
+        for session in self.sessions()? {
+            if !filter.sessions.is_empty() && !filter.sessions.contains(&session) {
+                continue;
+            }
+            for record in self.read(&session)?.iter().filter(|r| filter.matches(r)) {
+                serde_json::to_writer(&mut *out, record)?;
+                out.write_all(b"\n")?;
+                written += 1;
+            }
+        }
+        Ok(written)
+    }
+
+    pub fn holds(&self) -> io::Result<Vec<Hold>> {
+        match std::fs::read(self.dir.join(HOLDS_FILE)) {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn save_holds(&self, holds: &[Hold]) -> io::Result<()> {
+        std::fs::write(self.dir.join(HOLDS_FILE), serde_json::to_vec_pretty(holds)?)
+    }
+
+    /// Exempt a session from purging until released. Replaces an existing hold's reason.
+    pub fn hold(&self, session_id: &str, reason: &str) -> io::Result<()> {
+        let mut holds = self.holds()?;
+        holds.retain(|h| h.session_id != session_id);
+        holds.push(Hold {
+            session_id: session_id.to_string(),
+            reason: reason.to_string(),
+            held_at: crate::invites::now(),
+        });
+        self.save_holds(&holds)
+    }
+
+    /// Returns whether the session was held.
+    pub fn release(&self, session_id: &str) -> io::Result<bool> {
+        let mut holds = self.holds()?;
+        let before = holds.len();
+        holds.retain(|h| h.session_id != session_id);
+        self.save_holds(&holds)?;
+        Ok(holds.len() != before)
+    }
+
+    /// Remove records older than `retention` from every session that is not on hold.
+    pub fn purge(&self, retention: Duration, now: u64) -> io::Result<PurgeReport> {
+        let cutoff = now.saturating_sub(retention.as_secs());
+        let holds = self.holds()?;
+        let mut report = PurgeReport::default();
+
+        for session in self.sessions()? {
+            if holds.iter().any(|h| h.session_id == session) {
+                report.held.push(session);
+                continue;
+            }
+            let records = self.read(&session)?;
+            let kept: Vec<&AuditRecord> =
+                records.iter().filter(|r| r.timestamp >= cutoff).collect();
+            if kept.len() == records.len() {
+                continue;
+            }
+            report.records_removed += records.len() - kept.len();
+
+            let path = self.session_path(&session);
+            if kept.is_empty() {
+                std::fs::remove_file(&path)?;
+                report.sessions_removed.push(session);
+                continue;
+            }
+            let mut contents = Vec::new();
+            for record in kept {
+                serde_json::to_writer(&mut contents, record)?;
+                contents.push(b'\n');
+            }
+            let tmp = path.with_extension("jsonl.tmp");
+            std::fs::write(&tmp, contents)?;
+            std::fs::rename(&tmp, &path)?;
+        }
+        Ok(report)
+    }
+}
+
+/// Purge expired records every `interval` for as long as the returned task is alive.
+pub fn spawn_retention(
+    log: Arc<AuditLog>,
+    retention: Duration,
+    interval: Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match log.purge(retention, crate::invites::now()) {
+                Ok(report) if report.records_removed > 0 => tracing::info!(
+                    removed = report.records_removed,
+                    held_sessions = report.held.len(),
+                    "🧹 Audit retention purged records"
+                ),
+                Ok(_) => {}
+                Err(e) => tracing::error!(error = %e, "❌ Audit retention failed"),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_and_retention_with_holds() {
+        let dir = std::env::temp_dir().join(format!("parflow-audit-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let log = AuditLog::open(&dir).unwrap();
+
+        for session in ["s1", "s2"] {
+            log.record(session, None, AuditEvent::SessionCreated { project: "demo".into() })
+                .unwrap();
+            log.record(session, Some("p1"), AuditEvent::TerminalInput { input: "ls".into() })
+                .unwrap();
+        }
+
+        let mut out = Vec::new();
+        let filter =
+            ExportFilter { categories: vec![AuditCategory::Terminal], ..Default::default() };
+        assert_eq!(log.export(&filter, &mut out).unwrap(), 2);
+        let first: serde_json::Value =
+            serde_json::from_slice(out.split(|b| *b == b'\n').next().unwrap()).unwrap();
+        assert_eq!(first["schema"], SCHEMA_VERSION);
+        assert_eq!(first["category"], "terminal");
+        assert_eq!(first["event"], "terminal_input");
+        assert_eq!(first["input"], "ls");
+
+        log.hold("s1", "case 42").unwrap();
+        let later = crate::invites::now() + 10 * 86_400;
+        let report = log.purge(Duration::from_secs(86_400), later).unwrap();
+        assert_eq!(report.records_removed, 2);
+        assert_eq!(report.sessions_removed, vec!["s2".to_string()]);
+        assert_eq!(report.held, vec!["s1".to_string()]);
+        assert_eq!(log.read("s1").unwrap().len(), 2);
+
+        assert!(log.release("s1").unwrap());
+        log.purge(Duration::from_secs(86_400), later).unwrap();
+        assert!(log.sessions().unwrap().is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
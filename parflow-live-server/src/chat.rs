@@ -0,0 +1,179 @@
+//! Chat and inline code comments in live sessions
+//!
+//! Messages are kept in the session, oldest first, up to [`CHAT_HISTORY`], so participants who
+//! join late can catch up. A code comment is a message anchored to a line of a file. Every
+//! participant can chat, including read-only ones.
+
+use crate::audit::AuditEvent;
+use crate::{invites, LiveServer, LiveUpdate};
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Messages kept per session; older ones are dropped
+pub const CHAT_HISTORY: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatEntry {
+    pub id: String,
+    pub author_id: String,
+    pub author_name: String,
+    pub text: String,
+    /// Unix time in seconds
+    pub sent_at: u64,
+    /// Line the message comments on; `None` for plain chat
+    pub anchor: Option<CodeAnchor>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CodeAnchor {
+    pub file: String,
+    /// 1-based
+    pub line: u32,
+}
+
+impl LiveServer {
+    pub fn send_chat_message(
+        &self,
+        session_id: &str,
+        user_id: &str,
+        text: &str,
+    ) -> Result<ChatEntry> {
+        self.post(session_id, user_id, text, None)
+    }
+
+    /// Comment on `line` (1-based) of `file`.
+    pub fn comment_on_code(
+        &self,
+        session_id: &str,
+        user_id: &str,
+        file: &str,
+        line: u32,
+        text: &str,
+    ) -> Result<ChatEntry> {
+        if line == 0 {
+            bail!("lines are numbered from 1");
+        }
+        self.post(session_id, user_id, text, Some(CodeAnchor { file: file.to_string(), line }))
+    }
+
+    fn post(
+        &self,
+        session_id: &str,
+        user_id: &str,
+        text: &str,
+        anchor: Option<CodeAnchor>,
+    ) -> Result<ChatEntry> {
+        let text = text.trim();
+        if text.is_empty() {
+            bail!("empty message");
+        }
+        let mut session =
+            self.sessions.get_mut(session_id).ok_or_else(|| anyhow!("unknown session"))?;
+        let author = session
+            .participants
+            .iter()
+            .find(|p| p.id == user_id)
+            .ok_or_else(|| anyhow!("not a participant of this session"))?;
+        let entry = ChatEntry {
+            id: Uuid::new_v4().to_string(),
+            author_id: user_id.to_string(),
+            author_name: author.name.clone(),
+            text: text.to_string(),
+            sent_at: invites::now(),
+            anchor,
+        };
+        session.chat.push(entry.clone());
+        let overflow = session.chat.len().saturating_sub(CHAT_HISTORY);
+        session.chat.drain(..overflow);
+        drop(session);
+
+        let (event, update) = match &entry.anchor {
+            None => (
+                AuditEvent::ChatMessage { message: entry.text.clone() },
+                LiveUpdate::ChatMessage {
+                    id: entry.id.clone(),
+                    user_name: entry.author_name.clone(),
+                    text: entry.text.clone(),
+                },
+            ),
+            Some(CodeAnchor { file, line }) => (
+                AuditEvent::CodeComment {
+                    file: file.clone(),
+                    line: *line,
+                    text: entry.text.clone(),
+                },
+                LiveUpdate::CodeComment {
+                    id: entry.id.clone(),
+                    user_name: entry.author_name.clone(),
+                    file: file.clone(),
+                    line: *line,
+                    text: entry.text.clone(),
+                },
+            ),
+        };
+        self.audit(session_id, Some(user_id), event);
+        if let Some(tx) = self.broadcast_senders.get(session_id) {
+            let _ = tx.send(update);
+        }
+        self.mark_active(session_id, user_id);
+        Ok(entry)
+    }
+
+    /// Messages and comments of a session, oldest first
+    pub fn chat_history(&self, session_id: &str) -> Vec<ChatEntry> {
+        self.sessions.get(session_id).map(|s| s.chat.clone()).unwrap_or_default()
+    }
+
+    /// Comments on `file`, by line and then oldest first
+    pub fn code_comments(&self, session_id: &str, file: &str) -> Vec<ChatEntry> {
+        let mut comments: Vec<ChatEntry> = self
+            .chat_history(session_id)
+            .into_iter()
+            .filter(|entry| entry.anchor.as_ref().is_some_and(|anchor| anchor.file == file))
+            .collect();
+        comments.sort_by_key(|entry| entry.anchor.as_ref().map(|anchor| anchor.line));
+        comments
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_chat_and_code_comments_are_kept_and_broadcast() {
+        let server = LiveServer::new();
+        let session = server.create_session("demo").await;
+        let owner =
+            server.join_session(&session, "owner").await.unwrap().participants[0].id.clone();
+        let mut updates = server.subscribe_to_updates(&session).unwrap();
+
+        server.send_chat_message(&session, &owner, "  shall we fix the parser?  ").unwrap();
+        server.comment_on_code(&session, &owner, "src/parser.rs", 42, "off by one").unwrap();
+        server.comment_on_code(&session, &owner, "src/parser.rs", 7, "unused import").unwrap();
+        assert!(server.send_chat_message(&session, &owner, " ").is_err());
+        assert!(server.send_chat_message(&session, "stranger", "hi").is_err());
+        assert!(server.comment_on_code(&session, &owner, "src/parser.rs", 0, "?").is_err());
+
+        assert!(matches!(
+            updates.recv().await.unwrap(),
+            LiveUpdate::ChatMessage { text, user_name, .. }
+                if text == "shall we fix the parser?" && user_name == "owner"
+        ));
+        assert!(matches!(updates.recv().await.unwrap(), LiveUpdate::CodeComment { line: 42, .. }));
+        let lines: Vec<_> = server
+            .code_comments(&session, "src/parser.rs")
+            .iter()
+            .map(|comment| comment.anchor.as_ref().unwrap().line)
+            .collect();
+        assert_eq!(lines, [7, 42]);
+
+        for i in 0..CHAT_HISTORY {
+            server.send_chat_message(&session, &owner, &i.to_string()).unwrap();
+        }
+        let history = server.chat_history(&session);
+        assert_eq!(history.len(), CHAT_HISTORY);
+        assert_eq!(history[0].text, "0");
+    }
+}
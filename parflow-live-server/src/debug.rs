@@ -0,0 +1,793 @@
+//! Debug Adapter Protocol bridge for live sessions
+//!
+//! Each session can run one debug adapter, such as `debugpy` or `lldb-dap`, against its project.
+//! Participants connect their editors to the bridge as a DAP server (VS Code's `debugServer`
+//! launch setting) and identify themselves in the `launch` or `attach` arguments with
+//! `"token": "<debug token>"`, a secret the server issues each participant through
+//! [`LiveServer::debug_token`]. Live clients fetch it over their own link to the session rather
+//! than the shared terminal. The bridge shares the one adapter between them:
+//!
+//! - the first `initialize`, `launch`/`attach` and `configurationDone` go to the adapter; later
+//!   participants get the cached capabilities and synthetic replies, so they join the running
+//!   debuggee instead of starting another
+//! - breakpoints are kept per participant and the adapter gets their union, so one participant
+//!   setting breakpoints in a file does not clear another's
+//! - events, such as `stopped` or `output`, go to every connected editor and to the session's
+//!   subscribers as [`LiveUpdate::DebugEvent`]
+//! - read-only and agent-only participants can inspect the debuggee but not set breakpoints,
+//!   step or evaluate expressions, and sessions that restrict commands cannot `launch`
+//!
+//! The adapter stops when the last participant disconnects or the session ends.
+
+use crate::{LiveServer, LiveSession, LiveUpdate};
+use anyhow::{anyhow, bail, Context, Result};
+use dashmap::DashMap;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+};
+use tokio::net::TcpListener;
+use tokio::process::{ChildStdin, Command};
+use tokio::sync::{broadcast, mpsc, oneshot, Mutex};
+use tracing::{info, warn};
+
+/// Requests that change what the debuggee does, so they need the edit scope. `evaluate` and
+/// `completions` run code in it.
+const CONTROL: &[&str] = &[
+    "launch",
+    "evaluate",
+    "completions",
+    "setBreakpoints",
+    "setFunctionBreakpoints",
+    "setExceptionBreakpoints",
+    "setDataBreakpoints",
+    "setInstructionBreakpoints",
+    "continue",
+    "next",
+    "stepIn",
+    "stepOut",
+    "stepBack",
+    "reverseContinue",
+    "pause",
+    "restart",
+    "restartFrame",
+    "goto",
+    "setVariable",
+    "setExpression",
+    "writeMemory",
+    "terminate",
+    "terminateThreads",
+];
+
+/// Largest message accepted, so a `Content-Length` cannot make the bridge allocate at will
+pub const MAX_MESSAGE: usize = 16 * 1024 * 1024;
+
+/// Adapter command for a project in `language`, when there is a usual one
+pub fn default_adapter(language: &str) -> Option<Vec<String>> {
+    let command: &[&str] = match language {
+        "python" => &["python3", "-m", "debugpy.adapter"],
+        "rust" | "c" | "cpp" => &["lldb-dap"],
+        "go" => &["dlv", "dap"],
+        _ => return None,
+    };
+    Some(command.iter().map(|arg| arg.to_string()).collect())
+}
+
+/// Read one `Content-Length` framed message; `None` at end of stream
+pub async fn read_message<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = Some(value.trim().parse::<usize>()?);
+            }
+        }
+    }
+    let length = length.context("message without Content-Length")?;
+    if length > MAX_MESSAGE {
+        bail!("message of {} bytes is larger than the {} allowed", length, MAX_MESSAGE);
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+pub async fn write_message<W: AsyncWrite + Unpin>(writer: &mut W, message: &Value) -> Result<()> {
+    let body = serde_json::to_vec(message)?;
+    writer.write_all(format!("Content-Length: {}\r\n\r\n", body.len()).as_bytes()).await?;
+    writer.write_all(&body).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+struct Backend {
+    stdin: ChildStdin,
+    /// Kills the adapter when the bridge is done with it
+    _child: tokio::process::Child,
+}
+
+#[derive(Default)]
+struct State {
+    capabilities: Option<Value>,
+    launched: bool,
+    configured: bool,
+    /// Source path, then client, then the breakpoints that client asked for
+    breakpoints: HashMap<String, BTreeMap<u64, Vec<Value>>>,
+}
+
+struct Client {
+    sender: mpsc::UnboundedSender<Value>,
+    /// Set once the client identifies itself in `launch` or `attach`
+    participant: Option<String>,
+}
+
+/// The debug adapter of one live session and the editors connected to it
+pub struct DebugBridge {
+    session_id: String,
+    adapter: Vec<String>,
+    /// Where editors connect, once [`LiveServer::serve_debug_adapter`] listens
+    endpoint: OnceLock<SocketAddr>,
+    sessions: Arc<DashMap<String, LiveSession>>,
+    senders: Arc<DashMap<String, broadcast::Sender<LiveUpdate>>>,
+    /// Separate from `state` so replies to the adapter's own requests never wait on a request
+    /// in flight
+    backend: Mutex<Option<Backend>>,
+    /// Counts adapter starts, so the reader of a stopped adapter leaves its successor alone
+    generation: AtomicU64,
+    state: Mutex<State>,
+    clients: DashMap<u64, Client>,
+    /// Debug tokens to the participants they were issued to
+    tokens: DashMap<String, String>,
+    /// Requests sent to the adapter, by sequence number, waiting for a response
+    pending: DashMap<i64, oneshot::Sender<Value>>,
+    next_seq: AtomicI64,
+    next_client: AtomicU64,
+}
+
+impl DebugBridge {
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    pub fn endpoint(&self) -> Option<SocketAddr> {
+        self.endpoint.get().copied()
+    }
+
+    /// Editors connected right now
+    pub fn clients(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// The secret `participant` identifies themselves with, issued on first use
+    pub fn token(&self, participant: &str) -> Result<String> {
+        let session = self.sessions.get(&self.session_id).context("the session has ended")?;
+        if !session.participants.iter().any(|p| p.id == participant) {
+            bail!("not a participant of this session");
+        }
+        drop(session);
+        let issued = self.tokens.iter().find(|entry| entry.value() == participant);
+        if let Some(entry) = issued {
+            return Ok(entry.key().clone());
+        }
+        let token = uuid::Uuid::new_v4().to_string();
+        self.tokens.insert(token.clone(), participant.to_string());
+        Ok(token)
+    }
+
+    fn seq(&self) -> i64 {
+        self.next_seq.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    fn send(&self, client: u64, message: Value) {
+        if let Some(client) = self.clients.get(&client) {
+            let _ = client.sender.send(message);
+        }
+    }
+
+    /// Send an adapter event to the editors that joined and to the session's subscribers
+    fn broadcast(&self, event: Value) {
+        for client in self.clients.iter().filter(|client| client.participant.is_some()) {
+            let _ = client.sender.send(event.clone());
+        }
+        if let Some(tx) = self.senders.get(&self.session_id) {
+            let _ = tx.send(LiveUpdate::DebugEvent {
+                event: event["event"].as_str().unwrap_or_default().to_string(),
+                body: event.get("body").cloned().unwrap_or(Value::Null),
+            });
+        }
+    }
+
+    /// Start the adapter and the task that routes what it sends
+    async fn spawn_backend(self: &Arc<Self>) -> Result<()> {
+        let (program, args) = self.adapter.split_first().context("no debug adapter configured")?;
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("starting debug adapter {}", program))?;
+        let stdin = child.stdin.take().context("adapter without stdin")?;
+        let stdout = child.stdout.take().context("adapter without stdout")?;
+        *self.backend.lock().await = Some(Backend { stdin, _child: child });
+        let generation = self.generation.fetch_add(1, Ordering::Relaxed) + 1;
+        info!(session = %self.session_id, adapter = %program, "🐞 Debug adapter started");
+
+        let bridge = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut reader = BufReader::new(stdout);
+            while let Ok(Some(message)) = read_message(&mut reader).await {
+                let Some(bridge) = bridge.upgrade() else { return };
+                bridge.route(message).await;
+            }
+            if let Some(bridge) = bridge.upgrade() {
+                if bridge.generation.load(Ordering::Relaxed) == generation {
+                    bridge.broadcast(json!({"type": "event", "event": "terminated"}));
+                    bridge.stop().await;
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Handle a message from the adapter
+    async fn route(&self, message: Value) {
+        match message["type"].as_str() {
+            Some("response") => {
+                let seq = message["request_seq"].as_i64().unwrap_or_default();
+                if let Some((_, waiting)) = self.pending.remove(&seq) {
+                    let _ = waiting.send(message);
+                }
+            }
+            Some("event") => self.broadcast(message),
+            // Reverse requests such as runInTerminal would need one editor to act for everyone
+            Some("request") => {
+                let reply = json!({
+                    "seq": self.seq(),
+                    "type": "response",
+                    "request_seq": message["seq"],
+                    "command": message["command"],
+                    "success": false,
+                    "message": "not supported by the parflow live bridge",
+                });
+                if let Err(e) = self.write_backend(&reply).await {
+                    warn!(error = %e, "⚠️  Debug adapter unreachable");
+                }
+            }
+            _ => {}
+        }
+    }
+
+    async fn write_backend(&self, message: &Value) -> Result<()> {
+        let mut backend = self.backend.lock().await;
+        let backend = backend.as_mut().context("debug adapter is not running")?;
+        write_message(&mut backend.stdin, message).await
+    }
+
+    /// Stop the adapter and forget what it was told
+    pub async fn stop(&self) {
+        if self.backend.lock().await.take().is_some() {
+            info!(session = %self.session_id, "🐞 Debug adapter stopped");
+        }
+        // Fails requests still waiting, which may hold the state lock
+        self.pending.clear();
+        *self.state.lock().await = State::default();
+    }
+
+    /// Send a request to the adapter and wait for its response
+    async fn request(&self, command: &str, arguments: Value) -> Result<Value> {
+        let seq = self.seq();
+        let (tx, rx) = oneshot::channel();
+        self.pending.insert(seq, tx);
+        let request =
+            json!({"seq": seq, "type": "request", "command": command, "arguments": arguments});
+        if let Err(e) = self.write_backend(&request).await {
+            self.pending.remove(&seq);
+            return Err(e);
+        }
+        rx.await.map_err(|_| anyhow!("debug adapter exited"))
+    }
+
+    /// Check that the client's participant may send `command`
+    fn authorize(&self, client: u64, command: &str) -> Result<()> {
+        let participant = self.clients.get(&client).and_then(|c| c.participant.clone());
+        let Some(participant) = participant else {
+            bail!("send launch or attach with your debug token first");
+        };
+        let session = self.sessions.get(&self.session_id).context("the session has ended")?;
+        let participant = session
+            .participants
+            .iter()
+            .find(|p| p.id == participant)
+            .context("not a participant of this session")?;
+        if CONTROL.contains(&command) && !participant.scope.can_edit() {
+            bail!("{} participants cannot {}", participant.scope, command);
+        }
+        if command == "launch" && session.sandbox.allowed_commands.is_some() {
+            bail!("this session restricts commands, so it cannot launch programs");
+        }
+        Ok(())
+    }
+
+    /// Respond to one request of a client: the body of a successful response
+    async fn handle(
+        self: &Arc<Self>,
+        client: u64,
+        command: &str,
+        mut arguments: Value,
+    ) -> Result<Value> {
+        match command {
+            "initialize" => {
+                let mut state = self.state.lock().await;
+                if let Some(capabilities) = &state.capabilities {
+                    return Ok(capabilities.clone());
+                }
+                if self.backend.lock().await.is_none() {
+                    self.spawn_backend().await?;
+                }
+                let response = self.request(command, arguments).await?;
+                let capabilities = success(response)?;
+                state.capabilities = Some(capabilities.clone());
+                Ok(capabilities)
+            }
+            "launch" | "attach" => {
+                // The adapter has no use for the token, and must not log it
+                let token = arguments.as_object_mut().and_then(|a| a.remove("token"));
+                let token = token.context("pass your debug token as \"token\"")?;
+                let participant = token
+                    .as_str()
+                    .and_then(|token| self.tokens.get(token))
+                    .map(|participant| participant.clone())
+                    .context("unknown debug token")?;
+                if let Some(mut entry) = self.clients.get_mut(&client) {
+                    entry.participant = Some(participant);
+                }
+                let authorized = self.authorize(client, command);
+                if let Err(e) = authorized {
+                    if let Some(mut entry) = self.clients.get_mut(&client) {
+                        entry.participant = None;
+                    }
+                    return Err(e);
+                }
+                let mut state = self.state.lock().await;
+                if state.launched {
+                    // The debuggee already runs; this editor just needs to configure itself
+                    self.send(client, json!({"type": "event", "event": "initialized"}));
+                    return Ok(Value::Null);
+                }
+                let response = self.request(command, arguments).await?;
+                state.launched = true;
+                success(response)
+            }
+            "configurationDone" => {
+                self.authorize(client, command)?;
+                let mut state = self.state.lock().await;
+                if state.configured {
+                    return Ok(Value::Null);
+                }
+                let response = self.request(command, arguments).await?;
+                state.configured = true;
+                success(response)
+            }
+            "setBreakpoints" => {
+                self.authorize(client, command)?;
+                self.set_breakpoints(client, arguments).await
+            }
+            // Answered before the client is forgotten, see `serve_client`
+            "disconnect" => Ok(Value::Null),
+            _ => {
+                self.authorize(client, command)?;
+                success(self.request(command, arguments).await?)
+            }
+        }
+    }
+
+    /// Merge the client's breakpoints for a source with everyone else's, and answer with the
+    /// adapter's view of the client's own
+    async fn set_breakpoints(&self, client: u64, mut arguments: Value) -> Result<Value> {
+        let source = &arguments["source"];
+        let path = source["path"].as_str().or(source["name"].as_str()).unwrap_or_default();
+        let path = path.to_string();
+        let requested = arguments["breakpoints"].as_array().cloned().unwrap_or_default();
+
+        let mut state = self.state.lock().await;
+        let by_client = state.breakpoints.entry(path).or_default();
+        by_client.insert(client, requested);
+        // The union in client order, remembering which entries are this client's
+        let mut union = Vec::new();
+        let mut own = Vec::new();
+        for (owner, breakpoints) in by_client.iter() {
+            for breakpoint in breakpoints {
+                if *owner == client {
+                    own.push(union.len());
+                }
+                union.push(breakpoint.clone());
+            }
+        }
+        arguments["breakpoints"] = Value::Array(union);
+        if let Some(arguments) = arguments.as_object_mut() {
+            arguments.remove("lines");
+        }
+        let body = success(self.request("setBreakpoints", arguments).await?)?;
+        let set = body["breakpoints"].as_array().cloned().unwrap_or_default();
+        let own: Vec<Value> = own.into_iter().filter_map(|i| set.get(i).cloned()).collect();
+        Ok(json!({ "breakpoints": own }))
+    }
+
+    /// Forget the client; the last one out stops the adapter
+    async fn disconnect(&self, client: u64, arguments: Value) {
+        self.clients.remove(&client);
+        let mut state = self.state.lock().await;
+        for by_client in state.breakpoints.values_mut() {
+            by_client.remove(&client);
+        }
+        let running = self.backend.lock().await.is_some();
+        if self.clients.is_empty() && running {
+            if let Err(e) = self.request("disconnect", arguments).await {
+                warn!(error = %e, "⚠️  Debug adapter did not disconnect cleanly");
+            }
+            drop(state);
+            self.stop().await;
+        }
+    }
+
+    /// Serve one editor's DAP connection until it disconnects
+    pub async fn serve_client<S>(self: Arc<Self>, stream: S) -> Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let (reader, mut writer) = tokio::io::split(stream);
+        let mut reader = BufReader::new(reader);
+        let (sender, mut outgoing) = mpsc::unbounded_channel::<Value>();
+        let client = self.next_client.fetch_add(1, Ordering::Relaxed);
+        self.clients.insert(client, Client { sender, participant: None });
+
+        let bridge = Arc::downgrade(&self);
+        let writes = tokio::spawn(async move {
+            while let Some(mut message) = outgoing.recv().await {
+                let Some(bridge) = bridge.upgrade() else { break };
+                message["seq"] = json!(bridge.seq());
+                if write_message(&mut writer, &message).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        let requests = self.requests(client, &mut reader).await;
+        let arguments = requests.as_ref().ok().cloned().flatten().unwrap_or(json!({}));
+        self.disconnect(client, arguments).await;
+        // Forgetting the client closed its channel, so this ends once the replies are written
+        let _ = writes.await;
+        requests.map(|_| ())
+    }
+
+    /// Answer the client's requests until it disconnects, with the `disconnect` arguments, or
+    /// closes the connection
+    async fn requests<R: AsyncBufRead + Unpin>(
+        self: &Arc<Self>,
+        client: u64,
+        reader: &mut R,
+    ) -> Result<Option<Value>> {
+        while let Some(request) = read_message(reader).await? {
+            if request["type"] != "request" {
+                continue;
+            }
+            let command = request["command"].as_str().unwrap_or_default().to_string();
+            let arguments = request.get("arguments").cloned().unwrap_or(json!({}));
+            let result = self.handle(client, &command, arguments.clone()).await;
+            let mut response = json!({
+                "type": "response",
+                "request_seq": request["seq"],
+                "command": command,
+                "success": result.is_ok(),
+            });
+            match result {
+                Ok(Value::Null) => {}
+                Ok(body) => response["body"] = body,
+                Err(e) => response["message"] = json!(format!("{:#}", e)),
+            }
+            let _ = self.clients.get(&client).map(|c| c.sender.send(response));
+            if command == "disconnect" {
+                return Ok(Some(arguments));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// The body of a successful adapter response, or its error message
+fn success(response: Value) -> Result<Value> {
+    if response["success"].as_bool() == Some(true) {
+        return Ok(response.get("body").cloned().unwrap_or(Value::Null));
+    }
+    let message = response["body"]["error"]["format"]
+        .as_str()
+        .or(response["message"].as_str())
+        .unwrap_or("debug adapter request failed");
+    bail!("{}", message)
+}
+
+impl LiveServer {
+    /// `debug`: how the participant attaches their editor to the session's debug adapter
+    pub(crate) fn debug_command(&self, session_id: &str) -> String {
+        let endpoint = self.debuggers.get(session_id).and_then(|bridge| bridge.endpoint());
+        let Some(endpoint) = endpoint else {
+            return "No debugger in this session; start it with live-start --debug-port"
+                .to_string();
+        };
+        let attach = json!({
+            "name": "ParFlow live",
+            "request": "attach",
+            "debugServer": endpoint.port(),
+            "token": "<your debug token>",
+        });
+        // Everyone sees the shared terminal, so each participant's live client shows their token
+        format!(
+            "Debug adapter at {}. Add this to the configurations of your launch.json, with the \
+             type of your debugger and your debug token, which debug in your live client's own \
+             terminal shows only to you:\n{}",
+            endpoint,
+            serde_json::to_string_pretty(&attach).unwrap_or_default()
+        )
+    }
+
+    /// [`default_adapter`] for the language most of the session's files are in
+    pub fn default_debug_adapter(&self, session_id: &str) -> Option<Vec<String>> {
+        let session = self.sessions.get(session_id)?;
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for file in &session.code_files {
+            *counts.entry(file.language.as_str()).or_default() += 1;
+        }
+        let mut languages: Vec<_> = counts.into_iter().collect();
+        languages.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        languages.into_iter().find_map(|(language, _)| default_adapter(language))
+    }
+
+    /// The session's debug bridge, created with `adapter` as the command that starts the debug
+    /// adapter if the session has none yet
+    pub fn debug_bridge(&self, session_id: &str, adapter: Vec<String>) -> Result<Arc<DebugBridge>> {
+        if !self.sessions.contains_key(session_id) {
+            bail!("unknown session");
+        }
+        let bridge = self.debuggers.entry(session_id.to_string()).or_insert_with(|| {
+            Arc::new(DebugBridge {
+                session_id: session_id.to_string(),
+                adapter,
+                endpoint: OnceLock::new(),
+                sessions: self.sessions.clone(),
+                senders: self.broadcast_senders.clone(),
+                backend: Mutex::default(),
+                generation: AtomicU64::new(0),
+                state: Mutex::default(),
+                clients: DashMap::new(),
+                tokens: DashMap::new(),
+                pending: DashMap::new(),
+                next_seq: AtomicI64::new(0),
+                next_client: AtomicU64::new(0),
+            })
+        });
+        Ok(bridge.clone())
+    }
+
+    /// The debug token of a participant of the session, for their editor's `launch` or
+    /// `attach` arguments. Give it only to that participant.
+    pub fn debug_token(&self, session_id: &str, participant_id: &str) -> Result<String> {
+        let bridge = self.debuggers.get(session_id).context("no debugger in this session")?;
+        bridge.token(participant_id)
+    }
+
+    /// Accept DAP connections for a session on `listener` until it fails
+    pub async fn serve_debug_adapter(
+        &self,
+        session_id: &str,
+        listener: TcpListener,
+        adapter: Vec<String>,
+    ) -> Result<()> {
+        let bridge = self.debug_bridge(session_id, adapter)?;
+        let _ = bridge.endpoint.set(listener.local_addr()?);
+        loop {
+            let (stream, peer) = listener.accept().await?;
+            info!(session = session_id, %peer, "🐞 Debugger connected");
+            let bridge = bridge.clone();
+            tokio::spawn(async move {
+                if let Err(e) = bridge.serve_client(stream).await {
+                    warn!(%peer, error = %e, "⚠️  Debugger connection failed");
+                }
+            });
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::invites::{InviteScope, DEFAULT_TTL};
+    use std::time::Duration;
+    use tokio::io::DuplexStream;
+
+    /// A stand-in adapter: answers every request, sends `initialized` after launch, echoes
+    /// breakpoints as verified and stops at the first one on continue
+    const FAKE_ADAPTER: &str = r#"
+import json, sys
+inp, out, seq = sys.stdin.buffer, sys.stdout.buffer, 0
+def send(message):
+    global seq
+    seq += 1
+    message["seq"] = seq
+    body = json.dumps(message).encode()
+    out.write(b"Content-Length: %d\r\n\r\n" % len(body) + body)
+    out.flush()
+while True:
+    length = 0
+    while True:
+        line = inp.readline()
+        if not line:
+            sys.exit(0)
+        if line.strip() == b"":
+            break
+        length = int(line.split(b":")[1])
+    request = json.loads(inp.read(length))
+    arguments, body = request.get("arguments", {}), {}
+    if request["command"] == "initialize":
+        body = {"supportsConfigurationDoneRequest": True}
+    if request["command"] == "setBreakpoints":
+        body = {"breakpoints": [{"verified": True, "line": b["line"]} for b in arguments["breakpoints"]]}
+    send({"type": "response", "request_seq": request["seq"], "command": request["command"], "success": True, "body": body})
+    if request["command"] == "launch":
+        send({"type": "event", "event": "initialized"})
+    if request["command"] == "continue":
+        send({"type": "event", "event": "stopped", "body": {"reason": "breakpoint", "threadId": 1}})
+    if request["command"] == "disconnect":
+        sys.exit(0)
+"#;
+
+    struct Editor {
+        stream: BufReader<DuplexStream>,
+        seq: i64,
+    }
+
+    impl Editor {
+        fn connect(bridge: &Arc<DebugBridge>) -> Self {
+            let (ours, theirs) = tokio::io::duplex(64 * 1024);
+            tokio::spawn(bridge.clone().serve_client(theirs));
+            Self { stream: BufReader::new(ours), seq: 0 }
+        }
+
+        /// Send a request and return its response, collecting events that arrive first
+        async fn request(
+            &mut self,
+            command: &str,
+            arguments: Value,
+            events: &mut Vec<String>,
+        ) -> Value {
+            self.seq += 1;
+            let request = json!({"seq": self.seq, "type": "request", "command": command, "arguments": arguments});
+            write_message(self.stream.get_mut(), &request).await.unwrap();
+            loop {
+                let message = self.next().await;
+                if message["type"] == "response" {
+                    assert_eq!(message["request_seq"], self.seq);
+                    return message;
+                }
+                events.push(message["event"].as_str().unwrap().to_string());
+            }
+        }
+
+        async fn next(&mut self) -> Value {
+            let message =
+                tokio::time::timeout(Duration::from_secs(10), read_message(&mut self.stream));
+            message.await.expect("no message from the bridge").unwrap().unwrap()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bridge_shares_one_adapter_between_participants() {
+        let server = LiveServer::new();
+        let session = server.create_session("demo").await;
+        let owner =
+            server.join_session(&session, "owner").await.unwrap().participants[0].id.clone();
+        let invite = |scope| {
+            server.create_invitation(&session, &owner, "guest", scope, DEFAULT_TTL).unwrap()
+        };
+        let guest =
+            server.redeem_invitation(&invite(InviteScope::Edit).token, "guest").await.unwrap();
+        let guest = guest.participants[1].id.clone();
+        let viewer =
+            server.redeem_invitation(&invite(InviteScope::ReadOnly).token, "viewer").await.unwrap();
+        let viewer = viewer.participants[2].id.clone();
+        let mut updates = server.subscribe_to_updates(&session).unwrap();
+
+        let adapter = vec!["python3".to_string(), "-c".to_string(), FAKE_ADAPTER.to_string()];
+        let bridge = server.debug_bridge(&session, adapter).unwrap();
+        let (mut first, mut second, mut third) =
+            (Editor::connect(&bridge), Editor::connect(&bridge), Editor::connect(&bridge));
+        let (mut events, mut others) = (Vec::new(), Vec::new());
+
+        let init = first.request("initialize", json!({"adapterID": "python"}), &mut events).await;
+        assert_eq!(init["body"]["supportsConfigurationDoneRequest"], true);
+        let refused = first.request("threads", json!({}), &mut events).await;
+        assert_eq!(refused["success"], false);
+        let bogus = json!({"program": "app.py", "token": owner});
+        let bogus = first.request("launch", bogus, &mut events).await;
+        assert_eq!(bogus["message"], "unknown debug token");
+        let token = server.debug_token(&session, &owner).unwrap();
+        assert_eq!(server.debug_token(&session, &owner).unwrap(), token);
+        let launch = json!({"program": "app.py", "token": token});
+        assert_eq!(first.request("launch", launch, &mut events).await["success"], true);
+
+        let cached = second.request("initialize", json!({}), &mut others).await;
+        assert_eq!(cached["body"], init["body"]);
+        let attach = json!({"token": server.debug_token(&session, &guest).unwrap()});
+        assert_eq!(second.request("attach", attach, &mut others).await["success"], true);
+        assert_eq!(others, ["initialized"]);
+
+        let source = json!({"path": "/work/app.py"});
+        let lines = |lines: &[i64]| json!({"source": source, "breakpoints": lines.iter().map(|l| json!({"line": l})).collect::<Vec<_>>()});
+        first.request("setBreakpoints", lines(&[3]), &mut events).await;
+        let set = second.request("setBreakpoints", lines(&[5, 8]), &mut others).await;
+        assert_eq!(
+            set["body"]["breakpoints"],
+            json!([{"verified": true, "line": 5}, {"verified": true, "line": 8}])
+        );
+        let set = first.request("setBreakpoints", lines(&[3, 4]), &mut events).await;
+        assert_eq!(set["body"]["breakpoints"].as_array().unwrap().len(), 2);
+
+        let attach = json!({"token": server.debug_token(&session, &viewer).unwrap()});
+        assert_eq!(third.request("attach", attach, &mut Vec::new()).await["success"], true);
+        let step = third.request("next", json!({"threadId": 1}), &mut Vec::new()).await;
+        assert_eq!(step["success"], false);
+        assert!(step["message"].as_str().unwrap().contains("read-only"));
+        let evaluate = json!({"expression": "__import__('os').system('id')"});
+        let evaluate = third.request("evaluate", evaluate, &mut Vec::new()).await;
+        assert_eq!(evaluate["success"], false);
+
+        assert_eq!(
+            first.request("configurationDone", json!({}), &mut events).await["success"],
+            true
+        );
+        assert_eq!(
+            second.request("configurationDone", json!({}), &mut others).await["success"],
+            true
+        );
+        first.request("continue", json!({"threadId": 1}), &mut events).await;
+        assert_eq!(second.next().await["event"], "stopped");
+        assert_eq!(third.next().await["event"], "stopped");
+        let stopped = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                if let LiveUpdate::DebugEvent { event, body } = updates.recv().await.unwrap() {
+                    if event == "stopped" {
+                        return body;
+                    }
+                }
+            }
+        });
+        assert_eq!(stopped.await.unwrap()["reason"], "breakpoint");
+
+        for editor in [&mut first, &mut second, &mut third] {
+            editor.request("disconnect", json!({}), &mut Vec::new()).await;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(bridge.clients(), 0);
+        server.end_session(&session).await;
+        assert!(server.debuggers.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_oversized_messages_are_rejected() {
+        let header = format!("Content-Length: {}\r\n\r\n", MAX_MESSAGE + 1);
+        let error = read_message(&mut header.as_bytes()).await.unwrap_err();
+        assert!(error.to_string().contains("larger than"));
+    }
+}
@@ -0,0 +1,92 @@
+//! Incremental edits to session files
+//!
+//! Clients send [`EditOp`]s instead of whole files. Offsets count chars rather than bytes, so
+//! they mean the same thing whatever string type a client keeps its buffer in. Applied edits
+//! are broadcast as [`LiveUpdate::CodeChanged`](crate::LiveUpdate::CodeChanged) like any other
+//! change.
+
+use crate::LiveServer;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum EditOp {
+    Insert { offset: usize, text: String },
+    Delete { offset: usize, len: usize },
+}
+
+impl EditOp {
+    pub fn apply(&self, content: &mut String) -> Result<()> {
+        let byte = |chars: usize| {
+            content
+                .char_indices()
+                .map(|(i, _)| i)
+                .chain([content.len()])
+                .nth(chars)
+                .ok_or_else(|| anyhow!("offset {} is past the end of the file", chars))
+        };
+        match self {
+            EditOp::Insert { offset, text } => {
+                let at = byte(*offset)?;
+                content.insert_str(at, text);
+            }
+            EditOp::Delete { offset, len } => {
+                let (start, end) = (byte(*offset)?, byte(offset + len)?);
+                content.replace_range(start..end, "");
+            }
+        }
+        Ok(())
+    }
+}
+
+impl LiveServer {
+    /// Apply `ops` to `filename` in order. Nothing changes if any of them does not fit the file.
+    pub async fn apply_edits(
+        &self,
+        session_id: &str,
+        user_id: &str,
+        filename: &str,
+        ops: &[EditOp],
+    ) -> Result<()> {
+        let mut content = self
+            .sessions
+            .get(session_id)
+            .ok_or_else(|| anyhow!("unknown session"))?
+            .code_files
+            .iter()
+            .find(|file| file.filename == filename)
+            .map(|file| file.content.clone())
+            .unwrap_or_default();
+        for op in ops {
+            op.apply(&mut content)?;
+        }
+        self.handle_code_edit(session_id, user_id, filename, &content).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_applies_char_offsets() {
+        let server = LiveServer::new();
+        let session = server.create_session("demo").await;
+        let owner =
+            server.join_session(&session, "owner").await.unwrap().participants[0].id.clone();
+        server.handle_code_edit(&session, &owner, "notes.txt", "héllo wörld").await.unwrap();
+
+        let ops = [
+            EditOp::Delete { offset: 6, len: 5 },
+            EditOp::Insert { offset: 6, text: "thére".to_string() },
+            EditOp::Insert { offset: 11, text: "!".to_string() },
+        ];
+        server.apply_edits(&session, &owner, "notes.txt", &ops).await.unwrap();
+        let past_end = [EditOp::Delete { offset: 10, len: 5 }];
+        assert!(server.apply_edits(&session, &owner, "notes.txt", &past_end).await.is_err());
+
+        let files = server.sessions.get(&session).unwrap().code_files.clone();
+        assert_eq!(files[0].content, "héllo thére!");
+    }
+}
@@ -0,0 +1,381 @@
+//! Project files of live sessions
+//!
+//! The host imports a directory into a session's `code_files`, honouring `.gitignore` and
+//! skipping binary and oversized files. The directory stays the session's root, so files left out
+//! of the import can still be opened from disk later. Participants see the files as a
+//! [`FileTree`], built from `code_files` so it is never out of step with them, and follow opens,
+//! renames and deletes through [`LiveUpdate`]s. Changes stay in memory until
+//! [`LiveServer::sync_to_disk`] writes them back to the root.
+
+use crate::audit::AuditEvent;
+use crate::{CodeFile, CompilationStatus, LiveServer, LiveUpdate};
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+
+/// Files larger than this are left on disk when importing
+pub const MAX_IMPORT_FILE_SIZE: u64 = 1024 * 1024;
+
+#[derive(Debug, Default, Serialize)]
+pub struct ImportReport {
+    pub imported: usize,
+    /// Binary or oversized files, which can still be opened one at a time
+    pub skipped: Vec<String>,
+}
+
+/// A directory of a session's files
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileTree {
+    pub dirs: BTreeMap<String, FileTree>,
+    /// File names, sorted
+    pub files: Vec<String>,
+}
+
+impl FileTree {
+    fn from_paths<'a>(paths: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut tree = FileTree::default();
+        for path in paths {
+            let mut parts: Vec<&str> = path.split('/').collect();
+            let name = parts.pop().unwrap_or_default();
+            let dir = parts
+                .into_iter()
+                .fold(&mut tree, |dir, part| dir.dirs.entry(part.to_string()).or_default());
+            dir.files.push(name.to_string());
+        }
+        tree.sort();
+        tree
+    }
+
+    fn sort(&mut self) {
+        self.files.sort();
+        self.dirs.values_mut().for_each(FileTree::sort);
+    }
+
+    fn render(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        for (name, dir) in &self.dirs {
+            writeln!(f, "{}📁 {}/", "  ".repeat(depth), name)?;
+            dir.render(f, depth + 1)?;
+        }
+        for name in &self.files {
+            writeln!(f, "{}📄 {}", "  ".repeat(depth), name)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for FileTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.render(f, 0)
+    }
+}
+
+fn is_project_path(path: &str) -> bool {
+    !path.is_empty() && Path::new(path).components().all(|c| matches!(c, Component::Normal(_)))
+}
+
+/// `path` under `root`, unless it is absolute or leaves the root, also through symlinks.
+fn resolve(root: &Path, path: &str) -> Result<PathBuf> {
+    if !is_project_path(path) {
+        bail!("{} is not a path inside the project", path);
+    }
+    let resolved = root.join(path).canonicalize().with_context(|| format!("opening {}", path))?;
+    if !resolved.starts_with(root) {
+        bail!("{} is not a path inside the project", path);
+    }
+    Ok(resolved)
+}
+
+impl LiveServer {
+    /// Load the files under `dir` into a session and make it the session's root.
+    pub fn import_directory(&self, session_id: &str, dir: &Path) -> Result<ImportReport> {
+        let root = dir.canonicalize().with_context(|| format!("opening {}", dir.display()))?;
+        let mut report = ImportReport::default();
+        let mut files = Vec::new();
+        // Hidden files are kept, as long as git would keep them too
+        let walk = ignore::WalkBuilder::new(&root)
+            .hidden(false)
+            .filter_entry(|entry| entry.file_name() != ".git")
+            .build();
+        for entry in walk {
+            let entry = entry?;
+            if !entry.file_type().is_some_and(|kind| kind.is_file()) {
+                continue;
+            }
+            let Ok(relative) = entry.path().strip_prefix(&root) else { continue };
+            let name = relative.to_string_lossy().replace('\\', "/");
+            let small = entry.metadata()?.len() <= MAX_IMPORT_FILE_SIZE;
+            match std::fs::read(entry.path()).map(String::from_utf8) {
+                Ok(Ok(content)) if small => files.push((name, content)),
+                Ok(_) => report.skipped.push(name),
+                Err(e) => return Err(e).with_context(|| format!("reading {}", name)),
+            }
+        }
+        report.imported = files.len();
+
+        let mut session =
+            self.sessions.get_mut(session_id).ok_or_else(|| anyhow!("unknown session"))?;
+        let names: Vec<String> = files.iter().map(|(name, _)| name.clone()).collect();
+        for (filename, content) in files {
+            session.removed.remove(&filename);
+            match session.code_files.iter_mut().find(|f| f.filename == filename) {
+                Some(file) => file.content = content,
+                None => {
+                    let language = self.detect_language(&filename);
+                    session.code_files.push(CodeFile {
+                        filename,
+                        content,
+                        language,
+                        last_modified_by: String::new(),
+                        compilation_status: CompilationStatus::default(),
+                    })
+                }
+            }
+        }
+        session.root = Some(root.clone());
+        drop(session);
+
+        self.audit(
+            session_id,
+            None,
+            AuditEvent::FilesImported { root: root.display().to_string(), files: report.imported },
+        );
+        if let Some(tx) = self.broadcast_senders.get(session_id) {
+            let _ = tx.send(LiveUpdate::FilesImported { files: names });
+        }
+        Ok(report)
+    }
+
+    pub fn file_tree(&self, session_id: &str) -> FileTree {
+        let Some(session) = self.sessions.get(session_id) else { return FileTree::default() };
+        FileTree::from_paths(session.code_files.iter().map(|f| f.filename.as_str()))
+    }
+
+    /// Open a file of the session, loading it from the session's root if it was not imported.
+    /// Anyone in the session may open files; the others see who is looking at what.
+    pub fn open_file(&self, session_id: &str, user_id: &str, filename: &str) -> Result<CodeFile> {
+        let (user_name, file, root) = {
+            let session =
+                self.sessions.get(session_id).ok_or_else(|| anyhow!("unknown session"))?;
+            let participant = session
+                .participants
+                .iter()
+                .find(|p| p.id == user_id)
+                .ok_or_else(|| anyhow!("not a participant of this session"))?;
+            let file = session.code_files.iter().find(|f| f.filename == filename).cloned();
+            if file.is_none() && session.removed.contains(filename) {
+                bail!("{} was deleted in this session", filename);
+            }
+            (participant.name.clone(), file, session.root.clone())
+        };
+        let file = match file {
+            Some(file) => file,
+            None => {
+                let root = root.ok_or_else(|| anyhow!("no file named {}", filename))?;
+                let content = std::fs::read_to_string(resolve(&root, filename)?)
+                    .with_context(|| format!("reading {}", filename))?;
+                let file = CodeFile {
+                    filename: filename.to_string(),
+                    content,
+                    language: self.detect_language(filename),
+                    last_modified_by: String::new(),
+                    compilation_status: CompilationStatus::default(),
+                };
+                let mut session =
+                    self.sessions.get_mut(session_id).ok_or_else(|| anyhow!("unknown session"))?;
+                if !session.code_files.iter().any(|f| f.filename == filename) {
+                    session.code_files.push(file.clone());
+                }
+                file
+            }
+        };
+
+        if let Some(tx) = self.broadcast_senders.get(session_id) {
+            let _ = tx.send(LiveUpdate::FileOpened { filename: filename.to_string(), user_name });
+        }
+        self.mark_active(session_id, user_id);
+        Ok(file)
+    }
+
+    /// Rename a file, moving cursors and code comments on it along.
+    pub fn rename_file(&self, session_id: &str, user_id: &str, from: &str, to: &str) -> Result<()> {
+        let user_name = self.ensure_can_edit_files(session_id, user_id)?;
+        let mut session =
+            self.sessions.get_mut(session_id).ok_or_else(|| anyhow!("unknown session"))?;
+        if !is_project_path(to) {
+            bail!("{} is not a path inside the project", to);
+        }
+        if session.code_files.iter().any(|f| f.filename == to) {
+            bail!("{} already exists", to);
+        }
+        let file = session
+            .code_files
+            .iter_mut()
+            .find(|f| f.filename == from)
+            .ok_or_else(|| anyhow!("no file named {}", from))?;
+        file.filename = to.to_string();
+        file.language = self.detect_language(to);
+        session.removed.insert(from.to_string());
+        session.removed.remove(to);
+        for participant in &mut session.participants {
+            if participant.cursor_position.filename.as_deref() == Some(from) {
+                participant.cursor_position.filename = Some(to.to_string());
+            }
+        }
+        for anchor in session.chat.iter_mut().filter_map(|entry| entry.anchor.as_mut()) {
+            if anchor.file == from {
+                anchor.file = to.to_string();
+            }
+        }
+        drop(session);
+
+        self.audit(
+            session_id,
+            Some(user_id),
+            AuditEvent::FileRenamed { from: from.to_string(), to: to.to_string() },
+        );
+        if let Some(tx) = self.broadcast_senders.get(session_id) {
+            let _ = tx.send(LiveUpdate::FileRenamed {
+                from: from.to_string(),
+                to: to.to_string(),
+                user_name,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn delete_file(&self, session_id: &str, user_id: &str, filename: &str) -> Result<()> {
+        let user_name = self.ensure_can_edit_files(session_id, user_id)?;
+        let mut session =
+            self.sessions.get_mut(session_id).ok_or_else(|| anyhow!("unknown session"))?;
+        let before = session.code_files.len();
+        session.code_files.retain(|f| f.filename != filename);
+        if session.code_files.len() == before {
+            bail!("no file named {}", filename);
+        }
+        session.removed.insert(filename.to_string());
+        drop(session);
+
+        self.audit(
+            session_id,
+            Some(user_id),
+            AuditEvent::FileDeleted { filename: filename.to_string() },
+        );
+        if let Some(tx) = self.broadcast_senders.get(session_id) {
+            let _ = tx.send(LiveUpdate::FileDeleted { filename: filename.to_string(), user_name });
+        }
+        Ok(())
+    }
+
+    /// Write the session's files that differ from the root and remove the ones deleted or
+    /// renamed away. Returns how many files on disk changed.
+    pub fn sync_to_disk(&self, session_id: &str) -> Result<usize> {
+        let (root, files, removed) = {
+            let session =
+                self.sessions.get(session_id).ok_or_else(|| anyhow!("unknown session"))?;
+            let root = session.root.clone().ok_or_else(|| anyhow!("the session has no project"))?;
+            let files: Vec<(String, String)> = session
+                .code_files
+                .iter()
+                .map(|f| (f.filename.clone(), f.content.clone()))
+                .collect();
+            (root, files, session.removed.clone())
+        };
+        let mut changed = 0;
+        for filename in &removed {
+            // Checked by hand, as `resolve` needs the file to exist
+            if is_project_path(filename) && root.join(filename).is_file() {
+                std::fs::remove_file(root.join(filename))?;
+                changed += 1;
+            }
+        }
+        for (filename, content) in files {
+            if !is_project_path(&filename) {
+                bail!("{} is not a path inside the project", filename);
+            }
+            let path = root.join(&filename);
+            if std::fs::read(&path).is_ok_and(|on_disk| on_disk == content.as_bytes()) {
+                continue;
+            }
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&path, content).with_context(|| format!("writing {}", filename))?;
+            changed += 1;
+        }
+        if let Some(mut session) = self.sessions.get_mut(session_id) {
+            session.removed.retain(|filename| !removed.contains(filename));
+        }
+        Ok(changed)
+    }
+
+    /// The participant's name, if they may change files
+    fn ensure_can_edit_files(&self, session_id: &str, user_id: &str) -> Result<String> {
+        let session = self.sessions.get(session_id).ok_or_else(|| anyhow!("unknown session"))?;
+        let participant = session
+            .participants
+            .iter()
+            .find(|p| p.id == user_id)
+            .ok_or_else(|| anyhow!("not a participant of this session"))?;
+        if !participant.scope.can_edit() {
+            bail!("{} participants cannot change files", participant.scope);
+        }
+        Ok(participant.name.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_imports_a_project_and_tracks_file_changes() {
+        let dir = std::env::temp_dir().join(format!("parflow-files-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::create_dir_all(dir.join("target")).unwrap();
+        // The walk only reads .gitignore inside a repository
+        std::fs::create_dir_all(dir.join(".git")).unwrap();
+        std::fs::write(dir.join(".gitignore"), "target/\n*.log\n").unwrap();
+        std::fs::write(dir.join("src/main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(dir.join("src/lib.rs"), "pub fn lib() {}\n").unwrap();
+        std::fs::write(dir.join("target/out.rs"), "// built").unwrap();
+        std::fs::write(dir.join("build.log"), "noise").unwrap();
+        std::fs::write(dir.join("logo.png"), [0x89, b'P', b'N', b'G', 0xff, 0xfe]).unwrap();
+
+        let server = LiveServer::new();
+        let session = server.create_session("demo").await;
+        let owner =
+            server.join_session(&session, "owner").await.unwrap().participants[0].id.clone();
+        let report = server.import_directory(&session, &dir).unwrap();
+        assert_eq!(report.imported, 3);
+        assert_eq!(report.skipped, ["logo.png"]);
+
+        let tree = server.file_tree(&session);
+        assert_eq!(tree.files, [".gitignore"]);
+        assert_eq!(tree.dirs["src"].files, ["lib.rs", "main.rs"]);
+
+        // Ignored files are left out of the import but can still be opened
+        let log = server.open_file(&session, &owner, "build.log").unwrap();
+        assert_eq!(log.content, "noise");
+        assert!(server.open_file(&session, &owner, "../etc/passwd").is_err());
+
+        server.comment_on_code(&session, &owner, "src/lib.rs", 1, "rename me").unwrap();
+        server.rename_file(&session, &owner, "src/lib.rs", "src/util.rs").unwrap();
+        assert!(server.rename_file(&session, &owner, "src/util.rs", "src/main.rs").is_err());
+        assert_eq!(server.code_comments(&session, "src/util.rs").len(), 1);
+        server.delete_file(&session, &owner, "build.log").unwrap();
+        assert!(server.delete_file(&session, &owner, "build.log").is_err());
+
+        let tree = server.file_tree(&session);
+        assert_eq!(tree.dirs["src"].files, ["main.rs", "util.rs"]);
+        assert!(tree.to_string().contains("📄 util.rs"));
+        assert!(server.open_file(&session, &owner, "build.log").is_err());
+
+        assert_eq!(server.sync_to_disk(&session).unwrap(), 3);
+        assert!(!dir.join("src/lib.rs").exists() && !dir.join("build.log").exists());
+        assert_eq!(std::fs::read_to_string(dir.join("src/util.rs")).unwrap(), "pub fn lib() {}\n");
+        assert_eq!(server.sync_to_disk(&session).unwrap(), 0);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
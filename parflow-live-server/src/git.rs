@@ -0,0 +1,335 @@
+//! Git repositories behind live sessions
+//!
+//! When a session's project root is inside a git repository, [`GitBridge`] reports its branch and
+//! dirty files and commits on behalf of participants. Session edits are synced to disk first, so
+//! both cover what participants see. A commit is authored by the participant who made it, with a
+//! `Co-authored-by` trailer for everyone else who can edit, and its diff summary is broadcast as
+//! [`LiveUpdate::Committed`].
+
+use crate::audit::AuditEvent;
+use crate::{LiveServer, LiveUpdate};
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GitStatus {
+    /// `None` when HEAD is detached
+    pub branch: Option<String>,
+    pub dirty: Vec<DirtyFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DirtyFile {
+    pub path: String,
+    /// Two-letter code of `git status --short`, e.g. ` M` or `??`
+    pub status: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GitAuthor {
+    pub name: String,
+    pub email: String,
+}
+
+impl GitAuthor {
+    /// Participants who have not given an email get a placeholder
+    fn of(name: &str, email: Option<&str>) -> Self {
+        let placeholder = || {
+            let user: String = name
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+                .collect();
+            format!("{}@users.parflow.invalid", user)
+        };
+        Self {
+            name: name.to_string(),
+            email: email.map(str::to_string).unwrap_or_else(placeholder),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitSummary {
+    pub id: String,
+    pub branch: Option<String>,
+    /// First line of the message
+    pub subject: String,
+    pub author: String,
+    pub co_authors: Vec<String>,
+    pub files: Vec<FileDiff>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileDiff {
+    pub path: String,
+    /// `None` for binary files
+    pub insertions: Option<u32>,
+    pub deletions: Option<u32>,
+}
+
+impl CommitSummary {
+    /// `3 files changed, +10 -2`
+    pub fn stat(&self) -> String {
+        let insertions: u32 = self.files.iter().filter_map(|f| f.insertions).sum();
+        let deletions: u32 = self.files.iter().filter_map(|f| f.deletions).sum();
+        format!("{} files changed, +{} -{}", self.files.len(), insertions, deletions)
+    }
+}
+
+pub struct GitBridge {
+    repo: PathBuf,
+}
+
+impl GitBridge {
+    /// The repository containing `dir`
+    pub async fn open(dir: &Path) -> Result<Self> {
+        let toplevel = git(dir, &["rev-parse", "--show-toplevel"]).await?;
+        Ok(Self { repo: PathBuf::from(toplevel.trim()) })
+    }
+
+    pub fn repo(&self) -> &Path {
+        &self.repo
+    }
+
+    pub async fn status(&self) -> Result<GitStatus> {
+        let porcelain = git(&self.repo, &["status", "--porcelain=v1", "--branch"]).await?;
+        let mut lines = porcelain.lines();
+        let header = lines.next().and_then(|line| line.strip_prefix("## ")).unwrap_or_default();
+        let header = header.strip_prefix("No commits yet on ").unwrap_or(header);
+        let branch = match header.split(['.', ' ']).next() {
+            Some("HEAD") | Some("") | None => None,
+            Some(_) => Some(header.split("...").next().unwrap_or(header).to_string()),
+        };
+        let dirty = lines
+            .filter(|line| line.len() > 3)
+            .map(|line| {
+                let path = &line[3..];
+                // Renames list the old path first
+                let path = path.rsplit_once(" -> ").map_or(path, |(_, new)| new);
+                DirtyFile {
+                    path: path.trim_matches('"').to_string(),
+                    status: line[..2].to_string(),
+                }
+            })
+            .collect();
+        Ok(GitStatus { branch, dirty })
+    }
+
+    /// Commit everything in the working tree.
+    pub async fn commit(
+        &self,
+        message: &str,
+        author: &GitAuthor,
+        co_authors: &[GitAuthor],
+    ) -> Result<CommitSummary> {
+        let message = message.trim();
+        if message.is_empty() {
+            bail!("empty commit message");
+        }
+        git(&self.repo, &["add", "--all"]).await?;
+        if git(&self.repo, &["diff", "--cached", "--quiet"]).await.is_ok() {
+            bail!("nothing to commit");
+        }
+        let mut full_message = message.to_string();
+        if !co_authors.is_empty() {
+            full_message.push('\n');
+            for co_author in co_authors {
+                full_message.push_str(&format!(
+                    "\nCo-authored-by: {} <{}>",
+                    co_author.name, co_author.email
+                ));
+            }
+        }
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(&self.repo)
+            .args(["commit", "--quiet", "--no-verify", "-m", &full_message])
+            .env("GIT_AUTHOR_NAME", &author.name)
+            .env("GIT_AUTHOR_EMAIL", &author.email)
+            .env("GIT_COMMITTER_NAME", &author.name)
+            .env("GIT_COMMITTER_EMAIL", &author.email)
+            .output()
+            .await
+            .context("could not run git")?;
+        if !output.status.success() {
+            bail!("git commit failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+
+        let id = git(&self.repo, &["rev-parse", "HEAD"]).await?.trim().to_string();
+        let numstat = git(&self.repo, &["show", "--numstat", "--format=", "HEAD"]).await?;
+        let files = numstat
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(3, '\t');
+                let (insertions, deletions) = (fields.next()?, fields.next()?);
+                Some(FileDiff {
+                    path: fields.next()?.to_string(),
+                    insertions: insertions.parse().ok(),
+                    deletions: deletions.parse().ok(),
+                })
+            })
+            .collect();
+        Ok(CommitSummary {
+            id,
+            branch: self.status().await?.branch,
+            subject: message.lines().next().unwrap_or_default().to_string(),
+            author: author.name.clone(),
+            co_authors: co_authors.iter().map(|c| c.name.clone()).collect(),
+            files,
+        })
+    }
+}
+
+async fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .await
+        .context("could not run git")?;
+    if !output.status.success() {
+        bail!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+impl LiveServer {
+    /// The repository of a session's project, with session edits synced to it
+    async fn git_bridge(&self, session_id: &str) -> Result<GitBridge> {
+        self.sync_to_disk(session_id)?;
+        let root = self
+            .sessions
+            .get(session_id)
+            .and_then(|session| session.root.clone())
+            .ok_or_else(|| anyhow!("the session has no project"))?;
+        GitBridge::open(&root).await
+    }
+
+    pub async fn git_status(&self, session_id: &str) -> Result<GitStatus> {
+        self.git_bridge(session_id).await?.status().await
+    }
+
+    /// Record the email a participant's commits are attributed to.
+    pub fn set_git_email(&self, session_id: &str, user_id: &str, email: &str) -> Result<()> {
+        let mut session =
+            self.sessions.get_mut(session_id).ok_or_else(|| anyhow!("unknown session"))?;
+        let participant = session
+            .participants
+            .iter_mut()
+            .find(|p| p.id == user_id)
+            .ok_or_else(|| anyhow!("not a participant of this session"))?;
+        participant.email = Some(email.trim().to_string());
+        Ok(())
+    }
+
+    /// Commit the project as `user_id`, crediting everyone else who can edit as co-author.
+    pub async fn git_commit(
+        &self,
+        session_id: &str,
+        user_id: &str,
+        message: &str,
+    ) -> Result<CommitSummary> {
+        let (author, co_authors) = {
+            let session =
+                self.sessions.get(session_id).ok_or_else(|| anyhow!("unknown session"))?;
+            let participant = session
+                .participants
+                .iter()
+                .find(|p| p.id == user_id)
+                .ok_or_else(|| anyhow!("not a participant of this session"))?;
+            if !participant.scope.can_edit() {
+                bail!("{} participants cannot commit", participant.scope);
+            }
+            let co_authors: Vec<GitAuthor> = session
+                .participants
+                .iter()
+                .filter(|p| p.id != user_id && p.scope.can_edit())
+                .map(|p| GitAuthor::of(&p.name, p.email.as_deref()))
+                .collect();
+            (GitAuthor::of(&participant.name, participant.email.as_deref()), co_authors)
+        };
+        let summary =
+            self.git_bridge(session_id).await?.commit(message, &author, &co_authors).await?;
+
+        self.audit(
+            session_id,
+            Some(user_id),
+            AuditEvent::Committed { commit: summary.id.clone(), subject: summary.subject.clone() },
+        );
+        if let Some(tx) = self.broadcast_senders.get(session_id) {
+            let _ = tx.send(LiveUpdate::Committed { summary: summary.clone() });
+        }
+        Ok(summary)
+    }
+
+    pub(crate) async fn commit_command(
+        &self,
+        session_id: &str,
+        user_id: &str,
+        message: &str,
+    ) -> String {
+        match self.git_commit(session_id, user_id, message).await {
+            Ok(summary) => {
+                let mut output = format!(
+                    "Committed {} on {}: {}\n{}",
+                    &summary.id[..summary.id.len().min(8)],
+                    summary.branch.as_deref().unwrap_or("detached HEAD"),
+                    summary.subject,
+                    summary.stat()
+                );
+                if !summary.co_authors.is_empty() {
+                    output.push_str(&format!("\nCo-authors: {}", summary.co_authors.join(", ")));
+                }
+                output
+            }
+            Err(e) => format!("Commit failed: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::invites::{InviteScope, DEFAULT_TTL};
+
+    #[tokio::test]
+    async fn test_commits_session_edits_with_co_authors() {
+        let dir = std::env::temp_dir().join(format!("parflow-git-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        git(&dir, &["init", "--quiet", "--initial-branch=main"]).await.unwrap();
+        std::fs::write(dir.join("README.md"), "# demo\n").unwrap();
+
+        let server = LiveServer::new();
+        let session = server.create_session("demo").await;
+        let owner =
+            server.join_session(&session, "owner").await.unwrap().participants[0].id.clone();
+        for (name, scope) in [("ana", InviteScope::Edit), ("watcher", InviteScope::ReadOnly)] {
+            let invite =
+                server.create_invitation(&session, &owner, name, scope, DEFAULT_TTL).unwrap();
+            server.redeem_invitation(&invite.token, name).await.unwrap();
+        }
+        server.set_git_email(&session, &owner, "owner@example.com").unwrap();
+        server.import_directory(&session, &dir).unwrap();
+        server.handle_code_edit(&session, &owner, "src/main.rs", "fn main() {}\n").await.unwrap();
+
+        let status = server.git_status(&session).await.unwrap();
+        assert_eq!(status.branch.as_deref(), Some("main"));
+        assert_eq!(status.dirty.len(), 2);
+
+        let mut updates = server.subscribe_to_updates(&session).unwrap();
+        let summary = server.git_commit(&session, &owner, "Add main").await.unwrap();
+        assert_eq!(summary.co_authors, ["ana"]);
+        assert_eq!(summary.stat(), "2 files changed, +2 -0");
+        assert!(matches!(updates.recv().await.unwrap(), LiveUpdate::Committed { .. }));
+
+        let message = git(&dir, &["log", "-1", "--format=%an <%ae>%n%B"]).await.unwrap();
+        assert!(message.starts_with("owner <owner@example.com>\nAdd main\n"));
+        assert!(message.contains("Co-authored-by: ana <ana@users.parflow.invalid>"));
+        assert!(server.git_commit(&session, &owner, "Again").await.is_err());
+        assert!(server.git_status(&session).await.unwrap().dirty.is_empty());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
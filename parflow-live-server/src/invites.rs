@@ -0,0 +1,245 @@
+//! Invitation tokens for live sessions.
+//!
+//! Once a session has an owner, new participants join by redeeming a single-use token the
+//! owner generated with `invite <user>`. Tokens carry a scope and an expiry and can be revoked
+//! until they are redeemed. A token is `<session id>.<secret>`, so `live-join --token` does not
+//! need the session ID separately.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+pub const DEFAULT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// What a participant who joined with an invitation may do.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum InviteScope {
+    /// Use the shared terminal and edit code
+    #[default]
+    Edit,
+    /// Watch the session without contributing input or resources
+    ReadOnly,
+    /// Contribute compute to distributed builds, but no terminal or code input
+    AgentOnly,
+}
+
+impl InviteScope {
+    pub fn can_edit(self) -> bool {
+        self == InviteScope::Edit
+    }
+
+    pub fn contributes_resources(self) -> bool {
+        self != InviteScope::ReadOnly
+    }
+}
+
+impl fmt::Display for InviteScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            InviteScope::Edit => "edit",
+            InviteScope::ReadOnly => "read-only",
+            InviteScope::AgentOnly => "agent-only",
+        })
+    }
+}
+
+impl FromStr for InviteScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "edit" => Ok(InviteScope::Edit),
+            "read-only" | "readonly" => Ok(InviteScope::ReadOnly),
+            "agent-only" | "agent" => Ok(InviteScope::AgentOnly),
+            other => Err(format!("unknown scope {} (edit, read-only, agent-only)", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InviteStatus {
+    Pending,
+    Redeemed,
+    Revoked,
+    Expired,
+}
+
+impl fmt::Display for InviteStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            InviteStatus::Pending => "pending",
+            InviteStatus::Redeemed => "redeemed",
+            InviteStatus::Revoked => "revoked",
+            InviteStatus::Expired => "expired",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Invitation {
+    /// Short handle used to list and revoke the invitation; not enough to redeem it
+    pub id: String,
+    pub token: String,
+    pub session_id: String,
+    pub invitee: String,
+    pub scope: InviteScope,
+    /// Participant ID of the owner who issued it
+    pub issued_by: String,
+    /// Unix timestamps in seconds
+    pub created_at: u64,
+    pub expires_at: u64,
+    pub revoked: bool,
+    pub redeemed_by: Option<String>,
+}
+
+impl Invitation {
+    pub fn new(
+        session_id: &str,
+        issued_by: &str,
+        invitee: &str,
+        scope: InviteScope,
+        ttl: Duration,
+    ) -> Self {
+        let secret = Uuid::new_v4().simple().to_string();
+        let created_at = now();
+        Self {
+            id: Uuid::new_v4().simple().to_string()[..8].to_string(),
+            token: format!("{}.{}", session_id, secret),
+            session_id: session_id.to_string(),
+            invitee: invitee.to_string(),
+            scope,
+            issued_by: issued_by.to_string(),
+            created_at,
+            expires_at: created_at + ttl.as_secs(),
+            revoked: false,
+            redeemed_by: None,
+        }
+    }
+
+    pub fn status(&self, now: u64) -> InviteStatus {
+        if self.revoked {
+            InviteStatus::Revoked
+        } else if self.redeemed_by.is_some() {
+            InviteStatus::Redeemed
+        } else if now >= self.expires_at {
+            InviteStatus::Expired
+        } else {
+            InviteStatus::Pending
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InviteError {
+    UnknownSession,
+    NotOwner,
+    UnknownInvitation,
+    Expired,
+    Revoked,
+    AlreadyRedeemed,
+}
+
+impl fmt::Display for InviteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            InviteError::UnknownSession => "session not found",
+            InviteError::NotOwner => "only the session owner can manage invitations",
+            InviteError::UnknownInvitation => "invitation not found",
+            InviteError::Expired => "invitation has expired",
+            InviteError::Revoked => "invitation has been revoked",
+            InviteError::AlreadyRedeemed => "invitation has already been used",
+        })
+    }
+}
+
+impl std::error::Error for InviteError {}
+
+/// Session ID embedded in an invitation token.
+pub fn session_of(token: &str) -> Option<&str> {
+    token.rsplit_once('.').map(|(session, _)| session).filter(|s| !s.is_empty())
+}
+
+/// Parse a lifetime such as `90s`, `30m`, `12h` or `7d`; a bare number is seconds.
+pub fn parse_ttl(text: &str) -> Result<Duration, String> {
+    let text = text.trim();
+    let (number, unit) = match text.find(|c: char| !c.is_ascii_digit()) {
+        Some(split) => text.split_at(split),
+        None => (text, "s"),
+    };
+    let value: u64 = number.parse().map_err(|_| format!("invalid duration {}", text))?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(format!("invalid duration {} (use s, m, h or d)", text)),
+    };
+    Ok(Duration::from_secs(value * seconds))
+}
+
+pub(crate) fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LiveServer;
+
+    #[tokio::test]
+    async fn test_invitation_lifecycle() {
+        let server = LiveServer::new();
+        let session = server.create_session("demo").await;
+        let owner =
+            server.join_session(&session, "owner").await.unwrap().participants[0].id.clone();
+        assert!(server.join_session(&session, "mallory").await.is_none());
+
+        let invite = server
+            .create_invitation(&session, &owner, "ana", InviteScope::ReadOnly, DEFAULT_TTL)
+            .unwrap();
+        assert_eq!(session_of(&invite.token), Some(session.as_str()));
+        assert_eq!(
+            server
+                .create_invitation(&session, "someone", "eve", InviteScope::Edit, DEFAULT_TTL)
+                .unwrap_err(),
+            InviteError::NotOwner
+        );
+
+        let joined = server.redeem_invitation(&invite.token, "ana").await.unwrap();
+        let ana = joined.participants.iter().find(|p| p.name == "ana").unwrap();
+        assert_eq!(ana.scope, InviteScope::ReadOnly);
+        assert!(server.handle_terminal_input(&session, &ana.id, "status").await.is_err());
+        let stranger = uuid::Uuid::new_v4().to_string();
+        let refused = server.handle_code_edit(&session, &stranger, "main.rs", "evil").await;
+        assert_eq!(refused.unwrap_err().to_string(), "not a participant of this session");
+        let refused = server.handle_terminal_input(&session, &stranger, "status").await;
+        assert_eq!(refused.unwrap_err().to_string(), "not a participant of this session");
+        assert!(server.open_file(&session, &owner, "main.rs").is_err());
+        assert_eq!(
+            server.redeem_invitation(&invite.token, "ana").await.unwrap_err(),
+            InviteError::AlreadyRedeemed
+        );
+
+        let revoked = server
+            .create_invitation(&session, &owner, "bo", InviteScope::AgentOnly, DEFAULT_TTL)
+            .unwrap();
+        server.revoke_invitation(&session, &owner, &revoked.id).unwrap();
+        assert_eq!(
+            server.redeem_invitation(&revoked.token, "bo").await.unwrap_err(),
+            InviteError::Revoked
+        );
+
+        let expired = server
+            .create_invitation(&session, &owner, "cy", InviteScope::Edit, Duration::ZERO)
+            .unwrap();
+        assert_eq!(
+            server.redeem_invitation(&expired.token, "cy").await.unwrap_err(),
+            InviteError::Expired
+        );
+        assert_eq!(parse_ttl("2h"), Ok(Duration::from_secs(7200)));
+        assert!(parse_ttl("2w").is_err());
+    }
+}
@@ -1,26 +1,75 @@
+use audit::{AuditEvent, AuditLog};
 use dashmap::DashMap;
+use invites::{Invitation, InviteError, InviteScope};
+use parflow_core::languages;
+use parflow_test_orchestrator::TestResult;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::broadcast;
+use tracing::{info, instrument, warn};
 use uuid::Uuid;
 
+pub mod audit;
+pub mod chat;
+pub mod debug;
+pub mod edits;
+pub mod files;
+pub mod git;
+pub mod invites;
+mod metrics;
+pub mod presence;
+pub mod registry;
+pub mod sandbox;
+pub mod terminal;
+pub mod test_runs;
+
+pub use chat::{ChatEntry, CodeAnchor};
+pub use edits::EditOp;
+pub use files::{FileTree, ImportReport};
+pub use git::{CommitSummary, GitBridge, GitStatus};
+pub use presence::{Presence, PresenceState};
+pub use sandbox::{Isolation, SandboxPolicy};
+pub use terminal::TerminalSize;
+pub use test_runs::{ShardProgress, TestRunStatus};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LiveSession {
     pub session_id: String,
     pub project_name: String,
+    /// Participant ID of the first user to join; later users need an invitation
+    pub owner_id: Option<String>,
     pub participants: Vec<Participant>,
     pub shared_terminal: SharedTerminal,
     pub code_files: Vec<CodeFile>,
     pub compilation_results: CompilationStatus,
+    #[serde(default)]
+    pub sandbox: SandboxPolicy,
+    /// Chat messages and code comments, oldest first
+    #[serde(default)]
+    pub chat: Vec<ChatEntry>,
+    /// Directory the files were imported from
+    #[serde(default)]
+    pub root: Option<std::path::PathBuf>,
+    /// Files deleted or renamed away since the last sync to the root
+    #[serde(default)]
+    pub removed: std::collections::BTreeSet<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Participant {
     pub id: String,
     pub name: String,
+    #[serde(default)]
+    pub scope: InviteScope,
+    /// Address commits made in the session are attributed to
+    #[serde(default)]
+    pub email: Option<String>,
     pub terminal_tab: TerminalTab,
     pub resources: ParticipantResources,
     pub cursor_position: CursorPosition,
+    #[serde(default)]
+    pub presence: Presence,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,6 +93,8 @@ pub struct TerminalTab {
     pub tab_name: String,
     pub content: String,
     pub is_active: bool,
+    #[serde(default)]
+    pub size: TerminalSize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -112,23 +163,83 @@ pub enum ParticipantRole {
     ResourceProvider,
 }
 
+/// Bytes of output each terminal tab keeps; older output is dropped
+pub const TERMINAL_SCROLLBACK: usize = 64 * 1024;
+
+/// Sizes of the server's session state, for spotting unbounded growth
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ServerStats {
+    pub sessions: usize,
+    pub participants: usize,
+    /// Live receivers across all session broadcast channels
+    pub subscribers: usize,
+    pub invitations: usize,
+    /// Output held by all terminal tabs
+    pub terminal_bytes: usize,
+}
+
+/// Drop the oldest output of `content` so it fits in [`TERMINAL_SCROLLBACK`].
+fn trim_scrollback(content: &mut String) {
+    if content.len() > TERMINAL_SCROLLBACK {
+        let mut cut = content.len() - TERMINAL_SCROLLBACK;
+        while !content.is_char_boundary(cut) {
+            cut += 1;
+        }
+        content.drain(..cut);
+    }
+}
+
 #[derive(Default)]
 pub struct LiveServer {
     sessions: Arc<DashMap<String, LiveSession>>,
     broadcast_senders: Arc<DashMap<String, broadcast::Sender<LiveUpdate>>>,
+    /// Keyed by token
+    invitations: Arc<DashMap<String, Invitation>>,
+    /// Current or last sharded test run, keyed by session
+    test_runs: Arc<DashMap<String, test_runs::TestRun>>,
+    /// Running shells, keyed by session and tab
+    terminals: Arc<DashMap<(String, String), std::sync::Mutex<terminal::Pty>>>,
+    /// Debug adapter bridges, keyed by session
+    debuggers: Arc<DashMap<String, Arc<debug::DebugBridge>>>,
+    /// Policy new sessions start with
+    sandbox: SandboxPolicy,
+    audit: Option<Arc<AuditLog>>,
 }
 
 impl LiveServer {
     pub fn new() -> Self {
-        Self { sessions: Arc::new(DashMap::new()), broadcast_senders: Arc::new(DashMap::new()) }
+        Self::default()
+    }
+
+    /// Record joins, invitations, terminal input and edits of every session in `log`.
+    pub fn with_audit(mut self, log: Arc<AuditLog>) -> Self {
+        self.audit = Some(log);
+        self
+    }
+
+    /// Start new sessions under `policy` instead of the unrestricted default.
+    pub fn with_sandbox(mut self, policy: SandboxPolicy) -> Self {
+        self.sandbox = policy;
+        self
+    }
+
+    fn audit(&self, session_id: &str, actor: Option<&str>, event: AuditEvent) {
+        if let Some(log) = &self.audit {
+            if let Err(e) = log.record(session_id, actor, event) {
+                warn!(session = session_id, error = %e, "⚠️  Audit record lost");
+            }
+        }
     }
 
+    #[instrument(name = "session", skip_all, fields(session, project = project_name))]
     pub async fn create_session(&self, project_name: &str) -> String {
         let session_id = Uuid::new_v4().to_string();
+        tracing::Span::current().record("session", session_id.as_str());
 
         let session = LiveSession {
             session_id: session_id.clone(),
             project_name: project_name.to_string(),
+            owner_id: None,
             participants: Vec::new(),
             shared_terminal: SharedTerminal {
                 active_tabs: vec![TerminalTab {
@@ -137,6 +248,7 @@ impl LiveServer {
                     content: "Welcome to ParFlow Live! 👋\n\nType 'help' for available commands."
                         .to_string(),
                     is_active: true,
+                    size: TerminalSize::default(),
                 }],
                 broadcast_channel: format!("session_{}", session_id),
             },
@@ -147,71 +259,280 @@ impl LiveServer {
                 errors: Vec::new(),
                 warnings: Vec::new(),
             },
+            sandbox: self.sandbox.clone(),
+            chat: Vec::new(),
+            root: None,
+            removed: Default::default(),
         };
 
         let (tx, _) = broadcast::channel(100);
         self.broadcast_senders.insert(session_id.clone(), tx);
         self.sessions.insert(session_id.clone(), session);
+        metrics::sessions(1);
+        self.audit(
+            &session_id,
+            None,
+            AuditEvent::SessionCreated { project: project_name.to_string() },
+        );
+        info!("🟢 Session created");
 
         session_id
     }
 
+    /// Drop a session with its channel and invitations; subscribers see the channel close.
+    #[instrument(name = "session", skip_all, fields(session = session_id))]
+    pub async fn end_session(&self, session_id: &str) -> bool {
+        let ended = self.sessions.remove(session_id);
+        self.broadcast_senders.remove(session_id);
+        self.invitations.retain(|_, invitation| invitation.session_id != session_id);
+        self.test_runs.remove(session_id);
+        self.close_terminals(session_id, None);
+        if let Some((_, bridge)) = self.debuggers.remove(session_id) {
+            bridge.stop().await;
+        }
+        let Some((_, session)) = ended else { return false };
+        metrics::participants(-(session.participants.len() as i64));
+        metrics::sessions(-1);
+        self.audit(session_id, None, AuditEvent::SessionEnded);
+        info!("⏹️  Session ended");
+        true
+    }
+
+    pub fn stats(&self) -> ServerStats {
+        let mut stats = ServerStats {
+            sessions: self.sessions.len(),
+            invitations: self.invitations.len(),
+            subscribers: self.broadcast_senders.iter().map(|tx| tx.receiver_count()).sum(),
+            ..ServerStats::default()
+        };
+        for session in self.sessions.iter() {
+            stats.participants += session.participants.len();
+            stats.terminal_bytes +=
+                session.shared_terminal.active_tabs.iter().map(|t| t.content.len()).sum::<usize>();
+        }
+        stats
+    }
+
+    /// Join without an invitation. Only the first user, who becomes the owner, can do this.
+    #[instrument(name = "session", skip_all, fields(session = session_id))]
     pub async fn join_session(&self, session_id: &str, user_name: &str) -> Option<LiveSession> {
-        if let Some(mut session) = self.sessions.get_mut(session_id) {
-            let participant = Participant {
-                id: Uuid::new_v4().to_string(),
+        let owned = self.sessions.get(session_id)?.owner_id.is_some();
+        if owned {
+            return None;
+        }
+        self.add_participant(session_id, user_name, InviteScope::Edit, None)
+    }
+
+    /// Join with an invitation token, taking on the invitation's scope.
+    pub async fn redeem_invitation(
+        &self,
+        token: &str,
+        user_name: &str,
+    ) -> Result<LiveSession, InviteError> {
+        let (session_id, scope, invitation_id) = {
+            let mut invitation =
+                self.invitations.get_mut(token).ok_or(InviteError::UnknownInvitation)?;
+            match invitation.status(invites::now()) {
+                invites::InviteStatus::Pending => {}
+                invites::InviteStatus::Redeemed => return Err(InviteError::AlreadyRedeemed),
+                invites::InviteStatus::Revoked => return Err(InviteError::Revoked),
+                invites::InviteStatus::Expired => return Err(InviteError::Expired),
+            }
+            invitation.redeemed_by = Some(user_name.to_string());
+            (invitation.session_id.clone(), invitation.scope, invitation.id.clone())
+        };
+        self.add_participant(&session_id, user_name, scope, Some(&invitation_id))
+            .ok_or(InviteError::UnknownSession)
+    }
+
+    fn add_participant(
+        &self,
+        session_id: &str,
+        user_name: &str,
+        scope: InviteScope,
+        invitation: Option<&str>,
+    ) -> Option<LiveSession> {
+        let mut session = self.sessions.get_mut(session_id)?;
+        let participant = Participant {
+            id: Uuid::new_v4().to_string(),
+            name: user_name.to_string(),
+            scope,
+            email: None,
+            terminal_tab: TerminalTab {
+                tab_id: Uuid::new_v4().to_string(),
+                tab_name: format!("{}'s Terminal", user_name),
+                content: String::new(),
+                is_active: false,
+                size: TerminalSize::default(),
+            },
+            resources: ParticipantResources::default(),
+            cursor_position: CursorPosition::default(),
+            presence: Presence::new(invites::now()),
+        };
+
+        if session.owner_id.is_none() {
+            session.owner_id = Some(participant.id.clone());
+        }
+        self.audit(
+            session_id,
+            Some(&participant.id),
+            AuditEvent::Joined {
                 name: user_name.to_string(),
-                terminal_tab: TerminalTab {
-                    tab_id: Uuid::new_v4().to_string(),
-                    tab_name: format!("{}'s Terminal", user_name),
-                    content: String::new(),
-                    is_active: false,
-                },
-                resources: ParticipantResources::default(),
-                cursor_position: CursorPosition::default(),
-            };
+                scope,
+                invitation: invitation.map(str::to_string),
+            },
+        );
+        session.participants.push(participant);
+        metrics::participants(1);
 
-            session.participants.push(participant);
+        if let Some(tx) = self.broadcast_senders.get(session_id) {
+            let _ = tx.send(LiveUpdate::UserJoined {
+                user_name: user_name.to_string(),
+                participant_count: session.participants.len(),
+            });
+        }
 
-            if let Some(tx) = self.broadcast_senders.get(session_id) {
-                let _ = tx.send(LiveUpdate::UserJoined {
-                    user_name: user_name.to_string(),
-                    participant_count: session.participants.len(),
-                });
-            }
+        Some(session.clone())
+    }
+
+    #[instrument(name = "session", skip_all, fields(session = session_id))]
+    pub async fn leave_session(&self, session_id: &str, user_id: &str) {
+        let Some(mut session) = self.sessions.get_mut(session_id) else { return };
+        let Some(index) = session.participants.iter().position(|p| p.id == user_id) else {
+            return;
+        };
+        let participant = session.participants.remove(index);
+        metrics::participants(-1);
+        self.audit(session_id, Some(user_id), AuditEvent::Left);
+
+        if let Some(tx) = self.broadcast_senders.get(session_id) {
+            let _ = tx.send(LiveUpdate::UserLeft {
+                user_name: participant.name.clone(),
+                participant_count: session.participants.len(),
+            });
+        }
+        drop(session);
+        self.abandon_shard(session_id, user_id);
+        self.close_terminals(session_id, Some(&participant.terminal_tab.tab_id));
+    }
+
+    fn ensure_owner(&self, session_id: &str, user_id: &str) -> Result<(), InviteError> {
+        let session = self.sessions.get(session_id).ok_or(InviteError::UnknownSession)?;
+        if session.owner_id.as_deref() == Some(user_id) {
+            Ok(())
+        } else {
+            Err(InviteError::NotOwner)
+        }
+    }
+
+    pub fn create_invitation(
+        &self,
+        session_id: &str,
+        owner_id: &str,
+        invitee: &str,
+        scope: InviteScope,
+        ttl: Duration,
+    ) -> Result<Invitation, InviteError> {
+        self.ensure_owner(session_id, owner_id)?;
+        let invitation = Invitation::new(session_id, owner_id, invitee, scope, ttl);
+        self.invitations.insert(invitation.token.clone(), invitation.clone());
+        self.audit(
+            session_id,
+            Some(owner_id),
+            AuditEvent::InvitationCreated {
+                invitation: invitation.id.clone(),
+                invitee: invitee.to_string(),
+                scope,
+                expires_at: invitation.expires_at,
+            },
+        );
+        Ok(invitation)
+    }
 
-            return Some(session.clone());
+    /// Revoke a pending invitation by its short ID. Participants who already joined stay.
+    pub fn revoke_invitation(
+        &self,
+        session_id: &str,
+        owner_id: &str,
+        invitation_id: &str,
+    ) -> Result<Invitation, InviteError> {
+        self.ensure_owner(session_id, owner_id)?;
+        let mut invitation = self
+            .invitations
+            .iter_mut()
+            .find(|i| i.session_id == session_id && i.id == invitation_id)
+            .ok_or(InviteError::UnknownInvitation)?;
+        if invitation.redeemed_by.is_some() {
+            return Err(InviteError::AlreadyRedeemed);
         }
-        None
+        invitation.revoked = true;
+        let invitation = invitation.clone();
+        self.audit(
+            session_id,
+            Some(owner_id),
+            AuditEvent::InvitationRevoked { invitation: invitation.id.clone() },
+        );
+        Ok(invitation)
     }
 
+    /// Invitations issued for a session, oldest first.
+    pub fn invitations(&self, session_id: &str) -> Vec<Invitation> {
+        let mut invitations: Vec<Invitation> = self
+            .invitations
+            .iter()
+            .filter(|i| i.session_id == session_id)
+            .map(|i| i.clone())
+            .collect();
+        invitations.sort_by_key(|i| i.created_at);
+        invitations
+    }
+
+    #[instrument(name = "session", skip_all, fields(session = session_id))]
     pub async fn handle_terminal_input(
         &self,
         session_id: &str,
         user_id: &str,
         input: &str,
     ) -> Result<(), anyhow::Error> {
+        let scope = self.participant_scope(session_id, user_id)?;
+        if !scope.can_edit() {
+            anyhow::bail!("{} participants cannot use the shared terminal", scope);
+        }
+
+        self.audit(
+            session_id,
+            Some(user_id),
+            AuditEvent::TerminalInput { input: input.to_string() },
+        );
+        self.mark_active(session_id, user_id);
+        // The session must not be locked while the command runs, as commands read it
+        let output = self.execute_command(input, session_id, user_id).await?;
+
         if let Some(mut session) = self.sessions.get_mut(session_id) {
-            if let Some(_participant) = session.participants.iter_mut().find(|p| p.id == user_id) {
-                if let Some(active_tab) =
-                    session.shared_terminal.active_tabs.iter_mut().find(|t| t.is_active)
-                {
-                    let output = self.execute_command(input, session_id).await?;
-
-                    active_tab.content.push_str(&format!("\n$ {}\n{}", input, output));
-
-                    if let Some(tx) = self.broadcast_senders.get(session_id) {
-                        let _ = tx.send(LiveUpdate::TerminalOutput {
-                            tab_id: active_tab.tab_id.clone(),
-                            content: active_tab.content.clone(),
-                        });
-                    }
+            if let Some(active_tab) =
+                session.shared_terminal.active_tabs.iter_mut().find(|t| t.is_active)
+            {
+                // Anything that is not a ParFlow command goes to the tab's shell, which echoes it
+                let Some(output) = output else {
+                    let tab_id = active_tab.tab_id.clone();
+                    drop(session);
+                    return self.write_tab(session_id, &tab_id, format!("{}\n", input).as_bytes());
+                };
+                active_tab.content.push_str(&format!("\n$ {}\n{}", input, output));
+                trim_scrollback(&mut active_tab.content);
+
+                if let Some(tx) = self.broadcast_senders.get(session_id) {
+                    let _ = tx.send(LiveUpdate::TerminalOutput {
+                        tab_id: active_tab.tab_id.clone(),
+                        content: active_tab.content.clone(),
+                    });
                 }
             }
         }
         Ok(())
     }
 
+    #[instrument(name = "session", skip_all, fields(session = session_id))]
     pub async fn handle_code_edit(
         &self,
         session_id: &str,
@@ -219,7 +540,20 @@ impl LiveServer {
         filename: &str,
         new_content: &str,
     ) -> Result<(), anyhow::Error> {
+        let scope = self.participant_scope(session_id, user_id)?;
+        if !scope.can_edit() {
+            anyhow::bail!("{} participants cannot edit code", scope);
+        }
         if let Some(mut session) = self.sessions.get_mut(session_id) {
+            self.audit(
+                session_id,
+                Some(user_id),
+                AuditEvent::CodeEdited {
+                    filename: filename.to_string(),
+                    content: new_content.to_string(),
+                },
+            );
+            session.removed.remove(filename);
             if let Some(file) = session.code_files.iter_mut().find(|f| f.filename == filename) {
                 file.content = new_content.to_string();
                 file.last_modified_by = user_id.to_string();
@@ -232,6 +566,9 @@ impl LiveServer {
                     compilation_status: CompilationStatus::default(),
                 });
             }
+            // Compiling locks the session again
+            drop(session);
+            self.mark_active(session_id, user_id);
 
             self.trigger_compilation(session_id).await?;
 
@@ -246,6 +583,18 @@ impl LiveServer {
         Ok(())
     }
 
+    /// The scope `user_id` joined the session with, or an error if they are not in it
+    fn participant_scope(&self, session_id: &str, user_id: &str) -> anyhow::Result<InviteScope> {
+        let session =
+            self.sessions.get(session_id).ok_or_else(|| anyhow::anyhow!("unknown session"))?;
+        let participant = session
+            .participants
+            .iter()
+            .find(|p| p.id == user_id)
+            .ok_or_else(|| anyhow::anyhow!("not a participant of this session"))?;
+        Ok(participant.scope)
+    }
+
     pub async fn update_cursor_position(
         &self,
         session_id: &str,
@@ -270,6 +619,26 @@ impl LiveServer {
                 }
             }
         }
+        self.mark_active(session_id, user_id);
+        Ok(())
+    }
+
+    /// Record the resources `user_id` offers to the session, which weight its share of sharded
+    /// test runs.
+    pub fn declare_resources(
+        &self,
+        session_id: &str,
+        user_id: &str,
+        resources: ParticipantResources,
+    ) -> Result<(), anyhow::Error> {
+        let mut session =
+            self.sessions.get_mut(session_id).ok_or_else(|| anyhow::anyhow!("unknown session"))?;
+        let participant = session
+            .participants
+            .iter_mut()
+            .find(|p| p.id == user_id)
+            .ok_or_else(|| anyhow::anyhow!("not a participant of this session"))?;
+        participant.resources = resources;
         Ok(())
     }
 
@@ -277,11 +646,36 @@ impl LiveServer {
         &self,
         command: &str,
         session_id: &str,
-    ) -> Result<String, anyhow::Error> {
+        user_id: &str,
+    ) -> Result<Option<String>, anyhow::Error> {
+        if let Some(args) = command.trim().strip_prefix("invite ") {
+            return Ok(Some(self.invite_command(session_id, user_id, args)));
+        }
+        if let Some(args) = command.trim().strip_prefix("invites") {
+            return Ok(Some(self.invites_command(session_id, user_id, args.trim())));
+        }
+        if let Some(message) = command.trim().strip_prefix("commit ") {
+            return Ok(Some(self.commit_command(session_id, user_id, message).await));
+        }
+        if command.trim() == "tests" {
+            return Ok(Some(self.tests_command(session_id)));
+        }
+        if let Some(languages) = command.trim().strip_prefix("test") {
+            if languages.is_empty() || languages.starts_with(' ') {
+                return Ok(Some(self.test_command(session_id, user_id, languages).await));
+            }
+        }
+
         match command.trim() {
             "help" => Ok("Available commands:\n• code <file> - Edit a code file\n• compile - \
                           Trigger compilation\n• status - Show session status\n• resources - \
-                          Show shared resources\n• invite <user> - Invite another user"
+                          Show shared resources\n• invite <user> [--scope edit|read-only|\
+                          agent-only] [--expires 24h] - Invite another user\n• invites \
+                          [revoke <id>] - List or revoke invitations\n• test [languages] - Run \
+                          tests sharded across participants\n• tests - Show test run progress\n• \
+                          sandbox - Show what this session may run\n• files - Show the project \
+                          files\n• commit <message> - Commit the project, crediting all \
+                          editors\n• debug - Show how to attach a debugger\n• anything else - Run it in the terminal's shell"
                 .to_string()),
             "compile" => {
                 self.trigger_compilation(session_id).await?;
@@ -334,21 +728,108 @@ impl LiveServer {
                     Ok("Session not found".to_string())
                 }
             }
-            _ => Ok(format!("Executed: {}", command)),
+            "sandbox" => Ok(self.sandbox_policy(session_id).to_string()),
+            "files" => Ok(self.file_tree(session_id).to_string()),
+            "debug" => Ok(self.debug_command(session_id)),
+            _ => match self.sandbox_policy(session_id).check(command) {
+                Ok(()) => return Ok(None),
+                Err(reason) => Ok(format!("⛔ {}", reason)),
+            },
         }
+        .map(Some)
+    }
+
+    /// `invite <user> [--scope <scope>] [--expires <ttl>]`
+    fn invite_command(&self, session_id: &str, user_id: &str, args: &str) -> String {
+        let mut words = args.split_whitespace();
+        let Some(invitee) = words.next() else {
+            return "Usage: invite <user> [--scope edit|read-only|agent-only] [--expires 24h]"
+                .to_string();
+        };
+        let mut scope = InviteScope::Edit;
+        let mut ttl = invites::DEFAULT_TTL;
+        while let Some(flag) = words.next() {
+            let value = words.next().unwrap_or_default();
+            let parsed = match flag {
+                "--scope" => value.parse().map(|s| scope = s),
+                "--expires" => invites::parse_ttl(value).map(|t| ttl = t),
+                other => Err(format!("unknown option {}", other)),
+            };
+            if let Err(e) = parsed {
+                return format!("❌ {}", e);
+            }
+        }
+
+        match self.create_invitation(session_id, user_id, invitee, scope, ttl) {
+            Ok(invitation) => format!(
+                "🎟️  Invitation {} for {} ({}, expires in {})\nShare privately: parflow \
+                 live-join --token {} --name {}",
+                invitation.id,
+                invitee,
+                scope,
+                format_ttl(ttl),
+                invitation.token,
+                invitee
+            ),
+            Err(e) => format!("❌ {}", e),
+        }
+    }
+
+    /// `invites` lists invitations, `invites revoke <id>` revokes one.
+    fn invites_command(&self, session_id: &str, user_id: &str, args: &str) -> String {
+        if let Err(e) = self.ensure_owner(session_id, user_id) {
+            return format!("❌ {}", e);
+        }
+        if let Some(id) = args.strip_prefix("revoke") {
+            return match self.revoke_invitation(session_id, user_id, id.trim()) {
+                Ok(invitation) => {
+                    format!("🚫 Revoked invitation {} for {}", invitation.id, invitation.invitee)
+                }
+                Err(e) => format!("❌ {}", e),
+            };
+        }
+        if !args.is_empty() {
+            return "Usage: invites [revoke <id>]".to_string();
+        }
+
+        let invitations = self.invitations(session_id);
+        if invitations.is_empty() {
+            return "No invitations. Create one with: invite <user>".to_string();
+        }
+        let now = invites::now();
+        let mut lines = vec!["Invitations:".to_string()];
+        for invitation in invitations {
+            let status = invitation.status(now);
+            let detail = match (&invitation.redeemed_by, status) {
+                (Some(name), _) => format!("by {}", name),
+                (None, invites::InviteStatus::Pending) => format!(
+                    "expires in {}",
+                    format_ttl(Duration::from_secs(invitation.expires_at - now))
+                ),
+                _ => String::new(),
+            };
+            lines.push(format!(
+                "• {} {} ({}) - {} {}",
+                invitation.id, invitation.invitee, invitation.scope, status, detail
+            ));
+        }
+        lines.join("\n")
     }
 
     async fn trigger_compilation(&self, session_id: &str) -> Result<(), anyhow::Error> {
         if let Some(mut session) = self.sessions.get_mut(session_id) {
             session.compilation_results.status = CompilationState::Compiling;
+        } else {
+            return Ok(());
+        }
+        if let Some(tx) = self.broadcast_senders.get(session_id) {
+            let _ = tx.send(LiveUpdate::CompilationStarted);
+        }
 
-            if let Some(tx) = self.broadcast_senders.get(session_id) {
-                let _ = tx.send(LiveUpdate::CompilationStarted);
-            }
-
-            // Simulate compilation
-            tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+        // Simulate compilation, without keeping the session locked meanwhile
+        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
 
+        if let Some(mut session) = self.sessions.get_mut(session_id) {
             // Mock compilation results
             session.compilation_results = CompilationStatus {
                 status: CompilationState::Success,
@@ -376,15 +857,8 @@ impl LiveServer {
     }
 
     fn detect_language(&self, filename: &str) -> String {
-        if filename.ends_with(".rs") {
-            "rust".to_string()
-        } else if filename.ends_with(".py") {
-            "python".to_string()
-        } else if filename.ends_with(".js") || filename.ends_with(".ts") {
-            "javascript".to_string()
-        } else {
-            "unknown".to_string()
-        }
+        languages::for_path(filename)
+            .map_or_else(|| "unknown".to_string(), |plugin| plugin.name().to_string())
     }
 
     pub fn subscribe_to_updates(
@@ -394,30 +868,21 @@ impl LiveServer {
         self.broadcast_senders.get(session_id).map(|tx| tx.subscribe())
     }
 
+    #[instrument(name = "session", skip_all, fields(session = session_id))]
     pub async fn distribute_compilation(&self, session_id: &str) -> Result<(), anyhow::Error> {
         if let Some(session) = self.sessions.get(session_id) {
-            let total_cores: u32 =
-                session.participants.iter().map(|p| p.resources.available_cpu_cores).sum();
-            let total_memory: f64 =
-                session.participants.iter().map(|p| p.resources.available_memory_gb).sum();
-
-            println!(
-                "{} {} cores, {}GB memory",
-                "🔄 Distributing compilation across:".bright_green(),
-                total_cores,
-                total_memory
-            );
+            // Read-only observers watch the build but do not run any of it
+            let workers: Vec<&Participant> =
+                session.participants.iter().filter(|p| p.scope.contributes_resources()).collect();
+            let total_cores: u32 = workers.iter().map(|p| p.resources.available_cpu_cores).sum();
+            let total_memory: f64 = workers.iter().map(|p| p.resources.available_memory_gb).sum();
+
+            info!(cores = total_cores, memory_gb = total_memory, "🔄 Distributing compilation");
 
             // Distribute compilation tasks
             for (i, file) in session.code_files.iter().enumerate() {
-                if let Some(participant) = session.participants.get(i % session.participants.len())
-                {
-                    println!(
-                        "{} {} → {}",
-                        "📦 Compiling:".bright_blue(),
-                        file.filename,
-                        participant.name
-                    );
+                if let Some(participant) = workers.get(i % workers.len().max(1)) {
+                    info!(file = %file.filename, worker = %participant.name, "📦 Compiling");
                 }
             }
         }
@@ -431,6 +896,11 @@ pub enum LiveUpdate {
         user_name: String,
         participant_count: usize,
     },
+    PresenceChanged {
+        user_id: String,
+        user_name: String,
+        state: PresenceState,
+    },
     UserLeft {
         user_name: String,
         participant_count: usize,
@@ -439,6 +909,47 @@ pub enum LiveUpdate {
         tab_id: String,
         content: String,
     },
+    /// Raw output of a tab's shell, escape sequences included
+    TerminalData {
+        tab_id: String,
+        data: String,
+    },
+    TerminalResized {
+        tab_id: String,
+        size: TerminalSize,
+    },
+    ChatMessage {
+        id: String,
+        user_name: String,
+        text: String,
+    },
+    CodeComment {
+        id: String,
+        user_name: String,
+        file: String,
+        line: u32,
+        text: String,
+    },
+    FilesImported {
+        files: Vec<String>,
+    },
+    /// Also sent when the file was just loaded from disk, so it may be new to the tree
+    FileOpened {
+        filename: String,
+        user_name: String,
+    },
+    FileRenamed {
+        from: String,
+        to: String,
+        user_name: String,
+    },
+    FileDeleted {
+        filename: String,
+        user_name: String,
+    },
+    Committed {
+        summary: CommitSummary,
+    },
     CodeChanged {
         filename: String,
         content: String,
@@ -450,6 +961,23 @@ pub enum LiveUpdate {
         filename: String,
         position: CursorPosition,
     },
+    TestRunStarted {
+        run_id: String,
+        shards: Vec<ShardProgress>,
+    },
+    TestShardProgress {
+        run_id: String,
+        progress: ShardProgress,
+    },
+    TestRunFinished {
+        run_id: String,
+        results: Vec<TestResult>,
+    },
+    /// An event of the session's debug adapter, e.g. `stopped` or `output`, with its DAP body
+    DebugEvent {
+        event: String,
+        body: serde_json::Value,
+    },
     CompilationStarted,
     CompilationFinished {
         status: CompilationState,
@@ -470,6 +998,19 @@ impl Default for ParticipantResources {
     }
 }
 
+fn format_ttl(ttl: Duration) -> String {
+    let secs = ttl.as_secs();
+    if secs >= 24 * 60 * 60 {
+        format!("{}d", secs / (24 * 60 * 60))
+    } else if secs >= 60 * 60 {
+        format!("{}h", secs / (60 * 60))
+    } else if secs >= 60 {
+        format!("{}m", secs / 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
 // Remove the manual Default implementation since we're using #[derive(Default)]
 // impl Default for CursorPosition {
 //     fn default() -> Self {
@@ -487,6 +1028,3 @@ impl Default for CompilationStatus {
         }
     }
 }
-
-// Import colored for the distribute_compilation method
-use colored::*;
@@ -0,0 +1,28 @@
+//! Instruments for live sessions
+//!
+//! Participants go through the OpenTelemetry global meter provider and the session count through
+//! the `metrics` facade, so both cost nothing until a binary installs an exporter (see
+//! `parflow_core::telemetry`) or a recorder (parflow-rest's `/metrics`).
+
+use opentelemetry_api::global;
+use opentelemetry_api::metrics::UpDownCounter;
+use std::sync::OnceLock;
+
+/// `count` sessions were created (positive) or ended (negative).
+pub(crate) fn sessions(count: i64) {
+    ::metrics::gauge!("parflow_live_sessions_active", "subsystem" => "live")
+        .increment(count as f64);
+}
+
+/// `count` participants joined (positive) or left (negative) a session.
+pub(crate) fn participants(count: i64) {
+    static PARTICIPANTS: OnceLock<UpDownCounter<i64>> = OnceLock::new();
+    PARTICIPANTS
+        .get_or_init(|| {
+            global::meter("parflow-live-server")
+                .i64_up_down_counter("parflow.session.participants")
+                .with_description("Participants currently in live sessions")
+                .init()
+        })
+        .add(count, &[]);
+}
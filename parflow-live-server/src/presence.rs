@@ -0,0 +1,191 @@
+//! Who in a live session is actually around
+//!
+//! Anything a participant does in the session marks them active, and clients send heartbeats
+//! while they are open. Participants who have done nothing for [`IDLE_AFTER`] are idle; ones not
+//! heard from at all for [`DISCONNECTED_AFTER`] are disconnected, though they stay in the
+//! session until they leave. Changes are broadcast as [`LiveUpdate::PresenceChanged`]: right
+//! away when someone comes back, and by [`spawn_monitor`] when time passes.
+
+use crate::{invites, LiveServer, LiveUpdate};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Seconds without activity before a participant is idle
+pub const IDLE_AFTER: u64 = 5 * 60;
+/// Seconds without activity or heartbeat before a participant is disconnected
+pub const DISCONNECTED_AFTER: u64 = 90;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PresenceState {
+    #[default]
+    Active,
+    Idle,
+    Disconnected,
+}
+
+impl fmt::Display for PresenceState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PresenceState::Active => "active",
+            PresenceState::Idle => "idle",
+            PresenceState::Disconnected => "disconnected",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Presence {
+    pub state: PresenceState,
+    /// Unix time in seconds of the last thing the participant did
+    pub last_active: u64,
+    /// Unix time in seconds of the last activity or heartbeat
+    pub last_seen: u64,
+}
+
+impl Presence {
+    pub fn new(now: u64) -> Self {
+        Self { state: PresenceState::Active, last_active: now, last_seen: now }
+    }
+
+    /// What the state should be at `now`
+    pub fn state_at(&self, now: u64) -> PresenceState {
+        if now.saturating_sub(self.last_seen) >= DISCONNECTED_AFTER {
+            PresenceState::Disconnected
+        } else if now.saturating_sub(self.last_active) >= IDLE_AFTER {
+            PresenceState::Idle
+        } else {
+            PresenceState::Active
+        }
+    }
+}
+
+impl LiveServer {
+    /// Note that a participant did something.
+    pub(crate) fn mark_active(&self, session_id: &str, user_id: &str) {
+        self.update_presence(session_id, user_id, |presence, now| {
+            presence.last_active = now;
+            presence.last_seen = now;
+        });
+    }
+
+    /// Note that a participant's client is still there, without them doing anything.
+    pub fn heartbeat(&self, session_id: &str, user_id: &str) -> Result<()> {
+        if !self.update_presence(session_id, user_id, |presence, now| presence.last_seen = now) {
+            return Err(anyhow!("not a participant of this session"));
+        }
+        Ok(())
+    }
+
+    /// Recompute everyone's presence at `now`, broadcasting changes. Returns how many changed.
+    pub fn refresh_presence(&self, now: u64) -> usize {
+        let mut changes = Vec::new();
+        for mut session in self.sessions.iter_mut() {
+            let session_id = session.session_id.clone();
+            for participant in &mut session.participants {
+                let state = participant.presence.state_at(now);
+                if state != participant.presence.state {
+                    participant.presence.state = state;
+                    changes.push((session_id.clone(), participant.id.clone(), state));
+                }
+            }
+        }
+        for (session_id, user_id, state) in &changes {
+            self.broadcast_presence(session_id, user_id, *state);
+        }
+        changes.len()
+    }
+
+    /// Change a participant's presence with `update`, broadcasting if their state changes as a
+    /// result. Returns whether they are in the session.
+    fn update_presence(
+        &self,
+        session_id: &str,
+        user_id: &str,
+        update: impl FnOnce(&mut Presence, u64),
+    ) -> bool {
+        let now = invites::now();
+        let state = {
+            let Some(mut session) = self.sessions.get_mut(session_id) else { return false };
+            let Some(participant) = session.participants.iter_mut().find(|p| p.id == user_id)
+            else {
+                return false;
+            };
+            update(&mut participant.presence, now);
+            let state = participant.presence.state_at(now);
+            if state == participant.presence.state {
+                return true;
+            }
+            participant.presence.state = state;
+            state
+        };
+        self.broadcast_presence(session_id, user_id, state);
+        true
+    }
+
+    fn broadcast_presence(&self, session_id: &str, user_id: &str, state: PresenceState) {
+        let user_name = self.sessions.get(session_id).and_then(|session| {
+            session.participants.iter().find(|p| p.id == user_id).map(|p| p.name.clone())
+        });
+        if let (Some(user_name), Some(tx)) = (user_name, self.broadcast_senders.get(session_id)) {
+            let _ = tx.send(LiveUpdate::PresenceChanged {
+                user_id: user_id.to_string(),
+                user_name,
+                state,
+            });
+        }
+    }
+}
+
+/// Refresh presence every `interval` for as long as the returned task is alive.
+pub fn spawn_monitor(server: Arc<LiveServer>, interval: Duration) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            server.refresh_presence(invites::now());
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_participants_go_idle_disconnect_and_come_back() {
+        let server = LiveServer::new();
+        let session = server.create_session("demo").await;
+        let owner =
+            server.join_session(&session, "owner").await.unwrap().participants[0].id.clone();
+        let mut updates = server.subscribe_to_updates(&session).unwrap();
+        let now = invites::now();
+
+        assert_eq!(server.refresh_presence(now), 0);
+        server.heartbeat(&session, &owner).unwrap();
+        server.sessions.get_mut(&session).unwrap().participants[0].presence.last_active -=
+            IDLE_AFTER;
+        assert_eq!(server.refresh_presence(now), 1);
+        assert!(matches!(
+            updates.recv().await.unwrap(),
+            LiveUpdate::PresenceChanged { state: PresenceState::Idle, user_name, .. }
+                if user_name == "owner"
+        ));
+        assert_eq!(server.refresh_presence(now + DISCONNECTED_AFTER + 5), 1);
+        assert!(matches!(
+            updates.recv().await.unwrap(),
+            LiveUpdate::PresenceChanged { state: PresenceState::Disconnected, .. }
+        ));
+
+        server.send_chat_message(&session, &owner, "back").unwrap();
+        let _chat = updates.recv().await.unwrap();
+        assert!(matches!(
+            updates.recv().await.unwrap(),
+            LiveUpdate::PresenceChanged { state: PresenceState::Active, .. }
+        ));
+        assert!(server.heartbeat(&session, "stranger").is_err());
+    }
+}
@@ -0,0 +1,83 @@
+//! Discovery of live sessions hosted by other processes on this machine.
+//!
+//! Each hosting process announces its sessions as JSON files in a shared directory and removes
+//! them on exit, so read-only consoles such as `parflow dashboard` can list them.
+
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const DEFAULT_DIR: &str = ".parflow/live";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionAnnouncement {
+    pub session_id: String,
+    pub project: String,
+    pub port: u16,
+    pub pid: u32,
+    /// Unix timestamp in seconds
+    pub started_at: u64,
+}
+
+impl SessionAnnouncement {
+    pub fn new(session_id: &str, project: &str, port: u16) -> Self {
+        Self {
+            session_id: session_id.to_string(),
+            project: project.to_string(),
+            port,
+            pid: std::process::id(),
+            started_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Keeps a session listed until dropped.
+pub struct Announcement {
+    path: PathBuf,
+}
+
+impl Drop for Announcement {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+pub fn announce(dir: &Path, session: &SessionAnnouncement) -> io::Result<Announcement> {
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{}.json", session.session_id));
+    std::fs::write(&path, serde_json::to_vec_pretty(session)?)?;
+    Ok(Announcement { path })
+}
+
+fn process_alive(pid: u32) -> bool {
+    if cfg!(target_os = "linux") {
+        Path::new("/proc").join(pid.to_string()).exists()
+    } else {
+        true
+    }
+}
+
+/// Sessions announced in `dir` whose hosting process is still running, oldest first.
+/// Files left behind by crashed processes are cleaned up.
+pub fn list(dir: &Path) -> Vec<SessionAnnouncement> {
+    let Ok(entries) = std::fs::read_dir(dir) else { return Vec::new() };
+    let mut sessions: Vec<SessionAnnouncement> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let session: SessionAnnouncement =
+                serde_json::from_slice(&std::fs::read(&path).ok()?).ok()?;
+            if process_alive(session.pid) {
+                Some(session)
+            } else {
+                let _ = std::fs::remove_file(&path);
+                None
+            }
+        })
+        .collect();
+    sessions.sort_by_key(|s| s.started_at);
+    sessions
+}
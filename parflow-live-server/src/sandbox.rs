@@ -0,0 +1,254 @@
+//! Limits on what participants run in a session's terminals
+//!
+//! The session host sets a [`SandboxPolicy`] with three parts:
+//!
+//! - an allow-list of programs, checked for every command typed at the shared terminal;
+//! - how the shells behind terminal tabs are isolated from the host;
+//! - the directory those shells are confined to.
+//!
+//! While a session restricts programs, raw keystrokes cannot be checked and are refused, so
+//! shells only receive whole, checked commands.
+
+use crate::audit::AuditEvent;
+use crate::LiveServer;
+use anyhow::{anyhow, bail, Result};
+use portable_pty::CommandBuilder;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Where the shells of terminal tabs run
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Isolation {
+    /// Directly on the host, as the server's user
+    #[default]
+    None,
+    /// Under bubblewrap, with the host read-only except the session root
+    Bubblewrap,
+    /// In a throwaway container of `image`, with only the session root mounted
+    Container { image: String },
+}
+
+impl FromStr for Isolation {
+    type Err = anyhow::Error;
+
+    /// `none`, `bubblewrap` or `container:IMAGE`
+    fn from_str(spec: &str) -> Result<Self> {
+        match spec {
+            "none" => Ok(Isolation::None),
+            "bubblewrap" | "bwrap" => Ok(Isolation::Bubblewrap),
+            _ => match spec.strip_prefix("container:") {
+                Some(image) if !image.is_empty() => {
+                    Ok(Isolation::Container { image: image.to_string() })
+                }
+                _ => bail!(
+                    "unknown isolation '{}': expected none, bubblewrap or container:IMAGE",
+                    spec
+                ),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Isolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Isolation::None => f.write_str("none"),
+            Isolation::Bubblewrap => f.write_str("bubblewrap"),
+            Isolation::Container { image } => write!(f, "container:{}", image),
+        }
+    }
+}
+
+/// The default policy runs anything, unisolated, in the server's working directory.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SandboxPolicy {
+    /// Programs participants may run; `None` allows any
+    pub allowed_commands: Option<BTreeSet<String>>,
+    pub isolation: Isolation,
+    /// Directory shells start in and, when isolated, the only one they can write
+    pub root: Option<PathBuf>,
+    /// Let isolated shells reach the network
+    pub network: bool,
+    /// Program behind terminal tabs; unset uses the user's shell, or `sh` in containers
+    pub shell: Option<String>,
+}
+
+impl SandboxPolicy {
+    /// Check a command line against the allow-list. Every command of a pipeline or list must
+    /// be allowed, and substitutions, which could hide one, are refused outright, as are control
+    /// characters: the shell's terminal takes `\r` as Enter and tab as completion. So are
+    /// redirections, which would let an allowed program write or read any file the shell can,
+    /// like `ls > ~/.bashrc`.
+    pub fn check(&self, command: &str) -> Result<(), String> {
+        let Some(allowed) = &self.allowed_commands else { return Ok(()) };
+        if let Some(control) = command.chars().find(|c| c.is_control()) {
+            return Err(format!("control character {:?} is not allowed in this session", control));
+        }
+        if let Some(construct) = ["`", "$(", "<(", ">("].iter().find(|c| command.contains(**c)) {
+            return Err(format!("{} is not allowed in this session", construct));
+        }
+        if let Some(redirect) = command.chars().find(|c| matches!(c, '<' | '>')) {
+            return Err(format!("redirection with {} is not allowed in this session", redirect));
+        }
+        for segment in command.split(['|', '&', ';']) {
+            // Assignments count as the program, so `LD_PRELOAD=...` cannot be slipped in
+            match segment.split_whitespace().next() {
+                Some(program) if !allowed.contains(program) => {
+                    return Err(format!("'{}' is not an allowed command", program));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// The shell behind a terminal tab under this policy
+    pub(crate) fn shell(&self) -> Result<CommandBuilder> {
+        let root = match &self.root {
+            Some(root) => {
+                std::fs::create_dir_all(root)?;
+                root.canonicalize()?
+            }
+            None => std::env::current_dir()?,
+        };
+        let mut shell = match &self.isolation {
+            Isolation::None => match &self.shell {
+                Some(program) => CommandBuilder::new(program),
+                None => CommandBuilder::new_default_prog(),
+            },
+            Isolation::Bubblewrap => {
+                let mut bwrap = CommandBuilder::new("bwrap");
+                bwrap.args(["--ro-bind", "/", "/", "--dev", "/dev", "--proc", "/proc"]);
+                bwrap.args(["--tmpfs", "/tmp", "--unshare-all", "--die-with-parent"]);
+                if self.network {
+                    bwrap.arg("--share-net");
+                }
+                bwrap.arg("--bind");
+                bwrap.args([&root, &root]);
+                bwrap.arg("--chdir");
+                bwrap.arg(&root);
+                bwrap.arg("--");
+                let program = self.shell.clone().or_else(|| std::env::var("SHELL").ok());
+                bwrap.arg(program.unwrap_or_else(|| "/bin/sh".to_string()));
+                bwrap
+            }
+            Isolation::Container { image } => {
+                let mut docker = CommandBuilder::new("docker");
+                docker.args(["run", "--rm", "-it", "-w", "/workspace"]);
+                if !self.network {
+                    docker.args(["--network", "none"]);
+                }
+                docker.arg("-v");
+                docker.arg(format!("{}:/workspace", root.display()));
+                docker.args([image.as_str(), self.shell.as_deref().unwrap_or("sh")]);
+                docker
+            }
+        };
+        shell.cwd(root);
+        Ok(shell)
+    }
+}
+
+impl fmt::Display for SandboxPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let allowed = match &self.allowed_commands {
+            Some(commands) => commands.iter().cloned().collect::<Vec<_>>().join(", "),
+            None => "any".to_string(),
+        };
+        let root = match &self.root {
+            Some(root) => root.display().to_string(),
+            None => "server working directory".to_string(),
+        };
+        write!(
+            f,
+            "Sandbox:\n• Allowed commands: {}\n• Isolation: {}\n• Root: {}\n• Network: {}",
+            allowed,
+            self.isolation,
+            root,
+            if self.network || self.isolation == Isolation::None { "yes" } else { "no" }
+        )
+    }
+}
+
+impl LiveServer {
+    /// Replace the sandbox of a session. Running shells are stopped, so the next input starts
+    /// them under the new policy.
+    pub fn set_sandbox_policy(
+        &self,
+        session_id: &str,
+        user_id: &str,
+        policy: SandboxPolicy,
+    ) -> Result<()> {
+        let mut session =
+            self.sessions.get_mut(session_id).ok_or_else(|| anyhow!("unknown session"))?;
+        if session.owner_id.as_deref() != Some(user_id) {
+            bail!("only the session owner can change the sandbox");
+        }
+        session.sandbox = policy.clone();
+        drop(session);
+        self.audit(session_id, Some(user_id), AuditEvent::SandboxChanged { policy });
+        self.close_terminals(session_id, None);
+        Ok(())
+    }
+
+    pub(crate) fn sandbox_policy(&self, session_id: &str) -> SandboxPolicy {
+        self.sessions.get(session_id).map(|s| s.sandbox.clone()).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::invites::{InviteScope, DEFAULT_TTL};
+
+    #[tokio::test]
+    async fn test_allow_list_is_enforced_by_the_shared_terminal() {
+        let server = LiveServer::new();
+        let session = server.create_session("demo").await;
+        let owner =
+            server.join_session(&session, "owner").await.unwrap().participants[0].id.clone();
+        let invite = server
+            .create_invitation(&session, &owner, "guest", InviteScope::Edit, DEFAULT_TTL)
+            .unwrap();
+        let joined = server.redeem_invitation(&invite.token, "guest").await.unwrap();
+        let guest = joined.participants[1].id.clone();
+
+        let policy = SandboxPolicy {
+            allowed_commands: Some(["cargo".to_string(), "ls".to_string()].into()),
+            ..Default::default()
+        };
+        assert!(server.set_sandbox_policy(&session, &guest, policy.clone()).is_err());
+        server.set_sandbox_policy(&session, &owner, policy.clone()).unwrap();
+
+        assert!(policy.check("cargo test --workspace | ls -l").is_ok());
+        assert!(policy.check("ls; rm -rf /").is_err());
+        assert!(policy.check("ls $(rm -rf /)").is_err());
+        for hidden in ["ls\rrm -rf /", "ls\nrm -rf /", "ls \x1b[Arm", "r\tm -rf /"] {
+            assert!(
+                policy.check(hidden).unwrap_err().contains("control character"),
+                "{:?}",
+                hidden
+            );
+        }
+        assert!(policy.check("LD_PRELOAD=/tmp/x.so cargo build").is_err());
+        for redirect in ["ls > ~/.bashrc", "ls >> ~/.bashrc", "cargo run < /etc/shadow", "ls 2>x"] {
+            assert!(policy.check(redirect).unwrap_err().contains("redirection"), "{:?}", redirect);
+        }
+        assert_eq!(
+            "container:rust:1".parse::<Isolation>().unwrap().to_string(),
+            "container:rust:1"
+        );
+        assert!("chroot".parse::<Isolation>().is_err());
+
+        server.handle_terminal_input(&session, &guest, "rm -rf /").await.unwrap();
+        let main = &server.terminal_tabs(&session)[0];
+        assert!(main.content.ends_with("'rm' is not an allowed command"));
+        assert!(server.write_terminal(&session, &guest, "main", b"rm -rf /\n").is_err());
+        server.handle_terminal_input(&session, &guest, "ls > ~/.bashrc").await.unwrap();
+        let main = &server.terminal_tabs(&session)[0];
+        assert!(main.content.ends_with("redirection with > is not allowed in this session"));
+    }
+}
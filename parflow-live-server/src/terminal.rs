@@ -0,0 +1,258 @@
+//! Shells behind terminal tabs
+//!
+//! Every tab, the session's shared ones and each participant's own, gets a shell on a PTY the
+//! first time input reaches it. A reader thread appends what the shell writes to the tab's
+//! scrollback (trimmed to [`crate::TERMINAL_SCROLLBACK`]) and broadcasts it raw, escape sequences and
+//! all, as [`LiveUpdate::TerminalData`]. Anyone who can edit may type into shared tabs, but only
+//! its owner into a participant's tab. Shells are killed when their participant leaves or the
+//! session ends.
+
+use crate::{trim_scrollback, LiveServer, LiveSession, LiveUpdate, TerminalTab};
+use anyhow::{anyhow, bail, Context, Result};
+use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtySize};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TerminalSize {
+    pub rows: u16,
+    pub cols: u16,
+}
+
+impl Default for TerminalSize {
+    fn default() -> Self {
+        Self { rows: 24, cols: 80 }
+    }
+}
+
+impl From<TerminalSize> for PtySize {
+    fn from(size: TerminalSize) -> Self {
+        PtySize { rows: size.rows.max(1), cols: size.cols.max(1), pixel_width: 0, pixel_height: 0 }
+    }
+}
+
+/// A shell running on a PTY. Dropping it kills the shell.
+pub(crate) struct Pty {
+    master: Box<dyn MasterPty + Send>,
+    writer: Box<dyn Write + Send>,
+    child: Box<dyn Child + Send + Sync>,
+}
+
+impl Pty {
+    /// Start the user's shell and hand everything it writes to `on_output`, from a reader
+    /// thread, until it exits.
+    fn spawn(
+        size: TerminalSize,
+        mut shell: CommandBuilder,
+        mut on_output: impl FnMut(&str) + Send + 'static,
+    ) -> Result<Self> {
+        let pair = native_pty_system().openpty(size.into()).context("opening a PTY")?;
+        shell.env("TERM", "xterm-256color");
+        let child = pair.slave.spawn_command(shell).context("starting a shell")?;
+        // Only the shell may hold the slave, so the reader sees EOF once it exits
+        drop(pair.slave);
+
+        let mut reader = pair.master.try_clone_reader()?;
+        std::thread::Builder::new().name("parflow-pty".to_string()).spawn(move || {
+            let mut buffer = [0; 4096];
+            let mut pending = Vec::new();
+            while let Ok(read) = reader.read(&mut buffer) {
+                if read == 0 {
+                    break;
+                }
+                pending.extend_from_slice(&buffer[..read]);
+                // Hold back a character split across reads
+                let complete = match std::str::from_utf8(&pending) {
+                    Err(e) if e.error_len().is_none() => e.valid_up_to(),
+                    _ => pending.len(),
+                };
+                if complete > 0 {
+                    on_output(&String::from_utf8_lossy(&pending[..complete]));
+                    pending.drain(..complete);
+                }
+            }
+        })?;
+        let writer = pair.master.take_writer()?;
+        Ok(Self { master: pair.master, writer, child })
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.writer.write_all(data)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl Drop for Pty {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// A session's shared tab or a participant's own tab
+fn tab_mut<'a>(session: &'a mut LiveSession, tab_id: &str) -> Option<&'a mut TerminalTab> {
+    let shared = session.shared_terminal.active_tabs.iter_mut();
+    let own = session.participants.iter_mut().map(|p| &mut p.terminal_tab);
+    shared.chain(own).find(|tab| tab.tab_id == tab_id)
+}
+
+impl LiveServer {
+    /// The shared tabs of a session followed by every participant's tab
+    pub fn terminal_tabs(&self, session_id: &str) -> Vec<TerminalTab> {
+        let Some(session) = self.sessions.get(session_id) else { return Vec::new() };
+        let shared = session.shared_terminal.active_tabs.iter();
+        shared.chain(session.participants.iter().map(|p| &p.terminal_tab)).cloned().collect()
+    }
+
+    /// Check that `user_id` may type into `tab_id`.
+    fn ensure_tab_access(&self, session_id: &str, user_id: &str, tab_id: &str) -> Result<()> {
+        let session = self.sessions.get(session_id).ok_or_else(|| anyhow!("unknown session"))?;
+        let participant = session
+            .participants
+            .iter()
+            .find(|p| p.id == user_id)
+            .ok_or_else(|| anyhow!("not a participant of this session"))?;
+        if !participant.scope.can_edit() {
+            bail!("{} participants cannot use the shared terminal", participant.scope);
+        }
+        let shared = session.shared_terminal.active_tabs.iter().any(|tab| tab.tab_id == tab_id);
+        if !shared && participant.terminal_tab.tab_id != tab_id {
+            bail!("terminal tab {} belongs to another participant or does not exist", tab_id);
+        }
+        Ok(())
+    }
+
+    /// Send raw input, such as keystrokes, to the shell of `tab_id`, starting it if needed.
+    pub fn write_terminal(
+        &self,
+        session_id: &str,
+        user_id: &str,
+        tab_id: &str,
+        data: &[u8],
+    ) -> Result<()> {
+        self.ensure_tab_access(session_id, user_id, tab_id)?;
+        if self.sandbox_policy(session_id).allowed_commands.is_some() {
+            bail!("this session restricts commands; enter them at the shared terminal instead");
+        }
+        self.audit(
+            session_id,
+            Some(user_id),
+            crate::AuditEvent::TerminalInput { input: String::from_utf8_lossy(data).into_owned() },
+        );
+        self.mark_active(session_id, user_id);
+        self.write_tab(session_id, tab_id, data)
+    }
+
+    pub(crate) fn write_tab(&self, session_id: &str, tab_id: &str, data: &[u8]) -> Result<()> {
+        let key = (session_id.to_string(), tab_id.to_string());
+        let pty = match self.terminals.entry(key) {
+            dashmap::mapref::entry::Entry::Occupied(pty) => pty.into_ref(),
+            dashmap::mapref::entry::Entry::Vacant(slot) => {
+                let size = self
+                    .sessions
+                    .get_mut(session_id)
+                    .and_then(|mut session| tab_mut(&mut session, tab_id).map(|tab| tab.size))
+                    .ok_or_else(|| anyhow!("unknown terminal tab {}", tab_id))?;
+                let shell = self.sandbox_policy(session_id).shell()?;
+                slot.insert(Mutex::new(Pty::spawn(
+                    size,
+                    shell,
+                    self.tab_output(session_id, tab_id),
+                )?))
+            }
+        };
+        let written = pty.lock().unwrap().write(data);
+        written
+    }
+
+    /// Where a tab's shell output goes: its scrollback and the session's subscribers
+    fn tab_output(&self, session_id: &str, tab_id: &str) -> impl FnMut(&str) + Send + 'static {
+        let (sessions, senders) = (self.sessions.clone(), self.broadcast_senders.clone());
+        let (session_id, tab_id) = (session_id.to_string(), tab_id.to_string());
+        move |data| {
+            if let Some(mut session) = sessions.get_mut(&session_id) {
+                if let Some(tab) = tab_mut(&mut session, &tab_id) {
+                    tab.content.push_str(data);
+                    trim_scrollback(&mut tab.content);
+                }
+            }
+            if let Some(tx) = senders.get(&session_id) {
+                let _ = tx.send(LiveUpdate::TerminalData {
+                    tab_id: tab_id.clone(),
+                    data: data.to_string(),
+                });
+            }
+        }
+    }
+
+    pub fn resize_terminal(
+        &self,
+        session_id: &str,
+        user_id: &str,
+        tab_id: &str,
+        size: TerminalSize,
+    ) -> Result<()> {
+        self.ensure_tab_access(session_id, user_id, tab_id)?;
+        if let Some(mut session) = self.sessions.get_mut(session_id) {
+            if let Some(tab) = tab_mut(&mut session, tab_id) {
+                tab.size = size;
+            }
+        }
+        if let Some(pty) = self.terminals.get(&(session_id.to_string(), tab_id.to_string())) {
+            pty.lock().unwrap().master.resize(size.into())?;
+        }
+        if let Some(tx) = self.broadcast_senders.get(session_id) {
+            let _ = tx.send(LiveUpdate::TerminalResized { tab_id: tab_id.to_string(), size });
+        }
+        Ok(())
+    }
+
+    /// Kill the shells of a session, or only of the given tab.
+    pub(crate) fn close_terminals(&self, session_id: &str, tab_id: Option<&str>) {
+        self.terminals
+            .retain(|(session, tab), _| session != session_id || tab_id.is_some_and(|t| t != tab));
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::SandboxPolicy;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_runs_shell_commands_on_a_pty() {
+        // Unlike the user's shell, `sh` starts the same everywhere
+        let policy = SandboxPolicy { shell: Some("/bin/sh".to_string()), ..Default::default() };
+        let server = LiveServer::new().with_sandbox(policy);
+        let session = server.create_session("demo").await;
+        let owner =
+            server.join_session(&session, "owner").await.unwrap().participants[0].id.clone();
+        let mut updates = server.subscribe_to_updates(&session).unwrap();
+
+        server.handle_terminal_input(&session, &owner, "echo pty-$((40 + 2))").await.unwrap();
+        let streamed = tokio::time::timeout(Duration::from_secs(10), async {
+            let mut streamed = String::new();
+            while !streamed.contains("pty-42") {
+                if let LiveUpdate::TerminalData { data, .. } = updates.recv().await.unwrap() {
+                    streamed.push_str(&data);
+                }
+            }
+            streamed
+        })
+        .await;
+        assert!(streamed.is_ok(), "no shell output");
+        assert!(server.terminal_tabs(&session)[0].content.contains("pty-42"));
+
+        let own_tab = server.terminal_tabs(&session)[1].tab_id.clone();
+        let size = TerminalSize { rows: 40, cols: 120 };
+        server.resize_terminal(&session, &owner, &own_tab, size).unwrap();
+        assert_eq!(server.terminal_tabs(&session)[1].size, size);
+        assert!(server.write_terminal(&session, "stranger", &own_tab, b"ls\n").is_err());
+
+        server.end_session(&session).await;
+        assert_eq!(server.terminals.len(), 0);
+    }
+}
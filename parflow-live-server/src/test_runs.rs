@@ -0,0 +1,355 @@
+//! Test runs sharded across the machines of a session's participants
+//!
+//! The owner starts a run with provisioned test environments. The server splits them into units
+//! (see `parflow_test_orchestrator::shard`) and deals those to the participants who contribute
+//! resources, weighted by their declared cores. Each participant's machine claims its shard,
+//! runs the units and reports one result per unit; every report is broadcast as progress, and
+//! once the last shard is done the merged results are. A participant who leaves mid-run has its
+//! remaining units reported as errors, so the run still finishes.
+
+use crate::{LiveServer, LiveUpdate};
+use anyhow::{anyhow, bail, Result};
+use parflow_test_orchestrator::shard::{self, Shard, ShardWorker};
+use parflow_test_orchestrator::{TestEnvironment, TestOrchestrator, TestResult};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// How far one participant's shard has come
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShardProgress {
+    pub participant_id: String,
+    pub participant_name: String,
+    pub cores: u32,
+    pub units: usize,
+    pub completed: usize,
+    pub passed: usize,
+    pub failed: usize,
+}
+
+impl ShardProgress {
+    pub fn is_done(&self) -> bool {
+        self.completed >= self.units
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestRunStatus {
+    pub run_id: String,
+    pub shards: Vec<ShardProgress>,
+    /// Results merged per environment, once every shard is done
+    pub results: Option<Vec<TestResult>>,
+}
+
+pub(crate) struct TestRun {
+    id: String,
+    shards: Vec<RunShard>,
+}
+
+struct RunShard {
+    shard: Shard,
+    progress: ShardProgress,
+    results: Vec<TestResult>,
+}
+
+impl RunShard {
+    fn record(&mut self, result: TestResult) {
+        self.progress.completed += 1;
+        self.progress.passed += result.tests_passed;
+        self.progress.failed += result.tests_failed;
+        self.results.push(result);
+    }
+}
+
+impl TestRun {
+    fn is_finished(&self) -> bool {
+        self.shards.iter().all(|shard| shard.progress.is_done())
+    }
+
+    fn status(&self) -> TestRunStatus {
+        let results = self.is_finished().then(|| {
+            shard::merge(self.shards.iter().flat_map(|shard| shard.results.clone()).collect())
+        });
+        TestRunStatus {
+            run_id: self.id.clone(),
+            shards: self.shards.iter().map(|shard| shard.progress.clone()).collect(),
+            results,
+        }
+    }
+}
+
+impl LiveServer {
+    fn notify(&self, session_id: &str, update: LiveUpdate) {
+        if let Some(tx) = self.broadcast_senders.get(session_id) {
+            let _ = tx.send(update);
+        }
+    }
+
+    /// Shard `environments` across the participants who contribute resources. Only the owner
+    /// can start a run, and only when no other run of the session is in progress.
+    pub fn start_test_run(
+        &self,
+        session_id: &str,
+        owner_id: &str,
+        environments: &[TestEnvironment],
+    ) -> Result<TestRunStatus> {
+        self.ensure_owner(session_id, owner_id)?;
+        if self.test_runs.get(session_id).is_some_and(|run| !run.is_finished()) {
+            bail!("a test run is already in progress");
+        }
+        let participants: Vec<(String, String, u32)> = self
+            .sessions
+            .get(session_id)
+            .map(|session| {
+                session
+                    .participants
+                    .iter()
+                    .filter(|p| p.scope.contributes_resources())
+                    .map(|p| (p.id.clone(), p.name.clone(), p.resources.available_cpu_cores))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let workers: Vec<ShardWorker> = participants
+            .iter()
+            .map(|(id, _, cores)| ShardWorker { id: id.clone(), cores: *cores })
+            .collect();
+
+        let units = environments.iter().flat_map(shard::split).collect();
+        let shards = shard::plan(units, &workers)
+            .into_iter()
+            .zip(&participants)
+            .map(|(shard, (id, name, cores))| RunShard {
+                progress: ShardProgress {
+                    participant_id: id.clone(),
+                    participant_name: name.clone(),
+                    cores: *cores,
+                    units: shard.units.len(),
+                    completed: 0,
+                    passed: 0,
+                    failed: 0,
+                },
+                shard,
+                results: Vec::new(),
+            })
+            .collect();
+        let run = TestRun { id: Uuid::new_v4().to_string(), shards };
+        let status = run.status();
+        tracing::info!(
+            session = session_id,
+            run = %run.id,
+            workers = workers.len(),
+            units = run.shards.iter().map(|s| s.progress.units).sum::<usize>(),
+            "🧪 Test run sharded"
+        );
+        self.test_runs.insert(session_id.to_string(), run);
+        self.notify(
+            session_id,
+            LiveUpdate::TestRunStarted {
+                run_id: status.run_id.clone(),
+                shards: status.shards.clone(),
+            },
+        );
+        Ok(status)
+    }
+
+    /// The units of `participant_id`'s shard that it has not reported yet.
+    pub fn claim_shard(&self, session_id: &str, participant_id: &str) -> Option<Shard> {
+        let run = self.test_runs.get(session_id)?;
+        let shard = run.shards.iter().find(|s| s.progress.participant_id == participant_id)?;
+        let units =
+            shard.shard.units[shard.progress.completed.min(shard.progress.units)..].to_vec();
+        Some(Shard { worker: participant_id.to_string(), units })
+    }
+
+    /// Report the result of one unit of `participant_id`'s shard.
+    pub fn submit_test_result(
+        &self,
+        session_id: &str,
+        participant_id: &str,
+        result: TestResult,
+    ) -> Result<TestRunStatus> {
+        let mut run =
+            self.test_runs.get_mut(session_id).ok_or_else(|| anyhow!("no test run in session"))?;
+        let shard = run
+            .shards
+            .iter_mut()
+            .find(|s| s.progress.participant_id == participant_id)
+            .ok_or_else(|| anyhow!("participant has no shard in this test run"))?;
+        if shard.progress.is_done() {
+            bail!("every unit of this shard was already reported");
+        }
+        shard.record(result);
+        let progress = shard.progress.clone();
+        let status = run.status();
+        drop(run);
+
+        self.notify(
+            session_id,
+            LiveUpdate::TestShardProgress { run_id: status.run_id.clone(), progress },
+        );
+        if let Some(results) = &status.results {
+            tracing::info!(session = session_id, run = %status.run_id, "✅ Test run finished");
+            self.notify(
+                session_id,
+                LiveUpdate::TestRunFinished {
+                    run_id: status.run_id.clone(),
+                    results: results.clone(),
+                },
+            );
+        }
+        Ok(status)
+    }
+
+    /// Progress of the session's current or last test run.
+    pub fn test_run(&self, session_id: &str) -> Option<TestRunStatus> {
+        self.test_runs.get(session_id).map(|run| run.status())
+    }
+
+    /// Run `participant_id`'s remaining units on this machine, reporting each as it finishes.
+    /// Returns how many units ran.
+    pub async fn work_shard(&self, session_id: &str, participant_id: &str) -> Result<usize> {
+        let Some(shard) = self.claim_shard(session_id, participant_id) else { return Ok(0) };
+        let orchestrator = TestOrchestrator::new();
+        for unit in &shard.units {
+            for result in orchestrator.run_cross_language_tests(std::slice::from_ref(unit)).await? {
+                self.submit_test_result(session_id, participant_id, result)?;
+            }
+        }
+        Ok(shard.units.len())
+    }
+
+    /// Report what `participant_id` had left to run as errors, after it left the session.
+    pub(crate) fn abandon_shard(&self, session_id: &str, participant_id: &str) {
+        let Some(shard) = self.claim_shard(session_id, participant_id) else { return };
+        let name = self
+            .test_runs
+            .get(session_id)
+            .and_then(|run| {
+                let shard =
+                    run.shards.iter().find(|s| s.progress.participant_id == participant_id)?;
+                Some(shard.progress.participant_name.clone())
+            })
+            .unwrap_or_default();
+        for unit in shard.units {
+            let result = TestResult {
+                environment: unit.name.clone(),
+                error: Some(format!(
+                    "{} left the session before running {}",
+                    name,
+                    unit.selection.heuristics.join(", ")
+                )),
+                ..Default::default()
+            };
+            let _ = self.submit_test_result(session_id, participant_id, result);
+        }
+    }
+
+    /// `test [languages]`: provision environments here, shard them and run the owner's share.
+    pub(crate) async fn test_command(
+        &self,
+        session_id: &str,
+        user_id: &str,
+        languages: &str,
+    ) -> String {
+        let languages: Vec<&str> = languages.split_whitespace().collect();
+        let orchestrator = TestOrchestrator::new();
+        let started = match orchestrator.setup_multi_language_test_env(&languages).await {
+            Ok(environments) => self.start_test_run(session_id, user_id, &environments),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = started {
+            return format!("❌ {}", e);
+        }
+        if let Err(e) = self.work_shard(session_id, user_id).await {
+            return format!("❌ {}", e);
+        }
+        self.tests_command(session_id)
+    }
+
+    /// `tests`: progress of the current or last run
+    pub(crate) fn tests_command(&self, session_id: &str) -> String {
+        let Some(status) = self.test_run(session_id) else {
+            return "No test run yet. Start one with: test [languages]".to_string();
+        };
+        let mut lines = vec![format!("🧪 Test run {}:", &status.run_id[..8])];
+        for shard in &status.shards {
+            lines.push(format!(
+                "• {} ({} cores): {}/{} units, ✅ {} ❌ {}",
+                shard.participant_name,
+                shard.cores,
+                shard.completed,
+                shard.units,
+                shard.passed,
+                shard.failed
+            ));
+        }
+        if let Some(results) = &status.results {
+            let passed: usize = results.iter().map(|r| r.tests_passed).sum();
+            let failed: usize = results.iter().map(|r| r.tests_failed).sum();
+            lines.push(format!("Finished: ✅ {} passed, ❌ {} failed", passed, failed));
+        }
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::invites::{InviteScope, DEFAULT_TTL};
+    use crate::{LiveServer, LiveUpdate, ParticipantResources};
+    use parflow_test_orchestrator::{TestEnvironment, TestFramework, TestResult, TestSelection};
+
+    #[tokio::test]
+    async fn test_shards_by_cores_and_finishes_when_participants_leave() {
+        let server = LiveServer::new();
+        let session = server.create_session("demo").await;
+        let owner =
+            server.join_session(&session, "owner").await.unwrap().participants[0].id.clone();
+        let mut joined = Vec::new();
+        for (name, scope) in [("ana", InviteScope::Edit), ("obs", InviteScope::ReadOnly)] {
+            let invite =
+                server.create_invitation(&session, &owner, name, scope, DEFAULT_TTL).unwrap();
+            let session = server.redeem_invitation(&invite.token, name).await.unwrap();
+            joined.push(session.participants.last().unwrap().id.clone());
+        }
+        let ana = joined[0].clone();
+        let resources =
+            |cores| ParticipantResources { available_cpu_cores: cores, ..Default::default() };
+        server.declare_resources(&session, &owner, resources(8)).unwrap();
+        server.declare_resources(&session, &ana, resources(4)).unwrap();
+
+        let unit = |name: &str, file: &str| TestEnvironment {
+            name: name.to_string(),
+            language: "python".to_string(),
+            framework: TestFramework::Pytest,
+            project_dir: std::env::temp_dir(),
+            env_dir: std::env::temp_dir(),
+            command: Vec::new(),
+            env: Vec::new(),
+            selection: TestSelection { args: vec![file.to_string()], ..Default::default() },
+        };
+        let environments = [unit("pytest", "a.py"), unit("pytest", "b.py"), unit("pytest", "c.py")];
+        assert!(server.start_test_run(&session, &ana, &environments).is_err());
+        let mut updates = server.subscribe_to_updates(&session).unwrap();
+        let status = server.start_test_run(&session, &owner, &environments).unwrap();
+        // The read-only observer gets no shard; the owner has twice ana's cores
+        let units: Vec<usize> = status.shards.iter().map(|s| s.units).collect();
+        assert_eq!(units, [2, 1]);
+        assert!(server.start_test_run(&session, &owner, &environments).is_err());
+        assert!(matches!(updates.recv().await.unwrap(), LiveUpdate::TestRunStarted { .. }));
+
+        let passed =
+            TestResult { environment: "pytest".to_string(), tests_passed: 3, ..Default::default() };
+        for _ in 0..2 {
+            server.submit_test_result(&session, &owner, passed.clone()).unwrap();
+        }
+        assert!(server.submit_test_result(&session, &owner, passed.clone()).is_err());
+        assert_eq!(server.claim_shard(&session, &ana).unwrap().units.len(), 1);
+
+        server.leave_session(&session, &ana).await;
+        let status = server.test_run(&session).unwrap();
+        let results = status.results.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].tests_passed, 6);
+        assert!(results[0].error.as_deref().unwrap().contains("ana left the session"));
+        assert!(server.tests_command(&session).contains("Finished: ✅ 6 passed"));
+    }
+}
@@ -0,0 +1,177 @@
+//! What the server shows for one document: lint findings and migration suggestions as
+//! diagnostics, and a code lens with the complexity of each function above its declaration.
+
+use lsp_types::{
+    CodeLens, Command, Diagnostic, DiagnosticSeverity, NumberOrString, Position, Range,
+};
+use parflow_core::languages;
+use parflow_transpiler::complexity::{self, FunctionComplexity};
+use semantic_compiler::lints::{self, Linter};
+use semantic_compiler::{detect_patterns, CrossLanguageAnalyzer};
+
+/// Languages the server analyzes
+pub const LANGUAGES: &[&str] = &["python", "javascript", "typescript", "rust"];
+
+/// Editor command a complexity lens runs; clients without it just show the title
+pub const COMPLEXITY_COMMAND: &str = "parflow.showComplexity";
+
+/// Editor command a migration lens runs, with the file and the target language
+pub const MIGRATE_COMMAND: &str = "parflow.mirror";
+
+#[derive(Debug, Default)]
+pub struct Analysis {
+    pub diagnostics: Vec<Diagnostic>,
+    pub lenses: Vec<CodeLens>,
+}
+
+/// Language of `path` when the server analyzes it
+pub fn language(path: &str) -> Option<&'static str> {
+    languages::for_path(path).map(|plugin| plugin.name()).filter(|name| LANGUAGES.contains(name))
+}
+
+/// The whole of 1-based `line`, in UTF-16 code units as LSP counts them
+fn line_range(lines: &[&str], line: usize) -> Range {
+    let index = line.saturating_sub(1);
+    let width = lines.get(index).map_or(0, |text| text.encode_utf16().count());
+    let index = index as u32;
+    Range::new(Position::new(index, 0), Position::new(index, width as u32))
+}
+
+fn severity(severity: lints::Severity) -> DiagnosticSeverity {
+    match severity {
+        lints::Severity::Error => DiagnosticSeverity::ERROR,
+        lints::Severity::Warning => DiagnosticSeverity::WARNING,
+        lints::Severity::Info => DiagnosticSeverity::INFORMATION,
+    }
+}
+
+fn diagnostic(
+    range: Range,
+    severity: DiagnosticSeverity,
+    code: &str,
+    message: String,
+) -> Diagnostic {
+    Diagnostic {
+        range,
+        severity: Some(severity),
+        code: Some(NumberOrString::String(code.to_string())),
+        source: Some("parflow".to_string()),
+        message,
+        ..Default::default()
+    }
+}
+
+fn lens(range: Range, title: String, command: &str, arguments: Vec<serde_json::Value>) -> CodeLens {
+    let command = Command { title, command: command.to_string(), arguments: Some(arguments) };
+    CodeLens { range, command: Some(command), data: None }
+}
+
+/// Analyze one document; `path` picks the language, and files in other languages get nothing
+pub fn analyze(path: &str, text: &str) -> Analysis {
+    let Some(language) = language(path) else {
+        return Analysis::default();
+    };
+    let lines: Vec<&str> = text.lines().collect();
+    let mut analysis = Analysis::default();
+
+    for found in Linter::default().lint_source(text, path) {
+        let message = match &found.fix {
+            Some(fix) => format!("{} ({})", found.message, fix),
+            None => found.message,
+        };
+        let range = line_range(&lines, found.line);
+        analysis.diagnostics.push(diagnostic(
+            range,
+            severity(found.severity),
+            &found.rule,
+            message,
+        ));
+    }
+
+    let functions: Vec<FunctionComplexity> = complexity::analyze_functions(text, language)
+        .map(|report| report.functions)
+        .unwrap_or_default();
+    for function in &functions {
+        let title = format!(
+            "cognitive {} · cyclomatic {} · nesting {}",
+            function.cognitive, function.cyclomatic, function.max_nesting
+        );
+        let arguments = vec![path.into(), function.name.clone().into()];
+        let range = line_range(&lines, function.line);
+        analysis.lenses.push(lens(range, title, COMPLEXITY_COMMAND, arguments));
+    }
+
+    let analyzer = CrossLanguageAnalyzer;
+    let mut migrations: Vec<(usize, String)> = Vec::new();
+    for found in detect_patterns(text, language) {
+        let Some(target) = analyzer.get_optimal_language(&found.pattern) else { continue };
+        if target == language {
+            continue;
+        }
+        let declaration = functions
+            .iter()
+            .find(|function| (function.line..=function.end_line).contains(&found.line))
+            .map_or(found.line, |function| function.line);
+        let message = format!(
+            "{:?} pattern ({}) runs faster in {}; mirror it with `parflow mirror`",
+            found.pattern, found.evidence, target
+        );
+        let range = line_range(&lines, found.line);
+        analysis.diagnostics.push(diagnostic(
+            range,
+            DiagnosticSeverity::HINT,
+            "migration",
+            message,
+        ));
+        if !migrations.contains(&(declaration, target.clone())) {
+            let title = format!("⇢ mirror to {}", target);
+            let arguments = vec![path.into(), target.clone().into()];
+            analysis.lenses.push(lens(
+                line_range(&lines, declaration),
+                title,
+                MIGRATE_COMMAND,
+                arguments,
+            ));
+            migrations.push((declaration, target));
+        }
+    }
+    analysis.diagnostics.sort_by_key(|diagnostic| diagnostic.range.start.line);
+    analysis.lenses.sort_by_key(|lens| lens.range.start.line);
+    analysis
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyze_reports_lints_migrations_and_complexity() {
+        let python =
+            "def fib(n):\n    if n < 2:\n        return n\n    return fib(n - 1) + fib(n - 2)\n";
+        let analysis = analyze("/work/fib.py", python);
+
+        let codes: Vec<_> = analysis
+            .diagnostics
+            .iter()
+            .map(|d| (d.code.clone(), d.severity, d.range.start.line))
+            .collect();
+        let code = |code: &str| Some(NumberOrString::String(code.to_string()));
+        assert!(codes.contains(&(
+            code("recursive-without-memoization"),
+            Some(DiagnosticSeverity::WARNING),
+            3
+        )));
+        assert!(codes.contains(&(code("migration"), Some(DiagnosticSeverity::HINT), 3)));
+        assert_eq!(analysis.diagnostics[0].range.end.character, 34);
+
+        let titles: Vec<_> = analysis
+            .lenses
+            .iter()
+            .map(|lens| (lens.range.start.line, lens.command.as_ref().unwrap().title.as_str()))
+            .collect();
+        assert!(titles.contains(&(0, "⇢ mirror to rust")));
+        assert!(titles.iter().any(|(line, title)| *line == 0 && title.starts_with("cognitive ")));
+
+        assert!(analyze("/work/notes.md", "# fib").diagnostics.is_empty());
+    }
+}
@@ -0,0 +1,278 @@
+//! `parflow-lsp`: a Language Server Protocol server over stdio. Editors get ParFlow's lint
+//! findings and migration suggestions as diagnostics and per-function complexity as code lenses
+//! for the Python, JavaScript, TypeScript and Rust files of the workspace.
+
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, DidSaveTextDocument,
+    Notification as _, PublishDiagnostics,
+};
+use lsp_types::request::{CodeLensRequest, Request as _};
+use lsp_types::{
+    CodeLensOptions, CodeLensParams, InitializeParams, PublishDiagnosticsParams, SaveOptions,
+    ServerCapabilities, TextDocumentSyncCapability, TextDocumentSyncKind, TextDocumentSyncOptions,
+    TextDocumentSyncSaveOptions, Url,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+mod analysis;
+
+fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Options(TextDocumentSyncOptions {
+            open_close: Some(true),
+            change: Some(TextDocumentSyncKind::FULL),
+            save: Some(TextDocumentSyncSaveOptions::SaveOptions(SaveOptions {
+                include_text: Some(true),
+            })),
+            ..Default::default()
+        })),
+        code_lens_provider: Some(CodeLensOptions { resolve_provider: Some(false) }),
+        ..Default::default()
+    }
+}
+
+/// Files under `dir` the server analyzes, skipping hidden directories, `target` and
+/// `node_modules`
+fn source_files(dir: &Path, out: &mut Vec<PathBuf>) {
+    for path in std::fs::read_dir(dir).into_iter().flatten().flatten().map(|entry| entry.path()) {
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        if path.is_dir() {
+            if !name.starts_with('.') && name != "target" && name != "node_modules" {
+                source_files(&path, out);
+            }
+        } else if analysis::language(&name).is_some() {
+            out.push(path);
+        }
+    }
+}
+
+fn params<N: lsp_types::notification::Notification>(
+    notification: Notification,
+) -> Option<N::Params> {
+    notification.extract(N::METHOD).ok()
+}
+
+struct Server {
+    connection: Connection,
+    /// Text of the open documents, which may differ from the files on disk
+    documents: HashMap<Url, String>,
+}
+
+impl Server {
+    fn new(connection: Connection) -> Self {
+        Self { connection, documents: HashMap::new() }
+    }
+
+    fn text(&self, uri: &Url) -> Option<String> {
+        match self.documents.get(uri) {
+            Some(text) => Some(text.clone()),
+            None => std::fs::read_to_string(uri.to_file_path().ok()?).ok(),
+        }
+    }
+
+    fn publish(&self, uri: Url, text: &str) -> anyhow::Result<()> {
+        let diagnostics = analysis::analyze(uri.path(), text).diagnostics;
+        let params = PublishDiagnosticsParams { uri, diagnostics, version: None };
+        let notification = Notification::new(PublishDiagnostics::METHOD.to_string(), params);
+        self.connection.sender.send(notification.into())?;
+        Ok(())
+    }
+
+    /// Diagnostics for every file of the workspace, so problems show up before files are opened
+    fn publish_workspace(&self, roots: &[PathBuf]) -> anyhow::Result<()> {
+        for root in roots {
+            let mut files = Vec::new();
+            source_files(root, &mut files);
+            files.sort();
+            for file in files {
+                let Ok(text) = std::fs::read_to_string(&file) else { continue };
+                let Ok(uri) = Url::from_file_path(&file) else { continue };
+                self.publish(uri, &text)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_request(&self, request: Request) -> anyhow::Result<()> {
+        let response = match request.method.as_str() {
+            CodeLensRequest::METHOD => {
+                match serde_json::from_value::<CodeLensParams>(request.params) {
+                    Ok(params) => {
+                        let uri = params.text_document.uri;
+                        let lenses = self
+                            .text(&uri)
+                            .map(|text| analysis::analyze(uri.path(), &text).lenses)
+                            .unwrap_or_default();
+                        Response::new_ok(request.id, lenses)
+                    }
+                    Err(e) => Response::new_err(
+                        request.id,
+                        ErrorCode::InvalidParams as i32,
+                        e.to_string(),
+                    ),
+                }
+            }
+            method => Response::new_err(
+                request.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("unsupported request {}", method),
+            ),
+        };
+        self.connection.sender.send(response.into())?;
+        Ok(())
+    }
+
+    fn handle_notification(&mut self, notification: Notification) -> anyhow::Result<()> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let Some(params) = params::<DidOpenTextDocument>(notification) else {
+                    return Ok(());
+                };
+                let document = params.text_document;
+                self.publish(document.uri.clone(), &document.text)?;
+                self.documents.insert(document.uri, document.text);
+            }
+            DidChangeTextDocument::METHOD => {
+                let Some(params) = params::<DidChangeTextDocument>(notification) else {
+                    return Ok(());
+                };
+                // Full sync: the last change holds the whole document
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    let uri = params.text_document.uri;
+                    self.publish(uri.clone(), &change.text)?;
+                    self.documents.insert(uri, change.text);
+                }
+            }
+            DidSaveTextDocument::METHOD => {
+                let Some(params) = params::<DidSaveTextDocument>(notification) else {
+                    return Ok(());
+                };
+                let uri = params.text_document.uri;
+                if let Some(text) = params.text.or_else(|| self.text(&uri)) {
+                    self.publish(uri.clone(), &text)?;
+                    self.documents.insert(uri, text);
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let Some(params) = params::<DidCloseTextDocument>(notification) else {
+                    return Ok(());
+                };
+                self.documents.remove(&params.text_document.uri);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Serve until the client shuts the server down
+    fn run(mut self) -> anyhow::Result<()> {
+        while let Ok(message) = self.connection.receiver.recv() {
+            match message {
+                Message::Request(request) => {
+                    if self.connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    self.handle_request(request)?;
+                }
+                Message::Notification(notification) => self.handle_notification(notification)?,
+                Message::Response(_) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Workspace folders of the client, or its root when it predates folders
+#[allow(deprecated)]
+fn workspace_roots(params: &InitializeParams) -> Vec<PathBuf> {
+    let folders = params.workspace_folders.iter().flatten().map(|folder| &folder.uri);
+    let roots: Vec<PathBuf> = folders.filter_map(|uri| uri.to_file_path().ok()).collect();
+    if !roots.is_empty() {
+        return roots;
+    }
+    params.root_uri.iter().filter_map(|uri| uri.to_file_path().ok()).collect()
+}
+
+fn serve(connection: Connection) -> anyhow::Result<()> {
+    let initialize = connection.initialize(serde_json::to_value(capabilities())?)?;
+    let params: InitializeParams = serde_json::from_value(initialize)?;
+    let server = Server::new(connection);
+    server.publish_workspace(&workspace_roots(&params))?;
+    server.run()
+}
+
+fn main() -> anyhow::Result<()> {
+    // stdout carries the protocol, so anything for people goes to stderr
+    eprintln!("parflow-lsp {} listening on stdio", env!("CARGO_PKG_VERSION"));
+    let (connection, io_threads) = Connection::stdio();
+    serve(connection)?;
+    io_threads.join()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_types::{CodeLens, TextDocumentIdentifier};
+
+    #[test]
+    fn test_server_publishes_workspace_diagnostics_and_serves_code_lenses() {
+        let root = std::env::temp_dir().join(format!("parflow-lsp-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let file = root.join("fib.py");
+        std::fs::write(&file, "def fib(n):\n    return n if n < 2 else fib(n - 1) + fib(n - 2)\n")
+            .unwrap();
+        let uri = Url::from_file_path(&file).unwrap();
+
+        let (server, client) = Connection::memory();
+        let thread = std::thread::spawn(move || serve(server));
+        let initialize = serde_json::json!({
+            "capabilities": {},
+            "rootUri": Url::from_directory_path(&root).unwrap(),
+        });
+        client.sender.send(Request::new(1.into(), "initialize".into(), initialize).into()).unwrap();
+        client.receiver.recv().unwrap();
+        client
+            .sender
+            .send(Notification::new("initialized".into(), serde_json::json!({})).into())
+            .unwrap();
+
+        let Message::Notification(published) = client.receiver.recv().unwrap() else {
+            panic!("expected diagnostics")
+        };
+        let published: PublishDiagnosticsParams = serde_json::from_value(published.params).unwrap();
+        assert_eq!(published.uri, uri);
+        assert!(published.diagnostics.iter().any(|d| d.message.contains("fib calls itself")));
+
+        let params = CodeLensParams {
+            text_document: TextDocumentIdentifier { uri },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        };
+        client
+            .sender
+            .send(Request::new(2.into(), CodeLensRequest::METHOD.into(), params).into())
+            .unwrap();
+        let Message::Response(response) = client.receiver.recv().unwrap() else {
+            panic!("expected code lenses")
+        };
+        let lenses: Vec<CodeLens> = serde_json::from_value(response.result.unwrap()).unwrap();
+        assert!(lenses
+            .iter()
+            .any(|lens| lens.command.as_ref().unwrap().title == "⇢ mirror to rust"));
+
+        client
+            .sender
+            .send(Request::new(3.into(), "shutdown".into(), serde_json::Value::Null).into())
+            .unwrap();
+        client.receiver.recv().unwrap();
+        client
+            .sender
+            .send(Notification::new("exit".into(), serde_json::Value::Null).into())
+            .unwrap();
+        thread.join().unwrap().unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,356 @@
+//! Paired benchmarks for mirrored modules
+//!
+//! For each module [`crate::MirroringEngine::mirror_codebase`] mirrors, a [`BenchmarkHarness`]
+//! runs the original and the mirrored function over the same inputs. It checks that both give
+//! the same output and times them. The inputs come from the types in the module's
+//! [`InterfaceSpec`]. The harness is a Python script for a Python module and a Node.js script for
+//! a JavaScript one. It imports the mirrored build from its own directory: the PyO3 extension
+//! for Python, or the `wasm-pack --target nodejs --out-dir <module> --out-name <module>`
+//! package for JavaScript.
+
+use crate::interface::InterfaceSpec;
+use anyhow::{anyhow, bail, Context, Result};
+use parflow_transpiler::infer::{split_top_level, Signature};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Inputs generated per function
+const CASES: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkCase {
+    pub function: String,
+    /// Arguments of each call, in parameter order
+    pub inputs: Vec<Vec<Value>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkHarness {
+    /// Module as given to the mirroring
+    pub module: String,
+    /// `python` or `javascript`
+    pub language: String,
+    /// Original source file the harness imports
+    pub original: PathBuf,
+    /// Name of the mirrored build, after [`InterfaceSpec::module_name`]
+    pub mirrored: String,
+    pub cases: Vec<BenchmarkCase>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Mismatch {
+    pub input: Vec<Value>,
+    pub original: Value,
+    pub mirrored: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkResult {
+    pub function: String,
+    pub cases: usize,
+    pub mismatches: Vec<Mismatch>,
+    /// Mean time per call
+    pub original_ns: f64,
+    pub mirrored_ns: f64,
+}
+
+impl BenchmarkResult {
+    pub fn equivalent(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+
+    /// How many times faster the mirrored function is; `None` if it gives different output
+    pub fn speedup(&self) -> Option<f64> {
+        (self.equivalent() && self.mirrored_ns > 0.0).then(|| self.original_ns / self.mirrored_ns)
+    }
+}
+
+/// Geometric mean of the speedups of the functions that gave the same output
+pub fn overall_speedup(results: &[BenchmarkResult]) -> Option<f64> {
+    let speedups: Vec<f64> = results.iter().filter_map(BenchmarkResult::speedup).collect();
+    if speedups.is_empty() {
+        return None;
+    }
+    let log_sum: f64 = speedups.iter().map(|speedup| speedup.ln()).sum();
+    Some((log_sum / speedups.len() as f64).exp())
+}
+
+/// `CASES` sample values of the Rust type `ty`, small enough for exponential algorithms
+fn sample_values(ty: &str) -> Vec<Value> {
+    let ty = ty.trim().trim_start_matches('&').trim();
+    let inner = |prefix: &str| {
+        ty.strip_prefix(prefix).and_then(|rest| rest.strip_suffix('>')).map(str::trim)
+    };
+    if let Some(element) = inner("Vec<") {
+        let elements = sample_values(element);
+        return (0..CASES).map(|n| Value::Array(elements[..n].to_vec())).collect();
+    }
+    if let Some(element) = inner("Option<") {
+        let mut values = sample_values(element);
+        values[0] = Value::Null;
+        return values;
+    }
+    if let Some(entry) = inner("HashMap<") {
+        let value_type = split_top_level(entry).get(1).cloned().unwrap_or_default();
+        let values = sample_values(value_type);
+        return (0..CASES)
+            .map(|n| {
+                let keys = ["a", "b", "c"];
+                Value::Object(
+                    keys.iter()
+                        .zip(&values)
+                        .take(n)
+                        .map(|(k, v)| (k.to_string(), v.clone()))
+                        .collect(),
+                )
+            })
+            .collect();
+    }
+    if let Some(elements) = ty.strip_prefix('(').and_then(|rest| rest.strip_suffix(')')) {
+        let columns: Vec<Vec<Value>> =
+            split_top_level(elements).iter().map(|t| sample_values(t)).collect();
+        return (0..CASES)
+            .map(|n| Value::Array(columns.iter().map(|column| column[n].clone()).collect()))
+            .collect();
+    }
+    match ty {
+        "i64" | "i32" | "u32" | "u64" | "usize" => vec![json!(0), json!(1), json!(5), json!(10)],
+        "f64" | "f32" => vec![json!(0.0), json!(1.5), json!(-2.25), json!(10.0)],
+        "bool" => vec![json!(true), json!(false), json!(false), json!(true)],
+        "String" | "str" => vec![json!(""), json!("a"), json!("Hello"), json!("parflow mirror")],
+        "()" => vec![Value::Null; CASES],
+        // Untyped: a value of each kind
+        _ => vec![json!(1), json!("a"), json!([1, 2]), json!(2.5)],
+    }
+}
+
+/// Calls covering each parameter's sample values, defaults left to the function
+fn sample_inputs(signature: &Signature) -> Vec<Vec<Value>> {
+    let columns: Vec<Vec<Value>> =
+        signature.params.iter().map(|param| sample_values(&param.ty.ty)).collect();
+    (0..CASES).map(|n| columns.iter().map(|column| column[n].clone()).collect()).collect()
+}
+
+const PYTHON_DRIVER: &str = r#"
+def call(f, args):
+    try:
+        return True, f(*args)
+    except Exception as e:
+        return False, type(e).__name__
+
+def per_call(f, inputs):
+    rounds = 1
+    while True:
+        start = time.perf_counter_ns()
+        for _ in range(rounds):
+            for args in inputs:
+                call(f, args)
+        elapsed = time.perf_counter_ns() - start
+        if elapsed > 50_000_000 or rounds >= 1 << 20:
+            return elapsed / (rounds * max(len(inputs), 1))
+        rounds *= 10
+
+for case in CASES:
+    name, inputs = case["function"], case["inputs"]
+    f, g = getattr(original, name), getattr(mirrored, name)
+    mismatches = []
+    for args in inputs:
+        expected, actual = call(f, args), call(g, args)
+        # Both raising counts as the same behaviour; the exception types differ across the FFI
+        if expected != actual and (expected[0] or actual[0]):
+            mismatches.append({"input": args, "original": expected[1], "mirrored": actual[1]})
+    print(json.dumps({
+        "function": name,
+        "cases": len(inputs),
+        "mismatches": mismatches,
+        "original_ns": per_call(f, inputs),
+        "mirrored_ns": per_call(g, inputs),
+    }, default=repr))
+"#;
+
+const NODE_DRIVER: &str = r#"
+function call(f, args) {
+  try {
+    return [true, f(...args)];
+  } catch (e) {
+    return [false, e && e.name];
+  }
+}
+
+function perCall(f, inputs) {
+  for (let rounds = 1; ; rounds *= 10) {
+    const start = process.hrtime.bigint();
+    for (let i = 0; i < rounds; i++) {
+      for (const args of inputs) call(f, args);
+    }
+    const elapsed = Number(process.hrtime.bigint() - start);
+    if (elapsed > 50_000_000 || rounds >= 1 << 20) {
+      return elapsed / (rounds * Math.max(inputs.length, 1));
+    }
+  }
+}
+
+for (const { function: name, inputs } of CASES) {
+  const f = original[name] ?? original.default?.[name];
+  const g = mirrored[name];
+  const mismatches = [];
+  for (const args of inputs) {
+    const expected = call(f, args);
+    const actual = call(g, args);
+    // Both throwing counts as the same behaviour; the error types differ across the FFI
+    if (!isDeepStrictEqual(expected, actual) && (expected[0] || actual[0])) {
+      mismatches.push({ input: args, original: expected[1] ?? null, mirrored: actual[1] ?? null });
+    }
+  }
+  console.log(JSON.stringify({
+    function: name,
+    cases: inputs.length,
+    mismatches,
+    original_ns: perCall(f, inputs),
+    mirrored_ns: perCall(g, inputs),
+  }));
+}
+"#;
+
+impl BenchmarkHarness {
+    /// A harness for the functions of `spec`, whose source is at `original`
+    pub fn new(spec: &InterfaceSpec, original: &Path) -> Self {
+        let original = original.canonicalize().unwrap_or_else(|_| original.to_path_buf());
+        let cases = spec
+            .functions
+            .iter()
+            // Methods need an instance the harness cannot construct
+            .filter(|function| !function.signature.method)
+            .map(|function| BenchmarkCase {
+                function: function.signature.name.clone(),
+                inputs: sample_inputs(&function.signature),
+            })
+            .collect();
+        Self {
+            module: spec.module.clone(),
+            language: spec.language.clone(),
+            original,
+            mirrored: spec.module_name().to_string(),
+            cases,
+        }
+    }
+
+    /// File the script is written to, next to the mirrored build
+    pub fn file_name(&self) -> String {
+        match self.language.as_str() {
+            "python" => format!("bench_{}.py", self.mirrored),
+            _ => format!("bench_{}.mjs", self.mirrored),
+        }
+    }
+
+    pub fn script(&self) -> String {
+        let cases = serde_json::to_string(&self.cases).unwrap_or_else(|_| "[]".to_string());
+        let quoted_cases = serde_json::to_string(&cases).unwrap_or_default();
+        let original = serde_json::to_string(&self.original.to_string_lossy()).unwrap_or_default();
+        if self.language == "python" {
+            format!(
+                "\"\"\"Benchmark of `{module}` against its mirror `{mirrored}`\"\"\"\n\
+                 import importlib.util, json, os, sys, time\n\n\
+                 spec = importlib.util.spec_from_file_location(\"original\", {original})\n\
+                 original = importlib.util.module_from_spec(spec)\n\
+                 spec.loader.exec_module(original)\n\
+                 sys.path.insert(0, os.path.dirname(os.path.abspath(__file__)))\n\
+                 try:\n    import {mirrored} as mirrored\n\
+                 except ImportError as e:\n    \
+                 sys.exit(f\"{mirrored} is not built: {{e}}\")\n\n\
+                 CASES = json.loads({cases})\n{driver}",
+                module = self.module,
+                mirrored = self.mirrored,
+                original = original,
+                cases = quoted_cases,
+                driver = PYTHON_DRIVER
+            )
+        } else {
+            format!(
+                "// Benchmark of `{module}` against its mirror `{mirrored}`\n\
+                 import {{ createRequire }} from \"node:module\";\n\
+                 import {{ pathToFileURL }} from \"node:url\";\n\
+                 import {{ isDeepStrictEqual }} from \"node:util\";\n\n\
+                 const original = await import(pathToFileURL({original}));\n\
+                 let mirrored;\n\
+                 try {{\n  \
+                 mirrored = createRequire(import.meta.url)(\"./{mirrored}/{mirrored}.js\");\n\
+                 }} catch (e) {{\n  \
+                 console.error(`{mirrored} is not built: ${{e.message}}`);\n  process.exit(1);\n}}\n\n\
+                 const CASES = {cases};\n{driver}",
+                module = self.module,
+                mirrored = self.mirrored,
+                original = original,
+                cases = cases,
+                driver = NODE_DRIVER
+            )
+        }
+    }
+
+    /// Write the script to `dir`, where the mirrored build is, and run it
+    pub fn run(&self, dir: &Path) -> Result<Vec<BenchmarkResult>> {
+        let script = dir.join(self.file_name());
+        std::fs::write(&script, self.script())
+            .with_context(|| format!("writing {}", script.display()))?;
+        let interpreter = if self.language == "python" { "python3" } else { "node" };
+        let output = Command::new(interpreter)
+            .arg(&script)
+            .output()
+            .with_context(|| format!("running {}", interpreter))?;
+        if !output.status.success() {
+            bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| {
+                serde_json::from_str(line)
+                    .map_err(|e| anyhow!("unexpected benchmark output {:?}: {}", line, e))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generates_and_runs_paired_benchmarks() {
+        let python = "\
+def fib(n: int) -> int:
+    return n if n < 2 else fib(n - 1) + fib(n - 2)
+
+def total(prices: list[float], label: str) -> float:
+    return sum(prices)
+";
+        let dir = std::env::temp_dir().join(format!("parflow-bench-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        let original = dir.join("src/pricing.py");
+        std::fs::write(&original, python).unwrap();
+        let spec = InterfaceSpec::from_source("src/pricing.py", python, "python").unwrap();
+        let harness = BenchmarkHarness::new(&spec, &original);
+        assert_eq!(harness.file_name(), "bench_pricing.py");
+        assert_eq!(harness.cases[0].inputs, [[json!(0)], [json!(1)], [json!(5)], [json!(10)]]);
+        assert_eq!(harness.cases[1].inputs[2], [json!([0.0, 1.5]), json!("Hello")]);
+        assert!(harness.script().contains("import pricing as mirrored"));
+
+        // A stand-in for the built extension: the same fib, a total that is off by one
+        std::fs::write(
+            dir.join("pricing.py"),
+            format!("{}\ndef total(prices, label):\n    return sum(prices) + 1\n", python),
+        )
+        .unwrap();
+        let python_available = Command::new("python3").arg("--version").output().is_ok();
+        if python_available {
+            let results = harness.run(&dir).unwrap();
+            assert!(results[0].equivalent() && results[0].speedup().is_some());
+            assert_eq!(results[1].mismatches.len(), CASES);
+            assert_eq!(results[1].mismatches[0].original, json!(0));
+            assert_eq!(overall_speedup(&results), results[0].speedup());
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,494 @@
+//! Public API surface of a Python or JavaScript module
+//!
+//! Moving a module to Rust keeps its callers working only if the interface stays put.
+//! [`InterfaceSpec::extract`] lists the module's public functions with their parameters, the
+//! types [`parflow_transpiler::infer`] gives them (JSDoc tags and default values in JavaScript),
+//! and every place in the project that calls them. The spec is JSON, so it can be reviewed and
+//! corrected before [`crate::MirroringEngine::rust_skeleton`] and
+//! [`crate::MirroringEngine::ffi_shim`] generate code from it.
+
+use anyhow::{anyhow, Context, Result};
+use parflow_transpiler::infer::{
+    self, split_top_level, Confidence, Inferred, Param, Signature, TypeSource, DYNAMIC,
+};
+use semantic_compiler::ownership::language_for_path;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallSite {
+    /// Relative to the project root
+    pub file: String,
+    /// Counted from 1
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InterfaceFunction {
+    #[serde(flatten)]
+    pub signature: Signature,
+    pub confidence: Confidence,
+    pub callers: Vec<CallSite>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InterfaceSpec {
+    /// Path of the module, relative to the project root
+    pub module: String,
+    /// `python` or `javascript`
+    pub language: String,
+    pub functions: Vec<InterfaceFunction>,
+}
+
+impl InterfaceSpec {
+    /// The public functions of `module`, with their callers anywhere under `root`
+    pub fn extract(module: &Path, root: &Path) -> Result<Self> {
+        let language = language_for_path(&module.to_string_lossy())
+            .ok_or_else(|| anyhow!("{} is not a Python or JavaScript module", module.display()))?;
+        let code = std::fs::read_to_string(module)
+            .with_context(|| format!("reading {}", module.display()))?;
+        let name = module.strip_prefix(root).unwrap_or(module).to_string_lossy().into_owned();
+        let mut spec = Self::from_source(&name, &code, language)
+            .ok_or_else(|| anyhow!("{} is not a Python or JavaScript module", module.display()))?;
+
+        let mut files = Vec::new();
+        collect_sources(root, language, &mut files);
+        files.sort();
+        for file in files {
+            // Files that are not UTF-8 cannot call anything
+            let Ok(code) = std::fs::read_to_string(&file) else { continue };
+            let relative = file.strip_prefix(root).unwrap_or(&file);
+            spec.add_callers(&relative.to_string_lossy(), &code);
+        }
+        Ok(spec)
+    }
+
+    /// The public functions in `code`, without callers; `None` unless `language` is `python` or
+    /// `javascript`
+    pub fn from_source(module: &str, code: &str, language: &str) -> Option<Self> {
+        let signatures = match language {
+            "python" => python_functions(code),
+            "javascript" => javascript_functions(code),
+            _ => return None,
+        };
+        let functions = signatures
+            .into_iter()
+            .map(|signature| InterfaceFunction {
+                confidence: signature.confidence(),
+                signature,
+                callers: Vec::new(),
+            })
+            .collect();
+        Some(Self { module: module.to_string(), language: language.to_string(), functions })
+    }
+
+    /// Record where `code`, the contents of `file`, calls the spec's functions
+    pub fn add_callers(&mut self, file: &str, code: &str) {
+        for (i, line) in code.lines().enumerate() {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with('#') || trimmed.starts_with("//") || trimmed.starts_with('*') {
+                continue;
+            }
+            for function in &mut self.functions {
+                if calls(line, &function.signature.name) {
+                    function.callers.push(CallSite { file: file.to_string(), line: i + 1 });
+                }
+            }
+        }
+    }
+
+    /// The module's file name without its extension, which the bindings are named after
+    pub fn module_name(&self) -> &str {
+        Path::new(&self.module).file_stem().and_then(|stem| stem.to_str()).unwrap_or("module")
+    }
+}
+
+pub(crate) fn collect_sources(dir: &Path, language: &str, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else { return };
+    for path in entries.flatten().map(|entry| entry.path()) {
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        if path.is_dir() {
+            if !name.starts_with('.') && name != "target" && name != "node_modules" {
+                collect_sources(&path, language, out);
+            }
+        } else if language_for_path(&name) == Some(language) {
+            out.push(path);
+        }
+    }
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+/// Whether `line` calls `name`, directly or through a module (`utils.name(..)`)
+fn calls(line: &str, name: &str) -> bool {
+    let pattern = format!("{}(", name);
+    line.match_indices(&pattern).any(|(at, _)| {
+        let before = &line[..at];
+        let declaration = ["def", "function"].iter().any(|keyword| {
+            before
+                .trim_end()
+                .strip_suffix(keyword)
+                .is_some_and(|rest| before.ends_with(' ') && !rest.ends_with(is_identifier_char))
+        });
+        !before.ends_with(is_identifier_char) && !declaration
+    })
+}
+
+/// Top-level functions without a leading underscore, narrowed to `__all__` when it is set
+fn python_functions(code: &str) -> Vec<Signature> {
+    let lines: Vec<&str> = code.lines().collect();
+    let exported: Option<Vec<&str>> =
+        lines.iter().position(|line| line.starts_with("__all__")).map(|start| {
+            let mut names = Vec::new();
+            for line in &lines[start..] {
+                names.extend(line.split(['"', '\'']).skip(1).step_by(2));
+                if line.contains(']') || line.contains(')') {
+                    break;
+                }
+            }
+            names
+        });
+    infer::infer_signatures(code)
+        .into_iter()
+        .filter(|signature| lines[signature.line].starts_with("def "))
+        .filter(|signature| match &exported {
+            Some(names) => names.contains(&signature.name.as_str()),
+            None => !signature.name.starts_with('_'),
+        })
+        .collect()
+}
+
+/// Index of the parenthesis closing an already opened one in `text`
+fn closing_paren(text: &str) -> Option<usize> {
+    let mut depth = 1;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// `(exported, name, parameters, rest)` for a function declared at the start of `text`:
+/// `function f(..)`, `const f = (..) =>` or `const f = function (..)`, maybe exported or async
+fn javascript_declaration(text: &str) -> Option<(bool, &str, &str, &str)> {
+    let (exported, rest) = match text.strip_prefix("export ") {
+        Some(rest) => (true, rest.trim_start_matches("default ")),
+        None => (false, text),
+    };
+    let rest = rest.trim_start_matches("async ");
+    let (name, after, arrow) = match rest.strip_prefix("function ") {
+        Some(rest) => {
+            let (name, after) = rest.split_once('(')?;
+            (name, after, false)
+        }
+        None => {
+            let rest = ["const ", "let ", "var "].iter().find_map(|k| rest.strip_prefix(k))?;
+            let (name, value) = rest.split_once('=')?;
+            let value = value.trim_start().trim_start_matches("async ");
+            match value.strip_prefix("function") {
+                Some(expression) => (name, expression.split_once('(')?.1, false),
+                None => (name, value.strip_prefix('(')?, true),
+            }
+        }
+    };
+    let name = name.trim();
+    if name.is_empty() || !name.chars().all(is_identifier_char) {
+        return None;
+    }
+    let close = closing_paren(after)?;
+    let rest = &after[close + 1..];
+    if arrow && !rest.trim_start().starts_with("=>") {
+        return None;
+    }
+    Some((exported, name, &after[..close], rest))
+}
+
+/// The Rust type for a JSDoc type
+fn jsdoc_type(ty: &str) -> String {
+    let ty = ty.trim();
+    if let Some(inner) = ty.strip_prefix('?') {
+        return format!("Option<{}>", jsdoc_type(inner));
+    }
+    if let Some(element) = ty.strip_suffix("[]") {
+        return format!("Vec<{}>", jsdoc_type(element));
+    }
+    let (base, args) = match ty.split_once('<') {
+        Some((base, rest)) => (base, rest.strip_suffix('>').map(split_top_level)),
+        None => (ty, None),
+    };
+    let arg = |i: usize| {
+        args.as_ref()
+            .and_then(|args| args.get(i))
+            .map(|arg| jsdoc_type(arg))
+            .unwrap_or_else(|| DYNAMIC.to_string())
+    };
+    match base {
+        "number" => "f64".to_string(),
+        "string" => "String".to_string(),
+        "boolean" => "bool".to_string(),
+        "void" | "undefined" => "()".to_string(),
+        "*" | "any" | "object" | "Object" if args.is_none() => DYNAMIC.to_string(),
+        "Array" => format!("Vec<{}>", arg(0)),
+        "Object" | "Record" => format!("HashMap<String, {}>", arg(1)),
+        // Callers await the Rust port like they awaited the original
+        "Promise" => arg(0),
+        other => other.to_string(),
+    }
+}
+
+/// The type of a JavaScript literal
+fn literal_type(value: &str) -> Option<String> {
+    let value = value.trim().trim_end_matches(';');
+    let ty = if value.parse::<f64>().is_ok() {
+        "f64".to_string()
+    } else if value == "true" || value == "false" {
+        "bool".to_string()
+    } else if value.len() >= 2 && ['"', '\'', '`'].iter().any(|&q| value.starts_with(q)) {
+        "String".to_string()
+    } else if value.starts_with('[') {
+        format!("Vec<{}>", DYNAMIC)
+    } else {
+        return None;
+    };
+    Some(ty)
+}
+
+/// `@param` types by name and the `@returns` type from the JSDoc comment ending above `line`
+fn jsdoc(lines: &[&str], line: usize) -> (Vec<(String, String)>, Option<String>) {
+    let mut params = Vec::new();
+    let mut returns = None;
+    let comment = lines[..line].iter().rev().map(|line| line.trim()).take_while(|line| {
+        line.starts_with('*') || line.starts_with("/**") || line.starts_with("*/")
+    });
+    for line in comment {
+        let tag = line.trim_start_matches(['/', '*']).trim_start();
+        let (is_param, rest) = if let Some(rest) = tag.strip_prefix("@param") {
+            (true, rest)
+        } else if let Some(rest) =
+            tag.strip_prefix("@returns").or_else(|| tag.strip_prefix("@return"))
+        {
+            (false, rest)
+        } else {
+            continue;
+        };
+        let Some((ty, rest)) = rest.trim_start().strip_prefix('{').and_then(|r| r.split_once('}'))
+        else {
+            continue;
+        };
+        if is_param {
+            let name = rest.split_whitespace().next().unwrap_or_default();
+            let name = name.trim_matches(['[', ']']).split('=').next().unwrap_or_default();
+            params.push((name.to_string(), jsdoc_type(ty)));
+        } else {
+            returns = Some(jsdoc_type(ty));
+        }
+    }
+    (params, returns)
+}
+
+/// Lines of the function body starting on `line`, ending where its braces balance
+fn javascript_body<'a>(lines: &'a [&'a str], line: usize) -> &'a [&'a str] {
+    let mut depth = 0usize;
+    for (i, text) in lines.iter().enumerate().skip(line) {
+        depth += text.matches('{').count();
+        depth = depth.saturating_sub(text.matches('}').count());
+        if depth == 0 {
+            return &lines[line..=i];
+        }
+    }
+    &lines[line..]
+}
+
+/// Exported functions, declared with `export` or listed in `export { .. }` or `module.exports`
+fn javascript_functions(code: &str) -> Vec<Signature> {
+    let lines: Vec<&str> = code.lines().collect();
+    let exported_elsewhere: Vec<&str> = lines
+        .iter()
+        .map(|line| line.trim_start())
+        .filter(|line| {
+            line.starts_with("export {")
+                || line.starts_with("exports.")
+                || line.contains("module.exports")
+        })
+        .flat_map(|line| line.split(|c: char| !is_identifier_char(c)))
+        .collect();
+
+    let mut functions = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        if line.starts_with(char::is_whitespace) {
+            continue;
+        }
+        // A signature may span lines
+        let text = lines[i..lines.len().min(i + 10)].join("\n");
+        let Some((exported, name, params, rest)) = javascript_declaration(&text) else { continue };
+        if !exported && !exported_elsewhere.contains(&name) {
+            continue;
+        }
+
+        let (documented, documented_return) = jsdoc(&lines, i);
+        let body = javascript_body(&lines, i);
+        let params = split_top_level(params)
+            .into_iter()
+            .enumerate()
+            .map(|(n, param)| {
+                let (param, default) = match param.split_once('=') {
+                    Some((param, default)) => (param.trim(), Some(default)),
+                    None => (param, None),
+                };
+                if let Some(rest) = param.strip_prefix("...") {
+                    let ty =
+                        Inferred { ty: format!("Vec<{}>", DYNAMIC), source: TypeSource::Usage };
+                    return Param { name: rest.to_string(), ty };
+                }
+                // Destructured parameters have no name of their own
+                let name = if param.chars().all(is_identifier_char) {
+                    param.to_string()
+                } else {
+                    format!("arg{}", n)
+                };
+                let ty = if let Some((_, ty)) = documented.iter().find(|(doc, _)| *doc == name) {
+                    Inferred { ty: ty.clone(), source: TypeSource::Annotation }
+                } else if let Some(ty) = default.and_then(literal_type) {
+                    Inferred { ty, source: TypeSource::Literal }
+                } else {
+                    Inferred { ty: DYNAMIC.to_string(), source: TypeSource::Fallback }
+                };
+                Param { name, ty }
+            })
+            .collect();
+
+        // An arrow function without braces returns its expression
+        let expression = rest
+            .trim_start()
+            .strip_prefix("=>")
+            .and_then(|body| body.trim_start().lines().next())
+            .filter(|body| !body.starts_with('{'));
+        let returned: Vec<&str> = match expression {
+            Some(expression) => vec![expression],
+            None => body.iter().filter_map(|line| line.trim().strip_prefix("return ")).collect(),
+        };
+        let returns = match documented_return {
+            Some(ty) => Inferred { ty, source: TypeSource::Annotation },
+            None if returned.is_empty() => {
+                Inferred { ty: "()".to_string(), source: TypeSource::Literal }
+            }
+            None => {
+                let types: Vec<Option<String>> =
+                    returned.iter().map(|value| literal_type(value)).collect();
+                match &types[..] {
+                    [Some(first), rest @ ..]
+                        if rest.iter().all(|ty| ty.as_ref() == Some(first)) =>
+                    {
+                        Inferred { ty: first.clone(), source: TypeSource::Literal }
+                    }
+                    _ => Inferred { ty: DYNAMIC.to_string(), source: TypeSource::Fallback },
+                }
+            }
+        };
+
+        functions.push(Signature {
+            name: name.to_string(),
+            line: i,
+            method: false,
+            params,
+            returns,
+            generics: Vec::new(),
+            raises: body.iter().any(|line| line.contains("throw ")),
+        });
+    }
+    functions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MirroringEngine;
+
+    #[test]
+    fn test_extracts_interface_and_generates_bindings() {
+        let python = "\
+__all__ = ['area', 'scale']
+
+def area(width: float, height: float) -> float:
+    return width * height
+
+def scale(values, factor=2):
+    for v in values:
+        pass
+    if factor < 0:
+        raise ValueError(factor)
+    return [v * factor for v in values]
+
+def _helper(x):
+    return x
+";
+        let mut spec = InterfaceSpec::from_source("geometry/shapes.py", python, "python").unwrap();
+        spec.add_callers(
+            "app/views.py",
+            "from geometry import shapes\n\ndef render(w):\n    return shapes.area(w, 2)\n",
+        );
+        let names: Vec<&str> = spec.functions.iter().map(|f| f.signature.name.as_str()).collect();
+        assert_eq!(names, ["area", "scale"]);
+        assert_eq!(spec.functions[0].confidence, Confidence::High);
+        assert_eq!(spec.functions[0].callers, [CallSite { file: "app/views.py".into(), line: 4 }]);
+        assert_eq!(spec.functions[1].signature.params[1].ty.ty, "i64");
+        assert!(spec.functions[1].callers.is_empty());
+
+        let json = serde_json::to_string(&spec).unwrap();
+        assert_eq!(serde_json::from_str::<InterfaceSpec>(&json).unwrap(), spec);
+
+        let engine = MirroringEngine::new();
+        let skeleton = engine.rust_skeleton(&spec);
+        assert!(skeleton.contains(
+            "/// Called from app/views.py:4\npub fn area(width: f64, height: f64) -> Result<f64, Box<dyn std::error::Error>> {"
+        ));
+        let shim = engine.ffi_shim(&spec);
+        assert!(shim.contains("#[pyo3(name = \"scale\")]"));
+        assert!(
+            shim.contains("let values: Vec<serde_json::Value> = pythonize::depythonize(values)?;")
+        );
+        assert!(shim.contains("crate::scale(&values, factor)"));
+        assert!(shim.contains("fn shapes(m: &Bound<'_, PyModule>) -> PyResult<()> {"));
+
+        let javascript = "\
+/**
+ * @param {number[]} prices
+ * @returns {number}
+ */
+export function total(prices, tax = 0.2) {
+  return prices.reduce((a, b) => a + b, 0) * (1 + tax);
+}
+
+const label = (name) => `item ${name}`;
+function internal() {}
+module.exports = { label };
+";
+        let spec = InterfaceSpec::from_source("web/cart.js", javascript, "javascript").unwrap();
+        let signatures: Vec<&Signature> = spec.functions.iter().map(|f| &f.signature).collect();
+        assert_eq!(signatures.len(), 2);
+        assert_eq!((signatures[0].name.as_str(), signatures[0].line), ("total", 4));
+        assert_eq!(signatures[0].params[0].ty.ty, "Vec<f64>");
+        assert_eq!(signatures[0].params[1].ty.ty, "f64");
+        assert_eq!(signatures[0].returns.ty, "f64");
+        assert_eq!(
+            (signatures[1].name.as_str(), signatures[1].returns.ty.as_str()),
+            ("label", "String")
+        );
+        assert_eq!(spec.functions[1].confidence, Confidence::Low);
+        assert!(engine.ffi_shim(&spec).contains("#[wasm_bindgen(js_name = label)]"));
+
+        assert!(calls("x = utils.total(1)", "total"));
+        assert!(!calls("def total(x):", "total"));
+        assert!(!calls("subtotal(1)", "total"));
+    }
+}
@@ -1,3 +1,4 @@
+use parflow_transpiler::stdlib::StdlibMap;
 use semantic_compiler::PatternType;
 
 pub struct LanguageTranslator;
@@ -47,4 +48,16 @@ def process_data(data):
             ),
         }
     }
+
+    /// Rewrite standard library calls in `code` with `stdlib`, e.g. `len(x)` → `x.len()` from
+    /// Python to Rust
+    pub fn translate_calls(
+        &self,
+        code: &str,
+        source_lang: &str,
+        target_lang: &str,
+        stdlib: &StdlibMap,
+    ) -> String {
+        code.lines().map(|line| stdlib.rewrite(line, source_lang, target_lang) + "\n").collect()
+    }
 }
@@ -1,5 +1,9 @@
+pub mod benchmark;
+pub mod interface;
 pub mod language_translator;
 pub mod mirroring_engine;
 
+pub use benchmark::{BenchmarkHarness, BenchmarkResult};
+pub use interface::InterfaceSpec;
 pub use language_translator::LanguageTranslator;
 pub use mirroring_engine::{MirroringEngine, MirroringResult, RepositoryAnalysis};
@@ -1,6 +1,13 @@
+use crate::benchmark::{overall_speedup, BenchmarkHarness, BenchmarkResult};
+use crate::interface::{collect_sources, InterfaceFunction, InterfaceSpec};
 use anyhow::Result;
-use colored::*;
+use parflow_core::cancel::{CancellationToken, Partial};
+use parflow_transpiler::infer::DYNAMIC;
+use parflow_transpiler::options::TranspileOptions;
+use semantic_compiler::ownership::language_for_path;
 use serde::Serialize;
+use std::path::Path;
+use tracing::{info, instrument};
 
 #[derive(Default)]
 pub struct MirroringEngine;
@@ -11,7 +18,7 @@ impl MirroringEngine {
     }
 
     pub async fn analyze_repository(&self, repo_path: &str) -> Result<RepositoryAnalysis> {
-        println!("{} {}", "🔍 Analyzing repository:".bright_blue(), repo_path.bright_cyan());
+        info!(repo = repo_path, "🔍 Analyzing repository");
 
         // Mock implementation - in real version, this would analyze actual code
         let mut analysis = RepositoryAnalysis::new(repo_path);
@@ -23,7 +30,7 @@ impl MirroringEngine {
 
         analysis.generate_mirroring_plan();
 
-        println!("{}", "✅ Repository analysis complete!".bright_green());
+        info!(languages = analysis.languages.len(), "✅ Repository analysis complete");
         Ok(analysis)
     }
 
@@ -32,21 +39,70 @@ impl MirroringEngine {
         source_path: &str,
         target_language: &str,
     ) -> Result<MirroringResult> {
-        println!(
-            "{} {} {} {}",
-            "🔄 Mirroring:".bright_blue(),
-            source_path.bright_yellow(),
-            "→".bright_white(),
-            target_language.bright_green()
-        );
-
-        // Mock implementation
-        Ok(MirroringResult {
-            original_file_count: 50,
-            mirrored_file_count: 45,
-            performance_improvement: 3.5,
-            warnings: vec!["Some patterns couldn't be perfectly mirrored".to_string()],
-        })
+        let result = self
+            .mirror_codebase_cancellable(source_path, target_language, CancellationToken::new())
+            .await?;
+        Ok(result.value)
+    }
+
+    /// Same as `mirror_codebase`, leaving the remaining files unmirrored once `cancel` fires.
+    #[instrument(name = "mirror", skip_all, fields(source = source_path, target = target_language))]
+    pub async fn mirror_codebase_cancellable(
+        &self,
+        source_path: &str,
+        target_language: &str,
+        cancel: CancellationToken,
+    ) -> Result<Partial<MirroringResult>> {
+        info!("🔄 Mirroring codebase");
+
+        let source = Path::new(source_path);
+        let mut files = Vec::new();
+        for language in ["python", "javascript"] {
+            if source.is_dir() {
+                collect_sources(source, language, &mut files);
+            } else if language_for_path(source_path) == Some(language) {
+                files.push(source.to_path_buf());
+            }
+        }
+        files.sort();
+
+        let mut result = MirroringResult {
+            original_file_count: files.len(),
+            mirrored_file_count: 0,
+            // Nothing is measured until the benchmarks run against the mirrored build
+            performance_improvement: 1.0,
+            warnings: Vec::new(),
+            benchmarks: Vec::new(),
+            benchmark_results: Vec::new(),
+        };
+        for file in &files {
+            if cancel.is_cancelled() {
+                result.warnings.push(format!(
+                    "Cancelled after mirroring {} of {} files",
+                    result.mirrored_file_count, result.original_file_count
+                ));
+                return Ok(Partial::cancelled(result));
+            }
+            let Some(language) = language_for_path(&file.to_string_lossy()) else { continue };
+            let Ok(code) = std::fs::read_to_string(file) else {
+                result.warnings.push(format!("{} is not UTF-8", file.display()));
+                continue;
+            };
+            let module = file.strip_prefix(source).unwrap_or(file).to_string_lossy().into_owned();
+            let Some(spec) = InterfaceSpec::from_source(&module, &code, language) else { continue };
+            if spec.functions.is_empty() {
+                result.warnings.push(format!("{} has no public functions to mirror", module));
+                continue;
+            }
+            result.mirrored_file_count += 1;
+            if target_language == "rust" {
+                result.benchmarks.push(BenchmarkHarness::new(&spec, file));
+            }
+        }
+        if target_language != "rust" && result.mirrored_file_count > 0 {
+            result.warnings.push("Benchmarks are only generated for mirrors in Rust".to_string());
+        }
+        Ok(Partial::complete(result))
     }
 
     pub async fn mirror_with_dependencies(
@@ -54,13 +110,25 @@ impl MirroringEngine {
         source_path: &str,
         target_language: &str,
     ) -> Result<EnhancedMirroringResult> {
-        println!(
-            "{} {} {} {}",
-            "🔄 Mirroring with dependency analysis:".bright_blue().bold(),
-            source_path.bright_yellow(),
-            "→".bright_white(),
-            target_language.bright_green()
-        );
+        let result = self
+            .mirror_with_dependencies_cancellable(
+                source_path,
+                target_language,
+                CancellationToken::new(),
+            )
+            .await?;
+        Ok(result.value)
+    }
+
+    /// Same as `mirror_with_dependencies`, passing `cancel` on to the mirroring itself.
+    #[instrument(name = "mirror", skip_all, fields(source = source_path, target = target_language))]
+    pub async fn mirror_with_dependencies_cancellable(
+        &self,
+        source_path: &str,
+        target_language: &str,
+        cancel: CancellationToken,
+    ) -> Result<Partial<EnhancedMirroringResult>> {
+        info!("🔄 Mirroring with dependency analysis");
 
         // Use mock types instead of external dependency
         let crate_recommendations = MockCrateRecommendations {
@@ -89,10 +157,11 @@ impl MirroringEngine {
             },
         };
 
-        let mirror_result = self.mirror_codebase(source_path, target_language).await?;
+        let mirror_result =
+            self.mirror_codebase_cancellable(source_path, target_language, cancel).await?;
 
-        Ok(EnhancedMirroringResult {
-            basic_mirroring: mirror_result,
+        Ok(mirror_result.map(|basic_mirroring| EnhancedMirroringResult {
+            basic_mirroring,
             dependency_recommendations: crate_recommendations,
             compatibility_report: CompatibilityReport {
                 compatible_dependencies: vec![
@@ -106,8 +175,162 @@ impl MirroringEngine {
                                                integration"
                     .to_string()],
             },
-        })
+        }))
+    }
+
+    /// A Rust module with the functions of `spec`, their bodies left to port
+    pub fn rust_skeleton(&self, spec: &InterfaceSpec) -> String {
+        let options = TranspileOptions::default();
+        let mut code =
+            format!("//! Rust port of `{}`, generated from its interface spec\n", spec.module);
+        for function in &spec.functions {
+            let signature = &function.signature;
+            code.push('\n');
+            if !function.callers.is_empty() {
+                let sites: Vec<String> = function
+                    .callers
+                    .iter()
+                    .map(|site| format!("{}:{}", site.file, site.line))
+                    .collect();
+                code.push_str(&format!("/// Called from {}\n", sites.join(", ")));
+            }
+            code.push_str(&format!(
+                "pub {} {{\n    todo!(\"port {} from {}\")\n}}\n",
+                signature.to_rust(&options),
+                signature.name,
+                spec.module
+            ));
+        }
+        code
+    }
+
+    /// Bindings that expose [`Self::rust_skeleton`] under the original names, so callers keep
+    /// working: PyO3 for a Python module, wasm-bindgen for a JavaScript one. Values of types
+    /// that do not cross the boundary as they are go through `pythonize` or `serde_wasm_bindgen`.
+    pub fn ffi_shim(&self, spec: &InterfaceSpec) -> String {
+        let python = spec.language == "python";
+        let mut code = if python {
+            format!(
+                "//! Python bindings for `{}`, built as the extension module `{}`\n\n\
+                 use pyo3::exceptions::PyValueError;\nuse pyo3::prelude::*;\n",
+                spec.module,
+                spec.module_name()
+            )
+        } else {
+            format!(
+                "//! WebAssembly bindings for `{}`\n\nuse wasm_bindgen::prelude::*;\n",
+                spec.module
+            )
+        };
+        for function in &spec.functions {
+            code.push('\n');
+            code.push_str(&shim_function(function, python));
+        }
+        if python {
+            code.push_str(&format!(
+                "\n#[pymodule]\nfn {}(m: &Bound<'_, PyModule>) -> PyResult<()> {{\n",
+                spec.module_name()
+            ));
+            for function in &spec.functions {
+                code.push_str(&format!(
+                    "    m.add_function(wrap_pyfunction!({}_py, m)?)?;\n",
+                    function.signature.name
+                ));
+            }
+            code.push_str("    Ok(())\n}\n");
+        }
+        code
+    }
+}
+
+/// Types both PyO3 and wasm-bindgen convert without serde
+fn crosses_boundary(ty: &str) -> bool {
+    matches!(ty, "i64" | "f64" | "bool" | "usize" | "String" | "()")
+}
+
+/// One exported wrapper around the skeleton function for `function`
+fn shim_function(function: &InterfaceFunction, python: bool) -> String {
+    let options = TranspileOptions::default();
+    let signature = &function.signature;
+    // The bindings cannot be generic; generic parameters take any value
+    let concrete = |ty: &str| {
+        if signature.generics.iter().any(|generic| generic == ty) {
+            DYNAMIC.to_string()
+        } else {
+            ty.to_string()
+        }
+    };
+
+    let mut params = Vec::new();
+    let mut conversions = Vec::new();
+    let mut args = Vec::new();
+    for param in &signature.params {
+        let ty = concrete(&param.ty.ty);
+        if crosses_boundary(&ty) {
+            params.push(format!("{}: {}", param.name, ty));
+        } else if python {
+            params.push(format!("{}: &Bound<'_, PyAny>", param.name));
+            conversions
+                .push(format!("let {0}: {1} = pythonize::depythonize({0})?;", param.name, ty));
+        } else {
+            params.push(format!("{}: JsValue", param.name));
+            conversions.push(format!(
+                "let {0}: {1} = serde_wasm_bindgen::from_value({0})?;",
+                param.name, ty
+            ));
+        }
+        let borrowed = param.to_rust(&options).starts_with('&');
+        args.push(if borrowed { format!("&{}", param.name) } else { param.name.clone() });
+    }
+
+    let mut call = format!("crate::{}({})", signature.name, args.join(", "));
+    if signature.fallible(&options) {
+        let error = if python {
+            "PyValueError::new_err(e.to_string())"
+        } else {
+            "JsValue::from_str(&e.to_string())"
+        };
+        call = format!("{}.map_err(|e| {})?", call, error);
     }
+    let returns = concrete(&signature.returns.ty);
+    let (returns, body) = if crosses_boundary(&returns) {
+        (returns, format!("Ok({})", call))
+    } else if python {
+        params.insert(0, "py: Python<'_>".to_string());
+        (
+            "PyObject".to_string(),
+            format!("let result = {};\n    Ok(pythonize::pythonize(py, &result)?.unbind())", call),
+        )
+    } else {
+        (
+            "JsValue".to_string(),
+            format!("let result = {};\n    Ok(serde_wasm_bindgen::to_value(&result)?)", call),
+        )
+    };
+    let (attributes, name, result) = if python {
+        (
+            format!("#[pyfunction]\n#[pyo3(name = \"{}\")]", signature.name),
+            format!("{}_py", signature.name),
+            format!("PyResult<{}>", returns),
+        )
+    } else {
+        (
+            format!("#[wasm_bindgen(js_name = {})]", signature.name),
+            format!("{}_js", signature.name),
+            format!("Result<{}, JsValue>", returns),
+        )
+    };
+    let conversions: String =
+        conversions.iter().map(|conversion| format!("    {}\n", conversion)).collect();
+    format!(
+        "{}\npub fn {}({}) -> {} {{\n{}    {}\n}}\n",
+        attributes,
+        name,
+        params.join(", "),
+        result,
+        conversions,
+        body
+    )
 }
 
 #[derive(Debug, Serialize)]
@@ -163,8 +386,43 @@ pub struct MirroringSuggestion {
 pub struct MirroringResult {
     pub original_file_count: usize,
     pub mirrored_file_count: usize,
+    /// Speedup measured by [`Self::measure`]; 1.0 until then
     pub performance_improvement: f64,
     pub warnings: Vec<String>,
+    /// One harness per mirrored module
+    pub benchmarks: Vec<BenchmarkHarness>,
+    pub benchmark_results: Vec<BenchmarkResult>,
+}
+
+impl MirroringResult {
+    /// Run the benchmarks against the mirrored builds in `dir`, setting
+    /// `performance_improvement` to the measured speedup. A harness that cannot run, or a
+    /// function whose output differs, becomes a warning.
+    pub fn measure(&mut self, dir: &Path) {
+        self.benchmark_results.clear();
+        for harness in &self.benchmarks {
+            match harness.run(dir) {
+                Ok(results) => self.benchmark_results.extend(results),
+                Err(e) => self
+                    .warnings
+                    .push(format!("Benchmark of {} did not run: {:#}", harness.module, e)),
+            }
+        }
+        for result in &self.benchmark_results {
+            if let Some(mismatch) = result.mismatches.first() {
+                self.warnings.push(format!(
+                    "{} differs on {} of {} inputs, e.g. {} gives {} instead of {}",
+                    result.function,
+                    result.mismatches.len(),
+                    result.cases,
+                    serde_json::Value::from(mismatch.input.clone()),
+                    mismatch.mirrored,
+                    mismatch.original
+                ));
+            }
+        }
+        self.performance_improvement = overall_speedup(&self.benchmark_results).unwrap_or(1.0);
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -0,0 +1,317 @@
+//! Where tasks run
+//!
+//! An [`ExecutionBackend`] turns a [`LanguageTask`] into an [`ExecutionResult`]. Tasks that
+//! declare an `image` run in a container through [`ContainerBackend`], so a workflow needs no
+//! toolchain on the host beyond Docker or Podman:
+//!
+//! ```yaml
+//! - name: worker-test
+//!   language: Python
+//!   image: python:3.12
+//!   command: python
+//!   args: [-m, pytest]
+//!   working_dir: worker
+//!   resources: { cpu_cores: 2, memory_mb: 1024 }
+//! ```
+//!
+//! The workspace (the working directory of the orchestrator) is mounted at `/workspace`, and a
+//! relative `working_dir` is taken inside it. `resources` become the container's CPU and
+//! memory limits, and `env` its environment. A [`policy`](crate::policy) that denies the
+//! network runs the container without one, and a read-only one mounts the workspace read-only
+//! apart from its `allow_paths`, with a read-only root and a scratch `/tmp`. The runtime is
+//! `$PARFLOW_CONTAINER_RUNTIME` if set, else `docker` or `podman`, whichever is found on the
+//! `PATH` first.
+
+use crate::policy::{in_workspace, Filesystem, Network};
+use crate::{ExecutionResult, LanguageTask};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Selects the container runtime, `docker` or `podman`
+pub const RUNTIME_ENV: &str = "PARFLOW_CONTAINER_RUNTIME";
+/// Where the workspace is mounted in task containers
+pub const CONTAINER_WORKSPACE: &str = "/workspace";
+
+pub type TaskFuture = Pin<Box<dyn Future<Output = ExecutionResult> + Send>>;
+
+pub trait ExecutionBackend: Send + Sync {
+    /// Run `task` to completion. Dropping the future stops the task.
+    fn execute(&self, task: LanguageTask) -> TaskFuture;
+
+    /// Whether `task` really runs, rather than being simulated, so what it writes to the
+    /// workspace can be checked against its declared outputs
+    fn runs(&self, _task: &LanguageTask) -> bool {
+        true
+    }
+}
+
+/// Simulates every task: each succeeds after 500ms with placeholder output.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MockBackend;
+
+impl ExecutionBackend for MockBackend {
+    fn runs(&self, _task: &LanguageTask) -> bool {
+        false
+    }
+
+    fn execute(&self, task: LanguageTask) -> TaskFuture {
+        Box::pin(async move {
+            info!("▶️  Executing task");
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            ExecutionResult {
+                task_name: task.name(),
+                output: format!("Mock output from {} task", task.language),
+                language: task.language,
+                success: true,
+                execution_time: 500,
+                exit_code: Some(0),
+                changes: None,
+            }
+        })
+    }
+}
+
+/// Runs tasks that declare an image in a Docker or Podman container, and hands the others to
+/// `host`.
+pub struct ContainerBackend {
+    /// `None` when no runtime was found; tasks with an image then fail
+    runtime: Option<String>,
+    workspace: PathBuf,
+    host: Arc<dyn ExecutionBackend>,
+}
+
+impl ContainerBackend {
+    pub fn new(
+        runtime: Option<String>,
+        workspace: PathBuf,
+        host: Arc<dyn ExecutionBackend>,
+    ) -> Self {
+        Self { runtime, workspace, host }
+    }
+
+    /// The runtime from the environment or `PATH`, mounting the current directory
+    pub fn from_env(host: Arc<dyn ExecutionBackend>) -> Self {
+        let runtime =
+            std::env::var(RUNTIME_ENV).ok().filter(|runtime| !runtime.is_empty()).or_else(|| {
+                ["docker", "podman"].into_iter().find(|name| on_path(name)).map(String::from)
+            });
+        let workspace = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        Self::new(runtime, workspace, host)
+    }
+
+    /// Arguments to the runtime that run `task` in a container named `container`
+    pub fn run_args(&self, task: &LanguageTask, container: &str) -> Vec<String> {
+        let mut args: Vec<String> =
+            vec!["run".into(), "--rm".into(), "--name".into(), container.into()];
+        let read_only = task.policy.fs == Filesystem::ReadOnly;
+        args.push("--volume".into());
+        args.push(format!(
+            "{}:{}{}",
+            self.workspace.display(),
+            CONTAINER_WORKSPACE,
+            if read_only { ":ro" } else { "" }
+        ));
+        if read_only {
+            for path in task.policy.allow_paths.iter().filter(|path| in_workspace(path)) {
+                let path = path.trim_start_matches("./").trim_end_matches('/');
+                args.push("--volume".into());
+                args.push(format!(
+                    "{}:{}/{}",
+                    self.workspace.join(path).display(),
+                    CONTAINER_WORKSPACE,
+                    path
+                ));
+            }
+            args.extend(["--read-only".into(), "--tmpfs=/tmp".into()]);
+        }
+        if task.policy.network == Network::Deny {
+            args.push("--network=none".into());
+        }
+        let workdir = match &task.working_dir {
+            Some(dir) if Path::new(dir).is_absolute() => dir.clone(),
+            Some(dir) => format!("{}/{}", CONTAINER_WORKSPACE, dir.trim_start_matches("./")),
+            None => CONTAINER_WORKSPACE.to_string(),
+        };
+        args.push("--workdir".into());
+        args.push(workdir);
+        if let Some(resources) = &task.resources {
+            if resources.cpu_cores > 0 {
+                args.push(format!("--cpus={}", resources.cpu_cores));
+            }
+            if resources.memory_mb > 0 {
+                args.push(format!("--memory={}m", resources.memory_mb));
+            }
+        }
+        // Docker runs as root, leaving root-owned files in the workspace; Podman maps root to
+        // the calling user already
+        #[cfg(unix)]
+        if self.runtime.as_deref().is_some_and(|runtime| runtime.ends_with("docker")) {
+            use std::os::unix::fs::MetadataExt;
+            if let Ok(metadata) = std::fs::metadata(&self.workspace) {
+                args.push(format!("--user={}:{}", metadata.uid(), metadata.gid()));
+            }
+        }
+        for (name, value) in &task.env {
+            args.push("--env".into());
+            args.push(format!("{}={}", name, value));
+        }
+        args.push(task.image.clone().unwrap_or_default());
+        args.push(task.command.clone());
+        args.extend(task.args.iter().cloned());
+        args
+    }
+}
+
+impl ExecutionBackend for ContainerBackend {
+    fn runs(&self, task: &LanguageTask) -> bool {
+        task.image.is_some() || self.host.runs(task)
+    }
+
+    fn execute(&self, task: LanguageTask) -> TaskFuture {
+        let Some(image) = task.image.clone() else { return self.host.execute(task) };
+        let Some(runtime) = self.runtime.clone() else {
+            return Box::pin(async move {
+                failed(
+                    &task,
+                    format!(
+                        "no container runtime for image {}: install docker or podman, or set {}",
+                        image, RUNTIME_ENV
+                    ),
+                    0,
+                )
+            });
+        };
+        let container = format!("parflow-{}", uuid::Uuid::new_v4());
+        let args = self.run_args(&task, &container);
+        Box::pin(async move {
+            info!(%image, %runtime, "🐳 Executing task in container");
+            let started = Instant::now();
+            // The runtime client going away does not stop the container, so remove it unless
+            // it exits by itself
+            let mut cleanup =
+                RemoveContainer { runtime: runtime.clone(), container: Some(container) };
+            let run =
+                tokio::process::Command::new(&runtime).args(&args).kill_on_drop(true).output();
+            let output = match task.timeout_seconds {
+                Some(secs) => match tokio::time::timeout(Duration::from_secs(secs), run).await {
+                    Ok(output) => output,
+                    Err(_) => {
+                        warn!(secs, "⏱️  Task timed out");
+                        let elapsed = started.elapsed().as_millis();
+                        return failed(&task, format!("timed out after {}s", secs), elapsed);
+                    }
+                },
+                None => run.await,
+            };
+            let elapsed = started.elapsed().as_millis();
+            match output {
+                Ok(output) => {
+                    cleanup.container = None;
+                    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+                    text.push_str(&String::from_utf8_lossy(&output.stderr));
+                    ExecutionResult {
+                        task_name: task.name(),
+                        language: task.language,
+                        success: output.status.success(),
+                        output: text,
+                        execution_time: elapsed,
+                        exit_code: output.status.code(),
+                        changes: None,
+                    }
+                }
+                Err(e) => failed(&task, format!("could not start {}: {}", runtime, e), elapsed),
+            }
+        })
+    }
+}
+
+/// Force-removes a container that may still be running when dropped
+struct RemoveContainer {
+    runtime: String,
+    container: Option<String>,
+}
+
+impl Drop for RemoveContainer {
+    fn drop(&mut self) {
+        if let Some(container) = self.container.take() {
+            let _ = std::process::Command::new(&self.runtime)
+                .args(["rm", "--force", &container])
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .spawn();
+        }
+    }
+}
+
+fn failed(task: &LanguageTask, output: String, execution_time: u128) -> ExecutionResult {
+    ExecutionResult {
+        task_name: task.name(),
+        language: task.language.clone(),
+        success: false,
+        output,
+        execution_time,
+        exit_code: None,
+        changes: None,
+    }
+}
+
+pub(crate) fn on_path(binary: &str) -> bool {
+    let Some(path) = std::env::var_os("PATH") else { return false };
+    std::env::split_paths(&path)
+        .any(|dir| dir.join(binary).is_file() || dir.join(format!("{}.exe", binary)).is_file())
+}
+
+/// Containers for tasks with an image, under their policies, and the mock for the rest
+pub fn default_backend() -> Arc<dyn ExecutionBackend> {
+    Arc::new(ContainerBackend::from_env(Arc::new(MockBackend)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Resources;
+
+    #[tokio::test]
+    async fn test_runs_tasks_with_images_in_containers() {
+        let mut task: LanguageTask = serde_yaml::from_str(
+            "{ name: worker-test, language: Python, image: 'python:3.12', command: python, \
+               args: [-m, pytest], working_dir: ./worker }",
+        )
+        .unwrap();
+        task.resources = Some(Resources::new(2, 512));
+        let backend = ContainerBackend::new(
+            Some("podman".into()),
+            PathBuf::from("/src/app"),
+            Arc::new(MockBackend),
+        );
+        assert_eq!(
+            backend.run_args(&task, "parflow-1").join(" "),
+            "run --rm --name parflow-1 --volume /src/app:/workspace --workdir /workspace/worker \
+             --cpus=2 --memory=512m python:3.12 python -m pytest"
+        );
+
+        task.policy =
+            serde_yaml::from_str("{ network: deny, fs: read-only, allow_paths: [out/] }").unwrap();
+        assert_eq!(
+            backend.run_args(&task, "parflow-2").join(" "),
+            "run --rm --name parflow-2 --volume /src/app:/workspace:ro \
+             --volume /src/app/out:/workspace/out --read-only --tmpfs=/tmp --network=none \
+             --workdir /workspace/worker --cpus=2 --memory=512m python:3.12 python -m pytest"
+        );
+
+        let missing = ContainerBackend::new(None, PathBuf::from("/src/app"), Arc::new(MockBackend));
+        let result = missing.execute(task.clone()).await;
+        assert!(!result.success);
+        assert!(result.output.starts_with("no container runtime for image python:3.12"));
+
+        // Tasks without an image are not the container backend's business
+        task.image = None;
+        let result = missing.execute(task).await;
+        assert!(result.success);
+        assert_eq!(result.output, "Mock output from Python task");
+    }
+}
@@ -0,0 +1,536 @@
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+/// Bearer token [`HttpAgentControl`] sends with its requests, when set
+pub const AGENT_TOKEN_ENV: &str = "PARFLOW_AGENT_TOKEN";
+
+/// Time between version requests while waiting for a restarted agent
+const HANDSHAKE_POLL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentInfo {
+    pub id: String,
+    pub version: String,
+    pub capacity: u32,
+    pub state: AgentState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AgentState {
+    Active,
+    Draining,
+    Restarting,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollingUpgradePolicy {
+    /// Upper bound on agents taken out of rotation at once
+    pub max_batch_size: usize,
+    /// Fraction of total fleet capacity that must stay active during the upgrade
+    pub min_capacity_ratio: f64,
+    /// Failure rate (failed / attempted) above which the whole upgrade is rolled back
+    pub max_failure_rate: f64,
+}
+
+impl Default for RollingUpgradePolicy {
+    fn default() -> Self {
+        Self { max_batch_size: 2, min_capacity_ratio: 0.75, max_failure_rate: 0.2 }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct UpgradeReport {
+    pub target_version: String,
+    pub batches: usize,
+    pub upgraded: Vec<String>,
+    pub failed: Vec<String>,
+    pub rolled_back: bool,
+    /// Agents that did not report their previous version again after the roll back
+    #[serde(default)]
+    pub rollback_failed: Vec<String>,
+}
+
+/// Transport used by the coordinator to drive individual agents.
+///
+/// The worker protocol implements this; the coordinator only sequences calls.
+pub trait AgentControl {
+    fn drain(&self, agent_id: &str) -> impl Future<Output = Result<()>> + Send;
+    fn restart(&self, agent_id: &str, version: &str) -> impl Future<Output = Result<()>> + Send;
+    /// Returns the version the agent reports after reconnecting.
+    fn handshake(&self, agent_id: &str) -> impl Future<Output = Result<String>> + Send;
+}
+
+/// Where to reach an agent, as listed for [`HttpAgentControl`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentEndpoint {
+    pub id: String,
+    /// Base URL of the agent's control API
+    pub url: String,
+    pub capacity: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct AgentVersion {
+    version: String,
+}
+
+/// Drives agents through their HTTP control API, relative to each agent's base URL:
+///
+/// - `POST control/drain`: stop taking work, answering once work in flight is done
+/// - `POST control/restart` with `{"version": "..."}`: restart into that version
+/// - `GET control/version`: `{"version": "..."}` once the agent is up
+///
+/// Requests carry `Authorization: Bearer <token>` when a token is set.
+pub struct HttpAgentControl {
+    endpoints: HashMap<String, reqwest::Url>,
+    client: reqwest::Client,
+    token: Option<String>,
+    handshake_timeout: Duration,
+}
+
+impl HttpAgentControl {
+    pub fn new(endpoints: &[AgentEndpoint]) -> Result<Self> {
+        let endpoints = endpoints
+            .iter()
+            .map(|agent| {
+                // Without the trailing slash, joining would replace the last path segment
+                let base = format!("{}/", agent.url.trim_end_matches('/'));
+                let url = reqwest::Url::parse(&base)
+                    .with_context(|| format!("invalid control URL of agent {}", agent.id))?;
+                Ok((agent.id.clone(), url))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            endpoints,
+            client: reqwest::Client::new(),
+            token: None,
+            handshake_timeout: Duration::from_secs(60),
+        })
+    }
+
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.token = token;
+        self
+    }
+
+    /// How long a restarted agent has to answer with its version
+    pub fn with_handshake_timeout(mut self, timeout: Duration) -> Self {
+        self.handshake_timeout = timeout;
+        self
+    }
+
+    fn request(
+        &self,
+        method: reqwest::Method,
+        agent_id: &str,
+        path: &str,
+    ) -> Result<reqwest::RequestBuilder> {
+        let base =
+            self.endpoints.get(agent_id).ok_or_else(|| anyhow!("unknown agent {}", agent_id))?;
+        let request = self.client.request(method, base.join(path)?);
+        Ok(match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        })
+    }
+}
+
+impl AgentControl for HttpAgentControl {
+    async fn drain(&self, agent_id: &str) -> Result<()> {
+        self.request(reqwest::Method::POST, agent_id, "control/drain")?
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    async fn restart(&self, agent_id: &str, version: &str) -> Result<()> {
+        self.request(reqwest::Method::POST, agent_id, "control/restart")?
+            .json(&AgentVersion { version: version.to_string() })
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Ask for the version until the agent answers, as it is down while it restarts. An agent
+    /// that is up but refuses the request fails the handshake right away.
+    async fn handshake(&self, agent_id: &str) -> Result<String> {
+        let deadline = Instant::now() + self.handshake_timeout;
+        loop {
+            let request = self.request(reqwest::Method::GET, agent_id, "control/version")?;
+            let error = match request.send().await {
+                Ok(response) if response.status().is_client_error() => {
+                    bail!("agent {} refused the handshake: {}", agent_id, response.status())
+                }
+                Ok(response) => match response.error_for_status() {
+                    Ok(response) => return Ok(response.json::<AgentVersion>().await?.version),
+                    Err(e) => e,
+                },
+                Err(e) => e,
+            };
+            if Instant::now() >= deadline {
+                bail!(
+                    "agent {} did not answer within {}s: {}",
+                    agent_id,
+                    self.handshake_timeout.as_secs(),
+                    error
+                );
+            }
+            tokio::time::sleep(HANDSHAKE_POLL).await;
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct FleetCoordinator {
+    agents: HashMap<String, AgentInfo>,
+    published_version: Option<String>,
+}
+
+impl FleetCoordinator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register_agent(&mut self, id: &str, version: &str, capacity: u32) {
+        self.agents.insert(
+            id.to_string(),
+            AgentInfo {
+                id: id.to_string(),
+                version: version.to_string(),
+                capacity,
+                state: AgentState::Active,
+            },
+        );
+    }
+
+    pub fn agents(&self) -> Vec<&AgentInfo> {
+        let mut agents: Vec<&AgentInfo> = self.agents.values().collect();
+        agents.sort_by(|a, b| a.id.cmp(&b.id));
+        agents
+    }
+
+    pub fn publish_version(&mut self, version: &str) {
+        info!(version, "📣 Publishing agent version");
+        self.published_version = Some(version.to_string());
+    }
+
+    pub fn total_capacity(&self) -> u32 {
+        self.agents.values().map(|a| a.capacity).sum()
+    }
+
+    pub fn active_capacity(&self) -> u32 {
+        self.agents.values().filter(|a| a.state == AgentState::Active).map(|a| a.capacity).sum()
+    }
+
+    /// Pick the next batch of outdated agents whose removal keeps active capacity above the
+    /// policy threshold. Always returns at least one agent while any are outdated, so a fleet
+    /// that is too small to satisfy the threshold still makes progress one agent at a time.
+    fn next_batch(&self, target: &str, policy: &RollingUpgradePolicy) -> Vec<String> {
+        let floor = (self.total_capacity() as f64 * policy.min_capacity_ratio).ceil() as u32;
+        let mut remaining = self.active_capacity();
+        let mut batch = Vec::new();
+
+        for agent in self.agents() {
+            if agent.version == target || agent.state != AgentState::Active {
+                continue;
+            }
+            if batch.len() >= policy.max_batch_size.max(1) {
+                break;
+            }
+            if !batch.is_empty() && remaining.saturating_sub(agent.capacity) < floor {
+                break;
+            }
+            remaining = remaining.saturating_sub(agent.capacity);
+            batch.push(agent.id.clone());
+        }
+
+        batch
+    }
+
+    pub async fn rolling_upgrade<C: AgentControl>(
+        &mut self,
+        control: &C,
+        policy: &RollingUpgradePolicy,
+    ) -> Result<UpgradeReport> {
+        let target = self
+            .published_version
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("no agent version has been published"))?;
+
+        let previous: HashMap<String, String> =
+            self.agents.values().map(|a| (a.id.clone(), a.version.clone())).collect();
+        let mut report = UpgradeReport { target_version: target.clone(), ..Default::default() };
+
+        loop {
+            let batch = self.next_batch(&target, policy);
+            if batch.is_empty() {
+                break;
+            }
+            report.batches += 1;
+            info!(batch = report.batches, agents = %batch.join(", "), "🔄 Upgrading batch");
+
+            for id in &batch {
+                self.set_state(id, AgentState::Draining);
+                let outcome = async {
+                    control.drain(id).await?;
+                    self.set_state(id, AgentState::Restarting);
+                    control.restart(id, &target).await?;
+                    control.handshake(id).await
+                }
+                .await;
+
+                match outcome {
+                    Ok(reported) if reported == target => {
+                        if let Some(agent) = self.agents.get_mut(id) {
+                            agent.version = reported;
+                            agent.state = AgentState::Active;
+                        }
+                        report.upgraded.push(id.clone());
+                    }
+                    Ok(reported) => {
+                        warn!(agent = %id, %reported, "⚠️  Handshake mismatch");
+                        self.set_state(id, AgentState::Failed);
+                        report.failed.push(id.clone());
+                    }
+                    Err(e) => {
+                        error!(agent = %id, error = %e, "❌ Upgrade failed");
+                        self.set_state(id, AgentState::Failed);
+                        report.failed.push(id.clone());
+                    }
+                }
+            }
+
+            let attempted = report.upgraded.len() + report.failed.len();
+            let failure_rate = report.failed.len() as f64 / attempted as f64;
+            if failure_rate > policy.max_failure_rate {
+                error!(
+                    failure_rate = %format!("{:.0}%", failure_rate * 100.0),
+                    "⏪ Failure rate exceeded threshold, rolling back"
+                );
+                self.roll_back(control, &previous, &mut report).await;
+                report.rolled_back = true;
+                return Ok(report);
+            }
+        }
+
+        info!(agents = report.upgraded.len(), version = %target, "✅ Rolling upgrade complete");
+        Ok(report)
+    }
+
+    /// Restore the previous version of every agent the upgrade touched. As when upgrading, an
+    /// agent only counts as restored once it reports that version.
+    async fn roll_back<C: AgentControl>(
+        &mut self,
+        control: &C,
+        previous: &HashMap<String, String>,
+        report: &mut UpgradeReport,
+    ) {
+        let touched: Vec<String> = report.upgraded.iter().chain(&report.failed).cloned().collect();
+        for id in touched {
+            let Some(version) = previous.get(&id) else { continue };
+            let restored = async {
+                control.drain(&id).await?;
+                control.restart(&id, version).await?;
+                control.handshake(&id).await
+            }
+            .await;
+
+            let Some(agent) = self.agents.get_mut(&id) else { continue };
+            match restored {
+                Ok(reported) if reported == *version => {
+                    agent.version = reported;
+                    agent.state = AgentState::Active;
+                    continue;
+                }
+                Ok(reported) => {
+                    warn!(agent = %id, %reported, expected = %version, "⚠️  Roll back mismatch")
+                }
+                Err(e) => error!(agent = %id, error = %e, "❌ Roll back failed"),
+            }
+            agent.state = AgentState::Failed;
+            report.rollback_failed.push(id);
+        }
+    }
+
+    fn set_state(&mut self, id: &str, state: AgentState) {
+        if let Some(agent) = self.agents.get_mut(id) {
+            agent.state = state;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::sync::Mutex;
+
+    struct MockControl {
+        broken: HashSet<String>,
+        /// Agents that keep running 2.0 when restarted into anything else
+        stuck: HashSet<String>,
+        versions: Mutex<HashMap<String, String>>,
+    }
+
+    impl MockControl {
+        fn new(broken: &[&str]) -> Self {
+            Self {
+                broken: broken.iter().map(|s| s.to_string()).collect(),
+                stuck: HashSet::new(),
+                versions: Mutex::new(HashMap::new()),
+            }
+        }
+
+        fn with_stuck(mut self, stuck: &[&str]) -> Self {
+            self.stuck = stuck.iter().map(|s| s.to_string()).collect();
+            self
+        }
+    }
+
+    impl AgentControl for MockControl {
+        async fn drain(&self, _agent_id: &str) -> Result<()> {
+            Ok(())
+        }
+
+        async fn restart(&self, agent_id: &str, version: &str) -> Result<()> {
+            let version = if self.stuck.contains(agent_id) { "2.0" } else { version };
+            self.versions.lock().unwrap().insert(agent_id.to_string(), version.to_string());
+            Ok(())
+        }
+
+        async fn handshake(&self, agent_id: &str) -> Result<String> {
+            let version = self.versions.lock().unwrap().get(agent_id).cloned().unwrap();
+            if self.broken.contains(agent_id) && version == "2.0" {
+                anyhow::bail!("agent did not reconnect");
+            }
+            Ok(version)
+        }
+    }
+
+    fn fleet() -> FleetCoordinator {
+        let mut fleet = FleetCoordinator::new();
+        for id in ["a1", "a2", "a3", "a4"] {
+            fleet.register_agent(id, "1.0", 4);
+        }
+        fleet.publish_version("2.0");
+        fleet
+    }
+
+    #[tokio::test]
+    async fn test_rolling_upgrade_keeps_capacity() {
+        let mut fleet = fleet();
+        let policy = RollingUpgradePolicy::default();
+        let report = fleet.rolling_upgrade(&MockControl::new(&[]), &policy).await.unwrap();
+
+        assert_eq!(report.upgraded.len(), 4);
+        assert_eq!(report.batches, 4);
+        assert!(!report.rolled_back);
+        assert!(fleet.agents().iter().all(|a| a.version == "2.0"));
+    }
+
+    #[tokio::test]
+    async fn test_rolling_upgrade_rolls_back_on_failures() {
+        let mut fleet = fleet();
+        let policy = RollingUpgradePolicy::default();
+        let report = fleet.rolling_upgrade(&MockControl::new(&["a1"]), &policy).await.unwrap();
+
+        assert!(report.rolled_back);
+        assert!(report.rollback_failed.is_empty());
+        assert!(fleet.agents().iter().all(|a| a.version == "1.0"));
+    }
+
+    #[tokio::test]
+    async fn test_roll_back_checks_the_restored_version() {
+        let mut fleet = fleet();
+        let policy = RollingUpgradePolicy::default();
+        let control = MockControl::new(&["a2"]).with_stuck(&["a1"]);
+        let report = fleet.rolling_upgrade(&control, &policy).await.unwrap();
+
+        assert!(report.rolled_back);
+        assert_eq!(report.upgraded, ["a1"]);
+        assert_eq!(report.rollback_failed, ["a1"]);
+        let a1 = fleet.agents()[0];
+        assert_eq!((a1.version.as_str(), a1.state), ("2.0", AgentState::Failed));
+        assert!(fleet.agents()[1..]
+            .iter()
+            .all(|a| a.version == "1.0" && a.state == AgentState::Active));
+    }
+
+    /// Serve the control API for one agent, holding its version until it restarts into another
+    async fn spawn_agent(version: &str, token: &'static str) -> AgentEndpoint {
+        use axum::http::{HeaderMap, StatusCode};
+        use axum::routing::{get, post};
+        use axum::Json;
+        use std::sync::Arc;
+
+        let version = Arc::new(Mutex::new(version.to_string()));
+        let authorized = move |headers: &HeaderMap| {
+            let expected = format!("Bearer {}", token);
+            if headers.get("authorization").is_some_and(|value| value == expected.as_str()) {
+                Ok(())
+            } else {
+                Err(StatusCode::UNAUTHORIZED)
+            }
+        };
+        let (current, restarted) = (version.clone(), version);
+        let app = axum::Router::new()
+            .route(
+                "/control/drain",
+                post(move |headers: HeaderMap| async move { authorized(&headers) }),
+            )
+            .route(
+                "/control/restart",
+                post(move |headers: HeaderMap, Json(body): Json<AgentVersion>| async move {
+                    authorized(&headers)?;
+                    *restarted.lock().unwrap() = body.version;
+                    Ok::<_, StatusCode>(())
+                }),
+            )
+            .route(
+                "/control/version",
+                get(move |headers: HeaderMap| async move {
+                    authorized(&headers)?;
+                    let version = current.lock().unwrap().clone();
+                    Ok::<_, StatusCode>(Json(AgentVersion { version }))
+                }),
+            );
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/agent", listener.local_addr().unwrap());
+        let app = axum::Router::new().nest("/agent", app);
+        tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(app.into_make_service()));
+        AgentEndpoint { id: String::new(), url, capacity: 4 }
+    }
+
+    #[tokio::test]
+    async fn test_http_control_upgrades_agents() {
+        let mut endpoints = Vec::new();
+        for id in ["a1", "a2", "a3", "a4"] {
+            endpoints
+                .push(AgentEndpoint { id: id.to_string(), ..spawn_agent("1.0", "secret").await });
+        }
+
+        let control = HttpAgentControl::new(&endpoints).unwrap().with_token(Some("secret".into()));
+        assert_eq!(control.handshake("a1").await.unwrap(), "1.0");
+        assert!(control.handshake("a5").await.is_err());
+
+        let mut fleet = fleet();
+        let report =
+            fleet.rolling_upgrade(&control, &RollingUpgradePolicy::default()).await.unwrap();
+        assert_eq!(report.upgraded.len(), 4);
+        assert!(fleet.agents().iter().all(|a| a.version == "2.0"));
+        assert_eq!(control.handshake("a3").await.unwrap(), "2.0");
+
+        let unauthorized =
+            HttpAgentControl::new(&endpoints).unwrap().with_handshake_timeout(Duration::ZERO);
+        assert!(unauthorized.drain("a1").await.is_err());
+        assert!(unauthorized.handshake("a1").await.is_err());
+    }
+}
@@ -0,0 +1,327 @@
+//! Task dependency graph of a workflow
+//!
+//! Tasks name the tasks they need in `depends_on` and `inputs_from`. In a sequential workflow each task also
+//! depends on the one listed before it, which is the order it runs in anyway. The graph renders
+//! as Graphviz DOT or Mermaid with the critical path, the longest chain of dependent tasks,
+//! highlighted.
+//!
+//! With measured durations, [`TaskGraph::critical_path_report`] says which tasks gate the wall
+//! time of a run and where parallelizing or caching would shorten it most.
+
+use crate::MultiLanguageWorkflow;
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// Share of the critical path above which a task is worth caching
+const CACHE_SHARE: f64 = 0.3;
+
+#[derive(Debug, Clone)]
+pub struct TaskGraph {
+    pub name: String,
+    /// Task names, in workflow order
+    pub tasks: Vec<String>,
+    pub languages: Vec<String>,
+    /// Indexes of the tasks each task waits for
+    pub dependencies: Vec<Vec<usize>>,
+    /// The dependencies declared in `depends_on` and `inputs_from`, without those of
+    /// sequential order
+    declared: Vec<Vec<usize>>,
+}
+
+/// What gated the wall time of a run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CriticalPathReport {
+    /// Names and durations of the tasks on the critical path, first to last
+    pub path: Vec<(String, u128)>,
+    /// Wall time the dependencies allow at best, the length of the critical path
+    pub critical_ms: u128,
+    /// Time of all tasks added up, what the run takes without any parallelism
+    pub total_ms: u128,
+    pub suggestions: Vec<String>,
+}
+
+impl TaskGraph {
+    /// Fails on dependencies on unknown tasks, inputs from tasks without outputs and on
+    /// cycles.
+    pub fn new(workflow: &MultiLanguageWorkflow) -> Result<Self> {
+        let tasks: Vec<String> = workflow.tasks.iter().map(|task| task.name()).collect();
+        let mut dependencies = Vec::with_capacity(tasks.len());
+        let mut declared = Vec::with_capacity(tasks.len());
+        for (index, task) in workflow.tasks.iter().enumerate() {
+            let mut needs = Vec::new();
+            for dependency in task.dependencies() {
+                let inputs = task.inputs_from.contains(dependency);
+                let found = tasks.iter().position(|name| name == dependency);
+                // Tasks left out by a subset ran before, so there is nothing to wait for
+                let outputs = match found {
+                    Some(found) => Some(&workflow.tasks[found].outputs),
+                    None => workflow.upstream_outputs.get(dependency).filter(|_| inputs),
+                };
+                let Some(outputs) = outputs else {
+                    bail!("task '{}' depends on unknown task '{}'", tasks[index], dependency);
+                };
+                if inputs && outputs.is_empty() {
+                    bail!(
+                        "task '{}' takes inputs from '{}', which declares no outputs",
+                        tasks[index],
+                        dependency
+                    );
+                }
+                if let Some(found) = found.filter(|found| !needs.contains(found)) {
+                    needs.push(found);
+                }
+            }
+            declared.push(needs.clone());
+            if !workflow.concurrent && index > 0 && !needs.contains(&(index - 1)) {
+                needs.insert(0, index - 1);
+            }
+            dependencies.push(needs);
+        }
+        let graph = Self {
+            name: workflow.name.clone(),
+            tasks,
+            languages: workflow.tasks.iter().map(|task| task.language.clone()).collect(),
+            dependencies,
+            declared,
+        };
+        if graph.order().len() < graph.tasks.len() {
+            bail!("tasks of '{}' depend on each other in a cycle", graph.name);
+        }
+        Ok(graph)
+    }
+
+    /// Task indexes with every task after its dependencies, otherwise in workflow order. Tasks
+    /// on a cycle are left out.
+    pub fn order(&self) -> Vec<usize> {
+        let mut order = Vec::with_capacity(self.tasks.len());
+        let mut placed = vec![false; self.tasks.len()];
+        loop {
+            let next = (0..self.tasks.len())
+                .find(|&i| !placed[i] && self.dependencies[i].iter().all(|&d| placed[d]));
+            let Some(next) = next else { return order };
+            placed[next] = true;
+            order.push(next);
+        }
+    }
+
+    /// Longest chain of dependent tasks, weighted by `durations` (one per task), from first
+    /// to last. Its total is the shortest wall time the workflow can take.
+    pub fn critical_path(&self, durations: &[u128]) -> Vec<usize> {
+        longest_chain(&self.order(), &self.dependencies, durations)
+    }
+
+    /// The critical path of a run whose tasks took `durations` (milliseconds, one per task),
+    /// with suggestions for shortening it.
+    pub fn critical_path_report(&self, durations: &[u128]) -> CriticalPathReport {
+        let duration = |task: usize| durations.get(task).copied().unwrap_or(0);
+        let path = self.critical_path(durations);
+        let critical_ms: u128 = path.iter().map(|&task| duration(task)).sum();
+        let mut suggestions = Vec::new();
+
+        // Tasks of a sequential workflow wait for each other whether they need to or not
+        if self.declared != self.dependencies {
+            let unchained = longest_chain(&self.order(), &self.declared, durations);
+            let unchained_ms: u128 = unchained.iter().map(|&task| duration(task)).sum();
+            if unchained_ms < critical_ms {
+                suggestions.push(format!(
+                    "run the workflow concurrently: with only the declared dependencies, \
+                     wall time drops from {}ms to {}ms",
+                    critical_ms, unchained_ms
+                ));
+            }
+        }
+
+        let mut heavy: Vec<usize> = path
+            .iter()
+            .copied()
+            .filter(|&task| {
+                critical_ms > 0 && duration(task) as f64 / critical_ms as f64 >= CACHE_SHARE
+            })
+            .collect();
+        heavy.sort_by_key(|&task| std::cmp::Reverse(duration(task)));
+        for task in heavy {
+            let share = duration(task) as f64 / critical_ms as f64 * 100.0;
+            let waiting: Vec<&str> = (0..self.tasks.len())
+                .filter(|&other| self.dependencies[other].contains(&task))
+                .map(|other| self.tasks[other].as_str())
+                .collect();
+            let mut suggestion = format!(
+                "cache or split {}: it takes {}ms, {:.0}% of the critical path",
+                self.tasks[task],
+                duration(task),
+                share
+            );
+            if !waiting.is_empty() {
+                let _ = write!(suggestion, ", and {} wait for it", waiting.join(", "));
+            }
+            suggestions.push(suggestion);
+        }
+
+        CriticalPathReport {
+            path: path.iter().map(|&task| (self.tasks[task].clone(), duration(task))).collect(),
+            critical_ms,
+            total_ms: durations.iter().sum(),
+            suggestions,
+        }
+    }
+
+    /// Graphviz DOT, with the tasks and edges of `highlight` in red
+    pub fn to_dot(&self, highlight: &[usize]) -> String {
+        let mut dot =
+            format!("digraph \"{}\" {{\n  rankdir=LR;\n  node [shape=box];\n", escape(&self.name));
+        for (i, task) in self.tasks.iter().enumerate() {
+            let style = if highlight.contains(&i) { ", color=red, penwidth=2" } else { "" };
+            let _ = writeln!(
+                dot,
+                "  t{} [label=\"{}\\n{}\"{}];",
+                i,
+                escape(task),
+                escape(&self.languages[i]),
+                style
+            );
+        }
+        for (to, from) in self.edges() {
+            let style = if on_path(highlight, from, to) { " [color=red, penwidth=2]" } else { "" };
+            let _ = writeln!(dot, "  t{} -> t{}{};", from, to, style);
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Mermaid flowchart, with the tasks and edges of `highlight` in the `critical` class
+    pub fn to_mermaid(&self, highlight: &[usize]) -> String {
+        let mut mermaid = "flowchart LR\n".to_string();
+        for (i, task) in self.tasks.iter().enumerate() {
+            let _ = writeln!(
+                mermaid,
+                "  t{}[\"{}<br/>{}\"]",
+                i,
+                task.replace('"', "#quot;"),
+                self.languages[i].replace('"', "#quot;")
+            );
+        }
+        let mut critical_edges = Vec::new();
+        for (n, (to, from)) in self.edges().enumerate() {
+            let _ = writeln!(mermaid, "  t{} --> t{}", from, to);
+            if on_path(highlight, from, to) {
+                critical_edges.push(n.to_string());
+            }
+        }
+        if !highlight.is_empty() {
+            mermaid.push_str("  classDef critical stroke:#d00,stroke-width:3px\n");
+            let nodes: Vec<String> = highlight.iter().map(|i| format!("t{}", i)).collect();
+            let _ = writeln!(mermaid, "  class {} critical", nodes.join(","));
+        }
+        if !critical_edges.is_empty() {
+            let _ = writeln!(
+                mermaid,
+                "  linkStyle {} stroke:#d00,stroke-width:3px",
+                critical_edges.join(",")
+            );
+        }
+        mermaid
+    }
+
+    /// `(task, dependency)` pairs
+    fn edges(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.dependencies
+            .iter()
+            .enumerate()
+            .flat_map(|(task, needs)| needs.iter().map(move |&need| (task, need)))
+    }
+}
+
+/// Longest chain through `dependencies`, visiting tasks in `order`
+fn longest_chain(order: &[usize], dependencies: &[Vec<usize>], durations: &[u128]) -> Vec<usize> {
+    // Longest finish time of each task and the dependency it was reached through
+    let mut finish = vec![0u128; dependencies.len()];
+    let mut through: Vec<Option<usize>> = vec![None; dependencies.len()];
+    for &task in order {
+        let gate = dependencies[task].iter().copied().max_by_key(|&d| finish[d]);
+        finish[task] = gate.map_or(0, |d| finish[d]) + durations.get(task).copied().unwrap_or(0);
+        through[task] = gate;
+    }
+    let Some(mut last) = (0..dependencies.len()).max_by_key(|&i| (finish[i], usize::MAX - i))
+    else {
+        return Vec::new();
+    };
+    let mut path = vec![last];
+    while let Some(previous) = through[last] {
+        path.push(previous);
+        last = previous;
+    }
+    path.reverse();
+    path
+}
+
+fn on_path(path: &[usize], from: usize, to: usize) -> bool {
+    path.windows(2).any(|pair| pair == [from, to])
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workflow(yaml: &str) -> MultiLanguageWorkflow {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_orders_tasks_and_finds_the_critical_path() {
+        let flow = workflow(
+            r#"
+name: release
+concurrent: true
+tasks:
+  - { name: lib, language: Rust, command: cargo, args: [build] }
+  - { name: bindings, language: Python, command: maturin, args: [build], depends_on: [lib] }
+  - { name: docs, language: Node.js, command: npm, args: [run, docs] }
+  - { name: package, language: Python, command: twine, args: [check], depends_on: [bindings, docs] }
+"#,
+        );
+        let graph = TaskGraph::new(&flow).unwrap();
+        assert_eq!(graph.order(), [0, 1, 2, 3]);
+        assert_eq!(graph.critical_path(&[30, 20, 10, 5]), [0, 1, 3]);
+        assert_eq!(graph.critical_path(&[1, 1, 10, 5]), [2, 3]);
+
+        let dot = graph.to_dot(&[0, 1, 3]);
+        assert!(dot.contains("t0 -> t1 [color=red, penwidth=2];"));
+        assert!(dot.contains("t2 -> t3;"));
+        let mermaid = graph.to_mermaid(&[0, 1, 3]);
+        assert!(mermaid.contains("t1 --> t3"));
+        assert!(mermaid.contains("class t0,t1,t3 critical"));
+
+        // Sequential workflows chain their tasks
+        let mut sequential = flow;
+        sequential.concurrent = false;
+        sequential.tasks[3].depends_on.clear();
+        let graph = TaskGraph::new(&sequential).unwrap();
+        assert_eq!(graph.dependencies[2], [1]);
+        assert_eq!(graph.critical_path(&[1, 1, 1, 1]), [0, 1, 2, 3]);
+
+        // Only lib -> bindings is declared, so docs and package need not wait
+        let report = graph.critical_path_report(&[400, 300, 200, 100]);
+        assert_eq!(report.critical_ms, 1000);
+        assert_eq!(report.total_ms, 1000);
+        assert_eq!(report.path[0], ("lib".to_string(), 400));
+        assert!(report.suggestions[0].contains("from 1000ms to 700ms"));
+        assert!(report.suggestions[1].starts_with("cache or split lib: it takes 400ms, 40%"));
+        assert!(report.suggestions[1].ends_with("bindings wait for it"));
+        assert_eq!(report.suggestions.len(), 3);
+
+        sequential.tasks[0].depends_on = vec!["package".to_string()];
+        assert!(TaskGraph::new(&sequential).is_err());
+        sequential.tasks[0].depends_on = vec!["typo".to_string()];
+        assert!(TaskGraph::new(&sequential).is_err());
+        sequential.tasks[0].depends_on.clear();
+        sequential.tasks[3].inputs_from = vec!["docs".to_string()];
+        assert!(TaskGraph::new(&sequential).is_err());
+        sequential.tasks[2].outputs = vec!["site/**".to_string()];
+        assert_eq!(TaskGraph::new(&sequential).unwrap().declared[3], [2]);
+    }
+}
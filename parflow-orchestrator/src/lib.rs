@@ -1,24 +1,153 @@
-use colored::*;
+use parflow_core::cancel::{self, CancellationToken, Partial};
+use parflow_core::languages;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::mpsc::UnboundedSender;
+
+use tracing::{info, instrument, warn, Instrument};
+
+pub mod affinity;
+pub mod backend;
+pub mod fleet;
+pub mod graph;
+mod metrics;
+pub mod policy;
+pub mod queue;
+pub mod schedule;
+pub mod scheduler;
+pub mod snapshot;
+pub mod staging;
+pub mod watch;
+
+pub use affinity::{AffinityHint, AffinityModel, CacheHitReport, RunManifest};
+pub use backend::{ContainerBackend, ExecutionBackend, MockBackend};
+pub use fleet::{
+    AgentControl, AgentEndpoint, FleetCoordinator, HttpAgentControl, RollingUpgradePolicy,
+    UpgradeReport,
+};
+pub use graph::{CriticalPathReport, TaskGraph};
+pub use policy::{PolicyBackend, TaskPolicy};
+pub use queue::{JobQueue, QueuedWorkflow, TaskState};
+pub use schedule::{CronScheduler, OverlapPolicy, Schedule, ScheduleSpec, ScheduleStatus};
+pub use scheduler::{QueueMetrics, Resources, WorkStealingScheduler};
+pub use snapshot::{ChangeTracker, FileChanges, WorkspaceSnapshot};
+pub use watch::{WatchConfig, WatchRule};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct LanguageTask {
+    /// Name to refer to the task by, e.g. in watch rules; defaults to `<language>_task`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
     pub language: String,
     pub command: String,
     pub args: Vec<String>,
     pub working_dir: Option<String>,
     pub timeout_seconds: Option<u64>,
+    /// Names of the tasks that must succeed before this one starts
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+    /// Container image to run the task in, see [`backend`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    /// CPU and memory limits of the task's container
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resources: Option<Resources>,
+    /// Files the task must create or modify, as globs relative to the workspace, see
+    /// [`snapshot`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outputs: Vec<String>,
+    /// Tasks whose `outputs` are copied into this task's working directory before it starts,
+    /// see [`staging`]. This task waits for them as for `depends_on`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inputs_from: Vec<String>,
+    /// Environment variables of the task
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// Network and filesystem access of the task, see [`policy`]
+    #[serde(default, skip_serializing_if = "TaskPolicy::is_unrestricted")]
+    pub policy: TaskPolicy,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MultiLanguageWorkflow {
     pub name: String,
     pub tasks: Vec<LanguageTask>,
     pub concurrent: bool,
+    /// What `parflow watch` re-runs when files change
+    #[serde(default, skip_serializing_if = "WatchConfig::is_empty")]
+    pub watch: WatchConfig,
+    /// Report the files each task writes, see [`snapshot`]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub track_changes: bool,
+    /// Output patterns of tasks left out by [`subset`](Self::subset) that the remaining tasks
+    /// take inputs from, by task name; their outputs are staged as an earlier run left them
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub upstream_outputs: BTreeMap<String, Vec<String>>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl LanguageTask {
+    pub fn name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| format!("{}_task", self.language))
+    }
+
+    /// The tasks this one waits for: those of `depends_on`, then those of `inputs_from`
+    pub fn dependencies(&self) -> impl Iterator<Item = &String> {
+        self.depends_on.iter().chain(&self.inputs_from)
+    }
+}
+
+impl MultiLanguageWorkflow {
+    /// Read a workflow file: YAML for `.yaml` and `.yml`, JSON otherwise.
+    pub fn load(path: &std::path::Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+        let yaml = matches!(path.extension().and_then(|e| e.to_str()), Some("yaml" | "yml"));
+        let workflow = if yaml {
+            serde_yaml::from_str(&text).map_err(anyhow::Error::from)
+        } else {
+            serde_json::from_str(&text).map_err(anyhow::Error::from)
+        };
+        workflow.map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))
+    }
+
+    /// The same workflow with only the tasks at `indexes`, and only dependencies among them.
+    /// Inputs from tasks left out are still staged, from what they last wrote, see
+    /// [`upstream_outputs`](Self::upstream_outputs).
+    pub fn subset(&self, indexes: &[usize]) -> Self {
+        let mut tasks: Vec<LanguageTask> =
+            indexes.iter().filter_map(|&i| self.tasks.get(i).cloned()).collect();
+        let names: Vec<String> = tasks.iter().map(LanguageTask::name).collect();
+        let mut upstream_outputs = BTreeMap::new();
+        for task in &mut tasks {
+            task.depends_on.retain(|dependency| names.contains(dependency));
+            for upstream in task.inputs_from.iter().filter(|name| !names.contains(name)) {
+                let outputs = self.outputs_of(upstream).unwrap_or_default();
+                upstream_outputs.insert(upstream.clone(), outputs);
+            }
+        }
+        Self {
+            name: self.name.clone(),
+            tasks,
+            concurrent: self.concurrent,
+            watch: WatchConfig::default(),
+            track_changes: self.track_changes,
+            upstream_outputs,
+        }
+    }
+
+    /// Output patterns of the task named `name`, in the workflow or left out of it
+    pub fn outputs_of(&self, name: &str) -> Option<Vec<String>> {
+        match self.tasks.iter().find(|task| task.name() == name) {
+            Some(task) => Some(task.outputs.clone()),
+            None => self.upstream_outputs.get(name).cloned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExecutionResult {
     pub task_name: String,
     pub language: String,
@@ -26,150 +155,438 @@ pub struct ExecutionResult {
     pub output: String,
     pub execution_time: u128,
     pub exit_code: Option<i32>,
+    /// Files the task wrote, when the workflow tracks changes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changes: Option<FileChanges>,
+}
+
+/// Progress notifications emitted while a workflow runs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum WorkflowEvent {
+    TaskStarted { index: usize, language: String },
+    TaskFinished { index: usize, result: ExecutionResult },
 }
 
 pub struct MultiLanguageOrchestrator;
 
 impl MultiLanguageOrchestrator {
     pub async fn execute_workflow(workflow: MultiLanguageWorkflow) -> Vec<ExecutionResult> {
-        println!(
-            "{} {}",
-            "🚀 Executing Multi-Language Workflow:".bright_green().bold(),
-            workflow.name.bright_cyan()
-        );
+        let (events, _) = tokio::sync::mpsc::unbounded_channel();
+        Self::execute_workflow_with_events(workflow, events).await
+    }
+
+    /// Same as `execute_workflow`, additionally reporting per-task progress on `events`.
+    /// Send failures are ignored so a listener that goes away does not stop the workflow.
+    pub async fn execute_workflow_with_events(
+        workflow: MultiLanguageWorkflow,
+        events: UnboundedSender<WorkflowEvent>,
+    ) -> Vec<ExecutionResult> {
+        Self::execute_workflow_cancellable(workflow, events, CancellationToken::new()).await.value
+    }
+
+    /// Same as `execute_workflow_with_events`, stopping once `cancel` fires. Running tasks are
+    /// abandoned and reported as failed with "cancelled" output; tasks not yet started are
+    /// skipped, so the results hold only what ran.
+    pub async fn execute_workflow_cancellable(
+        workflow: MultiLanguageWorkflow,
+        events: UnboundedSender<WorkflowEvent>,
+        cancel: CancellationToken,
+    ) -> Partial<Vec<ExecutionResult>> {
+        let backend = backend::default_backend();
+        Self::execute_workflow_on(workflow, events, cancel, backend).await
+    }
+
+    /// Same as `execute_workflow_cancellable`, running the tasks on `backend`.
+    #[instrument(name = "workflow", skip_all, fields(workflow = %workflow.name))]
+    pub async fn execute_workflow_on(
+        workflow: MultiLanguageWorkflow,
+        events: UnboundedSender<WorkflowEvent>,
+        cancel: CancellationToken,
+        backend: Arc<dyn ExecutionBackend>,
+    ) -> Partial<Vec<ExecutionResult>> {
+        info!(tasks = workflow.tasks.len(), "🚀 Executing multi-language workflow");
+        let queued = workflow.tasks.len();
+        metrics::queued(queued as i64);
+        let started = Instant::now();
 
         let mut results = Vec::new();
+        // Invalid dependencies are reported by `parflow graph`; here they are just ignored
+        let graph = TaskGraph::new(&workflow)
+            .inspect_err(|e| warn!(error = %e, "⚠️  Ignoring task dependencies"))
+            .ok();
+        let order = graph.as_ref().map_or_else(|| (0..queued).collect(), TaskGraph::order);
+        // Measured time of each task, for the critical path
+        let mut durations = vec![0u128; queued];
+        let names: Vec<String> = workflow.tasks.iter().map(LanguageTask::name).collect();
+        let needs: Vec<Vec<usize>> = workflow
+            .tasks
+            .iter()
+            .map(|task| {
+                task.dependencies()
+                    .filter_map(|dependency| names.iter().position(|name| name == dependency))
+                    .collect()
+            })
+            .collect();
+        // The output patterns of the tasks each task takes inputs from
+        let inputs: Vec<Vec<(String, Vec<String>)>> = workflow
+            .tasks
+            .iter()
+            .map(|task| {
+                task.inputs_from
+                    .iter()
+                    .map(|name| (name.clone(), workflow.outputs_of(name).unwrap_or_default()))
+                    .collect()
+            })
+            .collect();
+        let outputs: Vec<String> =
+            workflow.tasks.iter().flat_map(|task| task.outputs.clone()).collect();
+        let tracker = (workflow.track_changes || !outputs.is_empty())
+            .then(|| Arc::new(ChangeTracker::new(".", outputs)));
 
         if workflow.concurrent {
-            // Execute all tasks concurrently (mock implementation)
+            // Execute all tasks concurrently, each once its dependencies have finished
+            let (done, finished): (Vec<_>, Vec<_>) =
+                (0..queued).map(|_| tokio::sync::watch::channel(None::<bool>)).unzip();
             let mut handles = Vec::new();
 
-            for task in workflow.tasks {
-                let handle = tokio::spawn(async move { Self::execute_task_mock(task).await });
+            for ((index, task), done) in workflow.tasks.into_iter().enumerate().zip(done) {
+                let events = events.clone();
+                let cancel = cancel.clone();
+                let backend = backend.clone();
+                let tracker = tracker.clone();
+                let inputs = inputs[index].clone();
+                // Without a valid graph, tasks wait only for earlier ones, as they would run in
+                // file order sequentially; waiting on later ones could wait on a cycle forever
+                let needs: Vec<_> = needs[index]
+                    .iter()
+                    .map(|&d| {
+                        let finished = (graph.is_some() || d < index).then(|| finished[d].clone());
+                        (names[d].clone(), finished)
+                    })
+                    .collect();
+                let handle = tokio::spawn(
+                    async move {
+                        let mut failed = None;
+                        for (dependency, finished) in needs {
+                            let succeeded = match finished {
+                                Some(mut finished) => finished
+                                    .wait_for(Option::is_some)
+                                    .await
+                                    .is_ok_and(|success| *success == Some(true)),
+                                None => false,
+                            };
+                            if !succeeded {
+                                failed = Some(dependency);
+                                break;
+                            }
+                        }
+                        let result = match failed {
+                            Some(dependency) => Self::skip_task(index, &task, &dependency, &events),
+                            None => {
+                                Self::execute_task_reporting(
+                                    index,
+                                    task,
+                                    inputs,
+                                    &events,
+                                    &cancel,
+                                    &backend,
+                                    tracker.as_ref(),
+                                )
+                                .await
+                            }
+                        };
+                        let _ = done.send(Some(result.success));
+                        result
+                    }
+                    .in_current_span(),
+                );
                 handles.push(handle);
             }
 
-            for handle in handles {
+            for (index, handle) in handles.into_iter().enumerate() {
                 if let Ok(result) = handle.await {
+                    durations[index] = result.execution_time;
                     results.push(result);
                 }
             }
         } else {
-            // Execute tasks sequentially (mock implementation)
-            for task in workflow.tasks {
-                let result = Self::execute_task_mock(task).await;
+            // Execute tasks sequentially
+            let mut succeeded = vec![false; queued];
+            let mut tasks: Vec<Option<LanguageTask>> =
+                workflow.tasks.into_iter().map(Some).collect();
+            for (position, index) in order.into_iter().enumerate() {
+                if cancel.is_cancelled() {
+                    metrics::queued(position as i64 - queued as i64);
+                    break;
+                }
+                let task = tasks[index].take().expect("tasks are ordered once");
+                let result = match needs[index].iter().find(|&&d| !succeeded[d]) {
+                    Some(&d) => Self::skip_task(index, &task, &names[d], &events),
+                    None => {
+                        Self::execute_task_reporting(
+                            index,
+                            task,
+                            inputs[index].clone(),
+                            &events,
+                            &cancel,
+                            &backend,
+                            tracker.as_ref(),
+                        )
+                        .await
+                    }
+                };
+                succeeded[index] = result.success;
+                durations[index] = result.execution_time;
                 results.push(result);
             }
         }
 
-        Self::generate_workflow_insights(&results);
-        results
+        if cancel.is_cancelled() {
+            warn!(finished = results.len(), "⏹️  Workflow cancelled, reporting finished tasks");
+        }
+        Self::generate_workflow_insights(&results, graph.as_ref(), &durations);
+        metrics::workflow_finished(workflow.concurrent, cancel.is_cancelled(), started.elapsed());
+        Partial::checked(results, &cancel)
     }
 
-    async fn execute_task_mock(task: LanguageTask) -> ExecutionResult {
-        let language = task.language.clone(); // Clone for use in output
-        println!(
-            "{} {} {}",
-            "▶️  Executing".bright_blue(),
-            language.bright_yellow(),
-            "task".bright_blue()
-        );
+    /// Report `task` as failed without running it, since `dependency` did not succeed.
+    fn skip_task(
+        index: usize,
+        task: &LanguageTask,
+        dependency: &str,
+        events: &UnboundedSender<WorkflowEvent>,
+    ) -> ExecutionResult {
+        warn!(task = %task.name(), dependency, "⏭️  Skipping task, its dependency failed");
+        metrics::queued(-1);
+        let result = ExecutionResult {
+            task_name: task.name(),
+            language: task.language.clone(),
+            success: false,
+            output: format!("skipped: {} failed", dependency),
+            execution_time: 0,
+            exit_code: None,
+            changes: None,
+        };
+        let _ = events.send(WorkflowEvent::TaskFinished { index, result: result.clone() });
+        result
+    }
 
-        // Mock execution - simulate some work
-        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+    /// Run `task` after staging `inputs`, the tasks it takes inputs from with their output
+    /// patterns.
+    #[instrument(name = "task", skip_all, fields(index, language = %task.language))]
+    async fn execute_task_reporting(
+        index: usize,
+        task: LanguageTask,
+        inputs: Vec<(String, Vec<String>)>,
+        events: &UnboundedSender<WorkflowEvent>,
+        cancel: &CancellationToken,
+        backend: &Arc<dyn ExecutionBackend>,
+        tracker: Option<&Arc<ChangeTracker>>,
+    ) -> ExecutionResult {
+        let _ = events.send(WorkflowEvent::TaskStarted { index, language: task.language.clone() });
+        metrics::queued(-1);
+        let started = Instant::now();
+        let (language, task_name) = (task.language.clone(), task.name());
+        let outputs = task.outputs.clone();
+        // Simulated tasks write nothing, so their outputs would always be missing
+        let tracker = tracker.filter(|_| backend.runs(&task));
+        let staged = if inputs.is_empty() { Ok(task) } else { Self::stage(task, inputs).await };
+        let result = match staged {
+            Ok(task) => {
+                let before = match tracker {
+                    Some(tracker) => tracker.capture().await,
+                    None => None,
+                };
+                match cancel::run_until_cancelled(cancel, backend.execute(task)).await {
+                    Some(mut result) => {
+                        if let (Some(tracker), Some(before)) = (tracker, before) {
+                            if let Some(after) = tracker.capture().await {
+                                Self::record_changes(&mut result, before.diff(&after), &outputs);
+                            }
+                        }
+                        result
+                    }
+                    None => {
+                        warn!("⏹️  Task cancelled");
+                        ExecutionResult {
+                            task_name,
+                            language,
+                            success: false,
+                            output: "cancelled".to_string(),
+                            execution_time: started.elapsed().as_millis(),
+                            exit_code: None,
+                            changes: None,
+                        }
+                    }
+                }
+            }
+            Err(e) => {
+                warn!(error = %e, "📭 Cannot stage inputs");
+                ExecutionResult {
+                    task_name,
+                    language,
+                    success: false,
+                    output: format!("staging inputs failed: {:#}", e),
+                    execution_time: started.elapsed().as_millis(),
+                    exit_code: None,
+                    changes: None,
+                }
+            }
+        };
+        metrics::task_finished(&result.language, result.success, started.elapsed());
+        let _ = events.send(WorkflowEvent::TaskFinished { index, result: result.clone() });
+        result
+    }
 
-        // Create output before moving language
-        let output = format!("Mock output from {} task", language);
-        let task_name = format!("{}_task", language);
+    /// Copy the outputs of `inputs` into the working directory of `task`, see [`staging`].
+    async fn stage(
+        mut task: LanguageTask,
+        inputs: Vec<(String, Vec<String>)>,
+    ) -> anyhow::Result<LanguageTask> {
+        let staged = tokio::task::spawn_blocking(move || {
+            staging::stage_inputs(std::path::Path::new("."), &mut task, &inputs)
+                .map(|files| (task, files))
+        })
+        .await?;
+        let (task, files) = staged?;
+        info!(files, "📦 Staged inputs");
+        Ok(task)
+    }
 
-        ExecutionResult {
-            task_name,
-            language,
-            success: true,
-            output,
-            execution_time: 500, // milliseconds
-            exit_code: Some(0),
+    /// Attach `changes` to `result`, failing it when it succeeded without writing one of its
+    /// declared `outputs`.
+    fn record_changes(result: &mut ExecutionResult, changes: FileChanges, outputs: &[String]) {
+        info!(
+            created = changes.created.len(),
+            modified = changes.modified.len(),
+            deleted = changes.deleted.len(),
+            "📝 Workspace changes"
+        );
+        if result.success {
+            let failure = match changes.missing_outputs(outputs) {
+                Ok(missing) if missing.is_empty() => None,
+                Ok(missing) => Some(format!("wrote nothing matching {}", missing.join(", "))),
+                Err(e) => Some(e.to_string()),
+            };
+            if let Some(failure) = failure {
+                warn!(task = %result.task_name, failure = %failure, "📭 Declared outputs missing");
+                result.success = false;
+                result.output = if result.output.is_empty() {
+                    failure
+                } else {
+                    format!("{}\n{}", result.output, failure)
+                };
+            }
         }
+        result.changes = Some(changes);
     }
 
+    /// Build each project, named by a source file or manifest, with the build command of its
+    /// language plugin. Results are keyed by language; languages without a build step are
+    /// skipped.
     pub async fn compile_multiple_languages(
         projects: Vec<&str>,
     ) -> HashMap<String, ExecutionResult> {
-        println!("{}", "🔨 Concurrent Multi-Language Compilation".bright_magenta().bold());
-
-        let mut compilation_tasks = Vec::new();
-
-        for project in projects {
-            if project.ends_with(".rs") || project.contains("Cargo.toml") {
-                compilation_tasks.push(LanguageTask {
-                    language: "Rust".to_string(),
-                    command: "cargo".to_string(),
-                    args: vec!["build", "--release"].into_iter().map(String::from).collect(),
-                    working_dir: Some(".".to_string()),
-                    timeout_seconds: Some(300),
-                });
-            } else if project.ends_with(".py") {
-                compilation_tasks.push(LanguageTask {
-                    language: "Python".to_string(),
-                    command: "python".to_string(),
-                    args: vec!["-m", "py_compile", project].into_iter().map(String::from).collect(),
-                    working_dir: None,
-                    timeout_seconds: Some(30),
-                });
-            } else if project.contains("package.json") {
-                compilation_tasks.push(LanguageTask {
-                    language: "Node.js".to_string(),
-                    command: "npm".to_string(),
-                    args: vec!["run", "build"].into_iter().map(String::from).collect(),
-                    working_dir: Some(".".to_string()),
-                    timeout_seconds: Some(120),
-                });
-            }
-        }
+        Self::compile_multiple_languages_cancellable(projects, CancellationToken::new()).await.value
+    }
+
+    /// Same as `compile_multiple_languages`, stopping the builds once `cancel` fires.
+    pub async fn compile_multiple_languages_cancellable(
+        projects: Vec<&str>,
+        cancel: CancellationToken,
+    ) -> Partial<HashMap<String, ExecutionResult>> {
+        info!(projects = projects.len(), "🔨 Concurrent multi-language compilation");
+
+        let compilation_tasks = projects.into_iter().filter_map(Self::build_task).collect();
 
         let workflow = MultiLanguageWorkflow {
             name: "Multi-Language Build".to_string(),
             tasks: compilation_tasks,
             concurrent: true,
+            watch: WatchConfig::default(),
+            track_changes: false,
+            upstream_outputs: BTreeMap::new(),
         };
 
-        let results = Self::execute_workflow(workflow).await;
+        let (events, _) = tokio::sync::mpsc::unbounded_channel();
+        let results = Self::execute_workflow_cancellable(workflow, events, cancel).await;
 
-        let mut result_map = HashMap::new();
-        for result in results {
-            result_map.insert(result.language.clone(), result);
-        }
-
-        result_map
+        results.map(|results| {
+            let mut result_map = HashMap::new();
+            for result in results {
+                result_map.insert(result.language.clone(), result);
+            }
+            result_map
+        })
     }
 
-    fn generate_workflow_insights(results: &[ExecutionResult]) {
-        println!("\n{}", "📊 Workflow Insights".bright_cyan().bold());
-        println!("{}", "─".repeat(40).bright_cyan());
+    /// The task building `project`, a source file or manifest naming the project in its
+    /// directory, with the build command of its language plugin
+    fn build_task(project: &str) -> Option<LanguageTask> {
+        let Some(plugin) = languages::for_path(project).or_else(|| languages::for_marker(project))
+        else {
+            warn!(project, "⚠️  No language plugin for project, skipping");
+            return None;
+        };
+        let dir = match Path::new(project).parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let Some(mut command) = plugin.build_command(dir) else {
+            info!(project, language = plugin.name(), "⏭️  Nothing to compile");
+            return None;
+        };
+        let program = command.remove(0);
+        Some(LanguageTask {
+            name: None,
+            language: plugin.name().to_string(),
+            command: program,
+            args: command,
+            working_dir: Some(dir.display().to_string()),
+            timeout_seconds: Some(300),
+            depends_on: Vec::new(),
+            image: None,
+            resources: None,
+            outputs: Vec::new(),
+            inputs_from: Vec::new(),
+            env: BTreeMap::new(),
+            policy: TaskPolicy::default(),
+        })
+    }
 
+    /// Log a summary of a finished run. With its task graph and the time each task took, this
+    /// includes the critical path and how to shorten it.
+    fn generate_workflow_insights(
+        results: &[ExecutionResult],
+        graph: Option<&TaskGraph>,
+        durations: &[u128],
+    ) {
         let total_time: u128 = results.iter().map(|r| r.execution_time).sum();
         let successful_tasks: Vec<&ExecutionResult> =
             results.iter().filter(|r| r.success).collect();
         let failed_tasks: Vec<&ExecutionResult> = results.iter().filter(|r| !r.success).collect();
 
-        println!("✅ Successful tasks: {}", successful_tasks.len().to_string().bright_green());
-        println!("❌ Failed tasks: {}", failed_tasks.len().to_string().bright_red());
-        println!("⏱️  Total execution time: {}ms", total_time.to_string().bright_yellow());
+        info!(
+            successful = successful_tasks.len(),
+            failed = failed_tasks.len(),
+            total_ms = total_time as u64,
+            "📊 Workflow insights"
+        );
 
         // Find fastest and slowest tasks
         if let Some(fastest) = results.iter().min_by_key(|r| r.execution_time) {
-            println!(
-                "⚡ Fastest: {} ({}ms)",
-                fastest.language.bright_green(),
-                fastest.execution_time.to_string().bright_green()
+            info!(
+                language = %fastest.language,
+                ms = fastest.execution_time as u64,
+                "⚡ Fastest task"
             );
         }
 
         if let Some(slowest) = results.iter().max_by_key(|r| r.execution_time) {
-            println!(
-                "🐌 Slowest: {} ({}ms)",
-                slowest.language.bright_red(),
-                slowest.execution_time.to_string().bright_red()
+            info!(
+                language = %slowest.language,
+                ms = slowest.execution_time as u64,
+                "🐌 Slowest task"
             );
         }
 
@@ -181,15 +598,218 @@ impl MultiLanguageOrchestrator {
             entry.1 += 1;
         }
 
-        println!("\n{}", "🌐 Language Performance".bright_blue().bold());
         for (lang, (total_time, count)) in language_stats {
-            let avg_time = total_time / count as u128;
-            println!(
-                "   {}: avg {}ms ({} tasks)",
-                lang.bright_yellow(),
-                avg_time.to_string().bright_white(),
-                count.to_string().bright_white()
+            let avg_ms = (total_time / count as u128) as u64;
+            info!(language = lang, avg_ms, tasks = count, "🌐 Language performance");
+        }
+
+        if let Some(graph) = graph.filter(|graph| !graph.tasks.is_empty()) {
+            let report = graph.critical_path_report(durations);
+            let path: Vec<&str> = report.path.iter().map(|(task, _)| task.as_str()).collect();
+            info!(
+                path = %path.join(" → "),
+                critical_ms = report.critical_ms as u64,
+                total_ms = report.total_ms as u64,
+                "🧭 Critical path"
             );
+            for suggestion in &report.suggestions {
+                info!(suggestion = %suggestion, "💡 Speed-up");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn workflow(concurrent: bool) -> MultiLanguageWorkflow {
+        let task = |language: &str| LanguageTask {
+            name: None,
+            language: language.to_string(),
+            command: "true".to_string(),
+            args: vec![],
+            working_dir: None,
+            timeout_seconds: None,
+            depends_on: Vec::new(),
+            image: None,
+            resources: None,
+            outputs: Vec::new(),
+            inputs_from: Vec::new(),
+            env: BTreeMap::new(),
+            policy: TaskPolicy::default(),
+        };
+        MultiLanguageWorkflow {
+            name: "cancel".to_string(),
+            tasks: vec![task("rust"), task("python")],
+            concurrent,
+            watch: WatchConfig::default(),
+            track_changes: false,
+            upstream_outputs: BTreeMap::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancel_stops_workflow_with_partial_results() {
+        for concurrent in [true, false] {
+            let cancel = CancellationToken::new();
+            let token = cancel.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                token.cancel();
+            });
+            let (events, _) = tokio::sync::mpsc::unbounded_channel();
+
+            let results = MultiLanguageOrchestrator::execute_workflow_cancellable(
+                workflow(concurrent),
+                events,
+                cancel,
+            )
+            .await;
+
+            assert!(results.cancelled);
+            // Concurrent tasks all started; the sequential one never reached python
+            assert_eq!(results.value.len(), if concurrent { 2 } else { 1 });
+            assert!(results.value.iter().all(|r| !r.success && r.output == "cancelled"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_builds_use_the_language_plugins() {
+        let task = MultiLanguageOrchestrator::build_task("engine/Cargo.toml").unwrap();
+        assert_eq!(task.language, "rust");
+        assert_eq!((task.command.as_str(), task.args.join(" ")), ("cargo", "build".to_string()));
+        assert_eq!(task.working_dir.as_deref(), Some("engine"));
+        let task = MultiLanguageOrchestrator::build_task("main.go").unwrap();
+        assert_eq!((task.command.as_str(), task.working_dir.as_deref()), ("go", Some(".")));
+        // Python has no build step, and READMEs no language
+        assert!(MultiLanguageOrchestrator::build_task("app/main.py").is_none());
+        assert!(MultiLanguageOrchestrator::build_task("README.md").is_none());
+
+        let results = MultiLanguageOrchestrator::compile_multiple_languages(vec![
+            "Cargo.toml",
+            "web/app.ts",
+            "main.py",
+        ])
+        .await;
+        let mut built: Vec<&String> = results.keys().collect();
+        built.sort();
+        assert_eq!(built, ["rust", "typescript"]);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_tasks_wait_for_their_dependencies() {
+        let mut flow = workflow(true);
+        flow.tasks[0].depends_on = vec!["python_task".to_string()];
+        let (events, mut received) = tokio::sync::mpsc::unbounded_channel();
+        let results = MultiLanguageOrchestrator::execute_workflow_with_events(flow, events).await;
+        assert!(results.iter().all(|r| r.success));
+
+        let mut order = Vec::new();
+        while let Ok(event) = received.try_recv() {
+            order.push(match event {
+                WorkflowEvent::TaskStarted { index, .. } => format!("start {}", index),
+                WorkflowEvent::TaskFinished { index, .. } => format!("finish {}", index),
+            });
+        }
+        assert_eq!(order, ["start 1", "finish 1", "start 0", "finish 0"]);
+    }
+
+    /// Really runs tasks, by writing `file` for those whose command is `write`
+    struct Writer {
+        file: std::path::PathBuf,
+    }
+
+    impl ExecutionBackend for Writer {
+        fn execute(&self, task: LanguageTask) -> backend::TaskFuture {
+            if task.command == "write" {
+                std::fs::create_dir_all(self.file.parent().unwrap()).unwrap();
+                std::fs::write(&self.file, "built").unwrap();
+            }
+            Box::pin(async move {
+                ExecutionResult {
+                    task_name: task.name(),
+                    language: task.language,
+                    success: true,
+                    output: String::new(),
+                    execution_time: 0,
+                    exit_code: Some(0),
+                    changes: None,
+                }
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_host_tasks_are_checked_for_outputs_only_when_they_run() {
+        let dir = format!("target/parflow-outputs-{}", std::process::id());
+        let mut flow = workflow(false);
+        flow.tasks.truncate(1);
+        flow.tasks[0].outputs = vec![format!("{}/*.txt", dir)];
+        let run = |flow: MultiLanguageWorkflow, backend: Arc<dyn ExecutionBackend>| async move {
+            let (events, _) = tokio::sync::mpsc::unbounded_channel();
+            let cancel = CancellationToken::new();
+            MultiLanguageOrchestrator::execute_workflow_on(flow, events, cancel, backend)
+                .await
+                .value
+        };
+
+        // The mock writes nothing, and is not held to the outputs
+        let results = run(flow.clone(), Arc::new(MockBackend)).await;
+        assert!(results[0].success);
+        assert_eq!(results[0].changes, None);
+
+        let writer = Arc::new(Writer { file: format!("{}/out.txt", dir).into() });
+        let results = run(flow.clone(), writer.clone()).await;
+        assert!(!results[0].success);
+        assert!(results[0].output.contains("wrote nothing matching"));
+
+        flow.tasks[0].command = "write".to_string();
+        let results = run(flow, writer).await;
+        assert!(results[0].success, "{}", results[0].output);
+        let changes = results[0].changes.as_ref().unwrap();
+        assert_eq!(changes.created, [format!("{}/out.txt", dir)]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_subsets_stage_inputs_from_tasks_left_out() {
+        let dir = format!("target/parflow-subset-{}", std::process::id());
+        std::fs::create_dir_all(format!("{}/out", dir)).unwrap();
+        std::fs::write(format!("{}/out/lib.txt", dir), "built").unwrap();
+        let mut flow = workflow(false);
+        flow.tasks[0].outputs = vec![format!("{}/out/*.txt", dir)];
+        flow.tasks[1].inputs_from = vec!["rust_task".to_string()];
+        flow.tasks[1].working_dir = Some(dir.clone());
+
+        let subset = flow.subset(&[1]);
+        assert_eq!(subset.tasks[0].inputs_from, ["rust_task"]);
+        assert_eq!(subset.upstream_outputs["rust_task"], flow.tasks[0].outputs);
+        assert!(TaskGraph::new(&subset).is_ok());
+        let results = MultiLanguageOrchestrator::execute_workflow(subset).await;
+        assert!(results[0].success, "{}", results[0].output);
+        let staged = std::fs::read_to_string(format!("{}/.parflow/inputs/rust_task/lib.txt", dir));
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(staged.unwrap(), "built");
+    }
+
+    #[tokio::test]
+    async fn test_dependency_cycles_skip_tasks_instead_of_hanging() {
+        for concurrent in [true, false] {
+            let mut flow = workflow(concurrent);
+            flow.tasks[0].depends_on = vec!["python_task".to_string()];
+            flow.tasks[1].depends_on = vec!["rust_task".to_string()];
+            let run = MultiLanguageOrchestrator::execute_workflow(flow);
+            let results = tokio::time::timeout(Duration::from_secs(5), run).await.unwrap();
+            assert_eq!(results.len(), 2);
+            assert!(results.iter().all(|r| !r.success && r.output.starts_with("skipped")));
+
+            let mut flow = workflow(concurrent);
+            flow.tasks[1].depends_on = vec!["python_task".to_string()];
+            let run = MultiLanguageOrchestrator::execute_workflow(flow);
+            let results = tokio::time::timeout(Duration::from_secs(5), run).await.unwrap();
+            assert_eq!(results.len(), 2);
         }
     }
 }
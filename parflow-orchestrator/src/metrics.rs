@@ -0,0 +1,87 @@
+//! Instruments for workflow execution
+//!
+//! Task metrics go through the OpenTelemetry global meter provider and workflow totals through the
+//! `metrics` facade, so both cost nothing until a binary installs an exporter (see
+//! `parflow_core::telemetry`) or a recorder (parflow-rest's `/metrics`).
+
+use opentelemetry_api::metrics::{Histogram, UpDownCounter};
+use opentelemetry_api::{global, KeyValue};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+struct Instruments {
+    task_duration: Histogram<f64>,
+    queue_depth: UpDownCounter<i64>,
+}
+
+fn instruments() -> &'static Instruments {
+    static INSTRUMENTS: OnceLock<Instruments> = OnceLock::new();
+    INSTRUMENTS.get_or_init(|| {
+        let meter = global::meter("parflow-orchestrator");
+        Instruments {
+            task_duration: meter
+                .f64_histogram("parflow.task.duration")
+                .with_description("Workflow task run time in milliseconds")
+                .init(),
+            queue_depth: meter
+                .i64_up_down_counter("parflow.task.queue_depth")
+                .with_description("Workflow tasks submitted but not yet started")
+                .init(),
+        }
+    })
+}
+
+/// `count` tasks were queued (positive) or left the queue (negative).
+pub(crate) fn queued(count: i64) {
+    instruments().queue_depth.add(count, &[]);
+}
+
+/// A whole workflow finished, either running every task or stopping on cancellation.
+pub(crate) fn workflow_finished(concurrent: bool, cancelled: bool, duration: Duration) {
+    let mode = if concurrent { "parallel" } else { "sequential" };
+    let outcome = if cancelled { "cancelled" } else { "complete" };
+    ::metrics::histogram!(
+        "parflow_workflow_duration_seconds",
+        "subsystem" => "orchestrator",
+        "mode" => mode
+    )
+    .record(duration.as_secs_f64());
+    ::metrics::counter!(
+        "parflow_workflows_total",
+        "subsystem" => "orchestrator",
+        "mode" => mode,
+        "outcome" => outcome
+    )
+    .increment(1);
+}
+
+pub(crate) fn task_finished(language: &str, success: bool, duration: Duration) {
+    instruments().task_duration.record(
+        duration.as_secs_f64() * 1000.0,
+        &[KeyValue::new("language", language.to_string()), KeyValue::new("success", success)],
+    );
+}
+
+/// Current depth of the work-stealing scheduler's queues.
+pub(crate) fn scheduler_depth(pending: usize, queued: usize, running: usize, workers: usize) {
+    for (state, count) in [("pending", pending), ("queued", queued), ("running", running)] {
+        ::metrics::gauge!(
+            "parflow_scheduler_tasks",
+            "subsystem" => "orchestrator",
+            "state" => state
+        )
+        .set(count as f64);
+    }
+    ::metrics::gauge!("parflow_scheduler_workers", "subsystem" => "orchestrator")
+        .set(workers as f64);
+}
+
+pub(crate) fn task_stolen() {
+    ::metrics::counter!("parflow_scheduler_steals_total", "subsystem" => "orchestrator")
+        .increment(1);
+}
+
+pub(crate) fn task_requeued() {
+    ::metrics::counter!("parflow_scheduler_requeues_total", "subsystem" => "orchestrator")
+        .increment(1);
+}
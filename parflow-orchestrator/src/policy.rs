@@ -0,0 +1,245 @@
+//! Limits on what tasks may reach
+//!
+//! A task's `policy` cuts it off from the network and makes the filesystem read-only apart
+//! from the paths it lists, so workflow files from an untrusted repository can be run:
+//!
+//! ```yaml
+//! - name: contrib-test
+//!   language: Python
+//!   command: python
+//!   args: [-m, pytest]
+//!   policy:
+//!     network: deny
+//!     fs: read-only
+//!     allow_paths: [.pytest_cache, reports]
+//! ```
+//!
+//! `allow_paths` are relative to the workspace and may not leave it: workflows with absolute
+//! paths or `..` in them do not load. Tasks with an image get the container equivalents from
+//! [`ContainerBackend`](crate::ContainerBackend), which is where the default backend enforces
+//! policies; it only simulates tasks without an image, so there is nothing of theirs to
+//! confine.
+//!
+//! Embedders that run host tasks with their own backend can wrap it in [`PolicyBackend`]. On
+//! Linux it runs them under bubblewrap (`bwrap`), creating missing `allow_paths`, or under
+//! `unshare` when only the network is denied; both rely on unprivileged user namespaces. Where
+//! neither is available, and on other systems, the task runs unrestricted with a warning.
+
+use crate::backend::{on_path, ExecutionBackend, TaskFuture};
+use crate::LanguageTask;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Network {
+    #[default]
+    Allow,
+    Deny,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Filesystem {
+    #[default]
+    ReadWrite,
+    ReadOnly,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskPolicy {
+    #[serde(default)]
+    pub network: Network,
+    #[serde(default)]
+    pub fs: Filesystem,
+    /// Paths relative to the workspace a task with a read-only filesystem may still write
+    #[serde(default, skip_serializing_if = "Vec::is_empty", deserialize_with = "workspace_paths")]
+    pub allow_paths: Vec<String>,
+}
+
+/// Whether `path` names something inside the workspace: relative, without `..`
+pub(crate) fn in_workspace(path: &str) -> bool {
+    !path.is_empty()
+        && Path::new(path)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+fn workspace_paths<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    let paths = Vec::<String>::deserialize(deserializer)?;
+    match paths.iter().find(|path| !in_workspace(path)) {
+        Some(path) => Err(serde::de::Error::custom(format!(
+            "allow_paths must stay inside the workspace, {:?} does not",
+            path
+        ))),
+        None => Ok(paths),
+    }
+}
+
+impl TaskPolicy {
+    pub fn is_unrestricted(&self) -> bool {
+        self.network == Network::Allow && self.fs == Filesystem::ReadWrite
+    }
+}
+
+/// What confines a task running on the host
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Confinement {
+    Bubblewrap,
+    Unshare,
+}
+
+impl Confinement {
+    /// The tool on this system that can enforce `policy`, if any
+    pub fn available(policy: &TaskPolicy) -> Option<Self> {
+        if !cfg!(target_os = "linux") {
+            return None;
+        }
+        if on_path("bwrap") {
+            Some(Self::Bubblewrap)
+        } else if policy.fs == Filesystem::ReadWrite && on_path("unshare") {
+            Some(Self::Unshare)
+        } else {
+            None
+        }
+    }
+
+    /// The command line running `task` under its policy, with allowed paths taken relative to
+    /// `workspace`
+    pub fn command(self, task: &LanguageTask, workspace: &Path) -> Vec<String> {
+        let policy = &task.policy;
+        let mut command: Vec<String> = match self {
+            Self::Unshare => {
+                ["unshare", "--user", "--map-root-user", "--net", "--"].map(String::from).to_vec()
+            }
+            Self::Bubblewrap => {
+                let mut bwrap = vec!["bwrap".to_string()];
+                let root = match policy.fs {
+                    Filesystem::ReadWrite => "--bind",
+                    Filesystem::ReadOnly => "--ro-bind",
+                };
+                bwrap
+                    .extend([root, "/", "/", "--dev", "/dev", "--proc", "/proc"].map(String::from));
+                if policy.fs == Filesystem::ReadOnly {
+                    bwrap.extend(["--tmpfs", "/tmp"].map(String::from));
+                    // Deserialized policies only have workspace paths, built ones are filtered
+                    for path in policy.allow_paths.iter().filter(|path| in_workspace(path)) {
+                        let path = workspace.join(path).display().to_string();
+                        bwrap.extend(["--bind".to_string(), path.clone(), path]);
+                    }
+                }
+                if policy.network == Network::Deny {
+                    bwrap.push("--unshare-net".to_string());
+                }
+                bwrap.extend(["--die-with-parent", "--"].map(String::from));
+                bwrap
+            }
+        };
+        command.push(task.command.clone());
+        command.extend(task.args.iter().cloned());
+        command
+    }
+}
+
+/// Confines the host tasks with a policy before handing them to `host`
+pub struct PolicyBackend {
+    workspace: PathBuf,
+    host: Arc<dyn ExecutionBackend>,
+}
+
+impl PolicyBackend {
+    pub fn new(workspace: PathBuf, host: Arc<dyn ExecutionBackend>) -> Self {
+        Self { workspace, host }
+    }
+}
+
+impl ExecutionBackend for PolicyBackend {
+    fn runs(&self, task: &LanguageTask) -> bool {
+        self.host.runs(task)
+    }
+
+    fn execute(&self, mut task: LanguageTask) -> TaskFuture {
+        if task.image.is_some() || task.policy.is_unrestricted() {
+            return self.host.execute(task);
+        }
+        match Confinement::available(&task.policy) {
+            Some(confinement) => {
+                info!(?confinement, "🔒 Confining task");
+                for path in task.policy.allow_paths.iter().filter(|path| in_workspace(path)) {
+                    // bwrap can only bind paths that exist
+                    let _ = std::fs::create_dir_all(self.workspace.join(path));
+                }
+                let mut command = confinement.command(&task, &self.workspace).into_iter();
+                task.command = command.next().unwrap_or_default();
+                task.args = command.collect();
+            }
+            None => warn!(
+                task = %task.name(),
+                "⚠️  Cannot enforce the task's policy here, running it unrestricted"
+            ),
+        }
+        self.host.execute(task)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_confines_host_commands_to_the_policy() {
+        let mut task: LanguageTask = serde_yaml::from_str(
+            "{ language: Python, command: pytest, args: [-q], \
+               policy: { network: deny, fs: read-only, allow_paths: [reports] } }",
+        )
+        .unwrap();
+        let workspace = Path::new("/src/app");
+        assert_eq!(
+            Confinement::Bubblewrap.command(&task, workspace).join(" "),
+            "bwrap --ro-bind / / --dev /dev --proc /proc --tmpfs /tmp \
+             --bind /src/app/reports /src/app/reports --unshare-net --die-with-parent -- pytest -q"
+        );
+        assert_eq!(
+            Confinement::Unshare.command(&task, workspace).join(" "),
+            "unshare --user --map-root-user --net -- pytest -q"
+        );
+
+        task.policy.network = Network::Allow;
+        assert!(!task.policy.is_unrestricted());
+        task.policy.fs = Filesystem::ReadWrite;
+        assert!(task.policy.is_unrestricted());
+        assert_eq!(
+            serde_yaml::to_string(&TaskPolicy::default()).unwrap(),
+            "network: allow\nfs: read-write\n"
+        );
+    }
+
+    #[test]
+    fn test_allow_paths_cannot_leave_the_workspace() {
+        let policy = |paths: &str| {
+            serde_yaml::from_str::<TaskPolicy>(&format!(
+                "{{ fs: read-only, allow_paths: {} }}",
+                paths
+            ))
+        };
+        assert_eq!(
+            policy("[reports, ./out/cache]").unwrap().allow_paths,
+            ["reports", "./out/cache"]
+        );
+        for escape in ["['/']", "[/etc]", "['../..']", "[out/../../home]", "['']"] {
+            let error = policy(escape).unwrap_err().to_string();
+            assert!(error.contains("must stay inside the workspace"), "{}: {}", escape, error);
+        }
+
+        // Policies built in code skip such paths instead
+        let mut task: LanguageTask =
+            serde_yaml::from_str("{ language: Python, command: pytest, args: [] }").unwrap();
+        task.policy.fs = Filesystem::ReadOnly;
+        task.policy.allow_paths = vec!["/".to_string(), "reports".to_string()];
+        let command = Confinement::Bubblewrap.command(&task, Path::new("/src/app")).join(" ");
+        assert!(command.contains("--bind /src/app/reports /src/app/reports --die-with-parent"));
+        assert!(!command.contains("--bind / /"));
+    }
+}
@@ -0,0 +1,251 @@
+//! Durable workflow runs
+//!
+//! A workflow submitted to the [`JobQueue`] gets a journal, `<dir>/<workflow_id>.jsonl`. The
+//! workflow itself is its first line; every task start and finish is appended, and flushed to
+//! disk, as it happens. If the process dies mid-run, [`JobQueue::run`] on the same id reads the
+//! journal back and runs only the tasks that had not succeeded, so the workflow continues from
+//! where it failed instead of starting over.
+
+use crate::{ExecutionResult, MultiLanguageOrchestrator, MultiLanguageWorkflow, WorkflowEvent};
+use anyhow::{anyhow, bail, Context, Result};
+use parflow_core::cancel::{CancellationToken, Partial};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// Externally tagged: internally tagged enums cannot hold the u128 times of results
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum JournalRecord {
+    Submitted { workflow_id: String, submitted_at: i64, workflow: MultiLanguageWorkflow },
+    TaskStarted { index: usize },
+    TaskFinished { index: usize, result: ExecutionResult },
+    Finished { at: i64, cancelled: bool },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskState {
+    Pending,
+    /// Started but never finished, e.g. because the process died
+    Running,
+    Succeeded,
+    Failed,
+}
+
+/// A workflow as its journal left it
+#[derive(Debug)]
+pub struct QueuedWorkflow {
+    pub workflow_id: String,
+    pub submitted_at: i64,
+    pub workflow: MultiLanguageWorkflow,
+    pub states: Vec<TaskState>,
+    /// Latest result of each task
+    pub results: Vec<Option<ExecutionResult>>,
+    /// Whether the last attempt ran to the end, successful or not
+    pub finished: bool,
+}
+
+impl QueuedWorkflow {
+    /// Tasks the next attempt runs: all that have not succeeded
+    pub fn remaining(&self) -> Vec<usize> {
+        (0..self.states.len()).filter(|&i| self.states[i] != TaskState::Succeeded).collect()
+    }
+}
+
+pub struct JobQueue {
+    dir: PathBuf,
+}
+
+impl JobQueue {
+    pub const DEFAULT_DIR: &'static str = ".parflow/queue";
+
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("creating job queue at {}", dir.display()))?;
+        Ok(Self { dir })
+    }
+
+    fn journal(&self, workflow_id: &str) -> PathBuf {
+        self.dir.join(format!("{}.jsonl", workflow_id))
+    }
+
+    /// Queue `workflow`, returning its id.
+    pub fn submit(&self, workflow: MultiLanguageWorkflow) -> Result<String> {
+        let workflow_id = uuid::Uuid::new_v4().to_string();
+        let mut journal = File::create_new(self.journal(&workflow_id))?;
+        append(
+            &mut journal,
+            &JournalRecord::Submitted {
+                workflow_id: workflow_id.clone(),
+                submitted_at: now(),
+                workflow,
+            },
+        )?;
+        Ok(workflow_id)
+    }
+
+    pub fn load(&self, workflow_id: &str) -> Result<QueuedWorkflow> {
+        let path = self.journal(workflow_id);
+        let text = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow!("no queued workflow {}: {}", workflow_id, e))?;
+        let mut lines = text.lines();
+        let first = lines.next().unwrap_or_default();
+        let Ok(JournalRecord::Submitted { workflow_id, submitted_at, workflow }) =
+            serde_json::from_str(first)
+        else {
+            bail!("{} does not start with a submitted workflow", path.display());
+        };
+        let tasks = workflow.tasks.len();
+        let mut queued = QueuedWorkflow {
+            workflow_id,
+            submitted_at,
+            workflow,
+            states: vec![TaskState::Pending; tasks],
+            results: vec![None; tasks],
+            finished: false,
+        };
+        // A line cut short by a crash is the last one; skip it like any unreadable line
+        for record in lines.filter_map(|line| serde_json::from_str::<JournalRecord>(line).ok()) {
+            match record {
+                JournalRecord::TaskStarted { index } if index < tasks => {
+                    queued.states[index] = TaskState::Running;
+                    queued.finished = false;
+                }
+                JournalRecord::TaskFinished { index, result } if index < tasks => {
+                    queued.states[index] =
+                        if result.success { TaskState::Succeeded } else { TaskState::Failed };
+                    queued.results[index] = Some(result);
+                }
+                JournalRecord::Finished { cancelled, .. } => queued.finished = !cancelled,
+                _ => {}
+            }
+        }
+        Ok(queued)
+    }
+
+    /// Every queued workflow, oldest first. Unreadable journals are skipped.
+    pub fn list(&self) -> Result<Vec<QueuedWorkflow>> {
+        let mut queued = Vec::new();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == "jsonl") {
+                let Some(id) = path.file_stem().and_then(|id| id.to_str()) else { continue };
+                if let Ok(workflow) = self.load(id) {
+                    queued.push(workflow);
+                }
+            }
+        }
+        queued.sort_by_key(|workflow| workflow.submitted_at);
+        Ok(queued)
+    }
+
+    /// Run the tasks of `workflow_id` that have not succeeded yet, journaling as they go.
+    /// Returns the latest result of every task that has one, in workflow order.
+    pub async fn run(
+        &self,
+        workflow_id: &str,
+        cancel: CancellationToken,
+    ) -> Result<Partial<Vec<ExecutionResult>>> {
+        let queued = self.load(workflow_id)?;
+        let remaining = queued.remaining();
+        let path = self.journal(workflow_id);
+        let mut journal = OpenOptions::new().append(true).open(&path)?;
+        // End a line left half-written by a crash, so it does not swallow the next record
+        if std::fs::read(&path)?.last() != Some(&b'\n') {
+            writeln!(journal)?;
+        }
+        let mut results = queued.results;
+
+        let (events, mut received) = tokio::sync::mpsc::unbounded_channel();
+        let run = MultiLanguageOrchestrator::execute_workflow_cancellable(
+            queued.workflow.subset(&remaining),
+            events,
+            cancel,
+        );
+        // Events carry indexes into the subset; the journal keeps those of the workflow
+        let record = async {
+            while let Some(event) = received.recv().await {
+                let record = match event {
+                    WorkflowEvent::TaskStarted { index, .. } => {
+                        JournalRecord::TaskStarted { index: remaining[index] }
+                    }
+                    WorkflowEvent::TaskFinished { index, result } => {
+                        results[remaining[index]] = Some(result.clone());
+                        JournalRecord::TaskFinished { index: remaining[index], result }
+                    }
+                };
+                append(&mut journal, &record)?;
+            }
+            Ok::<_, anyhow::Error>(())
+        };
+        let (ran, recorded) = tokio::join!(run, record);
+        recorded?;
+        append(&mut journal, &JournalRecord::Finished { at: now(), cancelled: ran.cancelled })?;
+        Ok(Partial { value: results.into_iter().flatten().collect(), cancelled: ran.cancelled })
+    }
+}
+
+fn append(journal: &mut File, record: &JournalRecord) -> Result<()> {
+    writeln!(journal, "{}", serde_json::to_string(record)?)?;
+    journal.sync_data()?;
+    Ok(())
+}
+
+fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_resumes_after_the_last_successful_task() {
+        let dir = std::env::temp_dir().join(format!("parflow-queue-{}", std::process::id()));
+        let queue = JobQueue::open(&dir).unwrap();
+        let workflow: MultiLanguageWorkflow = serde_json::from_str(
+            r#"{"name": "build", "concurrent": false, "tasks": [
+                {"name": "lib", "language": "Rust", "command": "cargo", "args": ["build"]},
+                {"name": "app", "language": "Python", "command": "python", "args": ["app.py"]}
+            ]}"#,
+        )
+        .unwrap();
+        let id = queue.submit(workflow).unwrap();
+
+        // The process died while running app, after lib succeeded
+        let mut journal = OpenOptions::new().append(true).open(queue.journal(&id)).unwrap();
+        let lib = ExecutionResult {
+            task_name: "lib".to_string(),
+            language: "Rust".to_string(),
+            success: true,
+            output: "built before the crash".to_string(),
+            execution_time: 5,
+            exit_code: Some(0),
+            changes: None,
+        };
+        append(&mut journal, &JournalRecord::TaskStarted { index: 0 }).unwrap();
+        append(&mut journal, &JournalRecord::TaskFinished { index: 0, result: lib }).unwrap();
+        append(&mut journal, &JournalRecord::TaskStarted { index: 1 }).unwrap();
+        write!(journal, "{{\"task_finished\":{{\"ind").unwrap();
+        let crashed = queue.load(&id).unwrap();
+        assert_eq!(crashed.states, [TaskState::Succeeded, TaskState::Running]);
+        assert_eq!(crashed.remaining(), [1]);
+
+        let results = queue.run(&id, CancellationToken::new()).await.unwrap();
+        assert!(!results.cancelled);
+        assert_eq!(results.value[0].output, "built before the crash");
+        assert_eq!(results.value[1].task_name, "app");
+
+        let resumed = queue.load(&id).unwrap();
+        assert!(resumed.finished);
+        assert!(resumed.remaining().is_empty());
+        assert_eq!(queue.list().unwrap().len(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
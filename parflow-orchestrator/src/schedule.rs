@@ -0,0 +1,479 @@
+//! Cron-scheduled workflows
+//!
+//! A [`Schedule`] binds a cron expression to a workflow. Expressions have the five standard
+//! fields (minute, hour, day of month, month, day of week) and are evaluated in UTC, so
+//! `0 3 * * 1-5` runs at 03:00 UTC on weekdays.
+//!
+//! The [`CronScheduler`] keeps its schedules in `<dir>/schedules.json`, rewritten on every change,
+//! so they survive server restarts. Only one scheduler at a time may own a directory: it holds a
+//! lock on `<dir>/schedules.lock`, and opening the directory elsewhere fails until it is gone, so
+//! schedules never fire twice. Runs missed while the server was down are not made up:
+//! after a restart each schedule next runs at its next matching time. A run that comes due
+//! while the previous run of the same schedule is still going follows the schedule's
+//! [`OverlapPolicy`].
+
+use crate::{MultiLanguageWorkflow, TaskGraph};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use croner::Cron;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, TryLockError};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+use tracing::{info, warn};
+
+const FILE: &str = "schedules.json";
+const LOCK: &str = "schedules.lock";
+
+/// What to do with a run that comes due while the previous one is still going
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlapPolicy {
+    /// Drop the new run
+    #[default]
+    Skip,
+    /// Start the new run once the previous one finishes; at most one run waits
+    Queue,
+    /// Start the new run alongside the previous one
+    Allow,
+}
+
+impl OverlapPolicy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Skip => "skip",
+            Self::Queue => "queue",
+            Self::Allow => "allow",
+        }
+    }
+}
+
+impl std::str::FromStr for OverlapPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "" | "skip" => Ok(Self::Skip),
+            "queue" => Ok(Self::Queue),
+            "allow" => Ok(Self::Allow),
+            _ => bail!("unknown overlap policy '{}' (expected skip, queue or allow)", s),
+        }
+    }
+}
+
+/// What a client asks to schedule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleSpec {
+    pub name: String,
+    pub cron: String,
+    pub workflow: MultiLanguageWorkflow,
+    #[serde(default)]
+    pub overlap: OverlapPolicy,
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Schedule {
+    pub id: String,
+    #[serde(flatten)]
+    pub spec: ScheduleSpec,
+    pub created_at: i64,
+    pub last_run_at: Option<i64>,
+}
+
+/// A schedule and what it is doing right now
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleStatus {
+    #[serde(flatten)]
+    pub schedule: Schedule,
+    /// Unset for disabled schedules
+    pub next_run_at: Option<i64>,
+    pub running: usize,
+    /// Whether a run is waiting for the running one, see [`OverlapPolicy::Queue`]
+    pub queued: bool,
+}
+
+/// Starts the workflow of a schedule; the returned future finishes with the run.
+pub type RunSchedule =
+    Arc<dyn Fn(Schedule) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
+
+struct Entry {
+    schedule: Schedule,
+    cron: Cron,
+    next: Option<DateTime<Utc>>,
+    running: usize,
+    queued: bool,
+}
+
+impl Entry {
+    fn new(schedule: Schedule, now: DateTime<Utc>) -> Result<Self> {
+        let cron = parse_cron(&schedule.spec.cron)?;
+        let mut entry = Self { schedule, cron, next: None, running: 0, queued: false };
+        entry.advance(now);
+        Ok(entry)
+    }
+
+    /// Move `next` to the first matching time after `now`
+    fn advance(&mut self, now: DateTime<Utc>) {
+        self.next = if self.schedule.spec.enabled {
+            self.cron.find_next_occurrence(&now, false).ok()
+        } else {
+            None
+        };
+    }
+
+    fn status(&self) -> ScheduleStatus {
+        ScheduleStatus {
+            schedule: self.schedule.clone(),
+            next_run_at: self.next.map(|next| next.timestamp()),
+            running: self.running,
+            queued: self.queued,
+        }
+    }
+}
+
+fn parse_cron(expression: &str) -> Result<Cron> {
+    Cron::new(expression)
+        .parse()
+        .map_err(|e| anyhow::anyhow!("invalid cron expression '{}': {}", expression, e))
+}
+
+fn validate(spec: &ScheduleSpec) -> Result<()> {
+    if spec.workflow.tasks.is_empty() {
+        bail!("workflow '{}' has no tasks", spec.workflow.name);
+    }
+    parse_cron(&spec.cron)?;
+    TaskGraph::new(&spec.workflow)?;
+    Ok(())
+}
+
+struct Inner {
+    path: PathBuf,
+    entries: Mutex<HashMap<String, Entry>>,
+    changed: Notify,
+    /// Held while the scheduler lives, see [`CronScheduler::open`]
+    _lock: File,
+}
+
+/// Persistent cron schedules and the loop that runs them. Clones share the same schedules.
+#[derive(Clone)]
+pub struct CronScheduler {
+    inner: Arc<Inner>,
+}
+
+impl CronScheduler {
+    /// Load the schedules kept in `dir`, creating it if needed. Fails while another scheduler,
+    /// in this process or another, has `dir` open.
+    pub fn open(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("creating schedule store at {}", dir.display()))?;
+        let lock = File::create(dir.join(LOCK))?;
+        match lock.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                bail!("the schedules in {} are already run by another scheduler", dir.display())
+            }
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+        let path = dir.join(FILE);
+        let schedules: Vec<Schedule> = if path.exists() {
+            serde_json::from_str(&std::fs::read_to_string(&path)?)
+                .with_context(|| format!("reading {}", path.display()))?
+        } else {
+            Vec::new()
+        };
+        let now = Utc::now();
+        let mut entries = HashMap::new();
+        for schedule in schedules {
+            entries.insert(schedule.id.clone(), Entry::new(schedule, now)?);
+        }
+        Ok(Self {
+            inner: Arc::new(Inner {
+                path,
+                entries: Mutex::new(entries),
+                changed: Notify::new(),
+                _lock: lock,
+            }),
+        })
+    }
+
+    /// All schedules, oldest first
+    pub fn list(&self) -> Vec<ScheduleStatus> {
+        let entries = self.inner.entries.lock().unwrap();
+        let mut schedules: Vec<ScheduleStatus> = entries.values().map(Entry::status).collect();
+        schedules.sort_by(|a, b| {
+            (a.schedule.created_at, &a.schedule.id).cmp(&(b.schedule.created_at, &b.schedule.id))
+        });
+        schedules
+    }
+
+    /// False once a panic while holding the schedules has poisoned them.
+    pub fn is_healthy(&self) -> bool {
+        !self.inner.entries.is_poisoned()
+    }
+
+    pub fn get(&self, id: &str) -> Option<ScheduleStatus> {
+        self.inner.entries.lock().unwrap().get(id).map(Entry::status)
+    }
+
+    /// Fails on invalid cron expressions and on workflows that cannot run.
+    pub fn create(&self, spec: ScheduleSpec) -> Result<ScheduleStatus> {
+        validate(&spec)?;
+        let now = Utc::now();
+        let schedule = Schedule {
+            id: uuid::Uuid::new_v4().to_string(),
+            spec,
+            created_at: now.timestamp(),
+            last_run_at: None,
+        };
+        let entry = Entry::new(schedule, now)?;
+        let status = entry.status();
+        let mut entries = self.inner.entries.lock().unwrap();
+        entries.insert(status.schedule.id.clone(), entry);
+        self.save(&entries)?;
+        self.inner.changed.notify_one();
+        Ok(status)
+    }
+
+    /// Replace the spec of schedule `id`, keeping its history. `None` if there is no such
+    /// schedule.
+    pub fn update(&self, id: &str, spec: ScheduleSpec) -> Result<Option<ScheduleStatus>> {
+        validate(&spec)?;
+        let mut entries = self.inner.entries.lock().unwrap();
+        let Some(entry) = entries.get_mut(id) else { return Ok(None) };
+        entry.cron = parse_cron(&spec.cron)?;
+        entry.schedule.spec = spec;
+        entry.advance(Utc::now());
+        let status = entry.status();
+        self.save(&entries)?;
+        self.inner.changed.notify_one();
+        Ok(Some(status))
+    }
+
+    /// Remove schedule `id`. Runs in progress finish; a queued run is dropped.
+    pub fn delete(&self, id: &str) -> Result<bool> {
+        let mut entries = self.inner.entries.lock().unwrap();
+        if entries.remove(id).is_none() {
+            return Ok(false);
+        }
+        self.save(&entries)?;
+        self.inner.changed.notify_one();
+        Ok(true)
+    }
+
+    /// Run schedules as they come due until the returned task is aborted.
+    pub fn start<F, Fut>(&self, run: F) -> tokio::task::JoinHandle<()>
+    where
+        F: Fn(Schedule) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let run: RunSchedule = Arc::new(move |schedule| Box::pin(run(schedule)));
+        let scheduler = self.clone();
+        tokio::spawn(async move {
+            loop {
+                let next = scheduler.tick(Utc::now(), &run);
+                let wait = match next {
+                    Some(next) => (next - Utc::now()).to_std().unwrap_or_default(),
+                    None => std::time::Duration::from_secs(3600),
+                };
+                tokio::select! {
+                    _ = tokio::time::sleep(wait) => {}
+                    _ = scheduler.inner.changed.notified() => {}
+                }
+            }
+        })
+    }
+
+    /// Start every schedule due by `now`, returning when the next one is due.
+    fn tick(&self, now: DateTime<Utc>, run: &RunSchedule) -> Option<DateTime<Utc>> {
+        let mut entries = self.inner.entries.lock().unwrap();
+        let mut started = false;
+        for entry in entries.values_mut() {
+            if entry.next.is_none_or(|next| next > now) {
+                continue;
+            }
+            entry.advance(now);
+            match (entry.running, entry.schedule.spec.overlap) {
+                (0, _) | (_, OverlapPolicy::Allow) => {
+                    self.launch(entry, now, run);
+                    started = true;
+                }
+                (_, OverlapPolicy::Queue) => entry.queued = true,
+                (_, OverlapPolicy::Skip) => {
+                    warn!(
+                        schedule = %entry.schedule.spec.name,
+                        "⏭️  Skipping scheduled run, the previous one is still going"
+                    );
+                }
+            }
+        }
+        if started {
+            if let Err(e) = self.save(&entries) {
+                warn!(error = %e, "⚠️  Could not save schedules");
+            }
+        }
+        entries.values().filter_map(|entry| entry.next).min()
+    }
+
+    fn launch(&self, entry: &mut Entry, now: DateTime<Utc>, run: &RunSchedule) {
+        entry.running += 1;
+        entry.schedule.last_run_at = Some(now.timestamp());
+        let schedule = entry.schedule.clone();
+        info!(
+            schedule = %schedule.spec.name,
+            workflow = %schedule.spec.workflow.name,
+            "⏰ Starting scheduled workflow"
+        );
+        let finished =
+            Finished { scheduler: self.clone(), id: schedule.id.clone(), run: run.clone() };
+        let run = run.clone();
+        tokio::spawn(async move {
+            // Dropped when the run ends, even by panicking
+            let _finished = finished;
+            run(schedule).await;
+        });
+    }
+
+    fn save(&self, entries: &HashMap<String, Entry>) -> Result<()> {
+        let mut schedules: Vec<&Schedule> = entries.values().map(|entry| &entry.schedule).collect();
+        schedules.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+        // Write aside and rename, so a crash never leaves a half-written file behind
+        let partial = self.inner.path.with_extension("json.tmp");
+        std::fs::write(&partial, serde_json::to_string_pretty(&schedules)?)?;
+        std::fs::rename(&partial, &self.inner.path)?;
+        Ok(())
+    }
+}
+
+/// Counts a run of schedule `id` as over and starts the run queued behind it
+struct Finished {
+    scheduler: CronScheduler,
+    id: String,
+    run: RunSchedule,
+}
+
+impl Drop for Finished {
+    fn drop(&mut self) {
+        let Ok(mut entries) = self.scheduler.inner.entries.lock() else { return };
+        let Some(entry) = entries.get_mut(&self.id) else { return };
+        entry.running -= 1;
+        if entry.running == 0 && std::mem::take(&mut entry.queued) {
+            self.scheduler.launch(entry, Utc::now(), &self.run);
+            if let Err(e) = self.scheduler.save(&entries) {
+                warn!(error = %e, "⚠️  Could not save schedules");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn spec(overlap: OverlapPolicy) -> ScheduleSpec {
+        ScheduleSpec {
+            name: "nightly".to_string(),
+            cron: "0 3 * * *".to_string(),
+            workflow: serde_json::from_str(
+                r#"{"name": "build", "concurrent": true, "tasks": [
+                    {"language": "Rust", "command": "cargo", "args": ["build"]}
+                ]}"#,
+            )
+            .unwrap(),
+            overlap,
+            enabled: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_persists_schedules_and_applies_overlap_policies() {
+        let dir = std::env::temp_dir().join(format!("parflow-schedules-{}", std::process::id()));
+        let scheduler = CronScheduler::open(&dir).unwrap();
+        let skip = scheduler.create(spec(OverlapPolicy::Skip)).unwrap().schedule.id;
+        let queue = scheduler.create(spec(OverlapPolicy::Queue)).unwrap().schedule.id;
+        let mut bad = spec(OverlapPolicy::Allow);
+        bad.cron = "every night".to_string();
+        assert!(scheduler.create(bad).is_err());
+
+        let next = scheduler.get(&skip).unwrap().next_run_at.unwrap();
+        assert_eq!(next % 86_400, 3 * 3600);
+
+        // Runs wait for a permit, so they stay running until the test lets them finish
+        let started = Arc::new(AtomicUsize::new(0));
+        let permits = Arc::new(tokio::sync::Semaphore::new(0));
+        let run: RunSchedule = {
+            let (started, permits) = (started.clone(), permits.clone());
+            Arc::new(move |_| {
+                let (started, permits) = (started.clone(), permits.clone());
+                Box::pin(async move {
+                    started.fetch_add(1, Ordering::SeqCst);
+                    permits.acquire().await.unwrap().forget();
+                })
+            })
+        };
+        let tomorrow = Utc::now() + chrono::Duration::days(1);
+        scheduler.tick(tomorrow, &run);
+        tokio::task::yield_now().await;
+        assert_eq!(started.load(Ordering::SeqCst), 2);
+
+        let day_after = tomorrow + chrono::Duration::days(1);
+        scheduler.tick(day_after, &run);
+        assert!(!scheduler.get(&skip).unwrap().queued);
+        assert!(scheduler.get(&queue).unwrap().queued);
+
+        // Finishing the queued schedule's run starts the waiting one
+        permits.add_permits(2);
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(started.load(Ordering::SeqCst), 3);
+        assert!(!scheduler.get(&queue).unwrap().queued);
+
+        // One scheduler per directory, so schedules do not fire twice
+        let error = CronScheduler::open(&dir).err().unwrap().to_string();
+        assert!(error.contains("already run by another scheduler"), "{}", error);
+        permits.add_permits(1);
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        drop(scheduler);
+        let reopened = CronScheduler::open(&dir).unwrap();
+        assert_eq!(reopened.list().len(), 2);
+        assert!(reopened.get(&queue).unwrap().schedule.last_run_at.is_some());
+        assert!(reopened.delete(&skip).unwrap());
+        assert!(!reopened.delete(&skip).unwrap());
+        let mut disabled = spec(OverlapPolicy::Queue);
+        disabled.enabled = false;
+        let updated = reopened.update(&queue, disabled).unwrap().unwrap();
+        assert_eq!(updated.next_run_at, None);
+        drop(reopened);
+        assert_eq!(CronScheduler::open(&dir).unwrap().list().len(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_runs_that_panic_stop_counting_as_running() {
+        let dir = std::env::temp_dir().join(format!("parflow-panics-{}", std::process::id()));
+        let scheduler = CronScheduler::open(&dir).unwrap();
+        let id = scheduler.create(spec(OverlapPolicy::Skip)).unwrap().schedule.id;
+        let run: RunSchedule = Arc::new(|_| Box::pin(async { panic!("workflow crashed") }));
+        scheduler.tick(Utc::now() + chrono::Duration::days(1), &run);
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(scheduler.get(&id).unwrap().running, 0);
+        drop(scheduler);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,399 @@
+//! Work-stealing scheduler for the distributed worker pool
+//!
+//! Every submitted task is queued on the worker that fits its CPU and memory demand and would
+//! end up least loaded relative to its cores. Workers pull their next task with
+//! [`WorkStealingScheduler::next_task`]: first from their own queue, and once that is empty by
+//! stealing from the back of the longest queue of another worker, so a worker that finishes
+//! early takes over work instead of idling. A task only starts when the worker has the cores and
+//! memory left for it beside what it already runs.
+//!
+//! When a worker disconnects its queued tasks move to the remaining workers and its running
+//! tasks are re-queued, up to [`WorkStealingScheduler::with_max_attempts`] interruptions. Tasks
+//! that fit no connected worker wait until one registers.
+
+use crate::{metrics, LanguageTask};
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use tracing::{info, warn};
+
+/// CPU and memory a worker declares, or a task needs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Resources {
+    pub cpu_cores: u32,
+    pub memory_mb: u64,
+}
+
+impl Resources {
+    pub fn new(cpu_cores: u32, memory_mb: u64) -> Self {
+        Self { cpu_cores, memory_mb }
+    }
+
+    pub fn fits(&self, demand: &Resources) -> bool {
+        demand.cpu_cores <= self.cpu_cores && demand.memory_mb <= self.memory_mb
+    }
+
+    fn saturating_sub(&self, other: &Resources) -> Resources {
+        Resources {
+            cpu_cores: self.cpu_cores.saturating_sub(other.cpu_cores),
+            memory_mb: self.memory_mb.saturating_sub(other.memory_mb),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledTask {
+    pub id: String,
+    pub task: LanguageTask,
+    pub demand: Resources,
+    /// Times the task was interrupted by its worker disconnecting
+    pub attempts: u32,
+}
+
+/// A task handed to a worker by [`WorkStealingScheduler::next_task`]
+#[derive(Debug, Clone)]
+pub struct Assignment {
+    pub worker: String,
+    pub task: ScheduledTask,
+    /// The worker whose queue the task was stolen from
+    pub stolen_from: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorkerLoad {
+    pub id: String,
+    pub capacity: Resources,
+    /// Resources taken by running tasks
+    pub in_use: Resources,
+    pub queued: usize,
+    pub running: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QueueMetrics {
+    /// Tasks that fit no connected worker
+    pub pending: usize,
+    pub queued: usize,
+    pub running: usize,
+    pub completed: usize,
+    pub failed: usize,
+    pub steals: u64,
+    pub requeued: u64,
+    pub workers: Vec<WorkerLoad>,
+}
+
+struct Worker {
+    capacity: Resources,
+    queue: VecDeque<ScheduledTask>,
+    running: Vec<ScheduledTask>,
+}
+
+impl Worker {
+    fn in_use(&self) -> Resources {
+        self.running.iter().fold(Resources::default(), |used, task| Resources {
+            cpu_cores: used.cpu_cores + task.demand.cpu_cores,
+            memory_mb: used.memory_mb + task.demand.memory_mb,
+        })
+    }
+
+    /// Cores the worker would be committed to with `extra` added, per core it has
+    fn load_with(&self, extra: &Resources) -> f64 {
+        let committed: u32 =
+            self.queue.iter().chain(&self.running).map(|task| task.demand.cpu_cores.max(1)).sum();
+        (committed + extra.cpu_cores.max(1)) as f64 / self.capacity.cpu_cores.max(1) as f64
+    }
+}
+
+pub struct WorkStealingScheduler {
+    workers: BTreeMap<String, Worker>,
+    pending: VecDeque<ScheduledTask>,
+    max_attempts: u32,
+    submitted: u64,
+    completed: usize,
+    failed: Vec<String>,
+    steals: u64,
+    requeued: u64,
+}
+
+impl Default for WorkStealingScheduler {
+    fn default() -> Self {
+        Self {
+            workers: BTreeMap::new(),
+            pending: VecDeque::new(),
+            max_attempts: 3,
+            submitted: 0,
+            completed: 0,
+            failed: Vec::new(),
+            steals: 0,
+            requeued: 0,
+        }
+    }
+}
+
+impl WorkStealingScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Give up on a task once `max_attempts` workers disconnected while running it.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Add a worker, or update the capacity of a connected one, and give it the pending tasks
+    /// it fits.
+    pub fn register_worker(&mut self, id: &str, capacity: Resources) {
+        info!(
+            worker = id,
+            cores = capacity.cpu_cores,
+            memory_mb = capacity.memory_mb,
+            "🤝 Worker registered"
+        );
+        self.workers
+            .entry(id.to_string())
+            .and_modify(|worker| worker.capacity = capacity)
+            .or_insert_with(|| Worker { capacity, queue: VecDeque::new(), running: Vec::new() });
+        for task in std::mem::take(&mut self.pending) {
+            self.place(task);
+        }
+        self.publish();
+    }
+
+    /// Queue `task` and return its id.
+    pub fn submit(&mut self, task: LanguageTask, demand: Resources) -> String {
+        self.submitted += 1;
+        let id = format!("task-{}", self.submitted);
+        self.place(ScheduledTask { id: id.clone(), task, demand, attempts: 0 });
+        self.publish();
+        id
+    }
+
+    fn place(&mut self, task: ScheduledTask) {
+        let best = self
+            .workers
+            .iter_mut()
+            .filter(|(_, worker)| worker.capacity.fits(&task.demand))
+            .min_by(|(_, a), (_, b)| {
+                a.load_with(&task.demand).total_cmp(&b.load_with(&task.demand))
+            });
+        match best {
+            Some((_, worker)) => worker.queue.push_back(task),
+            None => self.pending.push_back(task),
+        }
+    }
+
+    /// The next task `worker_id` should run: the first of its own queue that fits what it has
+    /// free or, when its queue is empty, one stolen from the back of the longest other queue.
+    /// `None` when nothing fits.
+    pub fn next_task(&mut self, worker_id: &str) -> Option<Assignment> {
+        let free = {
+            let worker = self.workers.get(worker_id)?;
+            worker.capacity.saturating_sub(&worker.in_use())
+        };
+        let worker = self.workers.get_mut(worker_id)?;
+        let has_queue = !worker.queue.is_empty();
+        let own = worker
+            .queue
+            .iter()
+            .position(|task| free.fits(&task.demand))
+            .and_then(|index| worker.queue.remove(index));
+        let (task, stolen_from) = match own {
+            Some(task) => (task, None),
+            // Tasks of its own that do not fit yet will once its running tasks finish
+            None if has_queue => return None,
+            None => {
+                let (victim, task) = self.steal(worker_id, &free)?;
+                self.steals += 1;
+                info!(worker = worker_id, from = %victim, task = %task.id, "🦝 Stole task");
+                metrics::task_stolen();
+                (task, Some(victim))
+            }
+        };
+        if let Some(worker) = self.workers.get_mut(worker_id) {
+            worker.running.push(task.clone());
+        }
+        self.publish();
+        Some(Assignment { worker: worker_id.to_string(), task, stolen_from })
+    }
+
+    fn steal(&mut self, thief: &str, free: &Resources) -> Option<(String, ScheduledTask)> {
+        let mut victims: Vec<(&String, &mut Worker)> =
+            self.workers.iter_mut().filter(|(id, _)| id.as_str() != thief).collect();
+        victims.sort_by_key(|(_, worker)| std::cmp::Reverse(worker.queue.len()));
+        victims.into_iter().find_map(|(id, worker)| {
+            let index = worker.queue.iter().rposition(|task| free.fits(&task.demand))?;
+            Some((id.clone(), worker.queue.remove(index)?))
+        })
+    }
+
+    /// Record that `worker_id` finished running `task_id`.
+    pub fn complete(&mut self, worker_id: &str, task_id: &str, success: bool) -> Result<()> {
+        let Some(worker) = self.workers.get_mut(worker_id) else {
+            bail!("unknown worker {}", worker_id);
+        };
+        let Some(index) = worker.running.iter().position(|task| task.id == task_id) else {
+            bail!("worker {} is not running {}", worker_id, task_id);
+        };
+        worker.running.remove(index);
+        if success {
+            self.completed += 1;
+        } else {
+            self.failed.push(task_id.to_string());
+        }
+        self.publish();
+        Ok(())
+    }
+
+    /// Remove `worker_id` and re-queue its tasks on the remaining workers. Returns the ids of
+    /// the running tasks it interrupted that were given up on.
+    pub fn disconnect(&mut self, worker_id: &str) -> Vec<String> {
+        let Some(worker) = self.workers.remove(worker_id) else { return Vec::new() };
+        warn!(
+            worker = worker_id,
+            running = worker.running.len(),
+            queued = worker.queue.len(),
+            "🔌 Worker disconnected, re-queueing its tasks"
+        );
+        let mut abandoned = Vec::new();
+        for mut task in worker.running {
+            task.attempts += 1;
+            if task.attempts >= self.max_attempts {
+                warn!(task = %task.id, attempts = task.attempts, "❌ Giving up on task");
+                abandoned.push(task.id.clone());
+                self.failed.push(task.id);
+                continue;
+            }
+            self.requeued += 1;
+            metrics::task_requeued();
+            self.place(task);
+        }
+        for task in worker.queue {
+            self.place(task);
+        }
+        self.publish();
+        abandoned
+    }
+
+    /// Ids of tasks that failed or were given up on
+    pub fn failed_tasks(&self) -> &[String] {
+        &self.failed
+    }
+
+    /// Whether every submitted task completed or failed
+    pub fn is_idle(&self) -> bool {
+        self.pending.is_empty()
+            && self
+                .workers
+                .values()
+                .all(|worker| worker.queue.is_empty() && worker.running.is_empty())
+    }
+
+    pub fn metrics(&self) -> QueueMetrics {
+        let workers: Vec<WorkerLoad> = self
+            .workers
+            .iter()
+            .map(|(id, worker)| WorkerLoad {
+                id: id.clone(),
+                capacity: worker.capacity,
+                in_use: worker.in_use(),
+                queued: worker.queue.len(),
+                running: worker.running.len(),
+            })
+            .collect();
+        QueueMetrics {
+            pending: self.pending.len(),
+            queued: workers.iter().map(|w| w.queued).sum(),
+            running: workers.iter().map(|w| w.running).sum(),
+            completed: self.completed,
+            failed: self.failed.len(),
+            steals: self.steals,
+            requeued: self.requeued,
+            workers,
+        }
+    }
+
+    fn publish(&self) {
+        let metrics = self.metrics();
+        metrics::scheduler_depth(
+            metrics.pending,
+            metrics.queued,
+            metrics.running,
+            metrics.workers.len(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(language: &str) -> LanguageTask {
+        LanguageTask {
+            name: None,
+            language: language.to_string(),
+            command: "true".to_string(),
+            args: Vec::new(),
+            working_dir: None,
+            timeout_seconds: None,
+            depends_on: Vec::new(),
+            image: None,
+            resources: None,
+            outputs: Vec::new(),
+            inputs_from: Vec::new(),
+            env: Default::default(),
+            policy: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_assigns_by_capacity_and_steals_when_idle() {
+        let mut scheduler = WorkStealingScheduler::new();
+        scheduler.register_worker("big", Resources::new(8, 16_384));
+        scheduler.register_worker("small", Resources::new(2, 2_048));
+
+        let heavy = scheduler.submit(task("rust"), Resources::new(4, 8_192));
+        for _ in 0..4 {
+            scheduler.submit(task("go"), Resources::new(1, 512));
+        }
+        let loads = scheduler.metrics().workers;
+        assert_eq!((loads[0].id.as_str(), loads[0].queued), ("big", 4));
+        assert_eq!((loads[1].id.as_str(), loads[1].queued), ("small", 1));
+
+        // The small worker drains its own queue, then steals from the big one but never the
+        // task it has no memory for
+        let own = scheduler.next_task("small").unwrap();
+        assert!(own.stolen_from.is_none());
+        scheduler.complete("small", &own.task.id, true).unwrap();
+        let stolen = scheduler.next_task("small").unwrap();
+        assert_eq!(stolen.stolen_from.as_deref(), Some("big"));
+        assert_ne!(stolen.task.id, heavy);
+        assert_eq!(scheduler.metrics().steals, 1);
+        assert!(scheduler.complete("big", &stolen.task.id, true).is_err());
+    }
+
+    #[test]
+    fn test_requeues_tasks_of_disconnected_workers() {
+        let mut scheduler = WorkStealingScheduler::new().with_max_attempts(2);
+        scheduler.register_worker("a", Resources::new(4, 4_096));
+        let id = scheduler.submit(task("python"), Resources::new(2, 1_024));
+        scheduler.submit(task("python"), Resources::new(2, 1_024));
+        assert_eq!(scheduler.next_task("a").unwrap().task.id, id);
+
+        assert!(scheduler.disconnect("a").is_empty());
+        let metrics = scheduler.metrics();
+        assert_eq!((metrics.pending, metrics.requeued), (2, 1));
+
+        scheduler.register_worker("b", Resources::new(4, 4_096));
+        let retry = scheduler.next_task("b").unwrap();
+        assert_eq!((retry.task.id.as_str(), retry.task.attempts), (id.as_str(), 1));
+        assert_eq!(scheduler.disconnect("b"), [id]);
+
+        scheduler.register_worker("c", Resources::new(4, 4_096));
+        let last = scheduler.next_task("c").unwrap();
+        scheduler.complete("c", &last.task.id, true).unwrap();
+        assert!(scheduler.is_idle());
+        assert_eq!(scheduler.metrics().completed, 1);
+        assert_eq!(scheduler.failed_tasks().len(), 1);
+    }
+}
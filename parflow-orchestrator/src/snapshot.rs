@@ -0,0 +1,272 @@
+//! What tasks write to the workspace
+//!
+//! When a workflow tracks changes, the workspace (the directory parflow runs in) is snapshotted
+//! before and after each task, and the task's result lists the files it created, modified and
+//! deleted. A task can declare the files it is expected to produce as glob patterns relative to
+//! the workspace:
+//!
+//! ```yaml
+//! track_changes: true
+//! tasks:
+//!   - name: web-build
+//!     language: Node.js
+//!     command: npm
+//!     args: [run, build]
+//!     outputs: ["dist/**/*.js"]
+//! ```
+//!
+//! In output patterns `*` matches within one directory and `**` across any number of them. A
+//! task that exits successfully but writes nothing matching one of its outputs is reported as
+//! failed. Declaring outputs turns tracking on for the whole workflow. Tasks the backend only
+//! simulates, see [`ExecutionBackend::runs`](crate::ExecutionBackend::runs), are not tracked.
+//!
+//! Files are compared by content: a snapshot records each file's modification time, size and
+//! BLAKE3 hash, and only rehashes files whose time or size changed since the previous snapshot.
+//! Tasks of a concurrent workflow are credited with every change made while they ran, including
+//! those of the tasks running beside them. Version control metadata, dependency and build
+//! caches ([`IGNORED_DIRS`]) are left out, except for the directories output patterns name
+//! literally: `target/release/server` has `target/release` snapshotted, without its
+//! subdirectories.
+
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobMatcher};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use tracing::warn;
+
+/// Directories that are never snapshotted
+pub const IGNORED_DIRS: &[&str] =
+    &[".git", ".parflow", "target", "node_modules", "__pycache__", ".pytest_cache", ".venv"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileState {
+    pub modified: Option<SystemTime>,
+    pub size: u64,
+    pub hash: blake3::Hash,
+}
+
+/// Every file of a workspace, keyed by its `/`-separated path relative to the workspace
+#[derive(Debug, Clone, Default)]
+pub struct WorkspaceSnapshot {
+    pub files: BTreeMap<String, FileState>,
+}
+
+/// Files that differ between two snapshots, sorted by path
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileChanges {
+    #[serde(default)]
+    pub created: Vec<String>,
+    #[serde(default)]
+    pub modified: Vec<String>,
+    #[serde(default)]
+    pub deleted: Vec<String>,
+}
+
+impl FileChanges {
+    pub fn is_empty(&self) -> bool {
+        self.created.is_empty() && self.modified.is_empty() && self.deleted.is_empty()
+    }
+
+    /// Created and modified files
+    pub fn written(&self) -> impl Iterator<Item = &String> {
+        self.created.iter().chain(&self.modified)
+    }
+
+    /// The patterns of `outputs` that no written file matches
+    pub fn missing_outputs(&self, outputs: &[String]) -> Result<Vec<String>> {
+        let mut missing = Vec::new();
+        for pattern in outputs {
+            let matcher = output_matcher(pattern)?;
+            if !self.written().any(|path| matcher.is_match(path)) {
+                missing.push(pattern.clone());
+            }
+        }
+        Ok(missing)
+    }
+}
+
+impl WorkspaceSnapshot {
+    /// Snapshot the files under `root` and those matching the output patterns `outputs`,
+    /// reusing the hashes of `previous` for files whose modification time and size did not
+    /// change.
+    pub fn capture(
+        root: &Path,
+        outputs: &[String],
+        previous: Option<&WorkspaceSnapshot>,
+    ) -> Result<Self> {
+        let mut files = workspace_files(root)?;
+        for pattern in outputs {
+            // Other outputs are in the workspace walk already
+            if literal_dir(pattern).split('/').any(|dir| IGNORED_DIRS.contains(&dir)) {
+                files.extend(output_files(root, pattern)?);
+            }
+        }
+        let mut snapshot = Self::default();
+        for (key, path) in files {
+            // Files removed while walking are simply missed
+            let Ok(metadata) = std::fs::metadata(&path) else { continue };
+            let (modified, size) = (metadata.modified().ok(), metadata.len());
+            let unchanged = previous
+                .and_then(|previous| previous.files.get(&key))
+                .filter(|state| state.modified.is_some() && state.modified == modified)
+                .filter(|state| state.size == size);
+            let hash = match unchanged {
+                Some(state) => state.hash,
+                None => match std::fs::read(&path) {
+                    Ok(data) => blake3::hash(&data),
+                    Err(_) => continue,
+                },
+            };
+            snapshot.files.insert(key, FileState { modified, size, hash });
+        }
+        Ok(snapshot)
+    }
+
+    /// What changed from this snapshot to `after`
+    pub fn diff(&self, after: &WorkspaceSnapshot) -> FileChanges {
+        let mut changes = FileChanges::default();
+        for (path, state) in &after.files {
+            match self.files.get(path) {
+                None => changes.created.push(path.clone()),
+                Some(before) if before.hash != state.hash => changes.modified.push(path.clone()),
+                Some(_) => {}
+            }
+        }
+        changes.deleted =
+            self.files.keys().filter(|path| !after.files.contains_key(*path)).cloned().collect();
+        changes
+    }
+}
+
+/// Matches paths against the output `pattern`
+pub fn output_matcher(pattern: &str) -> Result<GlobMatcher> {
+    let glob = GlobBuilder::new(pattern)
+        .literal_separator(true)
+        .build()
+        .with_context(|| format!("invalid output pattern '{}'", pattern))?;
+    Ok(glob.compile_matcher())
+}
+
+/// The directory `pattern` names before its first wildcard, with a trailing `/` unless empty
+pub fn literal_dir(pattern: &str) -> &str {
+    let literal = pattern.find(['*', '?', '[', '{']).map_or(pattern, |end| &pattern[..end]);
+    literal.rfind('/').map_or("", |slash| &pattern[..=slash])
+}
+
+/// The files under `root` outside [`IGNORED_DIRS`], as their `/`-separated path relative to
+/// `root` and their full path
+pub fn workspace_files(root: &Path) -> Result<Vec<(String, PathBuf)>> {
+    walk(root, "", true)
+}
+
+/// The files under `root` matching the output `pattern`, also inside [`IGNORED_DIRS`] when
+/// the pattern names them literally
+pub fn output_files(root: &Path, pattern: &str) -> Result<Vec<(String, PathBuf)>> {
+    let matcher = output_matcher(pattern)?;
+    let dir = literal_dir(pattern);
+    if !root.join(dir).is_dir() {
+        return Ok(Vec::new());
+    }
+    let rest = &pattern[dir.len()..];
+    let files = walk(root, dir, rest.contains('/') || rest.contains("**"))?;
+    Ok(files.into_iter().filter(|(key, _)| matcher.is_match(key)).collect())
+}
+
+/// The files in `start`, a `/`-terminated directory relative to `root` or empty, and with
+/// `recurse` in its subdirectories outside [`IGNORED_DIRS`]
+fn walk(root: &Path, start: &str, recurse: bool) -> Result<Vec<(String, PathBuf)>> {
+    let mut files = Vec::new();
+    let mut pending = vec![start.to_string()];
+    while let Some(relative) = pending.pop() {
+        let dir = root.join(&relative);
+        let entries =
+            std::fs::read_dir(&dir).with_context(|| format!("reading {}", dir.display()))?;
+        for entry in entries {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() && recurse && !IGNORED_DIRS.contains(&name.as_str()) {
+                pending.push(format!("{}{}/", relative, name));
+            } else if file_type.is_file() {
+                files.push((format!("{}{}", relative, name), entry.path()));
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Snapshots one workspace for the tasks of a workflow, keeping the latest snapshot so each
+/// capture only hashes what changed since the one before.
+#[derive(Debug)]
+pub struct ChangeTracker {
+    root: PathBuf,
+    /// Output patterns declared in the workflow
+    outputs: Vec<String>,
+    latest: Mutex<Option<WorkspaceSnapshot>>,
+}
+
+impl ChangeTracker {
+    pub fn new(root: impl Into<PathBuf>, outputs: Vec<String>) -> Self {
+        Self { root: root.into(), outputs, latest: Mutex::new(None) }
+    }
+
+    /// Snapshot the workspace, or `None` when it cannot be read
+    pub async fn capture(self: &Arc<Self>) -> Option<WorkspaceSnapshot> {
+        let tracker = self.clone();
+        let captured = tokio::task::spawn_blocking(move || {
+            let previous = tracker.latest.lock().unwrap().clone();
+            let snapshot =
+                WorkspaceSnapshot::capture(&tracker.root, &tracker.outputs, previous.as_ref())?;
+            *tracker.latest.lock().unwrap() = Some(snapshot.clone());
+            Ok::<_, anyhow::Error>(snapshot)
+        })
+        .await;
+        match captured.map_err(anyhow::Error::from).and_then(|captured| captured) {
+            Ok(snapshot) => Some(snapshot),
+            Err(e) => {
+                warn!(error = %e, "⚠️  Cannot snapshot the workspace");
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_reports_written_files_and_missing_outputs() {
+        let dir = std::env::temp_dir().join(format!("parflow-snapshot-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::create_dir_all(dir.join("target")).unwrap();
+        std::fs::write(dir.join("src/main.rs"), "fn main() {}").unwrap();
+        std::fs::write(dir.join("README.md"), "hello").unwrap();
+        std::fs::write(dir.join("notes.txt"), "draft").unwrap();
+        let outputs = ["dist/*.js".to_string(), "target/app".to_string(), "*.md".to_string()];
+        let before = WorkspaceSnapshot::capture(&dir, &outputs[..2], None).unwrap();
+
+        std::fs::create_dir_all(dir.join("dist")).unwrap();
+        std::fs::write(dir.join("dist/app.js"), "run()").unwrap();
+        std::fs::write(dir.join("target/app"), "binary").unwrap();
+        std::fs::write(dir.join("src/main.rs"), "fn main() { run() }").unwrap();
+        std::fs::remove_file(dir.join("notes.txt")).unwrap();
+        // Touching a file without changing it is not a modification
+        std::fs::write(dir.join("README.md"), "hello").unwrap();
+        std::fs::write(dir.join("target/debug.log"), "not an output").unwrap();
+        std::fs::create_dir_all(dir.join("docs")).unwrap();
+        std::fs::write(dir.join("docs/guide.md"), "# Guide").unwrap();
+        let after = WorkspaceSnapshot::capture(&dir, &outputs[..2], Some(&before)).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let changes = before.diff(&after);
+        assert_eq!(changes.created, ["dist/app.js", "docs/guide.md", "target/app"]);
+        assert_eq!(changes.modified, ["src/main.rs"]);
+        assert_eq!(changes.deleted, ["notes.txt"]);
+        // `*` stays in the top directory
+        assert_eq!(changes.missing_outputs(&outputs).unwrap(), ["*.md"]);
+    }
+}
@@ -0,0 +1,124 @@
+//! Passing files between tasks
+//!
+//! A task takes the declared `outputs` of other tasks with `inputs_from`. It waits for those
+//! tasks like it does for `depends_on`, and before it starts their outputs are copied into
+//! `.parflow/inputs/<task>/` under its working directory:
+//!
+//! ```yaml
+//! tasks:
+//!   - name: server-build
+//!     language: Rust
+//!     command: cargo
+//!     args: [build, --release]
+//!     outputs: [target/release/server]
+//!   - name: integration-test
+//!     language: Python
+//!     command: python
+//!     args: [-m, pytest, tests]
+//!     working_dir: integration
+//!     inputs_from: [server-build]
+//! ```
+//!
+//! Files keep their path below the directory the output pattern names literally, so the test
+//! above finds the binary at `integration/.parflow/inputs/server-build/server`, and
+//! `dist/**/*.js` would stage `dist/app/main.js` as `app/main.js`. The directory relative to
+//! the working directory is also in the task's environment as `PARFLOW_INPUTS_<TASK>`, the task
+//! name uppercased with other characters than letters and digits turned into `_`
+//! (`PARFLOW_INPUTS_SERVER_BUILD`).
+
+use crate::snapshot;
+use crate::LanguageTask;
+use anyhow::{Context, Result};
+use std::path::Path;
+
+/// Where inputs are staged, relative to the working directory of the task taking them
+pub const INPUTS_DIR: &str = ".parflow/inputs";
+
+/// Name of the environment variable holding the inputs staged from `task`
+pub fn env_var(task: &str) -> String {
+    let name: String = task
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect();
+    format!("PARFLOW_INPUTS_{}", name)
+}
+
+/// Copy the outputs of each `(task, output patterns)` of `inputs` from the workspace at `root`
+/// into the working directory of `task`, and point its environment at them. Returns the
+/// number of files copied.
+pub fn stage_inputs(
+    root: &Path,
+    task: &mut LanguageTask,
+    inputs: &[(String, Vec<String>)],
+) -> Result<usize> {
+    if inputs.is_empty() {
+        return Ok(0);
+    }
+    let working_dir = match &task.working_dir {
+        Some(dir) => root.join(dir),
+        None => root.to_path_buf(),
+    };
+    let mut staged = 0;
+    for (upstream, outputs) in inputs {
+        if outputs.is_empty() {
+            anyhow::bail!("'{}' declares no outputs to take inputs from", upstream);
+        }
+        let relative = format!("{}/{}", INPUTS_DIR, upstream);
+        let destination = working_dir.join(&relative);
+        // Inputs of an earlier run would otherwise linger next to the new ones
+        if destination.exists() {
+            std::fs::remove_dir_all(&destination)
+                .with_context(|| format!("clearing {}", destination.display()))?;
+        }
+        for pattern in outputs {
+            let prefix = snapshot::literal_dir(pattern);
+            for (key, path) in snapshot::output_files(root, pattern)? {
+                let target = destination.join(key.strip_prefix(prefix).unwrap_or(&key));
+                if let Some(parent) = target.parent() {
+                    std::fs::create_dir_all(parent)
+                        .with_context(|| format!("creating {}", parent.display()))?;
+                }
+                std::fs::copy(&path, &target)
+                    .with_context(|| format!("copying {} to {}", key, target.display()))?;
+                staged += 1;
+            }
+        }
+        task.env.insert(env_var(upstream), relative);
+    }
+    Ok(staged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stages_outputs_below_their_literal_directory() {
+        let root = std::env::temp_dir().join(format!("parflow-staging-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("target/release")).unwrap();
+        std::fs::create_dir_all(root.join("dist/app")).unwrap();
+        std::fs::create_dir_all(root.join("tests/.parflow/inputs/build/stale")).unwrap();
+        std::fs::write(root.join("target/release/server"), "binary").unwrap();
+        std::fs::write(root.join("dist/app/main.js"), "run()").unwrap();
+        let mut task: LanguageTask = serde_yaml::from_str(
+            "{ language: Python, command: pytest, args: [], working_dir: tests, \
+               inputs_from: [build] }",
+        )
+        .unwrap();
+        let outputs = vec!["target/release/server".to_string(), "dist/**/*.js".to_string()];
+
+        let staged = stage_inputs(&root, &mut task, &[("build".to_string(), outputs)]).unwrap();
+        let inputs = root.join("tests/.parflow/inputs/build");
+        let (server, script) = (inputs.join("server"), inputs.join("app/main.js"));
+        let (server, script) = (std::fs::read_to_string(server), std::fs::read_to_string(script));
+        let stale = inputs.join("stale").exists();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(staged, 2);
+        assert_eq!(server.unwrap(), "binary");
+        assert_eq!(script.unwrap(), "run()");
+        assert!(!stale);
+        assert_eq!(task.env["PARFLOW_INPUTS_BUILD"], ".parflow/inputs/build");
+        assert_eq!(env_var("server-build"), "PARFLOW_INPUTS_SERVER_BUILD");
+    }
+}
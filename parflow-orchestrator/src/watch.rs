@@ -0,0 +1,201 @@
+//! Re-running workflow tasks when files change
+//!
+//! The `watch` section of a workflow maps glob patterns, relative to the watched directory, to
+//! the names of the tasks a change should re-run:
+//!
+//! ```yaml
+//! watch:
+//!   rules:
+//!     - pattern: "api/**/*.rs"
+//!       tasks: [api-build]
+//!     - pattern: "worker/**/*.py"
+//!       tasks: [worker-test]
+//!   debounce_ms:
+//!     Rust: 2000
+//! ```
+//!
+//! Without rules, any change re-runs every task. Editors and build tools tend to write several
+//! files at once, so a task only runs once changes to its files have settled for the debounce of
+//! its language ([`default_debounce`] unless the workflow sets one).
+
+use crate::MultiLanguageWorkflow;
+use anyhow::{bail, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Component, Path};
+use std::time::{Duration, Instant};
+
+/// Directories whose changes never trigger a run, since tasks write to them
+pub const IGNORED_DIRS: &[&str] =
+    &[".git", ".parflow", "target", "node_modules", "__pycache__", ".pytest_cache", "dist"];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchConfig {
+    #[serde(default)]
+    pub rules: Vec<WatchRule>,
+    /// Debounce per language, in milliseconds
+    #[serde(default)]
+    pub debounce_ms: BTreeMap<String, u64>,
+}
+
+impl WatchConfig {
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty() && self.debounce_ms.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchRule {
+    pub pattern: String,
+    /// Names of the tasks to re-run, see [`LanguageTask::name`](crate::LanguageTask::name)
+    pub tasks: Vec<String>,
+}
+
+/// How long changes for a task of `language` must settle before it runs. Rust builds take the
+/// longest, so they wait for a burst of saves to finish before starting.
+pub fn default_debounce(language: &str) -> Duration {
+    match language.to_ascii_lowercase().as_str() {
+        "rust" => Duration::from_millis(1000),
+        "go" | "java" | "c++" | "cpp" => Duration::from_millis(700),
+        _ => Duration::from_millis(300),
+    }
+}
+
+/// The watch section of a workflow, checked against its tasks and ready to match paths
+#[derive(Debug)]
+pub struct WatchPlan {
+    globs: GlobSet,
+    /// Task indexes of each glob in `globs`
+    targets: Vec<Vec<usize>>,
+    task_count: usize,
+    debounce: Vec<Duration>,
+}
+
+impl WatchPlan {
+    pub fn new(workflow: &MultiLanguageWorkflow) -> Result<Self> {
+        let names: Vec<String> = workflow.tasks.iter().map(|task| task.name()).collect();
+        let mut builder = GlobSetBuilder::new();
+        let mut targets = Vec::new();
+        for rule in &workflow.watch.rules {
+            builder.add(Glob::new(&rule.pattern)?);
+            let mut indexes = Vec::new();
+            for task in &rule.tasks {
+                let matching: Vec<usize> =
+                    (0..names.len()).filter(|&i| names[i] == *task).collect();
+                if matching.is_empty() {
+                    bail!("watch rule '{}' names unknown task '{}'", rule.pattern, task);
+                }
+                indexes.extend(matching);
+            }
+            targets.push(indexes);
+        }
+        let debounce = workflow
+            .tasks
+            .iter()
+            .map(|task| match workflow.watch.debounce_ms.get(&task.language) {
+                Some(ms) => Duration::from_millis(*ms),
+                None => default_debounce(&task.language),
+            })
+            .collect();
+        Ok(Self { globs: builder.build()?, targets, task_count: names.len(), debounce })
+    }
+
+    /// Indexes of the tasks a change to `path`, relative to the watched directory, re-runs
+    pub fn tasks_for(&self, path: &Path) -> Vec<usize> {
+        let ignored = path.components().any(|component| match component {
+            Component::Normal(name) => IGNORED_DIRS.iter().any(|dir| name == *dir),
+            _ => false,
+        });
+        if ignored {
+            return Vec::new();
+        }
+        if self.targets.is_empty() {
+            return (0..self.task_count).collect();
+        }
+        let mut tasks: Vec<usize> =
+            self.globs.matches(path).into_iter().flat_map(|i| self.targets[i].clone()).collect();
+        tasks.sort_unstable();
+        tasks.dedup();
+        tasks
+    }
+}
+
+/// Tasks waiting for their changes to settle
+#[derive(Debug, Default)]
+pub struct Debouncer {
+    due: HashMap<usize, Instant>,
+}
+
+impl Debouncer {
+    /// Note a change for `tasks` at `now`, pushing back when they run.
+    pub fn record(&mut self, plan: &WatchPlan, tasks: &[usize], now: Instant) {
+        for &task in tasks {
+            self.due.insert(task, now + plan.debounce[task]);
+        }
+    }
+
+    /// When the next task is due, if any is waiting
+    pub fn next_due(&self) -> Option<Instant> {
+        self.due.values().min().copied()
+    }
+
+    /// Remove and return the tasks due by `now`, in workflow order.
+    pub fn take_due(&mut self, now: Instant) -> Vec<usize> {
+        let mut due: Vec<usize> =
+            self.due.iter().filter(|(_, at)| **at <= now).map(|(task, _)| *task).collect();
+        due.sort_unstable();
+        for task in &due {
+            self.due.remove(task);
+        }
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_changes_to_tasks_and_debounces_per_language() {
+        let workflow: MultiLanguageWorkflow = serde_yaml::from_str(
+            r#"
+name: demo
+concurrent: true
+tasks:
+  - { name: api-build, language: Rust, command: cargo, args: [build] }
+  - { language: Python, command: python, args: [-m, pytest] }
+watch:
+  rules:
+    - { pattern: "api/**/*.rs", tasks: [api-build] }
+    - { pattern: "**/*.py", tasks: [Python_task] }
+    - { pattern: "shared/*", tasks: [api-build, Python_task] }
+  debounce_ms: { Python: 50 }
+"#,
+        )
+        .unwrap();
+        let plan = WatchPlan::new(&workflow).unwrap();
+        assert_eq!(plan.tasks_for(Path::new("api/src/main.rs")), [0]);
+        assert_eq!(plan.tasks_for(Path::new("worker/jobs.py")), [1]);
+        assert_eq!(plan.tasks_for(Path::new("shared/schema.json")), [0, 1]);
+        assert!(plan.tasks_for(Path::new("README.md")).is_empty());
+        assert!(plan.tasks_for(Path::new("api/target/debug/build.rs")).is_empty());
+
+        let start = Instant::now();
+        let mut debouncer = Debouncer::default();
+        debouncer.record(&plan, &[0, 1], start);
+        assert_eq!(debouncer.next_due(), Some(start + Duration::from_millis(50)));
+        assert_eq!(debouncer.take_due(start + Duration::from_millis(100)), [1]);
+        // Another save pushes the Rust build back
+        debouncer.record(&plan, &[0], start + Duration::from_millis(900));
+        assert!(debouncer.take_due(start + Duration::from_millis(1000)).is_empty());
+        assert_eq!(debouncer.take_due(start + Duration::from_millis(1900)), [0]);
+        assert_eq!(debouncer.next_due(), None);
+
+        let mut typo = workflow;
+        typo.watch.rules[0].tasks = vec!["api-biuld".to_string()];
+        assert!(WatchPlan::new(&typo).is_err());
+        typo.watch.rules.clear();
+        assert_eq!(WatchPlan::new(&typo).unwrap().tasks_for(Path::new("README.md")), [0, 1]);
+    }
+}
@@ -0,0 +1,505 @@
+//! Third-party analyzers and transpiler passes loaded as WebAssembly modules
+//!
+//! Organizations that need proprietary language rules ship them as a `.wasm` file instead of
+//! forking parflow. A plugin is a core WebAssembly module that talks JSON across a small, stable
+//! ABI; strings cross the boundary as a pointer and length into the module's memory, and results
+//! come back packed into one `i64` as `ptr << 32 | len`.
+//!
+//! | export                           | signature           | purpose                          |
+//! |----------------------------------|---------------------|----------------------------------|
+//! | `memory`                         | memory              | buffer for requests and results  |
+//! | `parflow_abi_version`            | `() -> i32`         | must return [`ABI_VERSION`]      |
+//! | `parflow_alloc`                  | `(len) -> ptr`      | room for a request of `len` bytes |
+//! | `parflow_manifest`               | `() -> i64`         | [`Manifest`] as JSON             |
+//! | `parflow_analyze` (optional)     | `(ptr, len) -> i64` | [`AnalyzeRequest`] in, `[`[`Finding`]`]` out |
+//! | `parflow_transpile` (optional)   | `(ptr, len) -> i64` | [`TranspileRequest`] in, [`TranspileResponse`] out |
+//!
+//! Every call runs with a fuel budget, so a plugin stuck in a loop fails the call instead of
+//! hanging parflow, and a plugin's memory is capped at [`MEMORY_LIMIT`], so one that keeps
+//! growing it fails the call instead of exhausting the host. [`load_dir`] loads every plugin under `.parflow/plugins` and
+//! [`WasmPlugin::register`] adds the languages it declares to the global
+//! [`parflow_core::languages`] registry, which routes transpiles through the plugin.
+
+use anyhow::{bail, Context};
+use parflow_core::languages::{self, LanguagePlugin};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use tracing::warn;
+use wasmtime::{
+    Config, Engine, Instance, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc,
+};
+
+/// Version of the ABI this host speaks; plugins built for another version are rejected
+pub const ABI_VERSION: i32 = 1;
+
+/// Where `parflow` looks for plugins inside a project
+pub const PLUGIN_DIR: &str = ".parflow/plugins";
+
+/// Instructions a single plugin call may execute
+const FUEL_PER_CALL: u64 = 1_000_000_000;
+
+/// Bytes of linear memory a plugin may grow to
+pub const MEMORY_LIMIT: usize = 256 << 20;
+
+/// A language the plugin adds, or overrides when the name matches a builtin
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LanguageSpec {
+    pub name: String,
+    pub extensions: Vec<String>,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub function_keywords: Vec<String>,
+}
+
+/// A transpiler pass from one language to another
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TranspilePass {
+    pub from: String,
+    pub to: String,
+}
+
+/// What a plugin declares about itself through `parflow_manifest`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub languages: Vec<LanguageSpec>,
+    /// Languages whose files are sent to `parflow_analyze`
+    #[serde(default)]
+    pub analyzes: Vec<String>,
+    #[serde(default)]
+    pub transpiles: Vec<TranspilePass>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnalyzeRequest<'a> {
+    pub path: &'a str,
+    pub language: &'a str,
+    pub code: &'a str,
+}
+
+/// One diagnostic reported by a plugin analyzer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Finding {
+    /// Filled in by the host with the plugin's name
+    #[serde(default)]
+    pub plugin: String,
+    #[serde(default)]
+    pub path: String,
+    pub rule: String,
+    #[serde(default)]
+    pub line: usize,
+    pub message: String,
+    #[serde(default = "default_severity")]
+    pub severity: String,
+}
+
+fn default_severity() -> String {
+    "warning".to_string()
+}
+
+#[derive(Debug, Serialize)]
+pub struct TranspileRequest<'a> {
+    pub code: &'a str,
+    pub from: &'a str,
+    pub to: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TranspileResponse {
+    pub code: Option<String>,
+    pub error: Option<String>,
+}
+
+struct Runtime {
+    store: Store<StoreLimits>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    analyze: Option<TypedFunc<(i32, i32), i64>>,
+    transpile: Option<TypedFunc<(i32, i32), i64>>,
+}
+
+impl Runtime {
+    fn refuel(&mut self) -> anyhow::Result<()> {
+        self.store.set_fuel(FUEL_PER_CALL)
+    }
+
+    fn read(&mut self, packed: i64) -> anyhow::Result<Vec<u8>> {
+        let (ptr, len) = ((packed as u64 >> 32) as usize, (packed as u64 & 0xffff_ffff) as usize);
+        let data = self.memory.data(&self.store);
+        match data.get(ptr..ptr + len) {
+            Some(bytes) => Ok(bytes.to_vec()),
+            None => bail!("result at {}..{} is outside the plugin's memory", ptr, ptr + len),
+        }
+    }
+
+    /// Copy `request` into the plugin, call `func` on it and parse the JSON it returns
+    fn call<T: for<'de> Deserialize<'de>>(
+        &mut self,
+        func: TypedFunc<(i32, i32), i64>,
+        request: &impl Serialize,
+    ) -> anyhow::Result<T> {
+        let request = serde_json::to_vec(request)?;
+        self.refuel()?;
+        let ptr = self.alloc.call(&mut self.store, request.len() as i32)?;
+        self.memory.write(&mut self.store, ptr as usize, &request)?;
+        let packed = func.call(&mut self.store, (ptr, request.len() as i32))?;
+        let response = self.read(packed)?;
+        Ok(serde_json::from_slice(&response)?)
+    }
+}
+
+/// A loaded plugin; calls into it are serialized, since a module instance is single-threaded
+pub struct WasmPlugin {
+    manifest: Manifest,
+    path: PathBuf,
+    runtime: Mutex<Runtime>,
+}
+
+fn engine() -> &'static Engine {
+    static ENGINE: OnceLock<Engine> = OnceLock::new();
+    ENGINE.get_or_init(|| {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        Engine::new(&config).expect("default wasmtime configuration")
+    })
+}
+
+impl WasmPlugin {
+    /// Load a plugin from a `.wasm` file
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+        Self::from_bytes(&bytes, path).with_context(|| format!("loading {}", path.display()))
+    }
+
+    /// Load a plugin from the bytes of a module; `path` is only used in messages
+    pub fn from_bytes(bytes: &[u8], path: &Path) -> anyhow::Result<Self> {
+        let module = Module::new(engine(), bytes)?;
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(MEMORY_LIMIT)
+            .instances(1)
+            .trap_on_grow_failure(true)
+            .build();
+        let mut store = Store::new(engine(), limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(FUEL_PER_CALL)?;
+        let instance = Instance::new(&mut store, &module, &[])?;
+
+        let version = instance
+            .get_typed_func::<(), i32>(&mut store, "parflow_abi_version")
+            .context("missing export parflow_abi_version")?
+            .call(&mut store, ())?;
+        if version != ABI_VERSION {
+            bail!("plugin speaks ABI version {}, this parflow speaks {}", version, ABI_VERSION);
+        }
+        let memory = instance.get_memory(&mut store, "memory").context("missing export memory")?;
+        let alloc = instance
+            .get_typed_func(&mut store, "parflow_alloc")
+            .context("missing export parflow_alloc")?;
+        let manifest_fn = instance
+            .get_typed_func::<(), i64>(&mut store, "parflow_manifest")
+            .context("missing export parflow_manifest")?;
+        let analyze = instance.get_typed_func(&mut store, "parflow_analyze").ok();
+        let transpile = instance.get_typed_func(&mut store, "parflow_transpile").ok();
+
+        let mut runtime = Runtime { store, memory, alloc, analyze, transpile };
+        let packed = manifest_fn.call(&mut runtime.store, ())?;
+        let manifest: Manifest =
+            serde_json::from_slice(&runtime.read(packed)?).context("parsing the manifest")?;
+        if !manifest.analyzes.is_empty() && runtime.analyze.is_none() {
+            bail!("{} declares analyzers but does not export parflow_analyze", manifest.name);
+        }
+        if !manifest.transpiles.is_empty() && runtime.transpile.is_none() {
+            bail!(
+                "{} declares transpile passes but does not export parflow_transpile",
+                manifest.name
+            );
+        }
+        Ok(Self { manifest, path: path.to_path_buf(), runtime: Mutex::new(runtime) })
+    }
+
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
+
+    pub fn name(&self) -> &str {
+        &self.manifest.name
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the plugin has an analyzer for `language`
+    pub fn analyzes(&self, language: &str) -> bool {
+        self.manifest.analyzes.iter().any(|name| name.eq_ignore_ascii_case(language))
+    }
+
+    /// Run the plugin's analyzer over one file
+    pub fn analyze(&self, path: &str, language: &str, code: &str) -> anyhow::Result<Vec<Finding>> {
+        let mut runtime = self.runtime.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(func) = runtime.analyze.clone() else {
+            return Ok(Vec::new());
+        };
+        let mut findings: Vec<Finding> =
+            runtime.call(func, &AnalyzeRequest { path, language, code })?;
+        for finding in &mut findings {
+            finding.plugin = self.manifest.name.clone();
+            finding.path = path.to_string();
+        }
+        Ok(findings)
+    }
+
+    /// Run the plugin's pass from `from` to `to`; `Ok(None)` when it declares no such pass
+    pub fn transpile(&self, code: &str, from: &str, to: &str) -> anyhow::Result<Option<String>> {
+        let declared =
+            self.manifest.transpiles.iter().any(|pass| {
+                pass.from.eq_ignore_ascii_case(from) && pass.to.eq_ignore_ascii_case(to)
+            });
+        let mut runtime = self.runtime.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(func) = runtime.transpile.clone().filter(|_| declared) else {
+            return Ok(None);
+        };
+        let response: TranspileResponse =
+            runtime.call(func, &TranspileRequest { code, from, to })?;
+        match (response.code, response.error) {
+            (_, Some(error)) => bail!("{}: {}", self.manifest.name, error),
+            (code, None) => Ok(code),
+        }
+    }
+
+    /// Add the plugin's languages to the global registry, and its transpile passes to the
+    /// builtin languages they start from
+    pub fn register(self: &Arc<Self>) {
+        let registry = languages::registry();
+        let mut sources: Vec<String> =
+            self.manifest.transpiles.iter().map(|pass| pass.from.to_lowercase()).collect();
+        sources.sort();
+        sources.dedup();
+
+        for spec in &self.manifest.languages {
+            sources.retain(|source| *source != spec.name.to_lowercase());
+            let fallback = registry.get(&spec.name);
+            languages::register(WasmLanguage::new(spec, fallback, self.clone()));
+        }
+        for source in sources {
+            if let Some(builtin) = registry.get(&source) {
+                let spec = LanguageSpec {
+                    name: builtin.name().to_string(),
+                    extensions: builtin.extensions().iter().map(|e| e.to_string()).collect(),
+                    aliases: builtin.aliases().iter().map(|a| a.to_string()).collect(),
+                    function_keywords: Vec::new(),
+                };
+                languages::register(WasmLanguage::new(&spec, Some(builtin), self.clone()));
+            }
+        }
+    }
+}
+
+/// Strings handed to [`LanguagePlugin`], which wants them for the life of the process. Plugins
+/// are loaded once at startup, and interning keeps reloading from leaking the same name twice.
+fn intern(value: &str) -> &'static str {
+    static STRINGS: OnceLock<Mutex<HashMap<String, &'static str>>> = OnceLock::new();
+    let mut strings =
+        STRINGS.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+    strings
+        .entry(value.to_string())
+        .or_insert_with(|| Box::leak(value.to_string().into_boxed_str()))
+}
+
+fn intern_all(values: &[String]) -> &'static [&'static str] {
+    Box::leak(values.iter().map(|value| intern(value)).collect::<Vec<_>>().into_boxed_slice())
+}
+
+/// A language declared by a plugin; the project and toolchain hooks fall back to the language it
+/// replaces, so a plugin can add rules for Python without relearning how to run pytest
+struct WasmLanguage {
+    name: &'static str,
+    extensions: &'static [&'static str],
+    aliases: &'static [&'static str],
+    function_keywords: &'static [&'static str],
+    fallback: Option<Arc<dyn LanguagePlugin>>,
+    plugin: Arc<WasmPlugin>,
+}
+
+impl WasmLanguage {
+    fn new(
+        spec: &LanguageSpec,
+        fallback: Option<Arc<dyn LanguagePlugin>>,
+        plugin: Arc<WasmPlugin>,
+    ) -> Self {
+        let function_keywords = if spec.function_keywords.is_empty() {
+            fallback.as_ref().map(|language| language.function_keywords()).unwrap_or(&[])
+        } else {
+            intern_all(&spec.function_keywords)
+        };
+        Self {
+            name: intern(&spec.name.to_lowercase()),
+            extensions: intern_all(&spec.extensions),
+            aliases: intern_all(&spec.aliases),
+            function_keywords,
+            fallback,
+            plugin,
+        }
+    }
+}
+
+impl LanguagePlugin for WasmLanguage {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        self.extensions
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        self.aliases
+    }
+
+    fn project_markers(&self) -> &'static [&'static str] {
+        self.fallback.as_ref().map(|language| language.project_markers()).unwrap_or(&[])
+    }
+
+    fn toolchain(&self) -> Option<&'static str> {
+        self.fallback.as_ref().and_then(|language| language.toolchain())
+    }
+
+    fn build_command(&self, dir: &Path) -> Option<Vec<String>> {
+        self.fallback.as_ref().and_then(|language| language.build_command(dir))
+    }
+
+    fn test_command(&self, dir: &Path) -> Option<Vec<String>> {
+        self.fallback.as_ref().and_then(|language| language.test_command(dir))
+    }
+
+    fn function_keywords(&self) -> &'static [&'static str] {
+        self.function_keywords
+    }
+
+    fn transpile(&self, code: &str, target: &str) -> Option<String> {
+        match self.plugin.transpile(code, self.name, target) {
+            Ok(Some(code)) => Some(code),
+            Ok(None) => {
+                self.fallback.as_ref().and_then(|language| language.transpile(code, target))
+            }
+            Err(e) => {
+                warn!(plugin = self.plugin.name(), error = %e, "⚠️  Plugin failed to transpile");
+                None
+            }
+        }
+    }
+}
+
+/// Load every `.wasm` file in `dir`, sorted by name; a missing directory loads nothing
+pub fn load_dir(dir: impl AsRef<Path>) -> Vec<anyhow::Result<Arc<WasmPlugin>>> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir.as_ref())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "wasm"))
+        .collect();
+    paths.sort();
+    paths.into_iter().map(|path| WasmPlugin::load(path).map(Arc::new)).collect()
+}
+
+/// Run every plugin analyzer that covers `path`'s language
+pub fn analyze_file(
+    plugins: &[Arc<WasmPlugin>],
+    path: &str,
+    code: &str,
+) -> Vec<anyhow::Result<Vec<Finding>>> {
+    let Some(language) = languages::for_path(path) else {
+        return Vec::new();
+    };
+    plugins
+        .iter()
+        .filter(|plugin| plugin.analyzes(language.name()))
+        .map(|plugin| plugin.analyze(path, language.name(), code))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A plugin for a `.cob` language that flags every file and transpiles to Rust
+    const PLUGIN: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (global $next (mut i32) (i32.const 4096))
+          (data (i32.const 0) "{\"name\":\"acme-cobol\",\"version\":\"0.1.0\",\"languages\":[{\"name\":\"cobol\",\"extensions\":[\"cob\"],\"function_keywords\":[\"PROCEDURE \"]}],\"analyzes\":[\"cobol\"],\"transpiles\":[{\"from\":\"cobol\",\"to\":\"rust\"}]}")
+          (data (i32.const 1024) "[{\"rule\":\"acme/goto\",\"line\":3,\"message\":\"GO TO is banned\",\"severity\":\"error\"}]")
+          (data (i32.const 2048) "{\"code\":\"fn main() {}\"}")
+          (func (export "parflow_abi_version") (result i32) (i32.const 1))
+          (func (export "parflow_alloc") (param $len i32) (result i32)
+            (local $ptr i32)
+            (local.set $ptr (global.get $next))
+            (global.set $next (i32.add (global.get $next) (local.get $len)))
+            (local.get $ptr))
+          (func $pack (param $ptr i64) (param $len i64) (result i64)
+            (i64.or (i64.shl (local.get $ptr) (i64.const 32)) (local.get $len)))
+          (func (export "parflow_manifest") (result i64) (call $pack (i64.const 0) (i64.const 191)))
+          (func (export "parflow_analyze") (param i32 i32) (result i64)
+            (call $pack (i64.const 1024) (i64.const 78)))
+          (func (export "parflow_transpile") (param i32 i32) (result i64)
+            (call $pack (i64.const 2048) (i64.const 23))))
+    "#;
+
+    #[test]
+    fn test_wasm_plugin_adds_language_analyzer_and_transpiler() {
+        let bytes = wat::parse_str(PLUGIN).unwrap();
+        let plugin = Arc::new(WasmPlugin::from_bytes(&bytes, Path::new("acme.wasm")).unwrap());
+        assert_eq!(plugin.name(), "acme-cobol");
+        assert!(plugin.analyzes("COBOL"));
+        plugin.register();
+
+        let cobol = languages::for_path("payroll.cob").unwrap();
+        assert_eq!(cobol.name(), "cobol");
+        assert_eq!(cobol.function_keywords(), ["PROCEDURE "]);
+        assert_eq!(cobol.transpile("PROCEDURE DIVISION.", "rust").unwrap(), "fn main() {}");
+        assert!(cobol.transpile("PROCEDURE DIVISION.", "go").is_none());
+
+        let plugins = [plugin];
+        let results = analyze_file(&plugins, "payroll.cob", "GO TO END.");
+        let findings = results.into_iter().next().unwrap().unwrap();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].plugin, "acme-cobol");
+        assert_eq!(findings[0].path, "payroll.cob");
+        assert_eq!(findings[0].severity, "error");
+        assert!(analyze_file(&plugins, "main.py", "pass").is_empty());
+
+        let wrong_version =
+            PLUGIN.replace("(result i32) (i32.const 1)", "(result i32) (i32.const 7)");
+        let bytes = wat::parse_str(wrong_version).unwrap();
+        let error = WasmPlugin::from_bytes(&bytes, Path::new("old.wasm")).err().unwrap();
+        assert!(error.to_string().contains("ABI version 7"));
+    }
+
+    #[test]
+    fn test_plugin_memory_is_limited() {
+        let pages = MEMORY_LIMIT / 65536 + 1;
+        let huge = PLUGIN.replace(
+            r#"(memory (export "memory") 1)"#,
+            &format!(r#"(memory (export "memory") {})"#, pages),
+        );
+        let bytes = wat::parse_str(huge).unwrap();
+        assert!(WasmPlugin::from_bytes(&bytes, Path::new("huge.wasm")).is_err());
+
+        // Growing past the limit fails the call rather than the host
+        let growing = PLUGIN.replace(
+            "(local.get $ptr))",
+            &format!("(drop (memory.grow (i32.const {}))) (local.get $ptr))", pages),
+        );
+        let bytes = wat::parse_str(growing).unwrap();
+        let plugin = WasmPlugin::from_bytes(&bytes, Path::new("growing.wasm")).unwrap();
+        assert!(plugin.analyze("payroll.cob", "cobol", "GO TO END.").is_err());
+    }
+}
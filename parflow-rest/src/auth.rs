@@ -0,0 +1,218 @@
+//! Authentication middleware for the REST server.
+//!
+//! Clients authenticate with a static API key (`X-API-Key: <key>` or `Authorization: Bearer
+//! <key>`) or an HS256 JWT in the `Authorization` header. Each route is public, requires any
+//! valid credential, or requires a JWT scope; API keys are trusted with every scope.
+//!
+//! Configuration comes from the environment:
+//! - `PARFLOW_API_KEYS`: comma-separated static keys
+//! - `PARFLOW_JWT_SECRET`, optionally `PARFLOW_JWT_ISSUER` and `PARFLOW_JWT_AUDIENCE`
+//! - `PARFLOW_AUTH_ROUTES`: per-route policies, e.g. `/health=public,/par=scope:orchestrate`
+//!
+//! With neither keys nor a JWT secret configured, authentication is disabled.
+
+use axum::extract::{MatchedPath, State};
+use axum::http::{header, Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RoutePolicy {
+    Public,
+    Authenticated,
+    Scope(String),
+}
+
+impl std::str::FromStr for RoutePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "public" => Ok(Self::Public),
+            "authenticated" => Ok(Self::Authenticated),
+            _ => s
+                .strip_prefix("scope:")
+                .map(|scope| Self::Scope(scope.to_string()))
+                .ok_or_else(|| format!("unknown route policy {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Claims {
+    #[serde(default)]
+    scope: String,
+}
+
+/// Who made a request, inserted into request extensions for handlers.
+#[derive(Debug, Clone)]
+pub enum Principal {
+    ApiKey,
+    Token { scopes: HashSet<String> },
+}
+
+impl Principal {
+    fn has_scope(&self, scope: &str) -> bool {
+        match self {
+            Principal::ApiKey => true,
+            Principal::Token { scopes, .. } => scopes.contains(scope),
+        }
+    }
+}
+
+pub struct AuthConfig {
+    /// SHA-256 digests of the accepted API keys, so keys are not compared byte by byte
+    api_key_digests: HashSet<[u8; 32]>,
+    jwt: Option<(DecodingKey, Validation)>,
+    routes: HashMap<String, RoutePolicy>,
+}
+
+fn digest(key: &str) -> [u8; 32] {
+    Sha256::digest(key.as_bytes()).into()
+}
+
+impl AuthConfig {
+    pub fn new(api_keys: &[&str], jwt_secret: Option<&str>) -> Self {
+        let jwt = jwt_secret.map(|secret| {
+            let mut validation = Validation::new(Algorithm::HS256);
+            validation.required_spec_claims = HashSet::from(["exp".to_string()]);
+            (DecodingKey::from_secret(secret.as_bytes()), validation)
+        });
+
+        Self {
+            api_key_digests: api_keys.iter().map(|k| digest(k)).collect(),
+            jwt,
+            routes: HashMap::from([("/health".to_string(), RoutePolicy::Public)]),
+        }
+    }
+
+    pub fn from_env() -> Result<Self, String> {
+        let var = |name| std::env::var(name).ok().filter(|v: &String| !v.is_empty());
+
+        let keys = var("PARFLOW_API_KEYS").unwrap_or_default();
+        let keys: Vec<&str> = keys.split(',').map(str::trim).filter(|k| !k.is_empty()).collect();
+        let mut config = Self::new(&keys, var("PARFLOW_JWT_SECRET").as_deref());
+
+        if let Some((_, validation)) = &mut config.jwt {
+            if let Some(issuer) = var("PARFLOW_JWT_ISSUER") {
+                validation.set_issuer(&[issuer]);
+            }
+            if let Some(audience) = var("PARFLOW_JWT_AUDIENCE") {
+                validation.set_audience(&[audience]);
+            }
+        }
+
+        for rule in var("PARFLOW_AUTH_ROUTES").unwrap_or_default().split(',') {
+            if rule.trim().is_empty() {
+                continue;
+            }
+            let (route, policy) = rule
+                .split_once('=')
+                .ok_or_else(|| format!("invalid route policy {:?}, expected route=policy", rule))?;
+            config.routes.insert(route.trim().to_string(), policy.trim().parse()?);
+        }
+
+        Ok(config)
+    }
+
+    pub fn enabled(&self) -> bool {
+        !self.api_key_digests.is_empty() || self.jwt.is_some()
+    }
+
+    pub fn policy_for(&self, route: &str) -> &RoutePolicy {
+        self.routes.get(route).unwrap_or(&RoutePolicy::Authenticated)
+    }
+
+    /// Validate the credentials carried by a request's headers.
+    pub fn authenticate(&self, api_key: Option<&str>, bearer: Option<&str>) -> Option<Principal> {
+        for key in api_key.into_iter().chain(bearer) {
+            if self.api_key_digests.contains(&digest(key)) {
+                return Some(Principal::ApiKey);
+            }
+        }
+
+        let (key, validation) = self.jwt.as_ref()?;
+        let claims = decode::<Claims>(bearer?, key, validation).ok()?.claims;
+        Some(Principal::Token {
+            scopes: claims.scope.split_whitespace().map(str::to_string).collect(),
+        })
+    }
+}
+
+fn reject(status: StatusCode, message: &str) -> Response {
+    (status, Json(serde_json::json!({ "error": message }))).into_response()
+}
+
+pub async fn require_auth<B>(
+    State(config): State<Arc<AuthConfig>>,
+    mut request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    if !config.enabled() {
+        return next.run(request).await;
+    }
+
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+    let policy = config.policy_for(&route).clone();
+    if policy == RoutePolicy::Public {
+        return next.run(request).await;
+    }
+
+    let headers = request.headers();
+    let api_key = headers.get("x-api-key").and_then(|v| v.to_str().ok());
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+
+    let Some(principal) = config.authenticate(api_key, bearer) else {
+        return reject(StatusCode::UNAUTHORIZED, "missing or invalid credentials");
+    };
+    if let RoutePolicy::Scope(scope) = &policy {
+        if !principal.has_scope(scope) {
+            return reject(StatusCode::FORBIDDEN, &format!("requires scope {}", scope));
+        }
+    }
+
+    request.extensions_mut().insert(principal);
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+
+    #[test]
+    fn test_api_keys_and_jwt_scopes() {
+        let config = AuthConfig::new(&["k3y"], Some("secret"));
+        assert!(matches!(config.authenticate(Some("k3y"), None), Some(Principal::ApiKey)));
+        assert!(config.authenticate(Some("wrong"), None).is_none());
+
+        let claims = serde_json::json!({
+            "sub": "ci",
+            "scope": "orchestrate read",
+            "exp": 4_000_000_000u64,
+        });
+        let token =
+            encode(&Header::default(), &claims, &EncodingKey::from_secret(b"secret")).unwrap();
+        let principal = config.authenticate(None, Some(&token)).unwrap();
+        assert!(principal.has_scope("orchestrate"));
+        assert!(!principal.has_scope("admin"));
+
+        let forged =
+            encode(&Header::default(), &claims, &EncodingKey::from_secret(b"other")).unwrap();
+        assert!(config.authenticate(None, Some(&forged)).is_none());
+        assert_eq!(config.policy_for("/health"), &RoutePolicy::Public);
+    }
+}
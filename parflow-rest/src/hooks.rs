@@ -0,0 +1,179 @@
+//! Webhook triggers: `POST /hooks/:name` starts the workflow configured for the hook.
+//!
+//! Hooks are configured under `[hooks.<name>]`, see [`HookConfig`]. Every request must carry an
+//! HMAC-SHA256 signature of its body in the hook's signature header, `sha256=<hex>` as GitHub
+//! sends it, so the endpoints skip API key authentication. The body is a JSON payload: its
+//! fields can be required to match the hook's `filter`, and `{{payload.<field>}}` placeholders
+//! in the workflow's task arguments are filled from it, with `.` between nested fields and
+//! array indexes (`{{payload.commits.0.id}}`). Payload values reach the task verbatim, so
+//! workflows should not pass them to a shell.
+
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use hmac::{Hmac, Mac};
+use parflow_core::config::HookConfig;
+use parflow_orchestrator::{MultiLanguageOrchestrator, MultiLanguageWorkflow};
+use serde_json::Value;
+use sha2::Sha256;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+pub struct Hooks {
+    pub hooks: BTreeMap<String, HookConfig>,
+    /// Applied to tasks without a timeout
+    pub default_timeout_secs: Option<u64>,
+}
+
+fn reply(status: StatusCode, body: Value) -> Response {
+    (status, Json(body)).into_response()
+}
+
+fn error(status: StatusCode, message: impl std::fmt::Display) -> Response {
+    reply(status, serde_json::json!({ "error": message.to_string() }))
+}
+
+/// Whether `signature` (`sha256=<hex>`) is the HMAC-SHA256 of `body` under `secret`
+pub fn verify_signature(secret: &[u8], body: &[u8], signature: &str) -> bool {
+    let Some(Ok(expected)) = signature.strip_prefix("sha256=").map(hex::decode) else {
+        return false;
+    };
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC takes keys of any size");
+    mac.update(body);
+    mac.verify_slice(&expected).is_ok()
+}
+
+/// The value at a dotted `path` in `payload`
+fn lookup<'a>(payload: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(payload, |value, key| match value {
+        Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+        _ => value.get(key),
+    })
+}
+
+/// Fill the `{{payload.<field>}}` placeholders of `template`.
+pub fn render(template: &str, payload: &Value) -> Result<String, String> {
+    let mut rendered = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end =
+            after.find("}}").ok_or_else(|| format!("unclosed placeholder in '{}'", template))?;
+        let placeholder = after[..end].trim();
+        let path = placeholder
+            .strip_prefix("payload.")
+            .ok_or_else(|| format!("unknown placeholder '{}'", placeholder))?;
+        match lookup(payload, path) {
+            Some(Value::String(text)) => rendered.push_str(text),
+            Some(value) => rendered.push_str(&value.to_string()),
+            None => return Err(format!("payload has no field '{}'", path)),
+        }
+        rest = &after[end + 2..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+/// Whether `payload` has every field of `filter` with the same (string) value
+pub fn matches(filter: &BTreeMap<String, String>, payload: &Value) -> bool {
+    filter.iter().all(|(path, expected)| match lookup(payload, path) {
+        Some(Value::String(text)) => text == expected,
+        Some(value) => serde_json::from_str::<Value>(expected).is_ok_and(|e| e == *value),
+        None => false,
+    })
+}
+
+fn prepare(
+    hook: &HookConfig,
+    payload: &Value,
+    default_timeout_secs: Option<u64>,
+) -> Result<MultiLanguageWorkflow, String> {
+    let mut workflow = MultiLanguageWorkflow::load(&hook.workflow).map_err(|e| e.to_string())?;
+    for task in &mut workflow.tasks {
+        for arg in &mut task.args {
+            *arg = render(arg, payload)?;
+        }
+        task.timeout_seconds = task.timeout_seconds.or(default_timeout_secs);
+    }
+    Ok(workflow)
+}
+
+pub async fn receive(
+    State(hooks): State<Arc<Hooks>>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let Some(hook) = hooks.hooks.get(&name) else {
+        return error(StatusCode::NOT_FOUND, format!("unknown hook {}", name));
+    };
+    let Some(secret) = std::env::var(&hook.secret_env).ok().filter(|s| !s.is_empty()) else {
+        tracing::error!(hook = %name, variable = %hook.secret_env, "❌ Webhook secret not set");
+        return error(StatusCode::INTERNAL_SERVER_ERROR, "hook secret is not configured");
+    };
+    let signature = headers.get(&hook.signature_header).and_then(|v| v.to_str().ok());
+    if !signature.is_some_and(|signature| verify_signature(secret.as_bytes(), &body, signature)) {
+        return error(StatusCode::UNAUTHORIZED, "missing or invalid signature");
+    }
+
+    let payload: Value = match serde_json::from_slice(&body) {
+        Ok(payload) => payload,
+        Err(e) => return error(StatusCode::BAD_REQUEST, format!("invalid JSON payload: {}", e)),
+    };
+    if !matches(&hook.filter, &payload) {
+        return reply(StatusCode::OK, serde_json::json!({ "hook": name, "status": "ignored" }));
+    }
+    let workflow = match prepare(hook, &payload, hooks.default_timeout_secs) {
+        Ok(workflow) => workflow,
+        Err(e) => return error(StatusCode::UNPROCESSABLE_ENTITY, e),
+    };
+
+    let workflow_name = workflow.name.clone();
+    tracing::info!(hook = %name, workflow = %workflow_name, "🪝 Webhook starting workflow");
+    tokio::spawn(async move {
+        let started = Instant::now();
+        let results = MultiLanguageOrchestrator::execute_workflow(workflow).await;
+        crate::record_workflow("webhook", started);
+        let failed = results.iter().filter(|result| !result.success).count();
+        tracing::info!(hook = %name, tasks = results.len(), failed, "🪝 Webhook workflow finished");
+    });
+    reply(
+        StatusCode::ACCEPTED,
+        serde_json::json!({ "workflow": workflow_name, "status": "started" }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signatures_filters_and_templates() {
+        let body = br#"{"ref": "refs/heads/main", "after": "abc123", "commits": [{"id": "c1"}]}"#;
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"secret").unwrap();
+        mac.update(body);
+        let signature = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+        assert!(verify_signature(b"secret", body, &signature));
+        assert!(!verify_signature(b"other", body, &signature));
+        assert!(!verify_signature(b"secret", b"{}", &signature));
+        assert!(!verify_signature(b"secret", body, "abc123"));
+
+        let payload: Value = serde_json::from_slice(body).unwrap();
+        let main = BTreeMap::from([("ref".to_string(), "refs/heads/main".to_string())]);
+        assert!(matches(&main, &payload));
+        let tags = BTreeMap::from([("ref".to_string(), "refs/tags/v1".to_string())]);
+        assert!(!matches(&tags, &payload));
+
+        assert_eq!(
+            render("--rev={{ payload.after }} {{payload.commits.0.id}}", &payload).unwrap(),
+            "--rev=abc123 c1"
+        );
+        assert!(render("{{payload.pusher.name}}", &payload).is_err());
+        assert!(render("{{env.HOME}}", &payload).is_err());
+        assert_eq!(render("no placeholders", &payload).unwrap(), "no placeholders");
+    }
+}
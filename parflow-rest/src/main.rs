@@ -1,34 +1,112 @@
-use axum::routing::get;
-use axum::{Json, Router};
+use axum::routing::{get, post};
+use axum::{middleware, Json, Router};
+use axum_server::tls_rustls::RustlsConfig;
+use parflow_core::config::ParflowConfig;
+use parflow_core::logging::LogOptions;
+use parflow_core::tls::TlsSettings;
 use parflow_core::{run_example_par, run_example_seq};
+use parflow_orchestrator::CronScheduler;
 use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Instant;
 
-pub async fn run_rest_server(port: u16) -> Result<(), Box<dyn std::error::Error>> {
-    let app = Router::new().route("/par", get(handle_par)).route("/seq", get(handle_seq));
+mod auth;
+mod hooks;
+mod prometheus;
+mod schedules;
+mod transpile;
+
+pub async fn run_rest_server(
+    port: u16,
+    tls: Option<TlsSettings>,
+    schedules: CronScheduler,
+    hooks: hooks::Hooks,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let auth = Arc::new(auth::AuthConfig::from_env()?);
+    if !auth.enabled() {
+        tracing::warn!(
+            "⚠️  Authentication disabled, schedules are read-only: set PARFLOW_API_KEYS or \
+             PARFLOW_JWT_SECRET"
+        );
+    }
+
+    let metrics = prometheus::install()?;
+
+    let app = Router::new()
+        .route("/par", get(handle_par))
+        .route("/seq", get(handle_seq))
+        .route("/health", get(handle_health))
+        .route("/metrics", get(move || std::future::ready(metrics.render())))
+        .route("/transpile", post(transpile::transpile))
+        .merge(schedules::routes(auth.enabled()))
+        .route_layer(middleware::from_fn_with_state(auth, auth::require_auth))
+        // Webhooks authenticate with their signatures instead
+        .merge(
+            Router::new().route("/hooks/:name", post(hooks::receive)).with_state(Arc::new(hooks)),
+        )
+        .route_layer(middleware::from_fn(prometheus::track_requests))
+        .with_state(schedules);
 
     let addr = SocketAddr::from(([0, 0, 0, 0], port));
-    println!("🌐 REST server listening on {}", addr);
-    axum::Server::bind(&addr).serve(app.into_make_service()).await?;
+    match tls {
+        Some(tls) => {
+            if tls.client_ca.is_some() {
+                tracing::warn!(
+                    "⚠️  Client certificate verification is only supported by the gRPC server"
+                );
+            }
+            let identity = tls.identity()?;
+            let config = RustlsConfig::from_pem(identity.cert, identity.key).await?;
+            tracing::info!(%addr, "🔒 REST server listening (TLS)");
+            axum_server::bind_rustls(addr, config).serve(app.into_make_service()).await?;
+        }
+        None => {
+            tracing::info!(%addr, "🌐 REST server listening");
+            axum::Server::bind(&addr).serve(app.into_make_service()).await?;
+        }
+    }
     Ok(())
 }
 
+fn record_workflow(mode: &'static str, started: Instant) {
+    metrics::histogram!("parflow_workflow_duration_seconds", "subsystem" => "rest", "mode" => mode)
+        .record(started.elapsed().as_secs_f64());
+}
+
 async fn handle_par() -> Json<Vec<i32>> {
+    let started = Instant::now();
     let vec = run_example_par().await;
+    record_workflow("parallel", started);
     Json(vec)
 }
 
 async fn handle_seq() -> Json<Vec<i32>> {
+    let started = Instant::now();
     let vec = run_example_seq().await;
+    record_workflow("sequential", started);
     Json(vec)
 }
 
+async fn handle_health() -> &'static str {
+    "ok"
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    println!("🚀 Starting ParFlow REST Server");
+    let config = ParflowConfig::load()?.config;
+    let _telemetry = parflow_core::telemetry::init_from_env("parflow-rest")?;
+    parflow_core::logging::init(&LogOptions::from_config(&config.log)?)?;
+    tracing::info!("🚀 Starting ParFlow REST server");
 
-    let port = std::env::var("PORT").ok().and_then(|p| p.parse().ok()).unwrap_or(3000);
+    // PORT (set by the CLI and most hosting platforms) wins over the config file
+    let port =
+        std::env::var("PORT").ok().and_then(|p| p.parse().ok()).unwrap_or(config.server.rest_port);
 
-    run_rest_server(port).await
+    let schedules = CronScheduler::open(config.server.schedules_dir.join("rest"))?;
+    let default_timeout_secs = config.orchestrator.default_timeout_secs;
+    schedules.start(move |schedule| schedules::run(schedule, default_timeout_secs));
+    let hooks = hooks::Hooks { hooks: config.hooks, default_timeout_secs };
+    run_rest_server(port, TlsSettings::from_env()?, schedules, hooks).await
 }
 
 #[cfg(test)]
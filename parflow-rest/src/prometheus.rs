@@ -0,0 +1,69 @@
+//! Prometheus metrics for the REST server
+//!
+//! [`install`] sets the process-wide `metrics` recorder, so besides the request metrics recorded
+//! here, anything the ParFlow libraries emit in this process (workflow durations, active live
+//! sessions, benchmark runs, each labelled by `subsystem`) appears on `/metrics`. The route
+//! follows the usual auth rules; add `/metrics=public` to `PARFLOW_AUTH_ROUTES` to let a scraper
+//! in without credentials.
+
+use axum::extract::MatchedPath;
+use axum::http::Request;
+use axum::middleware::Next;
+use axum::response::Response;
+use metrics_exporter_prometheus::{BuildError, Matcher, PrometheusBuilder, PrometheusHandle};
+use std::time::{Duration, Instant};
+
+/// Histogram buckets in seconds, from a fast request up to a long workflow
+const SECONDS_BUCKETS: &[f64] =
+    &[0.005, 0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+/// How often idle histogram data is compacted
+const UPKEEP_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Install the global recorder and start its upkeep task; the handle renders `/metrics`.
+pub fn install() -> Result<PrometheusHandle, BuildError> {
+    let handle = PrometheusBuilder::new()
+        .set_buckets_for_metric(Matcher::Suffix("_seconds".to_string()), SECONDS_BUCKETS)?
+        .install_recorder()?;
+    metrics::describe_counter!("parflow_http_requests_total", "REST requests by route and status");
+    metrics::describe_histogram!(
+        "parflow_http_request_duration_seconds",
+        metrics::Unit::Seconds,
+        "REST request latency by route"
+    );
+
+    let upkeep = handle.clone();
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(UPKEEP_INTERVAL);
+        loop {
+            ticker.tick().await;
+            upkeep.run_upkeep();
+        }
+    });
+    Ok(handle)
+}
+
+/// Count and time every routed request, labelled with the route pattern rather than the raw
+/// path so label values stay bounded.
+pub async fn track_requests<B>(request: Request<B>, next: Next<B>) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let method = request.method().to_string();
+    let started = Instant::now();
+
+    let response = next.run(request).await;
+
+    let status = response.status().as_u16().to_string();
+    metrics::counter!(
+        "parflow_http_requests_total",
+        "route" => route.clone(),
+        "method" => method,
+        "status" => status
+    )
+    .increment(1);
+    metrics::histogram!("parflow_http_request_duration_seconds", "route" => route)
+        .record(started.elapsed().as_secs_f64());
+    response
+}
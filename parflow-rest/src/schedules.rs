@@ -0,0 +1,143 @@
+//! CRUD endpoints for cron-scheduled workflows
+//!
+//! - `GET /schedules` lists schedules, `POST /schedules` creates one
+//! - `GET`, `PUT` and `DELETE /schedules/:id` read, replace and remove one
+//!
+//! Scheduled workflows run with the server's permissions, so the endpoints that change
+//! schedules are only mounted when requests are authenticated; without `PARFLOW_API_KEYS` or
+//! `PARFLOW_JWT_SECRET` the schedules are read-only.
+//!
+//! Bodies are [`ScheduleSpec`]s and responses [`ScheduleStatus`]es, see
+//! [`parflow_orchestrator::schedule`] for the cron syntax and overlap policies.
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing;
+use axum::{Json, Router};
+use parflow_orchestrator::{CronScheduler, MultiLanguageOrchestrator, Schedule, ScheduleSpec};
+use std::time::Instant;
+
+fn error(status: StatusCode, message: impl std::fmt::Display) -> Response {
+    (status, Json(serde_json::json!({ "error": message.to_string() }))).into_response()
+}
+
+fn not_found(id: &str) -> Response {
+    error(StatusCode::NOT_FOUND, format!("unknown schedule {}", id))
+}
+
+/// The schedule endpoints, leaving out those that change schedules unless `writable`
+pub fn routes(writable: bool) -> Router<CronScheduler> {
+    if writable {
+        Router::new()
+            .route("/schedules", routing::get(list).post(create))
+            .route("/schedules/:id", routing::get(get).put(update).delete(delete))
+    } else {
+        Router::new()
+            .route("/schedules", routing::get(list))
+            .route("/schedules/:id", routing::get(get))
+    }
+}
+
+pub async fn list(State(schedules): State<CronScheduler>) -> Response {
+    Json(schedules.list()).into_response()
+}
+
+pub async fn create(
+    State(schedules): State<CronScheduler>,
+    Json(spec): Json<ScheduleSpec>,
+) -> Response {
+    match schedules.create(spec) {
+        Ok(created) => (StatusCode::CREATED, Json(created)).into_response(),
+        Err(e) => error(StatusCode::BAD_REQUEST, e),
+    }
+}
+
+pub async fn get(State(schedules): State<CronScheduler>, Path(id): Path<String>) -> Response {
+    match schedules.get(&id) {
+        Some(schedule) => Json(schedule).into_response(),
+        None => not_found(&id),
+    }
+}
+
+pub async fn update(
+    State(schedules): State<CronScheduler>,
+    Path(id): Path<String>,
+    Json(spec): Json<ScheduleSpec>,
+) -> Response {
+    match schedules.update(&id, spec) {
+        Ok(Some(updated)) => Json(updated).into_response(),
+        Ok(None) => not_found(&id),
+        Err(e) => error(StatusCode::BAD_REQUEST, e),
+    }
+}
+
+pub async fn delete(State(schedules): State<CronScheduler>, Path(id): Path<String>) -> Response {
+    match schedules.delete(&id) {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => not_found(&id),
+        Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
+
+/// Run the workflow of a schedule that came due, giving tasks without a timeout
+/// `default_timeout_secs`.
+pub async fn run(schedule: Schedule, default_timeout_secs: Option<u64>) {
+    let mut workflow = schedule.spec.workflow;
+    for task in &mut workflow.tasks {
+        task.timeout_seconds = task.timeout_seconds.or(default_timeout_secs);
+    }
+    let started = Instant::now();
+    let results = MultiLanguageOrchestrator::execute_workflow(workflow).await;
+    crate::record_workflow("scheduled", started);
+    let failed = results.iter().filter(|result| !result.success).count();
+    tracing::info!(
+        schedule = %schedule.spec.name,
+        tasks = results.len(),
+        failed,
+        "⏰ Scheduled workflow finished"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_schedules_are_read_only_without_authentication() {
+        let dir =
+            std::env::temp_dir().join(format!("parflow-rest-schedules-{}", std::process::id()));
+        let schedules = CronScheduler::open(&dir).unwrap();
+        let spec = r#"{"name": "nightly", "cron": "0 * * * *", "workflow": {"name": "nightly", "concurrent": false, "tasks": [
+            {"language": "Python", "command": "python", "args": ["main.py"]}]}}"#;
+        let request = |method: &str, uri: &str| {
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(spec))
+                .unwrap()
+        };
+
+        let app = routes(false).with_state(schedules.clone());
+        for (method, uri) in
+            [("POST", "/schedules"), ("PUT", "/schedules/x"), ("DELETE", "/schedules/x")]
+        {
+            let response = app.clone().oneshot(request(method, uri)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED, "{} {}", method, uri);
+        }
+        let response = app.oneshot(request("GET", "/schedules")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(schedules.list().is_empty());
+
+        let app = routes(true).with_state(schedules.clone());
+        let response = app.oneshot(request("POST", "/schedules")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(schedules.list().len(), 1);
+        drop(schedules);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
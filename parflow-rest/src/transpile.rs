@@ -0,0 +1,46 @@
+//! Transpilation as a service
+//!
+//! `POST /transpile` with `{"code": ..., "from": "python", "to": "rust"}` answers
+//! `{"code": ...}` with the transpiled source. Sources over
+//! [`MAX_CODE_BYTES`](parflow_transpiler::service::MAX_CODE_BYTES) get `413`, unsupported
+//! directions `400`, and transpilations still running after
+//! [`TIMEOUT`](parflow_transpiler::service::TIMEOUT) `504`.
+
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use parflow_transpiler::service::{self, Rejection};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize)]
+pub struct TranspileRequest {
+    pub code: String,
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TranspileResponse {
+    pub code: String,
+}
+
+fn error(status: StatusCode, message: impl std::fmt::Display) -> Response {
+    (status, Json(serde_json::json!({ "error": message.to_string() }))).into_response()
+}
+
+pub async fn transpile(Json(request): Json<TranspileRequest>) -> Response {
+    let TranspileRequest { code, from, to } = request;
+    let transpiling = tokio::task::spawn_blocking(move || service::transpile(&code, &from, &to));
+    match tokio::time::timeout(service::TIMEOUT, transpiling).await {
+        Ok(Ok(Ok(code))) => Json(TranspileResponse { code }).into_response(),
+        Ok(Ok(Err(rejection @ Rejection::TooLarge(_)))) => {
+            error(StatusCode::PAYLOAD_TOO_LARGE, rejection)
+        }
+        Ok(Ok(Err(rejection))) => error(StatusCode::BAD_REQUEST, rejection),
+        Ok(Err(e)) => error(StatusCode::INTERNAL_SERVER_ERROR, e),
+        Err(_) => error(
+            StatusCode::GATEWAY_TIMEOUT,
+            format!("transpilation took longer than {}s", service::TIMEOUT.as_secs()),
+        ),
+    }
+}
@@ -1,5 +1,4 @@
 use anyhow::Result;
-use colored::*;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -147,7 +146,7 @@ impl SystemOptimizer {
     }
 
     pub async fn analyze_system(&self) -> Result<SystemAnalysis> {
-        println!("{}", "🔍 Analyzing system performance and resources...".bright_blue());
+        tracing::info!("🔍 Analyzing system performance and resources");
 
         Ok(SystemAnalysis {
             memory_usage: MemoryAnalysis {
@@ -181,7 +180,7 @@ impl SystemOptimizer {
     }
 
     pub async fn detect_ai_slop(&self, path: &str) -> Result<AISlopAnalysis> {
-        println!("{} {}", "🤖 Detecting AI-generated code patterns:".bright_blue(), path);
+        tracing::info!(path, "🤖 Detecting AI-generated code patterns");
 
         Ok(AISlopAnalysis {
             total_files: 10,
@@ -0,0 +1,357 @@
+//! Affected-test selection from the files changed since a git ref
+//!
+//! Changed files are everything that differs between the merge base with the ref and the working
+//! tree, plus untracked files. Each environment then maps the files under its project to the
+//! narrowest selection its framework can express:
+//!
+//! - cargo: the packages owning the files, filtered to the changed modules' tests when every file
+//!   is a module under `src/`
+//! - pytest: changed test files, and for a source file `foo.py` the `test_foo.py`/`foo_test.py`
+//!   files in the project
+//! - jest: `--findRelatedTests`, which follows imports from the changed files
+//! - go: the packages containing the files
+//!
+//! Anything the mapping cannot narrow (manifests, configuration, `conftest.py`, deleted files)
+//! selects the environment's whole suite, and an environment with no relevant changes is not
+//! run. The mapping behind every selection is kept in [`TestSelection::heuristics`].
+
+use crate::environment::SKIPPED_DIRS;
+use crate::{TestEnvironment, TestFramework};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// Which tests of an environment to run; the default runs all of them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TestSelection {
+    /// Runner arguments: cargo `-p` packages, pytest files, jest related files, go packages
+    pub args: Vec<String>,
+    /// Test name filters handed to the test harness (libtest)
+    pub filters: Vec<String>,
+    /// How the changed files were mapped to this selection
+    pub heuristics: Vec<String>,
+}
+
+impl TestSelection {
+    pub fn is_everything(&self) -> bool {
+        self.args.is_empty() && self.filters.is_empty()
+    }
+
+    fn everything(heuristic: String) -> Self {
+        Self { heuristics: vec![heuristic], ..Default::default() }
+    }
+}
+
+/// Absolute paths of the files changed since the merge base of `since` and `HEAD`, including
+/// uncommitted and untracked files.
+///
+/// # Errors
+///
+/// When `root` is not in a git repository or `since` does not name a commit.
+pub(crate) async fn changed_files(root: &Path, since: &str) -> Result<Vec<PathBuf>> {
+    let toplevel = PathBuf::from(git(root, &["rev-parse", "--show-toplevel"]).await?.trim());
+    let toplevel = toplevel.canonicalize().unwrap_or(toplevel);
+    let base = git(root, &["merge-base", since, "HEAD"]).await?;
+    let diff = git(root, &["diff", "--name-only", base.trim()]).await?;
+    let untracked =
+        git(root, &["ls-files", "--others", "--exclude-standard", "--full-name"]).await?;
+    let files: BTreeSet<PathBuf> = diff
+        .lines()
+        .chain(untracked.lines())
+        .filter(|line| !line.is_empty())
+        .map(|line| toplevel.join(line))
+        .collect();
+    Ok(files.into_iter().collect())
+}
+
+async fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .await
+        .context("could not run git")?;
+    if !output.status.success() {
+        bail!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The tests of `env` affected by `changed`, or `None` when no changed file concerns it.
+pub(crate) fn select(env: &TestEnvironment, changed: &[PathBuf]) -> Option<TestSelection> {
+    let relevant: Vec<&Path> = changed
+        .iter()
+        .filter(|file| file.starts_with(&env.project_dir) && concerns(env.framework, file))
+        .map(PathBuf::as_path)
+        .collect();
+    if relevant.is_empty() {
+        return None;
+    }
+    Some(match env.framework {
+        TestFramework::CargoTest => select_cargo(&env.project_dir, &relevant),
+        TestFramework::Pytest => select_pytest(&env.project_dir, &relevant),
+        TestFramework::Jest => select_jest(&env.project_dir, &relevant),
+        TestFramework::GoTest => select_go(&env.project_dir, &relevant),
+    })
+}
+
+/// Whether a change to `file` can affect `framework`'s tests
+fn concerns(framework: TestFramework, file: &Path) -> bool {
+    let name = file.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    let extension = file.extension().map(|ext| ext.to_string_lossy()).unwrap_or_default();
+    match framework {
+        TestFramework::CargoTest => {
+            extension == "rs" || name == "Cargo.toml" || name == "Cargo.lock"
+        }
+        TestFramework::Pytest => {
+            extension == "py"
+                || matches!(
+                    name.as_ref(),
+                    "pytest.ini" | "pyproject.toml" | "setup.cfg" | "tox.ini"
+                )
+                || (name.starts_with("requirements") && extension == "txt")
+        }
+        TestFramework::Jest => {
+            matches!(extension.as_ref(), "js" | "jsx" | "ts" | "tsx" | "mjs" | "cjs")
+                || name == "package.json"
+                || name == "package-lock.json"
+        }
+        TestFramework::GoTest => extension == "go" || name == "go.mod" || name == "go.sum",
+    }
+}
+
+fn relative(path: &Path, base: &Path) -> String {
+    path.strip_prefix(base).unwrap_or(path).to_string_lossy().replace('\\', "/")
+}
+
+fn select_cargo(project: &Path, files: &[&Path]) -> TestSelection {
+    let mut packages = BTreeSet::new();
+    let mut filters = BTreeSet::new();
+    let mut heuristics = Vec::new();
+    let mut whole_packages = false;
+    for &file in files {
+        let shown = relative(file, project);
+        let Some((package_dir, package)) = owning_package(project, file) else {
+            return TestSelection::everything(format!(
+                "{} is outside any package: all tests",
+                shown
+            ));
+        };
+        packages.insert(package.clone());
+        match module_path(&relative(file, &package_dir)) {
+            Some(module) => {
+                heuristics.push(format!("{} → package {}, tests in `{}`", shown, package, module));
+                filters.insert(format!("{}::", module));
+            }
+            None => {
+                heuristics.push(format!("{} → all tests of package {}", shown, package));
+                whole_packages = true;
+            }
+        }
+    }
+    // Filters apply to every selected package, so one whole package rules them out
+    if whole_packages {
+        filters.clear();
+    }
+    TestSelection {
+        args: packages.into_iter().flat_map(|package| ["-p".to_string(), package]).collect(),
+        filters: filters.into_iter().collect(),
+        heuristics,
+    }
+}
+
+/// The nearest package manifest above `file` within `project`, as (dir, package name)
+fn owning_package(project: &Path, file: &Path) -> Option<(PathBuf, String)> {
+    file.ancestors().skip(1).take_while(|dir| dir.starts_with(project)).find_map(|dir| {
+        let manifest = std::fs::read_to_string(dir.join("Cargo.toml")).ok()?;
+        Some((dir.to_path_buf(), package_name(&manifest)?))
+    })
+}
+
+/// `name` from a manifest's `[package]` table; `None` for a virtual workspace manifest
+pub(crate) fn package_name(manifest: &str) -> Option<String> {
+    let mut in_package = false;
+    for line in manifest.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_package = line == "[package]";
+        } else if let Some(value) = line
+            .strip_prefix("name")
+            .and_then(|rest| rest.trim_start().strip_prefix('='))
+            .filter(|_| in_package)
+        {
+            return Some(value.trim().trim_matches('"').to_string());
+        }
+    }
+    None
+}
+
+/// `src/net/http.rs` or `src/net/http/mod.rs` → `net::http`; crate roots, binaries and files
+/// outside `src/` have no module of their own
+fn module_path(path_in_package: &str) -> Option<String> {
+    let module = path_in_package.strip_prefix("src/")?.strip_suffix(".rs")?;
+    let module = module.strip_suffix("/mod").unwrap_or(module);
+    if matches!(module, "lib" | "main") || module.starts_with("bin/") {
+        return None;
+    }
+    Some(module.replace('/', "::"))
+}
+
+fn select_pytest(project: &Path, files: &[&Path]) -> TestSelection {
+    let mut tests = BTreeSet::new();
+    let mut heuristics = Vec::new();
+    for &file in files {
+        let shown = relative(file, project);
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        if !file.exists() {
+            return TestSelection::everything(format!("{} was deleted: all tests", shown));
+        }
+        if is_python_test(&name) {
+            heuristics.push(format!("{} is a test file", shown));
+            tests.insert(shown);
+            continue;
+        }
+        let Some(stem) = name.strip_suffix(".py").filter(|&stem| stem != "conftest") else {
+            return TestSelection::everything(format!("{} configures pytest: all tests", shown));
+        };
+        let wanted = [format!("test_{}.py", stem), format!("{}_test.py", stem)];
+        let matching = find_files(project, &|name| wanted.iter().any(|w| w == name));
+        if matching.is_empty() {
+            return TestSelection::everything(format!(
+                "{} has no test file named after it: all tests",
+                shown
+            ));
+        }
+        for test in matching {
+            let test = relative(&test, project);
+            heuristics.push(format!("{} → {}", shown, test));
+            tests.insert(test);
+        }
+    }
+    TestSelection { args: tests.into_iter().collect(), filters: Vec::new(), heuristics }
+}
+
+pub(crate) fn is_python_test(name: &str) -> bool {
+    name.ends_with(".py") && (name.starts_with("test_") || name.ends_with("_test.py"))
+}
+
+/// Files under `dir` whose name satisfies `matches`, skipping hidden and build directories
+pub(crate) fn find_files(dir: &Path, matches: &dyn Fn(&str) -> bool) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+    let Ok(entries) = std::fs::read_dir(dir) else { return found };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        let path = entry.path();
+        if entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+            if !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_ref()) {
+                found.extend(find_files(&path, matches));
+            }
+        } else if matches(&name) {
+            found.push(path);
+        }
+    }
+    found.sort();
+    found
+}
+
+fn select_jest(project: &Path, files: &[&Path]) -> TestSelection {
+    let mut related = Vec::new();
+    for &file in files {
+        let shown = relative(file, project);
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        if name.starts_with("package") || name.starts_with("jest.config") {
+            return TestSelection::everything(format!("{} configures jest: all tests", shown));
+        }
+        if !file.exists() {
+            return TestSelection::everything(format!("{} was deleted: all tests", shown));
+        }
+        related.push(shown);
+    }
+    let heuristics =
+        vec![format!("jest --findRelatedTests follows imports from {}", related.join(", "))];
+    let args = std::iter::once("--findRelatedTests".to_string()).chain(related).collect();
+    TestSelection { args, filters: Vec::new(), heuristics }
+}
+
+fn select_go(project: &Path, files: &[&Path]) -> TestSelection {
+    let mut packages = BTreeSet::new();
+    let mut heuristics = Vec::new();
+    for &file in files {
+        let shown = relative(file, project);
+        if file.extension().is_none_or(|ext| ext != "go") {
+            return TestSelection::everything(format!("{} changes the module: all tests", shown));
+        }
+        let dir = relative(file.parent().unwrap_or(project), project);
+        let package = if dir.is_empty() { ".".to_string() } else { format!("./{}", dir) };
+        heuristics.push(format!("{} → package {}", shown, package));
+        packages.insert(package);
+    }
+    TestSelection { args: packages.into_iter().collect(), filters: Vec::new(), heuristics }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(framework: TestFramework, project_dir: &Path) -> TestEnvironment {
+        TestEnvironment {
+            name: framework.to_string(),
+            language: framework.language().to_string(),
+            framework,
+            project_dir: project_dir.to_path_buf(),
+            env_dir: project_dir.join(".ws"),
+            command: Vec::new(),
+            env: Vec::new(),
+            selection: TestSelection::default(),
+        }
+    }
+
+    #[test]
+    fn test_maps_changed_files_to_tests() {
+        let root =
+            std::env::temp_dir().join(format!("parflow-test-affected-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        for dir in ["core/src/net", "app/src", "py/pkg", "py/tests"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        let write = |path: &str, text: &str| std::fs::write(root.join(path), text).unwrap();
+        write("Cargo.toml", "[workspace]\nmembers = [\"core\", \"app\"]\n");
+        write("core/Cargo.toml", "[package]\nname = \"demo-core\"\nversion = \"0.1.0\"\n");
+        write("app/Cargo.toml", "[package]\nname = \"demo-app\"\n");
+        write("py/pkg/parser.py", "");
+        write("py/pkg/orphan.py", "");
+        write("py/tests/test_parser.py", "");
+
+        let cargo = env(TestFramework::CargoTest, &root);
+        let narrow =
+            select(&cargo, &[root.join("core/src/net/http.rs"), root.join("core/src/io/mod.rs")])
+                .unwrap();
+        assert_eq!(narrow.args, ["-p", "demo-core"]);
+        assert_eq!(narrow.filters, ["io::", "net::http::"]);
+        let wide =
+            select(&cargo, &[root.join("core/src/net/http.rs"), root.join("app/src/main.rs")])
+                .unwrap();
+        assert_eq!(wide.args, ["-p", "demo-app", "-p", "demo-core"]);
+        assert!(wide.filters.is_empty());
+        assert!(select(&cargo, &[root.join("Cargo.lock")]).unwrap().is_everything());
+        assert!(select(&cargo, &[root.join("README.md")]).is_none());
+
+        let pytest = env(TestFramework::Pytest, &root.join("py"));
+        let parser = select(&pytest, &[root.join("py/pkg/parser.py")]).unwrap();
+        assert_eq!(parser.args, ["tests/test_parser.py"]);
+        assert_eq!(parser.heuristics, ["pkg/parser.py → tests/test_parser.py"]);
+        assert!(select(&pytest, &[root.join("py/pkg/orphan.py")]).unwrap().is_everything());
+        assert!(select(&pytest, &[root.join("core/src/net/http.rs")]).is_none());
+
+        let go = env(TestFramework::GoTest, &root);
+        assert_eq!(
+            select(&go, &[root.join("a.go"), root.join("cmd/x/main.go")]).unwrap().args,
+            [".", "./cmd/x"]
+        );
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,242 @@
+//! Test framework detection and isolated environment provisioning
+//!
+//! A project directory (the root or one of its immediate subdirectories) gets one environment
+//! per framework it uses. Everything a run writes goes under the orchestrator's workspace, which
+//! is keyed by project path so a second run reuses the venv, `node_modules` and build caches of
+//! the first.
+
+use crate::{TestEnvironment, TestSelection};
+use anyhow::{bail, Context, Result};
+use parflow_core::languages;
+use parflow_core::toolchains::{Runtime, Toolchains};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Directories never searched for projects
+pub(crate) const SKIPPED_DIRS: &[&str] =
+    &["target", "node_modules", "venv", ".venv", "dist", "build"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TestFramework {
+    CargoTest,
+    Pytest,
+    Jest,
+    GoTest,
+}
+
+impl TestFramework {
+    pub const ALL: [TestFramework; 4] = [Self::CargoTest, Self::Pytest, Self::Jest, Self::GoTest];
+
+    pub fn language(self) -> &'static str {
+        match self {
+            Self::CargoTest => "rust",
+            Self::Pytest => "python",
+            Self::Jest => "javascript",
+            Self::GoTest => "go",
+        }
+    }
+
+    /// Whether `language` (as given on the command line, aliases included) selects this
+    /// framework
+    pub fn matches_language(self, language: &str) -> bool {
+        match (self, languages::canonical(language)) {
+            (Self::Jest, Some("typescript")) => true,
+            (_, Some(language)) => language == self.language(),
+            (_, None) => false,
+        }
+    }
+
+    /// Whether `dir` holds a project tested with this framework
+    pub fn detect(self, dir: &Path) -> bool {
+        let read = |name: &str| std::fs::read_to_string(dir.join(name)).unwrap_or_default();
+        match self {
+            Self::CargoTest => dir.join("Cargo.toml").is_file(),
+            Self::GoTest => dir.join("go.mod").is_file(),
+            Self::Pytest => {
+                dir.join("pytest.ini").is_file()
+                    || dir.join("conftest.py").is_file()
+                    || read("pyproject.toml").contains("[tool.pytest")
+                    || read("setup.cfg").contains("[tool:pytest]")
+                    || read("tox.ini").contains("[pytest]")
+                    || has_python_tests(&dir.join("tests"))
+                    || has_python_tests(dir)
+            }
+            Self::Jest => {
+                let manifest = read("package.json");
+                manifest.contains("\"jest\"")
+                    || ["js", "ts", "mjs", "cjs", "json"]
+                        .iter()
+                        .any(|ext| dir.join(format!("jest.config.{}", ext)).is_file())
+            }
+        }
+    }
+}
+
+impl fmt::Display for TestFramework {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::CargoTest => "cargo-test",
+            Self::Pytest => "pytest",
+            Self::Jest => "jest",
+            Self::GoTest => "go-test",
+        })
+    }
+}
+
+fn has_python_tests(dir: &Path) -> bool {
+    let Ok(entries) = std::fs::read_dir(dir) else { return false };
+    entries.flatten().any(|entry| {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        name.ends_with(".py") && (name.starts_with("test_") || name.ends_with("_test.py"))
+    })
+}
+
+/// `root` and its immediate, non-hidden subdirectories
+pub(crate) fn project_dirs(root: &Path) -> Vec<PathBuf> {
+    let mut dirs = vec![root.to_path_buf()];
+    if let Ok(entries) = std::fs::read_dir(root) {
+        let mut children: Vec<PathBuf> = entries
+            .flatten()
+            .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+            .filter(|entry| {
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_ref())
+            })
+            .map(|entry| entry.path())
+            .collect();
+        children.sort();
+        dirs.extend(children);
+    }
+    dirs
+}
+
+/// Default workspace for `root`: a directory under the system temp dir named after its path
+pub(crate) fn default_workspace(root: &Path) -> PathBuf {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    root.hash(&mut hasher);
+    std::env::temp_dir().join(format!("parflow-test-env-{:016x}", hasher.finish()))
+}
+
+/// Create the isolated environment for `framework` in `project_dir`.
+pub(crate) fn provision(
+    framework: TestFramework,
+    root: &Path,
+    project_dir: &Path,
+    workspace: &Path,
+) -> Result<TestEnvironment> {
+    let relative = project_dir.strip_prefix(root).unwrap_or(project_dir);
+    let label = match relative.to_string_lossy().replace(['/', '\\'], "-") {
+        project if project.is_empty() => framework.to_string(),
+        project => format!("{}-{}", framework, project),
+    };
+    let env_dir = workspace.join(&label);
+    std::fs::create_dir_all(&env_dir).with_context(|| format!("creating {}", env_dir.display()))?;
+    let path = |sub: &str| env_dir.join(sub).to_string_lossy().into_owned();
+
+    let (command, env): (Vec<String>, Vec<(String, String)>) = match framework {
+        TestFramework::CargoTest => {
+            (vec!["cargo".into(), "test".into()], vec![("CARGO_TARGET_DIR".into(), path("target"))])
+        }
+        TestFramework::GoTest => (
+            vec!["go".into(), "test".into(), "./...".into()],
+            vec![
+                ("GOCACHE".into(), path("gocache")),
+                ("GOTMPDIR".into(), env_dir.to_string_lossy().into_owned()),
+            ],
+        ),
+        TestFramework::Pytest => {
+            let venv = env_dir.join("venv");
+            if !venv.join("pyvenv.cfg").is_file() {
+                // System site packages keep an installed pytest usable without a download
+                run_setup(
+                    Command::new(python())
+                        .args(["-m", "venv", "--system-site-packages"])
+                        .arg(&venv),
+                    "creating the Python venv",
+                )?;
+            }
+            let bin = if cfg!(windows) { venv.join("Scripts") } else { venv.join("bin") };
+            (
+                vec![
+                    bin.join("python").to_string_lossy().into_owned(),
+                    "-m".into(),
+                    "pytest".into(),
+                    "-o".into(),
+                    format!("cache_dir={}", path("pytest_cache")),
+                ],
+                vec![
+                    ("VIRTUAL_ENV".into(), venv.to_string_lossy().into_owned()),
+                    ("PYTHONDONTWRITEBYTECODE".into(), "1".into()),
+                ],
+            )
+        }
+        TestFramework::Jest => {
+            let modules = env_dir.join("node_modules");
+            if !modules.is_dir() {
+                for manifest in ["package.json", "package-lock.json"] {
+                    if project_dir.join(manifest).is_file() {
+                        std::fs::copy(project_dir.join(manifest), env_dir.join(manifest))?;
+                    }
+                }
+                let install =
+                    if env_dir.join("package-lock.json").is_file() { "ci" } else { "install" };
+                run_setup(
+                    Command::new(npm())
+                        .args([install, "--no-audit", "--no-fund"])
+                        .current_dir(&env_dir),
+                    "installing node_modules",
+                )?;
+            }
+            let jest = modules.join(".bin").join(if cfg!(windows) { "jest.cmd" } else { "jest" });
+            (
+                vec![jest.to_string_lossy().into_owned(), "--ci".into()],
+                vec![
+                    ("NODE_PATH".into(), modules.to_string_lossy().into_owned()),
+                    ("npm_config_cache".into(), path("npm-cache")),
+                ],
+            )
+        }
+    };
+
+    Ok(TestEnvironment {
+        name: label,
+        language: framework.language().to_string(),
+        framework,
+        project_dir: project_dir.to_path_buf(),
+        env_dir,
+        command,
+        env,
+        selection: TestSelection::default(),
+    })
+}
+
+/// The interpreter venvs are created with: the one toolchain detection finds (pyenv's included),
+/// else whatever the usual name resolves to
+fn python() -> PathBuf {
+    match Toolchains::new().detect(Runtime::Python) {
+        Some(python) => python.binary,
+        None if cfg!(windows) => PathBuf::from("python"),
+        None => PathBuf::from("python3"),
+    }
+}
+
+fn npm() -> &'static str {
+    if cfg!(windows) {
+        "npm.cmd"
+    } else {
+        "npm"
+    }
+}
+
+fn run_setup(command: &mut Command, what: &str) -> Result<()> {
+    let output = command.output().with_context(|| format!("{}: could not start", what))?;
+    if !output.status.success() {
+        bail!("{} failed: {}", what, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
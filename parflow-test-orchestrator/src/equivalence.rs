@@ -0,0 +1,572 @@
+//! Golden-output equivalence of an original and a mirrored implementation
+//!
+//! Both implementations run over the same corpus of calls, each in its own process. Python and
+//! JavaScript modules are imported by a small driver script. A Rust file is compiled into a
+//! throwaway cargo project whose `main` deserializes each call's arguments with `serde_json`.
+//! A case's golden output is its `expected` value when the corpus gives one, and otherwise
+//! whatever the original returned. Outputs are compared structurally. Numbers compare by value,
+//! so Python's `1` equals Rust's `1.0`. Each difference is reported at its JSON path. Two calls
+//! that both fail count as equivalent, since exception types do not carry over between
+//! languages.
+
+use crate::{TestCase, TestOutcome, TestPerformance, TestResult};
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tokio::process::Command;
+
+/// Prefixes the drivers' result lines, so output printed by the code under test is ignored
+const MARKER: &str = "@@parflow ";
+/// Relative tolerance for floating-point outputs
+const FLOAT_TOLERANCE: f64 = 1e-9;
+
+/// One call in the corpus
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EquivalenceCase {
+    /// Shown in the report; `function#index` when omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub function: String,
+    /// Positional arguments
+    #[serde(default)]
+    pub args: Vec<Value>,
+    /// Golden output, when known independently of the original
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected: Option<Value>,
+}
+
+/// What a call produced
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Value(Value),
+    /// The call raised, threw, panicked or returned `Err`
+    Error(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Difference {
+    /// JSON path of the differing value, `$` for the whole output
+    pub path: String,
+    pub expected: Value,
+    pub actual: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CaseResult {
+    pub name: String,
+    pub function: String,
+    pub args: Vec<Value>,
+    pub original: Outcome,
+    pub mirrored: Outcome,
+    /// Where the mirrored output departs from the golden output
+    pub differences: Vec<Difference>,
+    pub equivalent: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EquivalenceReport {
+    pub original: PathBuf,
+    pub mirrored: PathBuf,
+    pub cases: Vec<CaseResult>,
+    pub duration_seconds: f64,
+}
+
+impl EquivalenceReport {
+    pub fn divergent(&self) -> impl Iterator<Item = &CaseResult> {
+        self.cases.iter().filter(|case| !case.equivalent)
+    }
+
+    /// The report as a suite in the orchestrator's results, one test per case, so it shows up in
+    /// the analysis and the JUnit and SARIF exports
+    pub fn to_test_result(&self) -> TestResult {
+        let cases: Vec<TestCase> = self
+            .cases
+            .iter()
+            .map(|case| TestCase {
+                name: case.name.clone(),
+                outcome: if case.equivalent { TestOutcome::Passed } else { TestOutcome::Failed },
+                duration_seconds: None,
+                message: (!case.equivalent).then(|| describe(case)),
+            })
+            .collect();
+        let failed = cases.iter().filter(|case| case.outcome == TestOutcome::Failed).count();
+        TestResult {
+            environment: format!("equivalence-{}", file_name(&self.mirrored)),
+            tests_passed: cases.len() - failed,
+            tests_failed: failed,
+            duration_seconds: self.duration_seconds,
+            performance_metrics: TestPerformance {
+                execution_time_ms: (self.duration_seconds * 1000.0) as u64,
+                ..Default::default()
+            },
+            cases,
+            ..Default::default()
+        }
+    }
+}
+
+/// One line per difference, e.g. `$.items[1]: expected 2, got 3`
+pub fn describe(case: &CaseResult) -> String {
+    match (&case.original, &case.mirrored) {
+        (Outcome::Value(_), Outcome::Error(error)) if case.differences.is_empty() => {
+            format!("mirrored failed: {}", error)
+        }
+        (Outcome::Error(error), Outcome::Value(_)) if case.differences.is_empty() => {
+            format!("original failed ({}) but mirrored returned a value", error)
+        }
+        _ => case
+            .differences
+            .iter()
+            .map(|d| format!("{}: expected {}, got {}", d.path, d.expected, d.actual))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+fn numbers_equal(a: &serde_json::Number, b: &serde_json::Number) -> bool {
+    if let (Some(a), Some(b)) = (a.as_i64(), b.as_i64()) {
+        return a == b;
+    }
+    match (a.as_f64(), b.as_f64()) {
+        (Some(a), Some(b)) => (a - b).abs() <= FLOAT_TOLERANCE * a.abs().max(b.abs()).max(1.0),
+        _ => false,
+    }
+}
+
+/// Where `actual` departs from `expected`, with JSON paths below `path`
+pub fn diff(expected: &Value, actual: &Value, path: &str) -> Vec<Difference> {
+    let differ = || {
+        vec![Difference {
+            path: path.to_string(),
+            expected: expected.clone(),
+            actual: actual.clone(),
+        }]
+    };
+    match (expected, actual) {
+        (Value::Number(a), Value::Number(b)) => {
+            if numbers_equal(a, b) {
+                Vec::new()
+            } else {
+                differ()
+            }
+        }
+        (Value::Array(a), Value::Array(b)) if a.len() == b.len() => a
+            .iter()
+            .zip(b)
+            .enumerate()
+            .flat_map(|(i, (a, b))| diff(a, b, &format!("{}[{}]", path, i)))
+            .collect(),
+        (Value::Object(a), Value::Object(b)) => {
+            let mut keys: Vec<&String> = a.keys().chain(b.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            keys.into_iter()
+                .flat_map(|key| {
+                    let (a, b) =
+                        (a.get(key).unwrap_or(&Value::Null), b.get(key).unwrap_or(&Value::Null));
+                    diff(a, b, &format!("{}.{}", path, key))
+                })
+                .collect()
+        }
+        _ if expected == actual => Vec::new(),
+        _ => differ(),
+    }
+}
+
+fn compare(
+    case: &EquivalenceCase,
+    index: usize,
+    original: Outcome,
+    mirrored: Outcome,
+) -> CaseResult {
+    let golden = match (&case.expected, &original) {
+        (Some(expected), _) => Some(expected),
+        (None, Outcome::Value(value)) => Some(value),
+        (None, Outcome::Error(_)) => None,
+    };
+    let (differences, equivalent) = match (golden, &mirrored) {
+        (Some(golden), Outcome::Value(value)) => {
+            let differences = diff(golden, value, "$");
+            let equivalent = differences.is_empty();
+            (differences, equivalent)
+        }
+        (None, Outcome::Error(_)) => (Vec::new(), true),
+        _ => (Vec::new(), false),
+    };
+    CaseResult {
+        name: case.name.clone().unwrap_or_else(|| format!("{}#{}", case.function, index)),
+        function: case.function.clone(),
+        args: case.args.clone(),
+        original,
+        mirrored,
+        differences,
+        equivalent,
+    }
+}
+
+const PYTHON_DRIVER: &str = r#"import importlib.util, json, sys
+spec = importlib.util.spec_from_file_location("module", sys.argv[1])
+module = importlib.util.module_from_spec(spec)
+spec.loader.exec_module(module)
+for case in json.load(sys.stdin):
+    try:
+        outcome = {"value": getattr(module, case["function"])(*case["args"])}
+    except Exception as e:
+        outcome = {"error": f"{type(e).__name__}: {e}"}
+    print("@@parflow " + json.dumps(outcome, default=repr), flush=True)
+"#;
+
+const NODE_DRIVER: &str = r#"import { readFileSync } from "node:fs";
+import { pathToFileURL } from "node:url";
+const module = await import(pathToFileURL(process.argv[2]));
+for (const { function: name, args } of JSON.parse(readFileSync(0, "utf8"))) {
+  let outcome;
+  try {
+    const f = module[name] ?? module.default?.[name];
+    outcome = { value: (await f(...args)) ?? null };
+  } catch (e) {
+    outcome = { error: `${e?.name}: ${e?.message ?? e}` };
+  }
+  console.log("@@parflow " + JSON.stringify(outcome));
+}
+"#;
+
+/// A Rust function the generated `main` can call
+struct RustFunction {
+    name: String,
+    /// Owned type to deserialize each argument into, and whether it is passed by reference
+    params: Vec<(String, &'static str)>,
+    fallible: bool,
+}
+
+/// `items` split on commas outside brackets
+fn split_commas(items: &str) -> Vec<&str> {
+    let (mut depth, mut start, mut parts) = (0i32, 0, Vec::new());
+    for (i, c) in items.char_indices() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' | ')' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(items[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(items[start..].trim());
+    parts.retain(|part| !part.is_empty());
+    parts
+}
+
+/// Index of the `)` closing the parameter list that `text` starts inside of
+fn closing_paren(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some(i),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Owned type of a parameter and how to pass it: `&str` is read as a `String` and passed as `&`
+fn owned(ty: &str) -> Option<(String, &'static str)> {
+    if ty.starts_with("impl ") || ty.contains('\'') {
+        return None;
+    }
+    Some(if let Some(inner) = ty.strip_prefix("&mut ") {
+        (inner.trim().to_string(), "&mut ")
+    } else if let Some(inner) = ty.strip_prefix('&') {
+        let inner = inner.trim();
+        let owned = match inner {
+            "str" => "String".to_string(),
+            _ => match inner.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+                Some(element) => format!("Vec<{}>", element),
+                None => inner.to_string(),
+            },
+        };
+        (owned, "&")
+    } else {
+        (ty.to_string(), "")
+    })
+}
+
+/// Free, non-generic functions declared at the top level of `code`
+fn rust_functions(code: &str) -> Vec<RustFunction> {
+    let mut functions = Vec::new();
+    let lines: Vec<&str> = code.lines().collect();
+    for (i, line) in lines.iter().enumerate() {
+        let declaration = line.strip_prefix("pub ").unwrap_or(line);
+        let Some(rest) = declaration.strip_prefix("fn ") else { continue };
+        // Signatures split over several lines end at the body's opening brace
+        let mut signature = rest.to_string();
+        for next in &lines[i + 1..] {
+            if signature.contains('{') || signature.trim_end().ends_with(';') {
+                break;
+            }
+            signature.push(' ');
+            signature.push_str(next.trim());
+        }
+        let Some((name, rest)) = signature.split_once('(') else { continue };
+        if name.contains('<') || name.trim() == "main" {
+            continue;
+        }
+        let Some(close) = closing_paren(rest) else { continue };
+        let params: Option<Vec<(String, &'static str)>> = split_commas(&rest[..close])
+            .into_iter()
+            .map(|param| owned(param.split_once(':')?.1.trim()))
+            .collect();
+        let Some(params) = params else { continue };
+        let returns = rest[close + 1..].split('{').next().unwrap_or_default();
+        let returns = returns.split(" where ").next().unwrap_or_default();
+        let fallible = returns.trim().trim_start_matches("->").trim().starts_with("Result<");
+        functions.push(RustFunction { name: name.trim().to_string(), params, fallible });
+    }
+    functions
+}
+
+/// A cargo project calling the functions in `code` by name, per the drivers' protocol
+fn rust_harness(code: &str) -> String {
+    let mut arms = String::new();
+    for function in rust_functions(code) {
+        let mut arguments = Vec::new();
+        arms.push_str(&format!("            \"{}\" => {{\n", function.name));
+        for (i, (ty, pass)) in function.params.iter().enumerate() {
+            arms.push_str(&format!(
+                "                let mut arg{i}: {ty} = serde_json::from_value(args.get({i}).cloned().unwrap_or_default()).map_err(|e| format!(\"argument {i}: {{}}\", e))?;\n"
+            ));
+            arguments.push(format!("{}arg{}", pass, i));
+        }
+        let mut call = format!("{}({})", function.name, arguments.join(", "));
+        if function.fallible {
+            call = format!("{}.map_err(|e| e.to_string())?", call);
+        }
+        arms.push_str(&format!(
+            "                serde_json::to_value({}).map_err(|e| e.to_string())\n            }}\n",
+            call
+        ));
+    }
+    // Inner attributes and doc comments are not allowed inside the wrapping module
+    let code: String = code
+        .lines()
+        .filter(|line| !line.starts_with("//!") && !line.starts_with("#!["))
+        .map(|line| format!("    {}\n", line))
+        .collect();
+    format!(
+        "#[allow(dead_code, unused, clippy::all)]\nmod mirrored {{\n{code}\n    \
+         pub fn __parflow_call(function: &str, args: Vec<serde_json::Value>) -> Result<serde_json::Value, String> {{\n        \
+         match function {{\n{arms}            other => Err(format!(\"no function {{}}\", other)),\n        }}\n    }}\n}}\n\n\
+         fn main() {{\n    \
+         let cases: Vec<serde_json::Value> = serde_json::from_reader(std::io::stdin()).expect(\"cases\");\n    \
+         std::panic::set_hook(Box::new(|_| {{}}));\n    \
+         for case in cases {{\n        \
+         let function = case[\"function\"].as_str().unwrap_or_default().to_string();\n        \
+         let args = case[\"args\"].as_array().cloned().unwrap_or_default();\n        \
+         let called = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| mirrored::__parflow_call(&function, args)));\n        \
+         let outcome = match called {{\n            \
+         Ok(Ok(value)) => serde_json::json!({{ \"value\": value }}),\n            \
+         Ok(Err(error)) => serde_json::json!({{ \"error\": error }}),\n            \
+         Err(panic) => {{\n                \
+         let message = panic.downcast_ref::<&str>().map(|s| s.to_string()).or_else(|| panic.downcast_ref::<String>().cloned()).unwrap_or_default();\n                \
+         serde_json::json!({{ \"error\": format!(\"panic: {{}}\", message) }})\n            \
+         }}\n        }};\n        \
+         println!(\"{marker}{{}}\", outcome);\n    }}\n}}\n",
+        code = code,
+        arms = arms,
+        marker = MARKER
+    )
+}
+
+/// Run the implementation at `path` over `cases`, one outcome per case
+async fn execute(path: &Path, cases: &[EquivalenceCase], work: &Path) -> Result<Vec<Outcome>> {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+    let mut command = match extension {
+        "py" => {
+            let driver = work.join("driver.py");
+            std::fs::write(&driver, PYTHON_DRIVER)?;
+            let mut command = Command::new("python3");
+            command.arg(driver).arg(path);
+            command
+        }
+        "js" | "mjs" | "cjs" => {
+            let driver = work.join("driver.mjs");
+            std::fs::write(&driver, NODE_DRIVER)?;
+            let mut command = Command::new("node");
+            command.arg(driver).arg(path);
+            command
+        }
+        "rs" => {
+            let code = std::fs::read_to_string(path)
+                .with_context(|| format!("reading {}", path.display()))?;
+            let project = work.join("rust");
+            std::fs::create_dir_all(project.join("src"))?;
+            std::fs::write(
+                project.join("Cargo.toml"),
+                "[package]\nname = \"parflow-equivalence\"\nversion = \"0.0.0\"\nedition = \"2021\"\n\n\
+                 [dependencies]\nserde_json = \"1\"\n\n[workspace]\n",
+            )?;
+            std::fs::write(project.join("src/main.rs"), rust_harness(&code))?;
+            let build = Command::new("cargo")
+                .args(["build", "--quiet", "--manifest-path"])
+                .arg(project.join("Cargo.toml"))
+                .output()
+                .await
+                .context("running cargo")?;
+            if !build.status.success() {
+                bail!(
+                    "{} does not build: {}",
+                    path.display(),
+                    String::from_utf8_lossy(&build.stderr).trim()
+                );
+            }
+            Command::new(project.join("target/debug/parflow-equivalence"))
+        }
+        other => bail!("cannot run .{} files (expected .py, .js or .rs)", other),
+    };
+
+    let calls: Vec<Value> = cases
+        .iter()
+        .map(|case| serde_json::json!({ "function": case.function, "args": case.args }))
+        .collect();
+    let input = work.join(format!("cases-{}.json", extension));
+    std::fs::write(&input, serde_json::to_vec(&calls)?)?;
+    let output = command
+        .stdin(std::fs::File::open(&input)?)
+        .output()
+        .await
+        .with_context(|| format!("running {}", path.display()))?;
+    let outcomes: Vec<Outcome> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.strip_prefix(MARKER))
+        .map(|line| serde_json::from_str(line).map_err(|e| anyhow!("{}: {}", line, e)))
+        .collect::<Result<_>>()?;
+    if outcomes.len() != cases.len() {
+        bail!(
+            "{} stopped after {} of {} cases: {}",
+            path.display(),
+            outcomes.len(),
+            cases.len(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(outcomes)
+}
+
+/// Read a corpus: a JSON array of cases
+pub fn load_cases(path: &Path) -> Result<Vec<EquivalenceCase>> {
+    let text =
+        std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    serde_json::from_str(&text).with_context(|| format!("parsing {}", path.display()))
+}
+
+/// Run `original` and `mirrored` over `cases` and compare their outputs
+pub async fn verify(
+    original: &Path,
+    mirrored: &Path,
+    cases: &[EquivalenceCase],
+) -> Result<EquivalenceReport> {
+    let started = Instant::now();
+    let work = std::env::temp_dir().join(format!("parflow-equivalence-{}", std::process::id()));
+    let (original_work, mirrored_work) = (work.join("original"), work.join("mirrored"));
+    std::fs::create_dir_all(&original_work)?;
+    std::fs::create_dir_all(&mirrored_work)?;
+    let outcomes = tokio::try_join!(
+        execute(original, cases, &original_work),
+        execute(mirrored, cases, &mirrored_work)
+    );
+    let _ = std::fs::remove_dir_all(&work);
+    let (original_outcomes, mirrored_outcomes) = outcomes?;
+
+    let cases = cases
+        .iter()
+        .zip(original_outcomes.into_iter().zip(mirrored_outcomes))
+        .enumerate()
+        .map(|(i, (case, (original, mirrored)))| compare(case, i, original, mirrored))
+        .collect();
+    Ok(EquivalenceReport {
+        original: original.to_path_buf(),
+        mirrored: mirrored.to_path_buf(),
+        cases,
+        duration_seconds: started.elapsed().as_secs_f64(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_diffs_outputs_and_generates_rust_harness() {
+        let differences = diff(
+            &json!({"total": 3, "items": [1, 2.0], "name": "a"}),
+            &json!({"total": 3.0, "items": [1, 2.5], "extra": true, "name": "a"}),
+            "$",
+        );
+        assert_eq!(
+            differences,
+            [
+                Difference { path: "$.extra".into(), expected: json!(null), actual: json!(true) },
+                Difference { path: "$.items[1]".into(), expected: json!(2.0), actual: json!(2.5) },
+            ]
+        );
+
+        let case = |expected| EquivalenceCase {
+            name: None,
+            function: "area".into(),
+            args: vec![json!(2), json!(3)],
+            expected,
+        };
+        let both_failed = compare(
+            &case(None),
+            0,
+            Outcome::Error("ValueError: w".into()),
+            Outcome::Error("w".into()),
+        );
+        assert!(both_failed.equivalent);
+        assert_eq!(both_failed.name, "area#0");
+        let golden =
+            compare(&case(Some(json!(6))), 1, Outcome::Value(json!(5)), Outcome::Value(json!(6.0)));
+        assert!(golden.equivalent);
+        let wrong =
+            compare(&case(None), 2, Outcome::Value(json!(6)), Outcome::Error("panic: boom".into()));
+        assert_eq!(describe(&wrong), "mirrored failed: panic: boom");
+
+        let report = EquivalenceReport {
+            original: "shapes.py".into(),
+            mirrored: "src/shapes.rs".into(),
+            cases: vec![both_failed, golden, wrong],
+            duration_seconds: 0.5,
+        };
+        assert_eq!(report.divergent().count(), 1);
+        let result = report.to_test_result();
+        assert_eq!(result.environment, "equivalence-shapes.rs");
+        assert_eq!((result.tests_passed, result.tests_failed), (2, 1));
+
+        let functions = rust_functions(
+            "//! Shapes\npub fn area(width: f64, height: f64) -> f64 {\n    width * height\n}\n\
+             fn scale(values: &[f64],\n         label: &str) -> Result<Vec<f64>, String> {\n    todo!()\n}\n\
+             pub fn apply<F: Fn()>(f: F) {}\nimpl Shape {\n    pub fn new() -> Self { Shape }\n}\n",
+        );
+        let names: Vec<&str> = functions.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["area", "scale"]);
+        assert_eq!(
+            functions[1].params,
+            [("Vec<f64>".to_string(), "&"), ("String".to_string(), "&")]
+        );
+        assert!(functions[1].fallible && !functions[0].fallible);
+        let harness = rust_harness("//! Shapes\nfn scale(values: &[f64], label: &str) -> Result<Vec<f64>, String> { todo!() }\n");
+        assert!(!harness.contains("//! Shapes"));
+        assert!(harness.contains("scale(&arg0, &arg1).map_err(|e| e.to_string())?"));
+    }
+}
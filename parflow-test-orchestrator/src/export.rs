@@ -0,0 +1,262 @@
+//! Test result export for CI systems and code-scanning UIs
+//!
+//! JUnit XML has one `<testsuite>` per result, so repeated runs of an environment appear as
+//! separate suites, and a suite that did not run carries a single `<error>`. SARIF 2.1.0 reports
+//! failed tests and suites that did not run as errors and flaky tests as warnings; a pytest node
+//! id also gives the test file as the result's location.
+
+use crate::{TestAnalysis, TestOutcome, TestResult};
+use serde_json::{json, Value};
+use std::fmt::Write;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Junit,
+    Sarif,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "junit" | "xml" => Ok(Self::Junit),
+            "sarif" => Ok(Self::Sarif),
+            other => Err(format!("unknown export format '{}' (expected junit or sarif)", other)),
+        }
+    }
+}
+
+/// Render `results` in `format`; `analysis` supplies the flaky tests for SARIF.
+pub fn export(format: ExportFormat, results: &[TestResult], analysis: &TestAnalysis) -> String {
+    match format {
+        ExportFormat::Junit => to_junit(results),
+        ExportFormat::Sarif => to_sarif(results, analysis),
+    }
+}
+
+pub fn to_junit(results: &[TestResult]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let total = |count: fn(&TestResult) -> usize| results.iter().map(count).sum::<usize>();
+    let _ = writeln!(
+        xml,
+        "<testsuites name=\"parflow\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\">",
+        total(|r| r.cases.len()),
+        total(|r| r.tests_failed),
+        total(|r| usize::from(r.error.is_some())),
+        total(|r| r.tests_skipped),
+        results.iter().map(|r| r.duration_seconds).sum::<f64>(),
+    );
+    for (id, result) in results.iter().enumerate() {
+        let name = escape(&result.environment);
+        let _ = writeln!(
+            xml,
+            "  <testsuite id=\"{}\" name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\">",
+            id,
+            name,
+            result.cases.len(),
+            result.tests_failed,
+            usize::from(result.error.is_some()),
+            result.tests_skipped,
+            result.duration_seconds,
+        );
+        if let Some(error) = &result.error {
+            let _ = writeln!(xml, "    <testcase name=\"{}\" classname=\"{}\">", name, name);
+            let _ = writeln!(
+                xml,
+                "      <error message=\"suite did not run\">{}</error>",
+                escape(error)
+            );
+            xml.push_str("    </testcase>\n");
+        }
+        for case in &result.cases {
+            let _ = write!(
+                xml,
+                "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+                escape(&case.name),
+                name,
+                case.duration_seconds.unwrap_or(0.0)
+            );
+            let message = case.message.as_deref().unwrap_or_default();
+            match case.outcome {
+                TestOutcome::Passed => xml.push_str("/>\n"),
+                TestOutcome::Failed => {
+                    let _ = writeln!(
+                        xml,
+                        ">\n      <failure message=\"{}\">{}</failure>\n    </testcase>",
+                        escape(first_line(message)),
+                        escape(message)
+                    );
+                }
+                TestOutcome::Skipped => {
+                    let _ = writeln!(
+                        xml,
+                        ">\n      <skipped message=\"{}\"/>\n    </testcase>",
+                        escape(first_line(message))
+                    );
+                }
+            }
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+pub fn to_sarif(results: &[TestResult], analysis: &TestAnalysis) -> String {
+    let mut findings = Vec::new();
+    for result in results {
+        if let Some(error) = &result.error {
+            findings.push(json!({
+                "ruleId": "suite-error",
+                "level": "error",
+                "message": { "text": format!("{} did not run: {}", result.environment, error) },
+            }));
+        }
+        for case in result.cases.iter().filter(|case| case.outcome == TestOutcome::Failed) {
+            let mut text = format!("{}: {} failed", result.environment, case.name);
+            if let Some(message) = case.message.as_deref().filter(|m| !m.trim().is_empty()) {
+                let _ = write!(text, ": {}", first_line(message));
+            }
+            findings.push(finding("test-failure", "error", text, &case.name));
+        }
+    }
+    for test in &analysis.flaky_tests {
+        let text = format!(
+            "{}: {} is flaky, {} of {} runs failed (flakiness {:.2})",
+            test.environment, test.name, test.failures, test.runs, test.flakiness
+        );
+        findings.push(finding("flaky-test", "warning", text, &test.name));
+    }
+
+    let rule = |id: &str, text: &str| json!({ "id": id, "shortDescription": { "text": text } });
+    let sarif = json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "parflow",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": [
+                        rule("test-failure", "Test failed"),
+                        rule("suite-error", "Test suite did not run"),
+                        rule("flaky-test", "Test both passed and failed across repeated runs"),
+                    ],
+                }
+            },
+            "results": findings,
+        }]
+    });
+    serde_json::to_string_pretty(&sarif).unwrap_or_default()
+}
+
+/// A SARIF result, located in the test's file when its name is a path-based id like
+/// `tests/test_api.py::test_login`
+fn finding(rule: &str, level: &str, text: String, test: &str) -> Value {
+    let mut finding = json!({ "ruleId": rule, "level": level, "message": { "text": text } });
+    if let Some((file, _)) = test.split_once("::").filter(|(file, _)| file.contains('.')) {
+        finding["locations"] = json!([{
+            "physicalLocation": { "artifactLocation": { "uri": file.replace('\\', "/") } }
+        }]);
+    }
+    finding
+}
+
+fn first_line(text: &str) -> &str {
+    text.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or_default()
+}
+
+/// Escape for XML text and attributes, dropping control characters XML 1.0 cannot hold (such
+/// as the ANSI colour codes in test output)
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FlakyTest, TestCase};
+
+    #[test]
+    fn test_exports_junit_and_sarif() {
+        let case = |name: &str, outcome, message: Option<&str>| TestCase {
+            name: name.to_string(),
+            outcome,
+            duration_seconds: Some(0.5),
+            message: message.map(str::to_string),
+        };
+        let results = vec![
+            TestResult {
+                environment: "pytest-api".to_string(),
+                tests_passed: 1,
+                tests_failed: 1,
+                cases: vec![
+                    case("tests/test_a.py::test_ok", TestOutcome::Passed, None),
+                    case(
+                        "tests/test_a.py::test_bad",
+                        TestOutcome::Failed,
+                        Some("\x1b[31massert 1 < 2\x1b[0m"),
+                    ),
+                ],
+                ..Default::default()
+            },
+            TestResult {
+                environment: "cargo-test".to_string(),
+                error: Some("could not compile".to_string()),
+                ..Default::default()
+            },
+        ];
+
+        let xml = to_junit(&results);
+        assert!(xml.contains("tests=\"2\" failures=\"1\" errors=\"1\""));
+        assert!(xml.contains(
+            "<testcase name=\"tests/test_a.py::test_ok\" classname=\"pytest-api\" time=\"0.500\"/>"
+        ));
+        assert!(xml.contains("<failure message=\"[31massert 1 &lt; 2[0m\">"));
+        assert!(xml.contains("<error message=\"suite did not run\">could not compile</error>"));
+
+        let analysis = TestAnalysis {
+            total_environments: 2,
+            total_tests: 2,
+            passed_tests: 1,
+            success_rate: 50.0,
+            average_duration_seconds: 0.0,
+            performance_bottlenecks: vec![],
+            optimization_suggestions: vec![],
+            flaky_tests: vec![FlakyTest {
+                environment: "pytest-api".to_string(),
+                name: "tests/test_a.py::test_bad".to_string(),
+                runs: 3,
+                failures: 1,
+                flakiness: 1.0,
+            }],
+            quarantine: vec![],
+        };
+        let sarif: Value = serde_json::from_str(&to_sarif(&results, &analysis)).unwrap();
+        let findings = sarif["runs"][0]["results"].as_array().unwrap();
+        let rules: Vec<&str> = findings.iter().map(|f| f["ruleId"].as_str().unwrap()).collect();
+        assert_eq!(rules, ["test-failure", "suite-error", "flaky-test"]);
+        assert_eq!(
+            findings[0]["locations"][0]["physicalLocation"]["artifactLocation"]["uri"],
+            "tests/test_a.py"
+        );
+        assert!(findings[1].get("locations").is_none());
+        assert_eq!(findings[2]["level"], "warning");
+        assert_eq!("SARIF".parse::<ExportFormat>(), Ok(ExportFormat::Sarif));
+    }
+}
@@ -0,0 +1,102 @@
+//! Flaky test detection over repeated runs
+//!
+//! A test is flaky when it both passed and failed across runs of the same environment. Its
+//! flakiness is how often the outcome flipped between consecutive runs, so a test that alternates
+//! scores 1.0 while one that failed once in ten runs scores about 0.2. Tests that fail every time
+//! are broken, not flaky, and are left to the failure report.
+
+use crate::{TestOutcome, TestResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Flaky tests at least this flaky are suggested for quarantine
+pub const QUARANTINE_FLAKINESS: f64 = 0.3;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FlakyTest {
+    pub environment: String,
+    pub name: String,
+    /// Runs in which the test passed or failed; skips do not count
+    pub runs: usize,
+    pub failures: usize,
+    /// Share of consecutive runs whose outcome differed, from 0 to 1
+    pub flakiness: f64,
+}
+
+impl FlakyTest {
+    pub fn should_quarantine(&self) -> bool {
+        self.flakiness >= QUARANTINE_FLAKINESS
+    }
+}
+
+/// Flaky tests in `results`, which hold several runs of the same environments in run order,
+/// most flaky first.
+pub fn detect(results: &[TestResult]) -> Vec<FlakyTest> {
+    let mut history: BTreeMap<(&str, &str), Vec<bool>> = BTreeMap::new();
+    for result in results {
+        for case in &result.cases {
+            let passed = match case.outcome {
+                TestOutcome::Passed => true,
+                TestOutcome::Failed => false,
+                TestOutcome::Skipped => continue,
+            };
+            history.entry((&result.environment, &case.name)).or_default().push(passed);
+        }
+    }
+
+    let mut flaky: Vec<FlakyTest> = history
+        .into_iter()
+        .filter(|(_, outcomes)| outcomes.contains(&true) && outcomes.contains(&false))
+        .map(|((environment, name), outcomes)| {
+            let flips = outcomes.windows(2).filter(|pair| pair[0] != pair[1]).count();
+            FlakyTest {
+                environment: environment.to_string(),
+                name: name.to_string(),
+                runs: outcomes.len(),
+                failures: outcomes.iter().filter(|&&passed| !passed).count(),
+                flakiness: flips as f64 / (outcomes.len() - 1) as f64,
+            }
+        })
+        .collect();
+    flaky.sort_by(|a, b| b.flakiness.total_cmp(&a.flakiness));
+    flaky
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TestCase;
+
+    fn run(outcomes: &[(&str, TestOutcome)]) -> TestResult {
+        let cases = outcomes
+            .iter()
+            .map(|&(name, outcome)| TestCase {
+                name: name.to_string(),
+                outcome,
+                duration_seconds: None,
+                message: None,
+            })
+            .collect();
+        TestResult { environment: "cargo-test".to_string(), cases, ..Default::default() }
+    }
+
+    #[test]
+    fn test_scores_alternating_tests_and_ignores_broken_ones() {
+        use TestOutcome::*;
+        let results = [
+            run(&[("steady", Passed), ("broken", Failed), ("racy", Passed), ("rare", Passed)]),
+            run(&[("steady", Passed), ("broken", Failed), ("racy", Failed), ("rare", Passed)]),
+            run(&[("steady", Passed), ("broken", Failed), ("racy", Passed), ("rare", Passed)]),
+            run(&[("steady", Passed), ("broken", Failed), ("racy", Skipped), ("rare", Passed)]),
+            run(&[("steady", Passed), ("broken", Failed), ("racy", Failed), ("rare", Failed)]),
+        ];
+        let flaky = detect(&results);
+        assert_eq!(flaky.len(), 2);
+        assert_eq!((flaky[0].name.as_str(), flaky[0].runs, flaky[0].failures), ("racy", 4, 2));
+        assert_eq!(flaky[0].flakiness, 1.0);
+        assert!(flaky[0].should_quarantine());
+        assert_eq!(flaky[1].name, "rare");
+        assert_eq!(flaky[1].flakiness, 0.25);
+        assert!(!flaky[1].should_quarantine());
+    }
+}
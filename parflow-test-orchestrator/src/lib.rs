@@ -1,24 +1,74 @@
 use anyhow::Result;
-use colored::*;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Serialize, Deserialize)]
+pub mod affected;
+pub mod environment;
+pub mod equivalence;
+pub mod export;
+pub mod flaky;
+mod run;
+pub mod shard;
+
+pub use affected::TestSelection;
+pub use environment::TestFramework;
+pub use equivalence::{EquivalenceCase, EquivalenceReport};
+pub use export::ExportFormat;
+pub use flaky::FlakyTest;
+
+/// An isolated place to run one framework's tests for one project.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TestEnvironment {
+    /// Framework plus the project's path relative to the root, e.g. `pytest-tools`
     pub name: String,
     pub language: String,
+    pub framework: TestFramework,
+    /// Where the tests are run from
+    pub project_dir: PathBuf,
+    /// This environment's directory in the workspace (venv, node_modules, target dir)
+    pub env_dir: PathBuf,
+    /// Program and arguments that run the suite
+    pub command: Vec<String>,
+    /// Variables pointing the tools at `env_dir`
+    pub env: Vec<(String, String)>,
+    /// Subset of the suite to run, see [`select_affected`](TestOrchestrator::select_affected)
+    pub selection: TestSelection,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TestResult {
     pub environment: String,
     pub tests_passed: usize,
     pub tests_failed: usize,
+    pub tests_skipped: usize,
     pub duration_seconds: f64,
-    pub coverage_percentage: f64,
+    /// Line coverage, `None` when no coverage tool for the framework is installed
+    pub coverage_percentage: Option<f64>,
     pub performance_metrics: TestPerformance,
+    pub cases: Vec<TestCase>,
+    /// Why the suite did not run at all (build error, missing tool)
+    pub error: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TestOutcome {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+/// One test as the framework reported it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestCase {
+    pub name: String,
+    pub outcome: TestOutcome,
+    pub duration_seconds: Option<f64>,
+    /// Failure output or skip reason
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TestPerformance {
     pub execution_time_ms: u64,
     pub memory_usage_mb: f64,
@@ -34,53 +84,310 @@ pub struct TestAnalysis {
     pub average_duration_seconds: f64,
     pub performance_bottlenecks: Vec<String>,
     pub optimization_suggestions: Vec<String>,
+    /// Tests that both passed and failed when runs were repeated, most flaky first
+    pub flaky_tests: Vec<FlakyTest>,
+    /// `environment: test` for flaky tests worth quarantining until fixed
+    pub quarantine: Vec<String>,
 }
 
-#[derive(Default)]
-pub struct TestOrchestrator;
+/// Tests at least this slow are listed as bottlenecks
+const SLOW_TEST_SECONDS: f64 = 1.0;
+/// Environments below this line coverage get a suggestion
+const LOW_COVERAGE_PERCENT: f64 = 60.0;
+
+pub struct TestOrchestrator {
+    root: PathBuf,
+    workspace: PathBuf,
+}
+
+impl Default for TestOrchestrator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl TestOrchestrator {
+    /// Test the project in the current directory
     pub fn new() -> Self {
-        Self
+        Self::for_project(std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")))
+    }
+
+    /// Test the project at `root`, keeping environments in a temp dir derived from its path
+    pub fn for_project(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        let root = root.canonicalize().unwrap_or(root);
+        let workspace = environment::default_workspace(&root);
+        Self { root, workspace }
+    }
+
+    /// Keep environments under `workspace` instead
+    pub fn with_workspace(mut self, workspace: impl Into<PathBuf>) -> Self {
+        self.workspace = workspace.into();
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
     }
 
+    pub fn workspace(&self) -> &Path {
+        &self.workspace
+    }
+
+    /// Frameworks used by the root and its immediate subdirectories, as `(framework, dir)`,
+    /// limited to `languages` unless that is empty.
+    pub fn detect_frameworks(&self, languages: &[&str]) -> Vec<(TestFramework, PathBuf)> {
+        let wanted = |framework: TestFramework| {
+            languages.is_empty() || languages.iter().any(|l| framework.matches_language(l))
+        };
+        // `cargo test` in a workspace root already covers its members
+        let workspace = std::fs::read_to_string(self.root.join("Cargo.toml"))
+            .is_ok_and(|manifest| manifest.contains("[workspace]"));
+        let covered = |framework: TestFramework, dir: &Path| {
+            framework == TestFramework::CargoTest && workspace && dir != self.root
+        };
+        environment::project_dirs(&self.root)
+            .into_iter()
+            .flat_map(|dir| {
+                TestFramework::ALL
+                    .into_iter()
+                    .filter(|&framework| {
+                        wanted(framework) && !covered(framework, &dir) && framework.detect(&dir)
+                    })
+                    .map(|framework| (framework, dir.clone()))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// One isolated environment per detected framework. An environment that cannot be set up
+    /// (no Python, offline npm) is skipped with a warning; detecting nothing is an error.
     pub async fn setup_multi_language_test_env(
         &self,
         languages: &[&str],
     ) -> Result<Vec<TestEnvironment>> {
-        println!("{} {:?}", "🧪 Setting up test environments for:".bright_green(), languages);
-        Ok(vec![TestEnvironment { name: "rust-tests".to_string(), language: "rust".to_string() }])
+        tracing::info!(?languages, root = %self.root.display(), "🧪 Setting up test environments");
+        let detected = self.detect_frameworks(languages);
+        if detected.is_empty() {
+            anyhow::bail!("no test framework found in {}", self.root.display());
+        }
+
+        let mut environments = Vec::new();
+        for (framework, dir) in detected {
+            let (root, workspace) = (self.root.clone(), self.workspace.clone());
+            let provisioned = tokio::task::spawn_blocking(move || {
+                environment::provision(framework, &root, &dir, &workspace)
+            })
+            .await?;
+            match provisioned {
+                Ok(env) => {
+                    tracing::info!(env = %env.name, dir = %env.env_dir.display(), "📦 Environment ready");
+                    environments.push(env);
+                }
+                Err(e) => tracing::warn!(%framework, error = %e, "⚠️  Environment skipped"),
+            }
+        }
+        Ok(environments)
     }
 
+    /// Narrow `environments` to the tests affected by files changed since `since` (a branch,
+    /// tag or commit), dropping environments no changed file concerns.
+    ///
+    /// # Errors
+    ///
+    /// When the project is not in a git repository or `since` is not a commit.
+    pub async fn select_affected(
+        &self,
+        environments: Vec<TestEnvironment>,
+        since: &str,
+    ) -> Result<Vec<TestEnvironment>> {
+        let changed = affected::changed_files(&self.root, since).await?;
+        tracing::info!(since, changed = changed.len(), "🔍 Selecting affected tests");
+        Ok(environments
+            .into_iter()
+            .filter_map(|mut env| match affected::select(&env, &changed) {
+                Some(selection) => {
+                    env.selection = selection;
+                    Some(env)
+                }
+                None => {
+                    tracing::info!(env = %env.name, "⏭️  No relevant changes, skipping");
+                    None
+                }
+            })
+            .collect())
+    }
+
+    /// Run every environment's suite in turn. Test failures and suites that could not run are
+    /// reported in the results rather than as an error.
     pub async fn run_cross_language_tests(
         &self,
-        _environments: &[TestEnvironment],
+        environments: &[TestEnvironment],
     ) -> Result<Vec<TestResult>> {
-        println!("{}", "🚀 Running cross-language tests...".bright_blue());
-        Ok(vec![TestResult {
-            environment: "rust-tests".to_string(),
-            tests_passed: 10,
-            tests_failed: 0,
-            duration_seconds: 2.5,
-            coverage_percentage: 85.0,
-            performance_metrics: TestPerformance {
-                execution_time_ms: 8500,
-                memory_usage_mb: 120.5,
-                cpu_usage_percent: 65.0,
-            },
-        }])
+        tracing::info!(environments = environments.len(), "🚀 Running cross-language tests");
+        let mut results = Vec::with_capacity(environments.len());
+        for env in environments {
+            let result = run::run_environment(env).await;
+            if result.error.is_none() {
+                tracing::info!(
+                    env = %result.environment,
+                    passed = result.tests_passed,
+                    failed = result.tests_failed,
+                    duration = result.duration_seconds,
+                    "✅ Suite finished"
+                );
+            }
+            results.push(result);
+        }
+        Ok(results)
+    }
+
+    /// Run the suites `repeat` times, one pass over all environments after another, so
+    /// [`analyze_test_performance`](Self::analyze_test_performance) can spot flaky tests.
+    pub async fn run_repeated(
+        &self,
+        environments: &[TestEnvironment],
+        repeat: usize,
+    ) -> Result<Vec<TestResult>> {
+        let mut results = Vec::with_capacity(environments.len() * repeat);
+        for run in 1..=repeat.max(1) {
+            tracing::info!(run, repeat, "🔁 Test run");
+            results.extend(self.run_cross_language_tests(environments).await?);
+        }
+        Ok(results)
+    }
+
+    /// Run `original` and `mirrored` over the calls in the `cases` corpus and diff their outputs,
+    /// see [`equivalence`]. Relative paths are taken from the project root.
+    /// [`EquivalenceReport::to_test_result`] puts the report alongside the suites' results.
+    pub async fn verify_equivalence(
+        &self,
+        original: &Path,
+        mirrored: &Path,
+        cases: &Path,
+    ) -> Result<EquivalenceReport> {
+        let cases = equivalence::load_cases(&self.root.join(cases))?;
+        tracing::info!(cases = cases.len(), "⚖️  Verifying equivalence");
+        equivalence::verify(&self.root.join(original), &self.root.join(mirrored), &cases).await
     }
 
     pub async fn analyze_test_performance(&self, results: &[TestResult]) -> Result<TestAnalysis> {
-        println!("{}", "📊 Analyzing test performance...".bright_magenta());
+        tracing::info!("📊 Analyzing test performance");
+        let passed: usize = results.iter().map(|r| r.tests_passed).sum();
+        let failed: usize = results.iter().map(|r| r.tests_failed).sum();
+        let executed = passed + failed;
+
+        let mut bottlenecks: Vec<String> = results
+            .iter()
+            .filter_map(|r| Some(format!("{} did not run: {}", r.environment, r.error.as_ref()?)))
+            .collect();
+        // Repeated runs report the same test several times; keep its slowest run
+        let mut slow: Vec<(&str, &TestCase, f64)> = results
+            .iter()
+            .flat_map(|r| r.cases.iter().map(move |case| (r.environment.as_str(), case)))
+            .filter_map(|(env, case)| Some((env, case, case.duration_seconds?)))
+            .filter(|(_, _, duration)| *duration >= SLOW_TEST_SECONDS)
+            .collect();
+        slow.sort_by(|a, b| b.2.total_cmp(&a.2));
+        let mut seen = HashSet::new();
+        slow.retain(|(env, case, _)| seen.insert((*env, case.name.as_str())));
+        bottlenecks.extend(
+            slow.iter().take(5).map(|(env, case, duration)| {
+                format!("{}: {} took {:.1}s", env, case.name, duration)
+            }),
+        );
+
+        let suggestions: Vec<String> = results
+            .iter()
+            .filter(|r| r.error.is_none())
+            .filter_map(|r| match r.coverage_percentage {
+                Some(coverage) if coverage < LOW_COVERAGE_PERCENT => {
+                    Some(format!("Raise test coverage of {} ({:.1}%)", r.environment, coverage))
+                }
+                Some(_) => None,
+                None => Some(format!("Install a coverage tool to measure {}", r.environment)),
+            })
+            .collect();
+
+        let flaky_tests = flaky::detect(results);
+        let quarantine = flaky_tests
+            .iter()
+            .filter(|test| test.should_quarantine())
+            .map(|test| format!("{}: {}", test.environment, test.name))
+            .collect();
+
         Ok(TestAnalysis {
-            total_environments: results.len(),
-            total_tests: results.iter().map(|r| r.tests_passed + r.tests_failed).sum(),
-            passed_tests: results.iter().map(|r| r.tests_passed).sum(),
-            success_rate: 100.0,
-            average_duration_seconds: 2.5,
-            performance_bottlenecks: vec![],
-            optimization_suggestions: vec![],
+            total_environments: unique(results.iter().map(|r| r.environment.clone())).len(),
+            total_tests: executed + results.iter().map(|r| r.tests_skipped).sum::<usize>(),
+            passed_tests: passed,
+            success_rate: if executed == 0 {
+                100.0
+            } else {
+                passed as f64 / executed as f64 * 100.0
+            },
+            average_duration_seconds: if results.is_empty() {
+                0.0
+            } else {
+                results.iter().map(|r| r.duration_seconds).sum::<f64>() / results.len() as f64
+            },
+            performance_bottlenecks: unique(bottlenecks),
+            optimization_suggestions: unique(suggestions),
+            flaky_tests,
+            quarantine,
         })
     }
 }
+
+/// `items` without repeats, in first-seen order
+fn unique(items: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    items.into_iter().filter(|item| seen.insert(item.clone())).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_detects_frameworks_and_isolates_environments() {
+        let root = std::env::temp_dir().join(format!("parflow-test-detect-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("web")).unwrap();
+        std::fs::create_dir_all(root.join("tools/tests")).unwrap();
+        std::fs::create_dir_all(root.join("member")).unwrap();
+        std::fs::write(root.join("Cargo.toml"), "[workspace]\nmembers = [\"member\"]\n").unwrap();
+        std::fs::write(root.join("member/Cargo.toml"), "[package]\nname = \"member\"\n").unwrap();
+        std::fs::write(root.join("go.mod"), "module demo\n").unwrap();
+        std::fs::write(root.join("tools/tests/test_tools.py"), "def test_ok(): pass\n").unwrap();
+        std::fs::write(root.join("web/package.json"), r#"{"devDependencies": {"jest": "29"}}"#)
+            .unwrap();
+
+        let orchestrator = TestOrchestrator::for_project(&root).with_workspace(root.join(".ws"));
+        let detected: Vec<_> = orchestrator
+            .detect_frameworks(&[])
+            .into_iter()
+            .map(|(framework, dir)| (framework, dir.file_name().unwrap().to_owned()))
+            .collect();
+        let root_name = orchestrator.root().file_name().unwrap().to_owned();
+        assert_eq!(
+            detected,
+            vec![
+                (TestFramework::CargoTest, root_name.clone()),
+                (TestFramework::GoTest, root_name),
+                (TestFramework::Pytest, "tools".into()),
+                (TestFramework::Jest, "web".into()),
+            ]
+        );
+        assert_eq!(orchestrator.detect_frameworks(&["go"]).len(), 1);
+
+        let environments =
+            orchestrator.setup_multi_language_test_env(&["rust", "go"]).await.unwrap();
+        assert_eq!(environments.len(), 2);
+        let cargo = &environments[0];
+        assert_eq!(cargo.name, "cargo-test");
+        assert!(cargo.env_dir.starts_with(root.join(".ws")) && cargo.env_dir.is_dir());
+        assert!(cargo.env.iter().any(|(name, _)| name == "CARGO_TARGET_DIR"));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,492 @@
+//! Running an environment's suite and reading its results
+//!
+//! Each framework is asked for machine-readable output: libtest's JSON events from
+//! `cargo test`, `go test -json`, jest's `--json` report and pytest-json-report's file when that
+//! plugin is installed. Where a format is unavailable (a stable libtest that rejects the JSON
+//! flag, pytest without the plugin) the human-readable per-test lines are parsed instead.
+//! Coverage comes from cargo-tarpaulin, coverage.py and jest's built-in istanbul, and is left
+//! unset when the tool is not installed.
+
+use crate::{TestCase, TestEnvironment, TestFramework, TestOutcome, TestPerformance, TestResult};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::process::Output;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+
+/// Lines of stderr kept when a suite fails to run
+const ERROR_TAIL_LINES: usize = 20;
+
+/// Run `env`'s suite. Failing tests are part of the result; a suite that does not run at all
+/// (build error, missing tool) comes back with `error` set and no cases.
+pub(crate) async fn run_environment(env: &TestEnvironment) -> TestResult {
+    let mut result = TestResult { environment: env.name.clone(), ..Default::default() };
+    let usage_before = ChildUsage::now();
+    let started = Instant::now();
+
+    let outcome = match env.framework {
+        TestFramework::CargoTest => run_cargo(env).await,
+        TestFramework::Pytest => run_pytest(env).await,
+        TestFramework::Jest => run_jest(env).await,
+        TestFramework::GoTest => run_go(env).await,
+    };
+
+    let elapsed = started.elapsed();
+    let usage = ChildUsage::now().since(&usage_before);
+    result.duration_seconds = elapsed.as_secs_f64();
+    result.performance_metrics = TestPerformance {
+        execution_time_ms: elapsed.as_millis() as u64,
+        memory_usage_mb: usage.max_rss_mb,
+        cpu_usage_percent: usage.cpu.as_secs_f64() / elapsed.as_secs_f64().max(1e-9) * 100.0,
+    };
+
+    match outcome {
+        Ok((cases, coverage)) => {
+            for case in &cases {
+                match case.outcome {
+                    TestOutcome::Passed => result.tests_passed += 1,
+                    TestOutcome::Failed => result.tests_failed += 1,
+                    TestOutcome::Skipped => result.tests_skipped += 1,
+                }
+            }
+            result.cases = cases;
+            result.coverage_percentage = coverage;
+        }
+        Err(error) => {
+            tracing::warn!(env = %env.name, %error, "⚠️  Test suite did not run");
+            result.error = Some(error);
+        }
+    }
+    result
+}
+
+type Parsed = Result<(Vec<TestCase>, Option<f64>), String>;
+
+async fn run_cargo(env: &TestEnvironment) -> Parsed {
+    let selection = &env.selection;
+    let command = [env.command.as_slice(), &selection.args].concat();
+    let mut args = vec!["--no-fail-fast", "--"];
+    args.extend(selection.filters.iter().map(String::as_str));
+    let json = [args.as_slice(), &["-Z", "unstable-options", "--format", "json", "--report-time"]]
+        .concat();
+    let mut output = execute(env, &command, &json).await?;
+    let mut cases = parse_cargo(&String::from_utf8_lossy(&output.stdout));
+    if cases.is_empty() && rejects_json(&String::from_utf8_lossy(&output.stderr)) {
+        // The JSON format is still behind -Z on stable, whose per-test lines are parsed instead
+        output = execute(env, &command, &args).await?;
+        cases = parse_cargo(&String::from_utf8_lossy(&output.stdout));
+    }
+    check_ran(&output, &cases)?;
+
+    let coverage = if on_path("cargo-tarpaulin") {
+        let target = env.env_dir.join("tarpaulin");
+        let target = target.to_string_lossy();
+        let tarpaulin =
+            [&["cargo".to_string(), "tarpaulin".to_string()], &selection.args[..]].concat();
+        let args = ["--skip-clean", "--target-dir", target.as_ref()];
+        match execute(env, &tarpaulin, &args).await {
+            Ok(output) => parse_tarpaulin(&String::from_utf8_lossy(&output.stdout)),
+            Err(error) => {
+                tracing::warn!(env = %env.name, %error, "⚠️  Coverage skipped");
+                None
+            }
+        }
+    } else {
+        None
+    };
+    Ok((cases, coverage))
+}
+
+async fn run_pytest(env: &TestEnvironment) -> Parsed {
+    let python = &env.command[0];
+    let report = env.env_dir.join("pytest-report.json");
+    let data_file = format!("--data-file={}", env.env_dir.join(".coverage").display());
+    let json_report = importable(python, "pytest_jsonreport").await;
+    let with_coverage = importable(python, "coverage").await;
+
+    let mut command = env.command.clone();
+    if with_coverage {
+        // python -m coverage run --data-file=... -m pytest ...
+        command.splice(1..1, ["-m", "coverage", "run", &data_file].map(String::from));
+    }
+    let mut args = vec!["-rA".to_string()];
+    if json_report {
+        args.push("--json-report".to_string());
+        args.push(format!("--json-report-file={}", report.display()));
+    }
+    args.extend(env.selection.args.iter().cloned());
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let _ = std::fs::remove_file(&report);
+    let output = execute(env, &command, &args).await?;
+
+    let cases = match std::fs::read_to_string(&report) {
+        Ok(json) if json_report => parse_pytest_report(&json),
+        _ => parse_pytest_summary(&String::from_utf8_lossy(&output.stdout)),
+    };
+    check_ran(&output, &cases)?;
+
+    let coverage = if with_coverage {
+        let report = [python.clone(), "-m".to_string(), "coverage".to_string()];
+        execute(env, &report, &["report", &data_file])
+            .await
+            .ok()
+            .and_then(|output| parse_coverage_py(&String::from_utf8_lossy(&output.stdout)))
+    } else {
+        None
+    };
+    Ok((cases, coverage))
+}
+
+async fn run_jest(env: &TestEnvironment) -> Parsed {
+    let report = env.env_dir.join("jest-results.json");
+    let coverage_dir = env.env_dir.join("coverage");
+    let mut args = vec![
+        "--json".to_string(),
+        format!("--outputFile={}", report.display()),
+        "--coverage".to_string(),
+        "--coverageReporters=json-summary".to_string(),
+        format!("--coverageDirectory={}", coverage_dir.display()),
+    ];
+    args.extend(env.selection.args.iter().cloned());
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let _ = std::fs::remove_file(&report);
+    let output = execute(env, &env.command, &args).await?;
+
+    let cases = std::fs::read_to_string(&report).map(|json| parse_jest(&json)).unwrap_or_default();
+    check_ran(&output, &cases)?;
+    let coverage = std::fs::read_to_string(coverage_dir.join("coverage-summary.json"))
+        .ok()
+        .and_then(|json| parse_istanbul_summary(&json));
+    Ok((cases, coverage))
+}
+
+async fn run_go(env: &TestEnvironment) -> Parsed {
+    // Flags go before the packages, which the selection narrows from `./...`
+    let mut command = env.command.clone();
+    if !env.selection.args.is_empty() {
+        command.truncate(2);
+        command.extend(env.selection.args.iter().cloned());
+    }
+    command.splice(2..2, ["-json", "-cover"].map(String::from));
+    let output = execute(env, &command, &[]).await?;
+    let (cases, coverage) = parse_go(&String::from_utf8_lossy(&output.stdout));
+    check_ran(&output, &cases)?;
+    Ok((cases, coverage))
+}
+
+/// Run `command` plus `args` in the project with the environment's variables.
+async fn execute(
+    env: &TestEnvironment,
+    command: &[String],
+    args: &[&str],
+) -> Result<Output, String> {
+    let (program, base) = command.split_first().ok_or("empty test command")?;
+    Command::new(program)
+        .args(base)
+        .args(args)
+        .current_dir(&env.project_dir)
+        .envs(env.env.iter().map(|(name, value)| (name, value)))
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| format!("could not start `{}`: {}", program, e))
+}
+
+/// A non-zero exit without a single test result means the suite never ran.
+fn check_ran(output: &Output, cases: &[TestCase]) -> Result<(), String> {
+    if output.status.success() || !cases.is_empty() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let lines: Vec<&str> = stderr.lines().collect();
+    let tail = lines[lines.len().saturating_sub(ERROR_TAIL_LINES)..].join("\n");
+    Err(format!("exited with {}: {}", output.status, tail.trim()))
+}
+
+async fn importable(python: &str, module: &str) -> bool {
+    Command::new(python)
+        .args(["-c", &format!("import {}", module)])
+        .output()
+        .await
+        .is_ok_and(|output| output.status.success())
+}
+
+fn on_path(binary: &str) -> bool {
+    let Some(path) = std::env::var_os("PATH") else { return false };
+    std::env::split_paths(&path)
+        .any(|dir| dir.join(binary).is_file() || dir.join(format!("{}.exe", binary)).is_file())
+}
+
+fn case(name: &str, outcome: TestOutcome) -> TestCase {
+    TestCase { name: name.to_string(), outcome, duration_seconds: None, message: None }
+}
+
+/// libtest JSON events, falling back to `test name ... ok` lines when JSON was refused
+/// Whether libtest refused the `-Z` flags, as a stable toolchain does
+fn rejects_json(stderr: &str) -> bool {
+    stderr.contains("only accepted on the nightly compiler")
+}
+
+fn parse_cargo(stdout: &str) -> Vec<TestCase> {
+    let mut cases = Vec::new();
+    for line in stdout.lines() {
+        if let Ok(event) = serde_json::from_str::<Value>(line) {
+            if event["type"] != "test" {
+                continue;
+            }
+            let outcome = match event["event"].as_str() {
+                Some("ok") => TestOutcome::Passed,
+                Some("failed" | "timeout") => TestOutcome::Failed,
+                Some("ignored") => TestOutcome::Skipped,
+                _ => continue,
+            };
+            let mut test = case(event["name"].as_str().unwrap_or_default(), outcome);
+            test.duration_seconds = event["exec_time"].as_f64();
+            test.message = event["stdout"].as_str().map(str::to_string);
+            cases.push(test);
+        } else if let Some((name, status)) =
+            line.strip_prefix("test ").and_then(|rest| rest.split_once(" ... "))
+        {
+            let outcome = match status {
+                "ok" => TestOutcome::Passed,
+                "FAILED" => TestOutcome::Failed,
+                status if status.starts_with("ignored") => TestOutcome::Skipped,
+                _ => continue,
+            };
+            cases.push(case(name, outcome));
+        }
+    }
+    cases
+}
+
+/// "62.50% coverage, 5/8 lines covered"
+fn parse_tarpaulin(stdout: &str) -> Option<f64> {
+    stdout.lines().rev().find_map(|line| line.split_once("% coverage")?.0.trim().parse().ok())
+}
+
+fn pytest_outcome(outcome: &str) -> TestOutcome {
+    match outcome.to_lowercase().as_str() {
+        "passed" | "xfailed" | "xfail" => TestOutcome::Passed,
+        "skipped" => TestOutcome::Skipped,
+        _ => TestOutcome::Failed,
+    }
+}
+
+/// pytest-json-report's `tests` array
+fn parse_pytest_report(json: &str) -> Vec<TestCase> {
+    let Ok(report) = serde_json::from_str::<Value>(json) else { return Vec::new() };
+    let Some(tests) = report["tests"].as_array() else { return Vec::new() };
+    tests
+        .iter()
+        .map(|test| {
+            let outcome = pytest_outcome(test["outcome"].as_str().unwrap_or_default());
+            let mut case = case(test["nodeid"].as_str().unwrap_or_default(), outcome);
+            let phases = ["setup", "call", "teardown"].map(|phase| &test[phase]);
+            case.duration_seconds =
+                Some(phases.iter().filter_map(|p| p["duration"].as_f64()).sum());
+            case.message = phases.iter().find_map(|p| p["longrepr"].as_str()).map(str::to_string);
+            case
+        })
+        .collect()
+}
+
+/// The `-rA` short summary: "PASSED tests/test_a.py::test_ok",
+/// "FAILED tests/test_a.py::test_bad - assert 1 == 2"
+fn parse_pytest_summary(stdout: &str) -> Vec<TestCase> {
+    const OUTCOMES: [&str; 6] = ["PASSED", "FAILED", "ERROR", "SKIPPED", "XFAIL", "XPASS"];
+    stdout
+        .lines()
+        .filter_map(|line| {
+            let (outcome, rest) = line.split_once(' ')?;
+            OUTCOMES.contains(&outcome).then_some(())?;
+            let (name, message) = match rest.split_once(" - ") {
+                Some((name, message)) => (name, Some(message.to_string())),
+                None => (rest, None),
+            };
+            let mut case = case(name.trim(), pytest_outcome(outcome));
+            case.message = message;
+            Some(case)
+        })
+        .collect()
+}
+
+/// "TOTAL    120     30    75%"
+fn parse_coverage_py(stdout: &str) -> Option<f64> {
+    let line = stdout.lines().find(|line| line.starts_with("TOTAL"))?;
+    line.split_whitespace().last()?.trim_end_matches('%').parse().ok()
+}
+
+fn parse_jest(json: &str) -> Vec<TestCase> {
+    let Ok(report) = serde_json::from_str::<Value>(json) else { return Vec::new() };
+    let Some(files) = report["testResults"].as_array() else { return Vec::new() };
+    files
+        .iter()
+        .flat_map(|file| file["assertionResults"].as_array().into_iter().flatten())
+        .map(|test| {
+            let outcome = match test["status"].as_str() {
+                Some("passed") => TestOutcome::Passed,
+                Some("failed") => TestOutcome::Failed,
+                _ => TestOutcome::Skipped,
+            };
+            let mut case = case(test["fullName"].as_str().unwrap_or_default(), outcome);
+            case.duration_seconds = test["duration"].as_f64().map(|ms| ms / 1000.0);
+            let failures: Vec<&str> = test["failureMessages"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+                .collect();
+            case.message = (!failures.is_empty()).then(|| failures.join("\n"));
+            case
+        })
+        .collect()
+}
+
+/// Line coverage from istanbul's `coverage-summary.json`
+fn parse_istanbul_summary(json: &str) -> Option<f64> {
+    serde_json::from_str::<Value>(json).ok()?["total"]["lines"]["pct"].as_f64()
+}
+
+/// `go test -json` events, with coverage averaged over the packages that report it
+fn parse_go(stdout: &str) -> (Vec<TestCase>, Option<f64>) {
+    let mut cases = Vec::new();
+    let mut output: HashMap<(String, String), String> = HashMap::new();
+    let mut coverage = Vec::new();
+    for event in stdout.lines().filter_map(|line| serde_json::from_str::<Value>(line).ok()) {
+        let package = event["Package"].as_str().unwrap_or_default().to_string();
+        let action = event["Action"].as_str().unwrap_or_default();
+        let Some(test) = event["Test"].as_str() else {
+            // "coverage: 75.0% of statements"
+            let percent = event["Output"].as_str().and_then(|line| {
+                line.split_once("coverage: ")?.1.split_once('%')?.0.parse::<f64>().ok()
+            });
+            coverage.extend(percent);
+            continue;
+        };
+        let key = (package, test.to_string());
+        let outcome = match action {
+            "output" => {
+                output.entry(key).or_default().push_str(event["Output"].as_str().unwrap_or(""));
+                continue;
+            }
+            "pass" => TestOutcome::Passed,
+            "fail" => TestOutcome::Failed,
+            "skip" => TestOutcome::Skipped,
+            _ => continue,
+        };
+        let mut case = case(&format!("{}::{}", key.0, key.1), outcome);
+        case.duration_seconds = event["Elapsed"].as_f64();
+        if outcome == TestOutcome::Failed {
+            case.message = output.remove(&key);
+        }
+        cases.push(case);
+    }
+    let coverage =
+        (!coverage.is_empty()).then(|| coverage.iter().sum::<f64>() / coverage.len() as f64);
+    (cases, coverage)
+}
+
+/// CPU time and peak memory of the waited-for child processes, from `getrusage`
+#[derive(Default)]
+struct ChildUsage {
+    cpu: Duration,
+    max_rss_mb: f64,
+}
+
+impl ChildUsage {
+    #[cfg(unix)]
+    fn now() -> Self {
+        // SAFETY: getrusage only writes the struct it is handed
+        let usage = unsafe {
+            let mut usage = std::mem::zeroed::<libc::rusage>();
+            libc::getrusage(libc::RUSAGE_CHILDREN, &mut usage);
+            usage
+        };
+        let time = |tv: libc::timeval| {
+            Duration::from_secs(tv.tv_sec as u64) + Duration::from_micros(tv.tv_usec as u64)
+        };
+        // ru_maxrss is in kilobytes, except on macOS where it is in bytes
+        let rss_bytes = if cfg!(target_os = "macos") {
+            usage.ru_maxrss as f64
+        } else {
+            usage.ru_maxrss as f64 * 1024.0
+        };
+        Self { cpu: time(usage.ru_utime) + time(usage.ru_stime), max_rss_mb: rss_bytes / 1e6 }
+    }
+
+    #[cfg(not(unix))]
+    fn now() -> Self {
+        Self::default()
+    }
+
+    /// Usage accrued after `earlier`. The peak is the largest single child so far, so it only
+    /// covers this run when that run set a new peak.
+    fn since(&self, earlier: &ChildUsage) -> ChildUsage {
+        ChildUsage { cpu: self.cpu.saturating_sub(earlier.cpu), max_rss_mb: self.max_rss_mb }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_each_framework_output() {
+        let cargo = parse_cargo(concat!(
+            "{ \"type\": \"suite\", \"event\": \"started\", \"test_count\": 3 }\n",
+            "{ \"type\": \"test\", \"event\": \"ok\", \"name\": \"a::works\", \"exec_time\": 0.5 }\n",
+            "{ \"type\": \"test\", \"event\": \"failed\", \"name\": \"a::breaks\", \"stdout\": \"boom\" }\n",
+            "test b::legacy ... ignored, slow\n",
+        ));
+        assert_eq!(cargo.len(), 3);
+        assert_eq!(cargo[0].duration_seconds, Some(0.5));
+        assert_eq!(
+            (cargo[1].outcome, cargo[1].message.as_deref()),
+            (TestOutcome::Failed, Some("boom"))
+        );
+        assert_eq!(cargo[2].outcome, TestOutcome::Skipped);
+        assert!(rejects_json("error: the option `Z` is only accepted on the nightly compiler\n"));
+        assert!(!rejects_json("error[E0425]: cannot find value `x` in this scope\n"));
+        assert_eq!(
+            parse_tarpaulin("|| Tested/Total Lines:\n62.50% coverage, 5/8 lines covered\n"),
+            Some(62.5)
+        );
+
+        let pytest = parse_pytest_summary(concat!(
+            "=== short test summary info ===\n",
+            "PASSED tests/test_a.py::test_ok\n",
+            "FAILED tests/test_a.py::test_bad - assert 1 == 2\n",
+        ));
+        assert_eq!(pytest[1].name, "tests/test_a.py::test_bad");
+        assert_eq!(pytest[1].message.as_deref(), Some("assert 1 == 2"));
+        let report = r#"{"tests": [{"nodeid": "t.py::x", "outcome": "passed",
+            "setup": {"duration": 0.25}, "call": {"duration": 0.5}}]}"#;
+        assert_eq!(parse_pytest_report(report)[0].duration_seconds, Some(0.75));
+        assert_eq!(
+            parse_coverage_py("Name  Stmts  Miss  Cover\nTOTAL   120   30   75%\n"),
+            Some(75.0)
+        );
+
+        let jest = parse_jest(
+            r#"{"testResults": [{"assertionResults": [
+                {"fullName": "sum adds", "status": "passed", "duration": 4},
+                {"fullName": "sum fails", "status": "failed", "failureMessages": ["expected 3"]}]}]}"#,
+        );
+        assert_eq!(
+            (jest[0].duration_seconds, jest[1].message.as_deref()),
+            (Some(0.004), Some("expected 3"))
+        );
+        assert_eq!(parse_istanbul_summary(r#"{"total": {"lines": {"pct": 81.5}}}"#), Some(81.5));
+
+        let (go, coverage) = parse_go(concat!(
+            "{\"Action\":\"output\",\"Package\":\"demo\",\"Test\":\"TestBad\",\"Output\":\"want 2\\n\"}\n",
+            "{\"Action\":\"fail\",\"Package\":\"demo\",\"Test\":\"TestBad\",\"Elapsed\":0.01}\n",
+            "{\"Action\":\"pass\",\"Package\":\"demo\",\"Test\":\"TestOk\",\"Elapsed\":0}\n",
+            "{\"Action\":\"output\",\"Package\":\"demo\",\"Output\":\"coverage: 80.0% of statements\\n\"}\n",
+        ));
+        assert_eq!(go[0].name, "demo::TestBad");
+        assert_eq!(go[0].message.as_deref(), Some("want 2\n"));
+        assert_eq!(go[1].outcome, TestOutcome::Passed);
+        assert_eq!(coverage, Some(80.0));
+    }
+}
@@ -0,0 +1,203 @@
+//! Splitting suites into shards that run in parallel on several machines
+//!
+//! A suite splits into units along the lines its framework can select on its own: cargo
+//! workspace members, pytest and jest test files, go packages. Units keep their environment's
+//! name, so [`merge`] can fold the per-unit results back into one result per environment. A
+//! suite that is already narrowed (see [`crate::affected`]) or has a single unit stays whole.
+//!
+//! Units are dealt largest first to the worker whose share would end lowest relative to its
+//! cores, so a worker with twice the cores gets about twice the work.
+
+use crate::affected::{find_files, is_python_test, package_name};
+use crate::environment::project_dirs;
+use crate::{TestEnvironment, TestFramework, TestPerformance, TestResult};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// A machine that runs shards, weighted by its cores
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShardWorker {
+    pub id: String,
+    pub cores: u32,
+}
+
+/// The units one worker runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Shard {
+    pub worker: String,
+    pub units: Vec<TestEnvironment>,
+}
+
+/// Split `env` into independently runnable units, or return it whole.
+pub fn split(env: &TestEnvironment) -> Vec<TestEnvironment> {
+    if !env.selection.is_everything() {
+        return vec![env.clone()];
+    }
+    let project = env.project_dir.as_path();
+    let relative = |path: &Path| {
+        path.strip_prefix(project).unwrap_or(path).to_string_lossy().replace('\\', "/")
+    };
+    let selections: Vec<(Vec<String>, String)> = match env.framework {
+        TestFramework::CargoTest => project_dirs(project)
+            .iter()
+            .filter_map(|dir| package_name(&std::fs::read_to_string(dir.join("Cargo.toml")).ok()?))
+            .map(|package| {
+                (vec!["-p".to_string(), package.clone()], format!("package {}", package))
+            })
+            .collect(),
+        TestFramework::Pytest => find_files(project, &is_python_test)
+            .iter()
+            .map(|file| (vec![relative(file)], format!("file {}", relative(file))))
+            .collect(),
+        TestFramework::Jest => find_files(project, &is_jest_test)
+            .iter()
+            .map(|file| {
+                let file = relative(file);
+                (vec!["--runTestsByPath".to_string(), file.clone()], format!("file {}", file))
+            })
+            .collect(),
+        TestFramework::GoTest => {
+            let mut packages: Vec<String> = find_files(project, &|name| name.ends_with("_test.go"))
+                .iter()
+                .map(|file| match relative(file.parent().unwrap_or(project)) {
+                    dir if dir.is_empty() => ".".to_string(),
+                    dir => format!("./{}", dir),
+                })
+                .collect();
+            packages.sort();
+            packages.dedup();
+            packages.into_iter().map(|p| (vec![p.clone()], format!("package {}", p))).collect()
+        }
+    };
+    if selections.len() < 2 {
+        return vec![env.clone()];
+    }
+    selections
+        .into_iter()
+        .map(|(args, unit)| {
+            let mut unit_env = env.clone();
+            unit_env.selection.args = args;
+            unit_env.selection.heuristics = vec![format!("shard unit: {}", unit)];
+            unit_env
+        })
+        .collect()
+}
+
+fn is_jest_test(name: &str) -> bool {
+    let Some((stem, extension)) = name.rsplit_once('.') else { return false };
+    matches!(extension, "js" | "jsx" | "ts" | "tsx" | "mjs" | "cjs")
+        && (stem.ends_with(".test") || stem.ends_with(".spec"))
+}
+
+/// Deal `units` to `workers` in proportion to their cores. Every worker gets a shard, possibly
+/// empty; no workers means no shards.
+pub fn plan(units: Vec<TestEnvironment>, workers: &[ShardWorker]) -> Vec<Shard> {
+    let mut shards: Vec<Shard> = workers
+        .iter()
+        .map(|worker| Shard { worker: worker.id.clone(), units: Vec::new() })
+        .collect();
+    if shards.is_empty() {
+        return shards;
+    }
+    // Whole suites first: they are the largest units
+    let mut units = units;
+    units.sort_by_key(|unit| !unit.selection.is_everything());
+    for unit in units {
+        let finish = |index: usize| {
+            (shards[index].units.len() + 1) as f64 / workers[index].cores.max(1) as f64
+        };
+        let best = (0..shards.len())
+            .min_by(|&a, &b| finish(a).total_cmp(&finish(b)))
+            .expect("at least one worker");
+        shards[best].units.push(unit);
+    }
+    shards
+}
+
+/// Fold per-unit results into one per environment, in first-seen order. Coverage of units
+/// cannot be combined without their line counts, so a merged result only keeps it when it came
+/// from a single unit.
+pub fn merge(results: Vec<TestResult>) -> Vec<TestResult> {
+    let mut merged: Vec<TestResult> = Vec::new();
+    for result in results {
+        let Some(into) = merged.iter_mut().find(|m| m.environment == result.environment) else {
+            merged.push(result);
+            continue;
+        };
+        into.tests_passed += result.tests_passed;
+        into.tests_failed += result.tests_failed;
+        into.tests_skipped += result.tests_skipped;
+        into.duration_seconds += result.duration_seconds;
+        into.coverage_percentage = None;
+        let (a, b) = (&into.performance_metrics, &result.performance_metrics);
+        let time = a.execution_time_ms + b.execution_time_ms;
+        into.performance_metrics = TestPerformance {
+            execution_time_ms: time,
+            memory_usage_mb: a.memory_usage_mb.max(b.memory_usage_mb),
+            cpu_usage_percent: (a.cpu_usage_percent * a.execution_time_ms as f64
+                + b.cpu_usage_percent * b.execution_time_ms as f64)
+                / time.max(1) as f64,
+        };
+        into.cases.extend(result.cases);
+        into.error = match (into.error.take(), result.error) {
+            (Some(a), Some(b)) => Some(format!("{}; {}", a, b)),
+            (a, b) => a.or(b),
+        };
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TestSelection;
+
+    #[test]
+    fn test_splits_plans_by_cores_and_merges() {
+        let root = std::env::temp_dir().join(format!("parflow-test-shard-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        for dir in ["a", "b", "c", "docs"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        std::fs::write(root.join("Cargo.toml"), "[workspace]\n").unwrap();
+        for member in ["a", "b", "c"] {
+            let manifest = format!("[package]\nname = \"{}\"\n", member);
+            std::fs::write(root.join(member).join("Cargo.toml"), manifest).unwrap();
+        }
+        let env = TestEnvironment {
+            name: "cargo-test".to_string(),
+            language: "rust".to_string(),
+            framework: TestFramework::CargoTest,
+            project_dir: root.clone(),
+            env_dir: root.join(".ws"),
+            command: vec!["cargo".to_string(), "test".to_string()],
+            env: Vec::new(),
+            selection: TestSelection::default(),
+        };
+
+        let units = split(&env);
+        let packages: Vec<&str> = units.iter().map(|u| u.selection.args[1].as_str()).collect();
+        assert_eq!(packages, ["a", "b", "c"]);
+        assert!(units.iter().all(|u| u.name == "cargo-test"));
+
+        let workers = [
+            ShardWorker { id: "big".to_string(), cores: 8 },
+            ShardWorker { id: "small".to_string(), cores: 4 },
+        ];
+        let shards = plan(units, &workers);
+        assert_eq!((shards[0].units.len(), shards[1].units.len()), (2, 1));
+        assert!(plan(vec![env.clone()], &[]).is_empty());
+
+        let unit_result = |passed, coverage| TestResult {
+            environment: "cargo-test".to_string(),
+            tests_passed: passed,
+            coverage_percentage: coverage,
+            ..Default::default()
+        };
+        let merged = merge(vec![unit_result(3, Some(80.0)), unit_result(2, None)]);
+        assert_eq!(merged.len(), 1);
+        assert_eq!((merged[0].tests_passed, merged[0].coverage_percentage), (5, None));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,306 @@
+//! Per-function complexity from tree-sitter parses
+//!
+//! [`analyze_functions`] finds every function in a Python, Rust, JavaScript or TypeScript source
+//! and measures it:
+//!
+//! - cyclomatic complexity: 1 plus one per branch, loop, `catch`/`except`, match arm or `case`,
+//!   ternary and `&&`/`||`/`and`/`or`
+//! - cognitive complexity (SonarSource): one per branch and loop plus one per level it is nested
+//!   in, one per `else`/`elif`, and one per run of the same boolean operator
+//! - the deepest nesting of branches and loops, and the parameter count
+//!
+//! Nested functions are measured on their own and left out of the function around them.
+
+use serde::Serialize;
+use tree_sitter::{Language, Node, Parser};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FunctionComplexity {
+    pub name: String,
+    /// First line, counted from 1
+    pub line: usize,
+    pub end_line: usize,
+    pub parameters: usize,
+    pub cyclomatic: usize,
+    pub cognitive: usize,
+    pub max_nesting: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ComplexityReport {
+    pub language: String,
+    /// In source order
+    pub functions: Vec<FunctionComplexity>,
+}
+
+impl ComplexityReport {
+    /// The `count` functions hardest to follow, by cognitive then cyclomatic complexity
+    pub fn hotspots(&self, count: usize) -> Vec<&FunctionComplexity> {
+        let mut ranked: Vec<&FunctionComplexity> = self.functions.iter().collect();
+        ranked.sort_by(|a, b| {
+            (b.cognitive, b.cyclomatic).cmp(&(a.cognitive, a.cyclomatic)).then(a.line.cmp(&b.line))
+        });
+        ranked.truncate(count);
+        ranked
+    }
+}
+
+/// Node kinds that matter for one language
+struct Grammar {
+    language: Language,
+    functions: &'static [&'static str],
+    /// Branches and loops: count for both metrics and nest what they contain
+    nesting: &'static [&'static str],
+    /// `elif` and `else`: count once for cognitive complexity, without nesting
+    flat: &'static [&'static str],
+    /// Match arms and `case`s: count for cyclomatic complexity only
+    cases: &'static [&'static str],
+    /// Nodes holding `operator` fields such as `&&`
+    logical: &'static [&'static str],
+    operators: &'static [&'static str],
+}
+
+fn grammar(language: &str) -> Option<Grammar> {
+    const JS_FUNCTIONS: &[&str] = &[
+        "function_declaration",
+        "function_expression",
+        "generator_function_declaration",
+        "arrow_function",
+        "method_definition",
+    ];
+    const JS_NESTING: &[&str] = &[
+        "if_statement",
+        "for_statement",
+        "for_in_statement",
+        "while_statement",
+        "do_statement",
+        "switch_statement",
+        "catch_clause",
+        "ternary_expression",
+    ];
+    let js = |language: Language| Grammar {
+        language,
+        functions: JS_FUNCTIONS,
+        nesting: JS_NESTING,
+        flat: &["else_clause"],
+        cases: &["switch_case"],
+        logical: &["binary_expression"],
+        operators: &["&&", "||", "??"],
+    };
+    Some(match language.to_lowercase().as_str() {
+        "python" => Grammar {
+            language: tree_sitter_python::LANGUAGE.into(),
+            functions: &["function_definition"],
+            nesting: &[
+                "if_statement",
+                "for_statement",
+                "while_statement",
+                "except_clause",
+                "match_statement",
+                "conditional_expression",
+            ],
+            flat: &["elif_clause", "else_clause"],
+            cases: &["case_clause", "for_in_clause", "if_clause"],
+            logical: &["boolean_operator"],
+            operators: &["and", "or"],
+        },
+        "rust" => Grammar {
+            language: tree_sitter_rust::LANGUAGE.into(),
+            functions: &["function_item"],
+            nesting: &[
+                "if_expression",
+                "for_expression",
+                "while_expression",
+                "loop_expression",
+                "match_expression",
+            ],
+            flat: &["else_clause"],
+            cases: &["match_arm"],
+            logical: &["binary_expression"],
+            operators: &["&&", "||"],
+        },
+        "javascript" => js(tree_sitter_javascript::LANGUAGE.into()),
+        "typescript" => js(tree_sitter_typescript::LANGUAGE_TSX.into()),
+        _ => return None,
+    })
+}
+
+/// Measure every function in `code`; `None` for a language without a grammar
+pub fn analyze_functions(code: &str, language: &str) -> Option<ComplexityReport> {
+    let grammar = grammar(language)?;
+    let mut parser = Parser::new();
+    parser.set_language(&grammar.language).ok()?;
+    let tree = parser.parse(code, None)?;
+
+    let mut functions = Vec::new();
+    collect_functions(&grammar, tree.root_node(), code.as_bytes(), &mut functions);
+    functions.sort_by_key(|function| function.line);
+    Some(ComplexityReport { language: language.to_lowercase(), functions })
+}
+
+fn collect_functions(
+    grammar: &Grammar,
+    node: Node,
+    source: &[u8],
+    out: &mut Vec<FunctionComplexity>,
+) {
+    if grammar.functions.contains(&node.kind()) {
+        out.push(measure(grammar, node, source));
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_functions(grammar, child, source, out);
+    }
+}
+
+fn function_name(node: Node, source: &[u8]) -> String {
+    let name = node.child_by_field_name("name").or_else(|| {
+        // `const handler = () => ...` names the arrow function after its variable
+        let parent = node.parent().filter(|parent| parent.kind() == "variable_declarator")?;
+        parent.child_by_field_name("name")
+    });
+    name.and_then(|name| name.utf8_text(source).ok())
+        .map(str::to_string)
+        .unwrap_or_else(|| "<anonymous>".to_string())
+}
+
+fn parameter_count(node: Node, source: &[u8]) -> usize {
+    let Some(parameters) = node.child_by_field_name("parameters") else {
+        // An arrow function with a single bare parameter
+        return usize::from(node.child_by_field_name("parameter").is_some());
+    };
+    let mut cursor = parameters.walk();
+    let count = parameters
+        .named_children(&mut cursor)
+        .filter(|parameter| {
+            let text = parameter.utf8_text(source).unwrap_or_default();
+            let receiver = parameter.kind() == "identifier" && matches!(text, "self" | "cls");
+            !receiver && !matches!(parameter.kind(), "self_parameter" | "comment")
+        })
+        .count();
+    count
+}
+
+#[derive(Default)]
+struct Counts {
+    cyclomatic: usize,
+    cognitive: usize,
+    max_nesting: usize,
+}
+
+fn measure(grammar: &Grammar, function: Node, source: &[u8]) -> FunctionComplexity {
+    let mut counts = Counts { cyclomatic: 1, ..Default::default() };
+    let mut cursor = function.walk();
+    for child in function.children(&mut cursor) {
+        walk(grammar, child, source, 0, &mut counts);
+    }
+    FunctionComplexity {
+        name: function_name(function, source),
+        line: function.start_position().row + 1,
+        end_line: function.end_position().row + 1,
+        parameters: parameter_count(function, source),
+        cyclomatic: counts.cyclomatic,
+        cognitive: counts.cognitive,
+        max_nesting: counts.max_nesting,
+    }
+}
+
+fn logical_operator<'a>(grammar: &Grammar, node: Node, source: &'a [u8]) -> Option<&'a str> {
+    if !grammar.logical.contains(&node.kind()) {
+        return None;
+    }
+    let operator = node.child_by_field_name("operator")?.utf8_text(source).ok()?;
+    grammar.operators.contains(&operator).then_some(operator)
+}
+
+fn walk(grammar: &Grammar, node: Node, source: &[u8], nesting: usize, counts: &mut Counts) {
+    let kind = node.kind();
+    if grammar.functions.contains(&kind) {
+        return;
+    }
+
+    // `else if` continues the chain it belongs to instead of nesting inside it
+    let else_if = node.parent().is_some_and(|parent| grammar.flat.contains(&parent.kind()));
+    let mut inner = nesting;
+    if grammar.nesting.contains(&kind) {
+        counts.cyclomatic += 1;
+        if else_if {
+            counts.cognitive += 1;
+        } else {
+            counts.cognitive += 1 + nesting;
+            inner = nesting + 1;
+            counts.max_nesting = counts.max_nesting.max(inner);
+        }
+    } else if grammar.flat.contains(&kind) {
+        let mut cursor = node.walk();
+        let chained =
+            node.named_children(&mut cursor).any(|child| grammar.nesting.contains(&child.kind()));
+        if kind == "elif_clause" {
+            counts.cyclomatic += 1;
+        }
+        if !chained {
+            counts.cognitive += 1;
+        }
+    } else if grammar.cases.contains(&kind) {
+        counts.cyclomatic += 1;
+    } else if let Some(operator) = logical_operator(grammar, node, source) {
+        counts.cyclomatic += 1;
+        let continues = node
+            .parent()
+            .and_then(|parent| logical_operator(grammar, parent, source))
+            .is_some_and(|parent| parent == operator);
+        if !continues {
+            counts.cognitive += 1;
+        }
+    }
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        walk(grammar, child, source, inner, counts);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_measures_functions_per_language() {
+        let python = "\
+def classify(self, items, limit=3):
+    for item in items:
+        if item > limit and item < 10:
+            return 'mid'
+        elif item == 0:
+            return 'zero'
+        else:
+            def helper(x):
+                return x
+    return 'none'
+";
+        let report = analyze_functions(python, "python").unwrap();
+        let classify = &report.functions[0];
+        assert_eq!((classify.name.as_str(), classify.line, classify.end_line), ("classify", 1, 10));
+        assert_eq!(classify.parameters, 2);
+        // for, if, elif, and
+        assert_eq!(classify.cyclomatic, 5);
+        // for 1, if 1+1, and 1, elif 1, else 1
+        assert_eq!(classify.cognitive, 6);
+        assert_eq!(classify.max_nesting, 2);
+        assert_eq!(report.functions[1].name, "helper");
+        assert_eq!(report.hotspots(1)[0].name, "classify");
+
+        let rust = "fn pick(&self, n: u32) -> u32 {\n    match n {\n        0 => 1,\n        _ => if n > 5 { 2 } else if n > 3 { 3 } else { 4 },\n    }\n}\n";
+        let pick = &analyze_functions(rust, "rust").unwrap().functions[0];
+        assert_eq!((pick.parameters, pick.cyclomatic, pick.max_nesting), (1, 6, 2));
+        // match 1, if 1+1, else if 1, else 1
+        assert_eq!(pick.cognitive, 5);
+
+        let typescript = "const check = (a: number, b: number) => a > 0 && b > 0 || a === b;\n";
+        let check = &analyze_functions(typescript, "typescript").unwrap().functions[0];
+        assert_eq!((check.name.as_str(), check.parameters), ("check", 2));
+        assert_eq!((check.cyclomatic, check.cognitive), (3, 2));
+
+        assert!(analyze_functions("", "cobol").is_none());
+    }
+}
@@ -0,0 +1,502 @@
+//! Type inference for Python→Rust
+//!
+//! Each `def` gets a typed Rust signature. A parameter's type comes from, in order, its
+//! annotation, its default value, or how the body uses it (`x.append(..)` makes a `Vec`,
+//! `x.upper()` a `String`, `x + 1` an `i64`). Return types come from the annotation or the
+//! `return` expressions, with local variables typed by the literals assigned to them. What
+//! cannot be inferred becomes `serde_json::Value`, or a generic when a parameter is only passed
+//! through to the return value. [`Signature::confidence`] says how much of it was guessed.
+
+use crate::options::{self, ErrorStyle, Ownership, TranspileOptions};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Type used for anything that cannot be inferred
+pub const DYNAMIC: &str = "serde_json::Value";
+
+/// Where an inferred type came from, from most to least reliable
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TypeSource {
+    Annotation,
+    Literal,
+    Usage,
+    Fallback,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Inferred {
+    pub ty: String,
+    pub source: TypeSource,
+}
+
+impl Inferred {
+    fn new(ty: impl Into<String>, source: TypeSource) -> Self {
+        Self { ty: ty.into(), source }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Confidence {
+    /// Every type was annotated
+    High,
+    /// Every type was annotated or inferred
+    Medium,
+    /// Some types fell back to `serde_json::Value` or a generic
+    Low,
+}
+
+impl fmt::Display for Confidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::High => "high",
+            Self::Medium => "medium",
+            Self::Low => "low",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Param {
+    pub name: String,
+    pub ty: Inferred,
+}
+
+impl Param {
+    /// The parameter's Rust type in a signature, borrowed unless `options` clone
+    pub fn to_rust(&self, options: &TranspileOptions) -> String {
+        parameter_type(&self.ty.ty, options)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Signature {
+    pub name: String,
+    /// Line of the `def`, counted from 0
+    pub line: usize,
+    /// Takes `self`, i.e. a method
+    pub method: bool,
+    pub params: Vec<Param>,
+    pub returns: Inferred,
+    pub generics: Vec<String>,
+    /// Contains `raise`
+    pub raises: bool,
+}
+
+impl Signature {
+    pub fn confidence(&self) -> Confidence {
+        let worst = self
+            .params
+            .iter()
+            .map(|param| param.ty.source)
+            .chain([self.returns.source])
+            .max()
+            .unwrap_or(TypeSource::Annotation);
+        match worst {
+            TypeSource::Annotation => Confidence::High,
+            TypeSource::Literal | TypeSource::Usage => Confidence::Medium,
+            TypeSource::Fallback => Confidence::Low,
+        }
+    }
+
+    /// Whether [`Self::to_rust`] returns a `Result`
+    pub fn fallible(&self, options: &TranspileOptions) -> bool {
+        options.errors_or(ErrorStyle::Result) == ErrorStyle::Result
+            && (self.raises || self.returns.ty != "()")
+    }
+
+    /// The Rust signature, without the opening brace
+    pub fn to_rust(&self, options: &TranspileOptions) -> String {
+        let mut params: Vec<String> = Vec::new();
+        if self.method {
+            params.push("&self".to_string());
+        }
+        params.extend(
+            self.params.iter().map(|param| format!("{}: {}", param.name, param.to_rust(options))),
+        );
+        let generics = if self.generics.is_empty() {
+            String::new()
+        } else {
+            format!("<{}>", self.generics.join(", "))
+        };
+
+        let returns = &self.returns.ty;
+        let returns = if self.fallible(options) {
+            format!(" -> Result<{}, Box<dyn std::error::Error>>", returns)
+        } else if returns == "()" {
+            String::new()
+        } else {
+            format!(" -> {}", returns)
+        };
+        format!("fn {}{}({}){}", self.name, generics, params.join(", "), returns)
+    }
+}
+
+/// Borrowed parameters take slices and references instead of owned collections
+fn parameter_type(ty: &str, options: &TranspileOptions) -> String {
+    if options.ownership == Ownership::Clone {
+        return ty.to_string();
+    }
+    if ty == "String" {
+        "&str".to_string()
+    } else if let Some(element) = ty.strip_prefix("Vec<").and_then(|rest| rest.strip_suffix('>')) {
+        format!("&[{}]", element)
+    } else if ty.starts_with("HashMap<") || ty.starts_with("HashSet<") || ty == DYNAMIC {
+        format!("&{}", ty)
+    } else {
+        ty.to_string()
+    }
+}
+
+/// Infer a signature for every `def` in `code`
+pub fn infer_signatures(code: &str) -> Vec<Signature> {
+    let lines: Vec<&str> = code.lines().collect();
+    lines
+        .iter()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let indent = indentation(line);
+            let def = line.trim().strip_prefix("def ")?.strip_suffix(':')?;
+            let body: Vec<&str> = lines[i + 1..]
+                .iter()
+                .take_while(|line| line.trim().is_empty() || indentation(line) > indent)
+                .copied()
+                .collect();
+            infer(def, i, &body)
+        })
+        .collect()
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Split on commas outside brackets and strings
+pub fn split_top_level(text: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut quote, mut start) = (0i32, None, 0);
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (Some(open), _) if c == open => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(' | '[' | '{') => depth += 1,
+            (None, ')' | ']' | '}') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                parts.push(text[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(text[start..].trim());
+    parts.retain(|part| !part.is_empty());
+    parts
+}
+
+/// The Rust type for a Python annotation
+pub fn annotation_type(annotation: &str) -> String {
+    let annotation = annotation.trim();
+    if let Some(inner) =
+        annotation.strip_suffix("| None").or_else(|| annotation.strip_suffix("|None"))
+    {
+        return format!("Option<{}>", annotation_type(inner));
+    }
+    let (base, args) = match annotation.split_once('[') {
+        Some((base, rest)) => (base.trim(), rest.strip_suffix(']').map(split_top_level)),
+        None => (annotation, None),
+    };
+    let arg = |i: usize| {
+        args.as_ref()
+            .and_then(|args| args.get(i))
+            .map(|arg| annotation_type(arg))
+            .unwrap_or_else(|| DYNAMIC.to_string())
+    };
+    match base.strip_prefix("typing.").unwrap_or(base) {
+        "int" => "i64".to_string(),
+        "float" => "f64".to_string(),
+        "str" => "String".to_string(),
+        "bool" => "bool".to_string(),
+        "bytes" => "Vec<u8>".to_string(),
+        "None" => "()".to_string(),
+        "Any" | "object" => DYNAMIC.to_string(),
+        "list" | "List" | "Sequence" | "Iterable" => format!("Vec<{}>", arg(0)),
+        "set" | "Set" => format!("HashSet<{}>", arg(0)),
+        "dict" | "Dict" | "Mapping" => format!("HashMap<{}, {}>", arg(0), arg(1)),
+        "Optional" => format!("Option<{}>", arg(0)),
+        "tuple" | "Tuple" => {
+            let count = args.as_ref().map_or(0, Vec::len);
+            format!("({})", (0..count).map(&arg).collect::<Vec<_>>().join(", "))
+        }
+        // Classes keep their names
+        other => other.to_string(),
+    }
+}
+
+/// The type of an expression, given the types of the variables in scope
+fn expression_type(expr: &str, scope: &HashMap<String, String>) -> Option<String> {
+    let expr = expr.trim();
+    if let Some(ty) = scope.get(expr) {
+        return Some(ty.clone());
+    }
+    if expr.parse::<i64>().is_ok() {
+        return Some("i64".to_string());
+    }
+    if expr.parse::<f64>().is_ok() {
+        return Some("f64".to_string());
+    }
+    if expr == "True" || expr == "False" {
+        return Some("bool".to_string());
+    }
+    let quoted = ['"', '\''].iter().any(|&q| {
+        let unprefixed = expr.trim_start_matches(['f', 'r']);
+        unprefixed.len() >= 2 && unprefixed.starts_with(q) && unprefixed.ends_with(q)
+    });
+    if quoted {
+        return Some("String".to_string());
+    }
+    if let Some(items) = expr.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+        let element = split_top_level(items).first().and_then(|item| expression_type(item, scope));
+        return Some(format!("Vec<{}>", element.unwrap_or_else(|| DYNAMIC.to_string())));
+    }
+    if let Some(items) = expr.strip_prefix('{').and_then(|rest| rest.strip_suffix('}')) {
+        let first = split_top_level(items).first().and_then(|item| item.split_once(':'));
+        let (key, value) = match first {
+            Some((key, value)) => (expression_type(key, scope), expression_type(value, scope)),
+            None => (Some("String".to_string()), None),
+        };
+        return Some(format!(
+            "HashMap<{}, {}>",
+            key.unwrap_or_else(|| DYNAMIC.to_string()),
+            value.unwrap_or_else(|| DYNAMIC.to_string())
+        ));
+    }
+    for (call, ty) in [
+        ("len(", "usize"),
+        ("str(", "String"),
+        ("int(", "i64"),
+        ("float(", "f64"),
+        ("bool(", "bool"),
+    ] {
+        if expr.starts_with(call) && expr.ends_with(')') {
+            return Some(ty.to_string());
+        }
+    }
+    if [" == ", " != ", " < ", " > ", " <= ", " >= ", " and ", " or ", " in "]
+        .iter()
+        .any(|op| expr.contains(op))
+        || expr.starts_with("not ")
+    {
+        return Some("bool".to_string());
+    }
+
+    // Arithmetic: the widest operand type wins
+    let operands: Vec<&str> = expr.split([' ', '+', '-', '*', '/', '%', '(', ')']).collect();
+    if operands.len() > 1 {
+        let types: Vec<String> = operands
+            .iter()
+            .filter(|operand| !operand.is_empty())
+            .filter_map(|operand| expression_type(operand, scope))
+            .collect();
+        for ty in ["String", "f64", "i64", "usize"] {
+            if types.iter().any(|t| t == ty) {
+                return Some(ty.to_string());
+            }
+        }
+    }
+    None
+}
+
+/// The type the body's use of `name` implies
+fn usage_type(name: &str, body: &[&str]) -> Option<String> {
+    let name = regex::escape(name);
+    let text = body.join("\n");
+    let used = |pattern: &str| Regex::new(&pattern.replace("NAME", &name)).unwrap();
+
+    if let Some(appended) = used(r"\bNAME\.append\((.+)\)").captures(&text) {
+        let element = expression_type(&appended[1], &HashMap::new());
+        return Some(format!("Vec<{}>", element.unwrap_or_else(|| DYNAMIC.to_string())));
+    }
+    if used(r"\bNAME\.(upper|lower|strip|split|startswith|endswith|replace|format)\(")
+        .is_match(&text)
+    {
+        return Some("String".to_string());
+    }
+    if used(r"\bNAME\.(keys|values|items|get)\(").is_match(&text) {
+        return Some(format!("HashMap<String, {}>", DYNAMIC));
+    }
+    if used(r"\bNAME\.extend\(|\bfor \w+ in NAME:|\bNAME\[").is_match(&text) {
+        return Some(format!("Vec<{}>", DYNAMIC));
+    }
+    let number = r"(\d+(?:\.\d+)?)";
+    let arithmetic = format!(r"\bNAME\s*[-+*/%<>]=?\s*{0}|{0}\s*[-+*/%<>]=?\s*NAME\b", number);
+    if let Some(captures) = used(&arithmetic).captures(&text) {
+        let literal = captures.get(1).or_else(|| captures.get(2)).unwrap().as_str();
+        return Some(if literal.contains('.') { "f64" } else { "i64" }.to_string());
+    }
+    None
+}
+
+fn infer(def: &str, line: usize, body: &[&str]) -> Option<Signature> {
+    let (name, rest) = def.split_once('(')?;
+    let (params, returns) = rest.rsplit_once(')')?;
+    let annotated_return = returns.trim().strip_prefix("->").map(annotation_type);
+
+    let mut method = false;
+    let mut inferred = Vec::new();
+    for param in split_top_level(params) {
+        let (param, default) = match param.split_once('=') {
+            Some((param, default)) => (param.trim(), Some(default.trim())),
+            None => (param, None),
+        };
+        let (param_name, annotation) = match param.split_once(':') {
+            Some((param, annotation)) => (param.trim(), Some(annotation)),
+            None => (param, None),
+        };
+        if param_name == "self" {
+            method = true;
+            continue;
+        }
+        let entry = if let Some(variadic) = param_name.strip_prefix("**") {
+            (
+                variadic,
+                Some(Inferred::new(format!("HashMap<String, {}>", DYNAMIC), TypeSource::Usage)),
+            )
+        } else if let Some(variadic) = param_name.strip_prefix('*') {
+            (variadic, Some(Inferred::new(format!("Vec<{}>", DYNAMIC), TypeSource::Usage)))
+        } else if let Some(annotation) = annotation {
+            (param_name, Some(Inferred::new(annotation_type(annotation), TypeSource::Annotation)))
+        } else {
+            let literal = default.and_then(|default| expression_type(default, &HashMap::new()));
+            let ty = match literal {
+                Some(ty) => Some(Inferred::new(ty, TypeSource::Literal)),
+                None => usage_type(param_name, body).map(|ty| Inferred::new(ty, TypeSource::Usage)),
+            };
+            (param_name, ty)
+        };
+        inferred.push(entry);
+    }
+
+    let mut scope: HashMap<String, String> = inferred
+        .iter()
+        .filter_map(|(name, ty)| Some((name.to_string(), ty.as_ref()?.ty.clone())))
+        .collect();
+    let mut return_types = Vec::new();
+    let mut raises = false;
+    for line in body.iter().map(|line| line.trim()) {
+        if let Some((variable, value)) = options::assignment(line) {
+            if let Some(ty) = expression_type(value, &scope) {
+                scope.entry(variable.to_string()).or_insert(ty);
+            }
+        } else if let Some(loop_variable) =
+            line.strip_prefix("for ").and_then(|rest| rest.split_once(" in range("))
+        {
+            scope.insert(loop_variable.0.trim().to_string(), "i64".to_string());
+        } else if line.starts_with("raise ") {
+            raises = true;
+        } else if let Some(value) = line.strip_prefix("return ") {
+            return_types.push((value.trim(), expression_type(value, &scope)));
+        }
+    }
+
+    // Parameters returned as they are pass through as generics; the rest are dynamic
+    let mut generics = Vec::new();
+    let mut params = Vec::new();
+    for (name, ty) in inferred {
+        let ty = ty.unwrap_or_else(|| {
+            if return_types.iter().any(|(value, _)| *value == name) {
+                let generic = ["T", "U", "V", "W"].get(generics.len()).copied().unwrap_or("X");
+                generics.push(generic.to_string());
+                Inferred::new(generic, TypeSource::Fallback)
+            } else {
+                Inferred::new(DYNAMIC, TypeSource::Fallback)
+            }
+        });
+        params.push(Param { name: name.to_string(), ty });
+    }
+
+    let returns = match annotated_return {
+        Some(ty) => Inferred::new(ty, TypeSource::Annotation),
+        None if return_types.is_empty() => Inferred::new("()", TypeSource::Literal),
+        None => {
+            let passed_through = |value: &str| {
+                params.iter().find(|param| param.name == value).map(|param| param.ty.ty.clone())
+            };
+            let types: Vec<Option<String>> = return_types
+                .iter()
+                .map(|(value, ty)| ty.clone().or_else(|| passed_through(value)))
+                .collect();
+            match types.first() {
+                Some(Some(first)) if types.iter().all(|ty| ty.as_ref() == Some(first)) => {
+                    let source = if generics.contains(first) {
+                        TypeSource::Fallback
+                    } else {
+                        TypeSource::Literal
+                    };
+                    Inferred::new(first.clone(), source)
+                }
+                _ => Inferred::new(DYNAMIC, TypeSource::Fallback),
+            }
+        }
+    };
+
+    Some(Signature {
+        name: name.trim().to_string(),
+        line,
+        method,
+        params,
+        returns,
+        generics,
+        raises,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infers_signatures_from_annotations_literals_and_usage() {
+        let code = "\
+def area(width: float, height: float) -> float:
+    return width * height
+
+def collect(items, limit=10, prefix=\"x\"):
+    items.append(1)
+    total = 0
+    if limit > 0:
+        raise ValueError(limit)
+    return total + 1
+
+def identity(value):
+    return value
+";
+        let signatures = infer_signatures(code);
+        let options = TranspileOptions::default();
+
+        assert_eq!(signatures[0].confidence(), Confidence::High);
+        assert_eq!(
+            signatures[0].to_rust(&options),
+            "fn area(width: f64, height: f64) -> Result<f64, Box<dyn std::error::Error>>"
+        );
+
+        assert_eq!(signatures[1].line, 3);
+        assert_eq!(signatures[1].confidence(), Confidence::Medium);
+        let exceptions = TranspileOptions { errors: ErrorStyle::Exception, ..Default::default() };
+        assert_eq!(
+            signatures[1].to_rust(&exceptions),
+            "fn collect(items: &[i64], limit: i64, prefix: &str) -> i64"
+        );
+
+        assert_eq!(signatures[2].confidence(), Confidence::Low);
+        assert_eq!(signatures[2].to_rust(&exceptions), "fn identity<T>(value: T) -> T");
+
+        assert_eq!(
+            annotation_type("Optional[dict[str, list[int]]]"),
+            "Option<HashMap<String, Vec<i64>>>"
+        );
+    }
+}
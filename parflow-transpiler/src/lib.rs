@@ -1,43 +1,54 @@
-use colored::*;
 use std::collections::HashMap;
 
+#[cfg(feature = "tree-sitter")]
+pub mod complexity;
+pub mod infer;
+pub mod options;
+pub mod repro;
+pub mod service;
+pub mod session;
+pub mod stdlib;
+
+use options::{ErrorStyle, Naming, Ownership, TranspileOptions};
+
 pub struct CodeTranspiler;
 
+/// Supported (from, to) language pairs
+pub const DIRECTIONS: &[(&str, &str)] = &[("python", "rust"), ("rust", "typescript")];
+
 impl CodeTranspiler {
+    /// Transpile between any supported pair in [`DIRECTIONS`], or through the transpile hook of
+    /// the source language's [`parflow_core::languages`] plugin, without console output; `None`
+    /// for an unsupported direction. Language names are case-insensitive.
+    pub fn transpile(code: &str, from: &str, to: &str) -> Option<String> {
+        Self::transpile_with(code, from, to, &TranspileOptions::default())
+    }
+
+    /// [`Self::transpile`] in the style set by `options`
+    pub fn transpile_with(
+        code: &str,
+        from: &str,
+        to: &str,
+        options: &TranspileOptions,
+    ) -> Option<String> {
+        match (from.to_lowercase().as_str(), to.to_lowercase().as_str()) {
+            ("python", "rust") => Some(Self::convert_python_to_rust(code, options)),
+            ("rust", "typescript") => Some(Self::convert_rust_to_typescript(code, options)),
+            (_, to) => parflow_core::languages::get(from)?.transpile(code, to),
+        }
+    }
+
     pub fn python_to_rust(python_code: &str) -> String {
-        println!("{}", "🔄 Transpiling Python → Rust".bright_blue().bold());
+        tracing::info!("🔄 Transpiling Python → Rust");
+        Self::convert_python_to_rust(python_code, &TranspileOptions::default())
+    }
 
+    pub(crate) fn convert_python_to_rust(python_code: &str, options: &TranspileOptions) -> String {
         let mut rust_code = String::from("// Auto-generated Rust code from Python\n");
         rust_code.push_str("fn main() {\n");
 
-        for line in python_code.lines() {
-            let trimmed = line.trim();
-            if trimmed.is_empty() {
-                continue;
-            }
-
-            let rust_line = if trimmed.starts_with("print(") && trimmed.ends_with(')') {
-                let content = &trimmed[6..trimmed.len() - 1]; // Remove "print(" and ")"
-                format!("    println!(\"{{}}\", {});", content)
-            } else if trimmed.starts_with("def ") && trimmed.ends_with(':') {
-                let func_def = &trimmed[4..trimmed.len() - 1]; // Remove "def " and ":"
-                format!("    fn {} {{", func_def)
-            } else if trimmed.starts_with("for ")
-                && trimmed.contains(" in range(")
-                && trimmed.ends_with(':')
-            {
-                // Simple for loop conversion
-                let loop_def = &trimmed[4..trimmed.len() - 1]; // Remove "for " and ":"
-                format!("    for {} {{", loop_def.replace(" in range(", " in "))
-            } else if trimmed.starts_with("if ") && trimmed.ends_with(':') {
-                let condition = &trimmed[3..trimmed.len() - 1]; // Remove "if " and ":"
-                format!("    if {} {{", condition)
-            } else if trimmed == "else:" {
-                "    } else {".to_string()
-            } else {
-                format!("    {}; // TODO: Manual conversion needed", trimmed)
-            };
-
+        let lines: Vec<&str> = python_code.lines().collect();
+        for (_, rust_line) in Self::python_block_to_rust(&lines, options) {
             rust_code.push_str(&rust_line);
             rust_code.push('\n');
         }
@@ -46,46 +57,100 @@ impl CodeTranspiler {
         rust_code
     }
 
-    pub fn rust_to_typescript(rust_code: &str) -> String {
-        println!("{}", "🔄 Transpiling Rust → TypeScript".bright_yellow().bold());
+    /// Convert Python `lines`, typing each `def` with [`infer`]. Every generated line comes with
+    /// the index of the line it was converted from.
+    pub(crate) fn python_block_to_rust(
+        lines: &[&str],
+        options: &TranspileOptions,
+    ) -> Vec<(usize, String)> {
+        let renamed: Vec<String> =
+            lines.iter().map(|line| options.rename(line, Naming::SnakeCase)).collect();
+        let signatures: HashMap<usize, infer::Signature> =
+            infer::infer_signatures(&renamed.join("\n"))
+                .into_iter()
+                .map(|signature| (signature.line, signature))
+                .collect();
 
-        let mut ts_code = String::from("// Auto-generated TypeScript code from Rust\n");
-
-        for line in rust_code.lines() {
+        let mut converted = Vec::new();
+        for (i, line) in lines.iter().enumerate() {
             let trimmed = line.trim();
             if trimmed.is_empty() {
                 continue;
             }
+            let rust_line = match signatures.get(&i) {
+                Some(signature) => Some(format!("    {} {{", signature.to_rust(options))),
+                None => Self::python_line_to_rust(trimmed, options),
+            };
+            converted.extend(rust_line.map(|rust_line| (i, rust_line)));
+        }
+        converted
+    }
 
-            let ts_line = if trimmed.starts_with("fn ") && trimmed.ends_with('{') {
-                let func_def = &trimmed[3..trimmed.len() - 1]; // Remove "fn " and "{"
-                format!("function {} {{", func_def)
-            } else if trimmed.starts_with("let ") && trimmed.ends_with(';') {
-                let var_def = &trimmed[4..trimmed.len() - 1]; // Remove "let " and ";"
-                format!("let {};", var_def)
-            } else if trimmed.starts_with("println!") && trimmed.ends_with(';') {
-                let content = &trimmed[9..trimmed.len() - 2]; // Remove "println!(\"" and "\");"
-                format!("console.log(\"{}\");", content)
-            } else if trimmed.starts_with("for ") && trimmed.ends_with('{') {
-                let loop_def = &trimmed[4..trimmed.len() - 1]; // Remove "for " and "{"
-                                                               // Convert Rust range syntax to TypeScript
-                if loop_def.contains("..") {
-                    let parts: Vec<&str> = loop_def.split(" in ").collect();
-                    if parts.len() == 2 {
-                        let range_parts: Vec<&str> = parts[1].split("..").collect();
-                        if range_parts.len() == 2 {
-                            return format!(
-                                "for (let {} = {}; {} < {}; {}++) {{",
-                                parts[0], range_parts[0], parts[0], range_parts[1], parts[0]
-                            );
-                        }
-                    }
+    /// Convert one trimmed, non-empty line of Python; `None` for a comment that is dropped
+    pub(crate) fn python_line_to_rust(trimmed: &str, options: &TranspileOptions) -> Option<String> {
+        if let Some(comment) = trimmed.strip_prefix('#') {
+            return options.preserve_comments.then(|| format!("    //{}", comment));
+        }
+        let rewritten = options.stdlib.rewrite(trimmed, "python", "rust");
+        let renamed = options.rename(&rewritten, Naming::SnakeCase);
+        let trimmed = renamed.as_str();
+
+        Some(if trimmed.starts_with("print(") && trimmed.ends_with(')') {
+            let content = &trimmed[6..trimmed.len() - 1]; // Remove "print(" and ")"
+            format!("    println!(\"{{}}\", {});", content)
+        } else if trimmed.starts_with("def ") && trimmed.ends_with(':') {
+            let func_def = &trimmed[4..trimmed.len() - 1]; // Remove "def " and ":"
+            format!("    fn {} {{", func_def)
+        } else if trimmed.starts_with("for ")
+            && trimmed.contains(" in range(")
+            && trimmed.ends_with(':')
+        {
+            // Simple for loop conversion
+            let loop_def = &trimmed[4..trimmed.len() - 1]; // Remove "for " and ":"
+            format!("    for {} {{", loop_def.replace(" in range(", " in "))
+        } else if trimmed.starts_with("if ") && trimmed.ends_with(':') {
+            let condition = &trimmed[3..trimmed.len() - 1]; // Remove "if " and ":"
+            format!("    if {} {{", condition)
+        } else if trimmed == "else:" {
+            "    } else {".to_string()
+        } else if let Some(error) = trimmed.strip_prefix("raise ") {
+            match options.errors_or(ErrorStyle::Result) {
+                ErrorStyle::Exception => format!("    panic!(\"{{:?}}\", {});", error),
+                _ => format!("    return Err({}.into());", error),
+            }
+        } else if let Some(value) = trimmed.strip_prefix("return ") {
+            match options.errors_or(ErrorStyle::Result) {
+                ErrorStyle::Exception => format!("    return {};", value),
+                _ => format!("    return Ok({});", value),
+            }
+        } else if let Some((name, value)) = options::assignment(trimmed) {
+            match options.ownership {
+                Ownership::Clone if options::is_name(value) => {
+                    format!("    let {} = {}.clone();", name, value)
                 }
-                format!("for {} {{", loop_def)
-            } else {
-                format!("// {}", trimmed)
-            };
+                Ownership::Borrow if options::is_name(value) => {
+                    format!("    let {} = &{};", name, value)
+                }
+                _ => format!("    let {} = {};", name, value),
+            }
+        } else {
+            format!("    {}; // TODO: Manual conversion needed", trimmed)
+        })
+    }
 
+    pub fn rust_to_typescript(rust_code: &str) -> String {
+        tracing::info!("🔄 Transpiling Rust → TypeScript");
+        Self::convert_rust_to_typescript(rust_code, &TranspileOptions::default())
+    }
+
+    pub(crate) fn convert_rust_to_typescript(
+        rust_code: &str,
+        options: &TranspileOptions,
+    ) -> String {
+        let mut ts_code = String::from("// Auto-generated TypeScript code from Rust\n");
+
+        let lines: Vec<&str> = rust_code.lines().collect();
+        for (_, ts_line) in Self::rust_block_to_typescript(&lines, options) {
             ts_code.push_str(&ts_line);
             ts_code.push('\n');
         }
@@ -93,6 +158,87 @@ impl CodeTranspiler {
         ts_code
     }
 
+    /// Convert Rust `lines`; every generated line comes with the index of its source line
+    pub(crate) fn rust_block_to_typescript(
+        lines: &[&str],
+        options: &TranspileOptions,
+    ) -> Vec<(usize, String)> {
+        lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .filter_map(|(i, line)| Some((i, Self::rust_line_to_typescript(line.trim(), options)?)))
+            .collect()
+    }
+
+    /// Convert one trimmed, non-empty line of Rust; `None` for a comment that is dropped
+    pub(crate) fn rust_line_to_typescript(
+        trimmed: &str,
+        options: &TranspileOptions,
+    ) -> Option<String> {
+        if let Some(comment) = trimmed.strip_prefix("//") {
+            return options.preserve_comments.then(|| format!("//{}", comment));
+        }
+        let rewritten = options.stdlib.rewrite(trimmed, "rust", "typescript");
+        let renamed = options.rename(&rewritten, Naming::CamelCase);
+        let trimmed = renamed.as_str();
+
+        Some(if trimmed.starts_with("fn ") && trimmed.ends_with('{') {
+            let func_def = &trimmed[3..trimmed.len() - 1]; // Remove "fn " and "{"
+            format!("function {} {{", func_def)
+        } else if trimmed.starts_with("let ") && trimmed.ends_with(';') {
+            let var_def = &trimmed[4..trimmed.len() - 1]; // Remove "let " and ";"
+            match var_def.split_once(" = ") {
+                Some((name, value)) => {
+                    let value = value.strip_prefix('&').unwrap_or(value);
+                    match (value.strip_suffix(".clone()"), options.ownership) {
+                        (Some(cloned), Ownership::Clone) => {
+                            format!("let {} = structuredClone({});", name, cloned)
+                        }
+                        (Some(cloned), Ownership::Borrow) => format!("let {} = {};", name, cloned),
+                        (None, _) => format!("let {} = {};", name, value),
+                    }
+                }
+                None => format!("let {};", var_def),
+            }
+        } else if trimmed.starts_with("println!") && trimmed.ends_with(';') {
+            let content = &trimmed[9..trimmed.len() - 2]; // Remove "println!(\"" and "\");"
+            format!("console.log(\"{}\");", content)
+        } else if let Some(error) =
+            trimmed.strip_prefix("return Err(").and_then(|rest| rest.strip_suffix(");"))
+        {
+            match options.errors_or(ErrorStyle::Exception) {
+                ErrorStyle::Result => format!("return {{ ok: false, error: {} }};", error),
+                _ => format!("throw {};", error),
+            }
+        } else if let Some(value) =
+            trimmed.strip_prefix("return Ok(").and_then(|rest| rest.strip_suffix(");"))
+        {
+            match options.errors_or(ErrorStyle::Exception) {
+                ErrorStyle::Result => format!("return {{ ok: true, value: {} }};", value),
+                _ => format!("return {};", value),
+            }
+        } else if trimmed.starts_with("for ") && trimmed.ends_with('{') {
+            let loop_def = &trimmed[4..trimmed.len() - 1]; // Remove "for " and "{"
+                                                           // Convert Rust range syntax to TypeScript
+            if loop_def.contains("..") {
+                let parts: Vec<&str> = loop_def.split(" in ").collect();
+                if parts.len() == 2 {
+                    let range_parts: Vec<&str> = parts[1].split("..").collect();
+                    if range_parts.len() == 2 {
+                        return Some(format!(
+                            "for (let {} = {}; {} < {}; {}++) {{",
+                            parts[0], range_parts[0], parts[0], range_parts[1], parts[0]
+                        ));
+                    }
+                }
+            }
+            format!("for {} {{", loop_def)
+        } else {
+            format!("// {}", trimmed)
+        })
+    }
+
     pub fn analyze_code_complexity(code: &str, _language: &str) -> HashMap<String, f64> {
         let mut metrics = HashMap::new();
 
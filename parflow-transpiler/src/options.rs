@@ -0,0 +1,233 @@
+//! Style of the generated code
+//!
+//! [`TranspileOptions`] is read from a TOML file (`parflow transpile --options file.toml`):
+//!
+//! ```toml
+//! naming = "camel_case"       # idiomatic (default), snake_case, camel_case or preserve
+//! errors = "exception"        # idiomatic (default), result or exception
+//! ownership = "clone"         # borrow (default) or clone
+//! preserve_comments = false   # default true
+//! ```
+//!
+//! `idiomatic` follows the target language: snake_case names and `Result` in Rust, camelCase
+//! names and exceptions in TypeScript.
+
+use crate::stdlib::StdlibMap;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Naming {
+    #[default]
+    Idiomatic,
+    SnakeCase,
+    CamelCase,
+    /// Keep the source names
+    Preserve,
+}
+
+/// How failures are reported: `raise` in Python, `return Err(..)` in Rust
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorStyle {
+    #[default]
+    Idiomatic,
+    /// Return the error (`Err(..)` in Rust, `{ ok: false, error }` in TypeScript)
+    Result,
+    /// Throw it (`panic!` in Rust, `throw` in TypeScript)
+    Exception,
+}
+
+/// How a value assigned from another variable is held
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Ownership {
+    /// Refer to the same value (`&x` in Rust, plain assignment in TypeScript)
+    #[default]
+    Borrow,
+    /// Copy it (`x.clone()` in Rust, `structuredClone(x)` in TypeScript)
+    Clone,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TranspileOptions {
+    pub naming: Naming,
+    pub errors: ErrorStyle,
+    pub ownership: Ownership,
+    pub preserve_comments: bool,
+    /// Standard library calls to rewrite, see [`StdlibMap::for_project`]
+    #[serde(skip, default = "StdlibMap::builtin")]
+    pub stdlib: StdlibMap,
+}
+
+impl Default for TranspileOptions {
+    fn default() -> Self {
+        Self {
+            naming: Naming::default(),
+            errors: ErrorStyle::default(),
+            ownership: Ownership::default(),
+            preserve_comments: true,
+            stdlib: StdlibMap::builtin(),
+        }
+    }
+}
+
+impl TranspileOptions {
+    pub fn from_toml_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("reading transpile options from {}", path.display()))?;
+        Ok(toml::from_str(&text)?)
+    }
+
+    /// The error style, with `Idiomatic` resolved to `idiomatic`
+    pub(crate) fn errors_or(&self, idiomatic: ErrorStyle) -> ErrorStyle {
+        match self.errors {
+            ErrorStyle::Idiomatic => idiomatic,
+            style => style,
+        }
+    }
+
+    /// Rename the identifiers in `line` outside string literals; `Idiomatic` means `idiomatic`
+    pub(crate) fn rename(&self, line: &str, idiomatic: Naming) -> String {
+        let naming = if self.naming == Naming::Idiomatic { idiomatic } else { self.naming };
+        let convert: fn(&str) -> String = match naming {
+            Naming::SnakeCase => to_snake_case,
+            Naming::CamelCase => to_camel_case,
+            Naming::Idiomatic | Naming::Preserve => return line.to_string(),
+        };
+
+        let mut renamed = String::with_capacity(line.len());
+        let mut word = String::new();
+        let mut quote: Option<char> = None;
+        let mut escaped = false;
+        let flush = |word: &mut String, renamed: &mut String| {
+            // Types and constants start with an uppercase letter and keep their names
+            if word.starts_with(|c: char| c.is_ascii_lowercase()) {
+                renamed.push_str(&convert(word));
+            } else {
+                renamed.push_str(word);
+            }
+            word.clear();
+        };
+        for c in line.chars() {
+            if let Some(open) = quote {
+                renamed.push(c);
+                match c {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    _ if c == open => quote = None,
+                    _ => {}
+                }
+            } else if c.is_alphanumeric() || c == '_' {
+                word.push(c);
+            } else {
+                flush(&mut word, &mut renamed);
+                quote = matches!(c, '"' | '\'').then_some(c);
+                renamed.push(c);
+            }
+        }
+        flush(&mut word, &mut renamed);
+        renamed
+    }
+}
+
+fn to_snake_case(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len() + 4);
+    for c in name.chars() {
+        if c.is_uppercase() {
+            snake.push('_');
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+fn to_camel_case(name: &str) -> String {
+    // Leading and trailing underscores mark private or unused names; keep them
+    let start = name.len() - name.trim_start_matches('_').len();
+    let end = name.trim_end_matches('_').len().max(start);
+    let mut camel = name[..start].to_string();
+    let mut upper = false;
+    for c in name[start..end].chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            camel.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            camel.push(c);
+        }
+    }
+    camel.push_str(&name[end..]);
+    camel
+}
+
+/// `name = value` for a plain assignment to a variable
+pub(crate) fn assignment(line: &str) -> Option<(&str, &str)> {
+    let (name, value) = line.split_once('=')?;
+    let name = name.trim();
+    (is_name(name) && !value.starts_with('=')).then(|| (name, value.trim()))
+}
+
+/// Whether `value` is a bare variable name
+pub(crate) fn is_name(value: &str) -> bool {
+    value.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && value.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CodeTranspiler;
+
+    #[test]
+    fn test_rename_skips_strings_and_types() {
+        let camel = TranspileOptions { naming: Naming::CamelCase, ..Default::default() };
+        assert_eq!(
+            camel.rename("let total_sum = add_one(Vec_X, \"a_b\");", Naming::SnakeCase),
+            "let totalSum = addOne(Vec_X, \"a_b\");"
+        );
+        assert_eq!(to_camel_case("_unused_"), "_unused_");
+
+        let idiomatic = TranspileOptions::default();
+        assert_eq!(idiomatic.rename("def addOne(x):", Naming::SnakeCase), "def add_one(x):");
+        let preserve = TranspileOptions { naming: Naming::Preserve, ..Default::default() };
+        assert_eq!(preserve.rename("addOne", Naming::SnakeCase), "addOne");
+
+        let parsed: TranspileOptions = toml::from_str("errors = \"exception\"").unwrap();
+        assert_eq!(parsed.errors, ErrorStyle::Exception);
+        assert!(parsed.preserve_comments);
+    }
+
+    #[test]
+    fn test_options_shape_generated_code() {
+        let python = "# check\nif x < 0:\n    raise ValueError(x)\ncopy = x\n";
+        let typescript = CodeTranspiler::transpile(
+            "let copy = x.clone();\nreturn Err(e);\n",
+            "rust",
+            "typescript",
+        )
+        .unwrap();
+        assert!(typescript.contains("let copy = x;\nthrow e;\n"));
+
+        let options = TranspileOptions {
+            errors: ErrorStyle::Exception,
+            ownership: Ownership::Clone,
+            preserve_comments: false,
+            ..Default::default()
+        };
+        let generated = CodeTranspiler::transpile_with(python, "python", "rust", &options).unwrap();
+        assert!(!generated.contains("check"));
+        assert!(
+            generated.contains("    panic!(\"{:?}\", ValueError(x));\n    let copy = x.clone();")
+        );
+        let idiomatic = CodeTranspiler::transpile(python, "python", "rust").unwrap();
+        assert!(idiomatic.contains("    // check\n"));
+        assert!(idiomatic.contains("    return Err(ValueError(x).into());\n    let copy = &x;"));
+    }
+}
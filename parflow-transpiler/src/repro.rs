@@ -0,0 +1,282 @@
+//! Shareable, anonymized reproductions for transpiler and analyzer failures.
+//!
+//! Starting from an input that makes a pass panic, `minimize` shrinks it with line-based delta
+//! debugging (ddmin), then strips literals and renames identifiers. Every step is only kept if
+//! the pass still fails with the same signature, so the result reproduces the original crash
+//! without exposing proprietary code.
+
+use crate::options::TranspileOptions;
+use crate::CodeTranspiler;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
+
+thread_local! {
+    /// Set while this thread runs a pass whose panic is caught on purpose
+    static EXPECTING_PANIC: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Wrap the panic hook, once, so it stays quiet about the panics [`ReproTarget::failure`]
+/// catches. Panics anywhere else, including other threads, still reach the previous hook.
+fn install_quiet_hook() {
+    static INSTALLED: Once = Once::new();
+    INSTALLED.call_once(|| {
+        let hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if !EXPECTING_PANIC.with(Cell::get) {
+                hook(info);
+            }
+        }));
+    });
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReproTarget {
+    PythonToRust,
+    RustToTypescript,
+    Complexity,
+}
+
+impl std::str::FromStr for ReproTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "python-to-rust" => Ok(Self::PythonToRust),
+            "rust-to-typescript" => Ok(Self::RustToTypescript),
+            "complexity" => Ok(Self::Complexity),
+            other => Err(format!(
+                "unknown target {} (expected python-to-rust, rust-to-typescript or complexity)",
+                other
+            )),
+        }
+    }
+}
+
+impl ReproTarget {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::PythonToRust => "python-to-rust",
+            Self::RustToTypescript => "rust-to-typescript",
+            Self::Complexity => "complexity",
+        }
+    }
+
+    fn run(&self, code: &str) {
+        match self {
+            Self::PythonToRust => {
+                CodeTranspiler::convert_python_to_rust(code, &TranspileOptions::default());
+            }
+            Self::RustToTypescript => {
+                CodeTranspiler::convert_rust_to_typescript(code, &TranspileOptions::default());
+            }
+            Self::Complexity => {
+                CodeTranspiler::analyze_code_complexity(code, "");
+            }
+        }
+    }
+
+    /// Run the pass and return its failure signature, if it panics. The panic is not printed.
+    pub fn failure(&self, code: &str) -> Option<String> {
+        install_quiet_hook();
+        EXPECTING_PANIC.with(|expecting| expecting.set(true));
+        let outcome = panic::catch_unwind(AssertUnwindSafe(|| self.run(code)));
+        EXPECTING_PANIC.with(|expecting| expecting.set(false));
+        outcome.err().map(|payload| {
+            payload
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| payload.downcast_ref::<&str>().map(|s| s.to_string()))
+                .unwrap_or_else(|| "non-string panic payload".to_string())
+        })
+    }
+}
+
+/// Offsets and lengths in panic messages shift as the input shrinks; compare without them.
+fn signature(message: &str) -> String {
+    let digits = Regex::new(r"\d+").unwrap();
+    let quoted = Regex::new(r"`[^`]*`").unwrap();
+    quoted.replace_all(&digits.replace_all(message, "N"), "`_`").into_owned()
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReproCase {
+    pub target: ReproTarget,
+    pub failure: String,
+    pub original_lines: usize,
+    pub minimized: String,
+    pub tests_run: usize,
+}
+
+impl ReproCase {
+    /// Markdown crash report to attach to a bug report.
+    pub fn render_report(&self) -> String {
+        format!(
+            "# ParFlow repro: {}\n\n\
+             **Failure:**\n\n```\n{}\n```\n\n\
+             Reduced from {} to {} lines in {} runs; literals stripped and identifiers renamed.\n\n\
+             **Input:**\n\n```\n{}\n```\n",
+            self.target.name(),
+            self.failure,
+            self.original_lines,
+            self.minimized.lines().count(),
+            self.tests_run,
+            self.minimized.trim_end()
+        )
+    }
+}
+
+struct Oracle {
+    target: ReproTarget,
+    expected: String,
+    runs: usize,
+}
+
+impl Oracle {
+    fn still_fails(&mut self, code: &str) -> bool {
+        self.runs += 1;
+        self.target.failure(code).is_some_and(|m| signature(&m) == self.expected)
+    }
+}
+
+/// Returns `None` if `code` does not make `target` fail in the first place.
+pub fn minimize(target: ReproTarget, code: &str) -> Option<ReproCase> {
+    let failure = target.failure(code)?;
+    let mut oracle = Oracle { target, expected: signature(&failure), runs: 1 };
+
+    let lines: Vec<String> = code.lines().map(str::to_string).collect();
+    let reduced = ddmin(lines.clone(), &mut |candidate| oracle.still_fails(&candidate.join("\n")));
+    let mut current = reduced.join("\n");
+
+    for pass in [strip_literals as fn(&str) -> String, rename_identifiers] {
+        let candidate = pass(&current);
+        if candidate != current && oracle.still_fails(&candidate) {
+            current = candidate;
+        }
+    }
+
+    // The original panic message can quote the proprietary input; report the reduced one
+    let failure = target.failure(&current).unwrap_or(failure);
+
+    Some(ReproCase {
+        target,
+        failure,
+        original_lines: lines.len(),
+        minimized: current,
+        tests_run: oracle.runs,
+    })
+}
+
+/// Zeller's ddmin over lines: find a 1-minimal subset for which `fails` still holds.
+pub fn ddmin(mut input: Vec<String>, fails: &mut dyn FnMut(&[String]) -> bool) -> Vec<String> {
+    let mut granularity = 2;
+
+    while input.len() >= 2 {
+        let chunk = input.len().div_ceil(granularity);
+        let chunks: Vec<Vec<String>> = input.chunks(chunk).map(|c| c.to_vec()).collect();
+        let mut reduced = false;
+
+        for (i, subset) in chunks.iter().enumerate() {
+            if fails(subset) {
+                input = subset.clone();
+                granularity = 2;
+                reduced = true;
+                break;
+            }
+
+            let complement: Vec<String> = chunks
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .flat_map(|(_, c)| c.iter().cloned())
+                .collect();
+            if chunks.len() > 2 && fails(&complement) {
+                input = complement;
+                granularity = (granularity - 1).max(2);
+                reduced = true;
+                break;
+            }
+        }
+
+        if !reduced {
+            if granularity >= input.len() {
+                break;
+            }
+            granularity = (granularity * 2).min(input.len());
+        }
+    }
+
+    input
+}
+
+pub fn strip_literals(code: &str) -> String {
+    let strings = Regex::new(r#""(?:[^"\\]|\\.)*"|'(?:[^'\\]|\\.)*'"#).unwrap();
+    let numbers = Regex::new(r"\b\d+(?:\.\d+)?\b").unwrap();
+    let code = strings.replace_all(code, "\"\"");
+    numbers.replace_all(&code, "0").into_owned()
+}
+
+const KEYWORDS: &[&str] = &[
+    // Python
+    "def", "return", "if", "elif", "else", "for", "while", "in", "range", "print", "import", "from",
+    "class", "and", "or", "not", "None", "True", "False", "pass", "lambda", "self",
+    // Rust
+    "fn", "let", "mut", "pub", "struct", "enum", "impl", "match", "loop", "use", "mod", "println",
+    "true", "false", "Some", "Ok", "Err", "String", "Vec", "i32", "i64", "u32", "u64", "usize",
+    "f64", "bool", "str", // TypeScript
+    "function", "const", "var", "console", "log", "number", "string", "boolean",
+];
+
+/// Rename every non-keyword identifier to `idN`, consistently across the input.
+pub fn rename_identifiers(code: &str) -> String {
+    let identifier = Regex::new(r"\b[A-Za-z_][A-Za-z0-9_]*\b").unwrap();
+    let keywords: HashSet<&str> = KEYWORDS.iter().copied().collect();
+    let mut names: HashMap<String, String> = HashMap::new();
+
+    identifier
+        .replace_all(code, |caps: &regex::Captures| {
+            let name = &caps[0];
+            if keywords.contains(name) {
+                return name.to_string();
+            }
+            let next = names.len() + 1;
+            names.entry(name.to_string()).or_insert_with(|| format!("id{}", next)).clone()
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_minimize_preserves_failure_and_anonymizes() {
+        // Panics reported to the hook the repro hook wraps
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        panic::set_hook(Box::new({
+            let reported = reported.clone();
+            move |info| {
+                let message = info.payload().downcast_ref::<&str>().copied().unwrap_or("other");
+                reported.lock().unwrap().push(message.to_string());
+            }
+        }));
+
+        let code = "fn secret_pricing() {\n    let margin = 42;\n    println!;\n    let rate = \
+                    \"internal\";\n}\n";
+        let case = minimize(ReproTarget::RustToTypescript, code).expect("input should fail");
+
+        assert_eq!(case.minimized.trim(), "println!;");
+        assert!(ReproTarget::RustToTypescript.failure(&case.minimized).is_some());
+        assert!(!case.minimized.contains("secret_pricing"));
+        assert!(minimize(ReproTarget::PythonToRust, "print(1)").is_none());
+
+        // Only the panics caught during reduction are kept quiet
+        assert!(reported.lock().unwrap().is_empty());
+        let _ = std::thread::spawn(|| panic!("unrelated")).join();
+        assert_eq!(*reported.lock().unwrap(), ["unrelated"]);
+    }
+}
@@ -0,0 +1,87 @@
+//! Limits on transpiling for remote callers
+//!
+//! The REST server (`POST /transpile`) and the gRPC server (`Transpile`) serve
+//! [`CodeTranspiler::transpile`] to editor plugins and web UIs. Both turn away sources over
+//! [`MAX_CODE_BYTES`] and unsupported directions with [`check`] before transpiling, and stop
+//! waiting for a result after [`TIMEOUT`].
+
+use crate::{CodeTranspiler, DIRECTIONS};
+use std::fmt;
+use std::time::Duration;
+
+/// Largest source accepted, in bytes
+pub const MAX_CODE_BYTES: usize = 1024 * 1024;
+
+/// How long a caller is kept waiting for a transpilation
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Why a transpilation request is turned away
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rejection {
+    /// The source is larger than [`MAX_CODE_BYTES`]
+    TooLarge(usize),
+    Unsupported {
+        from: String,
+        to: String,
+    },
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooLarge(size) => {
+                write!(f, "source is {} bytes, the limit is {}", size, MAX_CODE_BYTES)
+            }
+            Self::Unsupported { from, to } => write!(
+                f,
+                "cannot transpile {} to {}, supported: {}",
+                from,
+                to,
+                supported().join(", ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Rejection {}
+
+/// The supported directions, e.g. `python→rust`
+pub fn supported() -> Vec<String> {
+    DIRECTIONS.iter().map(|(from, to)| format!("{}→{}", from, to)).collect()
+}
+
+/// Whether a request to transpile `code` from `from` to `to` is served
+pub fn check(code: &str, from: &str, to: &str) -> Result<(), Rejection> {
+    if code.len() > MAX_CODE_BYTES {
+        return Err(Rejection::TooLarge(code.len()));
+    }
+    let (from_lower, to_lower) = (from.to_lowercase(), to.to_lowercase());
+    if !DIRECTIONS.iter().any(|&(f, t)| f == from_lower && t == to_lower) {
+        return Err(Rejection::Unsupported { from: from.to_string(), to: to.to_string() });
+    }
+    Ok(())
+}
+
+/// [`check`] the request, then transpile it
+pub fn transpile(code: &str, from: &str, to: &str) -> Result<String, Rejection> {
+    check(code, from, to)?;
+    CodeTranspiler::transpile(code, from, to)
+        .ok_or_else(|| Rejection::Unsupported { from: from.to_string(), to: to.to_string() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejects_large_sources_and_unsupported_directions() {
+        assert!(transpile("def f():\n    return 1\n", "Python", "Rust").is_ok());
+        let large = "x".repeat(MAX_CODE_BYTES + 1);
+        assert_eq!(check(&large, "python", "rust"), Err(Rejection::TooLarge(MAX_CODE_BYTES + 1)));
+        let unsupported = check("", "go", "rust").unwrap_err();
+        assert_eq!(
+            unsupported.to_string(),
+            "cannot transpile go to rust, supported: python→rust, rust→typescript"
+        );
+    }
+}
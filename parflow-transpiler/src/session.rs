@@ -0,0 +1,288 @@
+//! Incremental transpilation with source maps
+//!
+//! A [`TranspileSession`] keeps the source split into top-level functions (`def` in Python,
+//! `fn` in Rust), each with its transpiled lines. [`TranspileSession::edit`] replaces a range of
+//! source lines and re-transpiles only the functions whose source changed; the others keep their
+//! cached output. [`TranspileSession::source_map`] links every generated line back to the source
+//! line it came from, so a failure in mirrored code can be traced to the original.
+//!
+//! Lines are counted from 0 throughout, as in source map v3.
+
+use crate::options::TranspileOptions;
+use crate::CodeTranspiler;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::ops::Range;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    PythonToRust,
+    RustToTypescript,
+}
+
+impl Direction {
+    fn parse(from: &str, to: &str) -> Option<Self> {
+        match (from.to_lowercase().as_str(), to.to_lowercase().as_str()) {
+            ("python", "rust") => Some(Self::PythonToRust),
+            ("rust", "typescript") => Some(Self::RustToTypescript),
+            _ => None,
+        }
+    }
+
+    /// Lines [`CodeTranspiler::transpile`] puts before and after the converted source
+    fn frame(&self) -> (&'static [&'static str], &'static [&'static str]) {
+        match self {
+            Self::PythonToRust => {
+                (&["// Auto-generated Rust code from Python", "fn main() {"], &["}"])
+            }
+            Self::RustToTypescript => (&["// Auto-generated TypeScript code from Rust"], &[]),
+        }
+    }
+
+    /// The function a top-level source line starts, if any
+    fn function_name(&self, line: &str) -> Option<String> {
+        let rest = match self {
+            Self::PythonToRust => line.strip_prefix("def ")?,
+            Self::RustToTypescript => {
+                line.strip_prefix("fn ").or_else(|| line.strip_prefix("pub fn "))?
+            }
+        };
+        let name: String = rest.chars().take_while(|c| c.is_alphanumeric() || *c == '_').collect();
+        Some(name)
+    }
+
+    /// Convert a unit's lines, pairing each generated line with its offset in the unit
+    fn convert(&self, lines: &[String], options: &TranspileOptions) -> Vec<(usize, String)> {
+        let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+        match self {
+            Self::PythonToRust => CodeTranspiler::python_block_to_rust(&lines, options),
+            Self::RustToTypescript => CodeTranspiler::rust_block_to_typescript(&lines, options),
+        }
+    }
+}
+
+/// A top-level function, or the lines before the first one
+#[derive(Debug, Clone)]
+struct Unit {
+    /// First source line
+    start: usize,
+    source: Vec<String>,
+    /// Generated lines, each with its source line relative to `start`
+    generated: Vec<(usize, String)>,
+}
+
+/// Transpiled source that can be edited without re-transpiling unchanged functions
+#[derive(Debug, Clone)]
+pub struct TranspileSession {
+    direction: Direction,
+    options: TranspileOptions,
+    units: Vec<Unit>,
+}
+
+impl TranspileSession {
+    /// Parse and transpile `code`; `None` for a direction outside [`crate::DIRECTIONS`]
+    pub fn new(code: &str, from: &str, to: &str) -> Option<Self> {
+        Self::with_options(code, from, to, TranspileOptions::default())
+    }
+
+    /// [`Self::new`] in the style set by `options`
+    pub fn with_options(
+        code: &str,
+        from: &str,
+        to: &str,
+        options: TranspileOptions,
+    ) -> Option<Self> {
+        let direction = Direction::parse(from, to)?;
+        let mut session = Self { direction, options, units: Vec::new() };
+        session.rebuild(code.lines().map(str::to_string).collect());
+        Some(session)
+    }
+
+    /// The current source
+    pub fn source(&self) -> String {
+        self.units.iter().flat_map(|unit| &unit.source).map(|line| format!("{}\n", line)).collect()
+    }
+
+    fn line_count(&self) -> usize {
+        self.units.iter().map(|unit| unit.source.len()).sum()
+    }
+
+    /// Replace source `lines` with `replacement` (an empty range inserts) and re-transpile the
+    /// functions that changed. Returns their names, `(top level)` for code before the first one.
+    pub fn edit(&mut self, lines: Range<usize>, replacement: &str) -> Result<Vec<String>, String> {
+        let count = self.line_count();
+        if lines.start > lines.end || lines.end > count {
+            return Err(format!(
+                "cannot replace lines {}..{}, the source has {} lines",
+                lines.start, lines.end, count
+            ));
+        }
+        let mut source: Vec<String> =
+            self.units.iter().flat_map(|unit| unit.source.iter().cloned()).collect();
+        source.splice(lines, replacement.lines().map(str::to_string));
+        Ok(self.rebuild(source))
+    }
+
+    /// Split `source` into units, reusing the output of units whose source is unchanged, and
+    /// return the names of the units transpiled again
+    fn rebuild(&mut self, source: Vec<String>) -> Vec<String> {
+        let mut cached: HashMap<Vec<String>, Vec<Vec<(usize, String)>>> = HashMap::new();
+        for unit in self.units.drain(..) {
+            cached.entry(unit.source).or_default().push(unit.generated);
+        }
+
+        let mut starts: Vec<usize> = source
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| self.direction.function_name(line).is_some())
+            .map(|(i, _)| i)
+            .collect();
+        if starts.first() != Some(&0) {
+            starts.insert(0, 0);
+        }
+
+        let mut retranspiled = Vec::new();
+        for (i, &start) in starts.iter().enumerate() {
+            let end = starts.get(i + 1).copied().unwrap_or(source.len());
+            let lines = source[start..end].to_vec();
+            let name = lines.first().and_then(|line| self.direction.function_name(line));
+            let generated = match cached.get_mut(&lines).and_then(|outputs| outputs.pop()) {
+                Some(generated) => generated,
+                None => {
+                    retranspiled.push(name.unwrap_or_else(|| "(top level)".to_string()));
+                    self.direction.convert(&lines, &self.options)
+                }
+            };
+            self.units.push(Unit { start, source: lines, generated });
+        }
+        retranspiled
+    }
+
+    /// The transpiled code, as [`CodeTranspiler::transpile_with`] would produce it for
+    /// [`Self::source`]
+    pub fn output(&self) -> String {
+        let (header, footer) = self.direction.frame();
+        let body =
+            self.units.iter().flat_map(|unit| &unit.generated).map(|(_, line)| line.as_str());
+        header
+            .iter()
+            .copied()
+            .chain(body)
+            .chain(footer.iter().copied())
+            .map(|line| format!("{}\n", line))
+            .collect()
+    }
+
+    /// Where each line of [`Self::output`] came from
+    pub fn source_map(&self) -> SourceMap {
+        let (header, footer) = self.direction.frame();
+        let mut lines = vec![None; header.len()];
+        for unit in &self.units {
+            lines.extend(unit.generated.iter().map(|(offset, _)| Some(unit.start + offset)));
+        }
+        lines.extend(vec![None; footer.len()]);
+        SourceMap { lines }
+    }
+}
+
+/// Generated lines mapped to source lines; `None` for lines the transpiler adds on its own
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceMap {
+    pub lines: Vec<Option<usize>>,
+}
+
+#[derive(Serialize)]
+struct SourceMapV3<'a> {
+    version: u8,
+    file: &'a str,
+    sources: [&'a str; 1],
+    names: [&'a str; 0],
+    mappings: String,
+}
+
+impl SourceMap {
+    /// The source line generated line `line` came from
+    pub fn original_line(&self, line: usize) -> Option<usize> {
+        self.lines.get(line).copied().flatten()
+    }
+
+    /// Source map v3 JSON for generated `file` transpiled from `source`, understood by browsers
+    /// and debuggers
+    pub fn to_v3_json(&self, file: &str, source: &str) -> String {
+        // One segment per mapped line at column 0; the source line is relative to the last one
+        let mut previous = 0i64;
+        let mappings: Vec<String> = self
+            .lines
+            .iter()
+            .map(|line| match line {
+                Some(line) => {
+                    let delta = *line as i64 - previous;
+                    previous = *line as i64;
+                    [0, 0, delta, 0].into_iter().map(vlq).collect()
+                }
+                None => String::new(),
+            })
+            .collect();
+        let map = SourceMapV3 {
+            version: 3,
+            file,
+            sources: [source],
+            names: [],
+            mappings: mappings.join(";"),
+        };
+        serde_json::to_string(&map).expect("source map serializes")
+    }
+}
+
+/// Base64 VLQ encoding of one source map field
+fn vlq(value: i64) -> String {
+    const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut rest = if value < 0 { ((-value) << 1) | 1 } else { value << 1 };
+    let mut encoded = String::new();
+    loop {
+        let mut digit = rest & 0b11111;
+        rest >>= 5;
+        if rest > 0 {
+            digit |= 0b100000;
+        }
+        encoded.push(BASE64[digit as usize] as char);
+        if rest == 0 {
+            return encoded;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edits_retranspile_only_changed_functions() {
+        let code = "import math\n\ndef area(r):\n    print(r)\n\ndef volume(r):\n    print(r)\n";
+        let mut session = TranspileSession::new(code, "python", "rust").unwrap();
+        assert_eq!(session.output(), CodeTranspiler::transpile(code, "python", "rust").unwrap());
+
+        let changed = session.edit(6..7, "    print(r * r)").unwrap();
+        assert_eq!(changed, vec!["volume"]);
+        assert_eq!(
+            session.output(),
+            CodeTranspiler::transpile(&session.source(), "python", "rust").unwrap()
+        );
+        assert!(session.edit(3..9, "").is_err());
+
+        // Header, fn main, then `import math` from line 0 and `def area` from line 2
+        let map = session.source_map();
+        assert_eq!(&map.lines[..4], &[None, None, Some(0), Some(2)]);
+        assert_eq!(map.original_line(6), Some(6));
+        assert_eq!(map.lines.last(), Some(&None));
+        assert!(map.to_v3_json("out.rs", "in.py").contains("\"mappings\":\";;AAAA;AAEA;AACA;"));
+    }
+
+    #[test]
+    fn test_vlq_encoding() {
+        assert_eq!(vlq(0), "A");
+        assert_eq!(vlq(1), "C");
+        assert_eq!(vlq(-1), "D");
+        assert_eq!(vlq(16), "gB");
+    }
+}
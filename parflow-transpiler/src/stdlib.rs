@@ -0,0 +1,214 @@
+//! Standard library calls across languages
+//!
+//! A [`StdlibMap`] rewrites calls such as `len(x)` → `x.len()` or `json.dumps(x)` →
+//! `serde_json::to_string(&x)?`. The built-in table is `resources/stdlib.toml`; a project adds
+//! or overrides entries in [`PROJECT_FILE`] with the same format. The transpiler rewrites each
+//! line before converting it, and `parflow_mirror::LanguageTranslator` uses the same table.
+
+use crate::infer::split_top_level;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Project-specific mappings, relative to the project root
+pub const PROJECT_FILE: &str = ".parflow/stdlib.toml";
+
+const BUILTIN: &str = include_str!("../resources/stdlib.toml");
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallMapping {
+    pub from: String,
+    pub to: String,
+    /// A function (`json.dumps`) or, with a leading dot, a method (`.len`)
+    pub call: String,
+    /// The replacement, with `{0}`, `{1}`, ... for arguments, `{args}` for all of them and
+    /// `{self}` for a method's receiver
+    pub template: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StdlibMap {
+    #[serde(default, rename = "mapping")]
+    pub mappings: Vec<CallMapping>,
+}
+
+impl StdlibMap {
+    /// The mappings shipped with ParFlow
+    pub fn builtin() -> Self {
+        static PARSED: OnceLock<StdlibMap> = OnceLock::new();
+        PARSED
+            .get_or_init(|| toml::from_str(BUILTIN).expect("built-in stdlib table parses"))
+            .clone()
+    }
+
+    pub fn from_toml_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("reading stdlib mappings from {}", path.display()))?;
+        Ok(toml::from_str(&text)?)
+    }
+
+    /// The built-in mappings plus the project's [`PROJECT_FILE`] under `root`, if it exists
+    pub fn for_project(root: &Path) -> Result<Self> {
+        let mut map = Self::builtin();
+        let file = root.join(PROJECT_FILE);
+        if file.is_file() {
+            map.extend(Self::from_toml_file(&file)?);
+        }
+        Ok(map)
+    }
+
+    /// Add `other`'s mappings, replacing any for the same call and languages
+    pub fn extend(&mut self, other: StdlibMap) {
+        for mapping in other.mappings {
+            self.mappings.retain(|existing| {
+                (&existing.from, &existing.to, &existing.call)
+                    != (&mapping.from, &mapping.to, &mapping.call)
+            });
+            self.mappings.push(mapping);
+        }
+    }
+
+    /// Rewrite the calls in `line` from language `from` to `to`; language names are
+    /// case-insensitive
+    pub fn rewrite(&self, line: &str, from: &str, to: &str) -> String {
+        let mut line = line.to_string();
+        for mapping in &self.mappings {
+            if mapping.from.eq_ignore_ascii_case(from) && mapping.to.eq_ignore_ascii_case(to) {
+                line = mapping.rewrite(&line);
+            }
+        }
+        line
+    }
+}
+
+impl CallMapping {
+    fn rewrite(&self, line: &str) -> String {
+        let method = self.call.starts_with('.');
+        let pattern = format!("{}(", self.call);
+        let mut rewritten = String::new();
+        let mut rest = line;
+        while let Some(found) = find_outside_strings(rest, &pattern) {
+            let receiver_start = if method {
+                found - rest[..found].chars().rev().take_while(|c| is_path_char(*c)).count()
+            } else {
+                found
+            };
+            let preceded_by_path = rest[..found].chars().next_back().is_some_and(is_path_char);
+            let open = found + pattern.len() - 1;
+            let replacement = match closing_paren(rest, open) {
+                Some(close) if method == preceded_by_path => {
+                    let args = split_top_level(&rest[open + 1..close]);
+                    let receiver = &rest[receiver_start..found];
+                    self.fill(receiver, &args).map(|filled| (filled, close))
+                }
+                _ => None,
+            };
+            match replacement {
+                Some((filled, close)) => {
+                    rewritten.push_str(&rest[..receiver_start]);
+                    rewritten.push_str(&filled);
+                    rest = &rest[close + 1..];
+                }
+                None => {
+                    rewritten.push_str(&rest[..found + pattern.len()]);
+                    rest = &rest[found + pattern.len()..];
+                }
+            }
+        }
+        rewritten.push_str(rest);
+        rewritten
+    }
+
+    /// The template filled in, if it takes this many arguments
+    fn fill(&self, receiver: &str, args: &[&str]) -> Option<String> {
+        let mut filled =
+            self.template.replace("{self}", receiver).replace("{args}", &args.join(", "));
+        let mut used = 0;
+        while filled.contains(&format!("{{{}}}", used)) {
+            filled = filled.replace(&format!("{{{}}}", used), args.get(used)?);
+            used += 1;
+        }
+        (self.template.contains("{args}") || used == args.len()).then_some(filled)
+    }
+}
+
+fn is_path_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '.' || c == ':'
+}
+
+/// Byte offset of the first `pattern` outside string literals
+fn find_outside_strings(text: &str, pattern: &str) -> Option<usize> {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some(_) if c == '\\' => escaped = true,
+            Some(open) if c == open => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if text[i..].starts_with(pattern) => return Some(i),
+            None => {}
+        }
+    }
+    None
+}
+
+/// Byte offset of the parenthesis closing the one at `open`
+fn closing_paren(text: &str, open: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote = None;
+    for (i, c) in text[open..].char_indices() {
+        match (quote, c) {
+            (Some(q), _) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(' | '[' | '{') => depth += 1,
+            (None, ')' | ']' | '}') => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(open + i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrites_calls_and_accepts_project_mappings() {
+        let map = StdlibMap::builtin();
+        assert_eq!(
+            map.rewrite("print(len(items), json.dumps(config))", "python", "rust"),
+            "print(items.len(), serde_json::to_string(&config)?)"
+        );
+        assert_eq!(
+            map.rewrite("name.upper() + \"len(x)\"", "python", "rust"),
+            "name.to_uppercase() + \"len(x)\""
+        );
+        assert_eq!(map.rewrite("my_len(x)", "python", "rust"), "my_len(x)");
+        assert_eq!(map.rewrite("max(a, b, c)", "python", "rust"), "max(a, b, c)");
+        assert_eq!(
+            map.rewrite("let n = self.items.len();", "rust", "typescript"),
+            "let n = self.items.length;"
+        );
+
+        let dir = std::env::temp_dir().join(format!("parflow-stdlib-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join(".parflow")).unwrap();
+        std::fs::write(
+            dir.join(PROJECT_FILE),
+            "[[mapping]]\nfrom = \"python\"\nto = \"rust\"\ncall = \"len\"\ntemplate = \"count({0})\"\n",
+        )
+        .unwrap();
+        let project = StdlibMap::for_project(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(project.rewrite("len(items)", "Python", "Rust"), "count(items)");
+        assert_eq!(project.mappings.len(), map.mappings.len());
+    }
+}
@@ -1,36 +1,205 @@
 //! WebAssembly bindings for ParFlow
-//! 
+//!
 //! Provides WASM-compatible interfaces for cross-language orchestration
 //! and performance optimization between Rust and JavaScript.
 
+use futures::FutureExt;
+use serde::Serialize;
 use wasm_bindgen::prelude::*;
 
+pub mod progress;
+pub mod workers;
+
+/// Plain JS objects rather than `Map`s, so results can go straight to `JSON.stringify`
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, JsError> {
+    value
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| JsError::new(&e.to_string()))
+}
+
+#[derive(Serialize)]
+struct Transpiled {
+    from: String,
+    to: String,
+    code: String,
+}
+
+#[derive(Serialize)]
+struct Direction {
+    from: &'static str,
+    to: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Complexity {
+    total_lines: f64,
+    code_lines: f64,
+    comment_density: f64,
+    complexity_score: f64,
+    maintainability_index: f64,
+}
+
+/// Transpile `code` from one language to another
+///
+/// # Returns
+///
+/// `{ from, to, code }`; throws for an unsupported direction (see `transpile_directions`)
+#[wasm_bindgen]
+pub fn transpile(code: &str, from: &str, to: &str) -> Result<JsValue, JsError> {
+    let stage = format!("{} → {}", from, to);
+    progress::emit("transpile", &stage, 0, 1);
+    let transpiled = parflow_transpiler::CodeTranspiler::transpile(code, from, to)
+        .ok_or_else(|| JsError::new(&format!("unsupported transpilation {} → {}", from, to)))?;
+    progress::emit("transpile", &stage, 1, 1);
+    to_js(&Transpiled { from: from.to_lowercase(), to: to.to_lowercase(), code: transpiled })
+}
+
+/// Language pairs `transpile` supports
+///
+/// # Returns
+///
+/// An array of `{ from, to }`
+#[wasm_bindgen]
+pub fn transpile_directions() -> Result<JsValue, JsError> {
+    let directions: Vec<Direction> =
+        parflow_transpiler::DIRECTIONS.iter().map(|&(from, to)| Direction { from, to }).collect();
+    to_js(&directions)
+}
+
+/// Estimate the complexity of a snippet
+///
+/// # Returns
+///
+/// `{ totalLines, codeLines, commentDensity, complexityScore, maintainabilityIndex }`
+#[wasm_bindgen]
+pub fn analyze_complexity(code: &str, language: &str) -> Result<JsValue, JsError> {
+    progress::emit("analyzeComplexity", language, 0, 1);
+    let metrics = parflow_transpiler::CodeTranspiler::analyze_code_complexity(code, language);
+    progress::emit("analyzeComplexity", language, 1, 1);
+    let metric = |name: &str| metrics.get(name).copied().filter(|v| v.is_finite()).unwrap_or(0.0);
+    to_js(&Complexity {
+        total_lines: metric("total_lines"),
+        code_lines: metric("code_lines"),
+        comment_density: metric("comment_density"),
+        complexity_score: metric("complexity_score"),
+        maintainability_index: metric("maintainability_index"),
+    })
+}
+
+/// Find semantic patterns (recursion, map/reduce, iterator chains, endpoints, ...) in a snippet
+///
+/// # Returns
+///
+/// An array of `{ pattern, function, line, evidence }` in source order; `pattern` is a name
+/// such as `"FibonacciLike"` and `function` is `null` outside functions
+#[wasm_bindgen]
+pub fn detect_patterns(code: &str, language: &str) -> Result<JsValue, JsError> {
+    let mut scan = semantic_compiler::PatternScan::new(code, &language.to_lowercase());
+    let mut matches = Vec::new();
+    while let Some(found) = scan.step() {
+        matches.extend(found);
+        progress::emit("detectPatterns", "functions", scan.done(), scan.total());
+    }
+    matches.sort_by_key(|m| m.line);
+    to_js(&matches)
+}
+
+/// `detect_patterns` for large sources: yields to the browser every frame so progress
+/// listeners can repaint
+///
+/// # Returns
+///
+/// A Promise of the same array `detect_patterns` returns
+#[wasm_bindgen]
+pub async fn detect_patterns_async(code: String, language: String) -> Result<JsValue, JsError> {
+    let mut scan = semantic_compiler::PatternScan::new(&code, &language.to_lowercase());
+    let mut matches = Vec::new();
+    let mut slice_start = js_sys::Date::now();
+    while let Some(found) = scan.step() {
+        matches.extend(found);
+        progress::emit("detectPatterns", "functions", scan.done(), scan.total());
+        if js_sys::Date::now() - slice_start > progress::FRAME_BUDGET_MS {
+            progress::next_tick().await;
+            slice_start = js_sys::Date::now();
+        }
+    }
+    matches.sort_by_key(|m| m.line);
+    to_js(&matches)
+}
+
+/// The example tasks as blocking jobs for Web Workers, which may busy-wait
+fn example_tasks() -> Vec<Box<dyn FnOnce() -> i32 + Send>> {
+    parflow_core::EXAMPLE_TASKS
+        .iter()
+        .map(|&(ms, value)| {
+            Box::new(move || {
+                let until = js_sys::Date::now() + ms as f64;
+                while js_sys::Date::now() < until {}
+                value
+            }) as Box<dyn FnOnce() -> i32 + Send>
+        })
+        .collect()
+}
+
+/// Run the example tasks on `pool`, reporting each as it finishes
+async fn run_on_pool(pool: &workers::WorkerPool) -> Result<Vec<i32>, JsValue> {
+    let total = parflow_core::EXAMPLE_TASKS.len();
+    let finished = std::cell::Cell::new(0);
+    let mut results = Vec::with_capacity(total);
+    for task in example_tasks() {
+        let finished = &finished;
+        results.push(pool.submit(task)?.inspect(move |_| {
+            finished.set(finished.get() + 1);
+            // The last one is reported once all results are in
+            if finished.get() < total {
+                progress::emit("runParallel", "tasks", finished.get(), total);
+            }
+        }));
+    }
+    futures::future::try_join_all(results).await
+}
+
 /// Run parallel computation from JavaScript
-/// 
+///
 /// This function demonstrates cross-language parallel execution
 /// by running computations that can be called from JavaScript.
-/// 
+/// After `init_thread_pool` succeeds, each task runs on its own Web Worker.
+///
 /// # Returns
-/// 
+///
 /// A JavaScript Promise that resolves to the sum of parallel computation results
 #[wasm_bindgen]
 pub async fn run_js_par() -> JsValue {
-    let v = parflow_core::run_example_par().await;
+    let total = parflow_core::EXAMPLE_TASKS.len();
+    progress::emit("runParallel", "tasks", 0, total);
+    let pooled = match workers::pool() {
+        Some(pool) => run_on_pool(&pool).await.ok(),
+        None => None,
+    };
+    let v = match pooled {
+        Some(v) => v,
+        None => parflow_core::run_example_par().await,
+    };
+    progress::emit("runParallel", "tasks", total, total);
     let sum: i32 = v.into_iter().sum();
     JsValue::from_f64(sum as f64)
 }
 
 /// Run sequential computation from JavaScript
-/// 
+///
 /// This function demonstrates cross-language sequential execution
 /// for comparison with parallel performance.
-/// 
+///
 /// # Returns
-/// 
+///
 /// A JavaScript Promise that resolves to the sum of sequential computation results
 #[wasm_bindgen]
 pub async fn run_js_seq() -> JsValue {
+    let total = parflow_core::EXAMPLE_TASKS.len();
+    progress::emit("runSequential", "tasks", 0, total);
     let v = parflow_core::run_example_seq().await;
+    progress::emit("runSequential", "tasks", total, total);
     let sum: i32 = v.into_iter().sum();
     JsValue::from_f64(sum as f64)
 }
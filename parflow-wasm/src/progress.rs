@@ -0,0 +1,91 @@
+//! Progress events for long-running calls
+//!
+//! Callbacks registered with [`on_progress`] receive `{ operation, stage, done, total }` as work
+//! advances. The async calls hand control back to the browser between steps, so a progress bar
+//! driven by these events repaints while the analysis runs.
+
+use serde::Serialize;
+use std::cell::{Cell, RefCell};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+/// Time a step loop may hold the main thread before yielding, one frame at 60 Hz
+pub const FRAME_BUDGET_MS: f64 = 16.0;
+
+thread_local! {
+    static LISTENERS: RefCell<Vec<(u32, js_sys::Function)>> = const { RefCell::new(Vec::new()) };
+    static NEXT_ID: Cell<u32> = const { Cell::new(1) };
+}
+
+#[derive(Serialize)]
+struct Progress<'a> {
+    /// Call reporting progress, e.g. `"detectPatterns"`
+    operation: &'a str,
+    /// What it is working on, e.g. a function name
+    stage: &'a str,
+    done: usize,
+    total: usize,
+}
+
+/// Call `callback` with every progress event
+///
+/// # Returns
+///
+/// A subscription ID for `off_progress`
+#[wasm_bindgen]
+pub fn on_progress(callback: js_sys::Function) -> u32 {
+    let id = NEXT_ID.with(|next| next.replace(next.get() + 1));
+    LISTENERS.with(|listeners| listeners.borrow_mut().push((id, callback)));
+    id
+}
+
+/// Stop a subscription made with `on_progress`
+///
+/// # Returns
+///
+/// `false` if there was no such subscription
+#[wasm_bindgen]
+pub fn off_progress(id: u32) -> bool {
+    LISTENERS.with(|listeners| {
+        let mut listeners = listeners.borrow_mut();
+        let before = listeners.len();
+        listeners.retain(|(listener, _)| *listener != id);
+        listeners.len() != before
+    })
+}
+
+pub fn has_listeners() -> bool {
+    LISTENERS.with(|listeners| !listeners.borrow().is_empty())
+}
+
+/// Send an event to every listener. A listener that throws is logged and skipped, so a broken
+/// progress bar cannot fail the work it reports on.
+pub fn emit(operation: &str, stage: &str, done: usize, total: usize) {
+    if !has_listeners() {
+        return;
+    }
+    let Ok(event) = crate::to_js(&Progress { operation, stage, done, total }) else { return };
+    // Listeners may subscribe or unsubscribe from inside the callback
+    let listeners: Vec<js_sys::Function> =
+        LISTENERS.with(|listeners| listeners.borrow().iter().map(|(_, f)| f.clone()).collect());
+    for listener in listeners {
+        if let Err(e) = listener.call1(&JsValue::NULL, &event) {
+            web_sys::console::error_2(&"progress listener failed:".into(), &e);
+        }
+    }
+}
+
+/// Let the browser handle input and repaint before continuing.
+pub async fn next_tick() {
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        let global = js_sys::global();
+        let set_timeout = js_sys::Reflect::get(&global, &"setTimeout".into())
+            .ok()
+            .and_then(|f| f.dyn_into::<js_sys::Function>().ok());
+        let scheduled = set_timeout.map(|f| f.call2(&global, &resolve, &JsValue::from(0)));
+        if !matches!(scheduled, Some(Ok(_))) {
+            let _ = resolve.call0(&JsValue::NULL);
+        }
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
@@ -0,0 +1,160 @@
+//! Web Worker pool for running tasks in parallel
+//!
+//! Workers instantiate this same module on the page's shared `WebAssembly.Memory`, so a Rust
+//! closure can be handed to one by pointer. That needs a build with atomics (see
+//! `scripts/build-wasm-threads.sh`) served cross-origin isolated so `SharedArrayBuffer` exists;
+//! without it [`init_thread_pool`] returns `false` and callers run tasks on the main thread.
+
+use futures::channel::oneshot;
+use futures::TryFutureExt;
+use std::cell::RefCell;
+use std::future::Future;
+use std::rc::{Rc, Weak};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{DedicatedWorkerGlobalScope, Event, Worker};
+
+/// Script each worker runs, relative to the page; the build script copies it into `pkg/`
+pub const DEFAULT_WORKER_SCRIPT: &str = "./pkg/worker.js";
+
+thread_local! {
+    static POOL: RefCell<Option<Rc<WorkerPool>>> = const { RefCell::new(None) };
+}
+
+/// Installed `onmessage`/`onerror` handler of a busy worker
+type Reclaim = (Worker, Closure<dyn FnMut(Event)>);
+
+/// A task posted to a worker by pointer
+struct Work {
+    func: Box<dyn FnOnce() + Send>,
+}
+
+pub struct WorkerPool {
+    script: String,
+    idle: RefCell<Vec<Worker>>,
+    /// Handlers that return busy workers to `idle`, dropped once they fire
+    handlers: RefCell<Vec<Reclaim>>,
+    this: Weak<WorkerPool>,
+}
+
+/// Whether this module was built with shared memory and the page may share it with workers.
+pub fn threads_available() -> bool {
+    let isolated = js_sys::Reflect::get(&js_sys::global(), &"crossOriginIsolated".into())
+        .map(|v| v.as_bool() == Some(true))
+        .unwrap_or(false);
+    isolated
+        && wasm_bindgen::memory()
+            .unchecked_into::<js_sys::WebAssembly::Memory>()
+            .buffer()
+            .is_instance_of::<js_sys::SharedArrayBuffer>()
+}
+
+/// Start `size` workers running `script` (default `./pkg/worker.js`) for parallel execution.
+///
+/// # Returns
+///
+/// `false` when the page cannot share memory with workers; tasks then stay on the main thread
+#[wasm_bindgen]
+pub fn init_thread_pool(size: usize, script: Option<String>) -> Result<bool, JsValue> {
+    if !threads_available() {
+        return Ok(false);
+    }
+    let pool = WorkerPool::new(size, script.as_deref().unwrap_or(DEFAULT_WORKER_SCRIPT))?;
+    POOL.with(|p| *p.borrow_mut() = Some(pool));
+    Ok(true)
+}
+
+/// The pool started by [`init_thread_pool`], if any.
+pub fn pool() -> Option<Rc<WorkerPool>> {
+    POOL.with(|p| p.borrow().clone())
+}
+
+impl WorkerPool {
+    pub fn new(size: usize, script: &str) -> Result<Rc<Self>, JsValue> {
+        let pool = Rc::new_cyclic(|this| Self {
+            script: script.to_string(),
+            idle: RefCell::new(Vec::new()),
+            handlers: RefCell::new(Vec::new()),
+            this: this.clone(),
+        });
+        for _ in 0..size {
+            let worker = pool.spawn()?;
+            pool.idle.borrow_mut().push(worker);
+        }
+        Ok(pool)
+    }
+
+    fn spawn(&self) -> Result<Worker, JsValue> {
+        let worker = Worker::new(&self.script)?;
+        // worker.js instantiates the module on this memory before accepting work
+        let init = js_sys::Array::of2(&wasm_bindgen::module(), &wasm_bindgen::memory());
+        worker.post_message(&init)?;
+        Ok(worker)
+    }
+
+    /// Run `f` on an idle worker, starting a new one if all are busy.
+    pub fn execute(&self, f: impl FnOnce() + Send + 'static) -> Result<(), JsValue> {
+        let worker = match self.idle.borrow_mut().pop() {
+            Some(worker) => worker,
+            None => self.spawn()?,
+        };
+        let work = Box::into_raw(Box::new(Work { func: Box::new(f) }));
+        if let Err(e) = worker.post_message(&JsValue::from(work as u32)) {
+            // SAFETY: the worker never received the pointer, so it is still ours
+            drop(unsafe { Box::from_raw(work) });
+            return Err(e);
+        }
+        self.reclaim_when_done(worker);
+        Ok(())
+    }
+
+    /// Return `worker` to the idle list once it reports back (or fails).
+    fn reclaim_when_done(&self, worker: Worker) {
+        let pool = self.this.clone();
+        let finished = worker.clone();
+        let handler = Closure::<dyn FnMut(Event)>::new(move |event: Event| {
+            if let Some(error) = event.dyn_ref::<web_sys::ErrorEvent>() {
+                web_sys::console::error_1(&format!("worker failed: {}", error.message()).into());
+            }
+            let Some(pool) = pool.upgrade() else { return };
+            finished.set_onmessage(None);
+            finished.set_onerror(None);
+            pool.handlers.borrow_mut().retain(|(w, _)| !js_sys::Object::is(w, &finished));
+            pool.idle.borrow_mut().push(finished.clone());
+        });
+        worker.set_onmessage(Some(handler.as_ref().unchecked_ref()));
+        worker.set_onerror(Some(handler.as_ref().unchecked_ref()));
+        self.handlers.borrow_mut().push((worker, handler));
+    }
+
+    /// Run `task` on a worker; the future resolves to its result.
+    pub fn submit<T: Send + 'static>(
+        &self,
+        task: impl FnOnce() -> T + Send + 'static,
+    ) -> Result<impl Future<Output = Result<T, JsValue>>, JsValue> {
+        let (done, result) = oneshot::channel();
+        self.execute(move || {
+            let _ = done.send(task());
+        })?;
+        Ok(result.map_err(|_| JsValue::from_str("worker dropped its task")))
+    }
+
+    /// Run every task on a worker at once and collect the results in order.
+    pub async fn map<T: Send + 'static>(
+        &self,
+        tasks: Vec<Box<dyn FnOnce() -> T + Send>>,
+    ) -> Result<Vec<T>, JsValue> {
+        let results =
+            tasks.into_iter().map(|task| self.submit(task)).collect::<Result<Vec<_>, _>>()?;
+        futures::future::try_join_all(results).await
+    }
+}
+
+/// Called by worker.js with a pointer posted by [`WorkerPool::execute`]
+#[wasm_bindgen]
+pub fn worker_entry_point(ptr: u32) -> Result<(), JsValue> {
+    // SAFETY: `execute` leaked this box for exactly one worker to take back
+    let work = unsafe { Box::from_raw(ptr as *mut Work) };
+    (work.func)();
+    js_sys::global().unchecked_into::<DedicatedWorkerGlobalScope>().post_message(&JsValue::NULL)
+}
@@ -0,0 +1,22 @@
+// Web Worker of the parflow-wasm thread pool (see src/workers.rs), copied into pkg/ by
+// scripts/build-wasm-threads.sh.
+//
+// The first message carries the module and the page's shared memory; every later one is a
+// pointer to a task, which runs on this worker's own wasm instance.
+importScripts('./parflow_wasm.js');
+
+let ready = null;
+const queued = [];
+
+self.onmessage = async (event) => {
+    if (ready === null) {
+        const [module, memory] = event.data;
+        ready = wasm_bindgen({ module_or_path: module, memory });
+        await ready;
+        queued.splice(0).forEach((ptr) => wasm_bindgen.worker_entry_point(ptr));
+        return;
+    }
+    queued.push(event.data);
+    await ready;
+    queued.splice(0).forEach((ptr) => wasm_bindgen.worker_entry_point(ptr));
+};
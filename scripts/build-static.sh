@@ -0,0 +1,43 @@
+#!/bin/bash
+# Build fully static ParFlow binaries (CLI and daemons) for x86_64 Linux with musl.
+#
+# Usage: scripts/build-static.sh [extra cargo args, e.g. --no-default-features]
+
+set -e
+
+TARGET=x86_64-unknown-linux-musl
+PACKAGES="-p parflow-cli -p parflow-grpc -p parflow-rest"
+
+if ! rustup target list --installed | grep -q "^${TARGET}$"; then
+    echo "📦 Installing the ${TARGET} target..."
+    rustup target add "${TARGET}"
+fi
+
+# ring compiles C and assembly, which needs a musl-targeting C compiler
+if [ -z "${CC_x86_64_unknown_linux_musl}" ]; then
+    if command -v musl-gcc >/dev/null; then
+        export CC_x86_64_unknown_linux_musl=musl-gcc
+    else
+        echo "❌ musl-gcc not found (install musl-tools) and CC_x86_64_unknown_linux_musl unset"
+        exit 1
+    fi
+fi
+
+# Overrides the repo's target-cpu=native so the binaries run on any x86_64 machine
+export RUSTFLAGS="-C target-feature=+crt-static"
+
+echo "🔨 Building static binaries for ${TARGET}..."
+cargo build --release --target "${TARGET}" ${PACKAGES} "$@"
+
+for binary in parflow-cli parflow-grpc parflow-rest; do
+    path="target/${TARGET}/release/${binary}"
+    if ldd "${path}" 2>&1 | grep -qE "not a dynamic executable|statically linked"; then
+        echo "✅ ${path} is static"
+    else
+        echo "❌ ${path} has dynamic dependencies:"
+        ldd "${path}"
+        exit 1
+    fi
+done
+
+"target/${TARGET}/release/parflow-cli" status --capabilities
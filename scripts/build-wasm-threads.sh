@@ -0,0 +1,30 @@
+#!/bin/bash
+# Build parflow-wasm with shared memory so parallel tasks run on the Web Worker pool.
+#
+# Serve the output with `Cross-Origin-Opener-Policy: same-origin` and
+# `Cross-Origin-Embedder-Policy: require-corp` and call
+# `init_thread_pool(navigator.hardwareConcurrency)` once after loading the module.
+#
+# Usage: scripts/build-wasm-threads.sh [extra wasm-pack args]
+
+set -e
+
+if ! command -v wasm-pack >/dev/null; then
+    echo "❌ wasm-pack not found (cargo install wasm-pack)"
+    exit 1
+fi
+
+# std must be rebuilt with atomics, which needs nightly and its source
+rustup component add rust-src --toolchain nightly >/dev/null
+
+export RUSTFLAGS="-C target-feature=+atomics,+bulk-memory,+mutable-globals \
+-C link-arg=--shared-memory -C link-arg=--import-memory -C link-arg=--max-memory=1073741824 \
+-C link-arg=--export=__wasm_init_tls -C link-arg=--export=__tls_size \
+-C link-arg=--export=__tls_align -C link-arg=--export=__tls_base"
+
+echo "🔨 Building parflow-wasm with threads..."
+cd "$(dirname "$0")/../parflow-wasm"
+rustup run nightly wasm-pack build --target no-modules "$@" -- -Z build-std=panic_abort,std
+cp worker.js pkg/
+
+echo "✅ parflow-wasm/pkg is ready; serve it cross-origin isolated"
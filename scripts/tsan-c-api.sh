@@ -0,0 +1,39 @@
+#!/bin/bash
+# Run the C API callback stress test under ThreadSanitizer.
+#
+# Builds parflow-c and the standard library with -Zsanitizer=thread (needs a nightly toolchain
+# with rust-src), links parflow-c/tests/async_callbacks.c against the static library with
+# -fsanitize=thread and runs it. Any reported data race fails the script.
+#
+# Usage: scripts/tsan-c-api.sh [extra cargo args]
+
+set -e
+
+TOOLCHAIN=${TOOLCHAIN:-nightly}
+# rustc emits LLVM's TSAN instrumentation, so link with clang's runtime rather than GCC's
+CC=${CC:-clang}
+TARGET=$(rustc +"${TOOLCHAIN}" -vV | sed -n 's/^host: //p')
+OUT=target/tsan
+
+if ! rustup component list --toolchain "${TOOLCHAIN}" --installed | grep -q "^rust-src"; then
+    echo "📦 Installing rust-src for ${TOOLCHAIN}..."
+    rustup component add rust-src --toolchain "${TOOLCHAIN}"
+fi
+
+# The sanitizer flags apply to dependencies as well; build scripts are unaffected because
+# --target separates host and target artifacts
+export RUSTFLAGS="-Zsanitizer=thread"
+export CC CFLAGS="-fsanitize=thread"
+
+echo "🔨 Building parflow-c with ThreadSanitizer..."
+cargo +"${TOOLCHAIN}" build -Zbuild-std --target "${TARGET}" -p parflow-c "$@"
+
+mkdir -p "${OUT}"
+"${CC}" -fsanitize=thread -g -O1 -Iparflow-c/include \
+    parflow-c/tests/async_callbacks.c \
+    "target/${TARGET}/debug/libparflow_c.a" \
+    -lpthread -ldl -lm -o "${OUT}/async_callbacks"
+
+echo "🧪 Running async callback test..."
+TSAN_OPTIONS="halt_on_error=1 ${TSAN_OPTIONS}" "${OUT}/async_callbacks"
+echo "✅ No data races reported"
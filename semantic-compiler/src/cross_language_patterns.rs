@@ -1,4 +1,5 @@
 use crate::{PatternType, SemanticGraph};
+use serde::{Deserialize, Serialize};
 
 pub struct CrossLanguageAnalyzer;
 
@@ -10,27 +11,40 @@ impl CrossLanguageAnalyzer {
         analysis
     }
 
+    /// A migration for each pattern in `source_graph` that runs better in another language than
+    /// the graph's, with the number of nodes showing it; most nodes first
     pub fn suggest_migration_targets(
         &self,
-        _source_graph: &SemanticGraph,
+        source_graph: &SemanticGraph,
     ) -> Vec<MigrationSuggestion> {
-        // Mock implementation - in real implementation, this would analyze patterns
-        vec![
-            MigrationSuggestion {
-                pattern_type: PatternType::FibonacciLike,
-                current_language: "python".to_string(),
-                suggested_language: "rust".to_string(),
-                node_count: 5,
-                estimated_performance_gain: 10.0,
-            },
-            MigrationSuggestion {
-                pattern_type: PatternType::MapReduce,
-                current_language: "javascript".to_string(),
-                suggested_language: "python".to_string(),
-                node_count: 3,
-                estimated_performance_gain: 2.0,
-            },
-        ]
+        let mut counts: Vec<(PatternType, usize)> = Vec::new();
+        for (pattern, _) in source_graph.pattern_nodes() {
+            let pattern = pattern.detected();
+            match counts.iter_mut().find(|(counted, _)| *counted == pattern) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((pattern, 1)),
+            }
+        }
+        let current = &source_graph.language;
+        let mut suggestions: Vec<MigrationSuggestion> = counts
+            .into_iter()
+            .filter_map(|(pattern, node_count)| {
+                let target = self.get_optimal_language(&pattern)?;
+                (target != *current).then(|| MigrationSuggestion {
+                    pattern_type: pattern,
+                    current_language: current.clone(),
+                    estimated_performance_gain: self
+                        .estimate_performance_gain(&pattern, current, &target),
+                    suggested_language: target,
+                    node_count,
+                })
+            })
+            .collect();
+        suggestions.sort_by(|a, b| {
+            let pattern = |s: &MigrationSuggestion| format!("{:?}", s.pattern_type);
+            b.node_count.cmp(&a.node_count).then_with(|| pattern(a).cmp(&pattern(b)))
+        });
+        suggestions
     }
 
     pub fn get_optimal_language(&self, pattern: &PatternType) -> Option<String> {
@@ -45,7 +59,6 @@ impl CrossLanguageAnalyzer {
         }
     }
 
-    #[allow(dead_code)]
     fn estimate_performance_gain(&self, pattern: &PatternType, from: &str, to: &str) -> f64 {
         // Simple performance estimation
         match (pattern, from, to) {
@@ -146,7 +159,7 @@ pub struct OptimizationSuggestion {
     pub estimated_improvement: f64,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MigrationSuggestion {
     pub pattern_type: PatternType,
     pub current_language: String,
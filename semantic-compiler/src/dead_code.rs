@@ -0,0 +1,404 @@
+//! Dead code and unused exports across languages.
+//!
+//! Like [`crate::source_patterns`] this reads source text: functions are found by their
+//! declaration keyword, and the identifiers in a function's body are its edges in the call graph
+//! of its language. A function is live when an entry point reaches it: `main`, tests, functions
+//! with a decorator or attribute (a framework calls those), trait implementations, and anything
+//! named by top-level code. The rest are dead. An exported function may have users outside the
+//! project, so only private ones that nothing calls any more are safe to delete.
+//!
+//! Calls are matched by name without resolving modules, so functions that share a name keep
+//! each other alive: the analysis misses some dead code rather than report live code.
+
+use crate::ownership::{language_for_path, source_files};
+use crate::source_patterns::declared_function;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::Path;
+
+const LANGUAGES: &[&str] = &["rust", "python", "javascript", "typescript", "go"];
+
+/// Attributes and decorators that do not make a function reachable from outside
+const NOT_ENTRY_POINTS: &[&str] = &[
+    "#[inline",
+    "#[must_use",
+    "#[allow(",
+    "#[deprecated",
+    "#[doc",
+    "#[cfg(",
+    "#[cold",
+    "#[track_caller",
+    "@staticmethod",
+    "@classmethod",
+    "@property",
+    "@functools.cache",
+    "@functools.lru_cache",
+    "@lru_cache",
+    "@cache",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeadFunction {
+    /// Path as given to [`DeadCodeAnalyzer::add_file`]
+    pub file: String,
+    /// 1-based line of the declaration
+    pub line: usize,
+    pub name: String,
+    pub language: String,
+    /// Visible outside its module: `pub`, `export`, or a public Python or Go name
+    pub exported: bool,
+    /// Other dead functions that still call this one
+    pub callers: Vec<String>,
+    /// Private and called by nothing
+    pub safe_delete: bool,
+}
+
+impl DeadFunction {
+    pub fn suggestion(&self) -> String {
+        if self.safe_delete {
+            format!("safe to delete {}", self.name)
+        } else if self.exported {
+            format!(
+                "unused export; check for users outside the project before deleting {}",
+                self.name
+            )
+        } else {
+            format!("delete together with {}", self.callers.join(", "))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeadCodeReport {
+    /// Sorted by file and line
+    pub functions: Vec<DeadFunction>,
+}
+
+struct SourceFile {
+    path: String,
+    language: &'static str,
+    code: String,
+}
+
+struct Definition<'a> {
+    file: usize,
+    name: &'a str,
+    /// 0-based lines, the declaration first
+    start: usize,
+    end: usize,
+    exported: bool,
+    entry_point: bool,
+}
+
+/// How a line outside any function body counts
+enum LineKind {
+    /// Comments and imports
+    Ignored,
+    /// `export { .. }`, `module.exports`, `__all__`, `pub use`: exports the names on it
+    Exports,
+    Code,
+}
+
+#[derive(Default)]
+pub struct DeadCodeAnalyzer {
+    files: Vec<SourceFile>,
+}
+
+impl DeadCodeAnalyzer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every Rust, Python, JavaScript, TypeScript and Go file under `root`, skipping hidden
+    /// directories, `target` and `node_modules`. Paths are relative to `root`.
+    pub fn from_directory(root: &Path) -> Result<Self> {
+        let mut files = Vec::new();
+        source_files(root, &mut files)?;
+        files.sort();
+        let mut analyzer = Self::new();
+        for file in files {
+            // Files that are not UTF-8 are not source code worth reading
+            let Ok(code) = std::fs::read_to_string(&file) else { continue };
+            let relative = file.strip_prefix(root).unwrap_or(&file);
+            analyzer.add_file(&relative.to_string_lossy(), &code);
+        }
+        Ok(analyzer)
+    }
+
+    /// Add a file, `false` when its language is not analyzed.
+    pub fn add_file(&mut self, path: &str, code: &str) -> bool {
+        let Some(language) = analyzed_language(path) else { return false };
+        self.files.push(SourceFile { path: path.to_string(), language, code: code.to_string() });
+        true
+    }
+
+    pub fn analyze(&self) -> DeadCodeReport {
+        let mut functions: Vec<DeadFunction> =
+            LANGUAGES.iter().flat_map(|language| self.analyze_language(language)).collect();
+        functions.sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+        DeadCodeReport { functions }
+    }
+
+    fn analyze_language(&self, language: &str) -> Vec<DeadFunction> {
+        let mut definitions: Vec<Definition> = Vec::new();
+        // Names each definition mentions, in step with `definitions`
+        let mut calls: Vec<HashSet<&str>> = Vec::new();
+        let mut top_level: HashSet<&str> = HashSet::new();
+        let mut exported_names: HashSet<&str> = HashSet::new();
+
+        for (index, file) in self.files.iter().enumerate() {
+            if file.language != language {
+                continue;
+            }
+            let lines: Vec<&str> = file.code.lines().collect();
+            let found = definitions_in(&lines, language, index);
+            let base = definitions.len();
+            calls.extend(found.iter().map(|_| HashSet::new()));
+            for (i, line) in lines.iter().enumerate() {
+                let names = identifiers(line);
+                match line_kind(line.trim_start(), language) {
+                    LineKind::Ignored => {}
+                    LineKind::Exports => exported_names.extend(names),
+                    // Definitions are in source order, so the last one around a line is the
+                    // innermost
+                    LineKind::Code => match found.iter().rposition(|d| d.start <= i && i < d.end) {
+                        Some(owner) => calls[base + owner].extend(names),
+                        None => top_level.extend(names),
+                    },
+                }
+            }
+            definitions.extend(found);
+        }
+
+        let mut by_name: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (i, definition) in definitions.iter().enumerate() {
+            by_name.entry(definition.name).or_default().push(i);
+        }
+        let mut live: Vec<bool> =
+            definitions.iter().map(|d| d.entry_point || top_level.contains(d.name)).collect();
+        let mut queue: Vec<usize> = (0..definitions.len()).filter(|&i| live[i]).collect();
+        while let Some(caller) = queue.pop() {
+            for name in &calls[caller] {
+                for &callee in by_name.get(name).into_iter().flatten() {
+                    if !live[callee] {
+                        live[callee] = true;
+                        queue.push(callee);
+                    }
+                }
+            }
+        }
+
+        definitions
+            .iter()
+            .zip(&live)
+            .filter(|(_, live)| !**live)
+            .map(|(definition, _)| {
+                // A live caller would have made this function live, so every caller is dead.
+                // Declarations mention their own name, so same-named functions are not callers.
+                let callers: BTreeSet<&str> = definitions
+                    .iter()
+                    .zip(&calls)
+                    .filter(|(caller, names)| {
+                        caller.name != definition.name && names.contains(definition.name)
+                    })
+                    .map(|(caller, _)| caller.name)
+                    .collect();
+                let exported = definition.exported || exported_names.contains(definition.name);
+                DeadFunction {
+                    file: self.files[definition.file].path.clone(),
+                    line: definition.start + 1,
+                    name: definition.name.to_string(),
+                    language: language.to_string(),
+                    exported,
+                    safe_delete: !exported && callers.is_empty(),
+                    callers: callers.into_iter().map(str::to_string).collect(),
+                }
+            })
+            .collect()
+    }
+}
+
+fn analyzed_language(path: &str) -> Option<&'static str> {
+    language_for_path(path).filter(|language| LANGUAGES.contains(language))
+}
+
+fn identifiers(line: &str) -> impl Iterator<Item = &str> {
+    line.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|word| word.starts_with(|c: char| c.is_alphabetic() || c == '_'))
+}
+
+fn line_kind(trimmed: &str, language: &str) -> LineKind {
+    let starts = |prefixes: &[&str]| prefixes.iter().any(|p| trimmed.starts_with(p));
+    match language {
+        "python" if starts(&["#", "import ", "from "]) => LineKind::Ignored,
+        "python" if trimmed.starts_with("__all__") => LineKind::Exports,
+        "rust" if starts(&["//", "use "]) => LineKind::Ignored,
+        "rust" if trimmed.starts_with("pub use ") => LineKind::Exports,
+        "go" if starts(&["//", "import ", "package "]) => LineKind::Ignored,
+        "javascript" | "typescript" if starts(&["//", "import "]) => LineKind::Ignored,
+        "javascript" | "typescript"
+            if starts(&["export {", "export default ", "exports."])
+                || trimmed.contains("module.exports") =>
+        {
+            LineKind::Exports
+        }
+        _ => LineKind::Code,
+    }
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// End (exclusive) of the block declared on line `start`: by indentation in Python, by braces
+/// elsewhere
+fn block_end(lines: &[&str], start: usize, language: &str) -> usize {
+    if language == "python" {
+        // The signature may span lines, with `):` back at the declaration's indentation
+        let signature_end =
+            (start..lines.len()).find(|&i| lines[i].trim_end().ends_with(':')).unwrap_or(start);
+        let indent = indentation(lines[start]);
+        return (signature_end + 1..lines.len())
+            .find(|&i| !lines[i].trim().is_empty() && indentation(lines[i]) <= indent)
+            .unwrap_or(lines.len());
+    }
+    let (mut braces, mut brackets) = (0usize, 0usize);
+    let mut opened = false;
+    for (i, line) in lines.iter().enumerate().skip(start) {
+        for c in line.chars() {
+            match c {
+                '{' => {
+                    braces += 1;
+                    opened = true;
+                }
+                '}' => braces = braces.saturating_sub(1),
+                '(' | '[' => brackets += 1,
+                ')' | ']' => brackets = brackets.saturating_sub(1),
+                // A declaration without a body, such as a trait method
+                ';' if !opened && brackets == 0 => return i + 1,
+                _ => {}
+            }
+            if opened && braces == 0 {
+                return i + 1;
+            }
+        }
+    }
+    lines.len()
+}
+
+fn is_entry_point(name: &str, language: &str) -> bool {
+    match language {
+        "python" => {
+            name.starts_with("test")
+                || (name.starts_with("__") && name.ends_with("__"))
+                || matches!(name, "main" | "setUp" | "tearDown" | "setUpClass" | "tearDownClass")
+        }
+        "go" => {
+            matches!(name, "main" | "init")
+                || ["Test", "Benchmark", "Example", "Fuzz"].iter().any(|p| name.starts_with(p))
+        }
+        _ => name == "main",
+    }
+}
+
+/// Whether the attributes or decorators right above line `start` hand the function to a
+/// framework or the test harness
+fn annotated(lines: &[&str], start: usize) -> bool {
+    lines[..start]
+        .iter()
+        .rev()
+        .map(|line| line.trim_start())
+        .take_while(|line| {
+            line.starts_with("#[") || line.starts_with('@') || line.starts_with("///")
+        })
+        .filter(|line| !line.starts_with("///"))
+        .any(|line| !NOT_ENTRY_POINTS.iter().any(|marker| line.starts_with(marker)))
+}
+
+fn definitions_in<'a>(lines: &[&'a str], language: &str, file: usize) -> Vec<Definition<'a>> {
+    // Methods of `impl Trait for Type` are called through the trait
+    let trait_impls: Vec<(usize, usize)> = if language == "rust" {
+        (0..lines.len())
+            .filter(|&i| {
+                let trimmed = lines[i].trim_start();
+                trimmed.starts_with("impl") && trimmed.contains(" for ")
+            })
+            .map(|i| (i, block_end(lines, i, language)))
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    (0..lines.len())
+        .filter_map(|i| {
+            let name = declared_function(lines[i], language)?;
+            let trimmed = lines[i].trim_start();
+            let exported = match language {
+                "rust" => trimmed.starts_with("pub "),
+                "python" => !name.starts_with('_'),
+                "go" => name.starts_with(|c: char| c.is_uppercase()),
+                _ => trimmed.starts_with("export "),
+            };
+            let entry_point = is_entry_point(name, language)
+                || annotated(lines, i)
+                || trait_impls.iter().any(|&(start, end)| start < i && i < end);
+            Some(Definition {
+                file,
+                name,
+                start: i,
+                end: block_end(lines, i, language),
+                exported,
+                entry_point,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finds_dead_functions_per_language() {
+        let mut analyzer = DeadCodeAnalyzer::new();
+        analyzer.add_file(
+            "app/main.py",
+            "from app.util import _helper\n\ndef main():\n    run()\n\ndef run():\n    return _helper()\n\ndef _orphan():\n    return _only_from_orphan()\n\ndef _only_from_orphan():\n    return 2\n\nif __name__ == \"__main__\":\n    main()\n",
+        );
+        analyzer.add_file(
+            "app/util.py",
+            "def _helper():\n    return 1\n\ndef public_unused(\n    x,\n):\n    return x\n",
+        );
+        analyzer.add_file(
+            "src/lib.rs",
+            "use std::fmt;\n\npub fn api() -> u32 {\n    internal()\n}\n\nfn internal() -> u32 { 1 }\n\n#[inline]\nfn unused() {}\n\nimpl Default for Thing {\n    fn default() -> Self { Thing }\n}\n\n#[test]\nfn checks() {\n    assert_eq!(api(), 1);\n}\n",
+        );
+        analyzer.add_file(
+            "web/index.js",
+            "export function used() { return helper(); }\nfunction helper() { return 1; }\nfunction stale() { return 2; }\nexport function legacy() { return 3; }\nused();\n",
+        );
+        assert!(!analyzer.add_file("README.md", "# docs"));
+
+        let report = analyzer.analyze();
+        let dead: Vec<(&str, usize, &str, bool)> = report
+            .functions
+            .iter()
+            .map(|f| (f.file.as_str(), f.line, f.name.as_str(), f.safe_delete))
+            .collect();
+        assert_eq!(
+            dead,
+            [
+                ("app/main.py", 9, "_orphan", true),
+                ("app/main.py", 12, "_only_from_orphan", false),
+                ("app/util.py", 4, "public_unused", false),
+                ("src/lib.rs", 10, "unused", true),
+                ("web/index.js", 3, "stale", true),
+                ("web/index.js", 4, "legacy", false),
+            ]
+        );
+        assert_eq!(report.functions[1].suggestion(), "delete together with _orphan");
+        assert!(report.functions[2].exported);
+        assert!(report.functions[5].suggestion().starts_with("unused export"));
+    }
+}
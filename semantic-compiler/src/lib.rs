@@ -1,12 +1,24 @@
 use serde::{Deserialize, Serialize};
 
 pub mod cross_language_patterns;
+pub mod dead_code;
+pub mod lints;
+pub mod module_graph;
+pub mod ownership;
 pub mod pattern_recognizer;
+pub mod profiling;
 pub mod semantic_graph;
+pub mod source_patterns;
 
 pub use cross_language_patterns::{CrossLanguageAnalyzer, MigrationSuggestion, ProjectAnalysis};
+pub use dead_code::{DeadCodeAnalyzer, DeadCodeReport, DeadFunction};
+pub use lints::{Diagnostic, LintRule, Linter, Severity};
+pub use module_graph::ModuleGraph;
+pub use ownership::{OwnershipMap, OwnershipOptions};
 pub use pattern_recognizer::PatternRecognizer;
+pub use profiling::{Profile, Profiler, RankedMigration};
 pub use semantic_graph::{NodeType, SemanticGraph, SemanticNode};
+pub use source_patterns::{detect_patterns, PatternMatch, PatternScan};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PatternType {
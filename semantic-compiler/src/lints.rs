@@ -0,0 +1,273 @@
+//! Lint rules over the [`SemanticGraph`].
+//!
+//! A rule looks at one function node of a graph built by [`SemanticGraph::from_source`], with
+//! the call nodes under it, and reports [`Diagnostic`]s carrying a severity and, where there is
+//! an obvious one, a fix. [`Linter::default`] runs the builtin rules; organizations add their
+//! own with [`Linter::with_rule`].
+
+use crate::ownership::{language_for_path, source_files};
+use crate::semantic_graph::{NodeType, SemanticGraph, SemanticNode};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub rule: String,
+    pub severity: Severity,
+    pub message: String,
+    pub path: String,
+    /// 1-based line the diagnostic points at
+    pub line: usize,
+    pub function: Option<String>,
+    /// How to fix it, when there is an obvious way
+    pub fix: Option<String>,
+}
+
+pub trait LintRule: Send + Sync {
+    /// Kebab-case name, e.g. `sync-io-in-async`
+    fn name(&self) -> &'static str;
+
+    fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    /// Diagnostics for one [`NodeType::Function`] node of `graph`
+    fn check(&self, graph: &SemanticGraph, function: &SemanticNode) -> Vec<Diagnostic>;
+}
+
+fn metadata<'a>(node: &'a SemanticNode, key: &str) -> &'a str {
+    node.metadata.get(key).map(String::as_str).unwrap_or_default()
+}
+
+fn line(node: &SemanticNode) -> usize {
+    metadata(node, "line").parse().unwrap_or(0)
+}
+
+/// A diagnostic from `rule` about `node`, inside `function`
+pub fn diagnostic(
+    rule: &dyn LintRule,
+    function: &SemanticNode,
+    node: &SemanticNode,
+    message: String,
+    fix: Option<&str>,
+) -> Diagnostic {
+    Diagnostic {
+        rule: rule.name().to_string(),
+        severity: rule.severity(),
+        message,
+        path: metadata(function, "path").to_string(),
+        line: line(node),
+        function: Some(metadata(function, "name").to_string()),
+        fix: fix.map(str::to_string),
+    }
+}
+
+/// A function that calls itself and keeps no cache of its results, which makes overlapping
+/// subproblems exponential
+pub struct RecursionWithoutMemoization;
+
+/// How a method calls itself; `requests.get` inside `get` is not recursion
+const SELF_RECEIVERS: &[&str] = &["self.", "this.", "cls.", "Self::"];
+
+impl LintRule for RecursionWithoutMemoization {
+    fn name(&self) -> &'static str {
+        "recursive-without-memoization"
+    }
+
+    fn check(&self, graph: &SemanticGraph, function: &SemanticNode) -> Vec<Diagnostic> {
+        let name = metadata(function, "name");
+        let is_self = |callee: &str| {
+            let receiver = callee.strip_suffix(name);
+            callee == name || receiver.is_some_and(|r| SELF_RECEIVERS.contains(&r))
+        };
+        let calls: Vec<&SemanticNode> =
+            graph.children(function).filter(|node| node.node_type == NodeType::Call).collect();
+        let Some(first) = calls.iter().find(|call| is_self(metadata(call, "callee"))) else {
+            return Vec::new();
+        };
+        let mentions_cache = |text: &str| {
+            let text = text.to_lowercase();
+            text.contains("cache") || text.contains("memo")
+        };
+        if mentions_cache(metadata(function, "decorators"))
+            || calls.iter().any(|call| mentions_cache(metadata(call, "text")))
+        {
+            return Vec::new();
+        }
+        let fix = match graph.language.as_str() {
+            "python" => "decorate it with @functools.lru_cache(maxsize=None)",
+            "rust" => "keep results in a HashMap keyed by the arguments, or make it iterative",
+            _ => "keep results in a Map keyed by the arguments, or make it iterative",
+        };
+        let message = format!("{} calls itself without caching its results", name);
+        vec![diagnostic(self, function, first, message, Some(fix))]
+    }
+}
+
+/// Blocking file, network or process calls inside an async function, which stall every other
+/// task on the event loop
+pub struct SyncIoInAsync;
+
+const BLOCKING_PYTHON: &[&str] = &[
+    "open",
+    "input",
+    "time.sleep",
+    "requests.get",
+    "requests.post",
+    "requests.put",
+    "requests.delete",
+    "requests.request",
+    "subprocess.run",
+    "subprocess.check_output",
+    "urllib.request.urlopen",
+    "urlopen",
+];
+
+const BLOCKING_RUST: &[&str] = &[
+    "std::fs::",
+    "fs::",
+    "File::open",
+    "File::create",
+    "std::thread::sleep",
+    "thread::sleep",
+    "reqwest::blocking::",
+    "std::process::Command::new",
+    "Command::new",
+];
+
+fn blocking(language: &str, callee: &str) -> bool {
+    match language {
+        "python" => BLOCKING_PYTHON.contains(&callee),
+        "rust" => BLOCKING_RUST.iter().any(|prefix| callee.starts_with(prefix)),
+        "javascript" | "typescript" => callee.ends_with("Sync"),
+        _ => false,
+    }
+}
+
+impl LintRule for SyncIoInAsync {
+    fn name(&self) -> &'static str {
+        "sync-io-in-async"
+    }
+
+    fn check(&self, graph: &SemanticGraph, function: &SemanticNode) -> Vec<Diagnostic> {
+        if metadata(function, "async") != "true" {
+            return Vec::new();
+        }
+        let fix = match graph.language.as_str() {
+            "python" => "await asyncio.to_thread(...), or use an async library such as aiofiles",
+            "rust" => "use the tokio::fs or tokio::time equivalent, or tokio::task::spawn_blocking",
+            _ => "use the promise-based API, e.g. fs.promises or util.promisify(exec)",
+        };
+        graph
+            .children(function)
+            .filter(|node| node.node_type == NodeType::Call && metadata(node, "awaited") != "true")
+            .filter(|call| blocking(&graph.language, metadata(call, "callee")))
+            .map(|call| {
+                let message = format!(
+                    "{} blocks inside async function {}",
+                    metadata(call, "callee"),
+                    metadata(function, "name")
+                );
+                diagnostic(self, function, call, message, Some(fix))
+            })
+            .collect()
+    }
+}
+
+pub struct Linter {
+    rules: Vec<Box<dyn LintRule>>,
+}
+
+impl Default for Linter {
+    /// The builtin rules
+    fn default() -> Self {
+        Self::new().with_rule(RecursionWithoutMemoization).with_rule(SyncIoInAsync)
+    }
+}
+
+impl Linter {
+    /// A linter without rules
+    pub fn new() -> Self {
+        Self { rules: Vec::new() }
+    }
+
+    pub fn with_rule(mut self, rule: impl LintRule + 'static) -> Self {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    pub fn rules(&self) -> impl Iterator<Item = &dyn LintRule> {
+        self.rules.iter().map(|rule| rule.as_ref())
+    }
+
+    /// Every rule over every function of `graph`, by line
+    pub fn lint(&self, graph: &SemanticGraph) -> Vec<Diagnostic> {
+        let mut diagnostics: Vec<Diagnostic> = graph
+            .roots()
+            .filter(|node| node.node_type == NodeType::Function)
+            .flat_map(|function| self.rules.iter().flat_map(|rule| rule.check(graph, function)))
+            .collect();
+        diagnostics.sort_by(|a, b| (&a.path, a.line, &a.rule).cmp(&(&b.path, b.line, &b.rule)));
+        diagnostics
+    }
+
+    /// Lint one file; files in a language nobody registered have no diagnostics
+    pub fn lint_source(&self, code: &str, path: &str) -> Vec<Diagnostic> {
+        match language_for_path(path) {
+            Some(language) => self.lint(&SemanticGraph::from_source(code, language, path)),
+            None => Vec::new(),
+        }
+    }
+
+    /// Lint every source file under `root`, with paths relative to it
+    pub fn lint_directory(&self, root: &Path) -> Result<Vec<Diagnostic>> {
+        let mut files = Vec::new();
+        source_files(root, &mut files)?;
+        files.sort();
+        let mut diagnostics = Vec::new();
+        for file in files {
+            let Ok(code) = std::fs::read_to_string(&file) else { continue };
+            let relative = file.strip_prefix(root).unwrap_or(&file);
+            diagnostics.extend(self.lint_source(&code, &relative.to_string_lossy()));
+        }
+        Ok(diagnostics)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_rules_flag_recursion_and_blocking_io() {
+        let python = "import functools\n\ndef fib(n):\n    if n < 2:\n        return n\n    return fib(n - 1) + fib(n - 2)\n\n@functools.lru_cache\ndef cached(n):\n    return n if n < 2 else cached(n - 1) + cached(n - 2)\n\nasync def load(path):\n    with open(path) as f:\n        data = f.read()\n    await asyncio.sleep(0)\n    return data\n\ndef get(url):\n    return requests.get(url)\n";
+        let diagnostics = Linter::default().lint_source(python, "app.py");
+        let found: Vec<_> =
+            diagnostics.iter().map(|d| (d.rule.as_str(), d.line, d.function.as_deref())).collect();
+        assert_eq!(
+            found,
+            [
+                ("recursive-without-memoization", 6, Some("fib")),
+                ("sync-io-in-async", 13, Some("load")),
+            ]
+        );
+        assert_eq!(diagnostics[1].severity, Severity::Warning);
+        assert!(diagnostics[0].fix.as_deref().unwrap().contains("lru_cache"));
+
+        let rust = "async fn read(path: &str) -> String {\n    let a = tokio::fs::read_to_string(path).await.unwrap();\n    let b = std::fs::read_to_string(path).unwrap();\n    a + &b\n}\n";
+        let diagnostics = Linter::default().lint_source(rust, "src/io.rs");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, 3);
+        assert!(diagnostics[0].message.starts_with("std::fs::read_to_string blocks"));
+        assert!(Linter::new().lint_source(python, "app.py").is_empty());
+    }
+}
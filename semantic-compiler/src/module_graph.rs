@@ -0,0 +1,256 @@
+//! Intra-crate module dependency graphs.
+//!
+//! Parses a crate from its root file (`lib.rs` or `main.rs`), following `mod` declarations to
+//! the files they live in, and records which modules each module refers to through `use`
+//! declarations and paths. Modules are file-level: inline `mod` blocks belong to the file they
+//! are written in, though paths inside them are resolved against their own position in the
+//! tree. References that resolve to nothing in the crate (external crates, prelude items) are
+//! ignored.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use syn::visit::{self, Visit};
+
+/// Path of the crate root module
+pub const ROOT: &str = "crate";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModuleNode {
+    /// `crate`, `crate::parser`, `crate::parser::lexer`, ...
+    pub path: String,
+    pub file: PathBuf,
+    pub lines: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ModuleGraph {
+    pub modules: BTreeMap<String, ModuleNode>,
+    /// Modules each module refers to, without self references
+    pub dependencies: BTreeMap<String, BTreeSet<String>>,
+}
+
+/// Paths one module mentions, before they can be resolved against the whole tree
+struct References {
+    /// The file-level module the references are attributed to
+    node: String,
+    /// The (possibly inline) module they are written in
+    scope: String,
+    paths: Vec<Vec<String>>,
+}
+
+#[derive(Default)]
+struct Parsed {
+    graph: ModuleGraph,
+    /// Every module, inline ones included, mapped to the file-level module holding it
+    scopes: BTreeMap<String, String>,
+    references: Vec<References>,
+}
+
+impl ModuleGraph {
+    /// Build the graph of the crate whose root file is `root`.
+    pub fn from_crate_root(root: &Path) -> Result<Self> {
+        let mut parsed = Parsed::default();
+        parse_file(root, ROOT, true, &mut parsed)?;
+
+        let Parsed { mut graph, scopes, references } = parsed;
+        for module in graph.modules.keys() {
+            graph.dependencies.insert(module.clone(), BTreeSet::new());
+        }
+        for references in references {
+            for path in references.paths {
+                let Some(target) = resolve(&references.scope, &path, &scopes) else { continue };
+                if target != references.node {
+                    graph.dependencies.entry(references.node.clone()).or_default().insert(target);
+                }
+            }
+        }
+        Ok(graph)
+    }
+
+    pub fn total_lines(&self) -> usize {
+        self.modules.values().map(|module| module.lines).sum()
+    }
+}
+
+fn parse_file(file: &Path, module: &str, owns_directory: bool, parsed: &mut Parsed) -> Result<()> {
+    let source =
+        std::fs::read_to_string(file).with_context(|| format!("reading {}", file.display()))?;
+    let syntax = syn::parse_file(&source).with_context(|| format!("parsing {}", file.display()))?;
+    parsed.graph.modules.insert(
+        module.to_string(),
+        ModuleNode {
+            path: module.to_string(),
+            file: file.to_path_buf(),
+            lines: source.lines().count(),
+        },
+    );
+    // Submodules of lib.rs, main.rs and mod.rs live next to them, those of foo.rs in foo/
+    let directory = match (owns_directory, file.parent()) {
+        (true, Some(parent)) => parent.to_path_buf(),
+        (false, Some(parent)) => parent.join(file.file_stem().unwrap_or_default()),
+        (_, None) => PathBuf::new(),
+    };
+    parse_items(&syntax.items, module, module, &directory, parsed)
+}
+
+fn parse_items(
+    items: &[syn::Item],
+    node: &str,
+    scope: &str,
+    directory: &Path,
+    parsed: &mut Parsed,
+) -> Result<()> {
+    parsed.scopes.insert(scope.to_string(), node.to_string());
+    let mut collector = PathCollector::default();
+    for item in items {
+        let syn::Item::Mod(declaration) = item else {
+            collector.visit_item(item);
+            continue;
+        };
+        let child = format!("{}::{}", scope, declaration.ident);
+        let name = declaration.ident.to_string();
+        match &declaration.content {
+            Some((_, inline)) => parse_items(inline, node, &child, &directory.join(&name), parsed)?,
+            None => {
+                let flat = directory.join(format!("{}.rs", name));
+                let nested = directory.join(&name).join("mod.rs");
+                if flat.is_file() {
+                    parse_file(&flat, &child, false, parsed)?;
+                } else if nested.is_file() {
+                    parse_file(&nested, &child, true, parsed)?;
+                }
+                // Modules generated at build time or behind #[path] are left out
+            }
+        }
+    }
+    parsed.references.push(References {
+        node: node.to_string(),
+        scope: scope.to_string(),
+        paths: collector.paths,
+    });
+    Ok(())
+}
+
+/// The file-level module `path`, written in module `scope`, refers to
+fn resolve(scope: &str, path: &[String], scopes: &BTreeMap<String, String>) -> Option<String> {
+    let mut absolute: Vec<&str> = scope.split("::").collect();
+    let mut segments = path.iter().map(String::as_str).peekable();
+    match segments.peek().copied() {
+        Some("crate") => {
+            absolute.truncate(1);
+            segments.next();
+        }
+        Some("self") => {
+            segments.next();
+        }
+        Some("super") => {
+            while segments.peek() == Some(&"super") {
+                segments.next();
+                if absolute.len() > 1 {
+                    absolute.pop();
+                }
+            }
+        }
+        // A relative path only stays in the crate when it starts at a child module
+        Some(first) if scopes.contains_key(&format!("{}::{}", scope, first)) => {}
+        _ => return None,
+    }
+    absolute.extend(segments);
+    // The longest prefix naming a module; what follows are items inside it
+    (1..=absolute.len())
+        .rev()
+        .find_map(|length| scopes.get(&absolute[..length].join("::")))
+        .cloned()
+}
+
+#[derive(Default)]
+struct PathCollector {
+    paths: Vec<Vec<String>>,
+}
+
+impl PathCollector {
+    fn use_tree(&mut self, prefix: &mut Vec<String>, tree: &syn::UseTree) {
+        match tree {
+            syn::UseTree::Path(path) => {
+                prefix.push(path.ident.to_string());
+                self.use_tree(prefix, &path.tree);
+                prefix.pop();
+            }
+            syn::UseTree::Name(name) => {
+                self.paths.push([prefix.as_slice(), &[name.ident.to_string()]].concat())
+            }
+            syn::UseTree::Rename(rename) => {
+                self.paths.push([prefix.as_slice(), &[rename.ident.to_string()]].concat())
+            }
+            syn::UseTree::Glob(_) => self.paths.push(prefix.clone()),
+            syn::UseTree::Group(group) => {
+                for tree in &group.items {
+                    self.use_tree(prefix, tree);
+                }
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for PathCollector {
+    fn visit_item_use(&mut self, item: &'ast syn::ItemUse) {
+        self.use_tree(&mut Vec::new(), &item.tree);
+    }
+
+    fn visit_item_mod(&mut self, _: &'ast syn::ItemMod) {
+        // Nested inline modules are walked with their own scope
+    }
+
+    fn visit_path(&mut self, path: &'ast syn::Path) {
+        if path.segments.len() > 1 {
+            self.paths
+                .push(path.segments.iter().map(|segment| segment.ident.to_string()).collect());
+        }
+        visit::visit_path(self, path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolves_module_references_across_files() {
+        let dir = std::env::temp_dir().join(format!("parflow-modules-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("parser")).unwrap();
+        std::fs::write(
+            dir.join("lib.rs"),
+            "pub mod parser;\npub mod eval;\nmod util { pub fn trim() {} }\npub use eval::run;\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("parser.rs"), "mod lexer;\nuse crate::util::trim;\n").unwrap();
+        std::fs::write(
+            dir.join("parser/lexer.rs"),
+            "pub fn lex() { super::super::util::trim(); std::mem::drop(1); }\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("eval.rs"),
+            "use crate::parser::{self, lexer};\npub fn run() { helpers::go() }\nmod helpers { pub fn go() {} }\n",
+        )
+        .unwrap();
+
+        let graph = ModuleGraph::from_crate_root(&dir.join("lib.rs")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let modules: Vec<&str> = graph.modules.keys().map(String::as_str).collect();
+        assert_eq!(modules, ["crate", "crate::eval", "crate::parser", "crate::parser::lexer"]);
+        let dependencies = |module: &str| -> Vec<&str> {
+            graph.dependencies[module].iter().map(String::as_str).collect()
+        };
+        // Declaring a module is not a reference to it
+        assert_eq!(dependencies("crate"), ["crate::eval"]);
+        // `util` is inline in lib.rs, so it counts as the crate root
+        assert_eq!(dependencies("crate::parser"), ["crate"]);
+        assert_eq!(dependencies("crate::parser::lexer"), ["crate"]);
+        assert_eq!(dependencies("crate::eval"), ["crate::parser", "crate::parser::lexer"]);
+        assert_eq!(graph.total_lines(), 10);
+    }
+}
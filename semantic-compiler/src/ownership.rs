@@ -0,0 +1,493 @@
+//! Code ownership and expertise mapping.
+//!
+//! Combines `git blame` with the semantic graph to attribute modules (source directories, per
+//! language) and detected patterns to contributors. Graph nodes are tied to source through the
+//! `file`, `line_start` and `line_end` metadata keys, which the graphs of [`pattern_graphs`]
+//! carry. The resulting map drives reviewer suggestions for migrations and the bus-factor
+//! report.
+
+use crate::semantic_graph::{self, NodeType, SemanticNode};
+use crate::{MigrationSuggestion, SemanticGraph};
+use anyhow::{bail, Context, Result};
+use parflow_core::languages;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(Debug, Clone)]
+pub struct OwnershipOptions {
+    /// Contributors with no commits for this many days are treated as having left
+    pub departed_after_days: u64,
+    /// Owners below this share of a component are not considered for reviews
+    pub min_share: f64,
+}
+
+impl Default for OwnershipOptions {
+    fn default() -> Self {
+        Self { departed_after_days: 180, min_share: 0.1 }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Contributor {
+    pub email: String,
+    pub name: String,
+    pub last_active: i64,
+    pub departed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OwnerShare {
+    pub email: String,
+    pub lines: usize,
+    pub share: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComponentOwnership {
+    /// Module directory, or the pattern name for pattern ownership
+    pub component: String,
+    pub language: String,
+    pub total_lines: usize,
+    /// Sorted by descending share
+    pub owners: Vec<OwnerShare>,
+    /// Smallest number of contributors holding more than half of the lines
+    pub bus_factor: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum OwnershipRisk {
+    /// Everyone holding the majority of the component has left
+    Orphaned,
+    /// A single active contributor holds the majority of the component
+    SingleOwner,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BusFactorFinding {
+    pub component: String,
+    pub language: String,
+    pub risk: OwnershipRisk,
+    pub primary_owners: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigrationReviewers {
+    pub pattern: String,
+    pub current_language: String,
+    pub suggested_language: String,
+    /// People who know the code being migrated
+    pub source_owners: Vec<String>,
+    /// People with the most code in the target language
+    pub target_experts: Vec<String>,
+    /// The code being migrated has no active owner
+    pub orphaned: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct OwnershipMap {
+    pub contributors: BTreeMap<String, Contributor>,
+    pub modules: Vec<ComponentOwnership>,
+    pub patterns: Vec<ComponentOwnership>,
+}
+
+/// Language of a file by its extension, as the registered [`languages`] plugins know it
+pub fn language_for_path(path: &str) -> Option<&'static str> {
+    languages::for_path(path).map(|plugin| plugin.name())
+}
+
+/// Files under `dir` in a language [`language_for_path`] knows, skipping hidden directories,
+/// `target` and `node_modules`.
+pub(crate) fn source_files(dir: &Path, out: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = std::fs::read_dir(dir).with_context(|| format!("reading {}", dir.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        if path.is_dir() {
+            if !name.starts_with('.') && name != "target" && name != "node_modules" {
+                source_files(&path, out)?;
+            }
+        } else if language_for_path(&name).is_some() {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// One graph per language of the patterns [`detect_patterns`](crate::detect_patterns) finds in
+/// the source files under `dir`, with `file` relative to `dir`
+pub fn pattern_graphs(dir: &Path) -> Result<Vec<SemanticGraph>> {
+    let mut files = Vec::new();
+    source_files(dir, &mut files)?;
+    files.sort();
+    let mut graphs: BTreeMap<&str, SemanticGraph> = BTreeMap::new();
+    for path in files {
+        let relative = path.strip_prefix(dir).unwrap_or(&path);
+        let file: Vec<_> = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+        let file = file.join("/");
+        let Some(language) = language_for_path(&file) else { continue };
+        let Ok(code) = std::fs::read_to_string(&path) else { continue };
+        let graph = graphs.entry(language).or_insert_with(|| SemanticGraph::new(language));
+        add_patterns(graph, &code, &file);
+    }
+    Ok(graphs.into_values().collect())
+}
+
+/// Add a [`NodeType::Pattern`] root to `graph` for each pattern in `code`, the content of
+/// `file`, spanning the function the pattern is in
+pub fn add_patterns(graph: &mut SemanticGraph, code: &str, file: &str) {
+    let lines: Vec<&str> = code.lines().collect();
+    let functions = crate::source_patterns::functions(&lines, &graph.language);
+    for found in crate::detect_patterns(code, &graph.language) {
+        let Some(pattern) = semantic_graph::PatternType::from_detected(found.pattern) else {
+            continue;
+        };
+        let (start, end) = functions
+            .iter()
+            .find(|(start, end, _)| *start < found.line && found.line <= *end)
+            .map_or((found.line, found.line), |&(start, end, _)| (start + 1, end));
+        let id = graph.nodes.len() as u64 + 1;
+        graph.add_node(SemanticNode {
+            id,
+            node_type: NodeType::Pattern(pattern),
+            children: Vec::new(),
+            metadata: HashMap::from([
+                ("file".to_string(), file.to_string()),
+                ("line_start".to_string(), start.to_string()),
+                ("line_end".to_string(), end.to_string()),
+            ]),
+            language: graph.language.clone(),
+            pattern_hash: 0,
+        });
+        graph.root_nodes.push(id);
+    }
+}
+
+fn git(repo: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git").arg("-C").arg(repo).args(args).output()?;
+    if !output.status.success() {
+        bail!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Author email of every line of `file`, in order.
+fn blame(repo: &Path, file: &str) -> Result<Vec<String>> {
+    let porcelain = git(repo, &["blame", "--line-porcelain", "-w", "HEAD", "--", file])?;
+    let mut authors = Vec::new();
+    let mut current = String::new();
+    for line in porcelain.lines() {
+        if let Some(mail) = line.strip_prefix("author-mail ") {
+            current = mail.trim_matches(|c| c == '<' || c == '>').to_string();
+        } else if line.starts_with('\t') {
+            authors.push(current.clone());
+        }
+    }
+    Ok(authors)
+}
+
+fn summarize(
+    component: String,
+    language: String,
+    counts: HashMap<String, usize>,
+) -> ComponentOwnership {
+    let total_lines: usize = counts.values().sum();
+    let mut owners: Vec<OwnerShare> = counts
+        .into_iter()
+        .map(|(email, lines)| OwnerShare {
+            email,
+            lines,
+            share: lines as f64 / total_lines.max(1) as f64,
+        })
+        .collect();
+    owners.sort_by(|a, b| b.lines.cmp(&a.lines).then_with(|| a.email.cmp(&b.email)));
+
+    let mut covered = 0.0;
+    let bus_factor = owners
+        .iter()
+        .take_while(|o| {
+            let before = covered;
+            covered += o.share;
+            before <= 0.5
+        })
+        .count();
+
+    ComponentOwnership { component, language, total_lines, owners, bus_factor }
+}
+
+impl OwnershipMap {
+    /// Build the map for the repository at `repo`, attributing pattern nodes from `graphs`.
+    pub fn from_git(
+        repo: &Path,
+        graphs: &[SemanticGraph],
+        options: &OwnershipOptions,
+    ) -> Result<Self> {
+        let mut contributors = BTreeMap::new();
+        for line in git(repo, &["log", "--format=%ae%x09%an%x09%at"])?.lines() {
+            let mut fields = line.splitn(3, '\t');
+            let (Some(email), Some(name), Some(time)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            let time: i64 = time.parse().unwrap_or(0);
+            let entry = contributors.entry(email.to_string()).or_insert_with(|| Contributor {
+                email: email.to_string(),
+                name: name.to_string(),
+                last_active: time,
+                departed: false,
+            });
+            entry.last_active = entry.last_active.max(time);
+        }
+
+        let mut files = HashMap::new();
+        for file in git(repo, &["ls-files"])?.lines() {
+            if language_for_path(file).is_some() {
+                let authors = blame(repo, file).with_context(|| format!("blaming {}", file))?;
+                files.insert(file.to_string(), authors);
+            }
+        }
+
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+        Ok(Self::from_line_authors(contributors, &files, graphs, options, now))
+    }
+
+    /// Build the map from per-file line authors (`files[path][line - 1]` is the author email).
+    pub fn from_line_authors(
+        mut contributors: BTreeMap<String, Contributor>,
+        files: &HashMap<String, Vec<String>>,
+        graphs: &[SemanticGraph],
+        options: &OwnershipOptions,
+        now: i64,
+    ) -> Self {
+        let cutoff = now - (options.departed_after_days * 86_400) as i64;
+        for contributor in contributors.values_mut() {
+            contributor.departed = contributor.last_active < cutoff;
+        }
+
+        let mut modules: BTreeMap<(String, String), HashMap<String, usize>> = BTreeMap::new();
+        for (path, authors) in files {
+            let Some(language) = language_for_path(path) else { continue };
+            let module = Path::new(path)
+                .parent()
+                .map(|p| p.to_string_lossy().into_owned())
+                .filter(|p| !p.is_empty())
+                .unwrap_or_else(|| ".".to_string());
+            let counts = modules.entry((module, language.to_string())).or_default();
+            for author in authors {
+                *counts.entry(author.clone()).or_default() += 1;
+            }
+        }
+
+        let mut patterns: BTreeMap<(String, String), HashMap<String, usize>> = BTreeMap::new();
+        for graph in graphs {
+            for (pattern, node) in graph.pattern_nodes() {
+                let Some(authors) = node.metadata.get("file").and_then(|f| files.get(f)) else {
+                    continue;
+                };
+                let line = |key: &str| node.metadata.get(key).and_then(|v| v.parse::<usize>().ok());
+                let start = line("line_start").unwrap_or(1).max(1);
+                let end = line("line_end").unwrap_or(authors.len()).min(authors.len());
+                let counts =
+                    patterns.entry((format!("{:?}", pattern), graph.language.clone())).or_default();
+                for author in authors.iter().take(end).skip(start - 1) {
+                    *counts.entry(author.clone()).or_default() += 1;
+                }
+            }
+        }
+
+        let collect = |groups: BTreeMap<(String, String), HashMap<String, usize>>| {
+            groups
+                .into_iter()
+                .map(|((component, language), counts)| summarize(component, language, counts))
+                .collect()
+        };
+
+        Self { contributors, modules: collect(modules), patterns: collect(patterns) }
+    }
+
+    fn is_departed(&self, email: &str) -> bool {
+        self.contributors.get(email).is_some_and(|c| c.departed)
+    }
+
+    fn primary_owners<'a>(&self, component: &'a ComponentOwnership) -> &'a [OwnerShare] {
+        &component.owners[..component.bus_factor]
+    }
+
+    /// Components whose knowledge is concentrated in one person or has left with its owners.
+    pub fn bus_factor_report(&self) -> Vec<BusFactorFinding> {
+        let mut findings = Vec::new();
+        for component in self.modules.iter().chain(&self.patterns) {
+            let primary = self.primary_owners(component);
+            let risk = if !primary.is_empty() && primary.iter().all(|o| self.is_departed(&o.email))
+            {
+                OwnershipRisk::Orphaned
+            } else if component.bus_factor == 1 {
+                OwnershipRisk::SingleOwner
+            } else {
+                continue;
+            };
+            findings.push(BusFactorFinding {
+                component: component.component.clone(),
+                language: component.language.clone(),
+                risk,
+                primary_owners: primary.iter().map(|o| o.email.clone()).collect(),
+            });
+        }
+        findings.sort_by_key(|f| !matches!(f.risk, OwnershipRisk::Orphaned));
+        findings
+    }
+
+    /// Active contributors ranked by lines across `components`.
+    fn ranked_active<'a>(
+        &self,
+        components: impl Iterator<Item = &'a ComponentOwnership>,
+        min_share: f64,
+        max: usize,
+    ) -> Vec<String> {
+        let mut totals: HashMap<&str, usize> = HashMap::new();
+        for component in components {
+            for owner in component.owners.iter().filter(|o| o.share >= min_share) {
+                if !self.is_departed(&owner.email) {
+                    *totals.entry(&owner.email).or_default() += owner.lines;
+                }
+            }
+        }
+        let mut ranked: Vec<(&str, usize)> = totals.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        ranked.into_iter().take(max).map(|(email, _)| email.to_string()).collect()
+    }
+
+    /// Suggest reviewers for a migration: owners of the pattern in the current language (or of
+    /// that language as a whole when the pattern was not located), plus target-language experts.
+    pub fn suggest_reviewers(
+        &self,
+        suggestion: &MigrationSuggestion,
+        options: &OwnershipOptions,
+        max: usize,
+    ) -> MigrationReviewers {
+        let pattern = format!("{:?}", suggestion.pattern_type);
+        let current = &suggestion.current_language;
+
+        let mut source: Vec<&ComponentOwnership> = self
+            .patterns
+            .iter()
+            .filter(|p| p.component == pattern && &p.language == current)
+            .collect();
+        if source.is_empty() {
+            source = self.modules.iter().filter(|m| &m.language == current).collect();
+        }
+
+        let orphaned = !source.is_empty()
+            && source.iter().all(|component| {
+                self.primary_owners(component).iter().all(|o| self.is_departed(&o.email))
+            });
+
+        MigrationReviewers {
+            pattern,
+            current_language: current.clone(),
+            suggested_language: suggestion.suggested_language.clone(),
+            source_owners: self.ranked_active(source.into_iter(), options.min_share, max),
+            target_experts: self.ranked_active(
+                self.modules.iter().filter(|m| m.language == suggestion.suggested_language),
+                options.min_share,
+                max,
+            ),
+            orphaned,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PatternType;
+
+    fn contributor(email: &str, last_active: i64) -> (String, Contributor) {
+        let c = Contributor {
+            email: email.to_string(),
+            name: email.to_string(),
+            last_active,
+            departed: false,
+        };
+        (email.to_string(), c)
+    }
+
+    #[test]
+    fn test_bus_factor_and_reviewers() {
+        let now = 1_000 * 86_400;
+        let contributors = BTreeMap::from([
+            contributor("alice@x", now),
+            contributor("bob@x", now - 400 * 86_400),
+            contributor("carol@x", now),
+        ]);
+        let owned = |author: &str, n: usize| vec![author.to_string(); n];
+        let files = HashMap::from([
+            ("legacy/fib.py".to_string(), owned("bob@x", 40)),
+            ("core/lib.rs".to_string(), [owned("alice@x", 30), owned("carol@x", 20)].concat()),
+        ]);
+
+        let options = OwnershipOptions::default();
+        let map = OwnershipMap::from_line_authors(contributors, &files, &[], &options, now);
+        let report = map.bus_factor_report();
+        assert!(matches!(report[0].risk, OwnershipRisk::Orphaned));
+        assert_eq!(report[0].component, "legacy");
+
+        let suggestion = MigrationSuggestion {
+            pattern_type: PatternType::FibonacciLike,
+            current_language: "python".to_string(),
+            suggested_language: "rust".to_string(),
+            node_count: 1,
+            estimated_performance_gain: 10.0,
+        };
+        let reviewers = map.suggest_reviewers(&suggestion, &options, 2);
+        assert!(reviewers.orphaned);
+        assert!(reviewers.source_owners.is_empty());
+        assert_eq!(reviewers.target_experts, vec!["alice@x", "carol@x"]);
+    }
+
+    #[test]
+    fn test_pattern_expertise_from_a_repository() {
+        let repo = std::env::temp_dir().join(format!("parflow-ownership-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&repo);
+        std::fs::create_dir_all(repo.join("app")).unwrap();
+        let commit = |email: &str, file: &str, code: &str| {
+            std::fs::write(repo.join(file), code).unwrap();
+            git(&repo, &["add", file]).unwrap();
+            let author = format!("user.email={}", email);
+            let args = ["-c", &author, "-c", "user.name=dev", "commit", "-qm", file];
+            git(&repo, &args).unwrap();
+        };
+        git(&repo, &["init", "-q"]).unwrap();
+        commit(
+            "alice@x",
+            "app/fib.py",
+            "import os\n\ndef fib(n):\n    return n if n < 2 else fib(n - 1) + fib(n - 2)\n",
+        );
+        commit("bob@x", "app/io.py", "def read(path):\n    return open(path).read()\n");
+        commit("carol@x", "lib.rs", "pub fn id(x: u64) -> u64 {\n    x\n}\n");
+
+        let graphs = pattern_graphs(&repo).unwrap();
+        let python = graphs.iter().find(|graph| graph.language == "python").unwrap();
+        let node = python.roots().next().unwrap();
+        assert_eq!(node.metadata["file"], "app/fib.py");
+        assert_eq!((&*node.metadata["line_start"], &*node.metadata["line_end"]), ("3", "4"));
+
+        let options = OwnershipOptions::default();
+        let map = OwnershipMap::from_git(&repo, &graphs, &options).unwrap();
+        std::fs::remove_dir_all(&repo).unwrap();
+        let fib = map.patterns.iter().find(|p| p.component == "FibonacciLike").unwrap();
+        assert_eq!((fib.language.as_str(), fib.total_lines), ("python", 2));
+        assert_eq!(fib.owners[0].email, "alice@x");
+
+        let suggestions = crate::CrossLanguageAnalyzer.suggest_migration_targets(python);
+        assert_eq!(suggestions[0].suggested_language, "rust");
+        let reviewers = map.suggest_reviewers(&suggestions[0], &options, 2);
+        assert_eq!(reviewers.source_owners, ["alice@x"]);
+        assert_eq!(reviewers.target_experts, ["carol@x"]);
+    }
+}
@@ -0,0 +1,428 @@
+//! Measured hot paths from a sampling profiler.
+//!
+//! [`Profiler::record`] samples a running process (or a command it starts) with `perf` on
+//! Linux, `dtrace` on macOS or `py-spy` for Python, and turns the stacks into a [`Profile`].
+//! Node.js shows up in `perf` when started with `--perf-basic-prof`; stacks recorded elsewhere
+//! (clinic, 0x, or any `stackcollapse-*` script) load with [`Profile::from_collapsed`].
+//!
+//! Frames are mapped back to functions by name. [`Profile::rank_migrations`] finds the
+//! functions behind each [`MigrationSuggestion`] with [`detect_patterns`] and orders the
+//! suggestions by the share of samples spent in them, so the migration that would save the most
+//! time comes first.
+
+use crate::ownership::{language_for_path, source_files};
+use crate::{detect_patterns, MigrationSuggestion};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::process::Command;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Samples per second
+const FREQUENCY: u32 = 99;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Profiler {
+    Perf,
+    Dtrace,
+    PySpy,
+}
+
+impl FromStr for Profiler {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name {
+            "perf" => Ok(Self::Perf),
+            "dtrace" => Ok(Self::Dtrace),
+            "py-spy" => Ok(Self::PySpy),
+            other => bail!("unknown profiler {} (expected perf, dtrace or py-spy)", other),
+        }
+    }
+}
+
+/// What to profile
+#[derive(Debug, Clone)]
+pub enum Target {
+    /// A running process, sampled for the given time
+    Process { pid: u32, duration: Duration },
+    /// A command, sampled until it exits
+    Command(Vec<String>),
+}
+
+impl Target {
+    fn is_python(&self) -> bool {
+        let command_line = match self {
+            Self::Process { pid, .. } => {
+                std::fs::read_to_string(format!("/proc/{}/cmdline", pid)).unwrap_or_default()
+            }
+            Self::Command(args) => args.first().cloned().unwrap_or_default(),
+        };
+        command_line.contains("python")
+    }
+}
+
+impl Profiler {
+    /// py-spy for Python, otherwise the system profiler: perf on Linux, dtrace on macOS
+    pub fn detect(target: &Target) -> Option<Self> {
+        if target.is_python() {
+            Some(Self::PySpy)
+        } else if cfg!(target_os = "linux") {
+            Some(Self::Perf)
+        } else if cfg!(target_os = "macos") {
+            Some(Self::Dtrace)
+        } else {
+            None
+        }
+    }
+
+    fn program(&self) -> &'static str {
+        match self {
+            Self::Perf => "perf",
+            Self::Dtrace => "dtrace",
+            Self::PySpy => "py-spy",
+        }
+    }
+
+    /// Sample `target` and collect the stacks
+    pub fn record(&self, target: &Target) -> Result<Profile> {
+        let output = std::env::temp_dir().join(format!("parflow-profile-{}", std::process::id()));
+        let mut command = Command::new(self.program());
+        match (self, target) {
+            (Self::Perf, _) => {
+                command.args(["record", "-F", &FREQUENCY.to_string(), "-g", "-o"]).arg(&output);
+            }
+            (Self::PySpy, _) => {
+                command
+                    .args(["record", "--format", "raw", "--nonblocking", "--rate"])
+                    .arg(FREQUENCY.to_string())
+                    .arg("--output")
+                    .arg(&output);
+            }
+            (Self::Dtrace, Target::Process { duration, .. }) => {
+                command.args(["-q", "-x", "ustackframes=100", "-n"]).arg(format!(
+                    "profile-{} /pid == $target/ {{ @[ustack()] = count(); }} tick-{}s {{ exit(0); }}",
+                    FREQUENCY,
+                    duration.as_secs().max(1)
+                ));
+            }
+            (Self::Dtrace, Target::Command(_)) => {
+                command.args(["-q", "-x", "ustackframes=100", "-n"]).arg(format!(
+                    "profile-{} /pid == $target/ {{ @[ustack()] = count(); }}",
+                    FREQUENCY
+                ));
+            }
+        }
+        match (self, target) {
+            (Self::Perf, Target::Process { pid, duration }) => {
+                command.args(["-p", &pid.to_string(), "--", "sleep"]);
+                command.arg(duration.as_secs().max(1).to_string());
+            }
+            (Self::PySpy, Target::Process { pid, duration }) => {
+                command.args(["--pid", &pid.to_string(), "--duration"]);
+                command.arg(duration.as_secs().max(1).to_string());
+            }
+            (Self::Dtrace, Target::Process { pid, .. }) => {
+                command.args(["-p", &pid.to_string()]);
+            }
+            (Self::Perf | Self::PySpy, Target::Command(args)) => {
+                command.arg("--").args(args);
+            }
+            (Self::Dtrace, Target::Command(args)) => {
+                command.arg("-c").arg(args.join(" "));
+            }
+        }
+
+        let recorded = command
+            .output()
+            .with_context(|| format!("running {}; is it installed?", self.program()))?;
+        if !recorded.status.success() {
+            bail!(
+                "{} failed: {}",
+                self.program(),
+                String::from_utf8_lossy(&recorded.stderr).trim()
+            );
+        }
+        let profile = match self {
+            Self::Perf => {
+                let script = Command::new("perf").arg("script").arg("-i").arg(&output).output()?;
+                Profile::from_perf_script(&String::from_utf8_lossy(&script.stdout))
+            }
+            Self::PySpy => Profile::from_collapsed(&std::fs::read_to_string(&output)?),
+            Self::Dtrace => Profile::from_dtrace(&String::from_utf8_lossy(&recorded.stdout)),
+        };
+        let _ = std::fs::remove_file(&output);
+        Ok(profile)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Frame {
+    pub function: String,
+    pub file: Option<String>,
+}
+
+/// Frame text as profilers print it, reduced to the function and, when given, its file
+fn frame(text: &str) -> Option<Frame> {
+    let text = text.trim();
+    // py-spy: `function (path/to/file.py:12)`
+    if let Some((function, location)) = text.strip_suffix(')').and_then(|t| t.split_once(" (")) {
+        let file = location.rsplit_once(':').map_or(location, |(file, _)| file);
+        return Some(Frame { function: function.to_string(), file: Some(file.to_string()) });
+    }
+    // dtrace: `binary`function+0x1f`; perf: `function+0x1f`
+    let symbol = text.rsplit('`').next()?;
+    let symbol = symbol.split("+0x").next()?;
+    // V8 with --perf-basic-prof: `LazyCompile:*render /app/view.js:10`
+    let (symbol, file) = match symbol.split_once(' ') {
+        Some((symbol, location)) => (symbol, location.rsplit_once(':').map(|(file, _)| file)),
+        None => (symbol, None),
+    };
+    // Rust symbols end in a hash segment, `crate::module::function::h0123456789abcdef`
+    let last =
+        symbol.rsplit("::").find(|segment| !(segment.len() == 17 && segment.starts_with('h')))?;
+    // V8 with --perf-basic-prof marks the tier, `LazyCompile:*render`
+    let function = last.rsplit(':').next()?.trim_start_matches(['*', '~']);
+    if function.is_empty() || function.starts_with('[') {
+        return None;
+    }
+    Some(Frame { function: function.to_string(), file: file.map(str::to_string) })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Stack {
+    /// Outermost frame first
+    pub frames: Vec<Frame>,
+    pub samples: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FunctionTime {
+    pub function: String,
+    pub file: Option<String>,
+    /// Share of samples with the function on the stack
+    pub total: f64,
+    /// Share of samples with the function running itself
+    pub own: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Profile {
+    pub stacks: Vec<Stack>,
+}
+
+impl Profile {
+    /// Collapsed stacks, one `outer;inner;leaf count` per line, as written by py-spy's raw
+    /// format and the `stackcollapse` scripts
+    pub fn from_collapsed(text: &str) -> Self {
+        let stacks = text
+            .lines()
+            .filter_map(|line| {
+                let (frames, count) = line.trim().rsplit_once(' ')?;
+                Some(Stack {
+                    frames: frames.split(';').filter_map(frame).collect(),
+                    samples: count.parse().ok()?,
+                })
+            })
+            .collect();
+        Self { stacks }
+    }
+
+    /// `perf script` output: one block per sample, a header line then frames, innermost first
+    pub fn from_perf_script(text: &str) -> Self {
+        let stacks = text
+            .split("\n\n")
+            .filter_map(|block| {
+                let mut frames: Vec<Frame> = block
+                    .lines()
+                    .skip(1)
+                    .filter_map(|line| {
+                        // `    55d1c2a3b4c5 symbol+0x1f (/usr/bin/app)`
+                        let line = line.trim();
+                        let line = line.rsplit_once(" (").map_or(line, |(symbol, _)| symbol);
+                        frame(line.split_once(' ')?.1)
+                    })
+                    .collect();
+                frames.reverse();
+                (!frames.is_empty()).then_some(Stack { frames, samples: 1 })
+            })
+            .collect();
+        Self { stacks }
+    }
+
+    /// A dtrace `ustack()` aggregation: frames innermost first, then the count
+    pub fn from_dtrace(text: &str) -> Self {
+        let mut stacks = Vec::new();
+        let mut frames = Vec::new();
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            match line.parse() {
+                Ok(samples) => {
+                    frames.reverse();
+                    stacks.push(Stack { frames: std::mem::take(&mut frames), samples });
+                }
+                Err(_) => frames.extend(frame(line)),
+            }
+        }
+        Self { stacks }
+    }
+
+    pub fn samples(&self) -> usize {
+        self.stacks.iter().map(|stack| stack.samples).sum()
+    }
+
+    /// Share of samples with any of `functions` on the stack
+    pub fn share(&self, functions: &BTreeSet<&str>) -> f64 {
+        let hit: usize = self
+            .stacks
+            .iter()
+            .filter(|stack| stack.frames.iter().any(|f| functions.contains(f.function.as_str())))
+            .map(|stack| stack.samples)
+            .sum();
+        hit as f64 / self.samples().max(1) as f64
+    }
+
+    /// Functions by the share of samples they appear in, hottest first
+    pub fn functions(&self) -> Vec<FunctionTime> {
+        let mut totals: BTreeMap<(&str, Option<&str>), (usize, usize)> = BTreeMap::new();
+        for stack in &self.stacks {
+            // Recursive functions count once per sample
+            let on_stack: BTreeSet<(&str, Option<&str>)> =
+                stack.frames.iter().map(|f| (f.function.as_str(), f.file.as_deref())).collect();
+            for key in on_stack {
+                totals.entry(key).or_default().0 += stack.samples;
+            }
+            if let Some(leaf) = stack.frames.last() {
+                totals.entry((&leaf.function, leaf.file.as_deref())).or_default().1 +=
+                    stack.samples;
+            }
+        }
+        let samples = self.samples().max(1) as f64;
+        let mut functions: Vec<FunctionTime> = totals
+            .into_iter()
+            .map(|((function, file), (total, own))| FunctionTime {
+                function: function.to_string(),
+                file: file.map(str::to_string),
+                total: total as f64 / samples,
+                own: own as f64 / samples,
+            })
+            .collect();
+        functions.sort_by(|a, b| b.total.total_cmp(&a.total).then(b.own.total_cmp(&a.own)));
+        functions
+    }
+
+    /// Order `suggestions` by the time measured in the functions they would move, located
+    /// with [`detect_patterns`] in the sources under `project`
+    pub fn rank_migrations(
+        &self,
+        suggestions: Vec<MigrationSuggestion>,
+        project: &Path,
+    ) -> Result<Vec<RankedMigration>> {
+        let mut files = Vec::new();
+        source_files(project, &mut files)?;
+        let mut located = Vec::new();
+        for file in files {
+            let Some(language) = language_for_path(&file.to_string_lossy()) else { continue };
+            let Ok(code) = std::fs::read_to_string(&file) else { continue };
+            for found in detect_patterns(&code, language) {
+                if let Some(function) = found.function {
+                    located.push((language, found.pattern, function));
+                }
+            }
+        }
+
+        let mut ranked: Vec<RankedMigration> = suggestions
+            .into_iter()
+            .map(|suggestion| {
+                let functions: BTreeSet<&str> = located
+                    .iter()
+                    .filter(|(language, pattern, _)| {
+                        *language == suggestion.current_language
+                            && *pattern == suggestion.pattern_type
+                    })
+                    .map(|(_, _, function)| function.as_str())
+                    .collect();
+                let hotness = self.share(&functions);
+                // Amdahl's law: only the measured share gets faster
+                let gain = suggestion.estimated_performance_gain.max(1.0);
+                let expected_speedup = 1.0 / ((1.0 - hotness) + hotness / gain);
+                RankedMigration {
+                    functions: functions.into_iter().map(str::to_string).collect(),
+                    hotness,
+                    expected_speedup,
+                    suggestion,
+                }
+            })
+            .collect();
+        ranked.sort_by(|a, b| {
+            b.hotness.total_cmp(&a.hotness).then(b.expected_speedup.total_cmp(&a.expected_speedup))
+        });
+        Ok(ranked)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RankedMigration {
+    pub suggestion: MigrationSuggestion,
+    /// Functions in the project with the suggestion's pattern and language
+    pub functions: Vec<String>,
+    /// Share of samples spent in them
+    pub hotness: f64,
+    /// Whole-program speedup if they get the suggestion's gain
+    pub expected_speedup: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PatternType;
+
+    #[test]
+    fn test_parses_profiles_and_ranks_migrations() {
+        let collapsed = "main (app.py:3);fib (app.py:10);fib (app.py:10) 6\nmain (app.py:3);load (app.py:20) 2\n[idle] 2\n";
+        let profile = Profile::from_collapsed(collapsed);
+        assert_eq!(profile.samples(), 10);
+        let functions = profile.functions();
+        assert_eq!((functions[0].function.as_str(), functions[0].total), ("main", 0.8));
+        assert_eq!((functions[1].function.as_str(), functions[1].own), ("fib", 0.6));
+        assert_eq!(functions[1].file.as_deref(), Some("app.py"));
+
+        let perf = "app 123 1.0: 10101 cpu-clock:\n\t    55d1c2 app::fib::h0123456789abcdef+0x1f (/usr/bin/app)\n\t    55d1c3 main+0x8 (/usr/bin/app)\n\napp 123 1.1: 10101 cpu-clock:\n\t    55d1c3 main+0x10 (/usr/bin/app)\n";
+        let profile = Profile::from_perf_script(perf);
+        assert_eq!(profile.stacks[0].frames[1].function, "fib");
+        assert_eq!(profile.share(&BTreeSet::from(["fib"])), 0.5);
+
+        let dtrace = "  app`fib+0x12\n  app`main+0x4\n    7\n\n  app`main+0x8\n    3\n";
+        let profile = Profile::from_dtrace(dtrace);
+        assert_eq!(profile.stacks[0].frames[0].function, "main");
+        assert_eq!(profile.share(&BTreeSet::from(["fib"])), 0.7);
+
+        let dir = std::env::temp_dir().join(format!("parflow-profiling-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("app.py"), "def fib(n):\n    return fib(n - 1) + fib(n - 2)\n")
+            .unwrap();
+        let suggestion = |pattern_type, gain| MigrationSuggestion {
+            pattern_type,
+            current_language: "python".to_string(),
+            suggested_language: "rust".to_string(),
+            node_count: 1,
+            estimated_performance_gain: gain,
+        };
+        let ranked = Profile::from_collapsed(collapsed)
+            .rank_migrations(
+                vec![
+                    suggestion(PatternType::MapReduce, 20.0),
+                    suggestion(PatternType::FibonacciLike, 10.0),
+                ],
+                &dir,
+            )
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(ranked[0].suggestion.pattern_type, PatternType::FibonacciLike);
+        assert_eq!(ranked[0].functions, ["fib"]);
+        assert!((ranked[0].expected_speedup - 1.0 / (0.4 + 0.06)).abs() < 1e-9);
+        assert_eq!(ranked[1].hotness, 0.0);
+    }
+}
@@ -37,6 +37,8 @@ pub enum NodeType {
     Arithmetic,
     Comparison,
     Loop,
+    /// A call site; `callee` is the called path as written, e.g. `requests.get`
+    Call,
     Pattern(PatternType),
 }
 
@@ -53,6 +55,41 @@ pub enum PatternType {
     DatabaseQuery,
 }
 
+impl PatternType {
+    /// The graph's form of a pattern found in source, for the patterns graphs represent
+    pub fn from_detected(pattern: crate::PatternType) -> Option<Self> {
+        use crate::PatternType as Detected;
+        Some(match pattern {
+            Detected::FibonacciLike => Self::FibonacciLike,
+            Detected::MapReduce => Self::MapReduce,
+            Detected::Builder => Self::Builder,
+            Detected::IteratorChain => Self::IteratorChain,
+            Detected::RecursiveTree => Self::RecursiveTree,
+            Detected::Cacheable => Self::Cacheable,
+            Detected::DataProcessor => Self::DataProcessor,
+            Detected::WebEndpoint => Self::WebEndpoint,
+            Detected::DatabaseQuery => Self::DatabaseQuery,
+            _ => return None,
+        })
+    }
+
+    /// The pattern as source detection and migration suggestions name it
+    pub fn detected(&self) -> crate::PatternType {
+        use crate::PatternType as Detected;
+        match self {
+            Self::FibonacciLike => Detected::FibonacciLike,
+            Self::MapReduce => Detected::MapReduce,
+            Self::Builder => Detected::Builder,
+            Self::IteratorChain => Detected::IteratorChain,
+            Self::RecursiveTree => Detected::RecursiveTree,
+            Self::Cacheable => Detected::Cacheable,
+            Self::DataProcessor => Detected::DataProcessor,
+            Self::WebEndpoint => Detected::WebEndpoint,
+            Self::DatabaseQuery => Detected::DatabaseQuery,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SemanticGraph {
     pub nodes: HashMap<u64, SemanticNode>,
@@ -71,6 +108,80 @@ impl SemanticGraph {
         }
     }
 
+    /// Graph of the functions in one source file: a [`NodeType::Function`] root per function,
+    /// with `name`, `path`, `line`, `async` and `decorators` metadata, whose children are the
+    /// [`NodeType::Call`] nodes in its body, each with `callee`, `line`, `awaited` and `text`.
+    pub fn from_source(code: &str, language: &str, path: &str) -> Self {
+        let mut graph = Self::new(language);
+        let lines: Vec<&str> = code.lines().collect();
+        let mut next_id = 0;
+        let mut node = |node_type, metadata: HashMap<String, String>| {
+            next_id += 1;
+            let mut hasher = std::hash::DefaultHasher::new();
+            std::hash::Hash::hash(&node_type, &mut hasher);
+            std::hash::Hash::hash(&metadata.get("callee"), &mut hasher);
+            let pattern_hash = std::hash::Hasher::finish(&hasher);
+            let language = language.to_string();
+            SemanticNode {
+                id: next_id,
+                node_type,
+                children: Vec::new(),
+                metadata,
+                language,
+                pattern_hash,
+            }
+        };
+
+        for (start, end, name) in crate::source_patterns::functions(&lines, language) {
+            let Some(name) = name else { continue };
+            let decorators: Vec<&str> = lines[..start]
+                .iter()
+                .rev()
+                .map(|line| line.trim())
+                .take_while(|line| line.starts_with('@') || line.starts_with("#["))
+                .collect();
+            let mut function = node(
+                NodeType::Function,
+                HashMap::from([
+                    ("name".to_string(), name.to_string()),
+                    ("path".to_string(), path.to_string()),
+                    ("line".to_string(), (start + 1).to_string()),
+                    ("async".to_string(), lines[start].contains("async ").to_string()),
+                    ("decorators".to_string(), decorators.join(" ")),
+                ]),
+            );
+            for (i, line) in lines.iter().enumerate().take(end).skip(start + 1) {
+                for callee in calls(line) {
+                    let call = node(
+                        NodeType::Call,
+                        HashMap::from([
+                            ("callee".to_string(), callee.to_string()),
+                            ("line".to_string(), (i + 1).to_string()),
+                            ("awaited".to_string(), line.contains("await").to_string()),
+                            ("text".to_string(), line.trim().to_string()),
+                        ]),
+                    );
+                    function.children.push(graph.add_node(call));
+                }
+            }
+            let id = graph.add_node(function);
+            graph.root_nodes.push(id);
+        }
+        graph
+    }
+
+    /// Roots of the graph, in order
+    pub fn roots(&self) -> impl Iterator<Item = &SemanticNode> {
+        self.root_nodes.iter().filter_map(|id| self.nodes.get(id))
+    }
+
+    pub fn children<'a>(
+        &'a self,
+        node: &'a SemanticNode,
+    ) -> impl Iterator<Item = &'a SemanticNode> {
+        node.children.iter().filter_map(|id| self.nodes.get(id))
+    }
+
     pub fn add_node(&mut self, node: SemanticNode) -> u64 {
         let id = node.id;
         self.nodes.insert(id, node);
@@ -89,6 +200,14 @@ impl SemanticGraph {
         }
     }
 
+    /// Every node that matches a pattern, with the pattern it matches.
+    pub fn pattern_nodes(&self) -> Vec<(PatternType, &SemanticNode)> {
+        self.nodes
+            .values()
+            .filter_map(|node| self.analyze_node_pattern(node).map(|pattern| (pattern, node)))
+            .collect()
+    }
+
     fn analyze_node_pattern(&self, node: &SemanticNode) -> Option<PatternType> {
         match &node.node_type {
             NodeType::Function if node.children.len() >= 2 => {
@@ -147,3 +266,27 @@ impl SemanticGraph {
         }
     }
 }
+
+const NOT_CALLS: &[&str] =
+    &["if", "elif", "while", "for", "match", "return", "switch", "catch", "not", "and", "or", "in"];
+
+/// Called paths on `line`: the identifiers, with `.` and `::` separators, right before a `(`
+fn calls(line: &str) -> Vec<&str> {
+    let code = line.split("//").next().unwrap_or(line);
+    let code = if code.trim_start().starts_with('#') { "" } else { code };
+    let mut found = Vec::new();
+    for (open, _) in code.match_indices('(') {
+        let before = &code[..open];
+        let start = before
+            .rfind(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.' || c == ':' || c == '!'))
+            .map_or(0, |i| i + 1);
+        let callee = before[start..].trim_start_matches(['.', ':']);
+        if !callee.is_empty()
+            && !NOT_CALLS.contains(&callee)
+            && !callee.starts_with(char::is_numeric)
+        {
+            found.push(callee);
+        }
+    }
+    found
+}
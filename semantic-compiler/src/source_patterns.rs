@@ -0,0 +1,225 @@
+//! Pattern detection straight from source text.
+//!
+//! A cheap, parser-free pass for callers that only have a snippet, such as the browser
+//! playground: functions are found by their declaration keyword and each one is checked for the
+//! markers of a [`PatternType`]. It favours recall over precision.
+
+use crate::PatternType;
+use parflow_core::languages;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PatternMatch {
+    pub pattern: PatternType,
+    /// Function the pattern was found in, if any
+    pub function: Option<String>,
+    /// 1-based line of the first marker
+    pub line: usize,
+    /// What gave the pattern away
+    pub evidence: String,
+}
+
+/// Markers that identify a pattern wherever they appear.
+const MARKERS: &[(PatternType, &[&str])] = &[
+    (
+        PatternType::WebEndpoint,
+        &[
+            "@app.route",
+            "@app.get",
+            "@app.post",
+            "app.get(",
+            "app.post(",
+            "#[get(",
+            "#[post(",
+            "http.HandleFunc",
+        ],
+    ),
+    (PatternType::Cacheable, &["@lru_cache", "@cache", "@functools.cache", "memoize", "useMemo("]),
+    (
+        PatternType::ConcurrentTasks,
+        &["tokio::spawn", "thread::spawn", "asyncio.gather", "Promise.all", "go func", "rayon::"],
+    ),
+    (
+        PatternType::FileIO,
+        &["File::open", "fs::read", "fs::write", "open(", "readFile", "os.ReadFile"],
+    ),
+    (
+        PatternType::NetworkRequest,
+        &["requests.get", "requests.post", "fetch(", "reqwest::", "http.Get"],
+    ),
+    (
+        PatternType::DatabaseQuery,
+        &["SELECT ", "INSERT INTO", "DELETE FROM", ".execute(", ".query("],
+    ),
+];
+
+const REDUCERS: &[&str] = &[".reduce(", ".fold(", ".sum(", "reduce(", "sum("];
+const ADAPTERS: &[&str] =
+    &[".map(", ".filter(", ".filter_map(", ".flat_map(", ".zip(", ".take(", ".skip("];
+
+struct Function<'a> {
+    name: Option<&'a str>,
+    /// 0-based index of the first line
+    start: usize,
+    lines: &'a [&'a str],
+}
+
+/// Name declared on `line`, if it starts a function in `language`.
+pub(crate) fn declared_function<'a>(line: &'a str, language: &str) -> Option<&'a str> {
+    let line = line.trim_start();
+    let plugin = languages::get(language)?;
+    let rest = plugin.function_keywords().iter().find_map(|k| line.strip_prefix(k))?;
+    let name = rest.split(|c: char| !(c.is_alphanumeric() || c == '_')).next()?;
+    (!name.is_empty()).then_some(name)
+}
+
+/// Split `lines` at function declarations into (start, end, name); code before the first one
+/// is a nameless chunk.
+pub(crate) fn functions<'a>(
+    lines: &[&'a str],
+    language: &str,
+) -> Vec<(usize, usize, Option<&'a str>)> {
+    let mut starts: Vec<(usize, Option<&str>)> = lines
+        .iter()
+        .enumerate()
+        .filter_map(|(i, line)| declared_function(line, language).map(|name| (i, Some(name))))
+        .collect();
+    if starts.first().is_none_or(|(i, _)| *i > 0) {
+        starts.insert(0, (0, None));
+    }
+    starts
+        .iter()
+        .enumerate()
+        .map(|(n, &(start, name))| {
+            let end = starts.get(n + 1).map_or(lines.len(), |(next, _)| *next);
+            (start, end, name)
+        })
+        .collect()
+}
+
+fn first_line(function: &Function, predicate: impl Fn(&str) -> bool) -> Option<usize> {
+    function.lines.iter().position(|line| predicate(line)).map(|i| function.start + i + 1)
+}
+
+/// Patterns in `code`, in source order. `language` is lowercase (rust, python, javascript,
+/// typescript, go).
+pub fn detect_patterns(code: &str, language: &str) -> Vec<PatternMatch> {
+    let mut scan = PatternScan::new(code, language);
+    let mut matches = Vec::new();
+    while let Some(found) = scan.step() {
+        matches.extend(found);
+    }
+    matches.sort_by_key(|m| m.line);
+    matches
+}
+
+/// [`detect_patterns`] one function per step, for callers that report progress or hand control
+/// back between steps. Matches come out per function; sort them by line to get source order.
+pub struct PatternScan<'a> {
+    lines: Vec<&'a str>,
+    functions: Vec<(usize, usize, Option<&'a str>)>,
+    done: usize,
+}
+
+impl<'a> PatternScan<'a> {
+    pub fn new(code: &'a str, language: &str) -> Self {
+        let lines: Vec<&str> = code.lines().collect();
+        let functions = functions(&lines, language);
+        Self { lines, functions, done: 0 }
+    }
+
+    /// Steps in the whole scan
+    pub fn total(&self) -> usize {
+        self.functions.len()
+    }
+
+    /// Steps taken so far
+    pub fn done(&self) -> usize {
+        self.done
+    }
+
+    /// Check the next function; `None` once every function has been checked.
+    pub fn step(&mut self) -> Option<Vec<PatternMatch>> {
+        let &(start, end, name) = self.functions.get(self.done)?;
+        self.done += 1;
+        Some(function_patterns(&Function { name, start, lines: &self.lines[start..end] }))
+    }
+}
+
+fn function_patterns(function: &Function) -> Vec<PatternMatch> {
+    let mut matches = Vec::new();
+    let found = |pattern, line, evidence: String| PatternMatch {
+        pattern,
+        function: function.name.map(str::to_string),
+        line,
+        evidence,
+    };
+
+    if let Some(name) = function.name {
+        let call = format!("{}(", name);
+        let body = || function.lines.iter().enumerate().skip(1);
+        if let Some((i, _)) = body().find(|(_, line)| line.contains(&call)) {
+            let count: usize = body().map(|(_, line)| line.matches(&call).count()).sum();
+            let (pattern, evidence) = if count >= 2 {
+                (PatternType::FibonacciLike, format!("{} calls itself {} times", name, count))
+            } else {
+                (PatternType::RecursiveTree, format!("{} calls itself", name))
+            };
+            let line = function.start + i + 1;
+            matches.push(found(pattern, line, evidence));
+        }
+    }
+
+    let mapped = first_line(function, |l| l.contains("map("));
+    let reduced = first_line(function, |l| REDUCERS.iter().any(|r| l.contains(r)));
+    if let (Some(line), Some(_)) = (mapped, reduced) {
+        matches.push(found(PatternType::MapReduce, line, "map followed by a reduction".into()));
+    }
+
+    let chain = first_line(function, |l| {
+        ADAPTERS.iter().filter(|a| l.contains(*a)).count() >= 2
+            || (l.contains(".iter()") && ADAPTERS.iter().any(|a| l.contains(a)))
+    });
+    if let Some(line) = chain {
+        matches.push(found(PatternType::IteratorChain, line, "chained iterator adapters".into()));
+    }
+
+    let builder = first_line(function, |l| l.contains(".build()"))
+        .or_else(|| first_line(function, |l| l.contains("fn with_") && l.contains("Self")));
+    if let Some(line) = builder {
+        matches.push(found(PatternType::Builder, line, "builder methods".into()));
+    }
+
+    for (pattern, markers) in MARKERS {
+        let hit = function.lines.iter().enumerate().find_map(|(i, line)| {
+            markers.iter().find(|m| line.contains(*m)).map(|m| (function.start + i + 1, *m))
+        });
+        if let Some((line, marker)) = hit {
+            matches.push(found(*pattern, line, format!("uses {}", marker.trim())));
+        }
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_patterns_per_function() {
+        let python = "import functools\n\n@functools.cache\ndef fib(n):\n    if n < 2:\n        return n\n    return fib(n - 1) + fib(n - 2)\n\ndef total(xs):\n    return sum(map(lambda x: x * 2, xs))\n";
+        let found = detect_patterns(python, "python");
+        let kinds: Vec<_> = found.iter().map(|m| (m.pattern, m.function.as_deref())).collect();
+        assert!(kinds.contains(&(PatternType::Cacheable, None)));
+        assert!(kinds.contains(&(PatternType::FibonacciLike, Some("fib"))));
+        assert!(kinds.contains(&(PatternType::MapReduce, Some("total"))));
+        let fib = found.iter().find(|m| m.pattern == PatternType::FibonacciLike).unwrap();
+        assert_eq!(fib.line, 7);
+
+        let rust = "fn evens(v: &[u32]) -> Vec<u32> {\n    v.iter().filter(|x| *x % 2 == 0).copied().collect()\n}\n";
+        let found = detect_patterns(rust, "rust");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].pattern, PatternType::IteratorChain);
+        assert_eq!(found[0].function.as_deref(), Some("evens"));
+    }
+}